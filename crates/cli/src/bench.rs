@@ -0,0 +1,380 @@
+//! `roc bench`: measures top-level benchmark functions like `benchSort = \{} -> ...`.
+//!
+//! Benchmarks are always compiled with optimizations. Their timings can be saved as a JSON
+//! baseline, and compared with a baseline saved earlier.
+use bumpalo::Bump;
+use clap::ArgMatches;
+use roc_build::program::{
+    handle_error_module, handle_loading_problem, report_problems_monomorphized,
+};
+use roc_gen_llvm::llvm::build::LlvmBackendMode;
+use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError, Threading};
+use roc_mono::ir::OptLevel;
+use roc_packaging::cache::{self, RocCacheDir};
+use roc_repl_expect::bench::{BenchOptions, BenchSamples};
+use roc_reporting::report::ANSI_STYLE_CODES;
+use roc_target::Target;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{FLAG_BASELINE, FLAG_FILTER, FLAG_MAIN, FLAG_SAVE_BASELINE, ROC_FILE};
+
+/// Changes smaller than this fraction of the baseline are reported as noise, even when the
+/// samples vary less than that.
+const MIN_SIGNIFICANT_CHANGE: f64 = 0.02;
+
+/// The timings of benchmarks, as saved with `--save-baseline`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    benchmarks: Vec<BenchStats>,
+}
+
+/// What the samples of one benchmark came to. All times are in nanoseconds per run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BenchStats {
+    name: String,
+    mean: f64,
+    median: f64,
+    std_dev: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Faster(f64),
+    Slower(f64),
+    WithinNoise,
+}
+
+pub fn bench(matches: &ArgMatches, target: Target) -> io::Result<i32> {
+    let start_time = Instant::now();
+    let arena = &Bump::new();
+
+    let path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
+    let filter = matches.get_one::<String>(FLAG_FILTER);
+
+    let baseline = match matches.get_one::<PathBuf>(FLAG_BASELINE) {
+        Some(baseline_path) => Some(read_baseline(baseline_path)?),
+        None => None,
+    };
+
+    let load_config = LoadConfig {
+        target,
+        function_kind: FunctionKind::from_env(),
+        render: roc_reporting::report::RenderTarget::ColorTerminal,
+        palette: roc_reporting::report::DEFAULT_PALETTE,
+        threading: Threading::AllAvailable,
+        exec_mode: ExecutionMode::Bench,
+        incremental: None,
+    };
+    let load_result = roc_load::load_and_monomorphize(
+        arena,
+        path.to_path_buf(),
+        matches.get_one::<PathBuf>(FLAG_MAIN).cloned(),
+        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+        load_config,
+    );
+
+    let mut loaded = match load_result {
+        Ok(loaded) => loaded,
+        Err(LoadMonomorphizedError::LoadingProblem(problem)) => {
+            return handle_loading_problem(problem);
+        }
+        Err(LoadMonomorphizedError::ErrorModule(module)) => {
+            return handle_error_module(module, start_time.elapsed(), path.as_os_str(), false);
+        }
+    };
+
+    let problems = report_problems_monomorphized(&mut loaded);
+
+    if problems.warnings > 0 {
+        problems.print_error_warning_count(start_time.elapsed());
+        println!(".\n");
+    }
+
+    let root_id = loaded.module_id;
+    let interns = &loaded.interns.clone();
+    let sources = loaded.sources.clone();
+
+    let (dyn_lib, benchmarks_by_module, _) = roc_repl_expect::run::expect_mono_module_to_dylib(
+        arena,
+        target,
+        loaded,
+        OptLevel::Optimize,
+        LlvmBackendMode::CliTest,
+    )
+    .unwrap();
+
+    // Benchmarks of the root module go by their own name, the others by their qualified name.
+    let mut benchmarks: Vec<_> = benchmarks_by_module
+        .into_iter()
+        .flat_map(|(module_id, functions)| {
+            let module_name = if module_id == root_id {
+                None
+            } else {
+                let (module_path, _) = sources.get(&module_id).unwrap();
+
+                Some(crate::test_module_name(interns, module_id, module_path))
+            };
+
+            functions.benchmarks.into_iter().map(move |benchmark| {
+                let name = benchmark.symbol.as_str(interns);
+
+                let name = match &module_name {
+                    Some(module_name) => format!("{module_name}.{name}"),
+                    None => name.to_string(),
+                };
+
+                (name, benchmark)
+            })
+        })
+        .filter(|(name, _)| filter.map_or(true, |filter| name.contains(filter.as_str())))
+        .collect();
+
+    benchmarks.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if benchmarks.is_empty() {
+        match filter {
+            Some(_) => println!("No benchmarks matched --{FLAG_FILTER}."),
+            None => println!("No benchmarks were found. A benchmark is a top-level function like `benchSort = \\{{}} -> ...`."),
+        }
+
+        return Ok(2);
+    }
+
+    println!(
+        "Compiled in {} ms. Running {} benchmark(s)…\n",
+        start_time.elapsed().as_millis(),
+        benchmarks.len()
+    );
+
+    let name_width = benchmarks.iter().map(|(name, _)| name.len()).max().unwrap();
+    let (names, benchmarks): (Vec<_>, Vec<_>) = benchmarks.into_iter().unzip();
+
+    let mut names = names.into_iter();
+    let mut new_baseline = Baseline::default();
+    let mut crashed_count = 0;
+
+    roc_repl_expect::bench::run_benchmarks(
+        &dyn_lib,
+        benchmarks,
+        BenchOptions::default(),
+        |_, result| {
+            let name = names.next().unwrap();
+
+            match result {
+                Ok(samples) => {
+                    let stats = BenchStats::from_samples(name, &samples);
+                    let change = baseline
+                        .as_ref()
+                        .and_then(|baseline| baseline.get(&stats.name))
+                        .map(|before| stats.compare(before));
+
+                    println!("{}", stats.summary(name_width, &samples, change));
+
+                    new_baseline.benchmarks.push(stats);
+                }
+                Err(message) => {
+                    crashed_count += 1;
+
+                    println!(
+                        "{name:name_width$}  {}crashed: {message}{}",
+                        ANSI_STYLE_CODES.red, ANSI_STYLE_CODES.reset
+                    );
+                }
+            }
+        },
+    );
+
+    if let Some(save_path) = matches.get_one::<PathBuf>(FLAG_SAVE_BASELINE) {
+        std::fs::write(save_path, new_baseline.to_json())?;
+
+        println!(
+            "\nSaved the timings as a baseline in {}",
+            save_path.display()
+        );
+    }
+
+    Ok((crashed_count > 0) as i32)
+}
+
+fn read_baseline(path: &Path) -> io::Result<Baseline> {
+    let contents = std::fs::read_to_string(path)?;
+
+    serde_json::from_str(&contents).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is not a baseline saved by `roc bench`: {error}",
+                path.display()
+            ),
+        )
+    })
+}
+
+impl Baseline {
+    fn get(&self, name: &str) -> Option<&BenchStats> {
+        self.benchmarks.iter().find(|stats| stats.name == name)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a baseline is always valid JSON")
+    }
+}
+
+impl BenchStats {
+    fn from_samples(name: String, samples: &BenchSamples) -> Self {
+        let mut times: Vec<f64> = samples
+            .times
+            .iter()
+            .map(|time| time.as_nanos() as f64)
+            .collect();
+        times.sort_by(f64::total_cmp);
+
+        let count = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / count;
+
+        let middle = times.len() / 2;
+        let median = match times.len() % 2 {
+            0 => (times[middle - 1] + times[middle]) / 2.0,
+            _ => times[middle],
+        };
+
+        // the sample standard deviation, since the samples stand for all possible runs
+        let variance = match times.len() {
+            0 | 1 => 0.0,
+            _ => times.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / (count - 1.0),
+        };
+
+        Self {
+            name,
+            mean,
+            median,
+            std_dev: variance.sqrt(),
+        }
+    }
+
+    /// How the median changed since the baseline. Changes smaller than how much the samples of
+    /// either run vary are not significant.
+    fn compare(&self, before: &BenchStats) -> Change {
+        let change = (self.median - before.median) / before.median;
+        let noise = self.std_dev.max(before.std_dev) / before.median;
+
+        if !change.is_finite() || change.abs() <= noise.max(MIN_SIGNIFICANT_CHANGE) {
+            Change::WithinNoise
+        } else if change < 0.0 {
+            Change::Faster(-change)
+        } else {
+            Change::Slower(change)
+        }
+    }
+
+    fn summary(&self, name_width: usize, samples: &BenchSamples, change: Option<Change>) -> String {
+        let Self {
+            name,
+            mean,
+            median,
+            std_dev,
+        } = self;
+
+        let change = match change {
+            None => String::new(),
+            Some(Change::WithinNoise) => "  no change".to_string(),
+            Some(Change::Faster(fraction)) => format!(
+                "  {}{:.1}% faster{}",
+                ANSI_STYLE_CODES.green,
+                fraction * 100.0,
+                ANSI_STYLE_CODES.reset
+            ),
+            Some(Change::Slower(fraction)) => format!(
+                "  {}{:.1}% slower{}",
+                ANSI_STYLE_CODES.red,
+                fraction * 100.0,
+                ANSI_STYLE_CODES.reset
+            ),
+        };
+
+        format!(
+            "{name:name_width$}  median {:>10}  mean {:>10} ± {:<10}  ({} samples of {} runs){change}",
+            format_nanos(*median),
+            format_nanos(*mean),
+            format_nanos(*std_dev),
+            samples.times.len(),
+            samples.runs_per_sample,
+        )
+    }
+}
+
+fn format_nanos(nanos: f64) -> String {
+    if nanos < 1_000.0 {
+        format!("{nanos:.1} ns")
+    } else if nanos < 1_000_000.0 {
+        format!("{:.2} µs", nanos / 1_000.0)
+    } else if nanos < 1_000_000_000.0 {
+        format!("{:.2} ms", nanos / 1_000_000.0)
+    } else {
+        format!("{:.2} s", Duration::from_nanos(nanos as u64).as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(median: f64, std_dev: f64) -> BenchStats {
+        BenchStats {
+            name: "benchSort".to_string(),
+            mean: median,
+            median,
+            std_dev,
+        }
+    }
+
+    #[test]
+    fn stats_from_samples() {
+        let samples = BenchSamples {
+            times: [4, 1, 3, 2].map(Duration::from_nanos).to_vec(),
+            runs_per_sample: 10,
+        };
+
+        let stats = BenchStats::from_samples("benchSort".to_string(), &samples);
+
+        assert_eq!(stats.mean, 2.5);
+        assert_eq!(stats.median, 2.5);
+        assert!((stats.std_dev - 1.290_994).abs() < 1e-6);
+    }
+
+    #[test]
+    fn changes_within_noise_are_not_significant() {
+        let before = stats(100.0, 10.0);
+
+        assert_eq!(stats(108.0, 1.0).compare(&before), Change::WithinNoise);
+        assert_eq!(
+            stats(101.0, 0.0).compare(&stats(100.0, 0.0)),
+            Change::WithinNoise
+        );
+        assert_eq!(stats(125.0, 1.0).compare(&before), Change::Slower(0.25));
+        assert_eq!(stats(50.0, 1.0).compare(&before), Change::Faster(0.5));
+    }
+
+    #[test]
+    fn baseline_round_trip() {
+        let baseline = Baseline {
+            benchmarks: vec![stats(100.0, 10.0)],
+        };
+
+        let read: Baseline = serde_json::from_str(&baseline.to_json()).unwrap();
+
+        assert_eq!(read.get("benchSort").unwrap().median, 100.0);
+        assert!(read.get("benchOther").is_none());
+    }
+
+    #[test]
+    fn nanos_are_shown_in_a_readable_unit() {
+        assert_eq!(format_nanos(12.34), "12.3 ns");
+        assert_eq!(format_nanos(12_340.0), "12.34 µs");
+        assert_eq!(format_nanos(12_340_000.0), "12.34 ms");
+        assert_eq!(format_nanos(2_500_000_000.0), "2.50 s");
+    }
+}
@@ -18,7 +18,7 @@ use roc_collections::MutMap;
 use roc_error_macros::{internal_error, user_error};
 use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
-use roc_load::{ExpectMetadata, Threading};
+use roc_load::{ExpectMetadata, IncrementalCache, Threading};
 #[cfg(not(windows))]
 use roc_module::symbol::ModuleId;
 use roc_mono::ir::OptLevel;
@@ -41,9 +41,26 @@ use strum::IntoEnumIterator;
 #[cfg(not(target_os = "linux"))]
 use tempfile::TempDir;
 
+#[cfg(not(windows))]
+mod bench;
+#[cfg(not(windows))]
+pub use bench::bench;
+
 mod format;
 pub use format::{format_files, format_src, FormatMode};
 
+mod watch;
+pub use watch::{watch, watch_dev};
+
+#[cfg(not(windows))]
+mod test_coverage;
+#[cfg(not(windows))]
+mod test_filter;
+#[cfg(not(windows))]
+mod test_report;
+#[cfg(not(windows))]
+mod test_workers;
+
 pub const CMD_BUILD: &str = "build";
 pub const CMD_RUN: &str = "run";
 pub const CMD_DEV: &str = "dev";
@@ -53,11 +70,13 @@ pub const CMD_CHECK: &str = "check";
 pub const CMD_VERSION: &str = "version";
 pub const CMD_FORMAT: &str = "format";
 pub const CMD_TEST: &str = "test";
+pub const CMD_BENCH: &str = "bench";
 pub const CMD_GLUE: &str = "glue";
 pub const CMD_PREPROCESS_HOST: &str = "preprocess-host";
 
 pub const FLAG_EMIT_LLVM_IR: &str = "emit-llvm-ir";
 pub const FLAG_PROFILING: &str = "profiling";
+pub const FLAG_DEBUG: &str = "debug";
 pub const FLAG_BUNDLE: &str = "bundle";
 pub const FLAG_DEV: &str = "dev";
 pub const FLAG_OPTIMIZE: &str = "optimize";
@@ -80,6 +99,21 @@ pub const FLAG_WASM_STACK_SIZE_KB: &str = "wasm-stack-size-kb";
 pub const FLAG_OUTPUT: &str = "output";
 pub const FLAG_FUZZ: &str = "fuzz";
 pub const FLAG_MAIN: &str = "main";
+pub const FLAG_REPORTER: &str = "reporter";
+pub const FLAG_FILTER: &str = "filter";
+pub const FLAG_COVERAGE: &str = "coverage";
+pub const FLAG_SEED: &str = "seed";
+pub const FLAG_UPDATE_SNAPSHOTS: &str = "update-snapshots";
+pub const FLAG_TIMEOUT: &str = "timeout";
+pub const FLAG_WATCH: &str = "watch";
+pub const FLAG_BASELINE: &str = "baseline";
+pub const FLAG_SAVE_BASELINE: &str = "save-baseline";
+/// Internal: makes `roc test` act as a worker for a parallel `roc test` run,
+/// writing its results as JSON to the given path.
+const FLAG_TEST_WORKER_REPORT: &str = "worker-report";
+/// Internal: makes `roc repl` print the value of the given top-level def of the module read
+/// from stdin, instead of starting the repl. The language server evaluates defs with it.
+pub const FLAG_EVAL_DEF: &str = "eval-def";
 pub const ROC_FILE: &str = "ROC_FILE";
 pub const GLUE_DIR: &str = "GLUE_DIR";
 pub const GLUE_SPEC: &str = "GLUE_SPEC";
@@ -130,6 +164,12 @@ pub fn build_app() -> Command {
         .action(ArgAction::SetTrue)
         .required(false);
 
+    let flag_debug = Arg::new(FLAG_DEBUG)
+        .long(FLAG_DEBUG)
+        .help("Emit DWARF debug info that maps the compiled program back to its .roc source")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
     let flag_time = Arg::new(FLAG_TIME)
         .long(FLAG_TIME)
         .help("Print detailed compilation time information")
@@ -138,7 +178,7 @@ pub fn build_app() -> Command {
 
     let flag_linker = Arg::new(FLAG_LINKER)
         .long(FLAG_LINKER)
-        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32 or x86_64 Linux, because those are the only targets it currently supports. Otherwise the legacy linker is used by default.)")
+        .help("Set which linker to use\n(The surgical linker is enabled by default only when building for wasm32, x86_64 Linux, x86_64 Windows or macOS, because those are the only targets it currently supports. Otherwise the legacy linker is used by default.)")
         .value_parser(["surgical", "legacy"])
         .required(false);
 
@@ -172,6 +212,12 @@ pub fn build_app() -> Command {
         .value_parser(value_parser!(PathBuf))
         .required(false);
 
+    let flag_watch = Arg::new(FLAG_WATCH)
+        .long(FLAG_WATCH)
+        .help("Keep running, and run again whenever one of the modules changes\n(Modules that didn't change, and don't import ones that did, are not type-checked again.)")
+        .action(ArgAction::SetTrue)
+        .required(false);
+
     let roc_file_to_run = Arg::new(ROC_FILE)
         .help("The .roc file of an app to run")
         .value_parser(value_parser!(PathBuf))
@@ -205,6 +251,7 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_debug.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
@@ -265,11 +312,13 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_debug.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_watch.clone())
             .arg(
                 Arg::new(FLAG_VERBOSE)
                     .long(FLAG_VERBOSE)
@@ -277,6 +326,66 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false)
             )
+            .arg(
+                Arg::new(FLAG_REPORTER)
+                    .long(FLAG_REPORTER)
+                    .help("Print the test results in a machine-readable format instead\n(Each `expect` is reported as its own test case.)")
+                    .value_parser(["junit", "json"])
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_OUTPUT)
+                    .long(FLAG_OUTPUT)
+                    .help("Write the --reporter output to this file instead of stdout")
+                    .value_parser(value_parser!(PathBuf))
+                    .requires(FLAG_REPORTER)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_FILTER)
+                    .long(FLAG_FILTER)
+                    .help("Only run the `expect`s whose module name, preceding def name, or `# test: name` label comment contains this pattern\n(Use `path/to/File.roc:LINE` to run the single `expect` on that line instead.)")
+                    .value_parser(value_parser!(String))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_COVERAGE)
+                    .long(FLAG_COVERAGE)
+                    .help("Count which defs, `when` branches and `if` arms the expects run, and write an lcov report to this file\n(Defaults to lcov.info. A summary by module is printed as well.)")
+                    .value_parser(value_parser!(PathBuf))
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value("lcov.info")
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_SEED)
+                    .long(FLAG_SEED)
+                    .help("Generate the inputs of property expects like `expect \\x -> ...` from this seed\n(Defaults to a new seed on every run. Failures print the seed that reproduces them.)")
+                    .value_parser(value_parser!(u64))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_UPDATE_SNAPSHOTS)
+                    .long(FLAG_UPDATE_SNAPSHOTS)
+                    .help("Store the values of `expect Inspect.snapshot ...` expectations as their new snapshots, instead of comparing them with the stored ones")
+                    .action(ArgAction::SetTrue)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_TIMEOUT)
+                    .long(FLAG_TIMEOUT)
+                    .help("Stop an `expect` that runs for longer than this many seconds, and report it as a failure\n(Every `expect` runs in its own process, so one that crashes or times out doesn't stop the others.)")
+                    .value_parser(parse_timeout)
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_TEST_WORKER_REPORT)
+                    .long(FLAG_TEST_WORKER_REPORT)
+                    .value_parser(value_parser!(PathBuf))
+                    .hide(true)
+                    .required(false)
+            )
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to test")
@@ -287,6 +396,38 @@ pub fn build_app() -> Command {
             )
             .arg(args_for_app.clone().last(true))
         )
+        .subcommand(Command::new(CMD_BENCH)
+            .about("Measure how long the top-level benchmarks (functions like `benchSort = \\{} -> ...`) in a main module and any modules it imports take to run\n(Benchmarks are always compiled with --optimize.)")
+            .arg(flag_main.clone())
+            .arg(
+                Arg::new(FLAG_FILTER)
+                    .long(FLAG_FILTER)
+                    .help("Only run the benchmarks whose name contains this pattern")
+                    .value_parser(value_parser!(String))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_BASELINE)
+                    .long(FLAG_BASELINE)
+                    .help("Compare the timings with a baseline saved earlier with --save-baseline")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(FLAG_SAVE_BASELINE)
+                    .long(FLAG_SAVE_BASELINE)
+                    .help("Save the timings as a JSON baseline in this file, to compare later runs with")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+            )
+            .arg(
+                Arg::new(ROC_FILE)
+                    .help("The .roc file of the main module to benchmark")
+                    .value_parser(value_parser!(PathBuf))
+                    .required(false)
+                    .default_value(DEFAULT_ROC_FILENAME)
+            )
+        )
         .subcommand(Command::new(CMD_REPL)
             .about("Launch the interactive Read Eval Print Loop (REPL)")
             .arg(
@@ -303,6 +444,13 @@ pub fn build_app() -> Command {
                     .action(ArgAction::SetTrue)
                     .required(false)
             )
+            .arg(
+                Arg::new(FLAG_EVAL_DEF)
+                    .long(FLAG_EVAL_DEF)
+                    .value_parser(value_parser!(String))
+                    .hide(true)
+                    .required(false)
+            )
         )
         .subcommand(Command::new(CMD_RUN)
             .about("Run a .roc file even if it has build errors")
@@ -312,6 +460,7 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_debug.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
@@ -328,11 +477,13 @@ pub fn build_app() -> Command {
             .arg(flag_dev.clone())
             .arg(flag_emit_llvm_ir.clone())
             .arg(flag_profiling.clone())
+            .arg(flag_debug.clone())
             .arg(flag_time.clone())
             .arg(flag_linker.clone())
             .arg(flag_build_host.clone())
             .arg(flag_suppress_build_host_warning.clone())
             .arg(flag_fuzz.clone())
+            .arg(flag_watch.clone())
             .arg(roc_file_to_run.clone())
             .arg(args_for_app.clone().last(true))
         )
@@ -381,6 +532,7 @@ pub fn build_app() -> Command {
             .arg(flag_main.clone())
             .arg(flag_time.clone())
             .arg(flag_max_threads.clone())
+            .arg(flag_watch)
             .arg(
                 Arg::new(ROC_FILE)
                     .help("The .roc file to check")
@@ -472,6 +624,7 @@ pub fn build_app() -> Command {
         .arg(flag_dev)
         .arg(flag_emit_llvm_ir)
         .arg(flag_profiling)
+        .arg(flag_debug)
         .arg(flag_time)
         .arg(flag_linker)
         .arg(flag_build_host)
@@ -503,7 +656,16 @@ fn opt_level_from_flags(matches: &ArgMatches) -> OptLevel {
 }
 
 #[cfg(windows)]
-pub fn test(_matches: &ArgMatches, _target: Target) -> io::Result<i32> {
+pub fn bench(_matches: &ArgMatches, _target: Target) -> io::Result<i32> {
+    todo!("running benchmarks does not work on windows right now")
+}
+
+#[cfg(windows)]
+pub fn test(
+    _matches: &ArgMatches,
+    _target: Target,
+    _incremental: Option<&IncrementalCache>,
+) -> io::Result<i32> {
     todo!("running tests does not work on windows right now")
 }
 
@@ -512,19 +674,63 @@ struct ModuleTestResults {
     module_id: ModuleId,
     failed_count: usize,
     passed_count: usize,
+    skipped_count: usize,
     tests_duration: Duration,
 }
 
+/// Runs the top-level expects of the given files. Modules that were type-checked by an earlier
+/// call (and haven't changed since) are not checked again; an `incremental` cache keeps their
+/// types in memory between calls, otherwise they are read from the roc cache directory.
 #[cfg(not(windows))]
-pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
+pub fn test(
+    matches: &ArgMatches,
+    target: Target,
+    incremental: Option<&IncrementalCache>,
+) -> io::Result<i32> {
     use roc_build::program::report_problems_monomorphized;
     use roc_load::{ExecutionMode, FunctionKind, LoadConfig, LoadMonomorphizedError};
     use roc_packaging::cache;
+    use roc_region::all::LineInfo;
+    use roc_reporting::report::RenderTarget;
+    use test_coverage::CoverageReport;
+    use test_filter::{ExpectSource, TestFilter};
+    use test_report::{TestReport, TestReporter};
 
     let start_time = Instant::now();
     let arena = Bump::new();
     let opt_level = opt_level_from_flags(matches);
 
+    let reporter = matches
+        .get_one::<String>(FLAG_REPORTER)
+        .map(String::as_str)
+        .map(TestReporter::from_flag);
+    let worker_report_path = matches.get_one::<PathBuf>(FLAG_TEST_WORKER_REPORT);
+    let mut test_report = TestReport::default();
+
+    let test_filter = matches
+        .get_one::<String>(FLAG_FILTER)
+        .map(String::as_str)
+        .map(TestFilter::parse);
+
+    let coverage_path = matches.get_one::<PathBuf>(FLAG_COVERAGE);
+    let mut coverage_report = CoverageReport::default();
+
+    let seed = matches
+        .get_one::<u64>(FLAG_SEED)
+        .copied()
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_nanos() as u64)
+        });
+
+    // Machine-readable reports get plain-text failure messages, and replace
+    // the usual human-readable output entirely.
+    let render_target = match reporter {
+        Some(_) => RenderTarget::Generic,
+        None => RenderTarget::ColorTerminal,
+    };
+
     let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
         None => Threading::AllAvailable,
         Some(0) => user_error!("cannot build with at most 0 threads"),
@@ -564,8 +770,40 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         flatten_paths
     };
 
+    let max_workers = max_test_workers(threading);
+
+    let expect_options = roc_repl_expect::run::ExpectOptions {
+        seed,
+        update_snapshots: matches.get_flag(FLAG_UPDATE_SNAPSHOTS),
+        isolate: true,
+        timeout: matches.get_one::<Duration>(FLAG_TIMEOUT).copied(),
+        // Each expect runs in its own process, so the expects of a file run in parallel too,
+        // unless this is already one of several workers that test files in parallel.
+        max_children: if worker_report_path.is_some() {
+            1
+        } else {
+            max_workers
+        },
+    };
+
+    // Worker processes can't share an incremental cache, so watch mode tests in this process.
+    // Coverage counters are read from the code of this process, so coverage does too.
+    if paths.len() > 1
+        && max_workers > 1
+        && worker_report_path.is_none()
+        && incremental.is_none()
+        && coverage_path.is_none()
+    {
+        return test_in_workers(matches, &paths, max_workers, reporter);
+    }
+
+    let incremental = incremental
+        .cloned()
+        .unwrap_or_else(IncrementalCache::persistent);
+
     let mut all_files_total_failed_count = 0;
     let mut all_files_total_passed_count = 0;
+    let mut all_files_total_skipped_count = 0;
 
     for path in paths.iter() {
         let arena = &arena;
@@ -581,7 +819,10 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
             render: roc_reporting::report::RenderTarget::ColorTerminal,
             palette: roc_reporting::report::DEFAULT_PALETTE,
             threading,
-            exec_mode: ExecutionMode::Test,
+            exec_mode: ExecutionMode::Test {
+                coverage: coverage_path.is_some(),
+            },
+            incremental: Some(incremental.clone()),
         };
         let load_result = roc_load::load_and_monomorphize(
             arena,
@@ -603,6 +844,7 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         let problems = report_problems_monomorphized(&mut loaded);
 
         let mut expectations = std::mem::take(&mut loaded.expectations);
+        let coverage_points = std::mem::take(&mut loaded.coverage_points);
 
         let interns = loaded.interns.clone();
         let sources = loaded.sources.clone();
@@ -623,74 +865,344 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
                 problems.errors, 0,
                 "if there were errors, we would have already exited."
             );
-            if problems.warnings > 0 {
+            if problems.warnings > 0 && reporter.is_none() {
                 problems.print_error_warning_count(start_time.elapsed());
                 println!(".\n\nRunning tests…\n\n\x1B[36m{}\x1B[39m", "─".repeat(80));
             }
         }
 
         // Run the tests.
+        let coverage_counters =
+            roc_repl_expect::run::CoverageCounters::from_lib(&dyn_lib, &coverage_points);
         let arena = &bumpalo::Bump::new();
         let interns = arena.alloc(interns);
 
-        let mut writer = std::io::stdout();
+        let mut writer: Box<dyn io::Write> = match reporter {
+            Some(_) => Box::new(io::sink()),
+            None => Box::new(io::stdout()),
+        };
 
         let mut total_failed_count = 0;
         let mut total_passed_count = 0;
+        let mut total_skipped_count = 0;
 
         let mut results_by_module = Vec::new();
         let global_layout_interner = layout_interner.into_global();
 
         let compilation_duration = start_time.elapsed();
 
-        for (module_id, expects) in expects_by_module.into_iter() {
+        for (module_id, mut expects) in expects_by_module.into_iter() {
+            let (module_path, source) = sources.get(&module_id).unwrap();
+            let module_name = test_module_name(interns, module_id, module_path);
+
+            let mut skipped = Vec::new();
+
+            let line_info = LineInfo::new(source);
+            let expect_source = ExpectSource {
+                module_name: &module_name,
+                module_path,
+                source,
+                line_info: &line_info,
+            };
+
+            let test_names: MutMap<_, _> = expects
+                .pure
+                .iter()
+                .filter_map(|expect| {
+                    let name = expect_source.test_name(interns, expect)?;
+                    Some((expect.region, name))
+                })
+                .collect();
+
+            if let Some(test_filter) = &test_filter {
+                expects.pure.retain(|expect| {
+                    let selected = test_filter.matches(interns, &expect_source, expect);
+
+                    if !selected {
+                        skipped.push(expect.region);
+                    }
+
+                    selected
+                });
+            }
+
             let test_start_time = Instant::now();
 
-            let (failed_count, passed_count) = roc_repl_expect::run::run_toplevel_expects(
+            let expect_results = roc_repl_expect::run::run_toplevel_expects(
                 &mut writer,
-                roc_reporting::report::RenderTarget::ColorTerminal,
+                render_target,
                 arena,
                 interns,
                 &global_layout_interner,
                 &dyn_lib,
                 &mut expectations,
                 expects,
+                &coverage_counters,
+                expect_options,
             )
             .unwrap();
 
             let tests_duration = test_start_time.elapsed();
 
+            let failed_count = expect_results
+                .iter()
+                .filter(|result| !result.passed())
+                .count();
+            let passed_count = expect_results.len() - failed_count;
+            let skipped_count = skipped.len();
+
+            let wants_report = reporter.is_some() || worker_report_path.is_some();
+
+            if wants_report && !(expect_results.is_empty() && skipped.is_empty()) {
+                test_report.add_module(
+                    module_name,
+                    module_path,
+                    source,
+                    tests_duration,
+                    &expect_results,
+                    &skipped,
+                    &test_names,
+                );
+            }
+
             results_by_module.push(ModuleTestResults {
                 module_id,
                 failed_count,
                 passed_count,
+                skipped_count,
                 tests_duration,
             });
 
             total_failed_count += failed_count;
             total_passed_count += passed_count;
+            total_skipped_count += skipped_count;
+        }
+
+        // Every module that was instrumented is reported, including those without expects.
+        for (module_id, points) in coverage_points.iter() {
+            let (module_path, source) = sources.get(module_id).unwrap();
+            let hits = roc_repl_expect::run::coverage_counts(&dyn_lib, *module_id, points);
+
+            coverage_report.add_module(
+                test_module_name(interns, *module_id, module_path),
+                module_path,
+                source,
+                points,
+                &hits,
+                |symbol| symbol.as_str(interns).to_string(),
+            );
         }
 
         let total_duration = start_time.elapsed();
         all_files_total_failed_count += total_failed_count;
         all_files_total_passed_count += total_passed_count;
-        if total_failed_count == 0 && total_passed_count == 0 {
-            // Only report no expectations found once.
+        all_files_total_skipped_count += total_skipped_count;
+        if total_failed_count == 0 && total_passed_count == 0 && total_skipped_count == 0 {
+            // Only report no expectations found once. A file whose expects were all filtered
+            // out still reports how many were skipped.
             continue;
+        } else if reporter.is_some() {
+            // The combined report is printed once every file has been tested.
         } else if matches.get_flag(FLAG_VERBOSE) {
             println!("Compiled in {} ms.", compilation_duration.as_millis());
             for module_test_results in results_by_module {
                 print_test_results(module_test_results, &sources);
             }
         } else {
-            let test_summary_str =
-                test_summary(total_failed_count, total_passed_count, total_duration);
+            let test_summary_str = test_summary(
+                total_failed_count,
+                total_passed_count,
+                total_skipped_count,
+                total_duration,
+            );
             println!("{test_summary_str}");
         }
     }
-    if all_files_total_failed_count == 0 && all_files_total_passed_count == 0 {
+
+    if let Some(worker_report_path) = worker_report_path {
+        // The parent process combines the reports of all workers and prints the final results.
+        std::fs::write(worker_report_path, test_report.to_json())?;
+
+        return Ok((all_files_total_failed_count > 0) as i32);
+    }
+
+    if let Some(coverage_path) = coverage_path {
+        std::fs::write(coverage_path, coverage_report.to_lcov())?;
+
+        if reporter.is_none() && !coverage_report.is_empty() {
+            println!(
+                "\nCoverage (written to {}):\n\n{}",
+                coverage_path.display(),
+                coverage_report.summary_table()
+            );
+        }
+    }
+
+    finish_test_run(
+        matches,
+        reporter,
+        &test_report,
+        all_files_total_failed_count,
+        all_files_total_passed_count,
+        all_files_total_skipped_count,
+    )
+}
+
+/// A `--timeout` in seconds, like `10` or `0.5`
+fn parse_timeout(seconds: &str) -> Result<std::time::Duration, String> {
+    let invalid = || format!("`{seconds}` is not a number of seconds, like 10 or 0.5");
+
+    match seconds.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => {
+            std::time::Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// How many `roc test` worker processes (or the child processes running expects) may run at
+/// once, following `--max-threads`.
+#[cfg(not(windows))]
+fn max_test_workers(threading: Threading) -> usize {
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    match threading {
+        Threading::Single => 1,
+        Threading::AllAvailable => available,
+        Threading::AtMost(at_most) => Ord::min(available, at_most),
+    }
+}
+
+/// Tests every file in its own worker process, several at a time. The workers' output is
+/// printed in the order the files were given, so it reads the same as a sequential run.
+#[cfg(not(windows))]
+fn test_in_workers(
+    matches: &ArgMatches,
+    paths: &[PathBuf],
+    max_workers: usize,
+    reporter: Option<test_report::TestReporter>,
+) -> io::Result<i32> {
+    use std::io::Write;
+
+    let outcomes = test_workers::run_test_workers(paths, &test_worker_args(matches), max_workers)?;
+
+    let mut test_report = test_report::TestReport::default();
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        stdout.write_all(&outcome.stdout)?;
+        stderr.write_all(&outcome.stderr)?;
+
+        match outcome.report {
+            Some(report) => test_report.merge(report),
+            None if outcome.crashed() => {
+                let message = format!(
+                    "Testing {} crashed ({}), so the results of its expects are missing.",
+                    path.display(),
+                    outcome.status
+                );
+
+                if reporter.is_none() {
+                    println!(
+                        "\n{}{message}{}\n",
+                        ANSI_STYLE_CODES.red, ANSI_STYLE_CODES.reset
+                    );
+                }
+
+                test_report.add_crashed_file(path, message);
+            }
+            None => {
+                // This file had problems that kept its expects from running. A sequential
+                // run stops at the first such file, so this does too.
+                stdout.flush()?;
+
+                return Ok(outcome.status.code().unwrap_or(1));
+            }
+        }
+    }
+
+    finish_test_run(
+        matches,
+        reporter,
+        &test_report,
+        test_report.failed,
+        test_report.passed,
+        test_report.skipped,
+    )
+}
+
+/// The arguments for a worker process to test a single file the same way this process would.
+#[cfg(not(windows))]
+fn test_worker_args(matches: &ArgMatches) -> Vec<OsString> {
+    let mut args = vec![OsString::from(CMD_TEST)];
+
+    for flag in [
+        FLAG_OPTIMIZE,
+        FLAG_OPT_SIZE,
+        FLAG_DEV,
+        FLAG_VERBOSE,
+        FLAG_UPDATE_SNAPSHOTS,
+    ] {
+        if matches.get_flag(flag) {
+            args.push(format!("--{flag}").into());
+        }
+    }
+
+    if let Some(main_path) = matches.get_one::<PathBuf>(FLAG_MAIN) {
+        args.push(format!("--{FLAG_MAIN}").into());
+        args.push(main_path.as_os_str().to_owned());
+    }
+
+    for flag in [FLAG_FILTER, FLAG_REPORTER] {
+        if let Some(value) = matches.get_one::<String>(flag) {
+            args.push(format!("--{flag}={value}").into());
+        }
+    }
+
+    if let Some(seed) = matches.get_one::<u64>(FLAG_SEED) {
+        args.push(format!("--{FLAG_SEED}={seed}").into());
+    }
+
+    if let Some(timeout) = matches.get_one::<Duration>(FLAG_TIMEOUT) {
+        args.push(format!("--{FLAG_TIMEOUT}={}", timeout.as_secs_f64()).into());
+    }
+
+    // The workers already run in parallel, so each of them only needs one thread.
+    args.push(format!("--{FLAG_MAX_THREADS}=1").into());
+
+    args
+}
+
+/// Prints the report requested with `--reporter` (or explains why nothing ran),
+/// and returns the exit code for the whole `roc test` run.
+#[cfg(not(windows))]
+fn finish_test_run(
+    matches: &ArgMatches,
+    reporter: Option<test_report::TestReporter>,
+    test_report: &test_report::TestReport,
+    failed_count: usize,
+    passed_count: usize,
+    skipped_count: usize,
+) -> io::Result<i32> {
+    if let Some(reporter) = reporter {
+        let rendered = reporter.render(test_report);
+
+        match matches.get_one::<PathBuf>(FLAG_OUTPUT) {
+            Some(output_path) => std::fs::write(output_path, rendered)?,
+            None => print!("{rendered}"),
+        }
+    }
+
+    if failed_count == 0 && passed_count == 0 {
         // TODO print this in a more nicely formatted way!
-        println!("No expectations were found.");
+        if reporter.is_some() {
+            // the report already shows that nothing ran
+        } else if skipped_count > 0 {
+            println!("No expectations matched --{FLAG_FILTER}, {skipped_count} were skipped.");
+        } else {
+            println!("No expectations were found.");
+        }
 
         // If no tests ran, treat that as an error. This is perhaps
         // briefly annoying at the very beginning of a project when
@@ -699,7 +1211,7 @@ pub fn test(matches: &ArgMatches, target: Target) -> io::Result<i32> {
         // running tests altogether!
         Ok(2)
     } else {
-        Ok((all_files_total_failed_count > 0) as i32)
+        Ok((failed_count > 0) as i32)
     }
 }
 
@@ -737,10 +1249,11 @@ fn print_test_results(
         module_id,
         failed_count,
         passed_count,
+        skipped_count,
         tests_duration,
     } = module_test_results;
 
-    let test_summary_str = test_summary(failed_count, passed_count, tests_duration);
+    let test_summary_str = test_summary(failed_count, passed_count, skipped_count, tests_duration);
 
     let (module_path, _) = sources.get(&module_id).unwrap();
     let module_name = module_path.file_name().unwrap().to_str().unwrap();
@@ -748,20 +1261,54 @@ fn print_test_results(
     println!("\n{module_name}:\n    {test_summary_str}",);
 }
 
+/// The name to report a module's tests under. App modules don't have a user-facing
+/// module name, so those are reported by their file name instead.
 #[cfg(not(windows))]
-fn test_summary(failed_count: usize, passed_count: usize, tests_duration: Duration) -> String {
+fn test_module_name(
+    interns: &roc_module::symbol::Interns,
+    module_id: ModuleId,
+    module_path: &Path,
+) -> String {
+    let module_name = interns.module_name(module_id);
+
+    if module_name.starts_with('#') {
+        module_path
+            .file_name()
+            .unwrap_or(module_path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        module_name.to_string()
+    }
+}
+
+#[cfg(not(windows))]
+fn test_summary(
+    failed_count: usize,
+    passed_count: usize,
+    skipped_count: usize,
+    tests_duration: Duration,
+) -> String {
     let failed_color = if failed_count == 0 {
         ANSI_STYLE_CODES.green
     } else {
         ANSI_STYLE_CODES.red
     };
     let passed_color = ANSI_STYLE_CODES.green;
+    let skipped_color = ANSI_STYLE_CODES.yellow;
     let reset = ANSI_STYLE_CODES.reset;
 
-    format!(
-        "{failed_color}{failed_count}{reset} failed and {passed_color}{passed_count}{reset} passed in {} ms.",
-        tests_duration.as_millis()
-    )
+    if skipped_count == 0 {
+        format!(
+            "{failed_color}{failed_count}{reset} failed and {passed_color}{passed_count}{reset} passed in {} ms.",
+            tests_duration.as_millis()
+        )
+    } else {
+        format!(
+            "{failed_color}{failed_count}{reset} failed, {passed_color}{passed_count}{reset} passed and {skipped_color}{skipped_count}{reset} skipped in {} ms.",
+            tests_duration.as_millis()
+        )
+    }
 }
 
 /// Find the element of `options` with the smallest edit distance to
@@ -916,6 +1463,8 @@ pub fn build(
     let code_gen_backend = if matches!(opt_level, OptLevel::Development) {
         if matches!(target.architecture(), Architecture::Wasm32) {
             CodeGenBackend::Wasm
+        } else if should_run_expects {
+            CodeGenBackend::Assembly(AssemblyBackendMode::BinaryWithExpect)
         } else {
             CodeGenBackend::Assembly(AssemblyBackendMode::Binary)
         }
@@ -934,7 +1483,9 @@ pub fn build(
         user_error!("Cannot emit llvm ir while using a dev backend.");
     }
 
-    let emit_debug_info = matches.get_flag(FLAG_PROFILING)
+    let emit_source_locations = matches.get_flag(FLAG_DEBUG);
+    let emit_debug_info = emit_source_locations
+        || matches.get_flag(FLAG_PROFILING)
         || matches!(opt_level, OptLevel::Development | OptLevel::Normal);
     let emit_timings = matches.get_flag(FLAG_TIME);
 
@@ -978,11 +1529,13 @@ pub fn build(
         backend: code_gen_backend,
         opt_level,
         emit_debug_info,
+        emit_source_locations,
         emit_llvm_ir,
         fuzz,
     };
 
-    let load_config = standard_load_config(target, build_ordering, threading);
+    let mut load_config = standard_load_config(target, build_ordering, threading);
+    load_config.incremental = Some(IncrementalCache::persistent());
 
     let res_binary_path = roc_build::program::build_file(
         &arena,
@@ -1356,6 +1909,11 @@ fn roc_dev_native(
         0 => unsafe {
             // we are the child
 
+            // Don't outlive the process that reports our expects, e.g. when `roc dev --watch`
+            // stops it to run the app again.
+            #[cfg(target_os = "linux")]
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL);
+
             executable.execve(&argv, &envp);
 
             // Display a human-friendly error message
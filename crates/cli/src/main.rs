@@ -3,19 +3,20 @@ use bumpalo::Bump;
 use roc_build::link::LinkType;
 use roc_build::program::{check_file, CodeGenBackend};
 use roc_cli::{
-    build_app, default_linking_strategy, format_files, format_src, test, BuildConfig, FormatMode,
-    CMD_BUILD, CMD_CHECK, CMD_DEV, CMD_DOCS, CMD_FORMAT, CMD_GLUE, CMD_PREPROCESS_HOST, CMD_REPL,
-    CMD_RUN, CMD_TEST, CMD_VERSION, DIRECTORY_OR_FILES, FLAG_CHECK, FLAG_DEV, FLAG_LIB, FLAG_MAIN,
-    FLAG_MIGRATE, FLAG_NO_COLOR, FLAG_NO_HEADER, FLAG_NO_LINK, FLAG_OUTPUT, FLAG_PP_DYLIB,
-    FLAG_PP_HOST, FLAG_PP_PLATFORM, FLAG_STDIN, FLAG_STDOUT, FLAG_TARGET, FLAG_TIME, FLAG_VERBOSE,
-    GLUE_DIR, GLUE_SPEC, ROC_FILE, VERSION,
+    bench, build_app, default_linking_strategy, format_files, format_src, test, watch, watch_dev,
+    BuildConfig, FormatMode, CMD_BENCH, CMD_BUILD, CMD_CHECK, CMD_DEV, CMD_DOCS, CMD_FORMAT,
+    CMD_GLUE, CMD_PREPROCESS_HOST, CMD_REPL, CMD_RUN, CMD_TEST, CMD_VERSION, DIRECTORY_OR_FILES,
+    FLAG_CHECK, FLAG_DEV, FLAG_EVAL_DEF, FLAG_LIB, FLAG_MAIN, FLAG_MIGRATE, FLAG_NO_COLOR,
+    FLAG_NO_HEADER, FLAG_NO_LINK, FLAG_OUTPUT, FLAG_PP_DYLIB, FLAG_PP_HOST, FLAG_PP_PLATFORM,
+    FLAG_STDIN, FLAG_STDOUT, FLAG_TARGET, FLAG_TIME, FLAG_VERBOSE, FLAG_WATCH, GLUE_DIR, GLUE_SPEC,
+    ROC_FILE, VERSION,
 };
 use roc_docs::generate_docs_html;
 use roc_error_macros::user_error;
 use roc_fmt::MigrationFlags;
 use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::LlvmBackendMode;
-use roc_load::{LoadingProblem, Threading};
+use roc_load::{IncrementalCache, LoadingProblem, Threading};
 use roc_packaging::cache::{self, RocCacheDir};
 use roc_target::Target;
 use std::fs::{self, FileType};
@@ -79,16 +80,28 @@ fn main() -> io::Result<()> {
             }
         }
         Some((CMD_TEST, matches)) => {
-            if matches.contains_id(ROC_FILE) {
-                test(matches, Triple::host().into())
-            } else {
+            if !matches.contains_id(ROC_FILE) {
                 eprintln!("What .roc file do you want to test? Specify it at the end of the `roc test` command.");
 
                 Ok(1)
+            } else if matches.get_flag(FLAG_WATCH) {
+                let incremental = IncrementalCache::persistent();
+                let roc_file_path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
+
+                watch(roc_file_path, &incremental, || {
+                    test(matches, Triple::host().into(), Some(&incremental))?;
+
+                    Ok(None)
+                })
+            } else {
+                test(matches, Triple::host().into(), None)
             }
         }
+        Some((CMD_BENCH, matches)) => bench(matches, Triple::host().into()),
         Some((CMD_DEV, matches)) => {
-            if matches.contains_id(ROC_FILE) {
+            if matches.contains_id(ROC_FILE) && matches.get_flag(FLAG_WATCH) {
+                watch_dev(matches)
+            } else if matches.contains_id(ROC_FILE) {
                 build(
                     matches,
                     &subcommands,
@@ -262,6 +275,7 @@ fn main() -> io::Result<()> {
                             emit_timings,
                             RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
                             threading,
+                            None,
                         ) {
                             Ok((problems, total_time)) => {
                                 problems.print_error_warning_count(total_time);
@@ -288,28 +302,46 @@ fn main() -> io::Result<()> {
                     Ok(exit_code)
                 }
                 _ => {
-                    match check_file(
-                        &arena,
-                        roc_file_path.to_owned(),
-                        opt_main_path.cloned(),
-                        emit_timings,
-                        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
-                        threading,
-                    ) {
-                        Ok((problems, total_time)) => {
-                            problems.print_error_warning_count(total_time);
-                            println!(".\n");
-                            Ok(problems.exit_code())
-                        }
+                    let check = |incremental: Option<IncrementalCache>| {
+                        // every run gets a fresh arena, so that watch mode doesn't keep growing one
+                        let arena = Bump::new();
+
+                        match check_file(
+                            &arena,
+                            roc_file_path.to_owned(),
+                            opt_main_path.cloned(),
+                            emit_timings,
+                            RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+                            threading,
+                            incremental,
+                        ) {
+                            Ok((problems, total_time)) => {
+                                problems.print_error_warning_count(total_time);
+                                println!(".\n");
+                                problems.exit_code()
+                            }
 
-                        Err(LoadingProblem::FormattedReport(report, _)) => {
-                            print!("{report}");
+                            Err(LoadingProblem::FormattedReport(report, _)) => {
+                                print!("{report}");
 
-                            Ok(1)
-                        }
-                        Err(other) => {
-                            panic!("build_file failed with error:\n{other:?}");
+                                1
+                            }
+                            Err(other) => {
+                                panic!("build_file failed with error:\n{other:?}");
+                            }
                         }
+                    };
+
+                    if matches.get_flag(FLAG_WATCH) {
+                        let incremental = IncrementalCache::persistent();
+
+                        watch(roc_file_path, &incremental, || {
+                            check(Some(incremental.clone()));
+
+                            Ok(None)
+                        })
+                    } else {
+                        Ok(check(Some(IncrementalCache::persistent())))
                     }
                 }
             }
@@ -318,6 +350,25 @@ fn main() -> io::Result<()> {
             let has_color = !matches.get_one::<bool>(FLAG_NO_COLOR).unwrap();
             let has_header = !matches.get_one::<bool>(FLAG_NO_HEADER).unwrap();
 
+            if let Some(name) = matches.get_one::<String>(FLAG_EVAL_DEF) {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source)?;
+
+                let src_dir = std::env::current_dir()?;
+                let target = Triple::host().into();
+
+                return match roc_repl_cli::evaluate_module_def(src_dir, &source, name, target) {
+                    Ok(output) => {
+                        println!("{output}");
+                        Ok(0)
+                    }
+                    Err(problem) => {
+                        println!("{problem}");
+                        Ok(1)
+                    }
+                };
+            }
+
             Ok(roc_repl_cli::main(has_color, has_header))
         }
         Some((CMD_DOCS, matches)) => {
@@ -0,0 +1,380 @@
+//! Coverage reports for `roc test --coverage`: which top-level defs, `when` branches and `if`
+//! arms the expects ran, written in the lcov format that most coverage tools read.
+use roc_can::coverage::{CoverageKind, CoveragePoint};
+use roc_module::symbol::Symbol;
+use roc_region::all::LineInfo;
+use roc_reporting::report::ANSI_STYLE_CODES;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The coverage of every module tested during one `roc test` invocation.
+#[derive(Debug, Default)]
+pub struct CoverageReport {
+    files: Vec<FileCoverage>,
+}
+
+#[derive(Debug)]
+struct FileCoverage {
+    module: String,
+    path: PathBuf,
+    defs: Vec<DefCoverage>,
+    branches: Vec<BranchCoverage>,
+    /// How often each line ran, by 1-based line number
+    lines: BTreeMap<u32, u64>,
+}
+
+#[derive(Debug)]
+struct DefCoverage {
+    name: String,
+    line: u32,
+    hits: u64,
+}
+
+#[derive(Debug)]
+struct BranchCoverage {
+    line: u32,
+    hits: u64,
+}
+
+impl CoverageReport {
+    /// Records how often each of the `points` of a module ran. Testing several files can run
+    /// the same module more than once, in which case the hits of all runs are added up.
+    pub fn add_module(
+        &mut self,
+        module: String,
+        path: &Path,
+        source: &str,
+        points: &[CoveragePoint],
+        hits: &[u64],
+        def_name: impl Fn(Symbol) -> String,
+    ) {
+        let coverage = FileCoverage::new(module, path, source, points, hits, def_name);
+
+        // Modules are kept in order of their paths, so that reports don't change from run to run.
+        match self
+            .files
+            .binary_search_by(|file| file.path.as_path().cmp(path))
+        {
+            Ok(index) => self.files[index].merge(coverage),
+            Err(index) => self.files.insert(index, coverage),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The report in the lcov tracefile format, with one record per module.
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        for file in self.files.iter() {
+            writeln!(out, "TN:").unwrap();
+            writeln!(out, "SF:{}", file.path.display()).unwrap();
+
+            for def in file.defs.iter() {
+                writeln!(out, "FN:{},{}", def.line, def.name).unwrap();
+            }
+
+            for def in file.defs.iter() {
+                writeln!(out, "FNDA:{},{}", def.hits, def.name).unwrap();
+            }
+
+            let (defs_hit, defs_found) = file.defs_hit();
+            writeln!(out, "FNF:{defs_found}").unwrap();
+            writeln!(out, "FNH:{defs_hit}").unwrap();
+
+            for (index, branch) in file.branches.iter().enumerate() {
+                writeln!(out, "BRDA:{},0,{index},{}", branch.line, branch.hits).unwrap();
+            }
+
+            let (branches_hit, branches_found) = file.branches_hit();
+            writeln!(out, "BRF:{branches_found}").unwrap();
+            writeln!(out, "BRH:{branches_hit}").unwrap();
+
+            for (line, hits) in file.lines.iter() {
+                writeln!(out, "DA:{line},{hits}").unwrap();
+            }
+
+            let (lines_hit, lines_found) = file.lines_hit();
+            writeln!(out, "LF:{lines_found}").unwrap();
+            writeln!(out, "LH:{lines_hit}").unwrap();
+
+            writeln!(out, "end_of_record").unwrap();
+        }
+
+        out
+    }
+
+    /// A table of how much of each module ran, for printing after the test results.
+    pub fn summary_table(&self) -> String {
+        let header = ["Module", "Defs", "Branches", "Lines"];
+
+        let mut rows: Vec<[String; 4]> = self
+            .files
+            .iter()
+            .map(|file| {
+                [
+                    file.module.clone(),
+                    ratio(file.defs_hit()),
+                    ratio(file.branches_hit()),
+                    ratio(file.lines_hit()),
+                ]
+            })
+            .collect();
+
+        let total = |hit: fn(&FileCoverage) -> (usize, usize)| {
+            self.files
+                .iter()
+                .map(hit)
+                .fold((0, 0), |(a, b), (c, d)| (a + c, b + d))
+        };
+
+        rows.push([
+            "Total".to_string(),
+            ratio(total(FileCoverage::defs_hit)),
+            ratio(total(FileCoverage::branches_hit)),
+            ratio(total(FileCoverage::lines_hit)),
+        ]);
+
+        let mut widths = header.map(str::len);
+
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = Ord::max(*width, cell.chars().count());
+            }
+        }
+
+        let bold = ANSI_STYLE_CODES.bold;
+        let reset = ANSI_STYLE_CODES.reset;
+        let mut out = String::new();
+
+        let header_line = format_row(&header.map(String::from), &widths);
+        writeln!(out, "{bold}{header_line}{reset}").unwrap();
+
+        for row in rows.iter() {
+            writeln!(out, "{}", format_row(row, &widths)).unwrap();
+        }
+
+        out
+    }
+}
+
+impl FileCoverage {
+    fn new(
+        module: String,
+        path: &Path,
+        source: &str,
+        points: &[CoveragePoint],
+        hits: &[u64],
+        def_name: impl Fn(Symbol) -> String,
+    ) -> Self {
+        let line_info = LineInfo::new(source);
+        let source_lines: Vec<&str> = source.lines().collect();
+
+        let mut defs = Vec::new();
+        let mut branches = Vec::new();
+        let mut lines = BTreeMap::new();
+
+        // Visit enclosing regions before the regions they contain, so that the lines of
+        // a branch get the hits of that branch rather than those of its def.
+        let mut ordered: Vec<_> = points.iter().zip(hits.iter().copied()).collect();
+        ordered.sort_by_key(|(point, _)| {
+            (point.region.start(), std::cmp::Reverse(point.region.end()))
+        });
+
+        for (point, hits) in ordered {
+            let lc_region = line_info.convert_region(point.region);
+            let start_line = lc_region.start.line + 1;
+            let end_line = lc_region.end.line + 1;
+
+            match point.kind {
+                CoverageKind::Def(symbol) => defs.push(DefCoverage {
+                    name: def_name(symbol),
+                    line: start_line,
+                    hits,
+                }),
+                CoverageKind::WhenBranch | CoverageKind::IfArm => {
+                    branches.push(BranchCoverage {
+                        line: start_line,
+                        hits,
+                    });
+                }
+            }
+
+            // The first line of a region may also hold code of the enclosing region
+            // (e.g. `x = if a then b else c`), which ran at least as often.
+            let shares_first_line = source_lines
+                .get(start_line as usize - 1)
+                .and_then(|text| text.get(..lc_region.start.column as usize))
+                .is_some_and(|before| !before.trim().is_empty());
+
+            for line in start_line..=end_line {
+                let is_code = source_lines
+                    .get(line as usize - 1)
+                    .map(|text| text.trim())
+                    .is_some_and(|text| !text.is_empty() && !text.starts_with('#'));
+
+                if !is_code {
+                    continue;
+                }
+
+                let line_hits = lines.entry(line).or_insert(hits);
+
+                *line_hits = if line == start_line && shares_first_line {
+                    Ord::max(*line_hits, hits)
+                } else {
+                    hits
+                };
+            }
+        }
+
+        Self {
+            module,
+            path: path.to_path_buf(),
+            defs,
+            branches,
+            lines,
+        }
+    }
+
+    fn merge(&mut self, other: FileCoverage) {
+        for (def, other) in self.defs.iter_mut().zip(other.defs) {
+            def.hits += other.hits;
+        }
+
+        for (branch, other) in self.branches.iter_mut().zip(other.branches) {
+            branch.hits += other.hits;
+        }
+
+        for (line, hits) in other.lines {
+            *self.lines.entry(line).or_insert(0) += hits;
+        }
+    }
+
+    /// How many defs ran, out of how many there are
+    fn defs_hit(&self) -> (usize, usize) {
+        let hit = self.defs.iter().filter(|def| def.hits > 0).count();
+
+        (hit, self.defs.len())
+    }
+
+    fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .iter()
+            .filter(|branch| branch.hits > 0)
+            .count();
+
+        (hit, self.branches.len())
+    }
+
+    fn lines_hit(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|hits| **hits > 0).count();
+
+        (hit, self.lines.len())
+    }
+}
+
+fn ratio((hit, found): (usize, usize)) -> String {
+    if found == 0 {
+        "-".to_string()
+    } else {
+        let percent = 100.0 * hit as f64 / found as f64;
+
+        format!("{hit}/{found} ({percent:.1}%)")
+    }
+}
+
+fn format_row(row: &[String; 4], widths: &[usize; 4]) -> String {
+    let [module, rest @ ..] = row;
+    let mut line = format!("{module:<width$}", width = widths[0]);
+
+    for (cell, width) in rest.iter().zip(&widths[1..]) {
+        write!(line, "  {cell:>width$}").unwrap();
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roc_region::all::{Position, Region};
+
+    const SOURCE: &str =
+        "module [f]\n\nf = \\x ->\n    if x then\n        1\n    else\n        2\n";
+
+    fn point(start: u32, end: u32, kind: CoverageKind) -> CoveragePoint {
+        CoveragePoint {
+            region: Region::new(Position::new(start), Position::new(end)),
+            kind,
+        }
+    }
+
+    fn example_report(hits: &[u64]) -> CoverageReport {
+        let mut report = CoverageReport::default();
+
+        report.add_module(
+            "Example".to_string(),
+            Path::new("Example.roc"),
+            SOURCE,
+            &[
+                point(16, 64, CoverageKind::Def(Symbol::LIST_MAP)),
+                point(44, 45, CoverageKind::IfArm),
+                point(63, 64, CoverageKind::IfArm),
+            ],
+            hits,
+            |_| "f".to_string(),
+        );
+
+        report
+    }
+
+    #[test]
+    fn lcov_records() {
+        let lcov = example_report(&[2, 2, 0]).to_lcov();
+
+        assert_eq!(
+            lcov,
+            "TN:\nSF:Example.roc\nFN:3,f\nFNDA:2,f\nFNF:1\nFNH:1\n\
+             BRDA:5,0,0,2\nBRDA:7,0,1,0\nBRF:2\nBRH:1\n\
+             DA:3,2\nDA:4,2\nDA:5,2\nDA:6,2\nDA:7,0\nLF:5\nLH:4\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn merging_adds_hits() {
+        let mut report = example_report(&[2, 2, 0]);
+
+        report.add_module(
+            "Example".to_string(),
+            Path::new("Example.roc"),
+            SOURCE,
+            &[
+                point(16, 64, CoverageKind::Def(Symbol::LIST_MAP)),
+                point(44, 45, CoverageKind::IfArm),
+                point(63, 64, CoverageKind::IfArm),
+            ],
+            &[1, 0, 1],
+            |_| "f".to_string(),
+        );
+
+        let lcov = report.to_lcov();
+
+        assert!(lcov.contains("FNDA:3,f\n"));
+        assert!(lcov.contains("BRDA:7,0,1,1\n"));
+        assert!(lcov.contains("LH:5\n"));
+    }
+
+    #[test]
+    fn summary_counts() {
+        let table = example_report(&[2, 2, 0]).summary_table();
+
+        assert!(table.contains("1/1 (100.0%)"));
+        assert!(table.contains("1/2 (50.0%)"));
+        assert!(table.contains("4/5 (80.0%)"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Selecting which top-level `expect`s `roc test` runs.
+use roc_module::symbol::Interns;
+use roc_region::all::{LineInfo, Region};
+use roc_repl_expect::run::ToplevelExpect;
+use std::path::{Path, PathBuf};
+
+/// Written in a comment right above an expect to give it a name, e.g. `# test: empty input`
+const TEST_LABEL_PREFIX: &str = "test:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestFilter {
+    /// Run the expects whose module name, preceding def name, or `# test:` label contains this.
+    Pattern(String),
+    /// Run the expect at this (1-based) line of this file, written as `path/to/File.roc:12`.
+    Location { path: PathBuf, line: u32 },
+}
+
+/// Where an expect is written, and everything it can be selected by.
+pub struct ExpectSource<'a> {
+    pub module_name: &'a str,
+    pub module_path: &'a Path,
+    pub source: &'a str,
+    pub line_info: &'a LineInfo,
+}
+
+impl ExpectSource<'_> {
+    /// The name an expect is reported under: its `# test:` label, or otherwise the name
+    /// of the def it's written under. Unlike its line, this doesn't change when code above it moves.
+    pub fn test_name(&self, interns: &Interns, expect: &ToplevelExpect) -> Option<String> {
+        let header = ExpectHeader::new(self.source, self.line_info, expect.region);
+
+        match (header.label, expect.preceding_def) {
+            (Some(label), _) => Some(label.to_string()),
+            (None, Some(def)) => Some(def.as_str(interns).to_string()),
+            (None, None) => None,
+        }
+    }
+}
+
+impl TestFilter {
+    pub fn parse(filter: &str) -> Self {
+        if let Some((path, line)) = filter.rsplit_once(':') {
+            if let (true, Ok(line)) = (path.ends_with(".roc"), line.parse()) {
+                return TestFilter::Location {
+                    path: PathBuf::from(path),
+                    line,
+                };
+            }
+        }
+
+        TestFilter::Pattern(filter.to_string())
+    }
+
+    pub fn matches(
+        &self,
+        interns: &Interns,
+        expect_source: &ExpectSource,
+        expect: &ToplevelExpect,
+    ) -> bool {
+        let header =
+            ExpectHeader::new(expect_source.source, expect_source.line_info, expect.region);
+
+        match self {
+            TestFilter::Pattern(pattern) => {
+                let def_name = expect.preceding_def.map(|symbol| symbol.as_str(interns));
+
+                expect_source.module_name.contains(pattern.as_str())
+                    || def_name.map_or(false, |name| name.contains(pattern.as_str()))
+                    || header
+                        .label
+                        .map_or(false, |label| label.contains(pattern.as_str()))
+            }
+            TestFilter::Location { path, line } => {
+                let end_line = expect_source
+                    .line_info
+                    .convert_pos(expect.region.end())
+                    .line
+                    + 1;
+
+                (header.line..=end_line).contains(line)
+                    && same_file(path, expect_source.module_path)
+            }
+        }
+    }
+}
+
+/// What precedes the `expect` keyword. An expect's region starts at the blank lines
+/// and comments written before it, so that they're shown in failure reports.
+struct ExpectHeader<'a> {
+    /// The name from a `# test: name` comment, if there is one
+    label: Option<&'a str>,
+    /// The (1-based) line the `expect` keyword is on
+    line: u32,
+}
+
+impl<'a> ExpectHeader<'a> {
+    fn new(source: &'a str, line_info: &LineInfo, region: Region) -> Self {
+        let start = region.start().offset as usize;
+        let end = region.end().offset as usize;
+        let first_line = line_info.convert_pos(region.start()).line + 1;
+
+        let mut label = None;
+        let mut line = first_line;
+
+        for (index, text) in source[start..end].lines().enumerate() {
+            let text = text.trim();
+
+            if text.is_empty() {
+                continue;
+            }
+
+            match text.strip_prefix('#') {
+                Some(comment) => {
+                    if let Some(name) = comment.trim_start().strip_prefix(TEST_LABEL_PREFIX) {
+                        label = Some(name.trim());
+                    }
+                }
+                None => {
+                    line = first_line + index as u32;
+                    break;
+                }
+            }
+        }
+
+        Self { label, line }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roc_region::all::Position;
+
+    const SOURCE: &str = indoc::indoc!(
+        r#"
+        module [add]
+
+        add = \a, b -> a + b
+
+        # test: adds small numbers
+        expect add 1 2 == 3
+
+        expect
+            add 0 0 == 0
+        "#
+    );
+
+    fn header_of(start: &str, end: &str) -> (Option<&'static str>, u32) {
+        // regions start right after the previous def, like the parser produces them
+        let start = SOURCE.find(start).unwrap() + start.len();
+        let end = SOURCE.find(end).unwrap() + end.len();
+        let region = Region::new(Position::new(start as u32), Position::new(end as u32));
+
+        let line_info = LineInfo::new(SOURCE);
+        let header = ExpectHeader::new(SOURCE, &line_info, region);
+
+        (header.label, header.line)
+    }
+
+    #[test]
+    fn labelled_expect() {
+        assert_eq!(
+            header_of("a + b", "add 1 2 == 3"),
+            (Some("adds small numbers"), 6)
+        );
+    }
+
+    #[test]
+    fn unlabelled_expect() {
+        assert_eq!(header_of("add 1 2 == 3", "add 0 0 == 0"), (None, 8));
+    }
+
+    #[test]
+    fn parse_filters() {
+        assert_eq!(
+            TestFilter::parse("src/Add.roc:6"),
+            TestFilter::Location {
+                path: PathBuf::from("src/Add.roc"),
+                line: 6
+            }
+        );
+        assert_eq!(
+            TestFilter::parse("add"),
+            TestFilter::Pattern("add".to_string())
+        );
+        assert_eq!(
+            TestFilter::parse("Add.roc:add"),
+            TestFilter::Pattern("Add.roc:add".to_string())
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Machine-readable reports of `roc test` results, e.g. for CI systems to consume.
+use roc_collections::MutMap;
+use roc_region::all::{LineInfo, Region};
+use roc_repl_expect::run::ExpectResult;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestReporter {
+    Junit,
+    Json,
+}
+
+impl TestReporter {
+    pub fn from_flag(flag: &str) -> Self {
+        match flag {
+            "junit" => TestReporter::Junit,
+            "json" => TestReporter::Json,
+            other => roc_error_macros::internal_error!("unknown test reporter {other:?}"),
+        }
+    }
+
+    pub fn render(self, report: &TestReport) -> String {
+        match self {
+            TestReporter::Junit => report.to_junit_xml(),
+            TestReporter::Json => report.to_json(),
+        }
+    }
+}
+
+/// The results of every `expect` that ran during one `roc test` invocation,
+/// combined across all the files it was given.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TestReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration_secs: f64,
+    pub modules: Vec<ModuleReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleReport {
+    pub module: String,
+    pub path: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration_secs: f64,
+    pub tests: Vec<TestCaseReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestCaseReport {
+    pub name: String,
+    pub region: SourceRegion,
+    pub status: TestStatus,
+    pub duration_secs: f64,
+    /// The rendered report explaining why the expectation failed.
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    /// Not selected by `--filter`
+    Skipped,
+}
+
+/// A region of a source file, with 1-based lines and columns.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SourceRegion {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl SourceRegion {
+    fn new(line_info: &LineInfo, region: Region) -> Self {
+        let lc_region = line_info.convert_region(region);
+
+        Self {
+            start_line: lc_region.start.line + 1,
+            start_column: lc_region.start.column + 1,
+            end_line: lc_region.end.line + 1,
+            end_column: lc_region.end.column + 1,
+        }
+    }
+}
+
+impl TestCaseReport {
+    /// Expects without a name of their own are named after where they are, which
+    /// changes whenever code above them moves.
+    fn new(
+        line_info: &LineInfo,
+        region: Region,
+        name: Option<&String>,
+        status: TestStatus,
+    ) -> Self {
+        let region = SourceRegion::new(line_info, region);
+        let name = match name {
+            Some(name) => name.clone(),
+            None => format!(
+                "expect at line {}, column {}",
+                region.start_line, region.start_column
+            ),
+        };
+
+        Self {
+            name,
+            region,
+            status,
+            duration_secs: 0.0,
+            failure: None,
+        }
+    }
+
+    fn with_duration(self, duration: Duration) -> Self {
+        Self {
+            duration_secs: duration.as_secs_f64(),
+            ..self
+        }
+    }
+
+    fn with_failure(self, failure: Option<String>) -> Self {
+        Self { failure, ..self }
+    }
+}
+
+impl TestReport {
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_module(
+        &mut self,
+        module: String,
+        path: &Path,
+        source: &str,
+        tests_duration: Duration,
+        results: &[ExpectResult],
+        skipped: &[Region],
+        test_names: &MutMap<Region, String>,
+    ) {
+        let line_info = LineInfo::new(source);
+
+        let mut tests: Vec<_> = results
+            .iter()
+            .map(|result| {
+                let status = if result.passed() {
+                    TestStatus::Passed
+                } else {
+                    TestStatus::Failed
+                };
+
+                let name = test_names.get(&result.region);
+
+                TestCaseReport::new(&line_info, result.region, name, status)
+                    .with_duration(result.duration)
+                    .with_failure(result.failure_report.clone())
+            })
+            .chain(skipped.iter().map(|region| {
+                let name = test_names.get(region);
+
+                TestCaseReport::new(&line_info, *region, name, TestStatus::Skipped)
+            }))
+            .collect();
+
+        tests.sort_by_key(|test| (test.region.start_line, test.region.start_column));
+
+        // Several expects can be written under the same def, so number all but the first.
+        let mut seen: MutMap<String, usize> = MutMap::default();
+        for test in tests.iter_mut() {
+            let count = seen.entry(test.name.clone()).or_insert(0);
+            *count += 1;
+
+            if *count > 1 {
+                test.name = format!("{} ({count})", test.name);
+            }
+        }
+
+        let count = |status| tests.iter().filter(|test| test.status == status).count();
+        let passed = count(TestStatus::Passed);
+        let failed = count(TestStatus::Failed);
+        let skipped = count(TestStatus::Skipped);
+
+        self.passed += passed;
+        self.failed += failed;
+        self.skipped += skipped;
+        self.duration_secs += tests_duration.as_secs_f64();
+
+        self.modules.push(ModuleReport {
+            module,
+            path: path.display().to_string(),
+            passed,
+            failed,
+            skipped,
+            duration_secs: tests_duration.as_secs_f64(),
+            tests,
+        });
+    }
+
+    /// Adds the results of another run, e.g. one from a `roc test` worker process.
+    pub fn merge(&mut self, other: TestReport) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.duration_secs += other.duration_secs;
+        self.modules.extend(other.modules);
+    }
+
+    /// Records that testing a file crashed before it could report the results of its expects.
+    pub fn add_crashed_file(&mut self, path: &Path, message: String) {
+        let file_name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+
+        let test = TestCaseReport {
+            name: format!("expects in {file_name}"),
+            region: SourceRegion {
+                start_line: 1,
+                start_column: 1,
+                end_line: 1,
+                end_column: 1,
+            },
+            status: TestStatus::Failed,
+            duration_secs: 0.0,
+            failure: Some(message),
+        };
+
+        self.failed += 1;
+        self.modules.push(ModuleReport {
+            module: file_name.into_owned(),
+            path: path.display().to_string(),
+            passed: 0,
+            failed: 1,
+            skipped: 0,
+            duration_secs: 0.0,
+            tests: vec![test],
+        });
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a test report is always valid JSON")
+    }
+
+    pub fn to_junit_xml(&self) -> String {
+        let mut buf = String::new();
+
+        // Writing to a String cannot fail, so the results of write! are ignored throughout.
+        let _ = writeln!(buf, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            buf,
+            r#"<testsuites name="roc test" tests="{}" failures="{}" skipped="{}" time="{:.6}">"#,
+            self.passed + self.failed + self.skipped,
+            self.failed,
+            self.skipped,
+            self.duration_secs,
+        );
+
+        for module in self.modules.iter() {
+            let module_name = xml_escape(&module.module);
+            let path = xml_escape(&module.path);
+
+            let _ = writeln!(
+                buf,
+                r#"  <testsuite name="{module_name}" file="{path}" tests="{}" failures="{}" skipped="{}" time="{:.6}">"#,
+                module.passed + module.failed + module.skipped,
+                module.failed,
+                module.skipped,
+                module.duration_secs,
+            );
+
+            for test in module.tests.iter() {
+                let _ = write!(
+                    buf,
+                    r#"    <testcase name="{}" classname="{module_name}" file="{path}" line="{}" time="{:.6}""#,
+                    xml_escape(&test.name),
+                    test.region.start_line,
+                    test.duration_secs,
+                );
+
+                match &test.failure {
+                    None if test.status == TestStatus::Skipped => {
+                        let _ = writeln!(buf, ">");
+                        let _ = writeln!(buf, "      <skipped />");
+                        let _ = writeln!(buf, "    </testcase>");
+                    }
+                    None => {
+                        let _ = writeln!(buf, " />");
+                    }
+                    Some(failure) => {
+                        let _ = writeln!(buf, ">");
+                        let _ = writeln!(
+                            buf,
+                            r#"      <failure message="This expectation failed">{}</failure>"#,
+                            xml_escape(failure)
+                        );
+                        let _ = writeln!(buf, "    </testcase>");
+                    }
+                }
+            }
+
+            let _ = writeln!(buf, "  </testsuite>");
+        }
+
+        let _ = writeln!(buf, "</testsuites>");
+
+        buf
+    }
+}
+
+/// Escapes text for use in XML attribute values and text nodes. Control characters
+/// other than whitespace are not allowed in XML 1.0 documents, so they are dropped.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {}
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roc_module::symbol::Symbol;
+    use roc_region::all::Position;
+
+    fn expect_result(start: u32, end: u32, failure_report: Option<&str>) -> ExpectResult {
+        ExpectResult {
+            symbol: Symbol::LIST_MAP,
+            region: Region::new(Position::new(start), Position::new(end)),
+            duration: Duration::from_millis(2),
+            failure_report: failure_report.map(String::from),
+        }
+    }
+
+    const SOURCE: &str = "module []\n\nexpect 1 == 1\n\nexpect 1 < 0\n";
+
+    fn example_report() -> TestReport {
+        let mut report = TestReport::default();
+
+        report.add_module(
+            "Example".to_string(),
+            Path::new("Example.roc"),
+            SOURCE,
+            Duration::from_millis(4),
+            &[
+                expect_result(11, 24, None),
+                expect_result(26, 38, Some("This expectation failed:\n\n5│  expect 1 < 0")),
+            ],
+            &[],
+            &MutMap::default(),
+        );
+
+        report
+    }
+
+    #[test]
+    fn counts_and_regions() {
+        let report = example_report();
+
+        assert_eq!((report.passed, report.failed), (1, 1));
+
+        let tests = &report.modules[0].tests;
+        assert_eq!(tests[0].status, TestStatus::Passed);
+        assert_eq!(tests[1].status, TestStatus::Failed);
+        assert_eq!(
+            (tests[1].region.start_line, tests[1].region.start_column),
+            (5, 1)
+        );
+        assert_eq!(tests[1].name, "expect at line 5, column 1");
+    }
+
+    #[test]
+    fn named_expects() {
+        let source = "module []\n\nadd = 1\n\nexpect add == 1\n\nexpect add > 0\n";
+        let region = |start, end| Region::new(Position::new(start), Position::new(end));
+        let test_names = MutMap::from_iter([
+            (region(18, 35), "add".to_string()),
+            (region(35, 51), "add".to_string()),
+        ]);
+
+        let mut report = TestReport::default();
+        report.add_module(
+            "Example".to_string(),
+            Path::new("Example.roc"),
+            source,
+            Duration::from_millis(4),
+            &[expect_result(18, 35, None)],
+            &[region(35, 51)],
+            &test_names,
+        );
+
+        let names: Vec<_> = report.modules[0]
+            .tests
+            .iter()
+            .map(|test| test.name.as_str())
+            .collect();
+        assert_eq!(names, ["add", "add (2)"]);
+    }
+
+    #[test]
+    fn junit_escapes_failure_reports() {
+        let xml = example_report().to_junit_xml();
+
+        assert!(xml.contains(r#"<testsuites name="roc test" tests="2" failures="1" skipped="0""#));
+        assert!(xml.contains(
+            r#"<testsuite name="Example" file="Example.roc" tests="2" failures="1" skipped="0""#
+        ));
+        assert!(xml.contains("5│  expect 1 &lt; 0</failure>"));
+    }
+
+    #[test]
+    fn json_round_trip_and_merge() {
+        let mut report = example_report();
+        let from_worker: TestReport = serde_json::from_str(&example_report().to_json()).unwrap();
+
+        report.merge(from_worker);
+        report.add_crashed_file(Path::new("src/Crash.roc"), "crashed".to_string());
+
+        assert_eq!((report.passed, report.failed, report.skipped), (2, 3, 0));
+        assert_eq!(report.modules.len(), 3);
+        assert_eq!(report.modules[2].module, "Crash.roc");
+    }
+
+    #[test]
+    fn xml_escape_drops_control_characters() {
+        assert_eq!(xml_escape("a\u{1b}[31m<b>\n"), "a[31m&lt;b&gt;\n");
+    }
+}
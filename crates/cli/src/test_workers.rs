@@ -0,0 +1,100 @@
+//! Running `roc test` on many files at once, each in its own worker process.
+//!
+//! A worker is the `roc` binary itself, testing a single file and writing a [`TestReport`]
+//! for the parent process to combine. Because every file is tested in a separate process,
+//! an expect that crashes only loses the results of its own file.
+use crate::test_report::TestReport;
+use crate::FLAG_TEST_WORKER_REPORT;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The exit code of a process that panicked, e.g. through `internal_error!`
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// Everything a worker process left behind after testing one file.
+pub struct WorkerOutcome {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub status: ExitStatus,
+    /// `None` if the worker exited before it finished running the file's expects.
+    pub report: Option<TestReport>,
+}
+
+impl WorkerOutcome {
+    /// Whether the worker died, as opposed to stopping early because the file had errors.
+    pub fn crashed(&self) -> bool {
+        self.report.is_none() && matches!(self.status.code(), None | Some(PANIC_EXIT_CODE))
+    }
+}
+
+/// Tests each of the `paths` in a worker process, with at most `max_workers` running at once.
+/// The outcomes are in the same order as `paths`, no matter which worker finished first.
+pub fn run_test_workers(
+    paths: &[PathBuf],
+    worker_args: &[OsString],
+    max_workers: usize,
+) -> io::Result<Vec<WorkerOutcome>> {
+    let roc_exe = &std::env::current_exe()?;
+    let reports_dir = &tempfile::tempdir()?;
+    let next_index = &AtomicUsize::new(0);
+
+    let mut outcomes: Vec<(usize, io::Result<WorkerOutcome>)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..Ord::min(max_workers, paths.len()))
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut outcomes = Vec::new();
+
+                    loop {
+                        let index = next_index.fetch_add(1, Ordering::Relaxed);
+
+                        let Some(path) = paths.get(index) else {
+                            break outcomes;
+                        };
+
+                        let report_path = reports_dir.path().join(format!("{index}.json"));
+                        let outcome = run_test_worker(roc_exe, worker_args, path, &report_path);
+
+                        outcomes.push((index, outcome));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+fn run_test_worker(
+    roc_exe: &Path,
+    worker_args: &[OsString],
+    path: &Path,
+    report_path: &Path,
+) -> io::Result<WorkerOutcome> {
+    let output = Command::new(roc_exe)
+        .args(worker_args)
+        .arg(format!("--{FLAG_TEST_WORKER_REPORT}"))
+        .arg(report_path)
+        .arg(path)
+        .output()?;
+
+    let report = std::fs::read_to_string(report_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+
+    Ok(WorkerOutcome {
+        stdout: output.stdout,
+        stderr: output.stderr,
+        status: output.status,
+        report,
+    })
+}
@@ -0,0 +1,197 @@
+//! Running a command again every time one of the modules it loaded changes, for `--watch`.
+//!
+//! Every run shares one [`IncrementalCache`], so after an edit only the changed modules
+//! (and the modules that import them) are solved again.
+use bumpalo::Bump;
+use clap::ArgMatches;
+use roc_error_macros::user_error;
+use roc_load::{ExecutionMode, FunctionKind, IncrementalCache, LoadConfig, Threading};
+use roc_packaging::cache::{self, RocCacheDir};
+use roc_reporting::report::{RenderTarget, DEFAULT_PALETTE};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, SystemTime};
+use target_lexicon::Triple;
+
+use crate::{FLAG_MAX_THREADS, FLAG_WATCH, ROC_FILE};
+
+/// How often to check whether any of the watched files changed
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Editors often save a file in several steps, so give them a moment to finish before re-running.
+const SETTLE_TIME: Duration = Duration::from_millis(50);
+
+/// Calls `run` over and over, every time a module read by an earlier run changes,
+/// until the process is stopped.
+///
+/// `run` may start a child process that keeps running after it returns (e.g. the app that
+/// `roc dev` runs), in which case that process is killed before the next run.
+pub fn watch(
+    root_path: &Path,
+    incremental: &IncrementalCache,
+    mut run: impl FnMut() -> io::Result<Option<Child>>,
+) -> io::Result<i32> {
+    loop {
+        // Look at the files before running, so that changes made during the run aren't missed.
+        let mut files = WatchedFiles::new(root_path, incremental);
+
+        let mut opt_child = run()?;
+
+        files.add_new(incremental);
+
+        match opt_child {
+            Some(_) => println!("\nWatching {} for changes…\n", files.describe()),
+            None => println!("Watching {} for changes…\n", files.describe()),
+        }
+
+        let changed = loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            if let Some(child) = &mut opt_child {
+                if let Some(status) = child.try_wait()? {
+                    println!("\nThe program exited with {status}. Watching for changes…\n");
+
+                    opt_child = None;
+                }
+            }
+
+            if let Some(changed) = files.changed() {
+                break changed.to_path_buf();
+            }
+        };
+
+        std::thread::sleep(SETTLE_TIME);
+
+        if let Some(mut child) = opt_child {
+            // The child may have exited on its own since we last checked, which is fine.
+            let _ = child.kill();
+            child.wait()?;
+        }
+
+        println!("\n{} changed, running again…\n", changed.display());
+    }
+}
+
+/// `roc dev --watch`: runs `roc dev` in a child process, and restarts it whenever one of
+/// the app's modules changes.
+///
+/// The app has to run in its own process so that it can be stopped, so each run of `roc dev`
+/// starts from scratch. To find out which modules to watch, this process type-checks the app
+/// too, which is quick for all but the modules that changed.
+pub fn watch_dev(matches: &ArgMatches) -> io::Result<i32> {
+    let roc_file_path = matches.get_one::<PathBuf>(ROC_FILE).unwrap();
+    let threading = match matches.get_one::<usize>(FLAG_MAX_THREADS) {
+        None => Threading::AllAvailable,
+        Some(0) => user_error!("cannot build with at most 0 threads"),
+        Some(1) => Threading::Single,
+        Some(n) => Threading::AtMost(*n),
+    };
+
+    let roc_exe = std::env::current_exe()?;
+    let dev_args = args_without_watch();
+    let incremental = IncrementalCache::persistent();
+
+    watch(roc_file_path, &incremental, || {
+        let arena = Bump::new();
+        let load_config = LoadConfig {
+            target: Triple::host().into(),
+            function_kind: FunctionKind::from_env(),
+            render: RenderTarget::ColorTerminal,
+            palette: DEFAULT_PALETTE,
+            threading,
+            exec_mode: ExecutionMode::Check,
+            incremental: Some(incremental.clone()),
+        };
+
+        // Any problems are reported by `roc dev` itself; this is only to find the modules.
+        let _ = roc_load::load_and_typecheck(
+            &arena,
+            roc_file_path.to_owned(),
+            None,
+            RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
+            load_config,
+        );
+
+        Command::new(&roc_exe).args(&dev_args).spawn().map(Some)
+    })
+}
+
+/// The arguments this process was started with, minus `--watch`, for starting a child process
+/// that runs the same command once.
+fn args_without_watch() -> Vec<OsString> {
+    let watch_flag = format!("--{FLAG_WATCH}");
+    let mut args = Vec::new();
+    let mut in_app_args = false;
+
+    for arg in std::env::args_os().skip(1) {
+        // everything after `--` is passed on to the app, so it is left alone
+        if in_app_args || arg != watch_flag.as_str() {
+            in_app_args |= arg == "--";
+
+            args.push(arg);
+        }
+    }
+
+    args
+}
+
+struct WatchedFiles {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl WatchedFiles {
+    fn new(root_path: &Path, incremental: &IncrementalCache) -> Self {
+        let mut watched = Self {
+            files: vec![(root_path.to_path_buf(), modified_time(root_path))],
+        };
+
+        watched.add_new(incremental);
+
+        watched
+    }
+
+    /// Starts watching the modules that were read for the first time by the latest run.
+    fn add_new(&mut self, incremental: &IncrementalCache) {
+        for path in incremental.module_paths() {
+            if !self
+                .files
+                .iter()
+                .any(|(watched, _)| same_file(watched, &path))
+            {
+                let modified = modified_time(&path);
+
+                self.files.push((path, modified));
+            }
+        }
+    }
+
+    fn changed(&self) -> Option<&Path> {
+        self.files
+            .iter()
+            .find(|(path, modified)| modified_time(path) != *modified)
+            .map(|(path, _)| path.as_path())
+    }
+
+    fn describe(&self) -> String {
+        match self.files.len() {
+            1 => "1 file".to_string(),
+            count => format!("{count} files"),
+        }
+    }
+}
+
+/// When the file was last modified, or `None` if it doesn't exist (anymore)
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -38,6 +38,7 @@ mod cli_tests {
     const SUPPRESS_BUILD_HOST_WARNING_FLAG: &str =
         concatcp!("--", roc_cli::FLAG_SUPPRESS_BUILD_HOST_WARNING);
     const CHECK_FLAG: &str = concatcp!("--", roc_cli::FLAG_CHECK);
+    const DEV_FLAG: &str = concatcp!("--", roc_cli::FLAG_DEV);
     #[allow(dead_code)]
     const TARGET_FLAG: &str = concatcp!("--", roc_cli::FLAG_TARGET);
 
@@ -276,6 +277,90 @@ mod cli_tests {
         insta::assert_snapshot!(cli_test_out.normalize_stdout_and_stderr());
     }
 
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn transitive_expects_junit_reporter() {
+        let cli_test = ExecCli::new(
+            CMD_TEST,
+            file_from_root(
+                "crates/cli/tests/test-projects/expects_transitive",
+                "main.roc",
+            ),
+        )
+        .arg("--reporter=junit");
+
+        let cli_test_out = cli_test.run();
+        cli_test_out.assert_clean_success();
+
+        let stdout = &cli_test_out.stdout;
+        assert!(stdout.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(stdout.contains(r#"<testsuites name="roc test" tests="3" failures="0""#));
+        assert_eq!(stdout.matches("<testcase ").count(), 3);
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn transitive_expects_filter() {
+        let cli_test = ExecCli::new(
+            CMD_TEST,
+            file_from_root(
+                "crates/cli/tests/test-projects/expects_transitive",
+                "main.roc",
+            ),
+        )
+        .arg("--filter=Transitive");
+
+        let cli_test_out = cli_test.run();
+        cli_test_out.assert_clean_success();
+        cli_test_out.assert_stdout_and_stderr_ends_with(
+            "0 failed, 1 passed and 2 skipped in <ignored for test> ms.\n",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn transitive_expects_filter_skips_all() {
+        let cli_test = ExecCli::new(
+            CMD_TEST,
+            file_from_root(
+                "crates/cli/tests/test-projects/expects_transitive",
+                "main.roc",
+            ),
+        )
+        .arg("--filter=NoSuchExpect");
+
+        let cli_test_out = cli_test.run();
+        cli_test_out.assert_nonzero_exit();
+        cli_test_out.assert_stdout_and_stderr_ends_with(
+            "0 failed, 0 passed and 3 skipped in <ignored for test> ms.\nNo expectations matched --filter, 3 were skipped.\n",
+        );
+    }
+
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn transitive_expects_coverage() {
+        let dir = tempfile::tempdir().unwrap();
+        let lcov_path = dir.path().join("lcov.info");
+
+        let cli_test = ExecCli::new(
+            CMD_TEST,
+            file_from_root(
+                "crates/cli/tests/test-projects/expects_transitive",
+                "main.roc",
+            ),
+        )
+        .arg(format!("--coverage={}", lcov_path.display()));
+
+        let cli_test_out = cli_test.run();
+        cli_test_out.assert_clean_success();
+        assert!(cli_test_out.stdout.contains("Coverage (written to"));
+
+        let lcov = std::fs::read_to_string(&lcov_path).unwrap();
+        assert_eq!(lcov.matches("end_of_record").count(), 2);
+        assert!(lcov.contains("FNDA:2,addAndStringify\n"));
+        assert!(lcov.contains("FNDA:3,add\n"));
+    }
+
     #[test]
     #[cfg_attr(windows, ignore)]
     fn multiple_exposed() {
@@ -791,6 +876,27 @@ mod cli_tests {
             insta::assert_snapshot!(cli_dev_out.normalize_stdout_and_stderr());
         }
 
+        #[test]
+        #[cfg_attr(
+            windows,
+            ignore = "running `expect`s does not currently work on windows"
+        )]
+        fn expects_dev_failure_dev_backend() {
+            build_platform_host();
+
+            // `--dev` uses the dev backend, which reports failed expects to the parent `roc`
+            // through the shared memory, and calls the platform's `roc_dbg`
+            let cli_dev = ExecCli::new(
+                CMD_DEV,
+                file_from_root("crates/cli/tests/test-projects/expects", "expects.roc"),
+            )
+            .arg(DEV_FLAG);
+
+            let cli_dev_out = cli_dev.run();
+
+            insta::assert_snapshot!(cli_dev_out.normalize_stdout_and_stderr());
+        }
+
         #[test]
         #[cfg_attr(windows, ignore = "running tests does not work on windows right now")]
         fn expects_test_failure() {
@@ -126,6 +126,11 @@ pub fn build_zig_host_native(
             shared_lib_path.to_str().unwrap(),
             builtins_host_path.to_str().unwrap(),
         ]);
+
+        if target.contains("macos") {
+            // the surgical linker adds load commands for the app in the header padding
+            zig_cmd.arg("-headerpad_max_install_names");
+        }
     } else {
         zig_cmd.args(["build-obj", "-fPIC"]);
     }
@@ -301,6 +306,14 @@ pub fn build_c_host_native(
                     builtins_host_path,
                 );
             }
+            OperatingSystem::Mac => {
+                clang_cmd.args([
+                    shared_lib_path.to_str().unwrap(),
+                    "-fPIE",
+                    // the surgical linker adds load commands for the app in the header padding
+                    "-Wl,-headerpad_max_install_names",
+                ]);
+            }
             _ => {
                 clang_cmd.args([
                     shared_lib_path.to_str().unwrap(),
@@ -7,8 +7,8 @@ use roc_gen_dev::AssemblyBackendMode;
 use roc_gen_llvm::llvm::build::{module_from_builtins, LlvmBackendMode};
 use roc_gen_llvm::llvm::externs::add_default_roc_externs;
 use roc_load::{
-    EntryPoint, ExecutionMode, ExpectMetadata, FunctionKind, LoadConfig, LoadMonomorphizedError,
-    LoadedModule, LoadingProblem, MonomorphizedModule, Threading,
+    EntryPoint, ExecutionMode, ExpectMetadata, FunctionKind, IncrementalCache, LoadConfig,
+    LoadMonomorphizedError, LoadedModule, LoadingProblem, MonomorphizedModule, Threading,
 };
 use roc_mono::ir::{OptLevel, SingleEntryPoint};
 use roc_packaging::cache::RocCacheDir;
@@ -83,6 +83,9 @@ pub struct CodeGenOptions {
     pub backend: CodeGenBackend,
     pub opt_level: OptLevel,
     pub emit_debug_info: bool,
+    /// Emit DWARF line tables and variable locations that map the generated code back to
+    /// the `.roc` source, for `--debug`
+    pub emit_source_locations: bool,
     pub emit_llvm_ir: bool,
     pub fuzz: bool,
 }
@@ -101,6 +104,7 @@ pub fn gen_from_mono_module<'a>(
 ) -> GenFromMono<'a> {
     let path = roc_file_path;
     let debug = code_gen_options.emit_debug_info;
+    let source_locations = code_gen_options.emit_source_locations;
     let emit_llvm_ir = code_gen_options.emit_llvm_ir;
     let fuzz = code_gen_options.fuzz;
     let opt = code_gen_options.opt_level;
@@ -120,6 +124,7 @@ pub fn gen_from_mono_module<'a>(
                 built_host_opt,
                 wasm_dev_stack_bytes,
                 AssemblyBackendMode::Binary, // dummy value, unused in practice
+                false,
             )
         }
         CodeGenBackend::Assembly(backend_mode) => gen_from_mono_module_dev(
@@ -129,6 +134,7 @@ pub fn gen_from_mono_module<'a>(
             built_host_opt,
             wasm_dev_stack_bytes,
             backend_mode,
+            source_locations,
         ),
         CodeGenBackend::Llvm(backend_mode) => gen_from_mono_module_llvm(
             arena,
@@ -138,6 +144,7 @@ pub fn gen_from_mono_module<'a>(
             opt,
             backend_mode,
             debug,
+            source_locations,
             emit_llvm_ir,
             fuzz,
         ),
@@ -156,6 +163,7 @@ fn gen_from_mono_module_llvm<'a>(
     opt_level: OptLevel,
     backend_mode: LlvmBackendMode,
     emit_debug_info: bool,
+    emit_source_locations: bool,
     emit_llvm_ir: bool,
     fuzz: bool,
 ) -> GenFromMono<'a> {
@@ -204,6 +212,8 @@ fn gen_from_mono_module_llvm<'a>(
 
     let builder = context.create_builder();
     let (dibuilder, compile_unit) = roc_gen_llvm::llvm::build::Env::new_debug_info(module);
+    let debug_sources = emit_source_locations
+        .then(|| roc_gen_llvm::llvm::debug_info::DebugSources::new(&dibuilder, &loaded.sources));
 
     // Compile and add all the Procs before adding main
     let env = roc_gen_llvm::llvm::build::Env {
@@ -216,6 +226,7 @@ fn gen_from_mono_module_llvm<'a>(
         module,
         target,
         mode: backend_mode,
+        debug_sources: debug_sources.as_ref(),
 
         exposed_to_host: loaded
             .exposed_to_host
@@ -419,6 +430,7 @@ fn gen_from_mono_module_dev<'a>(
     built_host_opt: &BuiltHostOpt,
     wasm_dev_stack_bytes: Option<u32>,
     #[allow(unused_variables)] backend_mode: AssemblyBackendMode,
+    #[allow(unused_variables)] emit_source_locations: bool,
 ) -> GenFromMono<'a> {
     match (built_host_opt, target.architecture()) {
         (BuiltHostOpt::Additive(host_path), Architecture::Wasm32) => {
@@ -451,7 +463,13 @@ fn gen_from_mono_module_dev<'a>(
         (_, Architecture::X86_64 | Architecture::Aarch64) => {
             #[cfg(not(feature = "target-wasm32"))]
             {
-                gen_from_mono_module_dev_assembly(arena, loaded, target, backend_mode)
+                gen_from_mono_module_dev_assembly(
+                    arena,
+                    loaded,
+                    target,
+                    backend_mode,
+                    emit_source_locations,
+                )
             }
 
             #[cfg(feature = "target-wasm32")]
@@ -548,6 +566,7 @@ fn gen_from_mono_module_dev_assembly<'a>(
     loaded: MonomorphizedModule<'a>,
     target: Target,
     backend_mode: AssemblyBackendMode,
+    emit_source_locations: bool,
 ) -> GenFromMono<'a> {
     let all_code_gen_start = Instant::now();
 
@@ -559,15 +578,19 @@ fn gen_from_mono_module_dev_assembly<'a>(
         mut interns,
         exposed_to_host,
         mut layout_interner,
+        sources,
         ..
     } = loaded;
 
+    let debug_sources = emit_source_locations.then(|| &*arena.alloc(sources));
+
     let env = roc_gen_dev::Env {
         arena,
         module_id,
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: backend_mode,
+        debug_sources,
     };
 
     let module_object =
@@ -707,6 +730,7 @@ pub fn standard_load_config(
         palette: DEFAULT_PALETTE,
         threading,
         exec_mode,
+        incremental: None,
     }
 }
 
@@ -837,6 +861,26 @@ fn build_loaded_file<'a>(
         _ => unreachable!(),
     };
 
+    // Surgical linking is the default for executables on most targets, but some platforms only
+    // ship a legacy host, e.g. for macOS. Link those with the system linker instead of failing.
+    let linking_strategy = match linking_strategy {
+        LinkingStrategy::Surgical
+            if link_type == LinkType::Executable
+                && !build_host_requested
+                && target.find_surgical_host(&platform_main_roc_path).is_err()
+                && target.find_legacy_host(&platform_main_roc_path).is_ok() =>
+        {
+            if verbose {
+                println!(
+                    "No surgical host was found for target {target}, so I'm using the legacy linker."
+                );
+            }
+
+            LinkingStrategy::Legacy
+        }
+        other => other,
+    };
+
     let output_exe_path = get_exe_path(
         out_path,
         app_module_path.as_path(),
@@ -1350,6 +1394,7 @@ pub fn check_file<'a>(
     emit_timings: bool,
     roc_cache_dir: RocCacheDir<'_>,
     threading: Threading,
+    incremental: Option<IncrementalCache>,
 ) -> Result<(Problems, Duration), LoadingProblem<'a>> {
     let compilation_start = Instant::now();
 
@@ -1367,6 +1412,7 @@ pub fn check_file<'a>(
         palette: DEFAULT_PALETTE,
         threading,
         exec_mode: ExecutionMode::Check,
+        incremental,
     };
     let mut loaded = roc_load::load_and_typecheck(
         arena,
@@ -1433,6 +1479,7 @@ pub fn build_str_test<'a>(
         backend: CodeGenBackend::Llvm(LlvmBackendMode::Binary),
         opt_level: OptLevel::Normal,
         emit_debug_info: false,
+        emit_source_locations: false,
         emit_llvm_ir: false,
         fuzz: false,
     };
@@ -971,8 +971,74 @@ pub fn listAllocationPtr(
     return list.getAllocationDataPtr();
 }
 
+/// Copies `list`, whose elements contain no pointers, to `offset` in `ptr`, with its elements
+/// at `extra_offset`. The copy points to its elements with an offset into `ptr` instead of an
+/// address, the way `roc test` expects the values of a failed `expect`. Returns the offset
+/// after the elements.
+pub fn listCloneTo(
+    list: RocList,
+    element_width: usize,
+    ptr: [*]u8,
+    offset: usize,
+    extra_offset: usize,
+) callconv(.C) usize {
+    const WIDTH: usize = @sizeOf(RocList);
+    const num_bytes = list.len() * element_width;
+
+    // we only copy the elements we have, so the capacity is the length
+    const relative = [3]usize{ extra_offset, list.len(), list.len() };
+    @memcpy(ptr[offset..(offset + WIDTH)], std.mem.asBytes(&relative));
+
+    if (list.bytes) |source| {
+        @memcpy(ptr[extra_offset..(extra_offset + num_bytes)], source[0..num_bytes]);
+    }
+
+    return extra_offset + num_bytes;
+}
+
+/// Like `listCloneTo`, but for a list of strings, whose bytes are copied after the elements.
+pub fn listStrCloneTo(
+    list: RocList,
+    ptr: [*]u8,
+    offset: usize,
+    extra_offset: usize,
+) callconv(.C) usize {
+    const WIDTH: usize = @sizeOf(RocList);
+    const ELEMENT_WIDTH: usize = @sizeOf(str.RocStr);
+
+    const relative = [3]usize{ extra_offset, list.len(), list.len() };
+    @memcpy(ptr[offset..(offset + WIDTH)], std.mem.asBytes(&relative));
+
+    var rest_offset = extra_offset + list.len() * ELEMENT_WIDTH;
+
+    if (list.bytes) |source| {
+        const strings = @as([*]align(1) const str.RocStr, @ptrCast(source));
+
+        var i: usize = 0;
+        while (i < list.len()) : (i += 1) {
+            rest_offset = str.strCloneTo(strings[i], ptr, extra_offset + i * ELEMENT_WIDTH, rest_offset);
+        }
+    }
+
+    return rest_offset;
+}
+
 fn rcNone(_: ?[*]u8) callconv(.C) void {}
 
+test "listCloneTo" {
+    var list = RocList.fromSlice(u16, ([_]u16{ 1, 2, 3 })[0..], false);
+    defer list.decref(@alignOf(u16), @sizeOf(u16), false, rcNone);
+
+    var buffer = [_]u8{0} ** 64;
+    const next = listCloneTo(list, @sizeOf(u16), &buffer, 8, 32);
+
+    try expectEqual(next, 32 + 3 * @sizeOf(u16));
+
+    const header = @as(*align(1) const [3]usize, @ptrCast(buffer[8..32]));
+    try expectEqual(header.*, [3]usize{ 32, 3, 3 });
+    try expectEqual(std.mem.bytesAsSlice(u16, buffer[32..38])[2], 3);
+}
+
 test "listConcat: non-unique with unique overlapping" {
     var nonUnique = RocList.fromSlice(u8, ([_]u8{1})[0..], false);
     const bytes: [*]u8 = @as([*]u8, @ptrCast(nonUnique.bytes));
@@ -84,6 +84,8 @@ comptime {
     exportListFn(list.listConcatUtf8, "concat_utf8");
     exportListFn(list.listIncref, "incref");
     exportListFn(list.listDecref, "decref");
+    exportListFn(list.listCloneTo, "clone_to");
+    exportListFn(list.listStrCloneTo, "str_clone_to");
 }
 
 // Num Module
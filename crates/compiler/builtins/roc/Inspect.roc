@@ -33,6 +33,7 @@ module [
     apply,
     toInspector,
     toStr,
+    snapshot,
 ]
 
 import Bool exposing [Bool]
@@ -100,6 +101,19 @@ toStr = \val ->
     |> inspect
     |> toDbgStr
 
+## Checks that a value still renders (with [toStr]) the same way as the snapshot with the given
+## name, which `roc test` stores in a file next to the module. This makes expectations about
+## large values readable, because a failure shows which lines of the value changed.
+## ```roc
+## expect Inspect.snapshot "default config" (Config.parse defaultConfigText)
+## ```
+## Run `roc test --update-snapshots` to store new and changed snapshots. Outside of `roc test`,
+## this is always `Bool.true`.
+snapshot : Str, val -> Bool where val implements Inspect
+snapshot = \name, val -> snapshotStr name (toStr val)
+
+snapshotStr : Str, Str -> Bool
+
 # The current default formatter for inspect.
 # This just returns a simple string for debugging.
 # More powerful formatters will likely be wanted in the future.
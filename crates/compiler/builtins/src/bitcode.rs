@@ -382,6 +382,8 @@ pub const LIST_RELEASE_EXCESS_CAPACITY: &str = "roc_builtins.list.release_excess
 pub const LIST_CONCAT_UTF8: &str = "roc_builtins.list.concat_utf8";
 pub const LIST_INCREF: &str = "roc_builtins.list.incref";
 pub const LIST_DECREF: &str = "roc_builtins.list.decref";
+pub const LIST_CLONE_TO: &str = "roc_builtins.list.clone_to";
+pub const LIST_STR_CLONE_TO: &str = "roc_builtins.list.str_clone_to";
 
 pub const DEC_ABS: &str = "roc_builtins.dec.abs";
 pub const DEC_ACOS: &str = "roc_builtins.dec.acos";
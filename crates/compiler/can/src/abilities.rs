@@ -120,6 +120,15 @@ pub struct ImplKey {
     pub ability_member: Symbol,
 }
 
+impl ImplKey {
+    pub(crate) fn remap_symbols(self, remap: &impl Fn(Symbol) -> Symbol) -> Self {
+        Self {
+            opaque: remap(self.opaque),
+            ability_member: remap(self.ability_member),
+        }
+    }
+}
+
 /// Fully-resolved implementation of an ability member for an opaque type.
 /// This is only fully known after type solving of the owning module.
 #[derive(Clone, Debug)]
@@ -475,6 +484,51 @@ impl IAbilitiesStore<Resolved> {
         self.resolved_specializations.get(&id).copied()
     }
 
+    /// Replaces every symbol in the store with `remap(symbol)`, like
+    /// [`Subs::remap_symbols`](roc_types::subs::Subs::remap_symbols).
+    pub fn remap_symbols(&mut self, remap: &impl Fn(Symbol) -> Symbol) {
+        self.members_of_ability = std::mem::take(&mut self.members_of_ability)
+            .into_iter()
+            .map(|(ability, members)| (remap(ability), members.into_iter().map(remap).collect()))
+            .collect();
+
+        self.specialization_to_root = std::mem::take(&mut self.specialization_to_root)
+            .into_iter()
+            .map(|(symbol, key)| (remap(symbol), key.remap_symbols(remap)))
+            .collect();
+
+        self.ability_members = std::mem::take(&mut self.ability_members)
+            .into_iter()
+            .map(|(member, mut data)| {
+                data.parent_ability = remap(data.parent_ability);
+                (remap(member), data)
+            })
+            .collect();
+
+        self.declared_implementations = std::mem::take(&mut self.declared_implementations)
+            .into_iter()
+            .map(|(key, member_impl)| {
+                let member_impl = match member_impl {
+                    MemberImpl::Impl(symbol) => MemberImpl::Impl(remap(symbol)),
+                    MemberImpl::Error => MemberImpl::Error,
+                };
+                (key.remap_symbols(remap), member_impl)
+            })
+            .collect();
+
+        self.specializations = std::mem::take(&mut self.specializations)
+            .into_iter()
+            .map(|(symbol, mut info)| {
+                info.symbol = remap(info.symbol);
+                (remap(symbol), info)
+            })
+            .collect();
+
+        for symbol in self.resolved_specializations.values_mut() {
+            *symbol = remap(*symbol);
+        }
+    }
+
     pub fn serialize(&self, writer: &mut impl std::io::Write) -> std::io::Result<usize> {
         serialize::serialize(self, writer)
     }
@@ -97,6 +97,10 @@ macro_rules! map_symbol_to_lowlevel_and_arity {
                 LowLevel::SetJmp => unimplemented!(),
                 LowLevel::LongJmp => unimplemented!(),
                 LowLevel::SetLongJmpBuffer => unimplemented!(),
+                LowLevel::CoverageHit => unimplemented!(),
+                LowLevel::ExpectArbitrary => unimplemented!(),
+                LowLevel::ExpectDraw => unimplemented!(),
+                LowLevel::BenchBlackBox => unimplemented!(),
 
                 // these are not implemented, not sure why
                 LowLevel::StrFromInt => unimplemented!(),
@@ -219,6 +223,7 @@ map_symbol_to_lowlevel_and_arity! {
     UnboxExpr; BOX_UNBOX; 1,
     Unreachable; LIST_UNREACHABLE; 1,
     DictPseudoSeed; DICT_PSEUDO_SEED; 1,
+    ExpectSnapshot; INSPECT_SNAPSHOT_STR; 2,
 }
 
 /// Some builtins cannot be constructed in code gen alone, and need to be defined
@@ -0,0 +1,104 @@
+//! The parts of a module that `roc test --coverage` counts runs of.
+use roc_module::symbol::Symbol;
+use roc_region::all::Region;
+use roc_types::subs::Variable;
+
+use crate::expr::{Declarations, Expr};
+use crate::pattern::BindingsFromPattern;
+use crate::traverse::{walk_expr, Visitor};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageKind {
+    /// The body of a top-level def
+    Def(Symbol),
+    WhenBranch,
+    IfArm,
+}
+
+/// A region of a module that runs as a whole, whenever it runs at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoveragePoint {
+    pub region: Region,
+    pub kind: CoverageKind,
+}
+
+struct CoverageCollector {
+    points: Vec<CoveragePoint>,
+}
+
+impl Visitor for CoverageCollector {
+    fn visit_expr(&mut self, expr: &Expr, _region: Region, var: Variable) {
+        match expr {
+            Expr::When { branches, .. } => {
+                self.points
+                    .extend(branches.iter().map(|branch| CoveragePoint {
+                        region: branch.value.region,
+                        kind: CoverageKind::WhenBranch,
+                    }));
+            }
+            Expr::If {
+                branches,
+                final_else,
+                ..
+            } => {
+                let arms = branches.iter().map(|(_, loc_then)| loc_then.region);
+
+                self.points
+                    .extend(arms.chain([final_else.region]).map(|region| CoveragePoint {
+                        region,
+                        kind: CoverageKind::IfArm,
+                    }));
+            }
+            _ => (),
+        }
+
+        walk_expr(self, expr, var)
+    }
+}
+
+impl Declarations {
+    /// The top-level defs of the module, and the `when` branches and `if` arms in them,
+    /// ordered by where they start. Top-level `expect`s are tests, so they are left out.
+    pub fn coverage_points(&self) -> Vec<CoveragePoint> {
+        use crate::expr::DeclarationTag::*;
+
+        let mut collector = CoverageCollector { points: Vec::new() };
+
+        for index in 0..self.len() {
+            match self.declarations[index] {
+                tag @ (Value | Function(_) | Recursive(_) | TailRecursive(_) | Destructure(_)) => {
+                    let loc_expr = &self.expressions[index];
+
+                    // a destructure is named after the first value it defines
+                    let symbol = match tag {
+                        Destructure(d_index) => {
+                            let loc_pattern = &self.destructs[d_index.index()].loc_pattern;
+
+                            BindingsFromPattern::new(loc_pattern)
+                                .min_by_key(|(_, region)| region.start())
+                                .map_or(self.symbols[index].value, |(symbol, _)| symbol)
+                        }
+                        _ => self.symbols[index].value,
+                    };
+
+                    collector.points.push(CoveragePoint {
+                        region: loc_expr.region,
+                        kind: CoverageKind::Def(symbol),
+                    });
+
+                    collector.visit_expr(&loc_expr.value, loc_expr.region, self.variables[index]);
+                }
+                MutualRecursion { .. } => {
+                    // the defs of this group are visited individually by later iterations
+                }
+                Expectation => {}
+            }
+        }
+
+        collector
+            .points
+            .sort_by_key(|point| (point.region.start(), point.region.end()));
+
+        collector.points
+    }
+}
@@ -34,6 +34,7 @@ use roc_module::ident::Ident;
 use roc_module::ident::Lowercase;
 use roc_module::ident::ModuleName;
 use roc_module::ident::QualifiedModuleName;
+use roc_module::low_level::LowLevel;
 use roc_module::symbol::IdentId;
 use roc_module::symbol::ModuleId;
 use roc_module::symbol::Symbol;
@@ -1665,6 +1666,79 @@ impl DefOrdering {
     }
 }
 
+/// A property expect like `expect \x, y -> x + y == y + x` holds for any `x` and `y`.
+/// Its arguments become defs of values that `roc test` generates, so that
+/// the rest of the compiler sees a plain expect like
+///
+/// ```text
+/// expect
+///     x = <arbitrary>
+///     y = <arbitrary>
+///     x + y == y + x
+/// ```
+///
+/// Because they are defs, the arguments are shown when the expect fails.
+fn property_expect_to_plain_expect(scope: &mut Scope, condition: Expr) -> Expr {
+    let Expr::Closure(ClosureData {
+        arguments,
+        loc_body,
+        ..
+    }) = condition
+    else {
+        return condition;
+    };
+
+    let mut loc_body = *loc_body;
+
+    for (arg_var, _, loc_pattern) in arguments.into_iter().rev() {
+        let region = loc_pattern.region;
+
+        let (symbol, destructure) = match loc_pattern.value {
+            Pattern::Identifier(symbol) => (symbol, None),
+            _ => (scope.gen_unique_symbol(), Some(loc_pattern)),
+        };
+
+        if let Some(loc_pattern) = destructure {
+            let def = Def {
+                loc_pattern,
+                loc_expr: Loc::at(region, Var(symbol, arg_var)),
+                expr_var: arg_var,
+                pattern_vars: SendMap::default(),
+                annotation: None,
+                kind: DefKind::Let,
+            };
+
+            loc_body = Loc::at(
+                loc_body.region,
+                LetNonRec(Box::new(def), Box::new(loc_body)),
+            );
+        }
+
+        let def = Def {
+            loc_pattern: Loc::at(region, Pattern::Identifier(symbol)),
+            loc_expr: Loc::at(
+                region,
+                RunLowLevel {
+                    op: LowLevel::ExpectArbitrary,
+                    args: vec![],
+                    ret_var: arg_var,
+                },
+            ),
+            expr_var: arg_var,
+            pattern_vars: std::iter::once((symbol, arg_var)).collect(),
+            annotation: None,
+            kind: DefKind::Let,
+        };
+
+        loc_body = Loc::at(
+            loc_body.region,
+            LetNonRec(Box::new(def), Box::new(loc_body)),
+        );
+    }
+
+    loc_body.value
+}
+
 #[inline(always)]
 pub(crate) fn sort_top_level_can_defs(
     env: &mut Env<'_>,
@@ -1704,6 +1778,7 @@ pub(crate) fn sort_top_level_can_defs(
     for ((condition, region), preceding_comment) in it {
         // an `expect` does not have a user-defined name, but we'll need a name to call the expectation
         let name = scope.gen_unique_symbol();
+        let condition = property_expect_to_plain_expect(scope, condition);
 
         declarations.push_expect(preceding_comment, name, Loc::at(region, condition));
     }
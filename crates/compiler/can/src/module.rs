@@ -1,6 +1,8 @@
 use std::path::Path;
 
-use crate::abilities::{AbilitiesStore, ImplKey, PendingAbilitiesStore, ResolvedImpl};
+use crate::abilities::{
+    AbilitiesStore, ImplKey, MemberSpecializationInfo, PendingAbilitiesStore, ResolvedImpl,
+};
 use crate::annotation::{canonicalize_annotation, AnnotationFor};
 use crate::def::{canonicalize_defs, report_unused_imports, Def, DefKind};
 use crate::desugar::desugar_record_destructures;
@@ -1225,6 +1227,32 @@ pub struct TypeState {
 }
 
 impl TypeState {
+    /// Replaces every symbol in the type state with `remap(symbol)`, like
+    /// [`Subs::remap_symbols`].
+    pub fn remap_symbols(&mut self, remap: &impl Fn(Symbol) -> Symbol) {
+        self.subs.remap_symbols(remap);
+        self.abilities.remap_symbols(remap);
+
+        for (symbol, _) in self.exposed_vars_by_symbol.iter_mut() {
+            *symbol = remap(*symbol);
+        }
+
+        self.solved_implementations = std::mem::take(&mut self.solved_implementations)
+            .into_iter()
+            .map(|(key, resolved_impl)| {
+                let resolved_impl = match resolved_impl {
+                    ResolvedImpl::Impl(info) => ResolvedImpl::Impl(MemberSpecializationInfo::new(
+                        remap(info.symbol),
+                        info.specialization_lambda_sets,
+                    )),
+                    ResolvedImpl::Error => ResolvedImpl::Error,
+                };
+
+                (key.remap_symbols(remap), resolved_impl)
+            })
+            .collect();
+    }
+
     pub fn serialize(&self, writer: &mut impl std::io::Write) -> std::io::Result<usize> {
         let Self {
             subs,
@@ -970,3 +970,435 @@ pub fn find_declaration(symbol: Symbol, decls: &'_ Declarations) -> Option<Found
         }
     }
 }
+
+/// A place where a symbol is defined or used, as found by [find_symbol_occurrences].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolOccurrence {
+    /// The symbol is defined by the last identifier in this region, like `bar` in `bar = ...` or
+    /// in `[x, ..] as bar`.
+    Definition(Region),
+    /// The symbol is looked up by the last identifier in this region, like `bar` in `Foo.bar`.
+    Lookup(Region),
+    /// The symbol is defined by a record pattern field like `{ bar }`, so its identifier at this
+    /// region is also the field's label.
+    DestructShorthand(Region),
+    /// The symbol is defined by an optional record pattern field like `{ bar ? 0 }`, so its
+    /// identifier at the start of this region is also the field's label.
+    OptionalDestruct(Region),
+    /// The symbol is looked up by a record field like `{ bar }`, so its identifier at this region
+    /// is also the field's label.
+    FieldShorthand(Region),
+    /// The symbol is the record updated by the `{ bar & ... }` at this region.
+    RecordUpdate(Region),
+    /// The symbol's type annotation is at this region, just after its `bar :`.
+    Annotation(Region),
+}
+
+impl SymbolOccurrence {
+    pub fn region(&self) -> Region {
+        use SymbolOccurrence::*;
+        match self {
+            Definition(region)
+            | Lookup(region)
+            | DestructShorthand(region)
+            | OptionalDestruct(region)
+            | FieldShorthand(region)
+            | RecordUpdate(region)
+            | Annotation(region) => *region,
+        }
+    }
+
+    /// Whether the symbol is defined or annotated here, rather than used.
+    pub fn is_definition(&self) -> bool {
+        use SymbolOccurrence::*;
+        match self {
+            Definition(_) | DestructShorthand(_) | OptionalDestruct(_) | Annotation(_) => true,
+            Lookup(_) | FieldShorthand(_) | RecordUpdate(_) => false,
+        }
+    }
+}
+
+/// Finds every place in `decls` where `symbol` is defined, annotated or used, in source order.
+pub fn find_symbol_occurrences(symbol: Symbol, decls: &Declarations) -> Vec<SymbolOccurrence> {
+    let mut visitor = Collector {
+        symbol,
+        occurrences: Vec::new(),
+    };
+    visitor.visit_decls(decls);
+
+    visitor
+        .occurrences
+        .sort_by_key(|occurrence| occurrence.region().start());
+    return visitor.occurrences;
+
+    struct Collector {
+        symbol: Symbol,
+        occurrences: Vec<SymbolOccurrence>,
+    }
+
+    impl Collector {
+        fn visit_fields<'a>(&mut self, fields: impl Iterator<Item = &'a Field>) {
+            for field in fields {
+                let loc_expr = &field.loc_expr;
+
+                match loc_expr.value {
+                    // `{ bar }` is `{ bar: bar }` with the value where the label is; the
+                    // field's region can also span the spaces after it
+                    Expr::Var(symbol, _)
+                        if symbol == self.symbol
+                            && field.region.start() == loc_expr.region.start() =>
+                    {
+                        self.occurrences
+                            .push(SymbolOccurrence::FieldShorthand(loc_expr.region));
+                    }
+                    _ => self.visit_expr(&loc_expr.value, loc_expr.region, field.var),
+                }
+            }
+        }
+    }
+
+    impl Visitor for Collector {
+        fn visit_decls(&mut self, decls: &Declarations) {
+            // the annotations of top-level defs are only reachable through `decls`
+            for (loc_symbol, annotation) in decls.symbols.iter().zip(decls.annotations.iter()) {
+                match annotation {
+                    Some(annotation) if loc_symbol.value == self.symbol => self
+                        .occurrences
+                        .push(SymbolOccurrence::Annotation(annotation.region)),
+                    _ => {}
+                }
+            }
+
+            walk_decls(self, decls);
+        }
+
+        fn visit_def(&mut self, def: &Def) {
+            if let (Pattern::Identifier(symbol), Some(annotation)) =
+                (&def.loc_pattern.value, &def.annotation)
+            {
+                if *symbol == self.symbol {
+                    self.occurrences
+                        .push(SymbolOccurrence::Annotation(annotation.region));
+                }
+            }
+
+            walk_def(self, def);
+        }
+
+        fn visit_pattern(&mut self, pattern: &Pattern, region: Region, _opt_var: Option<Variable>) {
+            match pattern {
+                Pattern::Identifier(symbol)
+                | Pattern::AbilityMemberSpecialization { ident: symbol, .. }
+                | Pattern::As(_, symbol)
+                    if *symbol == self.symbol =>
+                {
+                    self.occurrences.push(SymbolOccurrence::Definition(region));
+                }
+                Pattern::Shadowed(_, loc_ident, symbol) if *symbol == self.symbol => {
+                    self.occurrences
+                        .push(SymbolOccurrence::Definition(loc_ident.region));
+                }
+                _ => {}
+            }
+
+            walk_pattern(self, pattern);
+        }
+
+        fn visit_record_destruct(&mut self, destruct: &RecordDestruct, region: Region) {
+            if destruct.symbol == self.symbol {
+                match destruct.typ {
+                    DestructType::Required => self
+                        .occurrences
+                        .push(SymbolOccurrence::DestructShorthand(region)),
+                    DestructType::Optional(..) => self
+                        .occurrences
+                        .push(SymbolOccurrence::OptionalDestruct(region)),
+                    DestructType::Guard(..) => {}
+                }
+            }
+
+            walk_record_destruct(self, destruct);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+            match expr {
+                Expr::Var(symbol, _)
+                | Expr::AbilityMember(symbol, _, _)
+                | Expr::ParamsVar { symbol, .. }
+                    if *symbol == self.symbol =>
+                {
+                    self.occurrences.push(SymbolOccurrence::Lookup(region));
+                }
+                Expr::Record { fields, .. } => self.visit_fields(fields.values()),
+                Expr::RecordUpdate {
+                    symbol, updates, ..
+                } => {
+                    if *symbol == self.symbol {
+                        self.occurrences
+                            .push(SymbolOccurrence::RecordUpdate(region));
+                    }
+
+                    self.visit_fields(updates.values());
+                }
+                _ => walk_expr(self, expr, var),
+            }
+        }
+    }
+}
+
+/// What introduces a binding found by [find_unannotated_bindings].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    /// A def like `bar = ...`, top-level or nested
+    Def,
+    /// An argument of a closure, like `x` in `\x -> ...`
+    Argument,
+    /// An identifier within a pattern, like `n` in `Ok n -> ...` or `{ n }`
+    Pattern,
+}
+
+/// A binding of a symbol that has no type annotation of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnannotatedBinding {
+    pub symbol: Symbol,
+    /// The region of the binding, ending where its name does
+    pub region: Region,
+    pub var: Variable,
+    pub kind: BindingKind,
+}
+
+/// Finds the bindings whose type is only known from inference: defs without an annotation, the
+/// arguments of closures that are not the body of an annotated def, and the identifiers bound
+/// within patterns. The bindings are sorted by region.
+pub fn find_unannotated_bindings(decls: &Declarations) -> Vec<UnannotatedBinding> {
+    let mut visitor = Collector {
+        annotated_functions: Vec::new(),
+        bindings: Vec::new(),
+    };
+    visitor.visit_decls(decls);
+
+    visitor
+        .bindings
+        .sort_by_key(|binding| binding.region.start());
+    return visitor.bindings;
+
+    struct Collector {
+        /// Top-level functions with an annotation, whose arguments are annotated by it
+        annotated_functions: Vec<Symbol>,
+        bindings: Vec<UnannotatedBinding>,
+    }
+
+    impl Collector {
+        fn push(&mut self, symbol: Symbol, region: Region, var: Variable, kind: BindingKind) {
+            self.bindings.push(UnannotatedBinding {
+                symbol,
+                region,
+                var,
+                kind,
+            });
+        }
+
+        fn visit_closure_help(
+            &mut self,
+            arguments: &[(Variable, AnnotatedMark, Loc<Pattern>)],
+            loc_body: &Loc<Expr>,
+            return_type: Variable,
+            is_annotated: bool,
+        ) {
+            for (var, _, arg) in arguments {
+                match arg.value {
+                    Pattern::Identifier(symbol) => {
+                        if !is_annotated {
+                            self.push(symbol, arg.region, *var, BindingKind::Argument);
+                        }
+                    }
+                    _ => self.visit_pattern(&arg.value, arg.region, Some(*var)),
+                }
+            }
+
+            self.visit_expr(&loc_body.value, loc_body.region, return_type);
+        }
+    }
+
+    impl Visitor for Collector {
+        fn visit_decls(&mut self, decls: &Declarations) {
+            // the annotations of top-level functions are only reachable through `decls`
+            for (loc_symbol, annotation) in decls.symbols.iter().zip(decls.annotations.iter()) {
+                if annotation.is_some() {
+                    self.annotated_functions.push(loc_symbol.value);
+                }
+            }
+
+            walk_decls(self, decls);
+        }
+
+        fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+            match decl {
+                DeclarationInfo::Value {
+                    loc_symbol,
+                    loc_expr,
+                    expr_var,
+                    pattern: Pattern::Identifier(_),
+                    annotation,
+                } => {
+                    if annotation.is_none() {
+                        self.push(
+                            loc_symbol.value,
+                            loc_symbol.region,
+                            expr_var,
+                            BindingKind::Def,
+                        );
+                    }
+
+                    self.visit_expr(&loc_expr.value, loc_expr.region, expr_var);
+                }
+                DeclarationInfo::Function {
+                    loc_symbol,
+                    loc_body,
+                    expr_var,
+                    pattern: Pattern::Identifier(_),
+                    function,
+                } => {
+                    let is_annotated = self.annotated_functions.contains(&loc_symbol.value);
+
+                    if !is_annotated {
+                        self.push(
+                            loc_symbol.value,
+                            loc_symbol.region,
+                            expr_var,
+                            BindingKind::Def,
+                        );
+                    }
+
+                    self.visit_closure_help(
+                        &function.value.arguments,
+                        loc_body,
+                        function.value.return_type,
+                        is_annotated,
+                    );
+                }
+                _ => walk_decl(self, decl),
+            }
+        }
+
+        fn visit_def(&mut self, def: &Def) {
+            let Pattern::Identifier(symbol) = def.loc_pattern.value else {
+                return walk_def(self, def);
+            };
+
+            let is_annotated = def.annotation.is_some();
+
+            if !is_annotated {
+                self.push(
+                    symbol,
+                    def.loc_pattern.region,
+                    def.expr_var,
+                    BindingKind::Def,
+                );
+            }
+
+            match &def.loc_expr.value {
+                Expr::Closure(ClosureData {
+                    arguments,
+                    loc_body,
+                    return_type,
+                    ..
+                }) => self.visit_closure_help(arguments, loc_body, *return_type, is_annotated),
+                expr => self.visit_expr(expr, def.loc_expr.region, def.expr_var),
+            }
+        }
+
+        fn visit_expr(&mut self, expr: &Expr, _region: Region, var: Variable) {
+            match expr {
+                Expr::Closure(ClosureData {
+                    arguments,
+                    loc_body,
+                    return_type,
+                    ..
+                }) => self.visit_closure_help(arguments, loc_body, *return_type, false),
+                _ => walk_expr(self, expr, var),
+            }
+        }
+
+        fn visit_pattern(&mut self, pattern: &Pattern, region: Region, opt_var: Option<Variable>) {
+            match (pattern, opt_var) {
+                (Pattern::Identifier(symbol) | Pattern::As(_, symbol), Some(var)) => {
+                    self.push(*symbol, region, var, BindingKind::Pattern);
+                }
+                _ => {}
+            }
+
+            walk_pattern(self, pattern);
+        }
+
+        fn visit_record_destruct(&mut self, destruct: &RecordDestruct, region: Region) {
+            if let DestructType::Required = destruct.typ {
+                self.push(destruct.symbol, region, destruct.var, BindingKind::Pattern);
+            }
+
+            walk_record_destruct(self, destruct);
+        }
+    }
+}
+
+/// A call of a function by its name, found by [find_calls].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoundCall {
+    /// The top-level def that the call is in
+    pub caller: Symbol,
+    /// The function that is called
+    pub callee: Symbol,
+    /// The region of the name of the function in the call
+    pub region: Region,
+}
+
+/// Finds the calls of functions by name, like `foo x` and `x |> Foo.bar`, in the top-level defs.
+/// Calls in expectations and destructures are not in a named def, so they are skipped. The calls
+/// are sorted by region.
+pub fn find_calls(decls: &Declarations) -> Vec<FoundCall> {
+    let mut visitor = Collector {
+        caller: None,
+        calls: Vec::new(),
+    };
+    visitor.visit_decls(decls);
+
+    visitor.calls.sort_by_key(|call| call.region.start());
+    return visitor.calls;
+
+    struct Collector {
+        /// The top-level def being visited, if it has a name
+        caller: Option<Symbol>,
+        calls: Vec<FoundCall>,
+    }
+
+    impl Visitor for Collector {
+        fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+            self.caller = match &decl {
+                DeclarationInfo::Value { loc_symbol, .. }
+                | DeclarationInfo::Function { loc_symbol, .. } => Some(loc_symbol.value),
+                DeclarationInfo::Expectation { .. } | DeclarationInfo::Destructure { .. } => None,
+            };
+
+            walk_decl(self, decl);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr, _region: Region, var: Variable) {
+            if let (Some(caller), Expr::Call(boxed, _, _)) = (self.caller, expr) {
+                let loc_function = &boxed.1;
+
+                match loc_function.value {
+                    Expr::Var(callee, _) | Expr::ParamsVar { symbol: callee, .. } => {
+                        self.calls.push(FoundCall {
+                            caller,
+                            callee,
+                            region: loc_function.region,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            walk_expr(self, expr, var);
+        }
+    }
+}
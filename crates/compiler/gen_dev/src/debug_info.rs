@@ -0,0 +1,906 @@
+//! DWARF debug info that maps the machine code of procs back to the `.roc` source, for
+//! `roc build --dev --debug`.
+//!
+//! Each proc's line table starts at the line of its def and gets a row for every statement that
+//! mono IR recorded a region for. Arguments are described by their offset from the frame
+//! pointer, which the backend keeps them at when building with debug info.
+use std::path::PathBuf;
+
+use gimli::{DwAt, DwAte, DwChildren, DwForm, DwLang, DwLne, DwTag};
+use object::write::{self, Object, SectionId, StandardSegment, SymbolId};
+use object::{BinaryFormat, RelocationEncoding, RelocationFlags, RelocationKind, SectionKind};
+use roc_builtins::bitcode::{FloatWidth, IntWidth};
+use roc_collections::all::MutMap;
+use roc_error_macros::internal_error;
+use roc_module::symbol::{Interns, ModuleId, Symbol};
+use roc_mono::layout::{Builtin, InLayout, LayoutInterner, LayoutRepr, STLayoutInterner};
+use roc_region::all::{LineInfo, Region};
+
+/// There is no language code for Roc, and debuggers handle C-like code best.
+const LANGUAGE: DwLang = gimli::DW_LANG_C;
+
+const LINE_BASE: i8 = -5;
+const LINE_RANGE: u8 = 14;
+const OPCODE_BASE: u8 = 13;
+const STANDARD_OPCODE_LENGTHS: [u8; OPCODE_BASE as usize - 1] =
+    [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+const ABBREV_COMPILE_UNIT: u64 = 1;
+const ABBREV_SUBPROGRAM: u64 = 2;
+const ABBREV_FORMAL_PARAMETER: u64 = 3;
+const ABBREV_BASE_TYPE: u64 = 4;
+const ABBREV_STRUCTURE_TYPE: u64 = 5;
+
+/// A proc whose def is known, along with where its machine code ended up.
+pub(crate) struct DebugProc {
+    pub symbol: Symbol,
+    pub region: Region,
+    /// The object symbol at the start of the proc's machine code
+    pub proc_id: SymbolId,
+    pub size: u64,
+    /// The offset into the proc's machine code where each statement with a known region starts
+    pub statements: Vec<(u64, Region)>,
+    pub arguments: Vec<DebugArgument>,
+}
+
+/// An argument of a proc that lives in its stack frame
+pub(crate) struct DebugArgument {
+    pub symbol: Symbol,
+    pub ty: DebugType,
+    /// The offset of the argument from the frame pointer
+    pub frame_offset: i32,
+}
+
+/// How a debugger should show the bytes of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DebugType {
+    Base {
+        name: &'static str,
+        encoding: DwAte,
+        size: u32,
+    },
+    /// A value whose contents aren't described, so only its bytes can be shown
+    Opaque { name: &'static str, size: u32 },
+}
+
+/// The debug type of values of `layout`, or `None` if they take up no space
+pub(crate) fn debug_type(
+    interner: &STLayoutInterner<'_>,
+    layout: InLayout<'_>,
+) -> Option<DebugType> {
+    let size = interner.stack_size(layout);
+    if size == 0 {
+        return None;
+    }
+
+    let ty = match interner.get_repr(layout) {
+        LayoutRepr::Builtin(Builtin::Int(width)) => DebugType::Base {
+            name: int_type_name(width),
+            encoding: if width.is_signed() {
+                gimli::DW_ATE_signed
+            } else {
+                gimli::DW_ATE_unsigned
+            },
+            size,
+        },
+        LayoutRepr::Builtin(Builtin::Float(width)) => DebugType::Base {
+            name: match width {
+                FloatWidth::F32 => "F32",
+                FloatWidth::F64 => "F64",
+            },
+            encoding: gimli::DW_ATE_float,
+            size,
+        },
+        LayoutRepr::Builtin(Builtin::Bool) => DebugType::Base {
+            name: "Bool",
+            encoding: gimli::DW_ATE_boolean,
+            size,
+        },
+        LayoutRepr::Builtin(Builtin::Decimal) => DebugType::Opaque { name: "Dec", size },
+        LayoutRepr::Builtin(Builtin::Str) => DebugType::Opaque { name: "Str", size },
+        LayoutRepr::Builtin(Builtin::List(_)) => DebugType::Opaque { name: "List", size },
+        LayoutRepr::Struct(_) => DebugType::Opaque {
+            name: "Struct",
+            size,
+        },
+        LayoutRepr::Union(_) => DebugType::Opaque { name: "Tag", size },
+        LayoutRepr::Ptr(_) | LayoutRepr::RecursivePointer(_) => {
+            DebugType::Opaque { name: "Ptr", size }
+        }
+        LayoutRepr::LambdaSet(_) => DebugType::Opaque {
+            name: "Closure",
+            size,
+        },
+        LayoutRepr::FunctionPointer(_) => DebugType::Opaque {
+            name: "Function",
+            size,
+        },
+        LayoutRepr::Erased(_) => DebugType::Opaque {
+            name: "Erased",
+            size,
+        },
+    };
+
+    Some(ty)
+}
+
+fn int_type_name(width: IntWidth) -> &'static str {
+    match width {
+        IntWidth::U8 => "U8",
+        IntWidth::U16 => "U16",
+        IntWidth::U32 => "U32",
+        IntWidth::U64 => "U64",
+        IntWidth::U128 => "U128",
+        IntWidth::I8 => "I8",
+        IntWidth::I16 => "I16",
+        IntWidth::I32 => "I32",
+        IntWidth::I64 => "I64",
+        IntWidth::I128 => "I128",
+    }
+}
+
+/// Adds the `.debug_abbrev`, `.debug_info`, `.debug_ranges` and `.debug_line` sections that
+/// describe `procs` to `output`.
+pub(crate) fn add_debug_sections(
+    output: &mut Object,
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    home: ModuleId,
+    interns: &Interns,
+    procs: &[DebugProc],
+) {
+    // The line table refers to files by their 1-based index in its file list
+    let mut files: Vec<(ModuleId, &PathBuf, LineInfo)> = Vec::new();
+    let mut units = Vec::with_capacity(procs.len());
+
+    for proc in procs {
+        let module_id = proc.symbol.module_id();
+        let Some((path, src)) = sources.get(&module_id) else {
+            continue;
+        };
+
+        let index = match files.iter().position(|(id, _, _)| *id == module_id) {
+            Some(index) => index,
+            None => {
+                files.push((module_id, path, LineInfo::new(src)));
+                files.len() - 1
+            }
+        };
+
+        let line_info = &files[index].2;
+        let row = |offset: u64, region: Region| {
+            let start = line_info.convert_pos(region.start());
+            LineRow {
+                offset,
+                line: start.line as u64 + 1,
+                column: start.column as u64 + 1,
+            }
+        };
+
+        let mut rows = vec![row(0, proc.region)];
+        rows.extend(
+            proc.statements
+                .iter()
+                .filter(|(offset, _)| *offset < proc.size)
+                .map(|(offset, region)| row(*offset, *region)),
+        );
+        rows.sort_by_key(|row| row.offset);
+
+        let arguments = proc
+            .arguments
+            .iter()
+            .map(|argument| UnitArgument {
+                name: argument.symbol.as_str(interns).to_string(),
+                ty: argument.ty,
+                frame_offset: argument.frame_offset,
+            })
+            .collect();
+
+        units.push(UnitProc {
+            name: proc.symbol.as_str(interns).to_string(),
+            proc_id: proc.proc_id,
+            size: proc.size,
+            file: index as u64 + 1,
+            rows,
+            arguments,
+        });
+    }
+
+    let unit = DebugUnit {
+        name: match sources.get(&home) {
+            Some((path, _)) => path.to_string_lossy().into_owned(),
+            None => String::from("roc_app"),
+        },
+        comp_dir: std::env::current_dir()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| ".".to_string()),
+        files: files
+            .iter()
+            .map(|(_, path, _)| path.to_string_lossy().into_owned())
+            .collect(),
+        procs: units,
+    };
+
+    write_debug_sections(output, &unit);
+}
+
+/// The compile unit of a module, with every symbol and region resolved to a name or position
+struct DebugUnit {
+    name: String,
+    comp_dir: String,
+    files: Vec<String>,
+    procs: Vec<UnitProc>,
+}
+
+struct UnitProc {
+    name: String,
+    proc_id: SymbolId,
+    size: u64,
+    file: u64,
+    /// The first row is the start of the proc, at the line of its def
+    rows: Vec<LineRow>,
+    arguments: Vec<UnitArgument>,
+}
+
+struct LineRow {
+    offset: u64,
+    line: u64,
+    column: u64,
+}
+
+struct UnitArgument {
+    name: String,
+    ty: DebugType,
+    frame_offset: i32,
+}
+
+fn write_debug_sections(output: &mut Object, unit: &DebugUnit) {
+    if unit.procs.is_empty() {
+        return;
+    }
+
+    // Mach-O keeps debug info in the objects rather than linking it, so offsets into the debug
+    // sections of an object need no relocation there.
+    let (section_offset_kind, section_names): (_, [&[u8]; 4]) = match output.format() {
+        BinaryFormat::Elf => (
+            Some(RelocationKind::Absolute),
+            [
+                b".debug_abbrev",
+                b".debug_info",
+                b".debug_ranges",
+                b".debug_line",
+            ],
+        ),
+        BinaryFormat::Coff => (
+            Some(RelocationKind::SectionOffset),
+            [
+                b".debug_abbrev",
+                b".debug_info",
+                b".debug_ranges",
+                b".debug_line",
+            ],
+        ),
+        BinaryFormat::MachO => (
+            None,
+            [
+                b"__debug_abbrev",
+                b"__debug_info",
+                b"__debug_ranges",
+                b"__debug_line",
+            ],
+        ),
+        _ => return,
+    };
+
+    // The register that holds the frame pointer, which arguments are located relative to
+    let frame_register = match output.architecture() {
+        object::Architecture::X86_64 => gimli::X86_64::RBP,
+        object::Architecture::Aarch64 => gimli::AArch64::X29,
+        _ => return,
+    };
+
+    let segment = output.segment_name(StandardSegment::Debug).to_vec();
+    let [abbrev_id, info_id, ranges_id, line_id] = section_names
+        .map(|name| output.add_section(segment.clone(), name.to_vec(), SectionKind::Debug));
+
+    let abbrev_symbol = output.section_symbol(abbrev_id);
+    let ranges_symbol = output.section_symbol(ranges_id);
+    let line_symbol = output.section_symbol(line_id);
+
+    let mut abbrev = DwarfSection::new(section_offset_kind);
+    abbrev.abbreviation(
+        ABBREV_COMPILE_UNIT,
+        gimli::DW_TAG_compile_unit,
+        gimli::DW_CHILDREN_yes,
+        &[
+            (gimli::DW_AT_producer, gimli::DW_FORM_string),
+            (gimli::DW_AT_language, gimli::DW_FORM_data2),
+            (gimli::DW_AT_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_comp_dir, gimli::DW_FORM_string),
+            (gimli::DW_AT_stmt_list, gimli::DW_FORM_sec_offset),
+            (gimli::DW_AT_low_pc, gimli::DW_FORM_addr),
+            (gimli::DW_AT_ranges, gimli::DW_FORM_sec_offset),
+        ],
+    );
+    abbrev.abbreviation(
+        ABBREV_SUBPROGRAM,
+        gimli::DW_TAG_subprogram,
+        gimli::DW_CHILDREN_yes,
+        &[
+            (gimli::DW_AT_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_linkage_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_decl_file, gimli::DW_FORM_udata),
+            (gimli::DW_AT_decl_line, gimli::DW_FORM_udata),
+            (gimli::DW_AT_low_pc, gimli::DW_FORM_addr),
+            (gimli::DW_AT_high_pc, gimli::DW_FORM_data8),
+            (gimli::DW_AT_frame_base, gimli::DW_FORM_exprloc),
+        ],
+    );
+    abbrev.abbreviation(
+        ABBREV_FORMAL_PARAMETER,
+        gimli::DW_TAG_formal_parameter,
+        gimli::DW_CHILDREN_no,
+        &[
+            (gimli::DW_AT_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_type, gimli::DW_FORM_ref4),
+            (gimli::DW_AT_location, gimli::DW_FORM_exprloc),
+        ],
+    );
+    abbrev.abbreviation(
+        ABBREV_BASE_TYPE,
+        gimli::DW_TAG_base_type,
+        gimli::DW_CHILDREN_no,
+        &[
+            (gimli::DW_AT_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_encoding, gimli::DW_FORM_data1),
+            (gimli::DW_AT_byte_size, gimli::DW_FORM_udata),
+        ],
+    );
+    abbrev.abbreviation(
+        ABBREV_STRUCTURE_TYPE,
+        gimli::DW_TAG_structure_type,
+        gimli::DW_CHILDREN_no,
+        &[
+            (gimli::DW_AT_name, gimli::DW_FORM_string),
+            (gimli::DW_AT_byte_size, gimli::DW_FORM_udata),
+        ],
+    );
+    abbrev.u8(0);
+
+    let mut info = DwarfSection::new(section_offset_kind);
+    info.u32(0); // unit_length, patched below
+    info.u16(4); // version
+    info.section_offset(abbrev_symbol);
+    info.u8(8); // address_size
+
+    info.uleb128(ABBREV_COMPILE_UNIT);
+    info.string(&format!("roc {}", env!("CARGO_PKG_VERSION")));
+    info.u16(LANGUAGE.0);
+    info.string(&unit.name);
+    info.string(&unit.comp_dir);
+    info.section_offset(line_symbol);
+    info.u64(0); // the base address of the ranges, which are relocated on their own
+    info.section_offset(ranges_symbol);
+
+    // Arguments refer to their type by its offset from the start of the compile unit
+    let mut types: Vec<(DebugType, u32)> = Vec::new();
+    for argument in unit.procs.iter().flat_map(|proc| &proc.arguments) {
+        if types.iter().any(|(ty, _)| *ty == argument.ty) {
+            continue;
+        }
+
+        types.push((argument.ty, info.data.len() as u32));
+        match argument.ty {
+            DebugType::Base {
+                name,
+                encoding,
+                size,
+            } => {
+                info.uleb128(ABBREV_BASE_TYPE);
+                info.string(name);
+                info.u8(encoding.0);
+                info.uleb128(size as u64);
+            }
+            DebugType::Opaque { name, size } => {
+                info.uleb128(ABBREV_STRUCTURE_TYPE);
+                info.string(name);
+                info.uleb128(size as u64);
+            }
+        }
+    }
+
+    for proc in &unit.procs {
+        let linkage_name = String::from_utf8_lossy(&output.symbol(proc.proc_id).name).into_owned();
+
+        info.uleb128(ABBREV_SUBPROGRAM);
+        info.string(&proc.name);
+        info.string(&linkage_name);
+        info.uleb128(proc.file);
+        info.uleb128(proc.rows[0].line);
+        info.address(proc.proc_id, 0);
+        info.u64(proc.size);
+        info.uleb128(2); // the length of the frame base expression
+        info.u8(gimli::DW_OP_breg0.0 + frame_register.0 as u8);
+        info.sleb128(0);
+
+        for argument in &proc.arguments {
+            let (_, type_offset) = types.iter().find(|(ty, _)| *ty == argument.ty).unwrap();
+            let mut location = DwarfSection::new(None);
+            location.u8(gimli::DW_OP_fbreg.0);
+            location.sleb128(argument.frame_offset as i64);
+
+            info.uleb128(ABBREV_FORMAL_PARAMETER);
+            info.string(&argument.name);
+            info.u32(*type_offset);
+            info.uleb128(location.data.len() as u64);
+            info.bytes(&location.data);
+        }
+        info.u8(0); // the end of the subprogram's children
+    }
+    info.u8(0); // the end of the compile unit's children
+    info.patch_unit_length(0);
+
+    let mut ranges = DwarfSection::new(section_offset_kind);
+    for proc in &unit.procs {
+        ranges.address(proc.proc_id, 0);
+        ranges.address(proc.proc_id, proc.size as i64);
+    }
+    ranges.u64(0);
+    ranges.u64(0);
+
+    let mut line_program = DwarfSection::new(section_offset_kind);
+    line_program.u32(0); // unit_length, patched below
+    line_program.u16(4); // version
+    line_program.u32(0); // header_length, patched below
+    line_program.u8(1); // minimum_instruction_length
+    line_program.u8(1); // maximum_operations_per_instruction
+    line_program.u8(1); // default_is_stmt
+    line_program.u8(LINE_BASE as u8);
+    line_program.u8(LINE_RANGE);
+    line_program.u8(OPCODE_BASE);
+    line_program.bytes(&STANDARD_OPCODE_LENGTHS);
+    line_program.u8(0); // no include_directories, the file names are relative to comp_dir
+    for path in &unit.files {
+        line_program.string(path);
+        line_program.uleb128(0); // directory index
+        line_program.uleb128(0); // modification time
+        line_program.uleb128(0); // length
+    }
+    line_program.u8(0);
+    let header_length = line_program.data.len() - 10;
+    line_program.data[6..10].copy_from_slice(&(header_length as u32).to_le_bytes());
+
+    // Every proc gets a sequence of its own, since the linker decides where each one ends up
+    for proc in &unit.procs {
+        line_program.extended_opcode(gimli::DW_LNE_set_address, 9);
+        line_program.address(proc.proc_id, 0);
+
+        if proc.file != 1 {
+            line_program.u8(gimli::DW_LNS_set_file.0);
+            line_program.uleb128(proc.file);
+        }
+
+        let mut offset = 0;
+        let mut line = 1;
+        for row in &proc.rows {
+            if row.offset != offset {
+                line_program.u8(gimli::DW_LNS_advance_pc.0);
+                line_program.uleb128(row.offset - offset);
+                offset = row.offset;
+            }
+
+            if row.line != line {
+                line_program.u8(gimli::DW_LNS_advance_line.0);
+                line_program.sleb128(row.line as i64 - line as i64);
+                line = row.line;
+            }
+
+            line_program.u8(gimli::DW_LNS_set_column.0);
+            line_program.uleb128(row.column);
+            line_program.u8(gimli::DW_LNS_copy.0);
+        }
+
+        line_program.u8(gimli::DW_LNS_advance_pc.0);
+        line_program.uleb128(proc.size - offset);
+        line_program.extended_opcode(gimli::DW_LNE_end_sequence, 1);
+    }
+    line_program.patch_unit_length(0);
+
+    abbrev.append_to(output, abbrev_id);
+    info.append_to(output, info_id);
+    ranges.append_to(output, ranges_id);
+    line_program.append_to(output, line_id);
+}
+
+/// The bytes of a DWARF section, along with the relocations of the addresses and section
+/// offsets in it
+struct DwarfSection {
+    data: Vec<u8>,
+    relocations: Vec<write::Relocation>,
+    /// How offsets into other debug sections get relocated, if they need to be at all
+    section_offset_kind: Option<RelocationKind>,
+}
+
+impl DwarfSection {
+    fn new(section_offset_kind: Option<RelocationKind>) -> Self {
+        Self {
+            data: Vec::new(),
+            relocations: Vec::new(),
+            section_offset_kind,
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.data.extend_from_slice(value.as_bytes());
+        self.data.push(0);
+    }
+
+    fn uleb128(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.data.push(byte);
+                return;
+            }
+
+            self.data.push(byte | 0x80);
+        }
+    }
+
+    fn sleb128(&mut self, mut value: i64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            let sign_bit_clear = byte & 0x40 == 0;
+            if (value == 0 && sign_bit_clear) || (value == -1 && !sign_bit_clear) {
+                self.data.push(byte);
+                return;
+            }
+
+            self.data.push(byte | 0x80);
+        }
+    }
+
+    /// An abbreviation with the attribute specifications of its entries, followed by the
+    /// terminating zeros
+    fn abbreviation(
+        &mut self,
+        code: u64,
+        tag: DwTag,
+        children: DwChildren,
+        attributes: &[(DwAt, DwForm)],
+    ) {
+        self.uleb128(code);
+        self.uleb128(tag.0 as u64);
+        self.u8(children.0);
+
+        for (name, form) in attributes {
+            self.uleb128(name.0 as u64);
+            self.uleb128(form.0 as u64);
+        }
+
+        self.uleb128(0);
+        self.uleb128(0);
+    }
+
+    fn extended_opcode(&mut self, opcode: DwLne, length: u64) {
+        self.u8(0);
+        self.uleb128(length);
+        self.u8(opcode.0);
+    }
+
+    /// A 64-bit address of `symbol` plus `addend`, which the linker fills in
+    fn address(&mut self, symbol: SymbolId, addend: i64) {
+        self.relocation(symbol, addend, RelocationKind::Absolute, 64);
+        self.u64(0);
+    }
+
+    /// A 32-bit offset into the section of `symbol`, which the linker fills in. Since every
+    /// debug section holds a single unit, the offset is 0 before relocation.
+    fn section_offset(&mut self, symbol: SymbolId) {
+        if let Some(kind) = self.section_offset_kind {
+            self.relocation(symbol, 0, kind, 32);
+        }
+        self.u32(0);
+    }
+
+    fn relocation(&mut self, symbol: SymbolId, addend: i64, kind: RelocationKind, size: u8) {
+        self.relocations.push(write::Relocation {
+            offset: self.data.len() as u64,
+            symbol,
+            addend,
+            flags: RelocationFlags::Generic {
+                kind,
+                encoding: RelocationEncoding::Generic,
+                size,
+            },
+        });
+    }
+
+    /// Fills in the 32-bit unit length at `offset`, which counts the bytes after it
+    fn patch_unit_length(&mut self, offset: usize) {
+        let length = (self.data.len() - offset - 4) as u32;
+        self.data[offset..offset + 4].copy_from_slice(&length.to_le_bytes());
+    }
+
+    fn append_to(self, output: &mut Object, section_id: SectionId) {
+        output.append_section_data(section_id, &self.data, 1);
+
+        for relocation in self.relocations {
+            if let Err(e) = output.add_relocation(section_id, relocation) {
+                internal_error!("{:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::{AttributeValue, ColumnType, EndianSlice, LittleEndian, Operation};
+    use object::write::{Symbol as ObjectSymbol, SymbolSection};
+    use object::{
+        Architecture, Endianness, Object as _, ObjectSection, SymbolFlags, SymbolKind, SymbolScope,
+    };
+
+    const I64: DebugType = DebugType::Base {
+        name: "I64",
+        encoding: gimli::DW_ATE_signed,
+        size: 8,
+    };
+    const STR: DebugType = DebugType::Opaque {
+        name: "Str",
+        size: 24,
+    };
+
+    /// An object with a single 32-byte proc `main` that takes an `I64` and a `Str`
+    fn object_with_debug_info(format: BinaryFormat) -> Vec<u8> {
+        let mut output = Object::new(format, Architecture::X86_64, Endianness::Little);
+        let text = output.section_id(write::StandardSection::Text);
+        let proc_id = output.add_symbol(ObjectSymbol {
+            name: b"roc__main_1_exposed".to_vec(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Linkage,
+            weak: false,
+            section: SymbolSection::Section(text),
+            flags: SymbolFlags::None,
+        });
+        output.add_symbol_data(proc_id, text, &[0x90; 32], 16);
+
+        let row = |offset, line, column| LineRow {
+            offset,
+            line,
+            column,
+        };
+
+        let unit = DebugUnit {
+            name: String::from("main.roc"),
+            comp_dir: String::from("/app"),
+            files: vec![String::from("main.roc")],
+            procs: vec![UnitProc {
+                name: String::from("main"),
+                proc_id,
+                size: 32,
+                file: 1,
+                rows: vec![row(0, 3, 1), row(8, 4, 5), row(20, 6, 5)],
+                arguments: vec![
+                    UnitArgument {
+                        name: String::from("count"),
+                        ty: I64,
+                        frame_offset: -16,
+                    },
+                    UnitArgument {
+                        name: String::from("name"),
+                        ty: STR,
+                        frame_offset: -40,
+                    },
+                ],
+            }],
+        };
+
+        write_debug_sections(&mut output, &unit);
+
+        output.write().unwrap()
+    }
+
+    fn load_dwarf(bytes: &[u8]) -> gimli::Dwarf<EndianSlice<'_, LittleEndian>> {
+        let file = object::File::parse(bytes).unwrap();
+
+        gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+            let data = match file.section_by_name(id.name()) {
+                Some(section) => section.data().unwrap(),
+                None => &[],
+            };
+
+            Ok(EndianSlice::new(data, LittleEndian))
+        })
+        .unwrap()
+    }
+
+    /// The (address, line, column) of every row of the line program, and whether it ends a
+    /// sequence
+    fn line_rows(bytes: &[u8]) -> Vec<(u64, u64, u64, bool)> {
+        let dwarf = load_dwarf(bytes);
+        let header = dwarf.units().next().unwrap().unwrap();
+        let unit = dwarf.unit(header).unwrap();
+        let mut rows = unit.line_program.unwrap().rows();
+
+        let mut result = Vec::new();
+        while let Some((_, row)) = rows.next_row().unwrap() {
+            let line = row.line().map_or(0, |line| line.get());
+            let column = match row.column() {
+                ColumnType::LeftEdge => 0,
+                ColumnType::Column(column) => column.get(),
+            };
+
+            result.push((row.address(), line, column, row.end_sequence()));
+        }
+
+        result
+    }
+
+    #[test]
+    fn line_program_has_a_row_per_statement() {
+        for format in [BinaryFormat::Elf, BinaryFormat::MachO, BinaryFormat::Coff] {
+            assert_eq!(
+                line_rows(&object_with_debug_info(format)),
+                vec![
+                    (0, 3, 1, false),
+                    (8, 4, 5, false),
+                    (20, 6, 5, false),
+                    (32, 6, 5, true),
+                ],
+                "line rows of {format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn arguments_are_located_relative_to_the_frame_base() {
+        let bytes = object_with_debug_info(BinaryFormat::Elf);
+        let dwarf = load_dwarf(&bytes);
+        let header = dwarf.units().next().unwrap().unwrap();
+        let unit = dwarf.unit(header).unwrap();
+
+        let name_of = |entry: &gimli::DebuggingInformationEntry<_>| {
+            let value = entry.attr_value(gimli::DW_AT_name).unwrap().unwrap();
+            let name = dwarf.attr_string(&unit, value).unwrap();
+            name.to_string_lossy().into_owned()
+        };
+
+        let mut subprograms = Vec::new();
+        let mut parameters = Vec::new();
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            match entry.tag() {
+                gimli::DW_TAG_subprogram => {
+                    let Some(AttributeValue::Exprloc(frame_base)) =
+                        entry.attr_value(gimli::DW_AT_frame_base).unwrap()
+                    else {
+                        panic!("subprogram without a frame base");
+                    };
+                    let mut ops = frame_base.operations(unit.encoding());
+
+                    subprograms.push((name_of(entry), ops.next().unwrap().unwrap()));
+                }
+                gimli::DW_TAG_formal_parameter => {
+                    let Some(AttributeValue::Exprloc(location)) =
+                        entry.attr_value(gimli::DW_AT_location).unwrap()
+                    else {
+                        panic!("parameter without a location");
+                    };
+                    let Some(AttributeValue::UnitRef(type_offset)) =
+                        entry.attr_value(gimli::DW_AT_type).unwrap()
+                    else {
+                        panic!("parameter without a type");
+                    };
+                    let ty = unit.entry(type_offset).unwrap();
+                    let byte_size = ty
+                        .attr_value(gimli::DW_AT_byte_size)
+                        .unwrap()
+                        .and_then(|value| value.udata_value());
+                    let mut ops = location.operations(unit.encoding());
+
+                    parameters.push((
+                        name_of(entry),
+                        name_of(&ty),
+                        ty.tag(),
+                        byte_size,
+                        ops.next().unwrap().unwrap(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(
+            subprograms,
+            vec![(
+                String::from("main"),
+                Operation::RegisterOffset {
+                    register: gimli::X86_64::RBP,
+                    offset: 0,
+                    base_type: gimli::UnitOffset(0),
+                }
+            )]
+        );
+        assert_eq!(
+            parameters,
+            vec![
+                (
+                    String::from("count"),
+                    String::from("I64"),
+                    gimli::DW_TAG_base_type,
+                    Some(8),
+                    Operation::FrameOffset { offset: -16 }
+                ),
+                (
+                    String::from("name"),
+                    String::from("Str"),
+                    gimli::DW_TAG_structure_type,
+                    Some(24),
+                    Operation::FrameOffset { offset: -40 }
+                ),
+            ]
+        );
+    }
+
+    // The examples from https://dwarfstd.org/doc/DWARF4.pdf, figures 22 and 23
+    #[test]
+    fn test_uleb128() {
+        for (value, expected) in [
+            (2, vec![0x02]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (129, vec![0x81, 0x01]),
+            (130, vec![0x82, 0x01]),
+            (12857, vec![0xb9, 0x64]),
+        ] {
+            let mut section = DwarfSection::new(None);
+            section.uleb128(value);
+            assert_eq!(section.data, expected, "uleb128 of {value}");
+        }
+    }
+
+    #[test]
+    fn test_sleb128() {
+        for (value, expected) in [
+            (2, vec![0x02]),
+            (-2, vec![0x7e]),
+            (127, vec![0xff, 0x00]),
+            (-127, vec![0x81, 0x7f]),
+            (128, vec![0x80, 0x01]),
+            (-128, vec![0x80, 0x7f]),
+            (129, vec![0x81, 0x01]),
+            (-129, vec![0xff, 0x7e]),
+        ] {
+            let mut section = DwarfSection::new(None);
+            section.sleb128(value);
+            assert_eq!(section.data, expected, "sleb128 of {value}");
+        }
+    }
+}
@@ -1262,12 +1262,20 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         src: AArch64GeneralReg,
         imm32: i32,
     ) {
-        if imm32 < 0 {
+        if imm32 < 0 && imm32 > -0x100_0000 {
             Self::sub_reg64_reg64_imm32(buf, dst, src, -imm32);
-        } else if imm32 < 0xFFF {
+        } else if (0..0xFFF).contains(&imm32) {
             add_reg64_reg64_imm12(buf, dst, src, imm32 as u16);
+        } else if (0..0x100_0000).contains(&imm32) {
+            // add the upper 12 bits shifted into place, and then the lower 12 bits
+            add_reg64_reg64_imm12_lsl12(buf, dst, src, (imm32 >> 12) as u16);
+            add_reg64_reg64_imm12(buf, dst, dst, (imm32 & 0xFFF) as u16);
         } else {
-            todo!("immediate additions with values greater than 12bits");
+            // too wide for the immediate forms, so move it into the scratch register and add that.
+            // The extended register form is used because it treats register 31 as SP, not ZR.
+            let tmp = AArch64GeneralReg::X15;
+            Self::mov_reg64_imm64(buf, tmp, imm32 as i64);
+            add_reg64_reg64_reg64_uxtx(buf, dst, src, tmp);
         }
     }
     #[inline(always)]
@@ -1873,12 +1881,20 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
         src: AArch64GeneralReg,
         imm32: i32,
     ) {
-        if imm32 < 0 {
-            Self::add_reg64_reg64_imm32(buf, dst, src, -imm32)
-        } else if imm32 < 0xFFF {
+        if imm32 < 0 && imm32 > -0x100_0000 {
+            Self::add_reg64_reg64_imm32(buf, dst, src, -imm32);
+        } else if (0..0xFFF).contains(&imm32) {
             sub_reg64_reg64_imm12(buf, dst, src, imm32 as u16);
+        } else if (0..0x100_0000).contains(&imm32) {
+            // subtract the upper 12 bits shifted into place, and then the lower 12 bits
+            sub_reg64_reg64_imm12_lsl12(buf, dst, src, (imm32 >> 12) as u16);
+            sub_reg64_reg64_imm12(buf, dst, dst, (imm32 & 0xFFF) as u16);
         } else {
-            todo!("immediate subtractions with values greater than 12bits");
+            // too wide for the immediate forms, so move it into the scratch register and subtract that.
+            // The extended register form is used because it treats register 31 as SP, not ZR.
+            let tmp = AArch64GeneralReg::X15;
+            Self::mov_reg64_imm64(buf, tmp, imm32 as i64);
+            sub_reg64_reg64_reg64_uxtx(buf, dst, src, tmp);
         }
     }
     #[inline(always)]
@@ -2139,7 +2155,10 @@ impl Assembler<AArch64GeneralReg, AArch64FloatReg> for AArch64Assembler {
             debug_assert!(offset % 8 == 0);
             ldr_freg64_reg64_imm12(buf, dst, src, (offset as u16) >> 3);
         } else {
-            todo!("base offsets over 32k for AArch64");
+            let tmp = AArch64GeneralReg::X15;
+            Self::mov_reg64_imm64(buf, tmp, offset as i64);
+            Self::add_reg64_reg64_reg64(buf, tmp, tmp, src);
+            ldr_freg64_reg64_imm12(buf, dst, tmp, 0);
         }
     }
 }
@@ -2341,6 +2360,52 @@ impl ArithmeticShifted {
     }
 }
 
+#[derive(PackedStruct)]
+#[packed_struct(endian = "msb")]
+pub struct ArithmeticExtended {
+    sf: bool,
+    op: bool, // add or subtract
+    s: bool,
+    fixed: Integer<u8, packed_bits::Bits<5>>, // = 0b01011,
+    opt: Integer<u8, packed_bits::Bits<2>>,   // = 0b00,
+    fixed2: bool,                             // = 0b1,
+    reg_m: Integer<u8, packed_bits::Bits<5>>,
+    option: Integer<u8, packed_bits::Bits<3>>,
+    imm3: Integer<u8, packed_bits::Bits<3>>,
+    reg_n: Integer<u8, packed_bits::Bits<5>>,
+    reg_d: Integer<u8, packed_bits::Bits<5>>,
+}
+
+impl Aarch64Bytes for ArithmeticExtended {}
+
+pub struct ArithmeticExtendedParams {
+    op: bool,
+    s: bool,
+    rm: AArch64GeneralReg,
+    rn: AArch64GeneralReg,
+    rd: AArch64GeneralReg,
+}
+
+impl ArithmeticExtended {
+    #[inline(always)]
+    fn new(ArithmeticExtendedParams { op, s, rm, rn, rd }: ArithmeticExtendedParams) -> Self {
+        Self {
+            sf: true,
+            op,
+            s,
+            fixed: 0b01011.into(),
+            opt: 0b00.into(),
+            fixed2: true,
+            reg_m: rm.id().into(),
+            // UXTX, i.e. the full 64 bits of Xm
+            option: 0b011.into(),
+            imm3: 0.into(),
+            reg_n: rn.id().into(),
+            reg_d: rd.id().into(),
+        }
+    }
+}
+
 // ARM manual section C1.2.4
 #[derive(Copy, Clone, PartialEq)]
 #[allow(dead_code)]
@@ -3236,6 +3301,26 @@ fn add_reg64_reg64_imm12(
     buf.extend(inst.bytes());
 }
 
+/// `ADD Xd, Xn, imm12, LSL #12` -> Add Xn and imm12 shifted left by 12 bits and place the result into Xd.
+#[inline(always)]
+fn add_reg64_reg64_imm12_lsl12(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src: AArch64GeneralReg,
+    imm12: u16,
+) {
+    let inst = ArithmeticImmediate::new(ArithmeticImmediateParams {
+        op: false,
+        s: false,
+        sh: true,
+        imm12,
+        rd: dst,
+        rn: src,
+    });
+
+    buf.extend(inst.bytes());
+}
+
 /// `ADD Xd, Xm, Xn` -> Add Xm and Xn and place the result into Xd.
 #[inline(always)]
 fn add_reg64_reg64_reg64(
@@ -3257,6 +3342,25 @@ fn add_reg64_reg64_reg64(
     buf.extend(inst.bytes());
 }
 
+/// `ADD Xd|SP, Xn|SP, Xm, UXTX` -> Add Xn and Xm and place the result into Xd.
+#[inline(always)]
+fn add_reg64_reg64_reg64_uxtx(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src1: AArch64GeneralReg,
+    src2: AArch64GeneralReg,
+) {
+    let inst = ArithmeticExtended::new(ArithmeticExtendedParams {
+        op: false,
+        s: false,
+        rm: src2,
+        rn: src1,
+        rd: dst,
+    });
+
+    buf.extend(inst.bytes());
+}
+
 /// `AND Xd, Xn, Xm` -> Bitwise AND Xn and Xm and place the result into Xd.
 #[inline(always)]
 fn and_reg64_reg64_reg64(
@@ -3807,6 +3911,26 @@ fn sub_reg64_reg64_imm12(
     buf.extend(inst.bytes());
 }
 
+/// `SUB Xd, Xn, imm12, LSL #12` -> Subtract imm12 shifted left by 12 bits from Xn and place the result into Xd.
+#[inline(always)]
+fn sub_reg64_reg64_imm12_lsl12(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src: AArch64GeneralReg,
+    imm12: u16,
+) {
+    let inst = ArithmeticImmediate::new(ArithmeticImmediateParams {
+        op: true,
+        s: false,
+        rd: dst,
+        rn: src,
+        imm12,
+        sh: true,
+    });
+
+    buf.extend(inst.bytes());
+}
+
 /// `SUB Xd, Xm, Xn` -> Subtract Xm and Xn and place the result into Xd.
 #[inline(always)]
 fn sub_reg64_reg64_reg64(
@@ -3828,6 +3952,25 @@ fn sub_reg64_reg64_reg64(
     buf.extend(inst.bytes());
 }
 
+/// `SUB Xd|SP, Xn|SP, Xm, UXTX` -> Subtract Xm from Xn and place the result into Xd.
+#[inline(always)]
+fn sub_reg64_reg64_reg64_uxtx(
+    buf: &mut Vec<'_, u8>,
+    dst: AArch64GeneralReg,
+    src1: AArch64GeneralReg,
+    src2: AArch64GeneralReg,
+) {
+    let inst = ArithmeticExtended::new(ArithmeticExtendedParams {
+        op: true,
+        s: false,
+        rm: src2,
+        rn: src1,
+        rd: dst,
+    });
+
+    buf.extend(inst.bytes());
+}
+
 /// `SUBS Xd, Xn, imm12` -> Subtract Xn and imm12 and place the result into Xd. Set condition flags.
 #[inline(always)]
 fn subs_reg64_reg64_imm12(
@@ -4531,6 +4674,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_reg64_reg64_reg64_uxtx() {
+        disassembler_test!(
+            add_reg64_reg64_reg64_uxtx,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, reg3: AArch64GeneralReg| {
+                if reg1 == AArch64GeneralReg::ZRSP || reg2 == AArch64GeneralReg::ZRSP {
+                    // With SP as an operand, the extension is shown as `lsl #0`, which is omitted.
+                    format!(
+                        "add {}, {}, {}",
+                        reg1.capstone_string(UsesSP),
+                        reg2.capstone_string(UsesSP),
+                        reg3.capstone_string(UsesZR)
+                    )
+                } else {
+                    format!(
+                        "add {}, {}, {}, uxtx",
+                        reg1.capstone_string(UsesSP),
+                        reg2.capstone_string(UsesSP),
+                        reg3.capstone_string(UsesZR)
+                    )
+                }
+            },
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_add_reg64_reg64_imm12() {
         disassembler_test!(
@@ -4547,6 +4718,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_reg64_reg64_imm32_wider_than_24_bits() {
+        // too wide for the immediate forms, so the immediate is moved into x15 first
+        disassembler_test!(
+            AArch64Assembler::add_reg64_reg64_imm32,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, imm: i32| {
+                let mov = if imm < 0 {
+                    "mov x15, #0xba99\nmovk x15, #0xfedc, lsl #16\nmovk x15, #0xffff, lsl #32\nmovk x15, #0xffff, lsl #48"
+                } else {
+                    "mov x15, #0x4567\nmovk x15, #0x123, lsl #16"
+                };
+                let extend = if reg1 == AArch64GeneralReg::ZRSP || reg2 == AArch64GeneralReg::ZRSP {
+                    ""
+                } else {
+                    ", uxtx"
+                };
+
+                format!(
+                    "{mov}\nadd {}, {}, x15{extend}",
+                    reg1.capstone_string(UsesSP),
+                    reg2.capstone_string(UsesSP),
+                )
+            },
+            [AArch64GeneralReg::X0, AArch64GeneralReg::ZRSP],
+            [AArch64GeneralReg::X1, AArch64GeneralReg::ZRSP],
+            [0x123_4567, -0x123_4567]
+        );
+    }
+
+    #[test]
+    fn test_add_reg64_reg64_imm12_lsl12() {
+        disassembler_test!(
+            add_reg64_reg64_imm12_lsl12,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, imm| format!(
+                "add {}, {}, #0x{:x}, lsl #12",
+                reg1.capstone_string(UsesSP),
+                reg2.capstone_string(UsesSP),
+                imm
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            [0x123]
+        );
+    }
+
     #[test]
     fn test_and_reg64_reg64_reg64() {
         disassembler_test!(
@@ -5081,6 +5297,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sub_reg64_reg64_reg64_uxtx() {
+        disassembler_test!(
+            sub_reg64_reg64_reg64_uxtx,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, reg3: AArch64GeneralReg| {
+                if reg1 == AArch64GeneralReg::ZRSP || reg2 == AArch64GeneralReg::ZRSP {
+                    // With SP as an operand, the extension is shown as `lsl #0`, which is omitted.
+                    format!(
+                        "sub {}, {}, {}",
+                        reg1.capstone_string(UsesSP),
+                        reg2.capstone_string(UsesSP),
+                        reg3.capstone_string(UsesZR)
+                    )
+                } else {
+                    format!(
+                        "sub {}, {}, {}, uxtx",
+                        reg1.capstone_string(UsesSP),
+                        reg2.capstone_string(UsesSP),
+                        reg3.capstone_string(UsesZR)
+                    )
+                }
+            },
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS
+        );
+    }
+
     #[test]
     fn test_sub_reg64_reg64_imm12() {
         disassembler_test!(
@@ -5097,6 +5341,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sub_reg64_reg64_imm32_wider_than_24_bits() {
+        // too wide for the immediate forms, so the immediate is moved into x15 first
+        disassembler_test!(
+            AArch64Assembler::sub_reg64_reg64_imm32,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, imm: i32| {
+                let mov = if imm < 0 {
+                    "mov x15, #0xba99\nmovk x15, #0xfedc, lsl #16\nmovk x15, #0xffff, lsl #32\nmovk x15, #0xffff, lsl #48"
+                } else {
+                    "mov x15, #0x4567\nmovk x15, #0x123, lsl #16"
+                };
+                let extend = if reg1 == AArch64GeneralReg::ZRSP || reg2 == AArch64GeneralReg::ZRSP {
+                    ""
+                } else {
+                    ", uxtx"
+                };
+
+                format!(
+                    "{mov}\nsub {}, {}, x15{extend}",
+                    reg1.capstone_string(UsesSP),
+                    reg2.capstone_string(UsesSP),
+                )
+            },
+            [AArch64GeneralReg::X0, AArch64GeneralReg::ZRSP],
+            [AArch64GeneralReg::X1, AArch64GeneralReg::ZRSP],
+            [0x123_4567, -0x123_4567]
+        );
+    }
+
+    #[test]
+    fn test_sub_reg64_reg64_imm12_lsl12() {
+        disassembler_test!(
+            sub_reg64_reg64_imm12_lsl12,
+            |reg1: AArch64GeneralReg, reg2: AArch64GeneralReg, imm| format!(
+                "sub {}, {}, #0x{:x}, lsl #12",
+                reg1.capstone_string(UsesSP),
+                reg2.capstone_string(UsesSP),
+                imm
+            ),
+            ALL_GENERAL_REGS,
+            ALL_GENERAL_REGS,
+            [0x123]
+        );
+    }
+
     #[test]
     fn test_sub_reg64_reg64_reg64() {
         disassembler_test!(
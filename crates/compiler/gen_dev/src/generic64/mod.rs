@@ -9,14 +9,15 @@ use roc_error_macros::{internal_error, todo_lambda_erasure};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::{CallerProc, CodeGenHelp, HelperOp};
 use roc_mono::ir::{
-    BranchInfo, HigherOrderLowLevel, JoinPointId, ListLiteralElement, Literal, Param, ProcLayout,
-    SelfRecursive, Stmt,
+    BranchInfo, HigherOrderLowLevel, JoinPointId, ListLiteralElement, Literal, LookupType, Param,
+    ProcLayout, SelfRecursive, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, LambdaName, Layout, LayoutIds, LayoutInterner, LayoutRepr, STLayoutInterner,
     TagIdIntType, UnionLayout,
 };
 use roc_mono::low_level::HigherOrder;
+use roc_region::all::Region;
 use roc_target::Target;
 use std::marker::PhantomData;
 
@@ -789,6 +790,11 @@ pub struct Backend64Bit<
     literal_map: MutMap<Symbol, (*const Literal<'a>, *const InLayout<'a>)>,
     join_map: MutMap<JoinPointId, Vec<'a, (u64, u64)>>,
 
+    // Debug info about the current procedure, only filled in when the object gets any.
+    argument_locations: Vec<'a, (Symbol, InLayout<'a>, i32)>,
+    statement_regions: MutMap<Symbol, Region>,
+    statement_locations: Vec<'a, (u64, Region)>,
+
     storage_manager: StorageManager<'a, 'r, GeneralReg, FloatReg, ASM, CC>,
 }
 
@@ -824,6 +830,9 @@ pub fn new_backend_64bit<
         free_map: MutMap::default(),
         literal_map: MutMap::default(),
         join_map: MutMap::default(),
+        argument_locations: bumpalo::vec![in env.arena],
+        statement_regions: MutMap::default(),
+        statement_locations: bumpalo::vec![in env.arena],
         storage_manager: storage::new_storage_manager(env, target),
     }
 }
@@ -906,6 +915,9 @@ impl<
         self.join_map.clear();
         self.free_map.clear();
         self.buf.clear();
+        self.argument_locations.clear();
+        self.statement_regions.clear();
+        self.statement_locations.clear();
         self.storage_manager.reset();
     }
 
@@ -947,6 +959,10 @@ impl<
         );
         let setup_offset = out.len();
 
+        for (offset, _) in self.statement_locations.iter_mut() {
+            *offset += setup_offset as u64;
+        }
+
         // Deal with jumps to the return address.
         let old_relocs = std::mem::replace(&mut self.relocs, bumpalo::vec![in self.env.arena]);
 
@@ -1040,6 +1056,36 @@ impl<
         );
     }
 
+    fn pin_arguments(&mut self, args: &'a [(InLayout<'a>, Symbol)]) {
+        for (layout, sym) in args {
+            if self.layout_interner.stack_size(*layout) == 0 {
+                continue;
+            }
+
+            self.storage_manager
+                .ensure_symbol_on_stack(&mut self.buf, sym);
+            let (base_offset, _) = self.storage_manager.stack_offset_and_size(sym);
+            self.argument_locations.push((*sym, *layout, base_offset));
+        }
+    }
+
+    fn argument_locations(&self) -> &[(Symbol, InLayout<'a>, i32)] {
+        &self.argument_locations
+    }
+
+    fn statement_regions_mut(&mut self) -> &mut MutMap<Symbol, Region> {
+        &mut self.statement_regions
+    }
+
+    fn mark_statement(&mut self, region: Region) {
+        self.statement_locations
+            .push((self.buf.len() as u64, region));
+    }
+
+    fn statement_locations(&self) -> &[(u64, Region)] {
+        &self.statement_locations
+    }
+
     /// Used for generating wrappers for malloc/realloc/free
     fn build_wrapped_jmp(&mut self) -> (&'a [u8], u64) {
         let mut out = bumpalo::vec![in self.env.arena];
@@ -1073,6 +1119,15 @@ impl<
         (out.into_bump_slice(), relocs)
     }
 
+    fn build_roc_dbg(&mut self) -> &'a [u8] {
+        let mut out = bumpalo::vec![in self.env.arena];
+
+        // tests only check the value of a dbg expression, so its message is dropped
+        ASM::ret(&mut out);
+
+        out.into_bump_slice()
+    }
+
     fn build_fn_pointer(&mut self, dst: &Symbol, fn_name: String) {
         let reg = self.storage_manager.claim_general_reg(&mut self.buf, dst);
 
@@ -1263,6 +1318,60 @@ impl<
         }
     }
 
+    fn build_expect(
+        &mut self,
+        condition: &Symbol,
+        region: Region,
+        lookups: &[Symbol],
+        variables: &[LookupType],
+    ) {
+        // Unlike the branches of a switch, the failure branch continues with the code after the
+        // expect. Everything is put on the stack first, so that both paths agree on where the
+        // symbols are once they meet again.
+        self.storage_manager.free_all_to_stack(&mut self.buf);
+
+        let mut base_storage = self.storage_manager.clone();
+        let base_literal_map = self.literal_map.clone();
+
+        let cond_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, condition);
+
+        // Jump past the failure branch if the condition holds.
+        // Since we don't know the offset yet, set it to 0 and overwrite later.
+        let jne_location = self.buf.len();
+        let start_offset =
+            ASM::jne_reg64_imm64_imm32(&mut self.buf, &mut self.storage_manager, cond_reg, 0, 0);
+
+        self.build_expect_failure(*condition, region, lookups, variables);
+
+        // Update important storage information to avoid overwrites.
+        base_storage.update_fn_call_stack_size(self.storage_manager.fn_call_stack_size());
+        base_storage
+            .used_callee_saved_regs
+            .extend(&self.storage_manager.used_callee_saved_regs);
+        let branch_stack_size = self.storage_manager.stack_size();
+
+        self.storage_manager = base_storage;
+        self.literal_map = base_literal_map;
+        self.storage_manager.update_stack_size(branch_stack_size);
+
+        // Overwrite the original jne with the correct offset.
+        let mut tmp = bumpalo::vec![in self.env.arena];
+        let end_offset = self.buf.len();
+        let jne_offset = end_offset - start_offset;
+        ASM::jne_reg64_imm64_imm32(
+            &mut tmp,
+            &mut self.storage_manager,
+            cond_reg,
+            0,
+            jne_offset as i32,
+        );
+        for (i, byte) in tmp.iter().enumerate() {
+            self.buf[jne_location + i] = *byte;
+        }
+    }
+
     fn build_join(
         &mut self,
         layout_ids: &mut LayoutIds<'a>,
@@ -3302,6 +3411,41 @@ impl<
         );
     }
 
+    fn build_unreachable(&mut self, sym: &Symbol, layout: &InLayout<'a>) {
+        match self.layout_interner.get_repr(*layout) {
+            single_register_integers!() | pointer_layouts!() => {
+                let reg = self.storage_manager.claim_general_reg(&mut self.buf, sym);
+                ASM::mov_reg64_imm64(&mut self.buf, reg, 0);
+            }
+            single_register_floats!() => {
+                let reg = self.storage_manager.claim_float_reg(&mut self.buf, sym);
+                ASM::mov_freg64_imm64(&mut self.buf, &mut self.relocs, reg, 0.0);
+            }
+            _ => {
+                let size = self.layout_interner.stack_size(*layout);
+                if size == 0 {
+                    self.storage_manager.no_data(sym);
+                    return;
+                }
+
+                let base_offset = self.storage_manager.claim_stack_area_layout(
+                    self.layout_interner,
+                    *sym,
+                    *layout,
+                );
+
+                // stack allocations are a multiple of 8 bytes wide
+                self.storage_manager
+                    .with_tmp_general_reg(&mut self.buf, |_, buf, reg| {
+                        ASM::mov_reg64_imm64(buf, reg, 0);
+                        for offset in (0..size as i32).step_by(8) {
+                            ASM::mov_base32_reg64(buf, base_offset + offset, reg);
+                        }
+                    });
+            }
+        }
+    }
+
     fn load_struct_at_index(
         &mut self,
         sym: &Symbol,
@@ -3609,6 +3753,21 @@ impl<
         );
     }
 
+    fn build_ptr_add_u64(&mut self, sym: Symbol, ptr: Symbol, amount: i32) {
+        let ptr_reg = self
+            .storage_manager
+            .load_to_general_reg(&mut self.buf, &ptr);
+
+        self.storage_manager
+            .with_tmp_general_reg(&mut self.buf, |_, buf, tmp| {
+                ASM::mov_reg64_mem64_offset32(buf, tmp, ptr_reg, 0);
+                ASM::add_reg64_reg64_imm32(buf, tmp, tmp, amount);
+                ASM::mov_mem64_offset32_reg64(buf, ptr_reg, 0, tmp);
+            });
+
+        self.storage_manager.no_data(&sym);
+    }
+
     fn build_ptr_clear_tag_id(&mut self, sym: Symbol, ptr: Symbol) {
         let buf = &mut self.buf;
 
@@ -6,25 +6,28 @@
 #![allow(clippy::large_enum_variant, clippy::upper_case_acronyms)]
 
 use std::collections::hash_map::Entry;
+use std::path::PathBuf;
 
 use bumpalo::{collections::Vec, Bump};
 use roc_builtins::bitcode::{self, FloatWidth, IntWidth};
 use roc_collections::all::{MutMap, MutSet};
-use roc_error_macros::{internal_error, todo_lambda_erasure};
+use roc_error_macros::{internal_error, todo_lambda_erasure, user_error};
 use roc_module::ident::ModuleName;
 use roc_module::low_level::{LowLevel, LowLevelWrapperType};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::code_gen_help::{CallerProc, CodeGenHelp};
 use roc_mono::ir::{
     BranchInfo, CallType, CrashTag, Expr, HigherOrderLowLevel, JoinPointId, ListLiteralElement,
-    Literal, ModifyRc, Param, Proc, ProcLayout, SelfRecursive, Stmt,
+    Literal, LookupType, ModifyRc, Param, Proc, ProcLayout, SelfRecursive, Stmt,
 };
 use roc_mono::layout::{
     Builtin, InLayout, LambdaName, Layout, LayoutIds, LayoutInterner, LayoutRepr, STLayoutInterner,
     TagIdIntType, UnionLayout,
 };
 use roc_mono::list_element_layout;
+use roc_region::all::Region;
 
+mod debug_info;
 mod generic64;
 mod object_builder;
 pub use object_builder::build_module;
@@ -35,6 +38,8 @@ mod run_roc;
 pub enum AssemblyBackendMode {
     /// Assumes primitives (roc_alloc, roc_panic, etc) are provided by the host
     Binary,
+    /// Like `Binary`, but failed `expect`s are reported to the `roc` process that runs the binary
+    BinaryWithExpect,
     /// Provides a testing implementation of primitives (roc_alloc, roc_panic, etc)
     Test,
     /// Provides a testing implementation of primitives (roc_alloc, roc_panic, etc)
@@ -45,6 +50,7 @@ impl AssemblyBackendMode {
     fn generate_allocators(self) -> bool {
         match self {
             AssemblyBackendMode::Binary => false,
+            AssemblyBackendMode::BinaryWithExpect => false,
             AssemblyBackendMode::Test => true,
             AssemblyBackendMode::Repl => true,
         }
@@ -53,6 +59,7 @@ impl AssemblyBackendMode {
     fn generate_roc_panic(self) -> bool {
         match self {
             AssemblyBackendMode::Binary => false,
+            AssemblyBackendMode::BinaryWithExpect => false,
             AssemblyBackendMode::Test => true,
             AssemblyBackendMode::Repl => true,
         }
@@ -61,10 +68,20 @@ impl AssemblyBackendMode {
     fn generate_roc_dbg(self) -> bool {
         match self {
             AssemblyBackendMode::Binary => false,
+            AssemblyBackendMode::BinaryWithExpect => false,
             AssemblyBackendMode::Test => true,
             AssemblyBackendMode::Repl => true,
         }
     }
+
+    fn runs_expects(self) -> bool {
+        match self {
+            AssemblyBackendMode::Binary => false,
+            AssemblyBackendMode::BinaryWithExpect => true,
+            AssemblyBackendMode::Test => false,
+            AssemblyBackendMode::Repl => false,
+        }
+    }
 }
 
 pub struct Env<'a> {
@@ -73,6 +90,9 @@ pub struct Env<'a> {
     pub exposed_to_host: MutSet<Symbol>,
     pub lazy_literals: bool,
     pub mode: AssemblyBackendMode,
+    /// The path and source of every module, when the object should carry DWARF debug info
+    /// that maps its code back to them
+    pub debug_sources: Option<&'a MutMap<ModuleId, (PathBuf, Box<str>)>>,
 }
 
 // These relocations likely will need a length.
@@ -275,8 +295,24 @@ impl<'a> LastSeenMap<'a> {
                 }
             }
 
-            Stmt::Dbg { .. } => todo!("dbg not implemented in the dev backend"),
-            Stmt::Expect { .. } => todo!("expect is not implemented in the dev backend"),
+            Stmt::Dbg {
+                symbol, remainder, ..
+            } => {
+                self.set_last_seen(*symbol, stmt);
+                self.scan_ast_help(remainder);
+            }
+            Stmt::Expect {
+                condition,
+                lookups,
+                remainder,
+                ..
+            } => {
+                self.set_last_seen(*condition, stmt);
+                for sym in *lookups {
+                    self.set_last_seen(*sym, stmt);
+                }
+                self.scan_ast_help(remainder);
+            }
 
             Stmt::Crash(msg, _crash_tag) => {
                 self.set_last_seen(*msg, stmt);
@@ -523,6 +559,22 @@ trait Backend<'a> {
     // The backend should track these args so it can use them as needed.
     fn load_args(&mut self, args: &'a [(InLayout<'a>, Symbol)], ret_layout: &InLayout<'a>);
 
+    /// pin_arguments keeps the args in the stack frame for as long as they are alive,
+    /// so that debug info can describe where they are.
+    fn pin_arguments(&mut self, args: &'a [(InLayout<'a>, Symbol)]);
+
+    /// The args pinned by pin_arguments, with their offsets from the frame pointer.
+    fn argument_locations(&self) -> &[(Symbol, InLayout<'a>, i32)];
+
+    /// The regions of the statements of the current procedure, for debug info, by the symbol each binds.
+    fn statement_regions_mut(&mut self) -> &mut MutMap<Symbol, Region>;
+
+    /// mark_statement notes that the code for the statement at `region` starts at the end of the buffer.
+    fn mark_statement(&mut self, region: Region);
+
+    /// The statements marked by mark_statement, with the offsets of their code in the finalized procedure.
+    fn statement_locations(&self) -> &[(u64, Region)];
+
     /// Used for generating wrappers for malloc/realloc/free
     fn build_wrapped_jmp(&mut self) -> (&'a [u8], u64);
 
@@ -531,6 +583,9 @@ trait Backend<'a> {
     fn build_roc_longjmp(&mut self) -> &'a [u8];
     fn build_roc_panic(&mut self) -> (&'a [u8], Vec<'a, Relocation>);
 
+    // use for roc_dbg
+    fn build_roc_dbg(&mut self) -> &'a [u8];
+
     /// build_proc creates a procedure and outputs it to the wrapped object writer.
     /// Returns the procedure bytes, its relocations, and the names of the refcounting functions it references.
     fn build_proc(
@@ -549,6 +604,11 @@ trait Backend<'a> {
 
         self.reset(proc_name, proc.is_self_recursive);
         self.load_args(proc.args, &proc.ret_layout);
+        if self.env().debug_sources.is_some() {
+            self.pin_arguments(proc.args);
+            self.statement_regions_mut()
+                .extend(proc.statement_regions.iter().copied());
+        }
         for (layout, sym) in proc.args {
             self.set_layout_map(*sym, layout);
         }
@@ -589,6 +649,9 @@ trait Backend<'a> {
     ) {
         match stmt {
             Stmt::Let(sym, expr, layout, following) => {
+                if let Some(region) = self.statement_regions_mut().get(sym).copied() {
+                    self.mark_statement(region);
+                }
                 self.build_expr(sym, expr, layout);
                 self.set_layout_map(*sym, layout);
                 self.free_symbols(stmt);
@@ -704,11 +767,61 @@ trait Backend<'a> {
                 self.build_jump(id, args, arg_layouts.into_bump_slice(), ret_layout);
                 self.free_symbols(stmt);
             }
+            Stmt::Dbg {
+                source_location,
+                source,
+                symbol,
+                variable: _,
+                remainder,
+            } => {
+                self.roc_dbg(source_location, source, *symbol);
+                self.free_symbols(stmt);
+                self.build_stmt(layout_ids, remainder, ret_layout);
+            }
+            Stmt::Expect {
+                condition,
+                region,
+                lookups,
+                variables,
+                remainder,
+            } => {
+                if self.env().mode.runs_expects() {
+                    self.load_literal_symbols(&[*condition]);
+                    self.build_expect(condition, *region, lookups, variables);
+                }
+                self.free_symbols(stmt);
+                self.build_stmt(layout_ids, remainder, ret_layout);
+            }
             Stmt::Crash(msg, crash_tag) => self.roc_panic(*msg, *crash_tag),
-            x => todo!("the statement, {:?}", x),
         }
     }
 
+    /// Adds one to the global counter of the region given by the literal `args`.
+    /// The counter is defined by the object builder the first time it is referenced.
+    fn build_coverage_hit(&mut self, sym: &Symbol, args: &[Symbol]) {
+        let mut literal_arg = |arg: &Symbol| match self.literal_map().get(arg) {
+            // This is safe for the same reason as in `load_literal_symbols`
+            Some((lit, _)) => match unsafe { **lit } {
+                Literal::Int(bytes) => i128::from_ne_bytes(bytes),
+                _ => internal_error!("CoverageHit: {:?} is not an integer literal", arg),
+            },
+            None => internal_error!("CoverageHit: {:?} is not a lazily loaded literal", arg),
+        };
+
+        let module_id = literal_arg(&args[0]) as u32;
+        let region = literal_arg(&args[1]) as u64;
+
+        let counter_ptr = self.debug_symbol("coverage_counter_ptr");
+
+        self.build_data_pointer(
+            &counter_ptr,
+            roc_mono::coverage::counter_name(module_id, region),
+        );
+        self.build_ptr_add_u64(*sym, counter_ptr, 1);
+
+        self.free_symbol(&counter_ptr);
+    }
+
     fn roc_panic(&mut self, msg: Symbol, crash_tag: CrashTag) {
         let error_message = self.debug_symbol("error_message");
 
@@ -741,6 +854,282 @@ trait Backend<'a> {
         self.free_symbol(&Symbol::DEV_TMP2);
     }
 
+    fn roc_dbg(&mut self, source_location: &'a str, source: &'a str, msg: Symbol) {
+        let location = self.debug_symbol("dbg_location");
+        let source_str = self.debug_symbol("dbg_source");
+        self.load_literal(&location, &Layout::STR, &Literal::Str(source_location));
+        self.load_literal(&source_str, &Layout::STR, &Literal::Str(source));
+        self.load_literal_symbols(&[msg]);
+
+        // like roc_panic, the roc_dbg defined by a platform expects each RocStr as a `*RocStr`.
+        // the strings are copied to the stack, and pointers to those copies are passed
+        let location_ptr = self.debug_symbol("dbg_location_ptr");
+        let msg_ptr = self.debug_symbol("dbg_msg_ptr");
+        let source_ptr = self.debug_symbol("dbg_source_ptr");
+        self.build_alloca(location_ptr, Some(location), Layout::STR);
+        self.build_alloca(msg_ptr, Some(msg), Layout::STR);
+        self.build_alloca(source_ptr, Some(source_str), Layout::STR);
+
+        self.build_fn_call(
+            &Symbol::DEV_TMP2,
+            String::from("roc_dbg"),
+            &[location_ptr, msg_ptr, source_ptr],
+            &[Layout::U64, Layout::U64, Layout::U64],
+            &Layout::UNIT,
+        );
+
+        for sym in [location, source_str, location_ptr, msg_ptr, source_ptr] {
+            self.free_symbol(&sym);
+        }
+        self.free_symbol(&Symbol::DEV_TMP2);
+    }
+
+    /// Copies the frame of a failed `expect` into the memory shared with the `roc` process that
+    /// runs the program, and lets that process know. The frame is laid out the way the LLVM
+    /// backend lays it out: a header with the region and module of the expect, the offset and
+    /// type variable of each looked up value, and then the values themselves.
+    fn build_expect_failure(
+        &mut self,
+        condition: Symbol,
+        region: Region,
+        lookups: &[Symbol],
+        variables: &[LookupType],
+    ) {
+        let shared_ptr = self.debug_symbol("shared_ptr");
+        self.build_fn_call(
+            &shared_ptr,
+            bitcode::UTILS_EXPECT_FAILED_START_SHARED_FILE.to_string(),
+            &[],
+            &[],
+            &Layout::U64,
+        );
+
+        // the shared memory starts with the number of frames and the offset of the first free byte
+        let mut offset = self.debug_symbol("expect_offset");
+        let offset_ptr = self.debug_symbol("expect_offset_ptr");
+        let ptr_size = self.debug_symbol("ptr_size");
+        self.load_literal_i64(&ptr_size, 8);
+        self.build_num_add_wrap(&offset_ptr, &shared_ptr, &ptr_size, &Layout::U64);
+        self.build_ptr_load(offset, offset_ptr, Layout::U64);
+
+        let module_id: u32 = unsafe { std::mem::transmute(condition.module_id()) };
+        let header = [region.start().offset, region.end().offset, module_id];
+        for value in header {
+            offset = self.build_expect_write_u32(shared_ptr, offset, value);
+        }
+
+        // each lookup gets a pointer-sized offset to its value and a u32 type variable
+        let entry_size = 8 + 4;
+        let table_size = self.debug_symbol("table_size");
+        self.load_literal_i64(&table_size, (lookups.len() * entry_size) as i64);
+        let mut entry = offset;
+        let values_start = self.debug_symbol("values_start");
+        self.build_num_add_wrap(&values_start, &entry, &table_size, &Layout::U64);
+        self.free_symbol(&table_size);
+        offset = values_start;
+
+        for (lookup, variable) in lookups.iter().zip(variables) {
+            let entry_ptr = self.debug_symbol("entry_ptr");
+            let ignored = self.debug_symbol("ignored");
+            self.build_num_add_wrap(&entry_ptr, &shared_ptr, &entry, &Layout::U64);
+            self.build_ptr_store(ignored, entry_ptr, offset, Layout::U64);
+            self.free_symbol(&ignored);
+            self.free_symbol(&entry_ptr);
+
+            let variable_offset = self.debug_symbol("variable_offset");
+            self.build_num_add_wrap(&variable_offset, &entry, &ptr_size, &Layout::U64);
+            self.free_symbol(&entry);
+            entry = self.build_expect_write_u32(shared_ptr, variable_offset, variable.index());
+
+            let layout = match self.layout_map().get(lookup) {
+                Some(layout) => *layout,
+                None => internal_error!("the lookup, {:?}, has no known layout", lookup),
+            };
+            offset = self.build_expect_clone(shared_ptr, offset, *lookup, layout);
+        }
+        self.free_symbol(&entry);
+
+        let ignored = self.debug_symbol("ignored");
+        self.build_ptr_add_u64(ignored, shared_ptr, 1);
+        self.free_symbol(&ignored);
+        self.build_ptr_store(ignored, offset_ptr, offset, Layout::U64);
+        self.free_symbol(&ignored);
+
+        self.build_fn_call(
+            &Symbol::DEV_TMP2,
+            bitcode::NOTIFY_PARENT_EXPECT.to_string(),
+            &[shared_ptr],
+            &[Layout::U64],
+            &Layout::UNIT,
+        );
+
+        for sym in [Symbol::DEV_TMP2, offset, offset_ptr, ptr_size, shared_ptr] {
+            self.free_symbol(&sym);
+        }
+    }
+
+    /// Writes `value` at `offset` into the expect frame at `shared_ptr`, returning the offset
+    /// after it. `offset` is freed.
+    fn build_expect_write_u32(&mut self, shared_ptr: Symbol, offset: Symbol, value: u32) -> Symbol {
+        let value_sym = self.debug_symbol("value");
+        let ptr = self.debug_symbol("ptr");
+        let ignored = self.debug_symbol("ignored");
+        let size = self.debug_symbol("size");
+        let next_offset = self.debug_symbol("next_offset");
+
+        self.load_literal(
+            &value_sym,
+            &Layout::U32,
+            &Literal::Int((value as u128).to_ne_bytes()),
+        );
+        self.build_num_add_wrap(&ptr, &shared_ptr, &offset, &Layout::U64);
+        self.build_ptr_store(ignored, ptr, value_sym, Layout::U32);
+
+        self.load_literal_i64(&size, 4);
+        self.build_num_add_wrap(&next_offset, &offset, &size, &Layout::U64);
+
+        for sym in [value_sym, ptr, ignored, size, offset] {
+            self.free_symbol(&sym);
+        }
+
+        next_offset
+    }
+
+    /// Copies `value` to `offset` into the expect frame at `shared_ptr`. The heap data of the
+    /// value goes right after it, and pointers to it are stored as offsets into the frame.
+    /// Returns the offset after everything that was copied. `offset` is freed.
+    fn build_expect_clone(
+        &mut self,
+        shared_ptr: Symbol,
+        offset: Symbol,
+        value: Symbol,
+        layout: InLayout<'a>,
+    ) -> Symbol {
+        self.load_literal_symbols(&[value]);
+
+        let stack_size = self.debug_symbol("stack_size");
+        let extra_offset = self.debug_symbol("extra_offset");
+        self.load_literal_i64(&stack_size, self.interner().stack_size(layout) as i64);
+        self.build_num_add_wrap(&extra_offset, &offset, &stack_size, &Layout::U64);
+        self.free_symbol(&stack_size);
+
+        let next_offset =
+            self.build_expect_clone_to(shared_ptr, offset, extra_offset, value, layout);
+
+        self.free_symbol(&offset);
+
+        next_offset
+    }
+
+    /// Copies `value` to `offset` into the expect frame at `shared_ptr`, and its heap data to
+    /// `extra_offset`, like the `build_clone` of the LLVM backend. Returns the offset after the
+    /// heap data. `extra_offset` is freed, unless it is returned.
+    fn build_expect_clone_to(
+        &mut self,
+        shared_ptr: Symbol,
+        offset: Symbol,
+        extra_offset: Symbol,
+        value: Symbol,
+        layout: InLayout<'a>,
+    ) -> Symbol {
+        match self.interner().get_repr(layout) {
+            // functions are never displayed, so nothing is written for them
+            LayoutRepr::LambdaSet(_) => extra_offset,
+            _ if self.interner().safe_to_memcpy(layout) => {
+                let ptr = self.debug_symbol("ptr");
+                let ignored = self.debug_symbol("ignored");
+                self.build_num_add_wrap(&ptr, &shared_ptr, &offset, &Layout::U64);
+                self.build_ptr_store(ignored, ptr, value, layout);
+                self.free_symbol(&ignored);
+                self.free_symbol(&ptr);
+
+                extra_offset
+            }
+            LayoutRepr::Builtin(Builtin::Str) => {
+                let next_offset = self.debug_symbol("next_offset");
+                self.build_fn_call(
+                    &next_offset,
+                    bitcode::STR_CLONE_TO.to_string(),
+                    &[value, shared_ptr, offset, extra_offset],
+                    &[Layout::STR, Layout::U64, Layout::U64, Layout::U64],
+                    &Layout::U64,
+                );
+                self.free_symbol(&extra_offset);
+
+                next_offset
+            }
+            LayoutRepr::Builtin(Builtin::List(element)) => {
+                let element_repr = self.interner().get_repr(element);
+                let next_offset = self.debug_symbol("next_offset");
+
+                if self.interner().safe_to_memcpy(element) {
+                    let element_width = self.debug_symbol("element_width");
+                    self.load_literal_i64(
+                        &element_width,
+                        self.interner().stack_size(element) as i64,
+                    );
+                    self.build_fn_call(
+                        &next_offset,
+                        bitcode::LIST_CLONE_TO.to_string(),
+                        &[value, element_width, shared_ptr, offset, extra_offset],
+                        &[layout, Layout::U64, Layout::U64, Layout::U64, Layout::U64],
+                        &Layout::U64,
+                    );
+                    self.free_symbol(&element_width);
+                } else if let LayoutRepr::Builtin(Builtin::Str) = element_repr {
+                    self.build_fn_call(
+                        &next_offset,
+                        bitcode::LIST_STR_CLONE_TO.to_string(),
+                        &[value, shared_ptr, offset, extra_offset],
+                        &[layout, Layout::U64, Layout::U64, Layout::U64],
+                        &Layout::U64,
+                    );
+                } else {
+                    unsupported_expect_lookup(self.interner(), layout)
+                }
+                self.free_symbol(&extra_offset);
+
+                next_offset
+            }
+            LayoutRepr::Struct(field_layouts) => {
+                // each field is written at its place in the struct, and its heap data after
+                // the heap data of the fields before it
+                let mut extra_offset = extra_offset;
+                let mut field_offset = 0;
+
+                for (index, field_layout) in field_layouts.iter().enumerate() {
+                    let field = self.debug_symbol("field");
+                    let field_position = self.debug_symbol("field_position");
+                    let field_offset_sym = self.debug_symbol("field_offset");
+                    self.load_struct_at_index(&field, &value, index as u64, field_layouts);
+                    self.load_literal_i64(&field_position, field_offset);
+                    self.build_num_add_wrap(
+                        &field_offset_sym,
+                        &offset,
+                        &field_position,
+                        &Layout::U64,
+                    );
+                    self.free_symbol(&field_position);
+
+                    extra_offset = self.build_expect_clone_to(
+                        shared_ptr,
+                        field_offset_sym,
+                        extra_offset,
+                        field,
+                        *field_layout,
+                    );
+
+                    self.free_symbol(&field_offset_sym);
+                    self.free_symbol(&field);
+                    field_offset += self.interner().stack_size(*field_layout) as i64;
+                }
+
+                extra_offset
+            }
+            _ => unsupported_expect_lookup(self.interner(), layout),
+        }
+    }
+
     // build_switch generates a instructions for a switch statement.
     fn build_switch(
         &mut self,
@@ -752,6 +1141,16 @@ trait Backend<'a> {
         ret_layout: &InLayout<'a>,
     );
 
+    /// build_expect generates the instructions that run `build_expect_failure` when the
+    /// condition of an `expect` does not hold.
+    fn build_expect(
+        &mut self,
+        condition: &Symbol,
+        region: Region,
+        lookups: &[Symbol],
+        variables: &[LookupType],
+    );
+
     // build_join generates a instructions for a join statement.
     fn build_join(
         &mut self,
@@ -1014,6 +1413,11 @@ trait Backend<'a> {
         arg_layouts: &[InLayout<'a>],
         ret_layout: &InLayout<'a>,
     ) {
+        if let LowLevel::CoverageHit = lowlevel {
+            // the arguments are only ever literals, which must not be loaded into registers
+            return self.build_coverage_hit(sym, args);
+        }
+
         // Now that the arguments are needed, load them if they are literals.
         self.load_literal_symbols(args);
         match lowlevel {
@@ -1848,6 +2252,7 @@ trait Backend<'a> {
             LowLevel::SetLongJmpBuffer => {
                 self.build_data_pointer(sym, String::from("setlongjmp_buffer"));
             }
+            LowLevel::CoverageHit => unreachable!("handled above"),
             LowLevel::DictPseudoSeed => self.build_fn_call(
                 sym,
                 bitcode::UTILS_DICT_PSEUDO_SEED.to_string(),
@@ -2216,7 +2621,144 @@ trait Backend<'a> {
                 self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout)
             }
 
-            x => todo!("low level, {:?}", x),
+            LowLevel::StrFromInt => {
+                let int_width = arg_layouts[0].try_int_width().unwrap();
+                let intrinsic = bitcode::STR_FROM_INT[int_width].to_string();
+                self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout)
+            }
+
+            LowLevel::StrFromFloat => {
+                let float_width = match self.interner().get_repr(arg_layouts[0]) {
+                    LayoutRepr::Builtin(Builtin::Float(float_width)) => float_width,
+                    x => internal_error!("StrFromFloat is not defined for {:?}", x),
+                };
+                let intrinsic = bitcode::STR_FROM_FLOAT[float_width].to_string();
+                self.build_fn_call(sym, intrinsic, args, arg_layouts, ret_layout)
+            }
+
+            LowLevel::StrReleaseExcessCapacity => self.build_fn_call(
+                sym,
+                bitcode::STR_RELEASE_EXCESS_CAPACITY.to_string(),
+                args,
+                arg_layouts,
+                ret_layout,
+            ),
+
+            LowLevel::ListGetCapacity => self.build_fn_call(
+                sym,
+                bitcode::LIST_CAPACITY.to_string(),
+                args,
+                arg_layouts,
+                ret_layout,
+            ),
+
+            LowLevel::ListIsUnique => self.build_fn_call(
+                sym,
+                bitcode::LIST_IS_UNIQUE.to_string(),
+                args,
+                arg_layouts,
+                ret_layout,
+            ),
+
+            LowLevel::NumToIntChecked => {
+                let arg_width = arg_layouts[0].try_int_width().unwrap();
+
+                // the return layout is { value: N, out_of_bounds: Bool }
+                let target_width = match self.interner().get_repr(*ret_layout) {
+                    LayoutRepr::Struct(&[value_layout, _]) => value_layout.try_int_width().unwrap(),
+                    x => internal_error!("NumToIntChecked has an unexpected return layout {:?}", x),
+                };
+
+                // a signed argument can also be below the minimum of the target
+                let intrinsic = if arg_width.is_signed() {
+                    &bitcode::NUM_INT_TO_INT_CHECKING_MAX_AND_MIN[target_width][arg_width]
+                } else {
+                    &bitcode::NUM_INT_TO_INT_CHECKING_MAX[target_width][arg_width]
+                };
+
+                self.build_fn_call(sym, intrinsic.to_string(), args, arg_layouts, ret_layout)
+            }
+
+            LowLevel::NumToFloatChecked => {
+                // the return layout is { value: F32 or F64, out_of_bounds: Bool }
+                let (value_layout, float_width) = match self.interner().get_repr(*ret_layout) {
+                    LayoutRepr::Struct(&[value_layout, _]) => match value_layout {
+                        Layout::F32 => (value_layout, FloatWidth::F32),
+                        Layout::F64 => (value_layout, FloatWidth::F64),
+                        x => internal_error!("NumToFloatChecked cannot convert to {:?}", x),
+                    },
+                    x => {
+                        internal_error!("NumToFloatChecked has an unexpected return layout {:?}", x)
+                    }
+                };
+
+                let value = self.debug_symbol("converted");
+                let is_infinite = self.debug_symbol("is_infinite");
+
+                match arg_layouts[0].try_to_int_width() {
+                    Some(int_width) => {
+                        self.build_int_to_float_cast(&value, &args[0], int_width, float_width);
+                    }
+                    None => {
+                        self.build_num_to_frac(&value, &args[0], &arg_layouts[0], &value_layout);
+                    }
+                }
+
+                // A number is out of bounds when it is finite but its conversion is not, like
+                // an F64 that is too big for an F32, or a U128 close to the maximum.
+                self.build_num_is_infinite(&is_infinite, &value, &value_layout);
+
+                let out_of_bounds = match arg_layouts[0] {
+                    Layout::F32 | Layout::F64 => {
+                        let was_finite = self.debug_symbol("was_finite");
+                        let out_of_bounds = self.debug_symbol("out_of_bounds");
+
+                        self.build_num_is_finite(&was_finite, &args[0], &arg_layouts[0]);
+                        self.build_int_bitwise_and(
+                            &out_of_bounds,
+                            &is_infinite,
+                            &was_finite,
+                            IntWidth::U8,
+                        );
+
+                        self.free_symbol(&was_finite);
+                        self.free_symbol(&is_infinite);
+
+                        out_of_bounds
+                    }
+                    _ => is_infinite,
+                };
+
+                let fields = self.env().arena.alloc([value, out_of_bounds]);
+                self.create_struct(sym, ret_layout, fields);
+
+                self.free_symbol(&out_of_bounds);
+                self.free_symbol(&value);
+            }
+
+            LowLevel::BenchBlackBox => {
+                // the dev backend does not optimize, so the value is always computed
+                self.create_struct(sym, ret_layout, &[]);
+            }
+
+            LowLevel::Unreachable => self.build_unreachable(sym, ret_layout),
+
+            LowLevel::ExpectDraw => unsupported_expect("property expects like `expect \\x -> ...`"),
+            LowLevel::ExpectSnapshot => unsupported_expect("`Inspect.snapshot`"),
+
+            LowLevel::ExpectArbitrary => {
+                unreachable!("ExpectArbitrary is replaced when specializing")
+            }
+
+            LowLevel::ListSortWith => {
+                unreachable!("these are higher order, and are handled elsewhere")
+            }
+
+            LowLevel::BoxExpr | LowLevel::UnboxExpr => {
+                unreachable!("The {:?} operation is turned into mono Expr", lowlevel)
+            }
+
+            LowLevel::Hash => unimplemented!(),
         }
     }
 
@@ -2683,6 +3225,10 @@ trait Backend<'a> {
 
     fn build_ptr_load(&mut self, sym: Symbol, ptr: Symbol, element_layout: InLayout<'a>);
 
+    /// Adds `amount` to the u64 that `ptr` points to, in place. Unlike `NumAdd`, this wraps
+    /// instead of panicking, so it can be used for counters. `sym` is the unit result.
+    fn build_ptr_add_u64(&mut self, sym: Symbol, ptr: Symbol, amount: i32);
+
     fn build_ptr_clear_tag_id(&mut self, sym: Symbol, ptr: Symbol);
 
     fn build_alloca(&mut self, sym: Symbol, value: Option<Symbol>, element_layout: InLayout<'a>);
@@ -2745,6 +3291,10 @@ trait Backend<'a> {
     /// create_struct creates a struct with the elements specified loaded into it as data.
     fn create_struct(&mut self, sym: &Symbol, layout: &InLayout<'a>, fields: &'a [Symbol]);
 
+    /// build_unreachable defines `sym` for an `Unreachable` low level. The value is never used,
+    /// so it is zeroed like in the other backends.
+    fn build_unreachable(&mut self, sym: &Symbol, layout: &InLayout<'a>);
+
     /// load_struct_at_index loads into `sym` the value at `index` in `structure`.
     fn load_struct_at_index(
         &mut self,
@@ -2861,3 +3411,20 @@ trait Backend<'a> {
         *self.last_seen_map() = LastSeenMap::scan_ast(stmt);
     }
 }
+
+/// Property expects need the choices that `roc test` makes, and snapshots need it to compare
+/// them with the stored ones, which only the LLVM backend has a way to do so far.
+fn unsupported_expect(what: &str) -> ! {
+    user_error!(
+        "The dev backend can't run {what} yet.\n\nRun the expects without `--dev` to use the LLVM backend instead."
+    )
+}
+
+/// The dev backend can't yet copy values of this layout into the frame of a failed `expect`.
+/// That is a missing feature rather than a bug in the program, so it's reported to the user.
+fn unsupported_expect_lookup<'a>(interner: &STLayoutInterner<'a>, layout: InLayout<'a>) -> ! {
+    user_error!(
+        "The dev backend can't show values like this one in the report of a failed `expect` yet: {}\n\nRun the expects without `--dev` to use the LLVM backend instead.",
+        interner.dbg(layout)
+    )
+}
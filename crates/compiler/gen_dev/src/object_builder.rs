@@ -1,3 +1,4 @@
+use crate::debug_info::{add_debug_sections, debug_type, DebugArgument, DebugProc};
 use crate::generic64::{aarch64, new_backend_64bit, x86_64};
 use crate::{AssemblyBackendMode, Backend, Env, Relocation};
 use bumpalo::collections::Vec;
@@ -319,7 +320,7 @@ fn generate_roc_panic<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object)
     }
 }
 
-fn generate_roc_dbg<'a, B: Backend<'a>>(_backend: &mut B, output: &mut Object) {
+fn generate_roc_dbg<'a, B: Backend<'a>>(backend: &mut B, output: &mut Object) {
     let text_section = output.section_id(StandardSection::Text);
     let proc_symbol = Symbol {
         name: "roc_dbg".as_bytes().to_vec(),
@@ -331,9 +332,10 @@ fn generate_roc_dbg<'a, B: Backend<'a>>(_backend: &mut B, output: &mut Object) {
         section: SymbolSection::Section(text_section),
         flags: SymbolFlags::None,
     };
-    let _proc_id = output.add_symbol(proc_symbol);
-    // TODO: Actually generate an impl instead of just an empty symbol.
-    // At a minimum, it should just be a ret.
+    let proc_id = output.add_symbol(proc_symbol);
+    let proc_data = backend.build_roc_dbg();
+
+    output.add_symbol_data(proc_id, text_section, proc_data, 16);
 }
 
 fn generate_wrapper<'a, B: Backend<'a>>(
@@ -587,7 +589,9 @@ fn build_object<'a, B: Backend<'a>>(
                         Exposed::TestMain,
                     );
                 }
-                AssemblyBackendMode::Binary => { /* do nothing */ }
+                AssemblyBackendMode::Binary | AssemblyBackendMode::BinaryWithExpect => {
+                    /* do nothing */
+                }
             }
 
             build_proc_symbol(
@@ -624,11 +628,13 @@ fn build_object<'a, B: Backend<'a>>(
 
     // Build procedures from user code
     let mut relocations = bumpalo::vec![in arena];
+    let mut debug_procs = std::vec::Vec::new();
     for (fn_name, section_id, proc_id, proc) in procs {
         build_proc(
             &mut output,
             &mut backend,
             &mut relocations,
+            &mut debug_procs,
             &mut layout_ids,
             data_section,
             fn_name,
@@ -720,6 +726,7 @@ fn build_object<'a, B: Backend<'a>>(
             &mut output,
             &mut backend,
             &mut relocations,
+            &mut debug_procs,
             &mut layout_ids,
             data_section,
             fn_name,
@@ -736,6 +743,12 @@ fn build_object<'a, B: Backend<'a>>(
             Err(e) => internal_error!("{:?}", e),
         }
     }
+
+    if let Some(sources) = backend.env().debug_sources {
+        let home = backend.env().module_id;
+        add_debug_sections(&mut output, sources, home, backend.interns(), &debug_procs);
+    }
+
     output
 }
 
@@ -778,6 +791,8 @@ fn build_exposed_proc<'a, B: Backend<'a>>(backend: &mut B, proc: &Proc<'a>) -> P
         ret_layout: proc.ret_layout,
         is_self_recursive: roc_mono::ir::SelfRecursive::NotSelfRecursive,
         is_erased: proc.is_erased,
+        region: proc.region,
+        statement_regions: proc.statement_regions,
     }
 }
 
@@ -859,6 +874,8 @@ fn build_exposed_generic_proc<'a, B: Backend<'a>>(backend: &mut B, proc: &Proc<'
         ret_layout: roc_mono::layout::Layout::UNIT,
         is_self_recursive: roc_mono::ir::SelfRecursive::NotSelfRecursive,
         is_erased: proc.is_erased,
+        region: proc.region,
+        statement_regions: proc.statement_regions,
     }
 }
 
@@ -927,6 +944,7 @@ fn build_proc<'a, B: Backend<'a>>(
     output: &mut Object,
     backend: &mut B,
     relocations: &mut Vec<'a, (SectionId, object::write::Relocation)>,
+    debug_procs: &mut std::vec::Vec<DebugProc>,
     layout_ids: &mut LayoutIds<'a>,
     data_section: SectionId,
     fn_name: String,
@@ -936,8 +954,18 @@ fn build_proc<'a, B: Backend<'a>>(
 ) {
     let mut local_data_index = 0;
     let target = backend.target();
+    let symbol = proc.name.name();
+    // Closures lifted out of a def have no region of their own, so they start at their
+    // first statement instead
+    let region = proc.region.or_else(|| {
+        proc.statement_regions
+            .iter()
+            .map(|(_, region)| *region)
+            .min_by_key(|region| region.start())
+    });
     let (proc_data, relocs, rc_proc_names) = backend.build_proc(proc, layout_ids);
     let proc_offset = output.add_symbol_data(proc_id, section_id, &proc_data, 16);
+
     for reloc in relocs.iter() {
         let elfreloc = match reloc {
             Relocation::LocalData { offset, data } => {
@@ -969,6 +997,7 @@ fn build_proc<'a, B: Backend<'a>>(
             }
             Relocation::LinkedData { offset, name } => {
                 add_undefined_rc_proc(output, name, &rc_proc_names);
+                add_coverage_counter(output, name);
 
                 if let Some(sym_id) = output.symbol_id(name.as_bytes()) {
                     if cfg!(all(target_arch = "aarch64", target_os = "linux")) {
@@ -1067,6 +1096,58 @@ fn build_proc<'a, B: Backend<'a>>(
         };
         relocations.push((section_id, elfreloc));
     }
+
+    // the proc's code borrows the backend, which the debug info is read from
+    let proc_size = proc_data.len() as u64;
+    drop((proc_data, relocs, rc_proc_names));
+
+    if let (Some(region), Some(_)) = (region, backend.env().debug_sources) {
+        let arguments = backend
+            .argument_locations()
+            .iter()
+            .filter_map(|(symbol, layout, frame_offset)| {
+                Some(DebugArgument {
+                    symbol: *symbol,
+                    ty: debug_type(backend.interner(), *layout)?,
+                    frame_offset: *frame_offset,
+                })
+            })
+            .collect();
+
+        debug_procs.push(DebugProc {
+            symbol,
+            region,
+            proc_id,
+            size: proc_size,
+            statements: backend.statement_locations().to_vec(),
+            arguments,
+        });
+    }
+}
+
+/// Coverage counters are defined the first time a proc counts a run of their region.
+fn add_coverage_counter(output: &mut Object<'_>, name: &str) {
+    if !name.starts_with("roc__coverage_") || output.symbol_id(name.as_bytes()).is_some() {
+        return;
+    }
+
+    let data_section = output.section_id(StandardSection::Data);
+    const SIZE: usize = core::mem::size_of::<u64>();
+
+    // the test runner looks the counters up by name, so they have to be exported
+    let symbol = Symbol {
+        name: name.as_bytes().to_vec(),
+        value: 0,
+        size: SIZE as u64,
+        kind: SymbolKind::Data,
+        scope: SymbolScope::Dynamic,
+        weak: false,
+        section: SymbolSection::Section(data_section),
+        flags: SymbolFlags::None,
+    };
+
+    let symbol_id = output.add_symbol(symbol);
+    output.add_symbol_data(symbol_id, data_section, &[0x00; SIZE], 8);
 }
 
 fn add_undefined_rc_proc(
@@ -5,6 +5,7 @@ use crate::llvm::build_list::{
 use crate::llvm::convert::{
     argument_type_from_layout, basic_type_from_builtin, basic_type_from_layout, zig_str_type,
 };
+use crate::llvm::debug_info::{self, DebugSources};
 use crate::llvm::expect::{clone_to_shared_memory, SharedMemoryPointer};
 use crate::llvm::memcpy::build_memcpy;
 use crate::llvm::refcounting::{
@@ -19,7 +20,7 @@ use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::debug_info::{
-    AsDIScope, DICompileUnit, DIFlagsConstants, DISubprogram, DebugInfoBuilder,
+    AsDIScope, DICompileUnit, DIFile, DIFlagsConstants, DISubprogram, DebugInfoBuilder,
 };
 use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::{Linkage, Module};
@@ -728,6 +729,19 @@ impl LlvmBackendMode {
             LlvmBackendMode::CliTest => true,
         }
     }
+
+    /// Only `roc test` compares `Inspect.snapshot`s with the stored ones. Anywhere else they
+    /// always match.
+    pub(crate) fn compares_snapshots(self) -> bool {
+        match self {
+            LlvmBackendMode::Binary => false,
+            LlvmBackendMode::BinaryWithExpect => false,
+            LlvmBackendMode::BinaryGlue => false,
+            LlvmBackendMode::GenTest => false,
+            LlvmBackendMode::WasmGenTest => false,
+            LlvmBackendMode::CliTest => true,
+        }
+    }
 }
 
 pub struct Env<'a, 'ctx, 'env> {
@@ -740,6 +754,8 @@ pub struct Env<'a, 'ctx, 'env> {
     pub interns: Interns,
     pub target: Target,
     pub mode: LlvmBackendMode,
+    /// The sources that debug info maps code back to, when building with `--debug`
+    pub debug_sources: Option<&'env DebugSources<'ctx>>,
     pub exposed_to_host: MutSet<Symbol>,
 }
 
@@ -974,8 +990,16 @@ impl<'a, 'ctx, 'env> Env<'a, 'ctx, 'env> {
     }
 
     pub fn new_subprogram(&self, function_name: &str) -> DISubprogram<'ctx> {
+        self.new_subprogram_at(function_name, self.compile_unit.get_file(), 0)
+    }
+
+    pub fn new_subprogram_at(
+        &self,
+        function_name: &str,
+        file: DIFile<'ctx>,
+        line_no: u32,
+    ) -> DISubprogram<'ctx> {
         let dibuilder = self.dibuilder;
-        let compile_unit = self.compile_unit;
 
         let ditype = dibuilder
             .create_basic_type(
@@ -987,22 +1011,22 @@ impl<'a, 'ctx, 'env> Env<'a, 'ctx, 'env> {
             .unwrap();
 
         let subroutine_type = dibuilder.create_subroutine_type(
-            compile_unit.get_file(),
+            file,
             /* return type */ Some(ditype.as_type()),
             /* parameter types */ &[],
             inkwell::debug_info::DIFlags::PUBLIC,
         );
 
         dibuilder.create_function(
-            /* scope */ compile_unit.get_file().as_debug_info_scope(),
+            /* scope */ file.as_debug_info_scope(),
             /* func name */ function_name,
             /* linkage_name */ None,
-            /* file */ compile_unit.get_file(),
-            /* line_no */ 0,
+            /* file */ file,
+            /* line_no */ line_no,
             /* DIType */ subroutine_type,
             /* is_local_to_unit */ true,
             /* is_definition */ true,
-            /* scope_line */ 0,
+            /* scope_line */ line_no,
             /* flags */ inkwell::debug_info::DIFlags::PUBLIC,
             /* is_optimized */ false,
         )
@@ -3077,6 +3101,10 @@ pub(crate) fn build_exp_stmt<'a, 'ctx>(
                     LayoutRepr::RecursivePointer(_)
                 ));
 
+                if let Some(location) = scope.statement_location(symbol) {
+                    env.builder.set_current_debug_location(location);
+                }
+
                 let val = build_exp_expr(
                     env,
                     layout_interner,
@@ -5751,7 +5779,8 @@ fn build_proc_header<'a, 'ctx>(
         Linkage::Internal,
     );
 
-    let subprogram = env.new_subprogram(&fn_name);
+    let subprogram = debug_info::proc_subprogram(env, proc, &fn_name)
+        .unwrap_or_else(|| env.new_subprogram(&fn_name));
     fn_val.set_subprogram(subprogram);
 
     debug_info_init!(env, fn_val);
@@ -6048,6 +6077,15 @@ fn build_proc<'a, 'ctx>(
 
     debug_info_init!(env, fn_val);
 
+    let location = debug_info::proc_location(env, proc, fn_val);
+    if let Some((_, loc)) = location {
+        builder.set_current_debug_location(loc);
+    }
+
+    for (symbol, statement_location) in debug_info::statement_locations(env, proc, fn_val) {
+        scope.insert_statement_location(symbol, statement_location);
+    }
+
     // Add args to scope
     for (arg_val, (layout, arg_symbol)) in fn_val.get_param_iter().zip(args) {
         arg_val.set_name(arg_symbol.as_str(&env.interns));
@@ -6070,6 +6108,10 @@ fn build_proc<'a, 'ctx>(
             builder.new_build_return(Some(&body));
         }
     }
+
+    if let Some(location) = location {
+        debug_info::describe_arguments(env, layout_interner, proc, fn_val, entry, location);
+    }
 }
 
 pub fn verify_fn(fn_val: FunctionValue<'_>) {
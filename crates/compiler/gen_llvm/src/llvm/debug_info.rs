@@ -0,0 +1,263 @@
+//! Debug info that maps the generated code back to the `.roc` source, for `roc build --debug`.
+//!
+//! A proc starts at the line of the top-level def it was specialized from, each statement that
+//! mono IR recorded a region for moves the location to that statement's line, and the proc's
+//! arguments are described at its entry.
+use std::path::PathBuf;
+
+use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::{
+    AsDIScope, DIFile, DIFlags, DIFlagsConstants, DILocation, DISubprogram, DIType,
+    DebugInfoBuilder,
+};
+use inkwell::values::FunctionValue;
+use roc_builtins::bitcode::{FloatWidth, IntWidth};
+use roc_collections::all::MutMap;
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_mono::ir::Proc;
+use roc_mono::layout::{Builtin, InLayout, LayoutInterner, LayoutRepr, STLayoutInterner};
+use roc_region::all::{LineInfo, Region};
+
+use super::build::Env;
+
+// https://dwarfstd.org/doc/DWARF5.pdf section 7.8
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x08;
+
+/// The source file of every module, so code can be attributed to lines in it.
+pub struct DebugSources<'ctx> {
+    files: MutMap<ModuleId, (DIFile<'ctx>, LineInfo)>,
+}
+
+impl<'ctx> DebugSources<'ctx> {
+    pub fn new(
+        dibuilder: &DebugInfoBuilder<'ctx>,
+        sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    ) -> Self {
+        let files = sources
+            .iter()
+            .map(|(module_id, (path, src))| {
+                let filename = path.file_name().unwrap_or_default().to_string_lossy();
+                let directory = match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy(),
+                    _ => ".".into(),
+                };
+                let file = dibuilder.create_file(&filename, &directory);
+
+                (*module_id, (file, LineInfo::new(src)))
+            })
+            .collect();
+
+        Self { files }
+    }
+
+    /// The file of the module that defines `symbol`, along with the 1-based line and column
+    /// that `region` starts at in it
+    fn location(&self, symbol: Symbol, region: Region) -> Option<(DIFile<'ctx>, u32, u32)> {
+        let (file, line_info) = self.files.get(&symbol.module_id())?;
+        let start = line_info.convert_pos(region.start());
+
+        Some((*file, start.line + 1, start.column + 1))
+    }
+}
+
+/// The subprogram of a proc whose def is known, placed at the line of that def
+pub(crate) fn proc_subprogram<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    proc: &Proc<'_>,
+    function_name: &str,
+) -> Option<DISubprogram<'ctx>> {
+    let (file, line, _) = env
+        .debug_sources?
+        .location(proc.name.name(), proc.region?)?;
+
+    Some(env.new_subprogram_at(function_name, file, line))
+}
+
+/// The file and location of the def of `proc`, in the scope of its function
+pub(crate) fn proc_location<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    proc: &Proc<'_>,
+    fn_val: FunctionValue<'ctx>,
+) -> Option<(DIFile<'ctx>, DILocation<'ctx>)> {
+    let (file, line, column) = env
+        .debug_sources?
+        .location(proc.name.name(), proc.region?)?;
+    let subprogram = fn_val.get_subprogram()?;
+
+    let location = env.dibuilder.create_debug_location(
+        env.context,
+        line,
+        column,
+        subprogram.as_debug_info_scope(),
+        /* inlined_at */ None,
+    );
+
+    Some((file, location))
+}
+
+/// The location of every statement of `proc` that mono IR recorded a region for, keyed by the
+/// symbol the statement binds
+pub(crate) fn statement_locations<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    proc: &Proc<'_>,
+    fn_val: FunctionValue<'ctx>,
+) -> Vec<(Symbol, DILocation<'ctx>)> {
+    let (Some(debug_sources), Some(subprogram)) = (env.debug_sources, fn_val.get_subprogram())
+    else {
+        return Vec::new();
+    };
+
+    proc.statement_regions
+        .iter()
+        .filter_map(|(symbol, region)| {
+            let (_, line, column) = debug_sources.location(proc.name.name(), *region)?;
+            let location = env.dibuilder.create_debug_location(
+                env.context,
+                line,
+                column,
+                subprogram.as_debug_info_scope(),
+                /* inlined_at */ None,
+            );
+
+            Some((*symbol, location))
+        })
+        .collect()
+}
+
+/// Describes the arguments of `proc` at the start of `entry`
+
+pub(crate) fn describe_arguments<'a, 'ctx>(
+    env: &Env<'a, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'a>,
+    proc: &Proc<'a>,
+    fn_val: FunctionValue<'ctx>,
+    entry: BasicBlock<'ctx>,
+    (file, location): (DIFile<'ctx>, DILocation<'ctx>),
+) {
+    let (Some(subprogram), Some(first_instruction)) =
+        (fn_val.get_subprogram(), entry.get_first_instruction())
+    else {
+        return;
+    };
+
+    let params = fn_val.get_param_iter().zip(proc.args.iter()).enumerate();
+
+    for (index, (arg_val, (layout, arg_symbol))) in params {
+        let Some(ditype) = argument_type(env, layout_interner, *layout, file) else {
+            continue;
+        };
+
+        let variable = env.dibuilder.create_parameter_variable(
+            subprogram.as_debug_info_scope(),
+            arg_symbol.as_str(&env.interns),
+            index as u32 + 1,
+            file,
+            location.get_line(),
+            ditype,
+            /* always_preserve */ true,
+            DIFlags::ZERO,
+        );
+
+        env.dibuilder
+            .insert_dbg_value_before(arg_val, variable, None, location, first_instruction);
+    }
+}
+
+/// Numbers and bools are described as base types; every other layout is described as an opaque
+/// struct of its size, so a debugger can still show its bytes
+fn argument_type<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'_>,
+    layout: InLayout<'_>,
+    file: DIFile<'ctx>,
+) -> Option<DIType<'ctx>> {
+    let (name, size_in_bits, encoding) = match layout_interner.get_repr(layout) {
+        LayoutRepr::Builtin(Builtin::Int(width)) => {
+            let encoding = if width.is_signed() {
+                DW_ATE_SIGNED
+            } else {
+                DW_ATE_UNSIGNED
+            };
+
+            (int_type_name(width), width.stack_size() * 8, encoding)
+        }
+        LayoutRepr::Builtin(Builtin::Float(width)) => {
+            let name = match width {
+                FloatWidth::F32 => "F32",
+                FloatWidth::F64 => "F64",
+            };
+
+            (name, width.stack_size() * 8, DW_ATE_FLOAT)
+        }
+        LayoutRepr::Builtin(Builtin::Bool) => ("Bool", 8, DW_ATE_BOOLEAN),
+        repr => return opaque_type(env, layout_interner, layout, repr, file),
+    };
+
+    let basic_type = env
+        .dibuilder
+        .create_basic_type(name, size_in_bits as u64, encoding, DIFlags::PUBLIC)
+        .ok()?;
+
+    Some(basic_type.as_type())
+}
+
+fn opaque_type<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    layout_interner: &STLayoutInterner<'_>,
+    layout: InLayout<'_>,
+    repr: LayoutRepr<'_>,
+    file: DIFile<'ctx>,
+) -> Option<DIType<'ctx>> {
+    let size_in_bits = layout_interner.stack_size(layout) as u64 * 8;
+    if size_in_bits == 0 {
+        return None;
+    }
+
+    let name = match repr {
+        LayoutRepr::Builtin(Builtin::Decimal) => "Dec",
+        LayoutRepr::Builtin(Builtin::Str) => "Str",
+        LayoutRepr::Builtin(Builtin::List(_)) => "List",
+        LayoutRepr::Struct(_) => "Struct",
+        LayoutRepr::Union(_) => "Tag",
+        LayoutRepr::Ptr(_) | LayoutRepr::RecursivePointer(_) => "Ptr",
+        LayoutRepr::LambdaSet(_) => "Closure",
+        LayoutRepr::FunctionPointer(_) => "Function",
+        LayoutRepr::Erased(_) => "Erased",
+        LayoutRepr::Builtin(Builtin::Int(_) | Builtin::Float(_) | Builtin::Bool) => return None,
+    };
+
+    let struct_type = env.dibuilder.create_struct_type(
+        /* scope */ file.as_debug_info_scope(),
+        /* name */ name,
+        /* file */ file,
+        /* line_number */ 0,
+        /* size_in_bits */ size_in_bits,
+        /* align_in_bits */ layout_interner.alignment_bytes(layout) * 8,
+        /* flags */ DIFlags::PUBLIC,
+        /* derived_from */ None,
+        /* elements */ &[],
+        /* runtime_language */ 0,
+        /* vtable_holder */ None,
+        /* unique_id */ "",
+    );
+
+    Some(struct_type.as_type())
+}
+
+fn int_type_name(width: IntWidth) -> &'static str {
+    match width {
+        IntWidth::U8 => "U8",
+        IntWidth::U16 => "U16",
+        IntWidth::U32 => "U32",
+        IntWidth::U64 => "U64",
+        IntWidth::U128 => "U128",
+        IntWidth::I8 => "I8",
+        IntWidth::I16 => "I16",
+        IntWidth::I32 => "I32",
+        IntWidth::I64 => "I64",
+        IntWidth::I128 => "I128",
+    }
+}
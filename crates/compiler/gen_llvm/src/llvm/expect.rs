@@ -25,6 +25,13 @@ use super::convert::struct_type_from_union_layout;
 use super::scope::Scope;
 use super::struct_::RocStruct;
 
+/// The global holding the name of the last snapshot an expect checked, as a `Str`
+pub const SNAPSHOT_NAME: &str = "roc__expect_snapshot_name";
+/// The global holding the rendered value of the last snapshot an expect checked, as a `Str`
+pub const SNAPSHOT_VALUE: &str = "roc__expect_snapshot_value";
+/// The global counting how many snapshots the current expect checked
+pub const SNAPSHOT_COUNT: &str = "roc__expect_snapshot_count";
+
 pub(crate) struct SharedMemoryPointer<'ctx>(PointerValue<'ctx>);
 
 impl<'ctx> SharedMemoryPointer<'ctx> {
@@ -139,6 +146,46 @@ fn offset_add<'ctx>(
     builder.new_build_int_add(current, intval, "offset_add")
 }
 
+/// Puts the name and value of a snapshot expectation in globals, for `roc test` to compare with
+/// the stored snapshot once the expect has run. Both strings are owned by this call and are never
+/// decremented, so they are still alive by then.
+pub(crate) fn build_expect_snapshot<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    name: BasicValueEnum<'ctx>,
+    value: BasicValueEnum<'ctx>,
+) -> BasicValueEnum<'ctx> {
+    let global = |global_name: &str, basic_type: BasicTypeEnum<'ctx>| {
+        env.module
+            .get_global(global_name)
+            .unwrap_or_else(|| {
+                let global = env.module.add_global(basic_type, None, global_name);
+                global.set_initializer(&basic_type.const_zero());
+                global
+            })
+            .as_pointer_value()
+    };
+
+    let i64_type = env.context.i64_type();
+
+    let name_ptr = global(SNAPSHOT_NAME, name.get_type());
+    let value_ptr = global(SNAPSHOT_VALUE, value.get_type());
+    let count_ptr = global(SNAPSHOT_COUNT, i64_type.into());
+
+    env.builder.new_build_store(name_ptr, name);
+    env.builder.new_build_store(value_ptr, value);
+
+    let count = env
+        .builder
+        .new_build_load(i64_type, count_ptr, "snapshot_count")
+        .into_int_value();
+    let incremented =
+        env.builder
+            .new_build_int_add(count, i64_type.const_int(1, false), "snapshot_count");
+    env.builder.new_build_store(count_ptr, incremented);
+
+    env.context.bool_type().const_int(1, false).into()
+}
+
 pub(crate) fn notify_parent_expect(env: &Env, shared_memory: &SharedMemoryPointer) {
     let func = env
         .module
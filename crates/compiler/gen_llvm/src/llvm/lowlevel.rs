@@ -1,10 +1,10 @@
 use inkwell::{
     attributes::{Attribute, AttributeLoc},
     module::Linkage,
-    types::IntType,
+    types::{BasicTypeEnum, IntType},
     values::{
         BasicValue, BasicValueEnum, FloatValue, FunctionValue, InstructionOpcode, IntValue,
-        StructValue,
+        PointerValue, StructValue,
     },
     AddressSpace, IntPredicate,
 };
@@ -45,6 +45,7 @@ use crate::llvm::{
         self, argument_type_from_layout, basic_type_from_layout, zig_num_parse_result_type,
         zig_to_int_checked_result_type,
     },
+    expect::build_expect_snapshot,
     intrinsics::{
         // These instrinsics do not generate calls to libc and are safe to keep.
         // If we find that any of them generate calls to libc on some platforms, we need to define them as zig bitcode.
@@ -1422,12 +1423,177 @@ pub(crate) fn run_low_level<'a, 'ctx>(
             call_bitcode_fn(env, &[], bitcode::UTILS_DICT_PSEUDO_SEED)
         }
 
+        CoverageHit => {
+            arguments!(module_id, region);
+
+            let counter =
+                coverage_counter(env, module_id.into_int_value(), region.into_int_value());
+            let i64_type = env.context.i64_type();
+
+            let count = env
+                .builder
+                .new_build_load(i64_type, counter, "coverage_count")
+                .into_int_value();
+            let incremented =
+                env.builder
+                    .new_build_int_add(count, i64_type.const_int(1, false), "coverage_inc");
+
+            env.builder.new_build_store(counter, incremented);
+
+            env.context.struct_type(&[], false).const_zero().into()
+        }
+
+        ExpectDraw => {
+            // ExpectDraw : U64 -> U64
+            arguments!(bound);
+
+            build_expect_draw(env, parent, bound.into_int_value())
+        }
+
+        ExpectArbitrary => unreachable!("ExpectArbitrary is replaced when specializing"),
+
+        ExpectSnapshot => {
+            // ExpectSnapshot : Str, Str -> Bool
+            arguments!(name, value);
+
+            if env.mode.compares_snapshots() {
+                build_expect_snapshot(env, name, value)
+            } else {
+                env.context.bool_type().const_int(1, false).into()
+            }
+        }
+
+        BenchBlackBox => {
+            // BenchBlackBox : a -> {}
+            arguments!(value);
+
+            // a volatile store can't be removed, so the value has to be computed
+            let sink = create_entry_block_alloca(env, value.get_type(), "bench_sink");
+            let store = env.builder.new_build_store(sink, value);
+            store.set_volatile(true).unwrap();
+
+            env.context.struct_type(&[], false).const_zero().into()
+        }
+
         ListIncref | ListDecref | SetJmp | LongJmp | SetLongJmpBuffer => {
             unreachable!("only inserted in dev backend codegen")
         }
     }
 }
 
+/// Takes the next of the choices that `roc test` made for a property expect, reduced to at
+/// most `bound`. Once all the choices are used up, every further choice is 0.
+fn build_expect_draw<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    parent: FunctionValue<'ctx>,
+    bound: IntValue<'ctx>,
+) -> BasicValueEnum<'ctx> {
+    use roc_mono::arbitrary::{CHOICES, CHOICES_LEN, CHOICES_USED};
+
+    let bd = env.builder;
+    let i64_type = env.context.i64_type();
+    let ptr_type = env.context.ptr_type(AddressSpace::default());
+
+    let global = |name: &str, basic_type: BasicTypeEnum<'ctx>| {
+        env.module
+            .get_global(name)
+            .unwrap_or_else(|| {
+                let global = env.module.add_global(basic_type, None, name);
+                global.set_initializer(&basic_type.const_zero());
+                global
+            })
+            .as_pointer_value()
+    };
+
+    let choices = global(CHOICES, ptr_type.into());
+    let choices_len = global(CHOICES_LEN, i64_type.into());
+    let choices_used = global(CHOICES_USED, i64_type.into());
+
+    let index = bd
+        .new_build_load(i64_type, choices_used, "choice_index")
+        .into_int_value();
+    let len = bd
+        .new_build_load(i64_type, choices_len, "choices_len")
+        .into_int_value();
+
+    let next_index = bd.new_build_int_add(index, i64_type.const_int(1, false), "next_index");
+    bd.new_build_store(choices_used, next_index);
+
+    let in_bounds = bd.new_build_int_compare(IntPredicate::ULT, index, len, "in_bounds");
+
+    let entry_block = bd.get_insert_block().unwrap();
+    let load_block = env.context.append_basic_block(parent, "load_choice");
+    let cont_block = env.context.append_basic_block(parent, "choice_cont");
+
+    bd.new_build_conditional_branch(in_bounds, load_block, cont_block);
+
+    let loaded = {
+        bd.position_at_end(load_block);
+
+        let choices_ptr = bd
+            .new_build_load(ptr_type, choices, "choices_ptr")
+            .into_pointer_value();
+        let choice_ptr =
+            unsafe { bd.new_build_in_bounds_gep(i64_type, choices_ptr, &[index], "choice_ptr") };
+        let loaded = bd.new_build_load(i64_type, choice_ptr, "choice");
+
+        bd.new_build_unconditional_branch(cont_block);
+
+        loaded
+    };
+
+    bd.position_at_end(cont_block);
+
+    let phi = bd.new_build_phi(i64_type, "raw_choice");
+    let exhausted = i64_type.const_zero();
+    phi.add_incoming(&[
+        (&loaded as &dyn BasicValue, load_block),
+        (&exhausted, entry_block),
+    ]);
+    let raw = phi.as_basic_value().into_int_value();
+
+    // `bound + 1` wraps around to 0 when every U64 is allowed
+    let modulus = bd.new_build_int_add(bound, i64_type.const_int(1, false), "modulus");
+    let unbounded = bd.new_build_int_compare(
+        IntPredicate::EQ,
+        modulus,
+        i64_type.const_zero(),
+        "unbounded",
+    );
+    let divisor = bd
+        .new_build_select(unbounded, i64_type.const_int(1, false), modulus, "divisor")
+        .into_int_value();
+    let reduced = bd.new_build_int_unsigned_rem(raw, divisor, "reduced_choice");
+
+    bd.new_build_select(unbounded, raw, reduced, "bounded_choice")
+}
+
+/// The global that counts runs of the region a [`LowLevel::CoverageHit`] is for.
+/// Its arguments are always literals, so the region is known at compile time.
+fn coverage_counter<'ctx>(
+    env: &Env<'_, 'ctx, '_>,
+    module_id: IntValue<'ctx>,
+    region: IntValue<'ctx>,
+) -> PointerValue<'ctx> {
+    let (Some(module_id), Some(region)) = (
+        module_id.get_zero_extended_constant(),
+        region.get_zero_extended_constant(),
+    ) else {
+        internal_error!("CoverageHit arguments must be literals");
+    };
+
+    let i64_type = env.context.i64_type();
+    let name = roc_mono::coverage::counter_name(module_id as u32, region);
+
+    let global = env.module.get_global(&name).unwrap_or_else(|| {
+        let global = env.module.add_global(i64_type, None, &name);
+        global.set_initializer(&i64_type.const_zero());
+        global
+    });
+
+    global.as_pointer_value()
+}
+
 fn intwidth_from_layout(layout: InLayout) -> IntWidth {
     layout.to_int_width()
 }
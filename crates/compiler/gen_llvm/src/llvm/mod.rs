@@ -4,7 +4,8 @@ pub mod build_list;
 pub mod build_str;
 pub mod compare;
 pub mod convert;
-mod expect;
+pub mod debug_info;
+pub mod expect;
 pub mod externs;
 mod intrinsics;
 mod lowlevel;
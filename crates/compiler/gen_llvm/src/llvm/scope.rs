@@ -1,5 +1,6 @@
 use inkwell::{
     basic_block::BasicBlock,
+    debug_info::DILocation,
     values::{BasicValue, BasicValueEnum, FunctionValue, PhiValue, PointerValue},
 };
 use roc_collections::ImMap;
@@ -14,6 +15,7 @@ pub(crate) struct Scope<'a, 'ctx> {
     symbols: ImMap<Symbol, (InLayout<'a>, BasicValueEnum<'ctx>)>,
     top_level_thunks: ImMap<Symbol, (ProcLayout<'a>, FunctionValue<'ctx>)>,
     join_points: ImMap<JoinPointId, (BasicBlock<'ctx>, Vec<JoinPointArg<'ctx>>)>,
+    statement_locations: ImMap<Symbol, DILocation<'ctx>>,
 }
 
 #[derive(Debug)]
@@ -55,6 +57,15 @@ impl<'a, 'ctx> Scope<'a, 'ctx> {
             .insert(symbol, (layout, function_value));
     }
 
+    pub fn insert_statement_location(&mut self, symbol: Symbol, location: DILocation<'ctx>) {
+        self.statement_locations.insert(symbol, location);
+    }
+
+    /// The source location of the statement that binds `symbol`, when building with `--debug`
+    pub fn statement_location(&self, symbol: &Symbol) -> Option<DILocation<'ctx>> {
+        self.statement_locations.get(symbol).copied()
+    }
+
     pub fn remove(&mut self, symbol: &Symbol) {
         self.symbols.remove(symbol);
     }
@@ -2245,6 +2245,15 @@ impl<'a> LowLevelCall<'a> {
             SetJmp | LongJmp | SetLongJmpBuffer => {
                 unreachable!("only inserted in dev backend codegen")
             }
+
+            CoverageHit => todo!("coverage is not implemented in the wasm backend"),
+            ExpectArbitrary => unreachable!("ExpectArbitrary is replaced when specializing"),
+            ExpectDraw => todo!("property expects are not implemented in the wasm backend"),
+            ExpectSnapshot => {
+                // Only `roc test` compares snapshots, and it doesn't use this backend
+                backend.code_builder.i32_const(1);
+            }
+            BenchBlackBox => todo!("benchmarks are not implemented in the wasm backend"),
         }
     }
 
@@ -19,8 +19,9 @@ const SKIP_SUBS_CACHE: bool = {
 pub use roc_load_internal::docs;
 pub use roc_load_internal::file::{
     ExecutionMode, ExpectMetadata, LoadConfig, LoadResult, LoadStart, LoadingProblem, Phase,
-    Threading,
+    SourceOverrides, Threading,
 };
+pub use roc_load_internal::incremental::IncrementalCache;
 pub use roc_load_internal::module::{
     CheckedModule, EntryPoint, Expectations, ExposedToHost, LoadedModule, MonomorphizedModule,
 };
@@ -68,6 +69,7 @@ pub fn load_single_threaded<'a>(
         target,
         function_kind,
         cached_subs,
+        None,
         render,
         palette,
         exec_mode,
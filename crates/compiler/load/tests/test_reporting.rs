@@ -130,6 +130,7 @@ mod test_reporting {
                 threading: Threading::Single,
                 exec_mode: ExecutionMode::Check,
                 function_kind: FunctionKind::LambdaSet,
+                incremental: None,
             };
             let result = roc_load::load_and_typecheck(
                 arena,
@@ -7,6 +7,7 @@ use roc_module::symbol::{IdentIds, ModuleId, ModuleIds, Symbol};
 use roc_parse::ast::{self, ExtractSpaces, TypeHeader};
 use roc_parse::ast::{AssignedField, FunctionArrow};
 use roc_parse::ast::{CommentOrNewline, TypeDef, ValueDef};
+use serde::{Deserialize, Serialize};
 
 // Documentation generation requirements
 
@@ -29,23 +30,25 @@ impl ModuleDocumentation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DocEntry {
     DocDef(DocDef),
     DetachedDoc(String),
     ModuleDoc(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocDef {
     pub name: String,
+    /// Not cached along with the rest of the entry, see [`resolve_cached_entries`]
+    #[serde(skip, default = "unresolved_symbol")]
     pub symbol: Symbol,
     pub type_vars: Vec<String>,
     pub type_annotation: TypeAnnotation,
     pub docs: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TypeAnnotation {
     TagUnion {
         tags: Vec<Tag>,
@@ -53,6 +56,7 @@ pub enum TypeAnnotation {
     },
     Function {
         args: Vec<TypeAnnotation>,
+        #[serde(with = "function_arrow")]
         arrow: FunctionArrow,
         output: Box<TypeAnnotation>,
     },
@@ -87,13 +91,13 @@ pub enum TypeAnnotation {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImplementsClause {
     pub name: String,
     pub abilities: Vec<TypeAnnotation>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecordField {
     RecordField {
         name: String,
@@ -108,7 +112,7 @@ pub enum RecordField {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AbilityMember {
     pub name: String,
     pub type_annotation: TypeAnnotation,
@@ -116,12 +120,56 @@ pub struct AbilityMember {
     pub docs: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     pub name: String,
     pub values: Vec<TypeAnnotation>,
 }
 
+/// Module ids can differ between loads, so the doc entries that the incremental cache keeps don't
+/// include symbols. This gives each def back its symbol, the same way [generate_module_docs]
+/// picked it in the first place.
+pub(crate) fn resolve_cached_entries(
+    entries: &mut [DocEntry],
+    home: ModuleId,
+    ident_ids: &IdentIds,
+) {
+    for entry in entries {
+        if let DocEntry::DocDef(doc_def) = entry {
+            if let Some(ident_id) = ident_ids.get_id(&doc_def.name) {
+                doc_def.symbol = Symbol::new(home, ident_id);
+            }
+        }
+    }
+}
+
+fn unresolved_symbol() -> Symbol {
+    Symbol::UNDERSCORE
+}
+
+mod function_arrow {
+    use roc_parse::ast::FunctionArrow;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        arrow: &FunctionArrow,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(matches!(arrow, FunctionArrow::Effectful))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<FunctionArrow, D::Error> {
+        let arrow = match bool::deserialize(deserializer)? {
+            true => FunctionArrow::Effectful,
+            false => FunctionArrow::Pure,
+        };
+
+        Ok(arrow)
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn generate_module_docs(
     scope: Scope,
@@ -1,10 +1,12 @@
 #![allow(clippy::too_many_arguments)]
 
-use crate::docs::ModuleDocumentation;
+use crate::docs::{DocEntry, ModuleDocumentation};
+use crate::incremental::IncrementalCache;
 use crate::module::{
     CheckedModule, ConstrainedModule, EntryPoint, Expectations, ExposedToHost,
     FoundSpecializationsModule, LateSpecializationsModule, LoadedModule, ModuleHeader,
-    ModuleTiming, MonomorphizedModule, ParsedModule, ToplevelExpects, TypeCheckedModule,
+    ModuleTiming, MonomorphizedModule, ParsedModule, ToplevelBenchmark, ToplevelExpects,
+    TypeCheckedModule,
 };
 use crate::module_cache::ModuleCache;
 use bumpalo::{collections::CollectIn, Bump};
@@ -16,7 +18,7 @@ use roc_builtins::roc::module_source;
 use roc_can::abilities::{AbilitiesStore, PendingAbilitiesStore, ResolvedImpl};
 use roc_can::constraint::{Constraint as ConstraintSoa, Constraints, TypeOrVar};
 use roc_can::env::FxMode;
-use roc_can::expr::{Declarations, ExpectLookup, PendingDerives};
+use roc_can::expr::{Declarations, ExpectLookup, Expr, FunctionDef, PendingDerives};
 use roc_can::module::{
     canonicalize_module_defs, ExposedByModule, ExposedForModule, ExposedModuleTypes, Module,
     ModuleParams, ResolvedImplementations, TypeState,
@@ -34,7 +36,9 @@ use roc_debug_flags::{
 use roc_derive::SharedDerivedModule;
 use roc_error_macros::internal_error;
 use roc_late_solve::{AbilitiesView, WorldAbilities};
+use roc_module::called_via::CalledVia;
 use roc_module::ident::{Ident, IdentSuffix, ModuleName, QualifiedModuleName};
+use roc_module::low_level::LowLevel;
 use roc_module::symbol::{
     IdentIds, IdentIdsByModule, Interns, ModuleId, ModuleIds, PQModuleName, PackageModuleIds,
     PackageQualified, Symbol,
@@ -114,6 +118,8 @@ pub struct LoadConfig {
     pub threading: Threading,
     pub exec_mode: ExecutionMode,
     pub function_kind: FunctionKind,
+    /// Solved types of modules from earlier loads, to reuse for the modules that haven't changed
+    pub incremental: Option<IncrementalCache>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -124,7 +130,13 @@ pub enum ExecutionMode {
     ExecutableIfCheck,
     /// Test is like [`ExecutionMode::ExecutableIfCheck`], but rather than producing a proper
     /// executable, run tests.
-    Test,
+    Test {
+        /// Whether to count how often each top-level def, `when` branch and `if` arm runs
+        coverage: bool,
+    },
+    /// Like [`ExecutionMode::Test`], but builds the benchmarks for `roc bench` rather than the
+    /// expects.
+    Bench,
 }
 
 impl ExecutionMode {
@@ -133,12 +145,20 @@ impl ExecutionMode {
 
         match self {
             Executable => Phase::MakeSpecializations,
-            Check | ExecutableIfCheck | Test => Phase::SolveTypes,
+            Check | ExecutableIfCheck | Test { .. } | Bench => Phase::SolveTypes,
         }
     }
 
     fn build_if_checks(&self) -> bool {
-        matches!(self, Self::ExecutableIfCheck | Self::Test)
+        matches!(
+            self,
+            Self::ExecutableIfCheck | Self::Test { .. } | Self::Bench
+        )
+    }
+
+    /// Whether to count runs of the code in `module_id`, for `roc test --coverage`
+    fn counts_coverage_of(&self, module_id: ModuleId) -> bool {
+        matches!(self, Self::Test { coverage: true }) && !module_id.is_builtin()
     }
 }
 
@@ -302,6 +322,24 @@ fn start_phase<'a>(
                     }
                 }
 
+                let mut cached_doc_entries = None;
+
+                if let Some(incremental) = &state.incremental {
+                    if !module_id.is_builtin() {
+                        if let Some(reused) = incremental.reusable_module(
+                            module_id,
+                            state.fx_mode,
+                            &mut state.module_cache,
+                        ) {
+                            state
+                                .cached_types
+                                .lock()
+                                .insert(module_id, reused.type_state);
+                            cached_doc_entries = reused.doc_entries;
+                        }
+                    }
+                }
+
                 let skip_constraint_gen = {
                     // Give this its own scope to make sure that the Guard from the lock() is dropped
                     // immediately after contains_key returns
@@ -316,6 +354,7 @@ fn start_phase<'a>(
                     aliases,
                     abilities_store,
                     skip_constraint_gen,
+                    cached_doc_entries,
                     exposed_module_ids: state.exposed_modules,
                     exec_mode: state.exec_mode,
                     imported_module_params,
@@ -419,7 +458,16 @@ fn start_phase<'a>(
                 let derived_module = SharedDerivedModule::clone(&state.derived_module);
 
                 let build_expects =
-                    matches!(state.exec_mode, ExecutionMode::Test) && expectations.is_some();
+                    matches!(state.exec_mode, ExecutionMode::Test { .. }) && expectations.is_some();
+                let build_benchmarks =
+                    matches!(state.exec_mode, ExecutionMode::Bench) && expectations.is_some();
+
+                if state.exec_mode.counts_coverage_of(module_id) {
+                    state
+                        .module_cache
+                        .coverage_points
+                        .insert(module_id, decls.coverage_points());
+                }
 
                 BuildTask::BuildPendingSpecializations {
                     layout_cache,
@@ -436,6 +484,8 @@ fn start_phase<'a>(
                     derived_module,
                     expectations,
                     build_expects,
+                    build_benchmarks,
+                    coverage: state.exec_mode.counts_coverage_of(module_id),
                 }
             }
             Phase::MakeSpecializations => {
@@ -555,6 +605,7 @@ fn start_phase<'a>(
                     exposed_by_module: state.exposed_types.clone(),
                     derived_module,
                     expectations,
+                    coverage: state.exec_mode.counts_coverage_of(module_id),
                 }
             }
         }
@@ -763,6 +814,9 @@ struct State<'a> {
     // cached types (used for builtin modules, could include packages in the future too)
     cached_types: CachedTypeState,
 
+    /// Where to look for, and record, the solved types of modules across loads
+    incremental: Option<IncrementalCache>,
+
     layout_interner: GlobalLayoutInterner<'a>,
 }
 
@@ -785,6 +839,7 @@ impl<'a> State<'a> {
         ident_ids_by_module: SharedIdentIdsByModule,
         arc_shorthands: Arc<Mutex<MutMap<&'a str, ShorthandPath>>>,
         cached_types: MutMap<ModuleId, TypeState>,
+        incremental: Option<IncrementalCache>,
         render: RenderTarget,
         palette: Palette,
         number_of_workers: usize,
@@ -823,6 +878,7 @@ impl<'a> State<'a> {
             timings: MutMap::default(),
             layout_caches: std::vec::Vec::with_capacity(number_of_workers),
             cached_types: Arc::new(Mutex::new(cached_types)),
+            incremental,
             render,
             palette,
             exec_mode,
@@ -903,6 +959,8 @@ enum BuildTask<'a> {
         abilities_store: PendingAbilitiesStore,
         exposed_module_ids: &'a [ModuleId],
         skip_constraint_gen: bool,
+        /// The module's doc entries from the incremental cache, if its types were reused
+        cached_doc_entries: Option<Vec<DocEntry>>,
         exec_mode: ExecutionMode,
         imported_module_params: VecMap<ModuleId, ModuleParams>,
         fx_mode: FxMode,
@@ -942,6 +1000,8 @@ enum BuildTask<'a> {
         derived_module: SharedDerivedModule,
         expectations: Option<Expectations>,
         build_expects: bool,
+        build_benchmarks: bool,
+        coverage: bool,
     },
     MakeSpecializations {
         module_id: ModuleId,
@@ -955,6 +1015,7 @@ enum BuildTask<'a> {
         world_abilities: WorldAbilities,
         derived_module: SharedDerivedModule,
         expectations: Option<Expectations>,
+        coverage: bool,
     },
 }
 
@@ -1126,6 +1187,7 @@ pub fn load_and_typecheck_str<'a>(
         threading,
         exec_mode: ExecutionMode::Check,
         function_kind,
+        incremental: None,
     };
 
     match load(
@@ -1157,8 +1219,15 @@ pub struct LoadStart<'a> {
     root_type: RootType,
     opt_platform_shorthand: Option<&'a str>,
     src_dir: PathBuf,
+    source_overrides: SourceOverrides,
 }
 
+/// Sources to use for modules instead of reading their files, keyed by the path of the module.
+///
+/// Editors use this to check modules against the unsaved contents of the other modules
+/// that are open, rather than what was last saved to disk.
+pub type SourceOverrides = MutMap<PathBuf, Box<str>>;
+
 #[derive(Debug, Clone)]
 enum RootType {
     Main,
@@ -1237,6 +1306,7 @@ impl<'a> LoadStart<'a> {
             root_msg: header_output.msg,
             root_type,
             opt_platform_shorthand: header_output.opt_platform_shorthand,
+            source_overrides: SourceOverrides::default(),
         })
     }
 
@@ -1293,8 +1363,18 @@ impl<'a> LoadStart<'a> {
             root_msg,
             root_type,
             opt_platform_shorthand: opt_platform_id,
+            source_overrides: SourceOverrides::default(),
         })
     }
+
+    /// Reads the modules that the root module imports from `source_overrides` instead of
+    /// from disk, wherever they have an entry.
+    pub fn with_source_overrides(self, source_overrides: SourceOverrides) -> Self {
+        Self {
+            source_overrides,
+            ..self
+        }
+    }
 }
 
 fn handle_root_type<'a>(
@@ -1542,6 +1622,7 @@ pub fn load<'a>(
             load_config.target,
             load_config.function_kind,
             cached_types,
+            load_config.incremental,
             load_config.render,
             load_config.palette,
             load_config.exec_mode,
@@ -1554,6 +1635,7 @@ pub fn load<'a>(
             load_config.target,
             load_config.function_kind,
             cached_types,
+            load_config.incremental,
             load_config.render,
             load_config.palette,
             threads,
@@ -1571,6 +1653,7 @@ pub fn load_single_threaded<'a>(
     target: Target,
     function_kind: FunctionKind,
     cached_types: MutMap<ModuleId, TypeState>,
+    incremental: Option<IncrementalCache>,
     render: RenderTarget,
     palette: Palette,
     exec_mode: ExecutionMode,
@@ -1586,7 +1669,7 @@ pub fn load_single_threaded<'a>(
         root_type,
         src_dir,
         opt_platform_shorthand,
-        ..
+        source_overrides,
     } = load_start;
 
     let (msg_tx, msg_rx) = bounded(1024);
@@ -1608,6 +1691,7 @@ pub fn load_single_threaded<'a>(
         ident_ids_by_module,
         arc_shorthands,
         cached_types,
+        incremental,
         render,
         palette,
         number_of_workers,
@@ -1638,7 +1722,15 @@ pub fn load_single_threaded<'a>(
         // then check if the worker can step
         let control_flow =
             roc_worker::worker_task_step(&worker, &injector, stealers, &worker_wakup_rx, |task| {
-                run_task(task, arena, &src_dir, msg_tx.clone(), roc_cache_dir, target)
+                run_task(
+                    task,
+                    arena,
+                    &src_dir,
+                    &source_overrides,
+                    msg_tx.clone(),
+                    roc_cache_dir,
+                    target,
+                )
             });
 
         match control_flow {
@@ -1951,6 +2043,7 @@ fn load_multi_threaded<'a>(
     target: Target,
     function_kind: FunctionKind,
     cached_types: MutMap<ModuleId, TypeState>,
+    incremental: Option<IncrementalCache>,
     render: RenderTarget,
     palette: Palette,
     available_threads: usize,
@@ -1967,7 +2060,7 @@ fn load_multi_threaded<'a>(
         root_type,
         src_dir,
         opt_platform_shorthand,
-        ..
+        source_overrides,
     } = load_start;
 
     let (msg_tx, msg_rx) = bounded(1024);
@@ -2004,6 +2097,7 @@ fn load_multi_threaded<'a>(
         ident_ids_by_module,
         arc_shorthands,
         cached_types,
+        incremental,
         render,
         palette,
         num_workers,
@@ -2056,9 +2150,11 @@ fn load_multi_threaded<'a>(
 
                 // We only want to move a *reference* to the main task queue's
                 // injector in the thread, not the injector itself
-                // (since other threads need to reference it too). Same with src_dir.
+                // (since other threads need to reference it too). Same with src_dir
+                // and source_overrides.
                 let injector = &injector;
                 let src_dir = &src_dir;
+                let source_overrides = &source_overrides;
 
                 // Record this thread's handle so the main thread can join it later.
                 let res_join_handle = thread_scope
@@ -2076,6 +2172,7 @@ fn load_multi_threaded<'a>(
                                     task,
                                     worker_arena,
                                     src_dir,
+                                    source_overrides,
                                     msg_tx.clone(),
                                     roc_cache_dir,
                                     target,
@@ -2237,6 +2334,10 @@ fn update<'a>(
             let home = header.module_id;
             let mut work = MutSet::default();
 
+            if let Some(incremental) = &state.incremental {
+                incremental.record_module_path(home, &header.module_path);
+            }
+
             // Only lock shorthands if this header has packages
             if !header.packages.is_empty() {
                 let mut shorthands = state.arc_shorthands.lock();
@@ -2505,6 +2606,19 @@ fn update<'a>(
             log!("solved types for {:?}", module_id);
             module_timing.end_time = Instant::now();
 
+            if let Some(incremental) = &state.incremental {
+                if solved_module.problems.is_empty() {
+                    incremental.insert(module_id, state.fx_mode, &mut state.module_cache, || {
+                        TypeState {
+                            subs: solved_subs.inner().clone(),
+                            exposed_vars_by_symbol: solved_module.exposed_vars_by_symbol.clone(),
+                            abilities: abilities_store.clone(),
+                            solved_implementations: solved_module.solved_implementations.clone(),
+                        }
+                    });
+                }
+            }
+
             state
                 .module_cache
                 .type_problems
@@ -2545,7 +2659,7 @@ fn update<'a>(
 
             let add_to_host_exposed = is_host_exposed &&
                 // During testing, we don't need to expose anything to the host.
-                !matches!(state.exec_mode, ExecutionMode::Test);
+                !matches!(state.exec_mode, ExecutionMode::Test { .. } | ExecutionMode::Bench);
 
             if add_to_host_exposed {
                 state.exposed_to_host.top_level_values.extend(
@@ -2708,7 +2822,7 @@ fn update<'a>(
 
             let subs = solved_subs.into_inner();
 
-            if !toplevel_expects.pure.is_empty() {
+            if !toplevel_expects.pure.is_empty() || !toplevel_expects.benchmarks.is_empty() {
                 state.toplevel_expects.insert(module_id, toplevel_expects);
             }
 
@@ -3203,7 +3317,7 @@ fn finish_specialization<'a>(
     let entry_point = {
         let interns: &mut Interns = &mut interns;
         match state.exec_mode {
-            ExecutionMode::Test => Ok(EntryPoint::Test),
+            ExecutionMode::Test { .. } | ExecutionMode::Bench => Ok(EntryPoint::Test),
             ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck => {
                 use PlatformPath::*;
 
@@ -3280,6 +3394,7 @@ fn finish_specialization<'a>(
         type_problems,
         can_problems,
         sources,
+        coverage_points,
         ..
     } = module_cache;
 
@@ -3300,6 +3415,7 @@ fn finish_specialization<'a>(
         can_problems,
         type_problems,
         expectations: module_expectations,
+        coverage_points,
         exposed_to_host,
         module_id,
         subs,
@@ -3661,6 +3777,7 @@ fn load_builtin_module<'a>(
 fn load_module<'a>(
     arena: &'a Bump,
     src_dir: &Path,
+    source_overrides: &SourceOverrides,
     module_name: PQModuleName<'a>,
     module_ids: Arc<Mutex<PackageModuleIds<'a>>>,
     arc_shorthands: Arc<Mutex<MutMap<&'a str, ShorthandPath>>>,
@@ -3716,6 +3833,24 @@ fn load_module<'a>(
 
     let (filename, opt_shorthand) = module_name_to_path(src_dir, &module_name, arc_shorthands);
 
+    if let Some(src) = source_overrides.get(&filename) {
+        let src_bytes = arena.alloc_slice_copy(src.as_bytes());
+
+        return parse_header(
+            arena,
+            Duration::default(),
+            filename,
+            false,
+            opt_shorthand,
+            Some(module_name),
+            module_ids,
+            ident_ids_by_module,
+            src_bytes,
+            roc_cache_dir,
+            module_start_time,
+        );
+    }
+
     load_filename(
         arena,
         filename,
@@ -4880,58 +5015,42 @@ fn run_solve<'a>(
     let has_dbgs = module.has_dbgs;
     let module = module;
 
-    let solve_result = {
-        if module_id.is_builtin() {
-            match cached_types.lock().remove(&module_id) {
-                None => run_solve_solve(
-                    exposed_for_module,
-                    types,
-                    constraints,
-                    constraint,
-                    function_kind,
-                    pending_derives,
-                    var_store,
-                    module,
-                    derived_module,
-                    is_host_exposed,
-                    //
-                    #[cfg(debug_assertions)]
-                    checkmate,
-                ),
-                Some(TypeState {
-                    subs,
-                    exposed_vars_by_symbol,
-                    abilities,
-                    solved_implementations,
-                }) => SolveResult {
-                    solved: Solved(subs),
-                    solved_implementations,
-                    exposed_vars_by_symbol,
-                    problems: vec![],
-                    abilities_store: abilities,
-                    imported_modules_with_params: vec![],
+    // Builtins are always cached; other modules are if an earlier load solved them
+    // and nothing they depend on has changed since.
+    let opt_cached_types = cached_types.lock().remove(&module_id);
 
-                    #[cfg(debug_assertions)]
-                    checkmate: None,
-                },
-            }
-        } else {
-            run_solve_solve(
-                exposed_for_module,
-                types,
-                constraints,
-                constraint,
-                function_kind,
-                pending_derives,
-                var_store,
-                module,
-                derived_module,
-                is_host_exposed,
-                //
-                #[cfg(debug_assertions)]
-                checkmate,
-            )
-        }
+    let solve_result = match opt_cached_types {
+        None => run_solve_solve(
+            exposed_for_module,
+            types,
+            constraints,
+            constraint,
+            function_kind,
+            pending_derives,
+            var_store,
+            module,
+            derived_module,
+            is_host_exposed,
+            //
+            #[cfg(debug_assertions)]
+            checkmate,
+        ),
+        Some(TypeState {
+            subs,
+            exposed_vars_by_symbol,
+            abilities,
+            solved_implementations,
+        }) => SolveResult {
+            solved: Solved(subs),
+            solved_implementations,
+            exposed_vars_by_symbol,
+            problems: vec![],
+            abilities_store: abilities,
+            imported_modules_with_params: vec![],
+
+            #[cfg(debug_assertions)]
+            checkmate: None,
+        },
     };
 
     let SolveResult {
@@ -4959,7 +5078,10 @@ fn run_solve<'a>(
         ExecutionMode::Check => {
             // Params are not lowered in check mode
         }
-        ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test => {
+        ExecutionMode::Executable
+        | ExecutionMode::ExecutableIfCheck
+        | ExecutionMode::Test { .. }
+        | ExecutionMode::Bench => {
             roc_lower_params::type_error::remove_module_param_arguments(
                 &mut problems,
                 home_has_params,
@@ -5118,6 +5240,7 @@ fn canonicalize_and_constrain<'a>(
     imported_abilities_state: PendingAbilitiesStore,
     parsed: ParsedModule<'a>,
     skip_constraint_gen: bool,
+    cached_doc_entries: Option<Vec<DocEntry>>,
     exposed_module_ids: &[ModuleId],
     exec_mode: ExecutionMode,
     imported_module_params: VecMap<ModuleId, ModuleParams>,
@@ -5193,23 +5316,40 @@ fn canonicalize_and_constrain<'a>(
 
     module_timing.canonicalize = canonicalize_end.duration_since(canonicalize_start);
 
-    // Generate documentation information
+    // Generate documentation information, unless the incremental cache already has it
     // TODO: store timing information?
     let module_docs = {
         let module_name = header_type.get_name();
         module_name.map(|module_name| {
             let mut scope = module_output.scope.clone();
             scope.add_docs_imports();
-            crate::docs::generate_module_docs(
-                scope,
-                module_id,
-                arena.alloc(qualified_module_ids.clone().into_module_ids()),
-                module_name.into(),
-                &parsed_defs_for_docs,
-                exposed_module_ids,
-                module_output.exposed_symbols.clone(),
-                parsed.header_comments,
-            )
+
+            match cached_doc_entries {
+                Some(mut entries) => {
+                    crate::docs::resolve_cached_entries(
+                        &mut entries,
+                        module_id,
+                        &scope.locals.ident_ids,
+                    );
+
+                    ModuleDocumentation {
+                        name: module_name.to_string(),
+                        entries,
+                        scope,
+                        exposed_symbols: module_output.exposed_symbols.clone(),
+                    }
+                }
+                None => crate::docs::generate_module_docs(
+                    scope,
+                    module_id,
+                    arena.alloc(qualified_module_ids.clone().into_module_ids()),
+                    module_name.into(),
+                    &parsed_defs_for_docs,
+                    exposed_module_ids,
+                    module_output.exposed_symbols.clone(),
+                    parsed.header_comments,
+                ),
+            }
         })
     };
 
@@ -5221,7 +5361,10 @@ fn canonicalize_and_constrain<'a>(
             // No need to lower params for `roc check` and lang server
             // If we did, we'd have to update the language server to exclude the extra arguments
         }
-        ExecutionMode::Executable | ExecutionMode::ExecutableIfCheck | ExecutionMode::Test => {
+        ExecutionMode::Executable
+        | ExecutionMode::ExecutableIfCheck
+        | ExecutionMode::Test { .. }
+        | ExecutionMode::Bench => {
             // We need to lower params only if the current module has any or imports at least one with params
             if module_output.module_params.is_some() || !imported_module_params.is_empty() {
                 roc_lower_params::lower::lower(
@@ -5646,6 +5789,7 @@ fn make_specializations<'a>(
     exposed_by_module: &ExposedByModule,
     derived_module: SharedDerivedModule,
     mut expectations: Option<Expectations>,
+    coverage: bool,
 ) -> Msg<'a> {
     let make_specializations_start = Instant::now();
     let mut update_mode_ids = UpdateModeIds::new();
@@ -5665,6 +5809,7 @@ fn make_specializations<'a>(
         exposed_by_module,
         derived_module: &derived_module,
         struct_indexing: UsageTrackingMap::default(),
+        coverage,
     };
 
     let mut procs = Procs::new_in(arena);
@@ -5735,6 +5880,8 @@ fn build_pending_specializations<'a>(
     derived_module: SharedDerivedModule,
     mut expectations: Option<Expectations>,
     build_expects: bool,
+    build_benchmarks: bool,
+    coverage: bool,
 ) -> Msg<'a> {
     let find_specializations_start = Instant::now();
 
@@ -5768,6 +5915,7 @@ fn build_pending_specializations<'a>(
         exposed_by_module,
         derived_module: &derived_module,
         struct_indexing: UsageTrackingMap::default(),
+        coverage,
     };
 
     let layout_cache_snapshot = layout_cache.snapshot();
@@ -5780,6 +5928,12 @@ fn build_pending_specializations<'a>(
             .copied()
     };
 
+    let named_defs_in_source_order = if build_expects {
+        named_defs_in_source_order(&declarations)
+    } else {
+        Vec::new()
+    };
+
     // Add modules' decls to Procs
     for index in 0..declarations.len() {
         use roc_can::expr::DeclarationTag::*;
@@ -5843,6 +5997,7 @@ fn build_pending_specializations<'a>(
                             closure_data.return_type,
                             *closure_data.loc_body,
                             false,
+                            body.region,
                         );
                     }
                     _ => {
@@ -5859,6 +6014,7 @@ fn build_pending_specializations<'a>(
                             body_var: expr_var,
                             // This is a 0-arity thunk, so it cannot be recursive
                             is_self_recursive: false,
+                            toplevel_region: Some(body.region),
                         };
 
                         procs_base.partial_procs.insert(symbol, proc);
@@ -5910,7 +6066,42 @@ fn build_pending_specializations<'a>(
                     );
                 }
 
+                if build_benchmarks && is_benchmark(mono_env.ident_ids, symbol, function_def) {
+                    // a thunk that runs the benchmark once, for `roc bench` to call repeatedly
+                    let thunk = mono_env.unique_symbol();
+                    module_thunks.push(thunk);
+
+                    procs_base.host_specializations.insert_host_exposed(
+                        mono_env.subs,
+                        LambdaName::no_niche(thunk),
+                        None,
+                        Variable::EMPTY_RECORD,
+                    );
+
+                    let proc = PartialProc {
+                        annotation: Variable::EMPTY_RECORD,
+                        // This is a 0-arity thunk, so it has no arguments.
+                        pattern_symbols: &[],
+                        // This is a top-level definition, so it cannot capture anything
+                        captured_symbols: CapturedSymbols::None,
+                        body: benchmark_thunk_body(symbol, expr_var, function_def),
+                        body_var: Variable::EMPTY_RECORD,
+                        // This is a 0-arity thunk, so it cannot be recursive
+                        is_self_recursive: false,
+                        toplevel_region: None,
+                    };
+
+                    procs_base.partial_procs.insert(thunk, proc);
+
+                    let benchmark = ToplevelBenchmark {
+                        symbol,
+                        region: declarations.symbols[index].region,
+                    };
+                    toplevel_expects.benchmarks.insert(thunk, benchmark);
+                }
+
                 let is_recursive = matches!(tag, Recursive(_) | TailRecursive(_));
+                let region = body.region;
 
                 register_toplevel_function_into_procs_base(
                     &mut mono_env,
@@ -5921,10 +6112,12 @@ fn build_pending_specializations<'a>(
                     function_def.return_type,
                     body,
                     is_recursive,
+                    region,
                 );
             }
             Destructure(d_index) => {
-                let loc_pattern = &declarations.destructs[d_index.index()].loc_pattern;
+                let destruct_def = &declarations.destructs[d_index.index()];
+                let loc_pattern = &destruct_def.loc_pattern;
 
                 use roc_can::pattern::Pattern;
                 let symbol = match &loc_pattern.value {
@@ -5943,7 +6136,45 @@ fn build_pending_specializations<'a>(
                         // this seems to work for now
                         *shadowed
                     }
-                    _ => todo!("top-level destrucuture patterns are not implemented"),
+                    _ => {
+                        for (&bound, &bound_var) in destruct_def.pattern_vars.iter() {
+                            // mark this symbols as a top-level thunk before any other work on the procs
+                            module_thunks.push(bound);
+
+                            if exposed_to_host.top_level_values.contains_key(&bound) {
+                                procs_base.host_specializations.insert_host_exposed(
+                                    mono_env.subs,
+                                    LambdaName::no_niche(bound),
+                                    None,
+                                    bound_var,
+                                );
+                            }
+
+                            let proc = PartialProc {
+                                annotation: bound_var,
+                                // This is a 0-arity thunk, so it has no arguments.
+                                pattern_symbols: &[],
+                                // This is a top-level definition, so it cannot capture anything
+                                captured_symbols: CapturedSymbols::None,
+                                body: destructure_thunk_body(
+                                    &mut mono_env,
+                                    destruct_def,
+                                    &body,
+                                    expr_var,
+                                    bound,
+                                ),
+                                body_var: bound_var,
+                                // This is a 0-arity thunk, so it cannot be recursive
+                                is_self_recursive: false,
+                                // every value of the destructure runs its body, so each counts it
+                                toplevel_region: Some(body.region),
+                            };
+
+                            procs_base.partial_procs.insert(bound, proc);
+                        }
+
+                        continue;
+                    }
                 };
 
                 // mark this symbols as a top-level thunk before any other work on the procs
@@ -5995,6 +6226,7 @@ fn build_pending_specializations<'a>(
                     body_var: expr_var,
                     // This is a 0-arity thunk, so it cannot be recursive
                     is_self_recursive: false,
+                    toplevel_region: Some(body.region),
                 };
 
                 procs_base.partial_procs.insert(symbol, proc);
@@ -6063,6 +6295,8 @@ fn build_pending_specializations<'a>(
                     body_var: expr_var,
                     // This is a 0-arity thunk, so it cannot be recursive
                     is_self_recursive: false,
+                    // Expects are tests, so their runs aren't counted
+                    toplevel_region: None,
                 };
 
                 // extend the region of the expect expression with the region of the preceding
@@ -6073,6 +6307,15 @@ fn build_pending_specializations<'a>(
 
                 toplevel_expects.pure.insert(symbol, region);
                 procs_base.partial_procs.insert(symbol, proc);
+
+                // the last def that starts before this expect is the one it's written under
+                let preceding = named_defs_in_source_order
+                    .partition_point(|(def_region, _)| def_region.start() < expr_region.start());
+
+                if let Some(index) = preceding.checked_sub(1) {
+                    let (_, def_symbol) = named_defs_in_source_order[index];
+                    toplevel_expects.preceding_defs.insert(symbol, def_symbol);
+                }
             }
         }
     }
@@ -6097,6 +6340,167 @@ fn build_pending_specializations<'a>(
     }
 }
 
+/// The top-level defs that have a name the user wrote, ordered by where they appear in the source.
+fn named_defs_in_source_order(declarations: &Declarations) -> Vec<(Region, Symbol)> {
+    use roc_can::expr::DeclarationTag::*;
+
+    let mut defs: Vec<_> = (0..declarations.len())
+        .filter(|index| {
+            matches!(
+                declarations.declarations[*index],
+                Value | Function(_) | Recursive(_) | TailRecursive(_)
+            )
+        })
+        .map(|index| {
+            let loc_symbol = declarations.symbols[index];
+            (loc_symbol.region, loc_symbol.value)
+        })
+        .collect();
+
+    defs.sort_by_key(|(region, _)| region.start());
+
+    defs
+}
+
+/// The body of the thunk for `bound`, one of the values that a top-level destructure defines:
+/// the destructure itself, as a local def, followed by the value.
+///
+/// The local def binds fresh symbols, so that the value refers to the local binding rather than
+/// to the thunk of `bound` itself.
+fn destructure_thunk_body(
+    mono_env: &mut roc_mono::ir::Env<'_, '_>,
+    destruct_def: &roc_can::expr::DestructureDef,
+    body: &Loc<Expr>,
+    expr_var: Variable,
+    bound: Symbol,
+) -> Expr {
+    let renames: MutMap<Symbol, Symbol> = destruct_def
+        .pattern_vars
+        .keys()
+        .map(|symbol| (*symbol, mono_env.unique_symbol()))
+        .collect();
+
+    let mut loc_pattern = destruct_def.loc_pattern.clone();
+    rename_bindings(&mut loc_pattern.value, &renames);
+
+    let def = roc_can::def::Def {
+        loc_pattern,
+        loc_expr: body.clone(),
+        expr_var,
+        pattern_vars: destruct_def
+            .pattern_vars
+            .iter()
+            .map(|(symbol, var)| (renames[symbol], *var))
+            .collect(),
+        annotation: None,
+        kind: roc_can::def::DefKind::Let,
+    };
+
+    let bound_var = destruct_def.pattern_vars.get(&bound).copied().unwrap();
+    let value = Loc::at(body.region, Expr::Var(renames[&bound], bound_var));
+
+    Expr::LetNonRec(Box::new(def), Box::new(value))
+}
+
+/// Replaces the symbols that `pattern` binds by the ones they map to in `renames`.
+fn rename_bindings(pattern: &mut roc_can::pattern::Pattern, renames: &MutMap<Symbol, Symbol>) {
+    use roc_can::pattern::{DestructType, Pattern::*};
+
+    let rename = |symbol: &mut Symbol| {
+        if let Some(renamed) = renames.get(symbol) {
+            *symbol = *renamed;
+        }
+    };
+
+    match pattern {
+        Identifier(symbol) => rename(symbol),
+        As(inner, symbol) => {
+            rename(symbol);
+            rename_bindings(&mut inner.value, renames);
+        }
+        AppliedTag { arguments, .. } => {
+            for (_, argument) in arguments.iter_mut() {
+                rename_bindings(&mut argument.value, renames);
+            }
+        }
+        UnwrappedOpaque { argument, .. } => rename_bindings(&mut argument.1.value, renames),
+        RecordDestructure { destructs, .. } => {
+            for destruct in destructs.iter_mut() {
+                rename(&mut destruct.value.symbol);
+
+                if let DestructType::Guard(_, guard) = &mut destruct.value.typ {
+                    rename_bindings(&mut guard.value, renames);
+                }
+            }
+        }
+        TupleDestructure { destructs, .. } => {
+            for destruct in destructs.iter_mut() {
+                rename_bindings(&mut destruct.value.typ.1.value, renames);
+            }
+        }
+        List { patterns, .. } => {
+            for element in patterns.patterns.iter_mut() {
+                rename_bindings(&mut element.value, renames);
+            }
+
+            if let Some((_, Some(rest))) = &mut patterns.opt_rest {
+                rename(&mut rest.value);
+            }
+        }
+        NumLiteral(..)
+        | IntLiteral(..)
+        | FloatLiteral(..)
+        | StrLiteral(_)
+        | SingleQuote(..)
+        | Underscore
+        | AbilityMemberSpecialization { .. }
+        | Shadowed(..)
+        | OpaqueNotInScope(_)
+        | UnsupportedPattern(_)
+        | MalformedPattern(..) => {}
+    }
+}
+
+/// Whether a top-level function is a benchmark: it takes `{}`, and its name is `bench` followed
+/// by a capitalized word, like `benchSortList`.
+fn is_benchmark(ident_ids: &IdentIds, symbol: Symbol, function_def: &FunctionDef) -> bool {
+    let is_named_like_a_benchmark = ident_ids
+        .get_name(symbol.ident_id())
+        .and_then(|name| name.strip_prefix("bench"))
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()));
+
+    let takes_empty_record = matches!(
+        function_def.arguments.as_slice(),
+        [(_, _, Loc { value: roc_can::pattern::Pattern::RecordDestructure { destructs, .. }, .. })]
+            if destructs.is_empty()
+    );
+
+    is_named_like_a_benchmark && takes_empty_record
+}
+
+/// `benchFoo {}`, with its result hidden from the optimizer so the work isn't optimized away
+fn benchmark_thunk_body(symbol: Symbol, fn_var: Variable, function_def: &FunctionDef) -> Expr {
+    let (arg_var, _, _) = function_def.arguments[0];
+
+    let call = Expr::Call(
+        Box::new((
+            fn_var,
+            Loc::at_zero(Expr::Var(symbol, fn_var)),
+            function_def.closure_type,
+            function_def.return_type,
+            function_def.fx_type,
+        )),
+        vec![(arg_var, Loc::at_zero(Expr::EmptyRecord))],
+        CalledVia::Space,
+    );
+
+    Expr::RunLowLevel {
+        op: LowLevel::BenchBlackBox,
+        args: vec![(function_def.return_type, call)],
+        ret_var: Variable::EMPTY_RECORD,
+    }
+}
+
 fn register_toplevel_function_into_procs_base<'a>(
     mono_env: &mut roc_mono::ir::Env<'a, '_>,
     procs_base: &mut ProcsBase<'a>,
@@ -6110,8 +6514,9 @@ fn register_toplevel_function_into_procs_base<'a>(
     return_type: Variable,
     body: Loc<roc_can::expr::Expr>,
     is_recursive: bool,
+    toplevel_region: Region,
 ) {
-    let partial_proc = PartialProc::from_named_function(
+    let mut partial_proc = PartialProc::from_named_function(
         mono_env,
         expr_var,
         arguments,
@@ -6121,6 +6526,8 @@ fn register_toplevel_function_into_procs_base<'a>(
         return_type,
     );
 
+    partial_proc.toplevel_region = Some(toplevel_region);
+
     procs_base.partial_procs.insert(symbol, partial_proc);
 }
 
@@ -6177,6 +6584,8 @@ fn load_derived_partial_procs<'a>(
             exposed_by_module,
             derived_module,
             struct_indexing: UsageTrackingMap::default(),
+            // Derived code isn't in any source file
+            coverage: false,
         };
 
         let partial_proc = match derived_expr {
@@ -6214,6 +6623,7 @@ fn load_derived_partial_procs<'a>(
                     body_var: derived_expr_var,
                     // This is a 0-arity thunk, so it cannot be recursive
                     is_self_recursive: false,
+                    toplevel_region: None,
                 }
             }
         };
@@ -6238,6 +6648,7 @@ fn run_task<'a>(
     task: BuildTask<'a>,
     arena: &'a Bump,
     src_dir: &Path,
+    source_overrides: &SourceOverrides,
     msg_tx: MsgSender<'a>,
     roc_cache_dir: RocCacheDir<'_>,
     target: Target,
@@ -6253,6 +6664,7 @@ fn run_task<'a>(
         } => load_module(
             arena,
             src_dir,
+            source_overrides,
             module_name,
             module_ids,
             shorthands,
@@ -6282,6 +6694,7 @@ fn run_task<'a>(
             aliases,
             abilities_store,
             skip_constraint_gen,
+            cached_doc_entries,
             exposed_module_ids,
             exec_mode,
             imported_module_params,
@@ -6296,6 +6709,7 @@ fn run_task<'a>(
                 abilities_store,
                 parsed,
                 skip_constraint_gen,
+                cached_doc_entries,
                 exposed_module_ids,
                 exec_mode,
                 imported_module_params,
@@ -6359,6 +6773,8 @@ fn run_task<'a>(
             derived_module,
             expectations,
             build_expects,
+            build_benchmarks,
+            coverage,
         } => Ok(build_pending_specializations(
             arena,
             solved_subs,
@@ -6375,6 +6791,8 @@ fn run_task<'a>(
             derived_module,
             expectations,
             build_expects,
+            build_benchmarks,
+            coverage,
         )),
         MakeSpecializations {
             module_id,
@@ -6388,6 +6806,7 @@ fn run_task<'a>(
             exposed_by_module,
             derived_module,
             expectations,
+            coverage,
         } => Ok(make_specializations(
             arena,
             module_id,
@@ -6402,6 +6821,7 @@ fn run_task<'a>(
             &exposed_by_module,
             derived_module,
             expectations,
+            coverage,
         )),
     };
 
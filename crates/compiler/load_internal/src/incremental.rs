@@ -0,0 +1,416 @@
+//! Reusing the solved types of modules that haven't changed since an earlier load.
+//!
+//! Solving is usually the most expensive part of checking a module, so each load records the
+//! solved types and the docs of its modules here. A later load then skips solving any module
+//! whose source, and the sources of every module it transitively imports, are still the same.
+//!
+//! The types are kept in memory for loads in the same process (e.g. by `roc check --watch`),
+//! and optionally on disk as well, so that the next `roc` process can reuse them too.
+use crate::docs::DocEntry;
+use crate::module_cache::ModuleCache;
+use roc_can::env::FxMode;
+use roc_can::module::TypeState;
+use roc_collections::{MutMap, MutSet};
+use roc_module::symbol::{ModuleId, PQModuleName, Symbol};
+use roc_serialize::bytes;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use parking_lot::Mutex;
+
+/// The directory in `roc_cache_dir()` where solved types are kept
+const SOLVED_TYPES_DIR_NAME: &str = "solved-types";
+
+/// The solved types and docs of modules from earlier loads, keyed by the path of the module.
+///
+/// This is cheap to clone; all clones share the same underlying storage.
+#[derive(Clone, Default)]
+pub struct IncrementalCache {
+    modules: Arc<Mutex<MutMap<PathBuf, CachedModule>>>,
+    /// The paths of every non-builtin module read by the loads using this cache
+    module_paths: Arc<Mutex<MutSet<PathBuf>>>,
+    /// Where to keep the solved types for later processes, if anywhere
+    opt_dir: Option<Arc<Path>>,
+    /// How many times the types of a module were reused instead of solved
+    reused: Arc<AtomicUsize>,
+}
+
+struct CachedModule {
+    key: blake3::Hash,
+    type_state: TypeState,
+    /// The id that each module the types refer to had when they were solved, by module name
+    module_ids: Vec<(String, ModuleId)>,
+    doc_entries: Option<Vec<DocEntry>>,
+}
+
+/// The solved types and docs of a module from an earlier load, with the ids of this load
+pub(crate) struct ReusedModule {
+    pub type_state: TypeState,
+    pub doc_entries: Option<Vec<DocEntry>>,
+}
+
+/// A module that went into solving another one
+struct Dependency {
+    name: String,
+    module_id: ModuleId,
+    source_hash: blake3::Hash,
+}
+
+impl std::fmt::Debug for IncrementalCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncrementalCache")
+            .field("modules", &self.modules.lock().len())
+            .field("opt_dir", &self.opt_dir)
+            .finish()
+    }
+}
+
+impl IncrementalCache {
+    /// A cache that only lasts as long as this process.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache that also keeps the solved types in the roc cache directory,
+    /// so that later `roc` processes can reuse them.
+    pub fn persistent() -> Self {
+        Self::persistent_in(roc_packaging::cache::roc_cache_dir().join(SOLVED_TYPES_DIR_NAME))
+    }
+
+    /// Like [`IncrementalCache::persistent`], but keeps the solved types in `dir`.
+    pub fn persistent_in(dir: PathBuf) -> Self {
+        Self {
+            opt_dir: Some(dir.into()),
+            ..Self::default()
+        }
+    }
+
+    /// The number of modules whose solved types are cached in memory
+    pub fn len(&self) -> usize {
+        self.modules.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of times a load using this cache reused the types of a module
+    pub fn reused_modules(&self) -> usize {
+        self.reused.load(Ordering::Relaxed)
+    }
+
+    /// The paths of every module that was read by a load using this cache, i.e. the files
+    /// that would have to change for a new load to give different results.
+    pub fn module_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self.module_paths.lock().iter().cloned().collect();
+        paths.sort();
+
+        paths
+    }
+
+    pub(crate) fn record_module_path(&self, module_id: ModuleId, path: &Path) {
+        if !module_id.is_builtin() {
+            self.module_paths.lock().insert(path.to_path_buf());
+        }
+    }
+
+    /// The solved types and docs of `module_id` from an earlier load, if they still apply.
+    ///
+    /// Module ids are handed out in the order that modules are discovered, which can differ
+    /// between loads (e.g. when several threads load modules). Cached modules are identified by
+    /// name instead, and the types they refer to are given the ids of this load.
+    pub(crate) fn reusable_module(
+        &self,
+        module_id: ModuleId,
+        fx_mode: FxMode,
+        module_cache: &mut ModuleCache,
+    ) -> Option<ReusedModule> {
+        let (path, _) = module_cache.sources.get(&module_id)?;
+        let path = path.clone();
+        let dependencies = dependencies(module_id, module_cache);
+        let key = cache_key(fx_mode, &dependencies);
+
+        let mut modules = self.modules.lock();
+
+        if !matches!(modules.get(&path), Some(cached) if cached.key == key) {
+            let cached = read_cached_module(self.opt_dir.as_deref()?, &path, key)?;
+            modules.insert(path.clone(), cached);
+        }
+
+        let reused = reuse(&modules[&path], &dependencies)?;
+        self.reused.fetch_add(1, Ordering::Relaxed);
+
+        Some(reused)
+    }
+
+    /// Record the solved types and docs of `module_id`, so that later loads can reuse them.
+    ///
+    /// Types are only recorded for modules without type problems, because the problems
+    /// of a module are not reported again when its cached types are reused.
+    pub(crate) fn insert(
+        &self,
+        module_id: ModuleId,
+        fx_mode: FxMode,
+        module_cache: &mut ModuleCache,
+        type_state: impl FnOnce() -> TypeState,
+    ) {
+        if module_id.is_builtin() {
+            return;
+        }
+
+        let Some((path, _)) = module_cache.sources.get(&module_id) else {
+            return;
+        };
+        let path = path.clone();
+        let dependencies = dependencies(module_id, module_cache);
+        let key = cache_key(fx_mode, &dependencies);
+
+        let mut modules = self.modules.lock();
+
+        if let Some(cached) = modules.get(&path) {
+            if cached.key == key {
+                // these are the types we reused, no need to copy them again
+                return;
+            }
+        }
+
+        let cached = CachedModule {
+            key,
+            type_state: type_state(),
+            module_ids: dependencies
+                .into_iter()
+                .map(|dependency| (dependency.name, dependency.module_id))
+                .collect(),
+            doc_entries: module_cache
+                .documentation
+                .get(&module_id)
+                .map(|docs| docs.entries.clone()),
+        };
+
+        if let Some(dir) = &self.opt_dir {
+            // The cache only saves time; if it can't be written, we'll solve again next time.
+            let _ = write_cached_module(dir, &path, &cached);
+        }
+
+        modules.insert(path, cached);
+    }
+}
+
+/// `module_id` and every non-builtin module it transitively imports, sorted by name. (Builtins
+/// are left out, because their sources can only change along with the compiler, and their ids
+/// never change.)
+fn dependencies(module_id: ModuleId, module_cache: &mut ModuleCache) -> Vec<Dependency> {
+    let mut visited = MutSet::default();
+    let mut stack = vec![module_id];
+
+    while let Some(id) = stack.pop() {
+        if id.is_builtin() || !visited.insert(id) {
+            continue;
+        }
+
+        if let Some(imports) = module_cache.imports.get(&id) {
+            stack.extend(imports.iter().copied());
+        }
+    }
+
+    let mut dependencies: Vec<_> = visited
+        .into_iter()
+        .filter_map(|id| {
+            let name = match module_cache.module_names.get(&id)? {
+                PQModuleName::Unqualified(name) => name.as_str().to_string(),
+                PQModuleName::Qualified(package, name) => format!("{package}.{name}"),
+            };
+            let (_, source) = module_cache.sources.get(&id)?;
+            let source_hash = *module_cache
+                .source_hashes
+                .entry(id)
+                .or_insert_with(|| blake3::hash(source.as_bytes()));
+
+            Some(Dependency {
+                name,
+                module_id: id,
+                source_hash,
+            })
+        })
+        .collect();
+
+    dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+
+    dependencies
+}
+
+/// Identifies everything that went into solving a module: the compiler, and the name and
+/// source of the module and every module it depends on.
+fn cache_key(fx_mode: FxMode, dependencies: &[Dependency]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+
+    hasher.update(compiler_fingerprint().as_bytes());
+
+    // Writing to a hasher cannot fail
+    bytes::serialize_slice(&[fx_mode], &mut hasher, 0).unwrap();
+
+    for dependency in dependencies {
+        hasher.update(dependency.name.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(dependency.source_hash.as_bytes());
+    }
+
+    hasher.finalize()
+}
+
+/// A copy of the cached types and docs, referring to modules by the ids of the current load
+fn reuse(cached: &CachedModule, dependencies: &[Dependency]) -> Option<ReusedModule> {
+    let mut new_ids = MutMap::default();
+
+    for (name, old_id) in &cached.module_ids {
+        let dependency = dependencies
+            .iter()
+            .find(|dependency| dependency.name == *name)?;
+
+        if dependency.module_id != *old_id {
+            new_ids.insert(*old_id, dependency.module_id);
+        }
+    }
+
+    let mut type_state = clone_type_state(&cached.type_state);
+
+    if !new_ids.is_empty() {
+        type_state.remap_symbols(&|symbol: Symbol| match new_ids.get(&symbol.module_id()) {
+            Some(module_id) => Symbol::new(*module_id, symbol.ident_id()),
+            None => symbol,
+        });
+    }
+
+    Some(ReusedModule {
+        type_state,
+        doc_entries: cached.doc_entries.clone(),
+    })
+}
+
+/// Something that changes whenever the compiler does, so that types solved by one build of
+/// the compiler (e.g. with different builtins, or a different `Subs` layout) are never
+/// reused by another.
+fn compiler_fingerprint() -> &'static blake3::Hash {
+    static FINGERPRINT: OnceLock<blake3::Hash> = OnceLock::new();
+
+    FINGERPRINT.get_or_init(|| {
+        let mut hasher = blake3::Hasher::new();
+
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+
+        if let Ok(exe) = std::env::current_exe() {
+            hasher.update(exe.as_os_str().as_encoded_bytes());
+
+            if let Ok(metadata) = std::fs::metadata(&exe) {
+                hasher.update(&metadata.len().to_le_bytes());
+                hasher.update(format!("{:?}", metadata.modified().ok()).as_bytes());
+            }
+        }
+
+        hasher.finalize()
+    })
+}
+
+/// Each module gets its own directory, which only ever holds its latest solved types.
+fn module_dir(dir: &Path, path: &Path) -> PathBuf {
+    let path_hash = blake3::hash(path.as_os_str().as_encoded_bytes());
+
+    dir.join(path_hash.to_hex().as_str())
+}
+
+/// The names of the modules that the types refer to, in the order of their ids, and the docs.
+/// Unlike the types and ids, these aren't read in place, so they are stored with bincode.
+type Metadata = (Vec<String>, Option<Vec<DocEntry>>);
+
+fn read_cached_module(dir: &Path, path: &Path, key: blake3::Hash) -> Option<CachedModule> {
+    let file = module_dir(dir, path).join(format!("{}.dat", key.to_hex()));
+    let bytes = std::fs::read(file).ok()?;
+
+    // Deserializing reads values straight out of the bytes, so they have to be aligned
+    // the way they were when they were written (just like the builtins' cached types).
+    let mut aligned = vec![0u128; bytes.len().div_ceil(std::mem::size_of::<u128>())];
+    let aligned_bytes =
+        unsafe { std::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, bytes.len()) };
+    aligned_bytes.copy_from_slice(&bytes);
+
+    let (type_state, offset) = TypeState::deserialize(aligned_bytes);
+
+    // Reading past the end would panic, so check that what follows the types is complete
+    let metadata_start =
+        offset.next_multiple_of(std::mem::align_of::<usize>()) + std::mem::size_of::<usize>();
+    if metadata_start > bytes.len() {
+        return None;
+    }
+
+    let (metadata_len, offset) = bytes::deserialize_slice::<usize>(aligned_bytes, 1, offset);
+    let metadata_end = offset.checked_add(metadata_len[0])?;
+    let (names, doc_entries): Metadata =
+        bincode::deserialize(aligned_bytes.get(offset..metadata_end)?).ok()?;
+
+    let module_ids_end = metadata_end.next_multiple_of(std::mem::align_of::<ModuleId>())
+        + names.len() * std::mem::size_of::<ModuleId>();
+    if module_ids_end != bytes.len() {
+        return None;
+    }
+
+    let (module_ids, _) =
+        bytes::deserialize_vec::<ModuleId>(aligned_bytes, names.len(), metadata_end);
+
+    Some(CachedModule {
+        key,
+        type_state,
+        module_ids: names.into_iter().zip(module_ids).collect(),
+        doc_entries,
+    })
+}
+
+fn write_cached_module(dir: &Path, path: &Path, cached: &CachedModule) -> io::Result<()> {
+    let module_dir = module_dir(dir, path);
+
+    // Only the latest types of a module are kept
+    if let Ok(entries) = std::fs::read_dir(&module_dir) {
+        for entry in entries.flatten() {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    std::fs::create_dir_all(&module_dir)?;
+
+    let (names, module_ids): (Vec<String>, Vec<ModuleId>) =
+        cached.module_ids.iter().cloned().unzip();
+    let metadata = bincode::serialize(&(names, &cached.doc_entries)).map_err(io::Error::other)?;
+
+    // Write to a temporary file first, so that other processes never read a partial file.
+    let mut file = tempfile::NamedTempFile::new_in(&module_dir)?;
+    let mut writer = io::BufWriter::new(&mut file);
+
+    let written = cached.type_state.serialize(&mut writer)?;
+    let written = bytes::serialize_slice(&[metadata.len()], &mut writer, written)?;
+    let written = bytes::serialize_slice(&metadata, &mut writer, written)?;
+    bytes::serialize_slice(&module_ids, &mut writer, written)?;
+    writer.flush()?;
+    drop(writer);
+
+    file.persist(module_dir.join(format!("{}.dat", cached.key.to_hex())))
+        .map_err(|err| err.error)?;
+
+    Ok(())
+}
+
+fn clone_type_state(type_state: &TypeState) -> TypeState {
+    let TypeState {
+        subs,
+        exposed_vars_by_symbol,
+        abilities,
+        solved_implementations,
+    } = type_state;
+
+    TypeState {
+        subs: subs.clone(),
+        exposed_vars_by_symbol: exposed_vars_by_symbol.clone(),
+        abilities: abilities.clone(),
+        solved_implementations: solved_implementations.clone(),
+    }
+}
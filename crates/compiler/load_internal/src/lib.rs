@@ -6,6 +6,7 @@
 use roc_module::symbol::ModuleId;
 pub mod docs;
 pub mod file;
+pub mod incremental;
 pub mod module;
 mod module_cache;
 
@@ -1,5 +1,6 @@
 use crate::docs::ModuleDocumentation;
 use roc_can::constraint::{Constraint as ConstraintSoa, Constraints};
+use roc_can::coverage::CoveragePoint;
 use roc_can::expr::ExpectLookup;
 use roc_can::{
     abilities::AbilitiesStore,
@@ -167,6 +168,18 @@ pub(crate) struct LateSpecializationsModule<'a> {
 #[derive(Debug, Default)]
 pub struct ToplevelExpects {
     pub pure: VecMap<Symbol, Region>,
+    /// For each expect, the top-level def written most recently before it in the source.
+    /// Expects usually sit right below the def they test, so `roc test` can select them by its name.
+    pub preceding_defs: VecMap<Symbol, Symbol>,
+    /// The benchmarks for `roc bench`, by the symbol of the thunk that runs them once
+    pub benchmarks: VecMap<Symbol, ToplevelBenchmark>,
+}
+
+/// A top-level function like `benchSort = \{} -> List.sortAsc numbers`, which `roc bench` measures
+#[derive(Debug, Clone, Copy)]
+pub struct ToplevelBenchmark {
+    pub symbol: Symbol,
+    pub region: Region,
 }
 
 #[derive(Debug)]
@@ -185,6 +198,8 @@ pub struct MonomorphizedModule<'a> {
     pub sources: MutMap<ModuleId, (PathBuf, Box<str>)>,
     pub timings: MutMap<ModuleId, ModuleTiming>,
     pub expectations: VecMap<ModuleId, Expectations>,
+    /// The parts of each module whose runs were counted, for `roc test --coverage`
+    pub coverage_points: MutMap<ModuleId, Vec<CoveragePoint>>,
     pub needs_prebuilt_host: bool,
     pub glue_layouts: GlueLayouts<'a>,
 }
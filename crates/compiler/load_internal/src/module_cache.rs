@@ -4,6 +4,7 @@ use crate::module::{
     ModuleHeader, ParsedModule, TypeCheckedModule,
 };
 use roc_can::abilities::PendingAbilitiesStore;
+use roc_can::coverage::CoveragePoint;
 use roc_can::module::ModuleParams;
 use roc_collections::{MutMap, MutSet, VecMap};
 use roc_module::ident::ModuleName;
@@ -41,10 +42,14 @@ pub(crate) struct ModuleCache<'a> {
     pub(crate) exposed_imports: MutMap<ModuleId, MutMap<Symbol, Region>>,
     pub(crate) top_level_thunks: MutMap<ModuleId, MutSet<Symbol>>,
     pub(crate) documentation: VecMap<ModuleId, ModuleDocumentation>,
+    /// The parts of each module to count runs of, for `roc test --coverage`
+    pub(crate) coverage_points: MutMap<ModuleId, Vec<CoveragePoint>>,
     pub(crate) can_problems: MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
     pub(crate) type_problems: MutMap<ModuleId, Vec<TypeError>>,
 
     pub(crate) sources: MutMap<ModuleId, (PathBuf, &'a str)>,
+    /// Hashes of the `sources`, computed as they are needed
+    pub(crate) source_hashes: MutMap<ModuleId, blake3::Hash>,
 }
 
 impl<'a> ModuleCache<'a> {
@@ -114,9 +119,11 @@ impl Default for ModuleCache<'_> {
             exposes: Default::default(),
             top_level_thunks: Default::default(),
             documentation: Default::default(),
+            coverage_points: Default::default(),
             can_problems: Default::default(),
             type_problems: Default::default(),
             sources: Default::default(),
+            source_hashes: Default::default(),
         }
     }
 }
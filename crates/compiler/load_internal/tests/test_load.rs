@@ -37,7 +37,7 @@ use roc_test_utils_dir::TmpDir;
 use roc_types::pretty_print::name_and_print_var;
 use roc_types::pretty_print::DebugPrint;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn load_and_typecheck(
     arena: &Bump,
@@ -63,6 +63,7 @@ fn load_and_typecheck(
         palette: DEFAULT_PALETTE,
         threading: Threading::Single,
         exec_mode: ExecutionMode::Check,
+        incremental: None,
     };
 
     match roc_load_internal::file::load(
@@ -690,6 +691,194 @@ fn app_dep_types() {
     );
 }
 
+fn load_with_incremental_cache(
+    filename: &Path,
+    incremental: &roc_load_internal::incremental::IncrementalCache,
+) -> LoadedModule {
+    let arena = Bump::new();
+    let load_start = LoadStart::from_path(
+        &arena,
+        filename.to_path_buf(),
+        None,
+        RenderTarget::Generic,
+        RocCacheDir::Disallowed,
+        DEFAULT_PALETTE,
+    )
+    .unwrap();
+    let load_config = LoadConfig {
+        target: TARGET,
+        function_kind: FunctionKind::LambdaSet,
+        render: RenderTarget::Generic,
+        palette: DEFAULT_PALETTE,
+        threading: Threading::Single,
+        exec_mode: ExecutionMode::Check,
+        incremental: Some(incremental.clone()),
+    };
+
+    match roc_load_internal::file::load(
+        &arena,
+        load_start,
+        Default::default(),
+        Default::default(),
+        RocCacheDir::Disallowed,
+        load_config,
+    ) {
+        Ok(LoadResult::TypeChecked(module)) => module,
+        _ => panic!("{} should type-check", filename.display()),
+    }
+}
+
+#[test]
+fn reuse_types_of_unchanged_modules() {
+    use roc_load_internal::incremental::IncrementalCache;
+
+    let filename = fixtures_dir().join("module_with_deps").join("Primary.roc");
+    let incremental = IncrementalCache::new();
+
+    let _first = load_with_incremental_cache(&filename, &incremental);
+    let cached_modules = incremental.len();
+
+    assert!(cached_modules > 1);
+    assert!(incremental
+        .module_paths()
+        .iter()
+        .any(|path| path.ends_with("Primary.roc")));
+
+    assert_eq!(incremental.reused_modules(), 0);
+
+    // every module is unchanged, so all of them are taken from the cache this time
+    let second = load_with_incremental_cache(&filename, &incremental);
+    assert_eq!(incremental.len(), cached_modules);
+    assert_eq!(incremental.reused_modules(), cached_modules);
+
+    expect_types(
+        second,
+        hashmap! {
+            "blah2" => "Frac *",
+            "blah3" => "Str",
+            "str" => "Str",
+            "alwaysThree" => "* -> Frac *",
+            "identity" => "a -> a",
+            "z" => "Frac *",
+            "w" => "Dep1.Identity {}",
+            "succeed" => "a -> Dep1.Identity a",
+            "yay" => "Res.Res {} err",
+            "withDefault" => "Res.Res a err, a -> a",
+        },
+    );
+}
+
+#[test]
+fn reuse_types_of_unchanged_modules_from_disk() {
+    use roc_load_internal::incremental::IncrementalCache;
+
+    let filename = fixtures_dir().join("module_with_deps").join("Primary.roc");
+    let dir = TmpDir::new("tmp/reuse_types_of_unchanged_modules_from_disk");
+
+    let first_cache = IncrementalCache::persistent_in(dir.path().to_path_buf());
+    let _first = load_with_incremental_cache(&filename, &first_cache);
+
+    // one directory per module
+    let cached_modules = std::fs::read_dir(dir.path()).unwrap().count();
+    assert_eq!(cached_modules, first_cache.len());
+
+    // a new cache (like the one a later `roc` process would make) reads the types from disk
+    let second_cache = IncrementalCache::persistent_in(dir.path().to_path_buf());
+    let second = load_with_incremental_cache(&filename, &second_cache);
+    assert_eq!(second_cache.len(), cached_modules);
+    assert_eq!(second_cache.reused_modules(), cached_modules);
+
+    expect_types(
+        second,
+        hashmap! {
+            "blah2" => "Frac *",
+            "blah3" => "Str",
+            "str" => "Str",
+            "alwaysThree" => "* -> Frac *",
+            "identity" => "a -> a",
+            "z" => "Frac *",
+            "w" => "Dep1.Identity {}",
+            "succeed" => "a -> Dep1.Identity a",
+            "yay" => "Res.Res {} err",
+            "withDefault" => "Res.Res a err, a -> a",
+        },
+    );
+}
+
+#[test]
+fn reuse_types_when_module_ids_change() {
+    use roc_load_internal::incremental::IncrementalCache;
+
+    let module_dir = fixtures_dir().join("module_with_deps");
+    let incremental = IncrementalCache::new();
+
+    // Dep3 is the first import of OneDep, but not of Primary, so it gets a different ModuleId
+    let _first = load_with_incremental_cache(&module_dir.join("OneDep.roc"), &incremental);
+    assert_eq!(incremental.reused_modules(), 0);
+
+    let second = load_with_incremental_cache(&module_dir.join("Primary.roc"), &incremental);
+    assert_eq!(incremental.reused_modules(), 1);
+
+    expect_types(
+        second,
+        hashmap! {
+            "blah2" => "Frac *",
+            "blah3" => "Str",
+            "str" => "Str",
+            "alwaysThree" => "* -> Frac *",
+            "identity" => "a -> a",
+            "z" => "Frac *",
+            "w" => "Dep1.Identity {}",
+            "succeed" => "a -> Dep1.Identity a",
+            "yay" => "Res.Res {} err",
+            "withDefault" => "Res.Res a err, a -> a",
+        },
+    );
+}
+
+fn documented_symbols(module: &LoadedModule) -> Vec<(String, Vec<(String, String)>)> {
+    let mut documented = module
+        .docs_by_module
+        .iter()
+        .map(|(_, docs)| {
+            let defs = docs
+                .entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    roc_load_internal::docs::DocEntry::DocDef(DocDef { name, symbol, .. }) => {
+                        Some((name.clone(), symbol.as_str(&module.interns).to_string()))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            (docs.name.clone(), defs)
+        })
+        .collect::<Vec<_>>();
+
+    documented.sort();
+    documented
+}
+
+#[test]
+fn reuse_docs_of_unchanged_modules_from_disk() {
+    use roc_load_internal::incremental::IncrementalCache;
+
+    let filename = fixtures_dir().join("module_with_deps").join("Primary.roc");
+    let dir = TmpDir::new("tmp/reuse_docs_of_unchanged_modules_from_disk");
+
+    let first_cache = IncrementalCache::persistent_in(dir.path().to_path_buf());
+    let first = load_with_incremental_cache(&filename, &first_cache);
+
+    let second_cache = IncrementalCache::persistent_in(dir.path().to_path_buf());
+    let second = load_with_incremental_cache(&filename, &second_cache);
+    assert_eq!(second_cache.reused_modules(), second_cache.len());
+
+    let documented = documented_symbols(&first);
+    assert!(documented.iter().any(|(_, defs)| !defs.is_empty()));
+    assert_eq!(documented_symbols(&second), documented);
+}
+
 #[test]
 fn imported_dep_regression() {
     let subs_by_module = Default::default();
@@ -129,6 +129,19 @@ pub enum LowLevel {
     SetJmp,
     LongJmp,
     SetLongJmpBuffer,
+    /// Counts a run of an instrumented region, for `roc test --coverage`
+    CoverageHit,
+    /// A random value of any type, for the arguments of a property expect like `expect \x -> ...`.
+    /// This is replaced by code that builds the value out of [`LowLevel::ExpectDraw`]s.
+    ExpectArbitrary,
+    /// The next choice (at most the given bound) that `roc test` made for a property expect
+    ExpectDraw,
+    /// Hands the name and rendered value of a snapshot expectation to `roc test`,
+    /// which compares the value with the stored snapshot after the expect ran
+    ExpectSnapshot,
+    /// Stores the result of a benchmark where the optimizer can't see it, so that the work of
+    /// `roc bench` isn't optimized away
+    BenchBlackBox,
 }
 
 macro_rules! higher_order {
@@ -235,6 +248,10 @@ macro_rules! map_symbol_to_lowlevel {
                 LowLevel::SetJmp => unimplemented!(),
                 LowLevel::LongJmp => unimplemented!(),
                 LowLevel::SetLongJmpBuffer => unimplemented!(),
+                LowLevel::CoverageHit => unimplemented!(),
+                LowLevel::ExpectArbitrary => unimplemented!(),
+                LowLevel::ExpectDraw => unimplemented!(),
+                LowLevel::BenchBlackBox => unimplemented!(),
 
                 // these are not implemented, not sure why
                 LowLevel::StrFromInt => unimplemented!(),
@@ -348,4 +365,5 @@ map_symbol_to_lowlevel! {
     Not <= BOOL_NOT;
     Unreachable <= LIST_UNREACHABLE;
     DictPseudoSeed <= DICT_PSEUDO_SEED;
+    ExpectSnapshot <= INSPECT_SNAPSHOT_STR;
 }
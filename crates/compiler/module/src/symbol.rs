@@ -1716,6 +1716,8 @@ define_builtins! {
         31 INSPECT_APPLY: "apply"
         32 INSPECT_TO_INSPECTOR: "toInspector"
         33 INSPECT_TO_STR: "toStr"
+        34 INSPECT_SNAPSHOT: "snapshot"
+        35 INSPECT_SNAPSHOT_STR: "snapshotStr"
     }
     15 TASK: "Task" => {
         0 TASK_TASK: "Task" exposed_type=true // the Task.Task opaque type
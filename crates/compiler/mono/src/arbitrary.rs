@@ -0,0 +1,794 @@
+//! Generating the arguments of property expects, like `expect \x, y -> x + y == y + x`.
+//!
+//! Each argument becomes a [`LowLevel::ExpectArbitrary`], which is replaced here by code that
+//! builds a value of the argument's type out of choices: every [`LowLevel::ExpectDraw`] takes
+//! the next number from a buffer of choices that `roc test` fills in before running the expect.
+//! Choices closer to 0 always give simpler values (shorter lists, smaller numbers, earlier
+//! tags), so `roc test` can shrink a failing input by shrinking its choices, and once the
+//! buffer runs out every further choice is 0.
+use bumpalo::collections::Vec;
+use roc_can::expr::{Expr as CanExpr, Field};
+use roc_collections::SendMap;
+use roc_module::low_level::LowLevel;
+use roc_module::symbol::Symbol;
+use roc_region::all::{Loc, Region};
+use roc_types::subs::{Content, FlatType, GetSubsSlice, Subs, Variable};
+use roc_types::types::AliasKind;
+
+use crate::ir::{
+    cond, runtime_error, with_hole, BranchInfo, Call, CallType, Env, Expr, JoinPointId, Literal,
+    Param, Procs, Stmt,
+};
+use crate::layout::{Builtin, InLayout, Layout, LayoutCache, LayoutRepr};
+
+/// The global pointing at the choices of the current run of a property expect
+pub const CHOICES: &str = "roc__expect_choices";
+/// The global holding how many choices there are
+pub const CHOICES_LEN: &str = "roc__expect_choices_len";
+/// The global counting how many choices the current run has drawn so far
+pub const CHOICES_USED: &str = "roc__expect_choices_used";
+
+/// How many times a value of a recursive type can contain another value of that type
+const MAX_DEPTH: usize = 3;
+
+/// The most elements a generated list (or characters a generated string) can have
+const MAX_LEN: u64 = 8;
+
+/// The strings that generated strings are made of
+const STRING_PIECES: &[&str] = &["a", "b", "z", "A", "0", " ", "-", "\n", "é", "🙂"];
+
+/// Replaces a [`LowLevel::ExpectArbitrary`] of type `var` with code that generates its value.
+pub(crate) fn arbitrary<'a>(
+    env: &mut Env<'a, '_>,
+    procs: &mut Procs<'a>,
+    layout_cache: &mut LayoutCache<'a>,
+    var: Variable,
+    assigned: Symbol,
+    hole: &'a Stmt<'a>,
+) -> Stmt<'a> {
+    let mut generator = Generator {
+        env,
+        procs,
+        layout_cache,
+    };
+
+    generator.value(var, MAX_DEPTH, assigned, hole)
+}
+
+struct Generator<'a, 'r, 'i> {
+    env: &'r mut Env<'a, 'i>,
+    procs: &'r mut Procs<'a>,
+    layout_cache: &'r mut LayoutCache<'a>,
+}
+
+impl<'a, 'r, 'i> Generator<'a, 'r, 'i> {
+    /// Binds `assigned` to a generated value of type `var`, and continues with `hole`.
+    fn value(
+        &mut self,
+        var: Variable,
+        depth: usize,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        let layout = match self
+            .layout_cache
+            .from_var(self.env.arena, var, self.env.subs)
+        {
+            Ok(layout) => layout,
+            Err(_) => {
+                return runtime_error(
+                    self.env,
+                    "The arguments of a property expect must have concrete types",
+                )
+            }
+        };
+
+        let mut var = var;
+        let mut depth = depth;
+
+        loop {
+            match *self.env.subs.get_content_without_compacting(var) {
+                Content::Alias(symbol, _, real, kind) => {
+                    if matches!(symbol, Symbol::DICT_DICT | Symbol::SET_SET) {
+                        // These are opaque for a reason: not every value of their
+                        // representation is a valid dict or set.
+                        return runtime_error(
+                            self.env,
+                            "Property expects can't generate Dicts or Sets yet",
+                        );
+                    }
+
+                    if kind == AliasKind::Opaque && is_number(self.layout_cache, layout) {
+                        return self.number(layout, assigned, hole);
+                    }
+
+                    var = real;
+                }
+                Content::RecursionVar { structure, .. } => {
+                    if depth == 0 {
+                        return runtime_error(self.env, "Generated a value that is too deep");
+                    }
+
+                    depth -= 1;
+                    var = structure;
+                }
+                Content::Structure(flat_type) => {
+                    return self.structure(flat_type, var, layout, depth, assigned, hole);
+                }
+                Content::LambdaSet(_) | Content::ErasedLambda => {
+                    return runtime_error(
+                        self.env,
+                        "Property expects can't generate functions as arguments",
+                    );
+                }
+                Content::FlexVar(_)
+                | Content::RigidVar(_)
+                | Content::FlexAbleVar(_, _)
+                | Content::RigidAbleVar(_, _)
+                | Content::RangedNumber(_)
+                    if is_number(self.layout_cache, layout) =>
+                {
+                    return self.number(layout, assigned, hole);
+                }
+                Content::FlexVar(_)
+                | Content::RigidVar(_)
+                | Content::FlexAbleVar(_, _)
+                | Content::RigidAbleVar(_, _)
+                | Content::RangedNumber(_)
+                | Content::Pure
+                | Content::Effectful
+                | Content::Error => {
+                    return runtime_error(
+                        self.env,
+                        "The arguments of a property expect must have concrete types",
+                    );
+                }
+            }
+        }
+    }
+
+    fn structure(
+        &mut self,
+        flat_type: FlatType,
+        var: Variable,
+        layout: InLayout<'a>,
+        depth: usize,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        let subs: &Subs = self.env.subs;
+
+        match flat_type {
+            FlatType::Apply(Symbol::STR_STR, _) => self.string(assigned, hole),
+            FlatType::Apply(Symbol::LIST_LIST, args) => {
+                let elem_var = subs.get_subs_slice(args)[0];
+
+                self.list(elem_var, layout, depth, assigned, hole)
+            }
+            FlatType::Apply(..) if is_number(self.layout_cache, layout) => {
+                self.number(layout, assigned, hole)
+            }
+            FlatType::Apply(..) => runtime_error(
+                self.env,
+                "Property expects can't generate values of this builtin type yet",
+            ),
+            FlatType::Func(..) | FlatType::EffectfulFunc => runtime_error(
+                self.env,
+                "Property expects can't generate functions as arguments",
+            ),
+            FlatType::EmptyRecord => Stmt::Let(assigned, Expr::Struct(&[]), Layout::UNIT, hole),
+            FlatType::Record(fields, ext) => {
+                let fields: std::vec::Vec<_> = fields
+                    .sorted_iterator(subs, ext)
+                    .map(|(label, field)| (label, *field.as_inner()))
+                    .collect();
+
+                let field_symbols: std::vec::Vec<_> = fields
+                    .iter()
+                    .map(|(label, field_var)| (label.clone(), *field_var, self.env.unique_symbol()))
+                    .collect();
+
+                let mut can_fields = SendMap::default();
+
+                for (label, field_var, symbol) in field_symbols.iter() {
+                    let field = Field {
+                        var: *field_var,
+                        region: Region::zero(),
+                        loc_expr: Box::new(Loc::at_zero(CanExpr::Var(*symbol, *field_var))),
+                    };
+
+                    can_fields.insert(label.clone(), field);
+                }
+
+                let record = CanExpr::Record {
+                    record_var: var,
+                    fields: can_fields,
+                };
+
+                let stmt = self.with_hole(record, var, assigned, hole);
+                let parts = field_symbols
+                    .into_iter()
+                    .map(|(_, var, symbol)| (var, symbol));
+
+                self.values_before(parts, depth, stmt)
+            }
+            FlatType::Tuple(elems, ext) => {
+                let elem_vars: std::vec::Vec<_> = elems
+                    .sorted_iterator(subs, ext)
+                    .map(|(_, elem_var)| elem_var)
+                    .collect();
+
+                let elems: std::vec::Vec<_> = elem_vars
+                    .into_iter()
+                    .map(|elem_var| (elem_var, self.env.unique_symbol()))
+                    .collect();
+
+                let tuple = CanExpr::Tuple {
+                    tuple_var: var,
+                    elems: elems
+                        .iter()
+                        .map(|(elem_var, symbol)| {
+                            let loc_expr = Loc::at_zero(CanExpr::Var(*symbol, *elem_var));
+
+                            (*elem_var, Box::new(loc_expr))
+                        })
+                        .collect(),
+                };
+
+                let stmt = self.with_hole(tuple, var, assigned, hole);
+
+                self.values_before(elems, depth, stmt)
+            }
+            FlatType::TagUnion(tags, ext) | FlatType::RecursiveTagUnion(_, tags, ext) => {
+                let (it, _) = tags.sorted_iterator_and_ext(subs, ext);
+                let tags = it
+                    .map(|(tag_name, args)| (tag_name, args.to_vec()))
+                    .collect();
+
+                self.tag_union(tags, var, ext.var(), layout, depth, assigned, hole)
+            }
+            FlatType::FunctionOrTagUnion(tag_names, _, ext) => {
+                let tags = subs
+                    .get_subs_slice(tag_names)
+                    .iter()
+                    .map(|tag_name| (tag_name.clone(), vec![]))
+                    .collect();
+
+                self.tag_union(tags, var, ext.var(), layout, depth, assigned, hole)
+            }
+            FlatType::EmptyTagUnion => runtime_error(
+                self.env,
+                "Property expects can't generate values of the empty tag union",
+            ),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn tag_union(
+        &mut self,
+        tags: std::vec::Vec<(roc_module::ident::TagName, std::vec::Vec<Variable>)>,
+        var: Variable,
+        ext_var: Variable,
+        layout: InLayout<'a>,
+        depth: usize,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        // Once values are as deep as they can get, only pick tags that end the recursion.
+        let tags: std::vec::Vec<_> = if depth == 0 {
+            tags.into_iter()
+                .filter(|(_, args)| {
+                    !args
+                        .iter()
+                        .any(|arg| contains_recursion(self.env.subs, *arg))
+                })
+                .collect()
+        } else {
+            tags
+        };
+
+        if tags.is_empty() {
+            return runtime_error(self.env, "Generated a value that is too deep");
+        }
+
+        self.one_of(
+            tags.len(),
+            layout,
+            assigned,
+            hole,
+            |generator, index, tag_assigned, jump| {
+                let (tag_name, arg_vars) = &tags[index];
+
+                let args: std::vec::Vec<_> = arg_vars
+                    .iter()
+                    .map(|arg_var| (*arg_var, generator.env.unique_symbol()))
+                    .collect();
+
+                let tag = CanExpr::Tag {
+                    tag_union_var: var,
+                    ext_var,
+                    name: tag_name.clone(),
+                    arguments: args
+                        .iter()
+                        .map(|(arg_var, symbol)| {
+                            (*arg_var, Loc::at_zero(CanExpr::Var(*symbol, *arg_var)))
+                        })
+                        .collect(),
+                };
+
+                let stmt = generator.with_hole(tag, var, tag_assigned, jump);
+
+                generator.values_before(args, depth, stmt)
+            },
+        )
+    }
+
+    fn number(&mut self, layout: InLayout<'a>, assigned: Symbol, hole: &'a Stmt<'a>) -> Stmt<'a> {
+        match self.layout_cache.get_repr(layout) {
+            LayoutRepr::Builtin(Builtin::Int(_)) => self.int(layout, assigned, hole),
+            _ => {
+                // A fraction of a whole number, e.g. 7 / 2
+                let whole = self.env.unique_symbol();
+                let whole_frac = self.env.unique_symbol();
+                let divisor = self.env.unique_symbol();
+                let divisor_plus_one = self.env.unique_symbol();
+                let divisor_frac = self.env.unique_symbol();
+                let one = self.env.unique_symbol();
+                let arena = self.env.arena;
+
+                let stmt = arena.alloc(self.lowlevel(
+                    LowLevel::NumDivFrac,
+                    &[whole_frac, divisor_frac],
+                    layout,
+                    assigned,
+                    hole,
+                ));
+                let stmt = arena.alloc(self.lowlevel(
+                    LowLevel::NumToFrac,
+                    &[divisor_plus_one],
+                    layout,
+                    divisor_frac,
+                    stmt,
+                ));
+                let stmt = arena.alloc(self.lowlevel(
+                    LowLevel::NumAddWrap,
+                    &[divisor, one],
+                    Layout::U64,
+                    divisor_plus_one,
+                    stmt,
+                ));
+                let stmt = arena.alloc(self.int_literal(1, Layout::U64, one, stmt));
+                let stmt = arena.alloc(self.draw(3, divisor, stmt));
+                let stmt = arena.alloc(self.lowlevel(
+                    LowLevel::NumToFrac,
+                    &[whole],
+                    layout,
+                    whole_frac,
+                    stmt,
+                ));
+
+                self.int(Layout::I64, whole, stmt)
+            }
+        }
+    }
+
+    /// Mostly small numbers, but also the largest ones and (for signed integers) negative ones.
+    fn int(&mut self, layout: InLayout<'a>, assigned: Symbol, hole: &'a Stmt<'a>) -> Stmt<'a> {
+        let bits = self.env.unique_symbol();
+        let signed_bits = self.env.unique_symbol();
+        let arena = self.env.arena;
+
+        // Going through I64 sign-extends small negative numbers into 128-bit integers.
+        let stmt = arena.alloc(self.lowlevel(
+            LowLevel::NumIntCast,
+            &[signed_bits],
+            layout,
+            assigned,
+            hole,
+        ));
+        let stmt = arena.alloc(self.lowlevel(
+            LowLevel::NumIntCast,
+            &[bits],
+            Layout::I64,
+            signed_bits,
+            stmt,
+        ));
+
+        self.one_of(
+            3,
+            Layout::U64,
+            bits,
+            stmt,
+            |generator, index, bits, jump| match index {
+                // a small number
+                0 => generator.draw(16, bits, jump),
+                // -1 and below, or the largest unsigned numbers
+                1 => {
+                    let small = generator.env.unique_symbol();
+                    let zero = generator.env.unique_symbol();
+
+                    let arena = generator.env.arena;
+
+                    let stmt = arena.alloc(generator.lowlevel(
+                        LowLevel::NumSubWrap,
+                        &[zero, small],
+                        Layout::U64,
+                        bits,
+                        jump,
+                    ));
+                    let stmt = arena.alloc(generator.int_literal(0, Layout::U64, zero, stmt));
+
+                    generator.draw(16, small, stmt)
+                }
+                // any number at all
+                _ => generator.draw(u64::MAX, bits, jump),
+            },
+        )
+    }
+
+    fn string(&mut self, assigned: Symbol, hole: &'a Stmt<'a>) -> Stmt<'a> {
+        self.repeat(
+            Layout::STR,
+            assigned,
+            hole,
+            |generator, acc, next, jump| {
+                let piece = generator.env.unique_symbol();
+
+                let stmt =
+                    generator.lowlevel(LowLevel::StrConcat, &[acc, piece], Layout::STR, next, jump);
+                let stmt = generator.env.arena.alloc(stmt);
+
+                generator.one_of(
+                    STRING_PIECES.len(),
+                    Layout::STR,
+                    piece,
+                    stmt,
+                    |_, index, piece, jump| {
+                        Stmt::Let(
+                            piece,
+                            Expr::Literal(Literal::Str(STRING_PIECES[index])),
+                            Layout::STR,
+                            jump,
+                        )
+                    },
+                )
+            },
+            |_, empty, hole| Stmt::Let(empty, Expr::Literal(Literal::Str("")), Layout::STR, hole),
+        )
+    }
+
+    fn list(
+        &mut self,
+        elem_var: Variable,
+        layout: InLayout<'a>,
+        depth: usize,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        if depth == 0 {
+            // the elements may be too deep to generate, but they don't have to be
+            let capacity = self.env.unique_symbol();
+            let stmt = self.lowlevel(
+                LowLevel::ListWithCapacity,
+                &[capacity],
+                layout,
+                assigned,
+                hole,
+            );
+
+            return self.int_literal(0, Layout::U64, capacity, self.env.arena.alloc(stmt));
+        }
+
+        self.repeat(
+            layout,
+            assigned,
+            hole,
+            |generator, acc, next, jump| {
+                let elem = generator.env.unique_symbol();
+
+                let stmt = generator.lowlevel(
+                    LowLevel::ListAppendUnsafe,
+                    &[acc, elem],
+                    layout,
+                    next,
+                    jump,
+                );
+
+                generator.value(elem_var, depth, elem, generator.env.arena.alloc(stmt))
+            },
+            |generator, empty, hole| {
+                let capacity = generator.env.unique_symbol();
+                let stmt = generator.lowlevel(
+                    LowLevel::ListWithCapacity,
+                    &[capacity],
+                    layout,
+                    empty,
+                    hole,
+                );
+
+                generator.int_literal(
+                    MAX_LEN as i128,
+                    Layout::U64,
+                    capacity,
+                    generator.env.arena.alloc(stmt),
+                )
+            },
+        )
+    }
+
+    /// Binds `assigned` to the result of taking an `empty` value and extending it with
+    /// `step` a drawn number of times:
+    ///
+    /// ```text
+    /// join done(assigned) = hole
+    /// join loop(acc, i) =
+    ///     if i < len then
+    ///         next = step(acc)
+    ///         jump loop(next, i + 1)
+    ///     else
+    ///         jump done(acc)
+    /// let len = draw(MAX_LEN)
+    /// let empty = ...
+    /// jump loop(empty, 0)
+    /// ```
+    fn repeat(
+        &mut self,
+        layout: InLayout<'a>,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+        step: impl FnOnce(&mut Self, Symbol, Symbol, &'a Stmt<'a>) -> Stmt<'a>,
+        empty: impl FnOnce(&mut Self, Symbol, &'a Stmt<'a>) -> Stmt<'a>,
+    ) -> Stmt<'a> {
+        let arena = self.env.arena;
+
+        let done_id = JoinPointId(self.env.unique_symbol());
+        let loop_id = JoinPointId(self.env.unique_symbol());
+
+        let len = self.env.unique_symbol();
+        let acc = self.env.unique_symbol();
+        let index = self.env.unique_symbol();
+        let next = self.env.unique_symbol();
+        let next_index = self.env.unique_symbol();
+        let one = self.env.unique_symbol();
+        let keep_going = self.env.unique_symbol();
+        let initial = self.env.unique_symbol();
+        let zero = self.env.unique_symbol();
+
+        let loop_again = arena.alloc(Stmt::Jump(loop_id, arena.alloc([next, next_index])));
+        let loop_again = self.lowlevel(
+            LowLevel::NumAddWrap,
+            &[index, one],
+            Layout::U64,
+            next_index,
+            loop_again,
+        );
+        let loop_again = self.int_literal(1, Layout::U64, one, arena.alloc(loop_again));
+        let step = step(self, acc, next, arena.alloc(loop_again));
+
+        let finish = Stmt::Jump(done_id, arena.alloc([acc]));
+        let loop_body = cond(self.env, keep_going, Layout::BOOL, step, finish, layout);
+        let loop_body = self.lowlevel(
+            LowLevel::NumLt,
+            &[index, len],
+            Layout::BOOL,
+            keep_going,
+            arena.alloc(loop_body),
+        );
+
+        let start = arena.alloc(Stmt::Jump(loop_id, arena.alloc([initial, zero])));
+        let start = self.int_literal(0, Layout::U64, zero, start);
+        let start = empty(self, initial, arena.alloc(start));
+        let start = self.draw(MAX_LEN, len, arena.alloc(start));
+
+        let looping = Stmt::Join {
+            id: loop_id,
+            parameters: arena.alloc([
+                Param {
+                    symbol: acc,
+                    layout,
+                },
+                Param {
+                    symbol: index,
+                    layout: Layout::U64,
+                },
+            ]),
+            body: arena.alloc(loop_body),
+            remainder: arena.alloc(start),
+        };
+
+        Stmt::Join {
+            id: done_id,
+            parameters: arena.alloc([Param {
+                symbol: assigned,
+                layout,
+            }]),
+            body: hole,
+            remainder: arena.alloc(looping),
+        }
+    }
+
+    /// Binds `assigned` to the result of one of `count` alternatives, picked by a drawn choice.
+    /// `alternative` builds the alternative with the given index, which must bind its result
+    /// to the given symbol and then continue with the given jump.
+    fn one_of(
+        &mut self,
+        count: usize,
+        layout: InLayout<'a>,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+        mut alternative: impl FnMut(&mut Self, usize, Symbol, &'a Stmt<'a>) -> Stmt<'a>,
+    ) -> Stmt<'a> {
+        if count == 1 {
+            return alternative(self, 0, assigned, hole);
+        }
+
+        let arena = self.env.arena;
+        let join_id = JoinPointId(self.env.unique_symbol());
+        let choice = self.env.unique_symbol();
+
+        let mut branches = Vec::with_capacity_in(count - 1, arena);
+        let mut default_branch = None;
+
+        for index in 0..count {
+            let result = self.env.unique_symbol();
+            let jump = arena.alloc(Stmt::Jump(join_id, arena.alloc([result])));
+            let stmt = alternative(self, index, result, jump);
+
+            if index + 1 < count {
+                branches.push((index as u64, BranchInfo::None, stmt));
+            } else {
+                default_branch = Some((BranchInfo::None, &*arena.alloc(stmt)));
+            }
+        }
+
+        let switch = Stmt::Switch {
+            cond_symbol: choice,
+            cond_layout: Layout::U64,
+            branches: branches.into_bump_slice(),
+            default_branch: default_branch.unwrap(),
+            ret_layout: layout,
+        };
+
+        let remainder = self.draw(count as u64 - 1, choice, arena.alloc(switch));
+
+        Stmt::Join {
+            id: join_id,
+            parameters: arena.alloc([Param {
+                symbol: assigned,
+                layout,
+            }]),
+            body: hole,
+            remainder: arena.alloc(remainder),
+        }
+    }
+
+    /// Generates each of `parts` into its symbol, before running `stmt`.
+    fn values_before(
+        &mut self,
+        parts: impl IntoIterator<Item = (Variable, Symbol)>,
+        depth: usize,
+        stmt: Stmt<'a>,
+    ) -> Stmt<'a> {
+        let parts: std::vec::Vec<_> = parts.into_iter().collect();
+
+        parts
+            .into_iter()
+            .rev()
+            .fold(stmt, |stmt, (part_var, symbol)| {
+                self.value(part_var, depth, symbol, self.env.arena.alloc(stmt))
+            })
+    }
+
+    fn with_hole(
+        &mut self,
+        can_expr: CanExpr,
+        var: Variable,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        with_hole(
+            self.env,
+            can_expr,
+            var,
+            self.procs,
+            self.layout_cache,
+            assigned,
+            hole,
+        )
+    }
+
+    /// Binds `assigned` to the next choice, which is at most `bound`.
+    fn draw(&mut self, bound: u64, assigned: Symbol, hole: &'a Stmt<'a>) -> Stmt<'a> {
+        let bound_symbol = self.env.unique_symbol();
+
+        let stmt = self.lowlevel(
+            LowLevel::ExpectDraw,
+            &[bound_symbol],
+            Layout::U64,
+            assigned,
+            hole,
+        );
+
+        self.int_literal(
+            bound as i128,
+            Layout::U64,
+            bound_symbol,
+            self.env.arena.alloc(stmt),
+        )
+    }
+
+    fn lowlevel(
+        &mut self,
+        op: LowLevel,
+        arguments: &[Symbol],
+        layout: InLayout<'a>,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        let call = Call {
+            call_type: CallType::LowLevel {
+                op,
+                update_mode: self.env.next_update_mode_id(),
+            },
+            arguments: self.env.arena.alloc_slice_copy(arguments),
+        };
+
+        Stmt::Let(assigned, Expr::Call(call), layout, hole)
+    }
+
+    fn int_literal(
+        &mut self,
+        value: i128,
+        layout: InLayout<'a>,
+        assigned: Symbol,
+        hole: &'a Stmt<'a>,
+    ) -> Stmt<'a> {
+        Stmt::Let(
+            assigned,
+            Expr::Literal(Literal::Int(value.to_ne_bytes())),
+            layout,
+            hole,
+        )
+    }
+}
+
+fn is_number<'a>(layout_cache: &LayoutCache<'a>, layout: InLayout<'a>) -> bool {
+    matches!(
+        layout_cache.get_repr(layout),
+        LayoutRepr::Builtin(Builtin::Int(_) | Builtin::Float(_) | Builtin::Decimal)
+    )
+}
+
+/// Whether generating a value of type `var` can need a value of the recursive type it is part
+/// of. Lists don't count, because they can always be empty.
+fn contains_recursion(subs: &Subs, var: Variable) -> bool {
+    match subs.get_content_without_compacting(var) {
+        Content::RecursionVar { .. } => true,
+        Content::Alias(_, _, real, _) => contains_recursion(subs, *real),
+        Content::Structure(flat_type) => match flat_type {
+            FlatType::Apply(Symbol::LIST_LIST, _) => false,
+            FlatType::Apply(_, args) => subs
+                .get_subs_slice(*args)
+                .iter()
+                .any(|arg| contains_recursion(subs, *arg)),
+            FlatType::Record(fields, ext) => fields
+                .sorted_iterator(subs, *ext)
+                .any(|(_, field)| contains_recursion(subs, *field.as_inner())),
+            FlatType::Tuple(elems, ext) => elems
+                .sorted_iterator(subs, *ext)
+                .any(|(_, elem)| contains_recursion(subs, elem)),
+            // a nested tag union only needs recursion if all of its tags do
+            FlatType::TagUnion(tags, ext) | FlatType::RecursiveTagUnion(_, tags, ext) => {
+                let (mut it, _) = tags.sorted_iterator_and_ext(subs, *ext);
+
+                it.all(|(_, args)| args.iter().any(|arg| contains_recursion(subs, *arg)))
+            }
+            FlatType::FunctionOrTagUnion(..)
+            | FlatType::EmptyRecord
+            | FlatType::EmptyTagUnion
+            | FlatType::Func(..)
+            | FlatType::EffectfulFunc => false,
+        },
+        _ => false,
+    }
+}
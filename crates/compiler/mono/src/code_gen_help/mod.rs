@@ -494,6 +494,8 @@ impl<'a> CodeGenHelp<'a> {
             ret_layout,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: false,
+            region: None,
+            statement_regions: &[],
         });
 
         proc_symbol
@@ -876,6 +878,8 @@ impl<'a> CallerProc<'a> {
             ret_layout: Layout::UNIT,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: false,
+            region: None,
+            statement_regions: &[],
         };
 
         if false {
@@ -1018,6 +1022,8 @@ impl<'a> CallerProc<'a> {
             ret_layout: Layout::BOOL,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: false,
+            region: None,
+            statement_regions: &[],
         };
 
         if false {
@@ -1125,6 +1131,8 @@ pub fn test_helper<'a>(
         ret_layout: output_layout,
         is_self_recursive: main_proc.is_self_recursive,
         is_erased: false,
+        region: None,
+        statement_regions: &[],
     }
 }
 
@@ -1325,6 +1333,8 @@ pub fn repl_helper<'a>(
         ret_layout: Layout::UNIT,
         is_self_recursive: main_proc.is_self_recursive,
         is_erased: false,
+        region: None,
+        statement_regions: &[],
     }
 }
 
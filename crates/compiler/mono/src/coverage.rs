@@ -0,0 +1,61 @@
+//! Counting how often parts of a program run, for `roc test --coverage`.
+//!
+//! With coverage enabled, every top-level def, `when` branch and `if` arm starts with a
+//! [`LowLevel::CoverageHit`]. Its two arguments are literals that identify the module and
+//! region it counts, and the backends give every such region a global `u64` counter, named by
+//! [`counter_name`], which the test runner reads after running the expects.
+use roc_module::low_level::LowLevel;
+use roc_module::symbol::ModuleId;
+use roc_region::all::Region;
+
+use crate::ir::{Call, CallType, Env, Expr, Literal, Stmt};
+use crate::layout::Layout;
+
+/// The arguments of the [`LowLevel::CoverageHit`] that counts runs of `region` in `module_id`
+pub fn counter_args(module_id: ModuleId, region: Region) -> (u32, u64) {
+    let module_id: u32 = unsafe { std::mem::transmute(module_id) };
+    let region = ((region.start().offset as u64) << 32) | region.end().offset as u64;
+
+    (module_id, region)
+}
+
+/// The name of the global that counts runs of the region given by [`counter_args`]
+pub fn counter_name(module_id: u32, region: u64) -> String {
+    format!("roc__coverage_{module_id}_{region:x}")
+}
+
+/// Counts a run of `region` every time `stmt` runs, if coverage is enabled.
+pub(crate) fn count_runs<'a>(env: &mut Env<'a, '_>, region: Region, stmt: Stmt<'a>) -> Stmt<'a> {
+    if !env.coverage {
+        return stmt;
+    }
+
+    let (module_id, region) = counter_args(env.home, region);
+
+    let module_id_symbol = env.unique_symbol();
+    let region_symbol = env.unique_symbol();
+    let hit_symbol = env.unique_symbol();
+
+    let hit = Expr::Call(Call {
+        call_type: CallType::LowLevel {
+            op: LowLevel::CoverageHit,
+            update_mode: env.next_update_mode_id(),
+        },
+        arguments: env.arena.alloc([module_id_symbol, region_symbol]),
+    });
+
+    let stmt = Stmt::Let(hit_symbol, hit, Layout::UNIT, env.arena.alloc(stmt));
+    let stmt = Stmt::Let(
+        region_symbol,
+        Expr::Literal(Literal::Int((region as i128).to_ne_bytes())),
+        Layout::U64,
+        env.arena.alloc(stmt),
+    );
+
+    Stmt::Let(
+        module_id_symbol,
+        Expr::Literal(Literal::Int((module_id as i128).to_ne_bytes())),
+        Layout::U32,
+        env.arena.alloc(stmt),
+    )
+}
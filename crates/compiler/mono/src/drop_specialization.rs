@@ -1619,6 +1619,11 @@ fn low_level_no_rc(lowlevel: &LowLevel) -> RC {
         PtrStore => RC::NoRc,
         PtrLoad => RC::NoRc,
         PtrCast => RC::NoRc,
+        CoverageHit => RC::NoRc,
+        ExpectArbitrary => unreachable!("ExpectArbitrary is replaced when specializing"),
+        ExpectDraw => RC::NoRc,
+        ExpectSnapshot => RC::NoRc,
+        BenchBlackBox => RC::NoRc,
 
         PtrClearTagId | RefCountIncRcPtr | RefCountDecRcPtr | RefCountIncDataPtr
         | RefCountDecDataPtr | RefCountIsUnique => {
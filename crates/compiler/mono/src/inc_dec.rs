@@ -1318,6 +1318,13 @@ pub(crate) fn lowlevel_borrow_signature(op: LowLevel) -> &'static [Ownership] {
         PtrLoad => &[OWNED],
         PtrCast => &[OWNED],
 
+        CoverageHit => &[IRRELEVANT, IRRELEVANT],
+        ExpectArbitrary => unreachable!("ExpectArbitrary is replaced when specializing"),
+        ExpectDraw => &[IRRELEVANT],
+        // the strings are kept alive for `roc test` to read after the expect ran
+        ExpectSnapshot => &[OWNED, OWNED],
+        BenchBlackBox => &[BORROWED],
+
         SetJmp | LongJmp | SetLongJmpBuffer => {
             unreachable!("only inserted in dev backend codegen")
         }
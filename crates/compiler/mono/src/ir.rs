@@ -1,5 +1,7 @@
 #![allow(clippy::manual_map)]
 
+use crate::arbitrary;
+use crate::coverage;
 use crate::ir::erased::{build_erased_function, ResolvedErasedLambda};
 use crate::ir::literal::{make_num_literal, IntOrFloatValue};
 use crate::layout::{
@@ -83,7 +85,7 @@ roc_error_macros::assert_sizeof_non_wasm!(ProcLayout, 5 * 8);
 roc_error_macros::assert_sizeof_non_wasm!(Call, 9 * 8);
 roc_error_macros::assert_sizeof_non_wasm!(CallType, 7 * 8);
 
-fn runtime_error<'a>(env: &mut Env<'a, '_>, msg: &'a str) -> Stmt<'a> {
+pub(crate) fn runtime_error<'a>(env: &mut Env<'a, '_>, msg: &'a str) -> Stmt<'a> {
     let sym = env.unique_symbol();
     Stmt::Let(
         sym,
@@ -211,6 +213,8 @@ pub struct PartialProc<'a> {
     pub body: roc_can::expr::Expr,
     pub body_var: Variable,
     pub is_self_recursive: bool,
+    /// The region of the top-level def this is the body of, if any
+    pub toplevel_region: Option<Region>,
 }
 
 impl<'a> PartialProc<'a> {
@@ -240,6 +244,7 @@ impl<'a> PartialProc<'a> {
                     body: body.value,
                     body_var: ret_var,
                     is_self_recursive,
+                    toplevel_region: None,
                 }
             }
 
@@ -257,6 +262,7 @@ impl<'a> PartialProc<'a> {
                     body: roc_can::expr::Expr::RuntimeError(error.value),
                     body_var: ret_var,
                     is_self_recursive: false,
+                    toplevel_region: None,
                 }
             }
         }
@@ -309,6 +315,12 @@ pub struct Proc<'a> {
     pub ret_layout: InLayout<'a>,
     pub is_self_recursive: SelfRecursive,
     pub is_erased: bool,
+    /// The region of the top-level def this proc was specialized from, if any. Debug info
+    /// uses it to map the proc's machine code back to its source.
+    pub region: Option<Region>,
+    /// The regions of the statements in the body, by the symbol each of them binds. Debug info
+    /// uses them for the line table, so stepping through the proc follows its source.
+    pub statement_regions: &'a [(Symbol, Region)],
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -920,6 +932,8 @@ pub struct Procs<'a> {
     pub externals_we_need: BumpMap<ModuleId, ExternalSpecializations<'a>>,
     symbol_specializations: SymbolSpecializations<'a>,
     specialization_stack: SpecializationStack<'a>,
+    /// The regions of the statements lowered so far, see [`Proc::statement_regions`]
+    statement_regions: Vec<'a, (Symbol, Region)>,
 
     pub imported_module_thunks: &'a [Symbol],
     pub module_thunks: &'a [Symbol],
@@ -938,6 +952,7 @@ impl<'a> Procs<'a> {
             host_exposed_lambda_sets: std::vec::Vec::new(),
             symbol_specializations: Default::default(),
             specialization_stack: SpecializationStack(Vec::with_capacity_in(16, arena)),
+            statement_regions: Vec::new_in(arena),
 
             imported_module_thunks: &[],
             module_thunks: &[],
@@ -1110,6 +1125,7 @@ impl<'a> Procs<'a> {
                                         body: body.value,
                                         body_var: ret_var,
                                         is_self_recursive,
+                                        toplevel_region: None,
                                     };
 
                                     self.partial_procs.insert(name.name(), partial_proc);
@@ -1138,6 +1154,7 @@ impl<'a> Procs<'a> {
                                     body: body.value,
                                     body_var: ret_var,
                                     is_self_recursive,
+                                    toplevel_region: None,
                                 };
 
                                 self.partial_procs.insert(name.name(), partial_proc)
@@ -1373,6 +1390,9 @@ pub struct Env<'a, 'i> {
     pub exposed_by_module: &'i ExposedByModule,
     pub derived_module: &'i SharedDerivedModule,
     pub struct_indexing: UsageTrackingMap<(Symbol, u64), Symbol>,
+    /// Whether to count runs of top-level defs, `when` branches and `if` arms,
+    /// for `roc test --coverage`
+    pub coverage: bool,
 }
 
 impl<'a, 'i> Env<'a, 'i> {
@@ -2424,13 +2444,21 @@ fn from_can_let<'a>(
 ) -> Stmt<'a> {
     use roc_can::expr::Expr::*;
 
+    let cont_region = cont.region;
+
     macro_rules! lower_rest {
         ($variable:expr, $expr:expr) => {
             lower_rest!(env, procs, layout_cache, $variable, $expr)
         };
         ($env:expr, $procs:expr, $layout_cache:expr, $variable:expr, $expr:expr) => {
             match opt_assigned_and_hole {
-                None => from_can($env, $variable, $expr, $procs, $layout_cache),
+                None => from_can_located(
+                    $env,
+                    $variable,
+                    Loc::at(cont_region, $expr),
+                    $procs,
+                    $layout_cache,
+                ),
                 Some((assigned, hole)) => with_hole(
                     $env,
                     $expr,
@@ -2703,6 +2731,8 @@ fn from_can_let<'a>(
                     .map(|(lay, (sym, _))| (lay, sym))
                     .chain(zero_specialization)
                 {
+                    procs.statement_regions.push((sym, def.loc_expr.region));
+
                     let literal = make_num_literal(&layout_cache.interner, layout, &str, val);
                     stmt = Stmt::Let(
                         sym,
@@ -2717,6 +2747,8 @@ fn from_can_let<'a>(
             _ => {
                 let rest = lower_rest!(variable, cont.value);
 
+                procs.statement_regions.push((*symbol, def.loc_expr.region));
+
                 with_hole(
                     env,
                     def.loc_expr.value,
@@ -2765,6 +2797,10 @@ fn from_can_let<'a>(
             let outer_symbol = env.unique_symbol();
             stmt = store_pattern(env, procs, layout_cache, &mono_pattern, outer_symbol, stmt);
 
+            procs
+                .statement_regions
+                .push((outer_symbol, def.loc_expr.region));
+
             // convert the def body, store in outer_symbol
             with_hole(
                 env,
@@ -3333,6 +3369,8 @@ fn generate_runtime_error_function<'a>(
         ret_layout,
         is_self_recursive: SelfRecursive::NotSelfRecursive,
         is_erased,
+        region: None,
+        statement_regions: &[],
     }
 }
 
@@ -3428,6 +3466,8 @@ fn generate_host_exposed_function<'a>(
                 ret_layout: result,
                 is_self_recursive: SelfRecursive::NotSelfRecursive,
                 is_erased: false,
+                region: None,
+                statement_regions: &[],
             };
 
             let top_level = ProcLayout::from_raw_named(env.arena, lambda_name, layout);
@@ -3492,6 +3532,8 @@ fn generate_host_exposed_lambda_set<'a>(
         ret_layout: return_layout,
         is_self_recursive: SelfRecursive::NotSelfRecursive,
         is_erased: false,
+        region: None,
+        statement_regions: &[],
     };
 
     let top_level = ProcLayout::new(
@@ -3553,8 +3595,20 @@ fn specialize_proc_help<'a>(
 
     let body = partial_proc.body.clone();
     let body_var = partial_proc.body_var;
+    let toplevel_region = partial_proc.toplevel_region;
 
+    // Specializing the body can specialize other procs along the way, which take their own
+    // statement regions off the end.
+    let regions_start = procs.statement_regions.len();
     let mut specialized_body = from_can(env, body_var, body, procs, layout_cache);
+    let statement_regions: &'a [_] = env
+        .arena
+        .alloc_slice_copy(&procs.statement_regions[regions_start..]);
+    procs.statement_regions.truncate(regions_start);
+
+    if let Some(region) = toplevel_region {
+        specialized_body = coverage::count_runs(env, region, specialized_body);
+    }
 
     let specialized_proc = match specialized {
         SpecializedLayout::FunctionPointerBody {
@@ -3586,6 +3640,8 @@ fn specialize_proc_help<'a>(
                 ret_layout,
                 is_self_recursive: recursivity,
                 is_erased,
+                region: toplevel_region,
+                statement_regions,
             }
         }
         SpecializedLayout::FunctionBody {
@@ -3801,6 +3857,8 @@ fn specialize_proc_help<'a>(
                 ret_layout,
                 is_self_recursive: recursivity,
                 is_erased,
+                region: toplevel_region,
+                statement_regions,
             }
         }
     };
@@ -4671,7 +4729,7 @@ pub fn with_hole<'a>(
                     if is_terminated {
                         let terminator = hole;
 
-                        let mut stmt = with_hole(
+                        let else_stmt = with_hole(
                             env,
                             final_else.value,
                             branch_var,
@@ -4680,6 +4738,7 @@ pub fn with_hole<'a>(
                             assigned,
                             terminator,
                         );
+                        let mut stmt = coverage::count_runs(env, final_else.region, else_stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = env.unique_symbol();
@@ -4693,6 +4752,7 @@ pub fn with_hole<'a>(
                                 assigned,
                                 terminator,
                             );
+                            let then = coverage::count_runs(env, loc_then.region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -4716,7 +4776,7 @@ pub fn with_hole<'a>(
                             .arena
                             .alloc(Stmt::Jump(id, env.arena.alloc([assigned_in_jump])));
 
-                        let mut stmt = with_hole(
+                        let else_stmt = with_hole(
                             env,
                             final_else.value,
                             branch_var,
@@ -4725,6 +4785,7 @@ pub fn with_hole<'a>(
                             assigned_in_jump,
                             terminator,
                         );
+                        let mut stmt = coverage::count_runs(env, final_else.region, else_stmt);
 
                         for (loc_cond, loc_then) in branches.into_iter().rev() {
                             let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -4744,6 +4805,7 @@ pub fn with_hole<'a>(
                                 assigned_in_jump,
                                 terminator,
                             );
+                            let then = coverage::count_runs(env, loc_then.region, then);
 
                             stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -5851,6 +5913,11 @@ pub fn with_hole<'a>(
 
                     Stmt::Let(assigned, expr, layout, hole)
                 }
+                ExpectArbitrary => {
+                    debug_assert!(arg_symbols.is_empty());
+
+                    arbitrary::arbitrary(env, procs, layout_cache, ret_var, assigned, hole)
+                }
                 _ => {
                     let call = self::Call {
                         call_type: CallType::LowLevel {
@@ -7076,7 +7143,9 @@ pub fn from_can<'a>(
                 "invalid condition type in if expression"
             );
 
-            let mut stmt = from_can(env, branch_var, final_else.value, procs, layout_cache);
+            let else_region = final_else.region;
+            let else_stmt = from_can_located(env, branch_var, *final_else, procs, layout_cache);
+            let mut stmt = coverage::count_runs(env, else_region, else_stmt);
 
             for (loc_cond, loc_then) in branches.into_iter().rev() {
                 let branching_symbol = possible_reuse_symbol_or_specialize(
@@ -7086,7 +7155,9 @@ pub fn from_can<'a>(
                     &loc_cond.value,
                     cond_var,
                 );
-                let then = from_can(env, branch_var, loc_then.value, procs, layout_cache);
+                let then_region = loc_then.region;
+                let then = from_can_located(env, branch_var, loc_then, procs, layout_cache);
+                let then = coverage::count_runs(env, then_region, then);
 
                 stmt = cond(env, branching_symbol, cond_layout, then, stmt, ret_layout);
 
@@ -7109,8 +7180,11 @@ pub fn from_can<'a>(
             loc_continuation,
             lookups_in_cond,
         } => {
-            let rest = from_can(env, variable, loc_continuation.value, procs, layout_cache);
+            let rest = from_can_located(env, variable, *loc_continuation, procs, layout_cache);
             let cond_symbol = env.unique_symbol();
+            procs
+                .statement_regions
+                .push((cond_symbol, loc_condition.region));
 
             let mut lookups = Vec::with_capacity_in(lookups_in_cond.len(), env.arena);
             let mut lookup_variables = Vec::with_capacity_in(lookups_in_cond.len(), env.arena);
@@ -7181,7 +7255,10 @@ pub fn from_can<'a>(
             variable: cond_variable,
             symbol: dbg_symbol,
         } => {
-            let rest = from_can(env, variable, loc_continuation.value, procs, layout_cache);
+            let rest = from_can_located(env, variable, *loc_continuation, procs, layout_cache);
+            procs
+                .statement_regions
+                .push((dbg_symbol, loc_message.region));
 
             compile_dbg(
                 env,
@@ -7220,7 +7297,7 @@ pub fn from_can<'a>(
                 unreachable!("recursive value does not have Identifier pattern")
             }
 
-            from_can(env, variable, cont.value, procs, layout_cache)
+            from_can_located(env, variable, *cont, procs, layout_cache)
         }
         LetNonRec(def, cont) => from_can_let(env, procs, layout_cache, def, cont, variable, None),
         _ => {
@@ -7231,6 +7308,34 @@ pub fn from_can<'a>(
     }
 }
 
+/// Like [from_can], but records the region of the returned value, when it gets a statement of
+/// its own rather than being made of other statements.
+fn from_can_located<'a>(
+    env: &mut Env<'a, '_>,
+    variable: Variable,
+    loc_expr: Loc<roc_can::expr::Expr>,
+    procs: &mut Procs<'a>,
+    layout_cache: &mut LayoutCache<'a>,
+) -> Stmt<'a> {
+    use roc_can::expr::Expr::*;
+
+    match loc_expr.value {
+        can_expr @ (When { .. }
+        | If { .. }
+        | Expect { .. }
+        | Dbg { .. }
+        | LetRec(..)
+        | LetNonRec(..)) => from_can(env, variable, can_expr, procs, layout_cache),
+        can_expr => {
+            let symbol = env.unique_symbol();
+            procs.statement_regions.push((symbol, loc_expr.region));
+
+            let hole = env.arena.alloc(Stmt::Ret(symbol));
+            with_hole(env, can_expr, variable, procs, layout_cache, symbol, hole)
+        }
+    }
+}
+
 fn store_specialized_expectation_lookups(
     env: &mut Env,
     lookup_variables: impl IntoIterator<Item = Variable>,
@@ -7256,6 +7361,7 @@ fn to_opt_branches<'a>(
     Pattern<'a>,
     Option<Loc<roc_can::expr::Expr>>,
     roc_can::expr::Expr,
+    Option<Region>,
 )> {
     debug_assert!(!branches.is_empty());
 
@@ -7301,7 +7407,12 @@ fn to_opt_branches<'a>(
                     };
 
                     // TODO remove clone?
-                    opt_branches.push((mono_pattern, when_branch.guard.clone(), loc_expr.value));
+                    opt_branches.push((
+                        mono_pattern,
+                        when_branch.guard.clone(),
+                        loc_expr.value,
+                        Some(when_branch.value.region),
+                    ));
                 }
                 Err(runtime_error) => {
                     // TODO remove clone?
@@ -7309,6 +7420,7 @@ fn to_opt_branches<'a>(
                         Pattern::Underscore,
                         when_branch.guard.clone(),
                         roc_can::expr::Expr::RuntimeError(runtime_error),
+                        Some(when_branch.value.region),
                     ));
                 }
             }
@@ -7322,6 +7434,7 @@ fn to_opt_branches<'a>(
             Pattern::Underscore,
             None,
             roc_can::expr::Expr::RuntimeError(roc_problem::can::RuntimeError::NonExhaustivePattern),
+            None,
         ));
     }
 
@@ -7362,7 +7475,7 @@ fn from_can_when<'a>(
     let arena = env.arena;
     let it = opt_branches
         .into_iter()
-        .filter_map(|(pattern, opt_guard, can_expr)| {
+        .filter_map(|(pattern, opt_guard, can_expr, opt_region)| {
             // If the pattern has a void layout we can drop it; however, we must still perform the
             // work of building the body, because that may contain specializations we must
             // discover for use elsewhere. See
@@ -7380,9 +7493,22 @@ fn from_can_when<'a>(
             };
 
             let branch_stmt = match join_point {
-                None => from_can(env, expr_var, can_expr, procs, layout_cache),
+                None => match opt_region {
+                    Some(region) => from_can_located(
+                        env,
+                        expr_var,
+                        Loc::at(region, can_expr),
+                        procs,
+                        layout_cache,
+                    ),
+                    None => from_can(env, expr_var, can_expr, procs, layout_cache),
+                },
                 Some(id) => {
                     let symbol = env.unique_symbol();
+                    if let Some(region) = opt_region {
+                        procs.statement_regions.push((symbol, region));
+                    }
+
                     let arguments = bumpalo::vec![in env.arena; symbol].into_bump_slice();
                     let jump = env.arena.alloc(Stmt::Jump(id, arguments));
 
@@ -7390,6 +7516,11 @@ fn from_can_when<'a>(
                 }
             };
 
+            let branch_stmt = match opt_region {
+                Some(region) => coverage::count_runs(env, region, branch_stmt),
+                None => branch_stmt,
+            };
+
             use decision_tree::Guard;
             let result = if let Some(loc_expr) = opt_guard {
                 let guard_spec = GuardStmtSpec {
@@ -10375,6 +10506,8 @@ where
             ret_layout: *field,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: false,
+            region: None,
+            statement_regions: &[],
         };
 
         answer.push(GlueProc {
@@ -10470,6 +10603,8 @@ where
             ret_layout: *field,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: false,
+            region: None,
+            statement_regions: &[],
         };
 
         answer.push(GlueProc {
@@ -9,8 +9,10 @@
 // Not a useful lint for us
 #![allow(clippy::too_many_arguments)]
 
+pub mod arbitrary;
 pub mod borrow;
 pub mod code_gen_help;
+pub mod coverage;
 pub mod drop_specialization;
 pub mod inc_dec;
 pub mod ir;
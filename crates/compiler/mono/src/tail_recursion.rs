@@ -783,6 +783,8 @@ impl<'a> TrmcEnv<'a> {
             ret_layout: proc.ret_layout,
             is_self_recursive: SelfRecursive::NotSelfRecursive,
             is_erased: proc.is_erased,
+            region: proc.region,
+            statement_regions: proc.statement_regions,
         }
     }
 
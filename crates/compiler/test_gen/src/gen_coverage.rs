@@ -0,0 +1,63 @@
+#[cfg(feature = "gen-dev")]
+use crate::helpers::dev::expect_coverage;
+
+#[allow(unused_imports)]
+use indoc::indoc;
+
+#[allow(unused_imports)]
+use roc_can::coverage::CoverageKind;
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn counts_defs_and_branches() {
+    let coverage = expect_coverage(indoc!(
+        r#"
+        module [classify, unused]
+
+        classify = \n ->
+            when n is
+                0 -> "zero"
+                _ -> if n > 10 then "big" else "small"
+
+        unused = \n -> n + 1
+
+        { first, second } = { first: "zero", second: 42i64 }
+
+        expect
+            a = classify 0
+            b = classify 20
+            c = classify 30
+
+            a == "zero" && b == "big" && c == "big" && first == "zero" && second == 42
+        "#
+    ));
+
+    let hits: Vec<_> = coverage
+        .iter()
+        .map(|(point, hits)| {
+            let kind = match point.kind {
+                CoverageKind::Def(_) => "def",
+                CoverageKind::WhenBranch => "when branch",
+                CoverageKind::IfArm => "if arm",
+            };
+
+            (kind, *hits)
+        })
+        .collect();
+
+    assert_eq!(
+        hits,
+        [
+            // classify, and its branches
+            ("def", 3),
+            ("when branch", 1),
+            ("when branch", 2),
+            ("if arm", 2),
+            ("if arm", 0),
+            // unused
+            ("def", 0),
+            // the destructure, once for `first` and once for `second`
+            ("def", 2),
+        ]
+    );
+}
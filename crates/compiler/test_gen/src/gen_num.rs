@@ -2771,7 +2771,7 @@ num_conversion_tests! {
 macro_rules! to_int_checked_tests {
     ($($fn:expr, $typ:ty, ($($test_name:ident, $input:expr, $output:expr)*))*) => {$($(
         #[test]
-        #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+        #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
         fn $test_name() {
             let sentinel = 23;
             // Some n = Ok n, None = OutOfBounds
@@ -2892,35 +2892,35 @@ fn wrap_with_default(test_roc_code: &str) -> String {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_i128_checked_smaller_width_pos() {
     let test_roc_code = wrap_with_default("Num.toI128Checked 15i8");
     assert_evals_to!(&test_roc_code, I128::from(15), I128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_i128_checked_smaller_width_neg() {
     let test_roc_code = wrap_with_default("Num.toI128Checked -15i8");
     assert_evals_to!(&test_roc_code, I128::from(-15), I128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_i128_checked_same() {
     let test_roc_code = wrap_with_default("Num.toI128Checked 15i128");
     assert_evals_to!(&test_roc_code, I128::from(15), I128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_i128_checked_same_width_unsigned_fits() {
     let test_roc_code = wrap_with_default("Num.toI128Checked 15u128");
     assert_evals_to!(&test_roc_code, I128::from(15), I128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_i128_checked_same_width_unsigned_oob() {
     let test_roc_code =
         "Result.isErr (Num.toI128Checked 170141183460469231731687303715884105728u128)";
@@ -2928,35 +2928,35 @@ fn to_i128_checked_same_width_unsigned_oob() {
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_u128_checked_smaller_width_pos() {
     let test_roc_code = wrap_with_default("Num.toU128Checked 15i8");
     assert_evals_to!(&test_roc_code, U128::from(15), U128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_u128_checked_smaller_width_neg_oob() {
     let test_roc_code = "Result.isErr (Num.toU128Checked -15i8)";
     assert_evals_to!(&test_roc_code, true, bool)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_u128_checked_same() {
     let test_roc_code = wrap_with_default("Num.toU128Checked 15u128");
     assert_evals_to!(&test_roc_code, U128::from(15), U128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_u128_checked_same_width_signed_fits() {
     let test_roc_code = wrap_with_default("Num.toU128Checked 15i128");
     assert_evals_to!(&test_roc_code, U128::from(15), U128)
 }
 
 #[test]
-#[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
+#[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_u128_checked_same_width_signed_oob() {
     let test_roc_code = "Result.isErr (Num.toU128Checked -1i128)";
     assert_evals_to!(&test_roc_code, true, bool)
@@ -3418,6 +3418,31 @@ fn to_float_f32() {
     )
 }
 
+#[test]
+#[cfg(feature = "gen-dev")]
+fn to_f32_checked() {
+    assert_evals_to!("Num.toF32Checked 100u8", RocResult::ok(100.0), RocResult<f32, ()>);
+    assert_evals_to!("Num.toF32Checked 1.5f64", RocResult::ok(1.5), RocResult<f32, ()>);
+    assert_evals_to!("Num.toF32Checked 1e300f64", RocResult::err(()), RocResult<f32, ()>);
+    assert_evals_to!(
+        "Num.toF32Checked Num.infinityF64",
+        RocResult::ok(f32::INFINITY),
+        RocResult<f32, ()>
+    );
+}
+
+#[test]
+#[cfg(feature = "gen-dev")]
+fn to_f64_checked() {
+    assert_evals_to!("Num.toF64Checked -42i64", RocResult::ok(-42.0), RocResult<f64, ()>);
+    assert_evals_to!("Num.toF64Checked 2.5f32", RocResult::ok(2.5), RocResult<f64, ()>);
+    assert_evals_to!(
+        "Num.toF64Checked Num.maxU128",
+        RocResult::ok(u128::MAX as f64),
+        RocResult<f64, ()>
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn to_float_f64() {
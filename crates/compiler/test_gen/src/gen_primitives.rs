@@ -576,7 +576,6 @@ fn top_level_constant() {
 }
 
 #[test]
-#[ignore]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn top_level_destructure() {
     assert_evals_to!(
@@ -4528,6 +4527,46 @@ fn reset_recursive_type_wraps_in_named_type() {
     );
 }
 
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
+fn dbg_passes_value_through() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            x = 40 + 2
+            dbg x
+
+            name = "a long string that is not stored inline in its RocStr"
+            dbg name
+
+            if Str.isEmpty name then 0 else x
+            "#
+        ),
+        42,
+        i64
+    );
+}
+
+#[test]
+#[cfg(any(feature = "gen-llvm", feature = "gen-dev"))]
+fn inline_expect_does_not_change_result() {
+    assert_evals_to!(
+        indoc!(
+            r#"
+            x = 42
+            expect x == 42
+
+            name = "a long string that is not stored inline in its RocStr"
+            expect name != "" && x > 0
+
+            x + 1
+            "#
+        ),
+        43,
+        i64
+    );
+}
+
 #[test]
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm", feature = "gen-dev"))]
 fn pass_lambda_set_to_function() {
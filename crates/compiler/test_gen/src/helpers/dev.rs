@@ -1,6 +1,8 @@
 use libloading::Library;
 use roc_build::link::{link, LinkType};
+use roc_can::coverage::CoveragePoint;
 use roc_load::{EntryPoint, ExecutionMode, LoadConfig, Threading};
+use roc_mono::coverage;
 use roc_mono::ir::CrashTag;
 use roc_mono::ir::SingleEntryPoint;
 use roc_packaging::cache::RocCacheDir;
@@ -8,7 +10,7 @@ use roc_region::all::LineInfo;
 use roc_solve::FunctionKind;
 use roc_std::RocStr;
 use std::mem::MaybeUninit;
-use tempfile::tempdir;
+use tempfile::{tempdir, TempDir};
 
 #[cfg(any(feature = "gen-llvm", feature = "gen-wasm"))]
 use roc_collections::all::MutMap;
@@ -42,7 +44,6 @@ pub fn helper(
     let dir = tempdir().unwrap();
     let filename = PathBuf::from("Test.roc");
     let src_dir = PathBuf::from("fake/test/path");
-    let app_o_file = dir.path().join("app.o");
 
     let module_src;
     let temp;
@@ -62,6 +63,7 @@ pub fn helper(
         threading: Threading::Single,
         exec_mode: ExecutionMode::Executable,
         function_kind: FunctionKind::LambdaSet,
+        incremental: None,
     };
     let loaded = roc_load::load_and_monomorphize_from_str(
         arena,
@@ -197,6 +199,7 @@ pub fn helper(
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: roc_gen_dev::AssemblyBackendMode::Test,
+        debug_sources: None,
     };
 
     let target = target_lexicon::Triple::host().into();
@@ -206,11 +209,22 @@ pub fn helper(
     let module_out = module_object
         .write()
         .expect("failed to build output object");
+
+    let lib = link_and_load(&dir, &module_out);
+
+    (main_fn_name, delayed_errors, lib)
+}
+
+/// Links the object file `module_out` with the builtins into a dynamic library in `dir`, and
+/// loads it.
+fn link_and_load(dir: &TempDir, module_out: &[u8]) -> Library {
+    let app_o_file = dir.path().join("app.o");
     std::fs::write(&app_o_file, module_out).expect("failed to write object to file");
 
     let builtins_host_tempfile =
         roc_bitcode::host_tempfile().expect("failed to write host builtins object to tempfile");
 
+    let target = target_lexicon::Triple::host().into();
     let (mut child, dylib_path) = link(
         target,
         app_o_file.clone(),
@@ -235,9 +249,93 @@ pub fn helper(
 
     // std::fs::copy(&path, "/tmp/libapp.so").unwrap();
 
-    let lib = unsafe { Library::new(path) }.expect("failed to load shared library");
+    unsafe { Library::new(path) }.expect("failed to load shared library")
+}
 
-    (main_fn_name, delayed_errors, lib)
+/// Runs the single top-level `expect` of the module `src` with coverage enabled, and returns
+/// how often each of the module's coverage points ran.
+#[allow(dead_code)]
+pub(crate) fn expect_coverage(src: &str) -> Vec<(CoveragePoint, u64)> {
+    use std::path::PathBuf;
+
+    let arena = bumpalo::Bump::new();
+    let dir = tempdir().unwrap();
+
+    let load_config = LoadConfig {
+        target: roc_target::Target::LinuxX64,
+        render: roc_reporting::report::RenderTarget::ColorTerminal,
+        palette: roc_reporting::report::DEFAULT_PALETTE,
+        threading: Threading::Single,
+        exec_mode: ExecutionMode::Test { coverage: true },
+        function_kind: FunctionKind::LambdaSet,
+        incremental: None,
+    };
+    let loaded = roc_load::load_and_monomorphize_from_str(
+        &arena,
+        PathBuf::from("Test.roc"),
+        src,
+        PathBuf::from("fake/test/path"),
+        None,
+        RocCacheDir::Disallowed,
+        load_config,
+    )
+    .expect("failed to load module");
+
+    let roc_load::MonomorphizedModule {
+        module_id,
+        procedures,
+        mut interns,
+        mut layout_interner,
+        toplevel_expects,
+        mut coverage_points,
+        ..
+    } = loaded;
+
+    let expects = &toplevel_expects[&module_id].pure;
+    assert_eq!(
+        expects.len(),
+        1,
+        "the module must have one top-level expect"
+    );
+
+    let env = roc_gen_dev::Env {
+        arena: &arena,
+        module_id,
+        exposed_to_host: expects.keys().copied().collect(),
+        lazy_literals: false,
+        mode: roc_gen_dev::AssemblyBackendMode::Test,
+        debug_sources: None,
+    };
+
+    let target = target_lexicon::Triple::host().into();
+    let module_object =
+        roc_gen_dev::build_module(&env, &mut interns, &mut layout_interner, target, procedures);
+
+    let module_out = module_object
+        .write()
+        .expect("failed to build output object");
+
+    let lib = link_and_load(&dir, &module_out);
+
+    run_test_main::<()>(&lib).expect("the expect crashed");
+
+    let points = coverage_points.remove(&module_id).unwrap_or_default();
+
+    points
+        .into_iter()
+        .map(|point| {
+            let (module_id, region) = coverage::counter_args(module_id, point.region);
+            let name = coverage::counter_name(module_id, region);
+
+            // a point whose code was never generated has no counter
+            let hits = match unsafe { lib.get::<*const u64>(name.as_bytes()) } {
+                Ok(counter) => unsafe { (*counter).read_volatile() },
+                Err(_) => 0,
+            };
+
+            (point, hits)
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -76,6 +76,7 @@ fn create_llvm_module<'a>(
         palette: DEFAULT_PALETTE,
         threading: Threading::Single,
         exec_mode: ExecutionMode::Executable,
+        incremental: None,
     };
     let loaded = roc_load::load_and_monomorphize_from_str(
         arena,
@@ -227,6 +228,7 @@ fn create_llvm_module<'a>(
         module,
         target,
         mode: config.mode,
+        debug_sources: None,
         // important! we don't want any procedures to get the C calling convention
         exposed_to_host: MutSet::default(),
     };
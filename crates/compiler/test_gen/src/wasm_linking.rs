@@ -116,6 +116,8 @@ fn build_app_mono<'a>(
         ret_layout: int_layout,
         is_self_recursive: SelfRecursive::NotSelfRecursive,
         is_erased: false,
+        region: None,
+        statement_regions: &[],
     };
 
     let proc_layout = ProcLayout {
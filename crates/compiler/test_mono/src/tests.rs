@@ -130,7 +130,7 @@ fn compiles_to_ir(test_name: &str, src: &str, mode: &str, allow_type_errors: boo
 
     let exec_mode = match mode {
         "exec" => ExecutionMode::Executable,
-        "test" => ExecutionMode::Test,
+        "test" => ExecutionMode::Test { coverage: false },
         _ => panic!("Invalid test_mono exec mode {mode}"),
     };
 
@@ -158,6 +158,7 @@ fn compiles_to_ir(test_name: &str, src: &str, mode: &str, allow_type_errors: boo
         render: roc_reporting::report::RenderTarget::Generic,
         palette: roc_reporting::report::DEFAULT_PALETTE,
         exec_mode,
+        incremental: None,
     };
     let loaded = roc_load::load_and_monomorphize_from_str(
         arena,
@@ -1961,6 +1961,38 @@ impl Subs {
         result
     }
 
+    /// Replaces every symbol these types refer to with `remap(symbol)`. Types solved in one
+    /// load can be reused by a later load this way, even if it gave the modules they refer to
+    /// different ids.
+    pub fn remap_symbols(&mut self, remap: &impl Fn(Symbol) -> Symbol) {
+        for symbol in self.symbol_names.iter_mut() {
+            *symbol = remap(*symbol);
+        }
+
+        for Uls(_, symbol, _) in self.unspecialized_lambda_sets.iter_mut() {
+            *symbol = remap(*symbol);
+        }
+
+        for v in 0..self.utable.len() {
+            let var = unsafe { Variable::from_index(v as u32) };
+            if self.utable.root_key_without_compacting(var) != var {
+                continue;
+            }
+
+            let content = match *self.get_content_without_compacting(var) {
+                Content::Alias(symbol, arguments, real_var, kind) => {
+                    Content::Alias(remap(symbol), arguments, real_var, kind)
+                }
+                Content::Structure(FlatType::Apply(symbol, arguments)) => {
+                    Content::Structure(FlatType::Apply(remap(symbol), arguments))
+                }
+                _ => continue,
+            };
+
+            self.set_content_unchecked(var, content);
+        }
+    }
+
     /// Returns the new recursion variable, which should be introduced to the environment as
     /// appropriate.
     #[must_use]
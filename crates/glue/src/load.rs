@@ -59,6 +59,7 @@ pub fn generate(
                 backend,
                 opt_level: OptLevel::Development,
                 emit_debug_info: false,
+                emit_source_locations: false,
                 emit_llvm_ir: false,
                 fuzz: false,
             };
@@ -430,6 +431,7 @@ pub fn load_types(
             palette: DEFAULT_PALETTE,
             threading,
             exec_mode: ExecutionMode::Check,
+            incremental: None,
         },
     )
     .unwrap_or_else(|problem| match problem {
@@ -9,27 +9,48 @@ use bumpalo::Bump;
 use parking_lot::Mutex;
 use roc_can::{abilities::AbilitiesStore, expr::Declarations};
 use roc_collections::{MutMap, MutSet, VecMap};
-use roc_load::{docs::ModuleDocumentation, CheckedModule, LoadedModule};
+use roc_load::{
+    docs::ModuleDocumentation, CheckedModule, ExecutionMode, LoadResult, LoadStart, LoadedModule,
+    SourceOverrides,
+};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_packaging::cache::{self, RocCacheDir};
-use roc_region::all::LineInfo;
+use roc_region::all::{LineInfo, Region};
 use roc_reporting::report::RocDocAllocator;
 use roc_solve_problem::TypeError;
 use roc_types::subs::{Subs, Variable};
 
-use tower_lsp::lsp_types::{Diagnostic, SemanticTokenType, Url};
+use tower_lsp::lsp_types::{CodeAction, Diagnostic, SemanticTokenType, Url};
 
 mod analysed_doc;
+mod code_lens;
 mod completion;
+mod formatting;
+mod hierarchy;
+mod imports;
+mod inlay_hints;
 mod parse_ast;
+mod quick_fix;
+mod references;
 mod semantic_tokens;
+mod signature_help;
+mod symbols;
 mod tokens;
 mod utils;
 
 use crate::convert::diag::{IntoLspDiagnostic, ProblemFmt};
 
 pub(crate) use self::analysed_doc::{AnalyzedDocument, DocInfo};
-use self::{analysed_doc::ModuleIdToUrl, tokens::Token};
+pub(crate) use self::code_lens::{
+    command_location, command_value, LensResults, DEBUG_EXPECT_COMMAND, EVALUATE_COMMAND,
+    RUN_EXPECT_COMMAND,
+};
+pub(crate) use self::references::{is_valid_name, RenameEdit};
+use self::{
+    analysed_doc::ModuleIdToUrl,
+    quick_fix::{ImportedModule, QuickFixes},
+    tokens::Token,
+};
 
 pub const HIGHLIGHT_TOKENS_LEGEND: &[SemanticTokenType] = Token::LEGEND;
 
@@ -98,25 +119,51 @@ pub(super) struct AnalyzedModule {
 pub struct AnalysisResult {
     module: Option<AnalyzedModule>,
     diagnostics: Vec<Diagnostic>,
+    /// Quick fixes for the diagnostics
+    code_actions: Vec<CodeAction>,
+    /// The names in `exposing [...]` lists of imports that the module doesn't use
+    unused_imports: Vec<Region>,
 }
 
-pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
+/// Analyzes the document and every module it imports. The modules that are open in the editor,
+/// `open_documents`, are loaded from their unsaved contents rather than from disk.
+pub(crate) fn global_analysis(
+    doc_info: DocInfo,
+    open_documents: Vec<DocInfo>,
+) -> Vec<AnalyzedDocument> {
     let fi = doc_info.url.to_file_path().unwrap();
     let src_dir = find_src_dir(&fi).to_path_buf();
 
+    let mut open_versions = HashMap::with_capacity(open_documents.len());
+    let mut source_overrides = SourceOverrides::default();
+
+    for open_document in open_documents {
+        if let Ok(path) = open_document.url.to_file_path() {
+            open_versions.insert(open_document.url, open_document.version);
+            source_overrides.insert(path, open_document.source.into_boxed_str());
+        }
+    }
+
     let arena = Bump::new();
-    let loaded = roc_load::load_and_typecheck_str(
-        &arena,
-        fi,
-        &doc_info.source,
-        src_dir,
-        None,
-        roc_target::Target::LinuxX64,
-        roc_load::FunctionKind::LambdaSet,
-        roc_reporting::report::RenderTarget::LanguageServer,
-        RocCacheDir::Persistent(cache::roc_cache_packages_dir().as_path()),
-        roc_reporting::report::DEFAULT_PALETTE,
-    );
+    let packages_dir = cache::roc_cache_packages_dir();
+    let roc_cache_dir = RocCacheDir::Persistent(&packages_dir);
+    let loaded = LoadStart::from_str(&arena, fi, None, &doc_info.source, roc_cache_dir, src_dir)
+        .and_then(|load_start| {
+            roc_load::load_single_threaded(
+                &arena,
+                load_start.with_source_overrides(source_overrides),
+                roc_target::Target::LinuxX64,
+                roc_load::FunctionKind::LambdaSet,
+                roc_reporting::report::RenderTarget::LanguageServer,
+                roc_reporting::report::DEFAULT_PALETTE,
+                roc_cache_dir,
+                ExecutionMode::Check,
+            )
+        })
+        .map(|load_result| match load_result {
+            LoadResult::TypeChecked(module) => module,
+            LoadResult::Monomorphized(_) => unreachable!("only type checking was requested"),
+        });
 
     let module = match loaded {
         Ok(module) => module,
@@ -131,6 +178,8 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
                 analysis_result: AnalysisResult {
                     module: None,
                     diagnostics: all_problems,
+                    code_actions: Vec::new(),
+                    unused_imports: Vec::new(),
                 },
             };
 
@@ -169,9 +218,12 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
         docs_by_module,
     ));
 
+    let imported_modules = imported_modules(&sources, &typechecked, &declarations_by_id);
+
     let mut builder = AnalyzedDocumentBuilder {
         interns: &interns,
         module_id_to_url: module_id_to_url_from_sources(&sources),
+        imported_modules,
         can_problems: &mut can_problems,
         type_problems: &mut type_problems,
         declarations_by_id: &mut declarations_by_id,
@@ -183,7 +235,13 @@ pub(crate) fn global_analysis(doc_info: DocInfo) -> Vec<AnalyzedDocument> {
     };
 
     for (module_id, (path, source)) in sources {
-        let doc = builder.build_document(path, source, module_id, doc_info.version);
+        // the modules that are open were loaded from the version of them in the editor
+        let version = open_versions
+            .get(&path_to_url(&path))
+            .copied()
+            .unwrap_or(doc_info.version);
+
+        let doc = builder.build_document(path, source, module_id, version);
         documents.push(doc);
     }
 
@@ -244,6 +302,36 @@ fn module_id_to_url_from_sources(sources: &MutMap<ModuleId, (PathBuf, Box<str>)>
         .collect()
 }
 
+/// The url, source and top-level defs of each module, for the quick fixes in the modules that
+/// import it
+fn imported_modules(
+    sources: &MutMap<ModuleId, (PathBuf, Box<str>)>,
+    typechecked: &MutMap<ModuleId, CheckedModule>,
+    declarations_by_id: &MutMap<ModuleId, Declarations>,
+) -> MutMap<ModuleId, ImportedModule> {
+    sources
+        .iter()
+        .filter_map(|(module_id, (path, source))| {
+            let declarations = match typechecked.get(module_id) {
+                Some(checked) => &checked.decls,
+                None => declarations_by_id.get(module_id)?,
+            };
+
+            let module = ImportedModule {
+                url: path_to_url(path),
+                source: source.clone(),
+                top_level: declarations
+                    .symbols
+                    .iter()
+                    .map(|loc_symbol| loc_symbol.value)
+                    .collect(),
+            };
+
+            Some((*module_id, module))
+        })
+        .collect()
+}
+
 fn path_to_url(path: &Path) -> Url {
     if path.is_relative() {
         // Make it <tmpdir>/path
@@ -262,6 +350,7 @@ struct RootModule {
 struct AnalyzedDocumentBuilder<'a> {
     interns: &'a Interns,
     module_id_to_url: ModuleIdToUrl,
+    imported_modules: MutMap<ModuleId, ImportedModule>,
     can_problems: &'a mut MutMap<ModuleId, Vec<roc_problem::can::Problem>>,
     type_problems: &'a mut MutMap<ModuleId, Vec<TypeError>>,
     declarations_by_id: &'a mut MutMap<ModuleId, Declarations>,
@@ -313,12 +402,21 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
             module_id_to_url: self.module_id_to_url.clone(),
         };
 
+        let url = path_to_url(&path);
         let line_info = LineInfo::new(&source);
-        let diagnostics = self.build_diagnostics(&path, &source, &line_info, module_id);
+        let unused_imports = self.unused_imports(module_id);
+        let (diagnostics, code_actions) = self.build_diagnostics(
+            &path,
+            &url,
+            &source,
+            &line_info,
+            &analyzed_module.declarations,
+            module_id,
+        );
 
         AnalyzedDocument {
             doc_info: DocInfo {
-                url: path_to_url(&path),
+                url,
                 line_info,
                 source: source.into(),
                 version,
@@ -326,6 +424,8 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
             analysis_result: AnalysisResult {
                 module: Some(analyzed_module),
                 diagnostics,
+                code_actions,
+                unused_imports,
             },
         }
     }
@@ -350,40 +450,78 @@ impl<'a> AnalyzedDocumentBuilder<'a> {
             .collect::<HashMap<_, _>>()
     }
 
+    fn unused_imports(&self, module_id: ModuleId) -> Vec<Region> {
+        self.can_problems
+            .get(&module_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|problem| match problem {
+                roc_problem::can::Problem::UnusedImport(_, region) => Some(*region),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Builds the diagnostics for the problems in a module, along with the quick fixes for them.
     fn build_diagnostics(
         &mut self,
         source_path: &Path,
+        url: &Url,
         source: &str,
         line_info: &LineInfo,
+        declarations: &Declarations,
         module_id: ModuleId,
-    ) -> Vec<Diagnostic> {
+    ) -> (Vec<Diagnostic>, Vec<CodeAction>) {
         let lines: Vec<_> = source.lines().collect();
 
         let alloc = RocDocAllocator::new(&lines, module_id, self.interns);
 
         let mut all_problems = Vec::new();
+        let mut code_actions = Vec::new();
         let fmt = ProblemFmt {
             alloc: &alloc,
             line_info,
             path: source_path,
         };
+        let quick_fixes = QuickFixes {
+            source,
+            line_info,
+            interns: self.interns,
+            declarations,
+            alloc: &alloc,
+            imported_modules: &self.imported_modules,
+        };
 
         let can_problems = self.can_problems.remove(&module_id).unwrap_or_default();
 
         let type_problems = self.type_problems.remove(&module_id).unwrap_or_default();
 
         for can_problem in can_problems {
+            let fixes = quick_fixes.for_can_problem(&can_problem);
+
             if let Some(diag) = can_problem.into_lsp_diagnostic(&fmt) {
+                code_actions.extend(
+                    fixes
+                        .into_iter()
+                        .map(|fix| fix.into_code_action(url, &diag)),
+                );
                 all_problems.push(diag);
             }
         }
 
         for type_problem in type_problems {
+            let fixes = quick_fixes.for_type_problem(&type_problem);
+
             if let Some(diag) = type_problem.into_lsp_diagnostic(&fmt) {
+                code_actions.extend(
+                    fixes
+                        .into_iter()
+                        .map(|fix| fix.into_code_action(url, &diag)),
+                );
                 all_problems.push(diag);
             }
         }
 
-        all_problems
+        (all_problems, code_actions)
     }
 }
@@ -1,35 +1,71 @@
 use log::{debug, info};
 use roc_fmt::MigrationFlags;
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use bumpalo::Bump;
 
 use roc_module::symbol::{ModuleId, Symbol};
+use roc_parse::ast::TypeDef;
 
 use roc_region::all::LineInfo;
 
 use tower_lsp::lsp_types::{
-    CompletionItem, Diagnostic, GotoDefinitionResponse, Hover, HoverContents, LanguageString,
-    Location, MarkedString, Position, Range, SemanticTokens, SemanticTokensResult, TextEdit, Url,
+    CallHierarchyIncomingCall, CallHierarchyItem, CodeAction, CodeActionKind, CodeActionOrCommand,
+    CodeLens, CompletionItem, CompletionItemKind, Diagnostic, DocumentSymbol, FoldingRange,
+    GotoDefinitionResponse, Hover, HoverContents, InlayHint, LanguageString, Location,
+    MarkedString, Position, Range, SemanticTokens, SemanticTokensResult, SignatureHelp,
+    SymbolInformation, SymbolKind, TextEdit, TypeHierarchyItem, Url, WorkspaceEdit,
 };
 
 use crate::{
     analysis::completion::{
         field_completion, get_completion_items, get_module_completion_items,
-        get_tag_completion_items,
+        get_tag_completion_items, make_completion_items,
     },
     convert::{ToRange, ToRocPosition},
 };
 
 use super::{
+    code_lens::{code_lenses, LensResults},
+    formatting::{indent_after_newline, minimal_edits, touches_lines},
+    hierarchy::{Hierarchies, Implementation},
+    imports::{add_import, organize_imports, package_dirs},
+    inlay_hints::{type_hints, TypeHint},
     parse_ast::Ast,
+    references::{references_at_occurrences, references_in_names, Reference},
     semantic_tokens::arrange_semantic_tokens,
+    signature_help::{call_at_end, signature_help},
+    symbols::{document_symbols, folding_ranges, fuzzy_matches},
     utils::{format_var_type, is_roc_identifier_char},
     AnalysisResult, AnalyzedModule,
 };
 
 pub(super) type ModuleIdToUrl = HashMap<ModuleId, Url>;
 
+/// Whether `kind` is one of `only`, or a more specific kind of one of them, like
+/// `source.organizeImports` is of `source`.
+fn is_kind_of(kind: &Option<CodeActionKind>, only: &[CodeActionKind]) -> bool {
+    let Some(kind) = kind else {
+        return false;
+    };
+
+    only.iter().any(|requested| {
+        kind.as_str() == requested.as_str()
+            || kind
+                .as_str()
+                .strip_prefix(requested.as_str())
+                .map_or(false, |rest| rest.starts_with('.'))
+    })
+}
+
+/// A top-level symbol, identified by the url of its module and its name. Unlike a [Symbol],
+/// this holds across analyses, so it can be looked up in every document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GlobalSymbol {
+    module_url: Url,
+    name: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct AnalyzedDocument {
     pub doc_info: DocInfo,
@@ -68,12 +104,6 @@ impl DocInfo {
         );
     }
 
-    fn whole_document_range(&self) -> Range {
-        let start = Position::new(0, 0);
-        let end = Position::new(self.line_info.num_lines(), 0);
-        Range::new(start, end)
-    }
-
     pub fn get_prefix_at_position(&self, position: Position) -> String {
         let position = position.to_roc_position(&self.line_info);
         let offset = position.offset as usize;
@@ -93,23 +123,60 @@ impl DocInfo {
         String::from(symbol)
     }
 
-    pub fn format(&self) -> Option<Vec<TextEdit>> {
-        let source = &self.source;
+    fn formatted(&self) -> Option<String> {
         let arena = &Bump::new();
 
-        let ast = Ast::parse(arena, source).ok()?;
+        let ast = Ast::parse(arena, &self.source).ok()?;
         let flags = MigrationFlags {
             snakify: false,
             parens_and_commas: false,
         };
-        let fmt = ast.fmt(flags);
 
-        if source == fmt.as_str() {
-            None
-        } else {
-            let range = self.whole_document_range();
-            let text_edit = TextEdit::new(range, fmt.to_string().to_string());
-            Some(vec![text_edit])
+        let formatted = ast.fmt(flags).as_str().to_string();
+
+        Some(formatted)
+    }
+
+    /// The edits that format the document, which only touch what the formatter changes.
+    pub fn format(&self) -> Option<Vec<TextEdit>> {
+        let formatted = self.formatted()?;
+        let edits = minimal_edits(&self.source, &formatted, &self.line_info);
+
+        (!edits.is_empty()).then_some(edits)
+    }
+
+    /// The edits that format the lines that `range` spans.
+    pub fn format_range(&self, range: Range) -> Option<Vec<TextEdit>> {
+        let edits: Vec<_> = self
+            .format()?
+            .into_iter()
+            .filter(|edit| touches_lines(edit, range))
+            .collect();
+
+        (!edits.is_empty()).then_some(edits)
+    }
+
+    /// The edits after `ch` is typed at `position`. Newlines after the start of a block indent
+    /// the line for its body.
+    pub fn format_on_type(&self, position: Position, ch: &str) -> Option<Vec<TextEdit>> {
+        if ch != "\n" {
+            return None;
+        }
+
+        Some(vec![indent_after_newline(&self.source, position)?])
+    }
+
+    /// The edit that adds `import <module_name> exposing [<name>]` to the document.
+    pub fn add_import(&self, module_name: &str, name: &str) -> Option<TextEdit> {
+        add_import(&self.source, &self.line_info, module_name, name)
+    }
+
+    /// The shorthands of the packages that the document's header names, along with the
+    /// directory of each package.
+    pub fn package_dirs(&self) -> Vec<(String, PathBuf)> {
+        match self.url.to_file_path() {
+            Ok(path) => package_dirs(&self.source, &path),
+            Err(()) => Vec::new(),
         }
     }
 
@@ -157,6 +224,230 @@ impl AnalyzedDocument {
         self.analysis_result.diagnostics.clone()
     }
 
+    /// Whether this is one of the builtin modules, like `List`, rather than a file of the user's
+    pub fn is_builtin(&self) -> bool {
+        self.module()
+            .map_or(false, |module| module.module_id.is_builtin())
+    }
+
+    /// The documents of the modules that this document imports directly
+    pub fn imported_urls(&self) -> Vec<Url> {
+        let Some(module) = self.module() else {
+            return Vec::new();
+        };
+
+        module
+            .imports_by_module
+            .keys()
+            .filter_map(|module_id| module.module_id_to_url.get(module_id).cloned())
+            .collect()
+    }
+
+    /// The quick fixes for the diagnostics that overlap `range`, and the type annotations that
+    /// can be added to the defs in it, of the kinds in `only` if it is given.
+    pub fn code_actions(
+        &self,
+        range: Range,
+        only: Option<&[CodeActionKind]>,
+    ) -> Vec<CodeActionOrCommand> {
+        let quick_fixes = self
+            .analysis_result
+            .code_actions
+            .iter()
+            .filter(|action| {
+                action.diagnostics.iter().flatten().any(|diagnostic| {
+                    diagnostic.range.start <= range.end && range.start <= diagnostic.range.end
+                })
+            })
+            .cloned();
+
+        let annotations = self.module().into_iter().flat_map(|module| {
+            type_hints(module, self.line_info(), range)
+                .into_iter()
+                .filter_map(|hint| hint.annotation_action(self.url()))
+        });
+
+        quick_fixes
+            .chain(annotations)
+            .filter(|action| only.map_or(true, |only| is_kind_of(&action.kind, only)))
+            .chain(only.and_then(|only| self.organize_imports(only)))
+            .map(CodeActionOrCommand::CodeAction)
+            .collect()
+    }
+
+    /// The source action that sorts and merges the imports and removes the unused names they
+    /// expose. Source actions are only offered when one of `only` asks for them, so that they
+    /// don't crowd out the quick fixes.
+    fn organize_imports(&self, only: &[CodeActionKind]) -> Option<CodeAction> {
+        let kind = CodeActionKind::SOURCE_ORGANIZE_IMPORTS;
+
+        if !is_kind_of(&Some(kind.clone()), only) {
+            return None;
+        }
+
+        let edits = organize_imports(
+            &self.doc_info.source,
+            self.line_info(),
+            &self.analysis_result.unused_imports,
+        );
+
+        if edits.is_empty() {
+            return None;
+        }
+
+        Some(CodeAction {
+            title: "Organize imports".to_string(),
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit::new(HashMap::from([(
+                self.url().clone(),
+                edits,
+            )]))),
+            ..Default::default()
+        })
+    }
+
+    /// The name that other modules import this one by, unless it is an app, package or
+    /// platform, which can't be imported.
+    pub fn module_name(&self) -> Option<String> {
+        let AnalyzedModule {
+            module_id, interns, ..
+        } = self.module()?;
+
+        let arena = Bump::new();
+        Ast::parse(&arena, &self.doc_info.source)
+            .ok()?
+            .module_exposes()?;
+
+        Some(module_id.to_ident_str(interns).to_string())
+    }
+
+    /// Completions for the values and types that the module exposes whose names start with
+    /// `prefix`, for the modules that don't import it yet.
+    pub fn exposed_completion_items(&self, prefix: &str) -> Vec<CompletionItem> {
+        let Some(AnalyzedModule {
+            module_id,
+            interns,
+            subs,
+            declarations,
+            modules_info,
+            ..
+        }) = self.module()
+        else {
+            return Vec::new();
+        };
+
+        let arena = Bump::new();
+        let Some(ast) = Ast::parse(&arena, &self.doc_info.source).ok() else {
+            return Vec::new();
+        };
+        let names: Vec<&str> = ast
+            .module_exposes()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|loc_name| loc_name.value)
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+
+        let values = declarations
+            .symbols
+            .iter()
+            .zip(declarations.variables.iter())
+            .filter(|(loc_symbol, _)| names.contains(&loc_symbol.value.as_str(interns)))
+            .map(|(loc_symbol, var)| (loc_symbol.value, *var))
+            .collect();
+
+        let mut items = make_completion_items(
+            &mut subs.clone(),
+            module_id,
+            interns,
+            modules_info.get_docs(module_id),
+            values,
+        );
+
+        items.extend(ast.type_defs().filter_map(|loc_type_def| {
+            let (header, kind) = match loc_type_def.value {
+                TypeDef::Alias { header, .. } => (header, CompletionItemKind::CLASS),
+                TypeDef::Opaque { header, .. } => (header, CompletionItemKind::STRUCT),
+                TypeDef::Ability { header, .. } => (header, CompletionItemKind::INTERFACE),
+            };
+
+            names.contains(&header.name.value).then(|| CompletionItem {
+                label: header.name.value.to_string(),
+                kind: Some(kind),
+                ..Default::default()
+            })
+        }));
+
+        items
+    }
+
+    /// The inferred types of the bindings in `range` that have no annotation.
+    pub fn inlay_hints(&self, range: Range) -> Option<Vec<InlayHint>> {
+        let hints = type_hints(self.module()?, self.line_info(), range);
+
+        Some(hints.into_iter().map(TypeHint::into_inlay_hint).collect())
+    }
+
+    /// The outline of the document: its defs and type definitions, with nested defs as children.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        document_symbols(self.module(), &self.doc_info.source, self.line_info())
+    }
+
+    /// The top-level defs, type definitions and ability members whose names fuzzy match `query`.
+    #[allow(deprecated)]
+    pub fn workspace_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let container_name = self
+            .url()
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file_name| file_name.strip_suffix(".roc"))
+            .map(str::to_string);
+
+        let with_members = self.document_symbols().into_iter().flat_map(|symbol| {
+            let members = match symbol.kind {
+                SymbolKind::INTERFACE => symbol.children.clone().unwrap_or_default(),
+                _ => Vec::new(),
+            };
+
+            std::iter::once(symbol).chain(members)
+        });
+
+        with_members
+            .filter(|symbol| fuzzy_matches(query, &symbol.name))
+            .map(|symbol| SymbolInformation {
+                name: symbol.name,
+                kind: symbol.kind,
+                tags: None,
+                deprecated: None,
+                location: self.location(symbol.selection_range),
+                container_name: container_name.clone(),
+            })
+            .collect()
+    }
+
+    /// The multi-line defs and expressions that can be folded.
+    pub fn folding_ranges(&self) -> Option<Vec<FoldingRange>> {
+        let AnalyzedModule { declarations, .. } = self.module()?;
+
+        Some(folding_ranges(declarations, self.line_info()))
+    }
+
+    /// The lenses to run the top-level expects and evaluate the top-level values, showing
+    /// `results` if they are of this version of the document.
+    pub fn code_lenses(&self, results: Option<&LensResults>) -> Vec<CodeLens> {
+        let Some(AnalyzedModule {
+            declarations,
+            interns,
+            ..
+        }) = self.module()
+        else {
+            return Vec::new();
+        };
+        let results = results.filter(|results| results.version == self.doc_info.version);
+
+        code_lenses(declarations, interns, self.line_info(), self.url(), results)
+    }
+
     pub fn symbol_at(&self, position: Position) -> Option<Symbol> {
         let line_info = self.line_info();
 
@@ -227,10 +518,268 @@ impl AnalyzedDocument {
         Some(GotoDefinitionResponse::Scalar(self.location(range)))
     }
 
+    /// The symbol whose name the module header exposes at `position`.
+    pub fn exposed_symbol_at(&self, position: Position) -> Option<Symbol> {
+        let AnalyzedModule {
+            module_id, interns, ..
+        } = self.module()?;
+
+        let position = position.to_roc_position(self.line_info());
+        let arena = &Bump::new();
+        let ast = Ast::parse(arena, &self.doc_info.source).ok()?;
+
+        let exposed = ast
+            .exposed_names()
+            .into_iter()
+            .find(|loc_name| loc_name.region.contains_pos(position))?;
+
+        let ident_id = interns
+            .all_ident_ids
+            .get(module_id)?
+            .get_id(exposed.value)?;
+
+        Some(Symbol::new(*module_id, ident_id))
+    }
+
+    /// Whether `symbol` is defined at the top level of its module, so that other modules can
+    /// refer to it.
+    pub fn is_top_level(&self, symbol: Symbol) -> bool {
+        let Some(AnalyzedModule {
+            module_id,
+            declarations,
+            ..
+        }) = self.module()
+        else {
+            return false;
+        };
+
+        // only the top-level symbols of other modules can be in scope here
+        symbol.module_id() != *module_id
+            || declarations
+                .symbols
+                .iter()
+                .any(|loc_symbol| loc_symbol.value == symbol)
+    }
+
+    /// Whether this document's module defines a top-level symbol named `name`.
+    pub fn defines_top_level(&self, name: &str) -> bool {
+        let Some(AnalyzedModule {
+            declarations,
+            interns,
+            ..
+        }) = self.module()
+        else {
+            return false;
+        };
+
+        declarations
+            .symbols
+            .iter()
+            .any(|loc_symbol| loc_symbol.value.as_str(interns) == name)
+    }
+
+    pub fn symbol_name(&self, symbol: Symbol) -> Option<&str> {
+        Some(symbol.as_str(&self.module()?.interns))
+    }
+
+    /// Whether `symbol` is defined by this document's module.
+    pub fn defines(&self, symbol: Symbol) -> bool {
+        self.module()
+            .map_or(false, |module| module.module_id == symbol.module_id())
+    }
+
+    pub(crate) fn global_symbol(&self, symbol: Symbol) -> Option<GlobalSymbol> {
+        Some(GlobalSymbol {
+            module_url: self.module_url(symbol.module_id())?,
+            name: self.symbol_name(symbol)?.to_string(),
+        })
+    }
+
+    /// The symbol that `global_symbol` is in this document's analysis, if its module is
+    /// part of it.
+    pub(crate) fn resolve_global_symbol(&self, global_symbol: &GlobalSymbol) -> Option<Symbol> {
+        let AnalyzedModule {
+            interns,
+            module_id_to_url,
+            ..
+        } = self.module()?;
+
+        let (module_id, _) = module_id_to_url
+            .iter()
+            .find(|(_, url)| **url == global_symbol.module_url)?;
+
+        let ident_id = interns
+            .all_ident_ids
+            .get(module_id)?
+            .get_id(&global_symbol.name)?;
+
+        Some(Symbol::new(*module_id, ident_id))
+    }
+
+    /// Every reference to `symbol` in this document, including the names exposed by the
+    /// module header and by imports.
+    pub(crate) fn references(&self, symbol: Symbol) -> Vec<Reference> {
+        let Some(AnalyzedModule {
+            module_id,
+            interns,
+            declarations,
+            ..
+        }) = self.module()
+        else {
+            return Vec::new();
+        };
+
+        let source = self.doc_info.source.as_str();
+        let name = symbol.as_str(interns);
+
+        let occurrences = roc_can::traverse::find_symbol_occurrences(symbol, declarations);
+        let mut references: Vec<_> = references_at_occurrences(source, occurrences, name).collect();
+
+        let arena = &Bump::new();
+        if let Ok(ast) = Ast::parse(arena, source) {
+            let names = if symbol.module_id() == *module_id {
+                ast.exposed_names()
+            } else {
+                let module_name = interns.module_name(symbol.module_id());
+
+                ast.names_exposed_by_imports_of(module_name.as_str())
+            };
+
+            references.extend(references_in_names(source, names, name));
+        }
+
+        references.sort_by_key(|reference| reference.region.start());
+        references.dedup();
+        references
+    }
+
+    pub fn reference_locations(&self, symbol: Symbol, include_declaration: bool) -> Vec<Location> {
+        self.references(symbol)
+            .into_iter()
+            .filter(|reference| include_declaration || !reference.is_definition)
+            .map(|reference| self.location(reference.region.to_range(self.line_info())))
+            .collect()
+    }
+
+    /// The range of the reference at `position`, which a rename would change.
+    pub(crate) fn reference_range_at(
+        &self,
+        symbol: Symbol,
+        position: Position,
+    ) -> Option<(Range, Reference)> {
+        let position = position.to_roc_position(self.line_info());
+
+        let reference = self
+            .references(symbol)
+            .into_iter()
+            .find(|reference| reference.region.contains_pos(position))?;
+
+        Some((reference.region.to_range(self.line_info()), reference))
+    }
+
+    /// The edits that rename `symbol` to `new_name` in this document.
+    pub fn rename_edits(&self, symbol: Symbol, new_name: &str) -> Result<Vec<TextEdit>, String> {
+        let Some(old_name) = self.symbol_name(symbol) else {
+            return Ok(Vec::new());
+        };
+
+        self.references(symbol)
+            .into_iter()
+            .map(|reference| {
+                let range = reference.region.to_range(self.line_info());
+
+                match reference.rename.new_text(old_name, new_name) {
+                    Some(new_text) => Ok(TextEdit::new(range, new_text)),
+                    None => Err(format!(
+                        "`{old_name}` is also the label of an optional record field on line {}, which would change with its name.",
+                        range.start.line + 1
+                    )),
+                }
+            })
+            .collect()
+    }
+
     pub(crate) fn module_url(&self, module_id: ModuleId) -> Option<Url> {
         self.module()?.module_id_to_url.get(&module_id).cloned()
     }
 
+    fn hierarchies(&self) -> Option<Hierarchies<'_>> {
+        Some(Hierarchies {
+            module: self.module()?,
+            source: &self.doc_info.source,
+            line_info: self.line_info(),
+            url: self.url(),
+        })
+    }
+
+    /// The call hierarchy item for `symbol`, if it is a top-level def of this document.
+    pub fn call_hierarchy_item(&self, symbol: Symbol) -> Option<CallHierarchyItem> {
+        self.hierarchies()?.call_hierarchy_item(symbol)
+    }
+
+    /// The calls of `callee` in this document, grouped by the top-level def they are in.
+    pub fn incoming_calls(&self, callee: Symbol) -> Vec<CallHierarchyIncomingCall> {
+        self.hierarchies()
+            .map(|hierarchies| hierarchies.incoming_calls(callee))
+            .unwrap_or_default()
+    }
+
+    /// The functions that the top-level def `caller` calls, with the ranges of the calls.
+    pub fn outgoing_calls(&self, caller: Symbol) -> Vec<(Symbol, Vec<Range>)> {
+        self.hierarchies()
+            .map(|hierarchies| hierarchies.outgoing_calls(caller))
+            .unwrap_or_default()
+    }
+
+    /// The ability or opaque type defined or named as implemented at `position`.
+    pub fn type_at(&self, position: Position) -> Option<Symbol> {
+        let position = position.to_roc_position(self.line_info());
+
+        self.hierarchies()?.type_at(position)
+    }
+
+    /// The type hierarchy item for `symbol`, if it is an ability or opaque type that this
+    /// document defines.
+    pub fn type_hierarchy_item(&self, symbol: Symbol) -> Option<TypeHierarchyItem> {
+        self.hierarchies()?.type_hierarchy_item(symbol)
+    }
+
+    /// The type hierarchy item for `ability` where `opaque` names it as implemented.
+    pub fn implements_item(&self, opaque: Symbol, ability: Symbol) -> Option<TypeHierarchyItem> {
+        self.hierarchies()?.implements_item(opaque, ability)
+    }
+
+    /// The abilities that the opaque type `opaque` implements.
+    pub(crate) fn implemented_abilities(&self, opaque: Symbol) -> Vec<(Symbol, Implementation)> {
+        self.hierarchies()
+            .map(|hierarchies| hierarchies.implemented_abilities(opaque))
+            .unwrap_or_default()
+    }
+
+    /// The opaque types of this document that implement `ability`.
+    pub(crate) fn implementations_of(&self, ability: Symbol) -> Vec<(Symbol, Implementation)> {
+        self.hierarchies()
+            .map(|hierarchies| hierarchies.implementations_of(ability))
+            .unwrap_or_default()
+    }
+
+    /// The signature of the function applied at `position` in `latest_doc`, which may be newer
+    /// than this analysis.
+    pub fn signature_help(
+        &self,
+        position: Position,
+        latest_doc: &DocInfo,
+    ) -> Option<SignatureHelp> {
+        let offset = position.to_roc_position(&latest_doc.line_info).offset as usize;
+        let call = call_at_end(latest_doc.source.get(..offset)?)?;
+
+        // the text after the function is what's being edited, so the function itself is still
+        // at the same place in this analysis
+        let function_position = roc_region::all::Position::new(call.offset as u32);
+
+        signature_help(self.module()?, call, function_position)
+    }
+
     pub fn completion_items(
         &self,
         position: Position,
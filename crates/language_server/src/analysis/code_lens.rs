@@ -0,0 +1,183 @@
+//! Code lenses to run the top-level expects of a document and evaluate its top-level values,
+//! which show the results of the latest run.
+use std::collections::HashMap;
+
+use roc_can::{
+    expr::Declarations,
+    traverse::{DeclarationInfo, Visitor},
+};
+use roc_module::symbol::Interns;
+use roc_region::all::{LineInfo, Region};
+use tower_lsp::lsp_types::{CodeLens, Command, Position, Url};
+
+use crate::{convert::ToRange, run::ExpectOutcome};
+
+pub(crate) const RUN_EXPECT_COMMAND: &str = "roc.runExpect";
+pub(crate) const DEBUG_EXPECT_COMMAND: &str = "roc.debugExpect";
+pub(crate) const EVALUATE_COMMAND: &str = "roc.evaluate";
+
+/// Lens titles are a single line, so long values are cut off.
+const MAX_VALUE_LEN: usize = 80;
+
+/// What was shown by running the code of a version of a document
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LensResults {
+    pub version: i32,
+    pub expects: Vec<ExpectOutcome>,
+    /// The rendered values of top-level defs, or why they could not be evaluated, by name
+    pub values: HashMap<String, Result<String, String>>,
+}
+
+impl LensResults {
+    pub fn new(version: i32) -> Self {
+        Self {
+            version,
+            ..Default::default()
+        }
+    }
+
+    /// Records the outcomes of a run, replacing the earlier outcomes of the same expects.
+    pub fn add_expects(&mut self, outcomes: Vec<ExpectOutcome>) {
+        self.expects
+            .retain(|old| !outcomes.iter().any(|new| new.region == old.region));
+        self.expects.extend(outcomes);
+    }
+
+    fn expect_at(&self, condition: Region) -> Option<&ExpectOutcome> {
+        self.expects
+            .iter()
+            .find(|outcome| outcome.region.contains(&condition))
+    }
+}
+
+/// "Run" and "Debug" lenses above each top-level expect, and an "Evaluate" lens above each
+/// top-level def that takes no arguments. `results`, of the same version of the document,
+/// are shown next to them.
+pub(super) fn code_lenses(
+    declarations: &Declarations,
+    interns: &Interns,
+    line_info: &LineInfo,
+    url: &Url,
+    results: Option<&LensResults>,
+) -> Vec<CodeLens> {
+    let mut collector = LensCollector {
+        interns,
+        line_info,
+        url,
+        results,
+        lenses: Vec::new(),
+    };
+    collector.visit_decls(declarations);
+
+    // declarations are sorted by their dependencies, not by where they are written
+    let mut lenses = collector.lenses;
+    lenses.sort_by_key(|lens| (lens.range.start.line, lens.range.start.character));
+
+    lenses
+}
+
+struct LensCollector<'a> {
+    interns: &'a Interns,
+    line_info: &'a LineInfo,
+    url: &'a Url,
+    results: Option<&'a LensResults>,
+    lenses: Vec<CodeLens>,
+}
+
+impl Visitor for LensCollector<'_> {
+    // only the top-level declarations get lenses, so they are not walked into
+    fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+        match decl {
+            DeclarationInfo::Expectation { loc_condition } => {
+                let region = loc_condition.region;
+                let range = region.to_range(self.line_info);
+                let arguments = || Some(vec![url_arg(self.url), position_arg(range.start)]);
+
+                let mut lenses = vec![
+                    lens(range, "▶ Run", RUN_EXPECT_COMMAND, arguments()),
+                    lens(range, "Debug", DEBUG_EXPECT_COMMAND, arguments()),
+                ];
+
+                match self.results.and_then(|results| results.expect_at(region)) {
+                    Some(outcome) if outcome.passed() => {
+                        lenses.push(lens(range, "✔ passed", RUN_EXPECT_COMMAND, arguments()));
+                    }
+                    Some(_) => {
+                        lenses.push(lens(range, "✘ failed", DEBUG_EXPECT_COMMAND, arguments()));
+                    }
+                    None => {}
+                }
+
+                self.lenses.extend(lenses);
+            }
+            DeclarationInfo::Value { loc_symbol, .. } => {
+                let name = loc_symbol.value.as_str(self.interns);
+                let range = loc_symbol.region.to_range(self.line_info);
+                let arguments = Some(vec![url_arg(self.url), serde_json::Value::from(name)]);
+
+                let title = match self.results.and_then(|results| results.values.get(name)) {
+                    Some(Ok(value)) => format!("= {}", one_line(value)),
+                    Some(Err(_)) => "✘ could not evaluate".to_string(),
+                    None => "Evaluate".to_string(),
+                };
+
+                self.lenses
+                    .push(lens(range, &title, EVALUATE_COMMAND, arguments));
+            }
+            DeclarationInfo::Function { .. } | DeclarationInfo::Destructure { .. } => {}
+        }
+    }
+}
+
+fn lens(
+    range: tower_lsp::lsp_types::Range,
+    title: &str,
+    command: &str,
+    arguments: Option<Vec<serde_json::Value>>,
+) -> CodeLens {
+    CodeLens {
+        range,
+        command: Some(Command {
+            title: title.to_string(),
+            command: command.to_string(),
+            arguments,
+        }),
+        data: None,
+    }
+}
+
+fn url_arg(url: &Url) -> serde_json::Value {
+    serde_json::Value::from(url.as_str())
+}
+
+fn position_arg(position: Position) -> serde_json::Value {
+    serde_json::json!({ "line": position.line, "character": position.character })
+}
+
+fn one_line(value: &str) -> String {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match value.char_indices().nth(MAX_VALUE_LEN) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value,
+    }
+}
+
+/// Where `expect` lenses were clicked, from the arguments of their commands
+pub(crate) fn command_location(arguments: &[serde_json::Value]) -> Option<(Url, Position)> {
+    let url = Url::parse(arguments.first()?.as_str()?).ok()?;
+    let position = arguments.get(1)?;
+
+    let line = position.get("line")?.as_u64()? as u32;
+    let character = position.get("character")?.as_u64()? as u32;
+
+    Some((url, Position::new(line, character)))
+}
+
+/// Which def an "Evaluate" lens was clicked for, from the arguments of its command
+pub(crate) fn command_value(arguments: &[serde_json::Value]) -> Option<(Url, String)> {
+    let url = Url::parse(arguments.first()?.as_str()?).ok()?;
+    let name = arguments.get(1)?.as_str()?;
+
+    Some((url, name.to_string()))
+}
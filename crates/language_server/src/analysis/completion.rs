@@ -19,7 +19,7 @@ use super::{utils::format_var_type, ModulesInfo};
 mod formatting;
 mod visitor;
 
-fn get_completions(
+pub(super) fn get_completions(
     position: Position,
     decls: &Declarations,
     prefix: String,
@@ -223,7 +223,7 @@ fn _alias_completions(
         .collect()
 }
 
-fn make_completion_items(
+pub(super) fn make_completion_items(
     subs: &mut Subs,
     module_id: &ModuleId,
     interns: &Interns,
@@ -311,7 +311,7 @@ struct RecFieldCompletion {
 
 /// Finds the types of and names of all the fields of a record.
 /// `var` should be a `Variable` that you know is of type record or else it will return an empty list.
-fn find_record_fields(var: Variable, subs: &mut Subs) -> Vec<(String, Variable)> {
+pub(super) fn find_record_fields(var: Variable, subs: &mut Subs) -> Vec<(String, Variable)> {
     let content = subs.get(var);
     match content.content {
         roc_types::subs::Content::Structure(typ) => match typ {
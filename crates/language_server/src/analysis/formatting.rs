@@ -0,0 +1,204 @@
+//! Formatting as edits of only what the formatter changes, so that editors keep the cursor
+//! position and undo history of the rest of the document.
+use std::ops;
+
+use roc_region::all::{LineInfo, Position, Region};
+use tower_lsp::lsp_types::{self, Range, TextEdit};
+
+use crate::convert::ToRange;
+
+/// Past this many pairs of changed lines, the lines between the first and last change are
+/// replaced as a whole rather than compared line by line.
+const MAX_COMPARED_LINE_PAIRS: usize = 4_000_000;
+
+/// A run of changed lines: the lines of the old text that are replaced by those of the new one
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hunk {
+    old: ops::Range<usize>,
+    new: ops::Range<usize>,
+}
+
+/// The edits that turn `source` into `formatted`, one for each run of changed lines, trimmed
+/// down to the characters that change.
+pub(super) fn minimal_edits(source: &str, formatted: &str, line_info: &LineInfo) -> Vec<TextEdit> {
+    let old_lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = formatted.split_inclusive('\n').collect();
+
+    let old_offsets = line_offsets(&old_lines);
+    let new_offsets = line_offsets(&new_lines);
+
+    changed_lines(&old_lines, &new_lines)
+        .into_iter()
+        .map(|Hunk { old, new }| {
+            let old_start = old_offsets[old.start];
+            let old_text = &source[old_start..old_offsets[old.end]];
+            let new_text = &formatted[new_offsets[new.start]..new_offsets[new.end]];
+
+            let prefix = common_prefix_len(old_text, new_text);
+            let suffix = common_suffix_len(&old_text[prefix..], &new_text[prefix..]);
+
+            let region = Region::new(
+                Position::new((old_start + prefix) as u32),
+                Position::new((old_start + old_text.len() - suffix) as u32),
+            );
+
+            TextEdit::new(
+                region.to_range(line_info),
+                new_text[prefix..new_text.len() - suffix].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Whether `edit` changes any of the lines that `range` spans.
+pub(super) fn touches_lines(edit: &TextEdit, range: Range) -> bool {
+    edit.range.start.line <= range.end.line && range.start.line <= edit.range.end.line
+}
+
+/// The indentation of the line at `position` after a newline is typed at the end of a line
+/// that opens a block, like `foo =`, `\x ->` or `when x is`. The block's body can't be
+/// formatted until it is written, so it is indented by another level for it.
+pub(super) fn indent_after_newline(
+    source: &str,
+    position: lsp_types::Position,
+) -> Option<TextEdit> {
+    let lines: Vec<&str> = source
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let line_index = position.line as usize;
+
+    let current = lines.get(line_index).copied().unwrap_or_default();
+    let previous = lines
+        .get(..line_index)?
+        .iter()
+        .rev()
+        .find(|line| !line.trim().is_empty())?;
+
+    if !opens_block(previous.trim_end()) {
+        return None;
+    }
+
+    let indent = format!("{}    ", leading_whitespace(previous));
+    let current_indent = leading_whitespace(current);
+
+    if current_indent == indent {
+        return None;
+    }
+
+    let start = lsp_types::Position::new(position.line, 0);
+    let end = lsp_types::Position::new(position.line, current_indent.len() as u32);
+
+    Some(TextEdit::new(Range::new(start, end), indent))
+}
+
+fn opens_block(line: &str) -> bool {
+    let ends_with_is = line
+        .strip_suffix("is")
+        .map_or(false, |rest| rest.ends_with(char::is_whitespace));
+
+    line.ends_with("->")
+        || (line.ends_with('=') && !["==", "!=", "<=", ">="].iter().any(|op| line.ends_with(op)))
+        || (ends_with_is && line.split_whitespace().any(|word| word == "when"))
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The offset of the start of each line, and of the end of the last one.
+fn line_offsets(lines: &[&str]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(lines.iter().scan(0, |offset, line| {
+            *offset += line.len();
+            Some(*offset)
+        }))
+        .collect()
+}
+
+/// The runs of lines that differ, found by the longest common subsequence of the lines
+/// between the first and last change.
+fn changed_lines(old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    if old_changed.is_empty() && new_changed.is_empty() {
+        return Vec::new();
+    }
+
+    if old_changed.len() * new_changed.len() > MAX_COMPARED_LINE_PAIRS {
+        return vec![Hunk {
+            old: prefix..old.len() - suffix,
+            new: prefix..new.len() - suffix,
+        }];
+    }
+
+    let (n, m) = (old_changed.len(), new_changed.len());
+
+    // common[i][j] is the length of the longest common subsequence of old_changed[i..] and
+    // new_changed[j..]
+    let mut common = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if old_changed[i] == new_changed[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut hunk: Option<Hunk> = None;
+    let (mut i, mut j) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && old_changed[i] == new_changed[j] {
+            hunks.extend(hunk.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let current = hunk.get_or_insert(Hunk {
+            old: prefix + i..prefix + i,
+            new: prefix + j..prefix + j,
+        });
+
+        if j < m && (i == n || common[i][j + 1] >= common[i + 1][j]) {
+            j += 1;
+            current.new.end = prefix + j;
+        } else {
+            i += 1;
+            current.old.end = prefix + i;
+        }
+    }
+
+    hunks.extend(hunk);
+    hunks
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a_char), b_char)| a_char != b_char)
+        .map_or(a.len().min(b.len()), |((index, _), _)| index)
+}
+
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .rev()
+        .zip(b.chars().rev())
+        .find(|((_, a_char), b_char)| a_char != b_char)
+        .map_or(a.len().min(b.len()), |((index, a_char), _)| {
+            a.len() - index - a_char.len_utf8()
+        })
+}
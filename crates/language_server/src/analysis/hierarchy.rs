@@ -0,0 +1,335 @@
+//! Call hierarchies of top-level defs, and type hierarchies of abilities and the opaque types
+//! that implement them.
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+use roc_can::{
+    abilities::AbilitiesStore,
+    traverse::{find_calls, find_declaration, DeclarationInfo, FoundDeclaration},
+};
+use roc_module::symbol::{Interns, Symbol, DERIVABLE_ABILITIES};
+use roc_parse::ast::TypeDef;
+use roc_region::all::{LineInfo, Position, Region};
+use roc_types::types::MemberImpl;
+use tower_lsp::lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyItem, Range, SymbolKind, TypeHierarchyItem, Url,
+};
+
+use crate::convert::ToRange;
+
+use super::{parse_ast::Ast, utils::format_var_type, AnalyzedModule};
+
+/// How an opaque type implements an ability
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Implementation {
+    /// Like `Eq` in `implements [Eq]`
+    Derived,
+    /// Like `Hash { hash: hashId }` in `implements [Hash { hash: hashId }]`
+    Custom,
+}
+
+impl Implementation {
+    pub fn detail(self) -> String {
+        match self {
+            Implementation::Derived => "derived".to_string(),
+            Implementation::Custom => "custom".to_string(),
+        }
+    }
+}
+
+/// A document's analysis, with what is needed to point at places in it
+pub(super) struct Hierarchies<'a> {
+    pub module: &'a AnalyzedModule,
+    pub source: &'a str,
+    pub line_info: &'a LineInfo,
+    pub url: &'a Url,
+}
+
+impl Hierarchies<'_> {
+    /// The item for a top-level def of the module, with its type as the detail.
+    pub fn call_hierarchy_item(&self, symbol: Symbol) -> Option<CallHierarchyItem> {
+        let AnalyzedModule {
+            module_id,
+            interns,
+            subs,
+            declarations,
+            ..
+        } = self.module;
+
+        let Some(FoundDeclaration::Decl(decl)) = find_declaration(symbol, declarations) else {
+            return None;
+        };
+        let region = decl.region();
+
+        let (kind, loc_symbol, expr_var) = match decl {
+            DeclarationInfo::Function {
+                loc_symbol,
+                expr_var,
+                ..
+            } => (SymbolKind::FUNCTION, loc_symbol, expr_var),
+            DeclarationInfo::Value {
+                loc_symbol,
+                expr_var,
+                ..
+            } => (SymbolKind::CONSTANT, loc_symbol, expr_var),
+            DeclarationInfo::Expectation { .. } | DeclarationInfo::Destructure { .. } => {
+                return None
+            }
+        };
+
+        Some(CallHierarchyItem {
+            name: symbol.as_str(interns).to_string(),
+            kind,
+            tags: None,
+            detail: Some(format_var_type(
+                expr_var,
+                &mut subs.clone(),
+                module_id,
+                interns,
+            )),
+            uri: self.url.clone(),
+            range: region.to_range(self.line_info),
+            selection_range: loc_symbol.region.to_range(self.line_info),
+            data: None,
+        })
+    }
+
+    /// The calls of `callee` in the module, grouped by the top-level def they are in.
+    pub fn incoming_calls(&self, callee: Symbol) -> Vec<CallHierarchyIncomingCall> {
+        self.calls_by(|call_callee, call_caller| (call_callee == callee).then_some(call_caller))
+            .into_iter()
+            .filter_map(|(caller, from_ranges)| {
+                Some(CallHierarchyIncomingCall {
+                    from: self.call_hierarchy_item(caller)?,
+                    from_ranges,
+                })
+            })
+            .collect()
+    }
+
+    /// The functions that the top-level def `caller` calls, with the ranges of the calls.
+    pub fn outgoing_calls(&self, caller: Symbol) -> Vec<(Symbol, Vec<Range>)> {
+        self.calls_by(|call_callee, call_caller| (call_caller == caller).then_some(call_callee))
+    }
+
+    /// Groups the ranges of the calls in the module by the symbol `key` picks out of their
+    /// callee and caller, in the order of the first call of each group.
+    fn calls_by(
+        &self,
+        key: impl Fn(Symbol, Symbol) -> Option<Symbol>,
+    ) -> Vec<(Symbol, Vec<Range>)> {
+        let mut groups: Vec<(Symbol, Vec<Range>)> = Vec::new();
+
+        for call in find_calls(&self.module.declarations) {
+            let Some(symbol) = key(call.callee, call.caller) else {
+                continue;
+            };
+            let range = call.region.to_range(self.line_info);
+
+            match groups.iter_mut().find(|(grouped, _)| *grouped == symbol) {
+                Some((_, ranges)) => ranges.push(range),
+                None => groups.push((symbol, vec![range])),
+            }
+        }
+
+        groups
+    }
+
+    /// The ability or opaque type defined at `position`, or the ability named at `position` in
+    /// the `implements [...]` clause of an opaque type.
+    pub fn type_at(&self, position: Position) -> Option<Symbol> {
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, self.source).ok()?;
+
+        let defined = ast
+            .type_defs()
+            .find_map(|loc_type_def| match loc_type_def.value {
+                TypeDef::Opaque { header, .. } | TypeDef::Ability { header, .. }
+                    if header.name.region.contains_pos(position) =>
+                {
+                    Some(header.name.value)
+                }
+                _ => None,
+            });
+
+        if let Some(name) = defined {
+            return self.own_symbol(name);
+        }
+
+        let (opaque_name, ability) = ast
+            .implemented_abilities()
+            .into_iter()
+            .find(|(_, ability)| ability.region.contains_pos(position))?;
+        let opaque = self.own_symbol(opaque_name)?;
+
+        self.implemented_abilities(opaque)
+            .into_iter()
+            .map(|(symbol, _)| symbol)
+            .find(|symbol| symbol.as_str(&self.module.interns) == ability.value)
+    }
+
+    /// The item for an ability or opaque type that the module defines.
+    pub fn type_hierarchy_item(&self, symbol: Symbol) -> Option<TypeHierarchyItem> {
+        if symbol.module_id() != self.module.module_id {
+            return None;
+        }
+
+        let name = symbol.as_str(&self.module.interns);
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, self.source).ok()?;
+
+        let item = ast
+            .type_defs()
+            .find_map(|loc_type_def| match loc_type_def.value {
+                TypeDef::Opaque { header, .. } if header.name.value == name => {
+                    Some((SymbolKind::STRUCT, loc_type_def.region, header.name.region))
+                }
+                TypeDef::Ability { header, .. } if header.name.value == name => Some((
+                    SymbolKind::INTERFACE,
+                    loc_type_def.region,
+                    header.name.region,
+                )),
+                _ => None,
+            })
+            .map(|(kind, region, name_region)| self.type_item(name, kind, region, name_region));
+
+        item
+    }
+
+    /// The item for `ability` where the opaque type `opaque` names it in its `implements [...]`
+    /// clause. This is where builtin abilities like `Eq`, which are not defined in any
+    /// document, are shown.
+    pub fn implements_item(&self, opaque: Symbol, ability: Symbol) -> Option<TypeHierarchyItem> {
+        let interns = &self.module.interns;
+        let opaque_name = opaque.as_str(interns);
+        let ability_name = ability.as_str(interns);
+
+        let arena = Bump::new();
+        let ast = Ast::parse(&arena, self.source).ok()?;
+
+        let (_, loc_ability) =
+            ast.implemented_abilities()
+                .into_iter()
+                .find(|(name, loc_ability)| {
+                    *name == opaque_name && loc_ability.value == ability_name
+                })?;
+
+        Some(self.type_item(
+            ability_name,
+            SymbolKind::INTERFACE,
+            loc_ability.region,
+            loc_ability.region,
+        ))
+    }
+
+    fn type_item(
+        &self,
+        name: &str,
+        kind: SymbolKind,
+        region: Region,
+        name_region: Region,
+    ) -> TypeHierarchyItem {
+        TypeHierarchyItem {
+            name: name.to_string(),
+            kind,
+            tags: None,
+            detail: None,
+            uri: self.url.clone(),
+            range: region.to_range(self.line_info),
+            selection_range: name_region.to_range(self.line_info),
+            data: None,
+        }
+    }
+
+    /// The abilities that `opaque` implements, sorted by name.
+    pub fn implemented_abilities(&self, opaque: Symbol) -> Vec<(Symbol, Implementation)> {
+        let interns = &self.module.interns;
+
+        let mut abilities: Vec<_> = self
+            .implementations()
+            .into_iter()
+            .filter(|(implementing, _, _)| *implementing == opaque)
+            .map(|(_, ability, implementation)| (ability, implementation))
+            .collect();
+
+        abilities.sort_by(|(a, _), (b, _)| a.as_str(interns).cmp(b.as_str(interns)));
+        abilities
+    }
+
+    /// The opaque types of the module that implement `ability`, sorted by name.
+    pub fn implementations_of(&self, ability: Symbol) -> Vec<(Symbol, Implementation)> {
+        let interns = &self.module.interns;
+
+        let mut opaques: Vec<_> = self
+            .implementations()
+            .into_iter()
+            .filter(|(opaque, implemented, _)| {
+                *implemented == ability && opaque.module_id() == self.module.module_id
+            })
+            .map(|(opaque, _, implementation)| (opaque, implementation))
+            .collect();
+
+        opaques.sort_by(|(a, _), (b, _)| a.as_str(interns).cmp(b.as_str(interns)));
+        opaques
+    }
+
+    /// Every opaque type with an implementation of an ability, with the ability. The
+    /// implementations are declared per member of the ability, so they are grouped here.
+    fn implementations(&self) -> Vec<(Symbol, Symbol, Implementation)> {
+        let AnalyzedModule {
+            abilities, interns, ..
+        } = self.module;
+
+        let mut implementations: HashMap<(Symbol, Symbol), Implementation> = HashMap::new();
+
+        for (impl_key, member_impl) in abilities.iter_declared_implementations() {
+            let Some(ability) = parent_ability(abilities, impl_key.ability_member) else {
+                continue;
+            };
+
+            implementations.insert(
+                (impl_key.opaque, ability),
+                implementation_kind(member_impl, interns),
+            );
+        }
+
+        implementations
+            .into_iter()
+            .map(|((opaque, ability), implementation)| (opaque, ability, implementation))
+            .collect()
+    }
+
+    fn own_symbol(&self, name: &str) -> Option<Symbol> {
+        let AnalyzedModule {
+            module_id, interns, ..
+        } = self.module;
+
+        let ident_id = interns.all_ident_ids.get(module_id)?.get_id(name)?;
+
+        Some(Symbol::new(*module_id, ident_id))
+    }
+}
+
+/// The ability that `member` belongs to. The members of builtin abilities are only known to the
+/// store of the module that defines them, unless they are derivable.
+fn parent_ability(abilities: &AbilitiesStore, member: Symbol) -> Option<Symbol> {
+    abilities
+        .member_def(member)
+        .map(|member_def| member_def.parent_ability)
+        .or_else(|| {
+            DERIVABLE_ABILITIES
+                .iter()
+                .find(|(_, members)| members.contains(&member))
+                .map(|(ability, _)| *ability)
+        })
+}
+
+/// Derived implementations are synthesized with names like `#Id_isEq`, which can't be written.
+fn implementation_kind(member_impl: &MemberImpl, interns: &Interns) -> Implementation {
+    match member_impl {
+        MemberImpl::Impl(impl_symbol) if impl_symbol.as_str(interns).starts_with('#') => {
+            Implementation::Derived
+        }
+        MemberImpl::Impl(_) | MemberImpl::Error => Implementation::Custom,
+    }
+}
@@ -0,0 +1,204 @@
+//! Managing the `import` statements of a document: adding the import for a name that a
+//! completion brings in from another module, and organizing the imports.
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use roc_packaging::{cache, https::PackageMetadata};
+use roc_parse::ast::ModuleImport;
+use roc_region::all::{LineInfo, Loc, Region};
+use tower_lsp::lsp_types::TextEdit;
+
+use crate::convert::ToRange;
+
+use super::{formatting::minimal_edits, parse_ast::Ast};
+
+/// The edit that adds `import <module_name> exposing [<name>]` after the last import of
+/// `source`, or after its header if it has no imports.
+pub(super) fn add_import(
+    source: &str,
+    line_info: &LineInfo,
+    module_name: &str,
+    name: &str,
+) -> Option<TextEdit> {
+    let arena = Bump::new();
+    let ast = Ast::parse(&arena, source).ok()?;
+    let statement = format!("import {module_name} exposing [{name}]");
+
+    let (position, new_text) = match ast.module_imports().last() {
+        Some(last) => (last.region.end(), format!("\n{statement}")),
+        None => (ast.header_end(), format!("\n\n{statement}")),
+    };
+
+    Some(TextEdit::new(
+        Region::new(position, position).to_range(line_info),
+        new_text,
+    ))
+}
+
+/// The edits that sort the imports of `source` by module name, merge the ones that import the
+/// same module under the same name, and remove the names in `exposing [...]` lists that the
+/// regions of `unused` point at. Imports that are separated by anything but whitespace, like
+/// a comment, are left alone.
+pub(super) fn organize_imports(
+    source: &str,
+    line_info: &LineInfo,
+    unused: &[Region],
+) -> Vec<TextEdit> {
+    let arena = Bump::new();
+    let Ok(ast) = Ast::parse(&arena, source) else {
+        return Vec::new();
+    };
+
+    // imports written in an old-style header are upgraded to defs that the header holds
+    let imports: Vec<_> = ast
+        .module_imports()
+        .into_iter()
+        .filter(|import| import.region.start() >= ast.header_end())
+        .collect();
+
+    let (Some(first), Some(last)) = (imports.first(), imports.last()) else {
+        return Vec::new();
+    };
+    let block = first.region.start().offset as usize..last.region.end().offset as usize;
+
+    let only_whitespace_between = imports.windows(2).all(|pair| {
+        let between =
+            &source[pair[0].region.end().offset as usize..pair[1].region.start().offset as usize];
+
+        between.trim().is_empty()
+    });
+
+    if !only_whitespace_between {
+        return Vec::new();
+    }
+
+    let mut organized: Vec<OrganizedImport> = Vec::new();
+
+    for import in &imports {
+        let Some(parts) = OrganizedImport::from_import(source, import, unused) else {
+            return Vec::new();
+        };
+
+        match organized.iter_mut().find(|other| other.head == parts.head) {
+            Some(other) => other.merge(parts.exposed),
+            None => organized.push(parts),
+        }
+    }
+
+    organized.sort_by(|a, b| a.module_name.cmp(&b.module_name).then(a.head.cmp(&b.head)));
+
+    let statements: Vec<_> = organized.iter().map(OrganizedImport::to_source).collect();
+    let organized_source = format!(
+        "{}{}{}",
+        &source[..block.start],
+        statements.join("\n"),
+        &source[block.end..]
+    );
+
+    minimal_edits(source, &organized_source, line_info)
+}
+
+/// An import, split into what identifies it and the names it exposes
+struct OrganizedImport {
+    /// Like `pf.Stdout`
+    module_name: String,
+    /// The source of the import up to `exposing`, like `import pf.Stdout as Out`
+    head: String,
+    exposed: Vec<String>,
+}
+
+impl OrganizedImport {
+    fn from_import(
+        source: &str,
+        loc_import: &Loc<ModuleImport<'_>>,
+        unused: &[Region],
+    ) -> Option<Self> {
+        let ModuleImport {
+            name,
+            params,
+            alias,
+            exposed,
+            ..
+        } = &loc_import.value;
+
+        let module_name = match name.value.package {
+            Some(package) => format!("{package}.{}", name.value.name.as_str()),
+            None => name.value.name.as_str().to_string(),
+        };
+
+        let mut head = format!("import {module_name}");
+
+        if let Some(params) = params {
+            head.push(' ');
+            head.push_str(source_of(source, params.params.region)?);
+        }
+
+        if let Some(alias) = alias {
+            head.push_str(" as ");
+            head.push_str(alias.item.value.as_str());
+        }
+
+        let exposed = exposed
+            .iter()
+            .flat_map(|exposed| exposed.item.items.iter())
+            .filter(|loc_name| !unused.iter().any(|region| loc_name.region.contains(region)))
+            .map(|loc_name| <&str>::from(*loc_name.value.item()).to_string())
+            .collect();
+
+        let mut organized = OrganizedImport {
+            module_name,
+            head,
+            exposed: Vec::new(),
+        };
+        organized.merge(exposed);
+
+        Some(organized)
+    }
+
+    fn merge(&mut self, exposed: Vec<String>) {
+        for name in exposed {
+            if !self.exposed.contains(&name) {
+                self.exposed.push(name);
+            }
+        }
+    }
+
+    fn to_source(&self) -> String {
+        if self.exposed.is_empty() {
+            self.head.clone()
+        } else {
+            format!("{} exposing [{}]", self.head, self.exposed.join(", "))
+        }
+    }
+}
+
+fn source_of(source: &str, region: Region) -> Option<&str> {
+    source.get(region.start().offset as usize..region.end().offset as usize)
+}
+
+/// The shorthands of the packages that the app, package or platform at `path` depends on,
+/// along with the directory each package's modules are in.
+pub(super) fn package_dirs(source: &str, path: &Path) -> Vec<(String, PathBuf)> {
+    let arena = Bump::new();
+    let Ok(ast) = Ast::parse(&arena, source) else {
+        return Vec::new();
+    };
+    let src_dir = path.parent().unwrap_or(path);
+
+    ast.packages()
+        .into_iter()
+        .filter_map(|(shorthand, package)| {
+            let dir = if package.starts_with("https://") {
+                let metadata = PackageMetadata::try_from(package).ok()?;
+
+                cache::roc_cache_packages_dir()
+                    .join(metadata.cache_subdir)
+                    .join(metadata.content_hash)
+            } else {
+                src_dir.join(package).parent()?.to_path_buf()
+            };
+
+            Some((shorthand.to_string(), dir))
+        })
+        .collect()
+}
@@ -0,0 +1,112 @@
+//! Inlay hints that show the inferred types of bindings that have no type annotation.
+use std::collections::HashMap;
+
+use roc_can::traverse::{find_unannotated_bindings, BindingKind, UnannotatedBinding};
+use roc_region::all::LineInfo;
+use roc_types::subs::Content;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, InlayHint, InlayHintKind, InlayHintLabel, Range, TextEdit, Url,
+    WorkspaceEdit,
+};
+
+use crate::convert::ToRange;
+
+use super::{utils::format_var_type, AnalyzedModule};
+
+/// The inferred type of a binding
+pub(super) struct TypeHint {
+    binding: UnannotatedBinding,
+    name: String,
+    range: Range,
+    type_str: String,
+}
+
+impl TypeHint {
+    pub fn into_inlay_hint(self) -> InlayHint {
+        let text_edits = self.annotation_edit().map(|edit| vec![edit]);
+
+        InlayHint {
+            position: self.range.end,
+            label: InlayHintLabel::String(format!(": {}", self.type_str)),
+            kind: Some(InlayHintKind::TYPE),
+            text_edits,
+            tooltip: None,
+            padding_left: None,
+            padding_right: None,
+            data: None,
+        }
+    }
+
+    /// A code action that adds the hinted type as an annotation, if the binding is a def.
+    pub fn annotation_action(&self, url: &Url) -> Option<CodeAction> {
+        let edit = self.annotation_edit()?;
+        let changes = HashMap::from([(url.clone(), vec![edit])]);
+
+        Some(CodeAction {
+            title: format!("Add a type annotation for `{}`", self.name),
+            kind: Some(CodeActionKind::REFACTOR_REWRITE),
+            edit: Some(WorkspaceEdit::new(changes)),
+            ..Default::default()
+        })
+    }
+
+    /// Inserts `name : Type` on its own line above the def, at the same indentation.
+    fn annotation_edit(&self) -> Option<TextEdit> {
+        if self.binding.kind != BindingKind::Def {
+            return None;
+        }
+
+        let start = self.range.start;
+        let indent = " ".repeat(start.character as usize);
+
+        Some(TextEdit {
+            range: Range::new(start, start),
+            new_text: format!("{} : {}\n{indent}", self.name, self.type_str),
+        })
+    }
+}
+
+/// The type hints for the bindings that overlap `range`.
+pub(super) fn type_hints(
+    module: &AnalyzedModule,
+    line_info: &LineInfo,
+    range: Range,
+) -> Vec<TypeHint> {
+    let AnalyzedModule {
+        declarations,
+        subs,
+        module_id,
+        interns,
+        ..
+    } = module;
+
+    let mut subs = subs.clone();
+
+    find_unannotated_bindings(declarations)
+        .into_iter()
+        .filter_map(|binding| {
+            let binding_range = binding.region.to_range(line_info);
+
+            // the compiler generates defs, e.g. for imports, that aren't written in the source
+            if !overlaps(binding_range, range) || binding.symbol.is_generated(interns) {
+                return None;
+            }
+
+            // a type that failed to check has already been reported, so there's nothing to show
+            if let Content::Error = subs.get_content_without_compacting(binding.var) {
+                return None;
+            }
+
+            Some(TypeHint {
+                binding,
+                name: binding.symbol.as_str(interns).to_string(),
+                range: binding_range,
+                type_str: format_var_type(binding.var, &mut subs, module_id, interns),
+            })
+        })
+        .collect()
+}
+
+fn overlaps(a: Range, b: Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
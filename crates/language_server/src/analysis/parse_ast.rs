@@ -1,11 +1,14 @@
 use bumpalo::Bump;
 use roc_fmt::{Buf, MigrationFlags};
 use roc_parse::{
-    ast::{Defs, Header, SpacesBefore},
-    header::parse_module_defs,
+    ast::{
+        Collection, Defs, Header, ImplementsAbilities, ImplementsAbility, ModuleImport, Spaced,
+        SpacesBefore, TypeAnnotation, TypeDef, ValueDef,
+    },
+    header::{parse_module_defs, ExposedName},
     parser::SyntaxError,
 };
-use roc_region::all::Loc;
+use roc_region::all::{Loc, Position};
 
 use self::format::FormattedAst;
 
@@ -16,6 +19,7 @@ mod format;
 pub struct Ast<'a> {
     arena: &'a Bump,
     module: SpacesBefore<'a, Header<'a>>,
+    header_end: Position,
     defs: Defs<'a>,
 }
 
@@ -26,6 +30,7 @@ impl<'a> Ast<'a> {
         let (module, state) = parse_header(arena, State::new(src.as_bytes()))
             .map_err(|e| SyntaxError::Header(e.problem))?;
 
+        let header_end = state.pos();
         let (header, defs) = module.item.upgrade_header_imports(arena);
 
         let defs = parse_module_defs(arena, state, defs)?;
@@ -35,6 +40,7 @@ impl<'a> Ast<'a> {
                 before: module.before,
                 item: header,
             },
+            header_end,
             defs,
             arena,
         })
@@ -58,4 +64,159 @@ impl<'a> Ast<'a> {
 
         header_tokens.into_iter().chain(body_tokens)
     }
+
+    /// The type aliases, opaque types and abilities that the module defines.
+    pub fn type_defs(&self) -> impl Iterator<Item = Loc<TypeDef<'a>>> + '_ {
+        self.defs.loc_defs().filter_map(Result::ok)
+    }
+
+    /// The names that the module header exposes, or that an app or platform provides.
+    pub fn exposed_names(&self) -> Vec<Loc<&'a str>> {
+        let exposed = match &self.module.item {
+            Header::Module(header) => &header.exposes,
+            Header::App(header) => &header.provides,
+            Header::Hosted(header) => &header.exposes.item,
+            Header::Platform(header) => &header.provides.item,
+            Header::Package(_) => return Vec::new(),
+        };
+
+        exposed_names(exposed)
+    }
+
+    /// The names in the `module [...]` header of a module that is not an app, platform or
+    /// package.
+    pub fn module_exposes(&self) -> Option<Vec<Loc<&'a str>>> {
+        match &self.module.item {
+            Header::Module(header) => Some(exposed_names(&header.exposes)),
+            _ => None,
+        }
+    }
+
+    /// The abilities that opaque types name in their `implements [...]` clauses, along with the
+    /// name of the opaque type, like `Id` and `Hash` in `Id := U64 implements [Hash]`.
+    pub fn implemented_abilities(&self) -> Vec<(&'a str, Loc<&'a str>)> {
+        self.type_defs()
+            .filter_map(|loc_type_def| match loc_type_def.value {
+                TypeDef::Opaque {
+                    header,
+                    derived: Some(derived),
+                    ..
+                } => Some((header.name.value, implements_items(&derived.value))),
+                _ => None,
+            })
+            .flat_map(|(opaque_name, abilities)| {
+                abilities.iter().filter_map(move |loc_implements| {
+                    let ability = implements_ability(&loc_implements.value)?;
+
+                    Some((opaque_name, ability))
+                })
+            })
+            .collect()
+    }
+
+    /// Where the module header ends and the defs begin.
+    pub fn header_end(&self) -> Position {
+        self.header_end
+    }
+
+    /// The `import` statements of the module, in the order they appear.
+    pub fn module_imports(&self) -> Vec<Loc<ModuleImport<'a>>> {
+        self.defs
+            .loc_defs()
+            .filter_map(|loc_def| match loc_def {
+                Err(Loc {
+                    region,
+                    value: ValueDef::ModuleImport(module_import),
+                }) => Some(Loc::at(region, module_import)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The shorthands of the packages that an app, package or platform depends on, along with
+    /// the path or url of each package, like `pf` and `"../platform/main.roc"`.
+    pub fn packages(&self) -> Vec<(&'a str, &'a str)> {
+        let packages = match &self.module.item {
+            Header::App(header) => &header.packages.value,
+            Header::Package(header) => &header.packages.value,
+            Header::Platform(header) => &header.packages.item,
+            Header::Module(_) | Header::Hosted(_) => return Vec::new(),
+        };
+
+        packages
+            .items
+            .iter()
+            .map(|loc_entry| {
+                let entry = loc_entry.value.item();
+
+                (entry.shorthand, entry.package_name.value.as_str())
+            })
+            .collect()
+    }
+
+    /// The names that `import <module_name> exposing [...]` brings into scope.
+    pub fn names_exposed_by_imports_of(&self, module_name: &str) -> Vec<Loc<&'a str>> {
+        self.defs
+            .value_defs
+            .iter()
+            .filter_map(|value_def| match value_def {
+                ValueDef::ModuleImport(ModuleImport {
+                    name,
+                    exposed: Some(exposed),
+                    ..
+                }) if name.value.name.as_str() == module_name => Some(exposed_names(&exposed.item)),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+fn implements_items<'a>(
+    mut implements: &ImplementsAbilities<'a>,
+) -> &'a [Loc<ImplementsAbility<'a>>] {
+    loop {
+        match implements {
+            ImplementsAbilities::Implements(collection) => return collection.items,
+            ImplementsAbilities::SpaceBefore(inner, _)
+            | ImplementsAbilities::SpaceAfter(inner, _) => {
+                implements = inner;
+            }
+        }
+    }
+}
+
+/// The name of the ability in `Eq` or `Hash { hash: myHash }`
+fn implements_ability<'a>(implements: &ImplementsAbility<'a>) -> Option<Loc<&'a str>> {
+    match implements {
+        ImplementsAbility::ImplementsAbility { ability, .. } => {
+            let mut annotation = &ability.value;
+
+            loop {
+                match annotation {
+                    TypeAnnotation::SpaceBefore(inner, _)
+                    | TypeAnnotation::SpaceAfter(inner, _) => {
+                        annotation = inner;
+                    }
+                    TypeAnnotation::Apply(_, name, _) => {
+                        return Some(Loc::at(ability.region, *name))
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        ImplementsAbility::SpaceBefore(inner, _) | ImplementsAbility::SpaceAfter(inner, _) => {
+            implements_ability(inner)
+        }
+    }
+}
+
+fn exposed_names<'a>(
+    exposed: &Collection<'a, Loc<Spaced<'a, ExposedName<'a>>>>,
+) -> Vec<Loc<&'a str>> {
+    exposed
+        .items
+        .iter()
+        .map(|loc_name| Loc::at(loc_name.region, (*loc_name.value.item()).into()))
+        .collect()
 }
@@ -0,0 +1,417 @@
+//! Quick fixes for problems found while analyzing a document, offered as code actions on the
+//! diagnostics of those problems.
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+use roc_can::{
+    expr::{Declarations, Expr},
+    traverse::{find_declaration, walk_expr, FoundDeclaration, Visitor},
+};
+use roc_collections::MutMap;
+use roc_exhaustive::Context;
+use roc_module::{
+    ident::ModuleName,
+    symbol::{Interns, ModuleId, Symbol},
+};
+use roc_problem::can::{Problem, RuntimeError};
+use roc_region::all::{LineInfo, Loc, Position, Region};
+use roc_reporting::{error::r#type::suggest, report::RocDocAllocator};
+use roc_solve_problem::TypeError;
+use roc_types::subs::Variable;
+use tower_lsp::lsp_types::{CodeAction, CodeActionKind, Diagnostic, TextEdit, Url, WorkspaceEdit};
+
+use crate::convert::ToRange;
+
+use super::parse_ast::Ast;
+
+/// The most names offered as replacements for a name that is not in scope
+const MAX_NAME_SUGGESTIONS: usize = 3;
+
+/// A fix for a single problem, made of edits to the document the problem is in, or to a
+/// module that it imports
+pub(super) struct QuickFix {
+    title: String,
+    edits: Vec<TextEdit>,
+    /// The document that the edits are in, if not the one with the problem
+    document: Option<Url>,
+    is_preferred: bool,
+}
+
+impl QuickFix {
+    fn new(title: String, edits: Vec<TextEdit>) -> Self {
+        Self {
+            title,
+            edits,
+            document: None,
+            is_preferred: false,
+        }
+    }
+
+    fn preferred(self) -> Self {
+        Self {
+            is_preferred: true,
+            ..self
+        }
+    }
+
+    fn in_document(self, document: Url) -> Self {
+        Self {
+            document: Some(document),
+            ..self
+        }
+    }
+
+    pub fn into_code_action(self, url: &Url, diagnostic: &Diagnostic) -> CodeAction {
+        let url = self.document.unwrap_or_else(|| url.clone());
+        let changes = HashMap::from([(url, self.edits)]);
+
+        CodeAction {
+            title: self.title,
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(changes)),
+            is_preferred: Some(self.is_preferred),
+            ..Default::default()
+        }
+    }
+}
+
+/// A module that the fixes for problems in other modules can edit
+pub(super) struct ImportedModule {
+    pub url: Url,
+    pub source: Box<str>,
+    /// The symbols of its top-level defs
+    pub top_level: Vec<Symbol>,
+}
+
+/// Everything about a document that is needed to work out how to fix its problems
+pub(super) struct QuickFixes<'a> {
+    pub source: &'a str,
+    pub line_info: &'a LineInfo,
+    pub interns: &'a Interns,
+    pub declarations: &'a Declarations,
+    pub alloc: &'a RocDocAllocator<'a>,
+    pub imported_modules: &'a MutMap<ModuleId, ImportedModule>,
+}
+
+impl QuickFixes<'_> {
+    pub fn for_can_problem(&self, problem: &Problem) -> Vec<QuickFix> {
+        match problem {
+            Problem::UnusedDef(symbol, _) => self.unused_def(*symbol),
+            Problem::UnusedImport(_, region) => self.remove_exposed_name(*region),
+            Problem::UnusedModuleImport(_, region) => self
+                .remove_lines(*region, "Remove the unused import")
+                .into_iter()
+                .collect(),
+            Problem::UnusedArgument(symbol, _, _, region)
+            | Problem::UnusedBranchDef(symbol, region) => {
+                self.prefix_with_underscore(*symbol, *region)
+            }
+            Problem::RuntimeError(RuntimeError::LookupNotInScope {
+                loc_name,
+                suggestion_options,
+                ..
+            }) => self.replace_name(
+                Loc::at(loc_name.region, loc_name.value.as_str()),
+                suggestion_options.iter().map(|option| option.as_ref()),
+            ),
+            Problem::RuntimeError(RuntimeError::ValueNotExposed {
+                module_name, ident, ..
+            }) => self
+                .expose_imported(module_name, ident.as_str())
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn for_type_problem(&self, problem: &TypeError) -> Vec<QuickFix> {
+        match problem {
+            TypeError::Exhaustive(roc_exhaustive::Error::Incomplete(
+                region,
+                Context::BadCase,
+                missing,
+            )) => self.add_missing_branches(*region, missing),
+            _ => Vec::new(),
+        }
+    }
+
+    fn edit(&self, region: Region, new_text: impl Into<String>) -> TextEdit {
+        TextEdit {
+            range: region.to_range(self.line_info),
+            new_text: new_text.into(),
+        }
+    }
+
+    fn insert(&self, position: Position, new_text: impl Into<String>) -> TextEdit {
+        self.edit(Region::new(position, position), new_text)
+    }
+
+    fn unused_def(&self, symbol: Symbol) -> Vec<QuickFix> {
+        let is_top_level = self
+            .declarations
+            .symbols
+            .iter()
+            .any(|loc_symbol| loc_symbol.value == symbol);
+
+        if is_top_level {
+            return self.expose(symbol).into_iter().collect();
+        }
+
+        match find_declaration(symbol, self.declarations) {
+            Some(FoundDeclaration::Def(def)) => self
+                .remove_lines(def.region(), "Remove the unused def")
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Adds a top-level def to the `module [...]` header, so other modules can use it.
+    fn expose(&self, symbol: Symbol) -> Option<QuickFix> {
+        let name = symbol.as_str(self.interns);
+        let edit = expose_edit(self.source, self.line_info, name)?;
+
+        Some(QuickFix::new(format!("Expose `{name}`"), vec![edit]))
+    }
+
+    /// Adds a top-level def of an imported module to the `module [...]` header of that module,
+    /// for a module that uses it although it is not exposed.
+    fn expose_imported(&self, module_name: &ModuleName, name: &str) -> Option<QuickFix> {
+        let module_id = self.interns.module_ids.get_id(module_name)?;
+        let module = self.imported_modules.get(&module_id)?;
+
+        let is_top_level = module
+            .top_level
+            .iter()
+            .any(|symbol| symbol.as_str(self.interns) == name);
+
+        if !is_top_level {
+            return None;
+        }
+
+        let edit = expose_edit(&module.source, &LineInfo::new(&module.source), name)?;
+
+        Some(
+            QuickFix::new(format!("Expose `{name}` from {module_name}"), vec![edit])
+                .in_document(module.url.clone()),
+        )
+    }
+
+    /// Removes an unused name from the `exposing [...]` list of an import, along with the comma
+    /// that separates it from the next or previous name.
+    fn remove_exposed_name(&self, region: Region) -> Vec<QuickFix> {
+        let start = region.start().offset as usize;
+        let end = region.end().offset as usize;
+
+        let after = &self.source[end..];
+        let before = &self.source[..start];
+
+        let (start, end) = if let Some(rest) = after.trim_start().strip_prefix(',') {
+            (start, self.source.len() - rest.trim_start().len())
+        } else if let Some(rest) = before.trim_end().strip_suffix(',') {
+            (rest.len(), end)
+        } else {
+            (start, end)
+        };
+
+        let removed = Region::new(position_at(start), position_at(end));
+
+        vec![QuickFix::new(
+            "Remove the unused import".to_string(),
+            vec![self.edit(removed, "")],
+        )
+        .preferred()]
+    }
+
+    /// Removes the lines that `region` spans, if nothing but `region` is on them.
+    fn remove_lines(&self, region: Region, title: &str) -> Option<QuickFix> {
+        let start = region.start().offset as usize;
+        let end = region.end().offset as usize;
+
+        let line_start = self.source[..start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |index| end + index + 1);
+
+        let is_alone = self.source[line_start..start].trim().is_empty()
+            && self.source[end..line_end].trim().is_empty();
+
+        if !is_alone {
+            return None;
+        }
+
+        let removed = Region::new(position_at(line_start), position_at(line_end));
+
+        Some(QuickFix::new(title.to_string(), vec![self.edit(removed, "")]).preferred())
+    }
+
+    fn prefix_with_underscore(&self, symbol: Symbol, region: Region) -> Vec<QuickFix> {
+        let name = symbol.as_str(self.interns);
+        let start = region.start().offset as usize;
+
+        let starts_with_name = self.source[start..]
+            .strip_prefix(name)
+            .map_or(false, |rest| {
+                !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            });
+
+        if !starts_with_name {
+            return Vec::new();
+        }
+
+        vec![QuickFix::new(
+            format!("Rename to `_{name}`"),
+            vec![self.insert(region.start(), "_")],
+        )]
+    }
+
+    fn replace_name<'b>(
+        &self,
+        loc_name: Loc<&str>,
+        options: impl Iterator<Item = &'b str>,
+    ) -> Vec<QuickFix> {
+        let matches = suggest::close_matches(loc_name.value, options.collect());
+
+        matches
+            .into_iter()
+            .take(MAX_NAME_SUGGESTIONS)
+            .enumerate()
+            .map(|(index, name)| {
+                let fix = QuickFix::new(
+                    format!("Change to `{name}`"),
+                    vec![self.edit(loc_name.region, name)],
+                );
+
+                if index == 0 {
+                    fix.preferred()
+                } else {
+                    fix
+                }
+            })
+            .collect()
+    }
+
+    /// Adds a branch that crashes for each pattern a `when` is missing. The `region` of the
+    /// problem spans from the condition of the `when` to the value of its last branch.
+    fn add_missing_branches(
+        &self,
+        region: Region,
+        missing: &[roc_exhaustive::Pattern],
+    ) -> Vec<QuickFix> {
+        let Some((first_pattern, last_value)) = find_when_branches(self.declarations, region)
+        else {
+            return Vec::new();
+        };
+
+        let indent = " ".repeat(self.line_info.convert_pos(first_pattern.start()).column as usize);
+
+        let new_branches: Option<String> = missing
+            .iter()
+            .map(|pattern| {
+                let source = roc_reporting::error::r#type::unhandled_pattern_to_source(
+                    self.alloc,
+                    pattern.clone(),
+                )?;
+
+                Some(format!("\n{indent}{source} -> crash \"TODO\""))
+            })
+            .collect();
+
+        let Some(new_branches) = new_branches else {
+            return Vec::new();
+        };
+
+        let title = if missing.len() == 1 {
+            "Add the missing branch"
+        } else {
+            "Add the missing branches"
+        };
+
+        vec![QuickFix::new(
+            title.to_string(),
+            vec![self.insert(last_value.end(), new_branches)],
+        )
+        .preferred()]
+    }
+}
+
+/// Finds the `when` whose condition starts, and whose last branch ends, at the bounds of
+/// `region`. Returns the regions of its first branch pattern and of its last branch value.
+fn find_when_branches(decls: &Declarations, region: Region) -> Option<(Region, Region)> {
+    let mut finder = WhenFinder {
+        region,
+        found: None,
+    };
+    finder.visit_decls(decls);
+    return finder.found;
+
+    struct WhenFinder {
+        region: Region,
+        found: Option<(Region, Region)>,
+    }
+
+    impl Visitor for WhenFinder {
+        fn should_visit(&mut self, region: Region) -> bool {
+            self.found.is_none() && region.contains(&self.region)
+        }
+
+        fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+            if let Expr::When {
+                loc_cond, branches, ..
+            } = expr
+            {
+                if loc_cond.region.start() == self.region.start() {
+                    let first_pattern = branches.first().and_then(|first| first.patterns.first());
+                    let last_value = branches.last().map(|last| last.value.region);
+
+                    if let (Some(first_pattern), Some(last_value)) = (first_pattern, last_value) {
+                        if last_value.end() == self.region.end() {
+                            self.found = Some((first_pattern.pattern.region, last_value));
+                            return;
+                        }
+                    }
+                }
+            }
+
+            if self.should_visit(region) {
+                walk_expr(self, expr, var);
+            }
+        }
+    }
+}
+
+/// Adds `name` to the `module [...]` header of `source`: after the last exposed name, or into
+/// the empty list that ends the header.
+fn expose_edit(source: &str, line_info: &LineInfo, name: &str) -> Option<TextEdit> {
+    let arena = Bump::new();
+    let ast = Ast::parse(&arena, source).ok()?;
+    let exposed = ast.module_exposes()?;
+
+    let (position, new_text) = match exposed.last() {
+        Some(last) => (last.region.end(), format!(", {name}")),
+        None => {
+            // the list ends the header, unless it is an old `interface` header with imports
+            let header = source[..ast.header_end().offset as usize].trim_end();
+            let open_bracket = header.strip_suffix(']')?.rfind('[')?;
+            let before_list = header[..open_bracket].trim_end();
+
+            if !(before_list.ends_with("module") || before_list.ends_with("->")) {
+                return None;
+            }
+
+            (position_at(open_bracket + 1), name.to_string())
+        }
+    };
+
+    Some(TextEdit {
+        range: Region::new(position, position).to_range(line_info),
+        new_text,
+    })
+}
+
+fn position_at(offset: usize) -> Position {
+    Position::new(offset as u32)
+}
@@ -0,0 +1,154 @@
+//! Finds the identifiers in a document that refer to a symbol, for find-references and rename.
+//!
+//! Which symbol each occurrence refers to is decided by canonicalization; the source is only
+//! read to narrow an occurrence's region down to the identifier itself.
+use roc_can::traverse::SymbolOccurrence;
+use roc_region::all::{Loc, Position, Region};
+
+/// An identifier that refers to a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Reference {
+    /// The region of just the identifier
+    pub region: Region,
+    /// Whether the symbol is defined or annotated here, rather than used
+    pub is_definition: bool,
+    pub rename: RenameEdit,
+}
+
+/// How the identifier of a [Reference] changes when its symbol is renamed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenameEdit {
+    /// It is replaced by the new name.
+    Replace,
+    /// It is also a record field's label, like in `{ bar }`, so the label is kept: `{ bar: baz }`.
+    KeepLabel,
+    /// It is also the label of an optional record field like `{ bar ? 0 }`, which can't be
+    /// given a different name.
+    Unsupported,
+}
+
+impl RenameEdit {
+    pub fn new_text(&self, old_name: &str, new_name: &str) -> Option<String> {
+        match self {
+            RenameEdit::Replace => Some(new_name.to_string()),
+            RenameEdit::KeepLabel => Some(format!("{old_name}: {new_name}")),
+            RenameEdit::Unsupported => None,
+        }
+    }
+}
+
+/// The references to the symbol named `name`, at the occurrences found in `source`.
+pub(super) fn references_at_occurrences<'a>(
+    source: &'a str,
+    occurrences: impl IntoIterator<Item = SymbolOccurrence> + 'a,
+    name: &'a str,
+) -> impl Iterator<Item = Reference> + 'a {
+    occurrences.into_iter().filter_map(move |occurrence| {
+        use SymbolOccurrence::*;
+
+        let region = occurrence.region();
+
+        let (identifier, rename) = match occurrence {
+            Definition(_) | Lookup(_) => (
+                find_ident(source, region, name, Which::Last)?,
+                RenameEdit::Replace,
+            ),
+            RecordUpdate(_) => (
+                find_ident(source, region, name, Which::First)?,
+                RenameEdit::Replace,
+            ),
+            DestructShorthand(_) | FieldShorthand(_) => (
+                find_ident(source, region, name, Which::First)?,
+                RenameEdit::KeepLabel,
+            ),
+            OptionalDestruct(_) => (
+                find_ident(source, region, name, Which::First)?,
+                RenameEdit::Unsupported,
+            ),
+            Annotation(_) => (annotated_name(source, region, name)?, RenameEdit::Replace),
+        };
+
+        Some(Reference {
+            region: identifier,
+            is_definition: occurrence.is_definition(),
+            rename,
+        })
+    })
+}
+
+/// The references among `names`, like the exposed names of a module header.
+pub(super) fn references_in_names<'a>(
+    source: &'a str,
+    names: Vec<Loc<&'a str>>,
+    name: &'a str,
+) -> impl Iterator<Item = Reference> + 'a {
+    names
+        .into_iter()
+        .filter(move |loc_name| loc_name.value == name)
+        .filter_map(move |loc_name| {
+            Some(Reference {
+                region: find_ident(source, loc_name.region, name, Which::First)?,
+                is_definition: false,
+                rename: RenameEdit::Replace,
+            })
+        })
+}
+
+/// Whether `name` can be the name of a value or function, like `bar` or `readFile!`
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.strip_suffix('!').unwrap_or(name).chars();
+
+    matches!(chars.next(), Some(first) if first.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && roc_parse::keyword::is_allowed_identifier(name)
+}
+
+#[derive(Clone, Copy)]
+enum Which {
+    First,
+    Last,
+}
+
+/// Finds the identifier `name` within `region`, not counting longer identifiers that contain it.
+fn find_ident(source: &str, region: Region, name: &str, which: Which) -> Option<Region> {
+    let start = region.start().offset as usize;
+    let text = source.get(start..region.end().offset as usize)?;
+
+    let mut found = text.match_indices(name).filter(|(index, _)| {
+        let before = text[..*index].chars().next_back();
+        let after = text[index + name.len()..].chars().next();
+
+        !before.map_or(false, |c| c.is_ascii_alphanumeric() || c == '_')
+            && !after.map_or(false, |c| c.is_ascii_alphanumeric() || c == '_' || c == '!')
+    });
+
+    let (index, _) = match which {
+        Which::First => found.next(),
+        Which::Last => found.last(),
+    }?;
+
+    Some(region_of(start + index, name.len()))
+}
+
+/// Finds the `name` of `name : ...` just before the type annotation at `annotation`.
+fn annotated_name(source: &str, annotation: Region, name: &str) -> Option<Region> {
+    let before = source
+        .get(..annotation.start().offset as usize)?
+        .trim_end()
+        .strip_suffix(':')?
+        .trim_end();
+
+    let start = before.strip_suffix(name)?.len();
+
+    let is_whole_ident =
+        !before[..start].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+
+    is_whole_ident.then(|| region_of(start, name.len()))
+}
+
+fn region_of(offset: usize, len: usize) -> Region {
+    Region::new(
+        Position::new(offset as u32),
+        Position::new((offset + len) as u32),
+    )
+}
@@ -0,0 +1,389 @@
+//! Signature help for the function application being typed.
+//!
+//! The application is found in the text before the cursor, since it is usually incomplete while
+//! being typed. Its function is then looked up by name in the latest analysis that type checked.
+use roc_module::symbol::{ModuleId, Symbol};
+use roc_region::all::Position;
+use roc_types::subs::Variable;
+use tower_lsp::lsp_types::{
+    Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, SignatureHelp,
+    SignatureInformation,
+};
+
+use super::{
+    completion::{find_record_fields, get_completions},
+    utils::format_var_type,
+    AnalyzedModule,
+};
+
+/// Words after which a new expression starts, rather than another argument
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "when", "is", "expect", "dbg", "return", "crash",
+];
+
+/// A function application that the cursor is at the end of, like `List.walk list `
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Call<'a> {
+    /// The function, like `List.walk`, `rec.fn` or `fn`
+    pub function: &'a str,
+    /// The offset of the function within the text
+    pub offset: usize,
+    /// The index of the argument being typed
+    pub argument: usize,
+}
+
+/// Finds the application that `text` ends in. Only applications on the last line are found.
+pub(super) fn call_at_end(text: &str) -> Option<Call<'_>> {
+    let bytes = text.as_bytes();
+
+    // the spans of the terms of the application, from last to first
+    let mut terms: Vec<(usize, usize)> = Vec::new();
+    let mut term_end = None;
+    let mut is_piped = false;
+    let mut depth = 0;
+    let mut index = bytes.len();
+
+    while index > 0 {
+        let byte = bytes[index - 1];
+
+        if byte == b'"' {
+            // strings can contain anything, so skip over them whole
+            term_end.get_or_insert(index);
+            index = string_start(bytes, index - 1)?;
+            continue;
+        }
+
+        if depth > 0 {
+            match byte {
+                b')' | b']' | b'}' => depth += 1,
+                b'(' | b'[' | b'{' => depth -= 1,
+                _ => {}
+            }
+        } else {
+            match byte {
+                b'\n' => break,
+                b' ' | b'\t' | b'\r' => {
+                    if let Some(end) = term_end.take() {
+                        terms.push((index, end));
+                    }
+                }
+                b')' | b']' | b'}' => {
+                    term_end.get_or_insert(index);
+                    depth += 1;
+                }
+                b'>' if index >= 2 && bytes[index - 2] == b'|' => {
+                    is_piped = true;
+                    break;
+                }
+                b'!' if index >= 2 && is_ident_byte(bytes[index - 2]) => {
+                    // the suffix of an effectful function like `line!`
+                    term_end.get_or_insert(index);
+                }
+                byte if is_ident_byte(byte) || byte == b'.' || !byte.is_ascii() => {
+                    term_end.get_or_insert(index);
+                }
+                _ => break,
+            }
+        }
+
+        index -= 1;
+    }
+
+    if let Some(end) = term_end {
+        terms.push((index, end));
+    }
+
+    terms.reverse();
+
+    let after_keyword = terms
+        .iter()
+        .rposition(|&(start, end)| KEYWORDS.contains(&&text[start..end]))
+        .map_or(0, |keyword| keyword + 1);
+    let terms = &terms[after_keyword..];
+
+    let &(start, end) = terms.first()?;
+    let function = &text[start..end];
+
+    if !is_function_name(function) {
+        return None;
+    }
+
+    let is_typing_term = !text.ends_with(|c: char| c.is_ascii_whitespace());
+    let typed_arguments = terms.len() - 1;
+
+    let argument = match (is_typing_term, typed_arguments) {
+        // the function's own name is being typed
+        (true, 0) => return None,
+        (true, typed) => typed - 1,
+        (false, typed) => typed,
+    };
+
+    Some(Call {
+        function,
+        offset: start,
+        argument: argument + usize::from(is_piped),
+    })
+}
+
+/// The signature of the function that `call` applies, with the argument it is at highlighted.
+pub(super) fn signature_help(
+    module: &AnalyzedModule,
+    call: Call<'_>,
+    position: Position,
+) -> Option<SignatureHelp> {
+    let (type_str, docs) = function_type_and_docs(module, call.function, position)?;
+
+    let prefix = format!("{} : ", call.function);
+    let parameters = parameter_offsets(&type_str)?
+        .into_iter()
+        .map(|(start, end)| ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                utf16_len(&prefix) + utf16_len(&type_str[..start]),
+                utf16_len(&prefix) + utf16_len(&type_str[..end]),
+            ]),
+            documentation: None,
+        })
+        .collect();
+
+    let active_parameter = Some(call.argument as u32);
+
+    let signature = SignatureInformation {
+        label: prefix + &type_str,
+        documentation: docs.map(|docs| {
+            Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: docs.trim().to_string(),
+            })
+        }),
+        parameters: Some(parameters),
+        active_parameter,
+    };
+
+    Some(SignatureHelp {
+        signatures: vec![signature],
+        active_signature: Some(0),
+        active_parameter,
+    })
+}
+
+/// Looks up `function` in scope at `position`, as a function of an imported module like
+/// `List.walk`, a function in a record like `rec.fn`, or a def or argument like `fn`.
+fn function_type_and_docs(
+    module: &AnalyzedModule,
+    function: &str,
+    position: Position,
+) -> Option<(String, Option<String>)> {
+    let AnalyzedModule {
+        module_id,
+        interns,
+        subs,
+        declarations,
+        exposed_imports,
+        imports_by_module,
+        modules_info,
+        ..
+    } = module;
+
+    let (qualifier, name) = match function.rsplit_once('.') {
+        Some((qualifier, name)) => (Some(qualifier), name),
+        None => (None, function),
+    };
+
+    let named = |symbol: &Symbol| symbol.as_str(interns) == name;
+
+    let imported = |defining_module: ModuleId, symbol: Symbol, var: Variable| {
+        let type_str = modules_info.with_subs(&defining_module, |subs| {
+            format_var_type(var, subs, &defining_module, interns)
+        })?;
+        let docs = modules_info
+            .get_docs(&defining_module)
+            .and_then(|docs| docs.get_doc_for_symbol(&symbol));
+
+        Some((type_str, docs))
+    };
+
+    match qualifier {
+        Some(module_name) if module_name.starts_with(|c: char| c.is_ascii_uppercase()) => {
+            let (imported_id, exposed) = imports_by_module
+                .iter()
+                .find(|(id, _)| id.to_ident_str(interns).as_str() == module_name)?;
+            let (symbol, var) = exposed.iter().find(|(symbol, _)| named(symbol))?;
+
+            imported(*imported_id, *symbol, *var)
+        }
+        Some(record_path) => {
+            let mut subs = subs.clone();
+            let mut fields = record_path.split('.');
+
+            let record_name = fields.next()?;
+            let (_, record_var) =
+                get_completions(position, declarations, record_name.to_string(), interns)
+                    .into_iter()
+                    .find(|(symbol, _)| symbol.as_str(interns) == record_name)?;
+
+            let var = fields.chain([name]).try_fold(record_var, |var, field| {
+                find_record_fields(var, &mut subs)
+                    .into_iter()
+                    .find(|(field_name, _)| field_name == field)
+                    .map(|(_, field_var)| field_var)
+            })?;
+
+            Some((format_var_type(var, &mut subs, module_id, interns), None))
+        }
+        None => {
+            let local = get_completions(position, declarations, name.to_string(), interns)
+                .into_iter()
+                .find(|(symbol, _)| named(symbol));
+
+            match local {
+                Some((symbol, var)) => {
+                    let type_str = format_var_type(var, &mut subs.clone(), module_id, interns);
+                    let docs = modules_info
+                        .get_docs(module_id)
+                        .and_then(|docs| docs.get_doc_for_symbol(&symbol));
+
+                    Some((type_str, docs))
+                }
+                None => {
+                    let (symbol, var) = exposed_imports.iter().find(|(symbol, _)| named(symbol))?;
+
+                    imported(symbol.module_id(), *symbol, *var)
+                }
+            }
+        }
+    }
+}
+
+/// The byte ranges of the parameters of a printed function type, like `List a` and `(a -> b)`
+/// in `List a, (a -> b) -> List b`. Types that are not functions have no parameters.
+fn parameter_offsets(type_str: &str) -> Option<Vec<(usize, usize)>> {
+    let bytes = type_str.as_bytes();
+
+    let mut parameters = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+
+    for (index, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                parameters.push(trimmed(type_str, start, index));
+                start = index + 1;
+            }
+            b'-' | b'=' if depth == 0 && bytes.get(index + 1) == Some(&b'>') => {
+                parameters.push(trimmed(type_str, start, index));
+                return Some(parameters);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The range from `start` to `end` without the whitespace around it
+fn trimmed(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let slice = &text[start..end];
+    let leading = slice.len() - slice.trim_start().len();
+
+    (start + leading, start + slice.trim_end().len())
+}
+
+/// Finds the start of the string literal that ends with the quote at `close`.
+fn string_start(bytes: &[u8], close: usize) -> Option<usize> {
+    (0..close)
+        .rev()
+        .find(|&index| bytes[index] == b'"' && (index == 0 || bytes[index - 1] != b'\\'))
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn is_function_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic())
+        && text.ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '!')
+        && text.split('.').last().map_or(false, |name| {
+            name.starts_with(|c: char| c.is_ascii_lowercase())
+        })
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function: &str, offset: usize, argument: usize) -> Option<Call<'_>> {
+        Some(Call {
+            function,
+            offset,
+            argument,
+        })
+    }
+
+    #[test]
+    fn call_after_a_space_is_at_the_next_argument() {
+        assert_eq!(
+            call_at_end("main = List.walk list "),
+            call("List.walk", 7, 1)
+        );
+        assert_eq!(call_at_end("Stdout.line! "), call("Stdout.line!", 0, 0));
+        assert_eq!(call_at_end("rec.fn 1 "), call("rec.fn", 0, 1));
+    }
+
+    #[test]
+    fn call_with_an_argument_being_typed() {
+        assert_eq!(call_at_end("foo bar ba"), call("foo", 0, 1));
+        assert_eq!(call_at_end("List.wa"), None);
+    }
+
+    #[test]
+    fn call_skips_strings_and_parens() {
+        assert_eq!(call_at_end(r#"join ", " "#), call("join", 0, 1));
+        assert_eq!(call_at_end("f (g 1 2) "), call("f", 0, 1));
+    }
+
+    #[test]
+    fn call_after_a_pipe_skips_the_piped_argument() {
+        assert_eq!(call_at_end("list |> List.map "), call("List.map", 8, 1));
+    }
+
+    #[test]
+    fn call_starts_after_keywords_and_lines() {
+        assert_eq!(call_at_end("if foo x "), call("foo", 3, 1));
+        assert_eq!(call_at_end("foo\nbar "), call("bar", 4, 0));
+    }
+
+    #[test]
+    fn call_of_something_else_than_a_function() {
+        assert_eq!(call_at_end("x = 1 "), None);
+        assert_eq!(call_at_end("Ok "), None);
+    }
+
+    #[test]
+    fn parameters_of_function_types() {
+        assert_eq!(
+            parameter_offsets("Str, List Str -> Str"),
+            Some(vec![(0, 3), (5, 13)])
+        );
+        assert_eq!(
+            parameter_offsets("List a, (a -> b) -> List b"),
+            Some(vec![(0, 6), (8, 16)])
+        );
+        assert_eq!(
+            parameter_offsets("{ a : Str, b : U64 }, Str -> Str"),
+            Some(vec![(0, 20), (22, 25)])
+        );
+        assert_eq!(parameter_offsets("Str => {}"), Some(vec![(0, 3)]));
+    }
+
+    #[test]
+    fn parameters_of_other_types() {
+        assert_eq!(parameter_offsets("Str"), None);
+        assert_eq!(parameter_offsets("List (a -> b)"), None);
+    }
+}
@@ -0,0 +1,283 @@
+//! The outline of a document, for document and workspace symbols, and the regions of it that
+//! editors can fold.
+use bumpalo::Bump;
+use roc_can::{
+    def::Def,
+    expr::{Declarations, Expr},
+    pattern::Pattern,
+    traverse::{walk_decl, walk_def, walk_expr, DeclarationInfo, Visitor},
+};
+use roc_module::symbol::{Interns, ModuleId, Symbol};
+use roc_parse::ast::TypeDef;
+use roc_region::all::{LineInfo, Region};
+use roc_types::subs::{Subs, Variable};
+use tower_lsp::lsp_types::{DocumentSymbol, FoldingRange, SymbolKind};
+
+use crate::convert::ToRange;
+
+use super::{parse_ast::Ast, utils::format_var_type, AnalyzedModule};
+
+/// The defs and type definitions of a document, with the defs nested in them as children, in
+/// the order they appear.
+pub(super) fn document_symbols(
+    module: Option<&AnalyzedModule>,
+    source: &str,
+    line_info: &LineInfo,
+) -> Vec<DocumentSymbol> {
+    let mut symbols = type_def_symbols(source, line_info);
+
+    if let Some(module) = module {
+        let mut collector = SymbolCollector {
+            interns: &module.interns,
+            module_id: module.module_id,
+            subs: module.subs.clone(),
+            line_info,
+            scopes: vec![Vec::new()],
+        };
+        collector.visit_decls(&module.declarations);
+
+        symbols.extend(collector.scopes.pop().unwrap_or_default());
+    }
+
+    symbols.sort_by_key(|symbol| symbol.range.start);
+    symbols
+}
+
+/// Whether the characters of `query` appear in `name` in order, ignoring case, so that `mpfl`
+/// matches `mapFileLines`.
+pub(super) fn fuzzy_matches(query: &str, name: &str) -> bool {
+    let mut name_chars = name.chars().map(|c| c.to_ascii_lowercase());
+
+    query
+        .chars()
+        .map(|c| c.to_ascii_lowercase())
+        .all(|query_char| name_chars.any(|name_char| name_char == query_char))
+}
+
+#[allow(deprecated)]
+fn document_symbol(
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: Region,
+    selection_range: Region,
+    line_info: &LineInfo,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: range.to_range(line_info),
+        selection_range: selection_range.to_range(line_info),
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// Type aliases, opaque types and abilities are not part of the canonical declarations, so they
+/// are read from the parsed document.
+fn type_def_symbols(source: &str, line_info: &LineInfo) -> Vec<DocumentSymbol> {
+    let arena = Bump::new();
+    let Ok(ast) = Ast::parse(&arena, source) else {
+        return Vec::new();
+    };
+
+    ast.type_defs()
+        .map(|loc_type_def| {
+            let (header, kind, members) = match loc_type_def.value {
+                TypeDef::Alias { header, .. } => (header, SymbolKind::TYPE_PARAMETER, &[][..]),
+                TypeDef::Opaque { header, .. } => (header, SymbolKind::STRUCT, &[][..]),
+                TypeDef::Ability {
+                    header, members, ..
+                } => (header, SymbolKind::INTERFACE, members),
+            };
+
+            let children = members
+                .iter()
+                .map(|member| {
+                    document_symbol(
+                        member.name.value.item().to_string(),
+                        None,
+                        SymbolKind::METHOD,
+                        Region::span_across(&member.name.region, &member.typ.region),
+                        member.name.region,
+                        line_info,
+                        Vec::new(),
+                    )
+                })
+                .collect();
+
+            document_symbol(
+                header.name.value.to_string(),
+                None,
+                kind,
+                loc_type_def.region,
+                header.name.region,
+                line_info,
+                children,
+            )
+        })
+        .collect()
+}
+
+struct SymbolCollector<'a> {
+    interns: &'a Interns,
+    module_id: ModuleId,
+    subs: Subs,
+    line_info: &'a LineInfo,
+    /// The symbols found so far in each def being visited, outermost first
+    scopes: Vec<Vec<DocumentSymbol>>,
+}
+
+impl SymbolCollector<'_> {
+    /// Adds the symbol for a def, with the symbols that `visit_body` finds as its children.
+    fn push_def(
+        &mut self,
+        symbol: Symbol,
+        kind: SymbolKind,
+        var: Variable,
+        range: Region,
+        selection_range: Region,
+        visit_body: impl FnOnce(&mut Self),
+    ) {
+        self.scopes.push(Vec::new());
+        visit_body(self);
+        let children = self.scopes.pop().unwrap_or_default();
+
+        let name = symbol.as_str(self.interns).to_string();
+        let detail = format_var_type(var, &mut self.subs, &self.module_id, self.interns);
+
+        let document_symbol = document_symbol(
+            name,
+            Some(detail),
+            kind,
+            range,
+            selection_range,
+            self.line_info,
+            children,
+        );
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(document_symbol);
+        }
+    }
+}
+
+impl Visitor for SymbolCollector<'_> {
+    fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+        let region = decl.region();
+
+        match decl {
+            DeclarationInfo::Value {
+                loc_symbol,
+                expr_var,
+                ..
+            } => self.push_def(
+                loc_symbol.value,
+                SymbolKind::CONSTANT,
+                expr_var,
+                region,
+                loc_symbol.region,
+                |this| walk_decl(this, decl),
+            ),
+            DeclarationInfo::Function {
+                loc_symbol,
+                expr_var,
+                ..
+            } => self.push_def(
+                loc_symbol.value,
+                SymbolKind::FUNCTION,
+                expr_var,
+                region,
+                loc_symbol.region,
+                |this| walk_decl(this, decl),
+            ),
+            DeclarationInfo::Expectation { .. } | DeclarationInfo::Destructure { .. } => {
+                walk_decl(self, decl)
+            }
+        }
+    }
+
+    fn visit_def(&mut self, def: &Def) {
+        let Pattern::Identifier(symbol) = def.loc_pattern.value else {
+            return walk_def(self, def);
+        };
+
+        let kind = match def.loc_expr.value {
+            Expr::Closure(_) => SymbolKind::FUNCTION,
+            _ => SymbolKind::VARIABLE,
+        };
+
+        self.push_def(
+            symbol,
+            kind,
+            def.expr_var,
+            def.region(),
+            def.loc_pattern.region,
+            |this| walk_def(this, def),
+        );
+    }
+}
+
+/// The multi-line defs and expressions of a document, like `when` blocks and records, outermost
+/// first. At most one range starts on each line.
+pub(super) fn folding_ranges(decls: &Declarations, line_info: &LineInfo) -> Vec<FoldingRange> {
+    let mut collector = FoldingCollector {
+        line_info,
+        ranges: Vec::new(),
+    };
+    collector.visit_decls(decls);
+
+    let mut ranges = collector.ranges;
+    ranges.sort_by_key(|range| range.start_line);
+    ranges.dedup_by_key(|range| range.start_line);
+    ranges
+}
+
+struct FoldingCollector<'a> {
+    line_info: &'a LineInfo,
+    ranges: Vec<FoldingRange>,
+}
+
+impl FoldingCollector<'_> {
+    fn push(&mut self, region: Region) {
+        let range = region.to_range(self.line_info);
+
+        if range.start.line < range.end.line {
+            self.ranges.push(FoldingRange {
+                start_line: range.start.line,
+                end_line: range.end.line,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+impl Visitor for FoldingCollector<'_> {
+    fn visit_decl(&mut self, decl: DeclarationInfo<'_>) {
+        self.push(decl.region());
+        walk_decl(self, decl);
+    }
+
+    fn visit_def(&mut self, def: &Def) {
+        self.push(def.region());
+        walk_def(self, def);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr, region: Region, var: Variable) {
+        match expr {
+            Expr::When { .. }
+            | Expr::If { .. }
+            | Expr::Record { .. }
+            | Expr::RecordUpdate { .. }
+            | Expr::List { .. }
+            | Expr::Tuple { .. }
+            | Expr::Closure(_) => self.push(region),
+            _ => {}
+        }
+
+        walk_expr(self, expr, var);
+    }
+}
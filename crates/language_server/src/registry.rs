@@ -1,19 +1,36 @@
 use log::{debug, info, trace};
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, OnceLock},
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
     time::Duration,
 };
 
-use tokio::sync::{Mutex, MutexGuard};
+use tokio::sync::{Mutex, MutexGuard, Notify};
 
 use tower_lsp::lsp_types::{
-    CompletionResponse, Diagnostic, GotoDefinitionResponse, Hover, Position, SemanticTokensResult,
-    TextEdit, Url,
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, CodeActionKind,
+    CodeActionResponse, CodeLens, CompletionItem, CompletionItemLabelDetails, CompletionResponse,
+    Diagnostic, DocumentDiagnosticReport, DocumentSymbolResponse, FoldingRange,
+    FullDocumentDiagnosticReport, GotoDefinitionResponse, Hover, InlayHint, Location, Position,
+    PrepareRenameResponse, PreviousResultId, Range, RelatedFullDocumentDiagnosticReport,
+    RelatedUnchangedDocumentDiagnosticReport, SemanticTokensResult, SignatureHelp,
+    SymbolInformation, TextEdit, TypeHierarchyItem, UnchangedDocumentDiagnosticReport, Url,
+    WorkspaceDiagnosticReport, WorkspaceDocumentDiagnosticReport, WorkspaceEdit,
+    WorkspaceFullDocumentDiagnosticReport,
 };
 
-use crate::analysis::{AnalyzedDocument, DocInfo};
+use roc_module::symbol::Symbol;
+use roc_packaging::cache;
+
+use crate::{
+    analysis::{is_valid_name, AnalyzedDocument, DocInfo, LensResults, RenameEdit},
+    run::{self, ExpectOutcome},
+};
 
 #[derive(Debug)]
 pub(crate) struct DocumentPair {
@@ -51,17 +68,90 @@ impl Default for RegistryConfig {
 #[derive(Debug, Default)]
 pub(crate) struct Registry {
     documents: Mutex<HashMap<Url, DocumentPair>>,
+    /// The documents that are open in the editor, whose unsaved contents are used for them
+    open_urls: Mutex<HashSet<Url>>,
+    /// How many times analyses have been applied, which identifies the diagnostics of every
+    /// document for pull diagnostics
+    generation: AtomicU64,
+    /// Notified whenever an analysis is applied
+    changed: Notify,
+    /// What running the code lenses of each document showed
+    lens_results: Mutex<HashMap<Url, LensResults>>,
     config: RegistryConfig,
 }
 
 impl Registry {
     pub(crate) fn new(config: RegistryConfig) -> Self {
         Self {
-            documents: Default::default(),
             config,
+            ..Default::default()
         }
     }
 
+    pub async fn open(&self, url: Url) {
+        self.open_urls.lock().await.insert(url);
+    }
+
+    pub async fn close(&self, url: &Url) {
+        self.open_urls.lock().await.remove(url);
+    }
+
+    /// The latest contents of `url`, if it is open in the editor.
+    pub async fn open_document(&self, url: &Url) -> Option<DocInfo> {
+        if !self.open_urls.lock().await.contains(url) {
+            return None;
+        }
+
+        self.document_info_by_url(url).await
+    }
+
+    /// The latest contents of every document that is open in the editor, other than `except`.
+    pub async fn open_documents(&self, except: &Url) -> Vec<DocInfo> {
+        let open_urls = self.open_urls.lock().await;
+        let documents = self.documents.lock().await;
+
+        open_urls
+            .iter()
+            .filter(|url| *url != except)
+            .filter_map(|url| documents.get(url))
+            .map(|pair| pair.info.clone())
+            .collect()
+    }
+
+    /// The documents of every module that has been analyzed, other than the builtin ones.
+    pub async fn urls(&self) -> HashSet<Url> {
+        self.analyzed_documents()
+            .await
+            .iter()
+            .filter(|document| !document.is_builtin())
+            .map(|document| document.url().clone())
+            .collect()
+    }
+
+    /// The documents that import `url`, directly or through other modules.
+    pub async fn dependents(&self, url: &Url) -> Vec<Url> {
+        let documents = self.analyzed_documents().await;
+
+        let mut dependents: Vec<Url> = Vec::new();
+        let mut imported = vec![url.clone()];
+
+        while let Some(imported_url) = imported.pop() {
+            for document in &documents {
+                let dependent = document.url();
+
+                if dependent != url
+                    && !dependents.contains(dependent)
+                    && document.imported_urls().contains(&imported_url)
+                {
+                    dependents.push(dependent.clone());
+                    imported.push(dependent.clone());
+                }
+            }
+        }
+
+        dependents
+    }
+
     pub async fn get_latest_version(&self, url: &Url) -> Option<i32> {
         self.documents.lock().await.get(url).map(|x| x.info.version)
     }
@@ -118,6 +208,9 @@ impl Registry {
             let document = Arc::new(document);
             Registry::update_document(&mut documents, document, &updating_url);
         }
+
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.changed.notify_waiters();
     }
 
     pub async fn apply_doc_info_changes(&self, url: Url, info: DocInfo) {
@@ -164,6 +257,340 @@ impl Registry {
         .ok()
     }
 
+    /// The latest analysis of every document that type checked, or else its last good one.
+    async fn analyzed_documents(&self) -> Vec<Arc<AnalyzedDocument>> {
+        self.documents
+            .lock()
+            .await
+            .values()
+            .map(|pair| match pair.latest_document.get() {
+                Some(latest) if latest.type_checked() => latest.clone(),
+                _ => pair.last_good_document.clone(),
+            })
+            .collect()
+    }
+
+    /// Finds the symbol at `position`, and every document that can refer to it, along with the
+    /// symbol as each document's analysis knows it. Local symbols can only be referred to by
+    /// their own document, top-level ones by any document whose analysis includes their module.
+    async fn symbol_in_documents(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<(Arc<AnalyzedDocument>, Symbol)>> {
+        let (document, symbol) = self.symbol_at(url, position).await?;
+
+        let global_symbol = match document.global_symbol(symbol) {
+            Some(global_symbol) if document.is_top_level(symbol) => global_symbol,
+            _ => return Some(vec![(document, symbol)]),
+        };
+
+        let others = self
+            .analyzed_documents()
+            .await
+            .into_iter()
+            .filter(|other| other.url() != url)
+            .filter_map(|other| {
+                let symbol = other.resolve_global_symbol(&global_symbol)?;
+
+                Some((other, symbol))
+            });
+
+        Some(std::iter::once((document, symbol)).chain(others).collect())
+    }
+
+    async fn symbol_at(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<(Arc<AnalyzedDocument>, Symbol)> {
+        let document = self.latest_document_by_url(url).await?;
+        let symbol = document
+            .symbol_at(position)
+            .or_else(|| document.exposed_symbol_at(position))?;
+
+        Some((document, symbol))
+    }
+
+    /// The document of the user's that defines `symbol` of `document`, along with the symbol
+    /// as its analysis knows it.
+    async fn defining_document(
+        &self,
+        document: &Arc<AnalyzedDocument>,
+        symbol: Symbol,
+    ) -> Option<(Arc<AnalyzedDocument>, Symbol)> {
+        if symbol.is_builtin() {
+            return None;
+        }
+
+        if document.defines(symbol) {
+            return Some((document.clone(), symbol));
+        }
+
+        let global_symbol = document.global_symbol(symbol)?;
+
+        self.analyzed_documents()
+            .await
+            .into_iter()
+            .find_map(|other| {
+                let symbol = other.resolve_global_symbol(&global_symbol)?;
+
+                other.defines(symbol).then_some((other, symbol))
+            })
+    }
+
+    pub async fn references(
+        &self,
+        url: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let documents = self.symbol_in_documents(url, position).await?;
+
+        let locations = documents
+            .iter()
+            .flat_map(|(document, symbol)| {
+                document.reference_locations(*symbol, include_declaration)
+            })
+            .collect();
+
+        Some(locations)
+    }
+
+    pub async fn prepare_rename(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Result<Option<PrepareRenameResponse>, String> {
+        let Some(documents) = self.symbol_in_documents(url, position).await else {
+            return Ok(None);
+        };
+        let (document, symbol) = &documents[0];
+
+        check_renamable(document, *symbol)?;
+
+        match document.reference_range_at(*symbol, position) {
+            Some((_, reference)) if reference.rename == RenameEdit::Unsupported => Err(format!(
+                "`{}` is also the label of an optional record field, which would change with its name.",
+                document.symbol_name(*symbol).unwrap_or_default()
+            )),
+            Some((range, _)) => Ok(Some(PrepareRenameResponse::Range(range))),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn rename(
+        &self,
+        url: &Url,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, String> {
+        if !is_valid_name(new_name) {
+            return Err(format!(
+                "`{new_name}` is not a valid name. Names of values and functions start with a lowercase letter, like `myValue` or `readFile!`."
+            ));
+        }
+
+        let Some(documents) = self.symbol_in_documents(url, position).await else {
+            return Ok(None);
+        };
+
+        let (document, symbol) = &documents[0];
+        check_renamable(document, *symbol)?;
+
+        let mut changes = HashMap::new();
+
+        for (document, symbol) in &documents {
+            if document.defines(*symbol)
+                && document.is_top_level(*symbol)
+                && document.defines_top_level(new_name)
+            {
+                return Err(format!(
+                    "`{new_name}` is already defined at the top level of {}.",
+                    document.url()
+                ));
+            }
+
+            let edits = document.rename_edits(*symbol, new_name)?;
+
+            if !edits.is_empty() {
+                changes.insert(document.url().clone(), edits);
+            }
+        }
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    pub async fn prepare_call_hierarchy(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<CallHierarchyItem>> {
+        let (document, symbol) = self.symbol_at(url, position).await?;
+
+        if !document.is_top_level(symbol) {
+            return None;
+        }
+
+        let (document, symbol) = self.defining_document(&document, symbol).await?;
+
+        Some(vec![document.call_hierarchy_item(symbol)?])
+    }
+
+    /// The calls of the def of `item` in every analyzed document.
+    pub async fn incoming_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Option<Vec<CallHierarchyIncomingCall>> {
+        let documents = self
+            .symbol_in_documents(&item.uri, item.selection_range.start)
+            .await?;
+
+        let mut calls: Vec<_> = documents
+            .iter()
+            .flat_map(|(document, symbol)| document.incoming_calls(*symbol))
+            .collect();
+
+        calls.sort_by(|a, b| {
+            (a.from.uri.as_str(), a.from.range.start.line)
+                .cmp(&(b.from.uri.as_str(), b.from.range.start.line))
+        });
+
+        Some(calls)
+    }
+
+    /// The calls in the def of `item` of the top-level defs of the user's modules.
+    pub async fn outgoing_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Option<Vec<CallHierarchyOutgoingCall>> {
+        let (document, symbol) = self
+            .symbol_at(&item.uri, item.selection_range.start)
+            .await?;
+
+        let mut calls = Vec::new();
+
+        for (callee, from_ranges) in document.outgoing_calls(symbol) {
+            let Some((callee_document, callee)) = self.defining_document(&document, callee).await
+            else {
+                continue;
+            };
+
+            if let Some(to) = callee_document.call_hierarchy_item(callee) {
+                calls.push(CallHierarchyOutgoingCall { to, from_ranges });
+            }
+        }
+
+        Some(calls)
+    }
+
+    pub async fn prepare_type_hierarchy(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<TypeHierarchyItem>> {
+        let document = self.latest_document_by_url(url).await?;
+        let symbol = document.type_at(position)?;
+
+        if let Some(item) = self.defined_type_hierarchy_item(&document, symbol).await {
+            return Some(vec![item]);
+        }
+
+        // builtin abilities are shown where they are named as implemented
+        let item = document
+            .implementations_of(symbol)
+            .into_iter()
+            .filter_map(|(opaque, _)| document.implements_item(opaque, symbol))
+            .find(|item| {
+                item.selection_range.start <= position && position <= item.selection_range.end
+            })?;
+
+        Some(vec![item])
+    }
+
+    /// The abilities that the opaque type of `item` implements.
+    pub async fn supertypes(&self, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+        let document = self.latest_document_by_url(&item.uri).await?;
+        let opaque = document.type_at(item.selection_range.start)?;
+
+        let mut supertypes = Vec::new();
+
+        for (ability, implementation) in document.implemented_abilities(opaque) {
+            let item = match self.defined_type_hierarchy_item(&document, ability).await {
+                Some(item) => Some(item),
+                None => document.implements_item(opaque, ability),
+            };
+
+            if let Some(item) = item {
+                supertypes.push(TypeHierarchyItem {
+                    detail: Some(implementation.detail()),
+                    ..item
+                });
+            }
+        }
+
+        Some(supertypes)
+    }
+
+    /// The opaque types of every analyzed document that implement the ability of `item`.
+    pub async fn subtypes(&self, item: &TypeHierarchyItem) -> Option<Vec<TypeHierarchyItem>> {
+        let document = self.latest_document_by_url(&item.uri).await?;
+        let ability = document.type_at(item.selection_range.start)?;
+
+        let global_symbol = document.global_symbol(ability);
+        let mut documents = vec![(document.clone(), ability)];
+
+        for other in self.analyzed_documents().await {
+            if other.url() == document.url() || other.is_builtin() {
+                continue;
+            }
+
+            // builtin abilities are the same symbol in every analysis
+            let ability = if ability.is_builtin() {
+                Some(ability)
+            } else {
+                global_symbol
+                    .as_ref()
+                    .and_then(|global_symbol| other.resolve_global_symbol(global_symbol))
+            };
+
+            if let Some(ability) = ability {
+                documents.push((other, ability));
+            }
+        }
+
+        let mut subtypes: Vec<_> = documents
+            .iter()
+            .flat_map(|(document, ability)| {
+                document
+                    .implementations_of(*ability)
+                    .into_iter()
+                    .filter_map(|(opaque, implementation)| {
+                        Some(TypeHierarchyItem {
+                            detail: Some(implementation.detail()),
+                            ..document.type_hierarchy_item(opaque)?
+                        })
+                    })
+            })
+            .collect();
+
+        subtypes.sort_by(|a, b| (&a.name, a.uri.as_str()).cmp(&(&b.name, b.uri.as_str())));
+
+        Some(subtypes)
+    }
+
+    /// The item for the ability or opaque type `symbol` of `document` at its definition, if
+    /// that is in one of the user's modules.
+    async fn defined_type_hierarchy_item(
+        &self,
+        document: &Arc<AnalyzedDocument>,
+        symbol: Symbol,
+    ) -> Option<TypeHierarchyItem> {
+        let (document, symbol) = self.defining_document(document, symbol).await?;
+
+        document.type_hierarchy_item(symbol)
+    }
+
     pub async fn diagnostics(&self, url: &Url) -> Vec<Diagnostic> {
         let Some(document) = self.latest_document_by_url(url).await else {
             return vec![];
@@ -171,6 +598,226 @@ impl Registry {
         document.diagnostics()
     }
 
+    fn result_id(&self) -> String {
+        self.generation.load(Ordering::SeqCst).to_string()
+    }
+
+    /// The diagnostics of `url` for a pull, unless they haven't changed since the pull that
+    /// was answered with `previous_result_id`.
+    pub async fn document_diagnostic(
+        &self,
+        url: &Url,
+        previous_result_id: Option<String>,
+    ) -> DocumentDiagnosticReport {
+        // the id is taken before waiting for the latest analysis, so that it never claims to be
+        // newer than the diagnostics it is sent with
+        let result_id = self.result_id();
+        let document = self.latest_document_by_url(url).await;
+
+        if previous_result_id.as_ref() == Some(&result_id) && self.result_id() == result_id {
+            return DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id,
+                },
+            });
+        }
+
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items: document
+                    .map(|document| document.diagnostics())
+                    .unwrap_or_default(),
+            },
+        })
+    }
+
+    /// The diagnostics of every analyzed document for a pull. Clients pull again as soon as
+    /// they have an answer, so if nothing has changed since the `previous_result_ids` this
+    /// waits until something does.
+    pub async fn workspace_diagnostic(
+        &self,
+        previous_result_ids: Vec<PreviousResultId>,
+    ) -> WorkspaceDiagnosticReport {
+        loop {
+            let changed = self.changed.notified();
+            let result_id = self.result_id();
+
+            let is_unchanged = !previous_result_ids.is_empty()
+                && previous_result_ids
+                    .iter()
+                    .all(|previous| previous.value == result_id);
+
+            if !is_unchanged {
+                return self.workspace_diagnostic_report(result_id).await;
+            }
+
+            changed.await;
+        }
+    }
+
+    async fn workspace_diagnostic_report(&self, result_id: String) -> WorkspaceDiagnosticReport {
+        let open_urls = self.open_urls.lock().await;
+        let documents = self.documents.lock().await;
+
+        // documents that are still being analyzed are reported once they are applied
+        let mut items: Vec<_> = documents
+            .values()
+            .filter_map(|pair| {
+                let document = pair.latest_document.get()?;
+
+                if document.is_builtin() {
+                    return None;
+                }
+
+                let version = open_urls
+                    .contains(document.url())
+                    .then_some(pair.info.version as i64);
+
+                Some(WorkspaceDocumentDiagnosticReport::Full(
+                    WorkspaceFullDocumentDiagnosticReport {
+                        uri: document.url().clone(),
+                        version,
+                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                            result_id: Some(result_id.clone()),
+                            items: document.diagnostics(),
+                        },
+                    },
+                ))
+            })
+            .collect();
+
+        items.sort_by(|a, b| report_url(a).as_str().cmp(report_url(b).as_str()));
+
+        WorkspaceDiagnosticReport { items }
+    }
+
+    pub async fn code_actions(
+        &self,
+        url: &Url,
+        range: Range,
+        only: Option<&[CodeActionKind]>,
+    ) -> Option<CodeActionResponse> {
+        let document = self.latest_document_by_url(url).await?;
+        let actions = document.code_actions(range, only);
+
+        (!actions.is_empty()).then_some(actions)
+    }
+
+    pub async fn inlay_hints(&self, url: &Url, range: Range) -> Option<Vec<InlayHint>> {
+        self.latest_document_by_url(url).await?.inlay_hints(range)
+    }
+
+    pub async fn document_symbols(&self, url: &Url) -> Option<DocumentSymbolResponse> {
+        let document = self.latest_document_by_url(url).await?;
+
+        Some(DocumentSymbolResponse::Nested(document.document_symbols()))
+    }
+
+    /// The symbols of every analyzed module whose names fuzzy match `query`.
+    pub async fn workspace_symbols(&self, query: &str) -> Option<Vec<SymbolInformation>> {
+        let mut symbols: Vec<_> = self
+            .analyzed_documents()
+            .await
+            .iter()
+            .filter(|document| !document.is_builtin())
+            .flat_map(|document| document.workspace_symbols(query))
+            .collect();
+
+        symbols.sort_by(|a, b| {
+            (&a.name, a.location.uri.as_str()).cmp(&(&b.name, b.location.uri.as_str()))
+        });
+
+        Some(symbols)
+    }
+
+    pub async fn folding_ranges(&self, url: &Url) -> Option<Vec<FoldingRange>> {
+        self.latest_document_by_url(url).await?.folding_ranges()
+    }
+
+    pub async fn code_lenses(&self, url: &Url) -> Option<Vec<CodeLens>> {
+        let document = self.latest_document_by_url(url).await?;
+        let lens_results = self.lens_results.lock().await;
+
+        Some(document.code_lenses(lens_results.get(url)))
+    }
+
+    /// Runs the top-level expect at `position` of the latest version of `url`, and keeps its
+    /// outcome to show in its lenses.
+    pub async fn run_expect(&self, url: &Url, position: Position) -> Result<ExpectOutcome, String> {
+        let document = self
+            .latest_document_by_url(url)
+            .await
+            .ok_or_else(|| format!("{url} has not been analyzed yet."))?;
+        let DocInfo {
+            source,
+            version,
+            line_info,
+            ..
+        } = document.doc_info.clone();
+        let path = file_path(url)?;
+
+        // `roc test` reads the module from disk
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(source.as_str()) {
+            return Err(format!("Save {} to run its expects.", path.display()));
+        }
+
+        let mut outcomes = run::run_expects(&path, &line_info, position.line + 1).await?;
+
+        let outcome = outcomes
+            .pop()
+            .ok_or_else(|| "There is no top-level expect here.".to_string())?;
+
+        self.lens_results_of(url, version)
+            .await
+            .add_expects(vec![outcome.clone()]);
+
+        Ok(outcome)
+    }
+
+    /// Evaluates the top-level def `name` of the latest version of `url`, and keeps its value
+    /// to show in its lens.
+    pub async fn evaluate(&self, url: &Url, name: String) -> Result<String, String> {
+        let document = self
+            .latest_document_by_url(url)
+            .await
+            .ok_or_else(|| format!("{url} has not been analyzed yet."))?;
+        let DocInfo {
+            source, version, ..
+        } = document.doc_info.clone();
+        let path = file_path(url)?;
+
+        let value = run::evaluate(&path, &source, &name).await;
+
+        self.lens_results_of(url, version)
+            .await
+            .values
+            .insert(name, value.clone());
+
+        value
+    }
+
+    /// The lens results of `url`, which are cleared when they are of an older version.
+    async fn lens_results_of(
+        &self,
+        url: &Url,
+        version: i32,
+    ) -> tokio::sync::MappedMutexGuard<'_, LensResults> {
+        MutexGuard::map(self.lens_results.lock().await, |lens_results| {
+            let results = lens_results
+                .entry(url.clone())
+                .or_insert_with(|| LensResults::new(version));
+
+            if results.version != version {
+                *results = LensResults::new(version);
+            }
+
+            results
+        })
+    }
+
     pub async fn hover(&self, url: &Url, position: Position) -> Option<Hover> {
         self.latest_document_by_url(url).await?.hover(position)
     }
@@ -192,10 +839,32 @@ impl Registry {
         document.format()
     }
 
+    pub async fn range_formatting(&self, url: &Url, range: Range) -> Option<Vec<TextEdit>> {
+        let document = self.document_info_by_url(url).await?;
+        document.format_range(range)
+    }
+
+    pub async fn on_type_formatting(
+        &self,
+        url: &Url,
+        position: Position,
+        ch: &str,
+    ) -> Option<Vec<TextEdit>> {
+        let document = self.document_info_by_url(url).await?;
+        document.format_on_type(position, ch)
+    }
+
     pub async fn semantic_tokens(&self, url: &Url) -> Option<SemanticTokensResult> {
         let document = self.document_info_by_url(url).await?;
         document.semantic_tokens()
     }
+    pub async fn signature_help(&self, url: &Url, position: Position) -> Option<SignatureHelp> {
+        let lock = self.documents.lock().await;
+        let pair = lock.get(url)?;
+
+        pair.last_good_document.signature_help(position, &pair.info)
+    }
+
     pub async fn completion_items(
         &self,
         url: &Url,
@@ -205,16 +874,122 @@ impl Registry {
         let lock = self.documents.lock().await;
         let pair = lock.get(url)?;
 
-        let latest_doc_info = &pair.info;
+        let latest_doc_info = pair.info.clone();
+        let document = pair.last_good_document.clone();
+        drop(lock);
+
         info!(
             "Using document version:{:?} for completion ",
             latest_doc_info.version
         );
 
-        let completions = pair
-            .last_good_document
-            .completion_items(position, latest_doc_info)?;
+        let mut completions = document.completion_items(position, &latest_doc_info)?;
+
+        let prefix = latest_doc_info.get_prefix_at_position(position);
+        let import_completions = self
+            .import_completions(&document, &latest_doc_info, &prefix, &completions)
+            .await;
+        completions.extend(import_completions);
 
         Some(CompletionResponse::Array(completions))
     }
+
+    /// Completions for the names that the modules `document` doesn't import yet expose, which
+    /// add the `import` for them when they are accepted. Names that are already in scope, in
+    /// `in_scope`, are left out.
+    async fn import_completions(
+        &self,
+        document: &AnalyzedDocument,
+        latest_doc_info: &DocInfo,
+        prefix: &str,
+        in_scope: &[CompletionItem],
+    ) -> Vec<CompletionItem> {
+        if prefix.is_empty() || prefix.contains('.') {
+            return Vec::new();
+        }
+
+        let documents = self.analyzed_documents().await;
+        let imported = document.imported_urls();
+        let package_dirs: Vec<_> = documents
+            .iter()
+            .flat_map(|other| other.doc_info.package_dirs())
+            .collect();
+
+        let importable = documents.iter().filter(|other| {
+            other.url() != document.url() && !other.is_builtin() && !imported.contains(other.url())
+        });
+
+        let mut completions = Vec::new();
+
+        for other in importable {
+            let Some(module_name) = import_name(other, document.url(), &package_dirs) else {
+                continue;
+            };
+
+            for item in other.exposed_completion_items(prefix) {
+                if in_scope.iter().any(|in_scope| in_scope.label == item.label) {
+                    continue;
+                }
+
+                let Some(edit) = latest_doc_info.add_import(&module_name, &item.label) else {
+                    continue;
+                };
+
+                completions.push(CompletionItem {
+                    label_details: Some(CompletionItemLabelDetails {
+                        detail: None,
+                        description: Some(module_name.clone()),
+                    }),
+                    additional_text_edits: Some(vec![edit]),
+                    ..item
+                });
+            }
+        }
+
+        completions
+    }
+}
+
+/// The name that the module of `document` is imported by from the module at `importing_url`.
+/// The modules of a package are qualified by its shorthand, unless they are imported from
+/// within the package.
+fn import_name(
+    document: &AnalyzedDocument,
+    importing_url: &Url,
+    package_dirs: &[(String, PathBuf)],
+) -> Option<String> {
+    let module_name = document.module_name()?;
+    let path = document.url().to_file_path().ok()?;
+    let importing_path = importing_url.to_file_path().ok()?;
+
+    match package_dirs.iter().find(|(_, dir)| path.starts_with(dir)) {
+        Some((_, dir)) if importing_path.starts_with(dir) => Some(module_name),
+        Some((shorthand, _)) => Some(format!("{shorthand}.{module_name}")),
+        // the packages of packages can't be imported
+        None if path.starts_with(cache::roc_cache_packages_dir()) => None,
+        None => Some(module_name),
+    }
+}
+
+fn file_path(url: &Url) -> Result<PathBuf, String> {
+    url.to_file_path()
+        .map_err(|()| format!("{url} is not a file, so its code can't run."))
+}
+
+fn check_renamable(document: &AnalyzedDocument, symbol: Symbol) -> Result<(), String> {
+    if symbol.is_builtin() {
+        return Err(format!(
+            "`{}` is a builtin, so it can't be renamed.",
+            document.symbol_name(symbol).unwrap_or_default()
+        ));
+    }
+
+    Ok(())
+}
+
+fn report_url(report: &WorkspaceDocumentDiagnosticReport) -> &Url {
+    match report {
+        WorkspaceDocumentDiagnosticReport::Full(full) => &full.uri,
+        WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) => &unchanged.uri,
+    }
 }
@@ -0,0 +1,162 @@
+//! Runs the code of a document for its code lenses: its top-level expects, the way `roc test`
+//! does, and its top-level values, the way the repl does. The code runs in a `roc` child
+//! process, which is killed if it doesn't finish in time, so that an infinite loop or a crash
+//! in the user's code can't take the language server down with it.
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+use roc_region::all::{LineColumn, LineColumnRegion, LineInfo, Region};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// How long an expect may run before `roc test` stops it and reports it as a failure
+const EXPECT_TIMEOUT_SECS: u64 = 10;
+
+/// How long the `roc` process may take, compiling included, before it is killed
+const RUN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The outcome of running a top-level `expect`.
+#[derive(Debug, Clone)]
+pub(crate) struct ExpectOutcome {
+    /// The region of the expect, along with the comment before it
+    pub region: Region,
+    /// The rendered report of why the expect failed, or `None` if it passed
+    pub failure_report: Option<String>,
+}
+
+impl ExpectOutcome {
+    pub fn passed(&self) -> bool {
+        self.failure_report.is_none()
+    }
+}
+
+/// Runs the top-level expects of the module at `path` that are on the (1-based) `line`, with
+/// `roc test`. The module's `line_info` locates the expects that ran.
+pub(crate) async fn run_expects(
+    path: &Path,
+    line_info: &LineInfo,
+    line: u32,
+) -> Result<Vec<ExpectOutcome>, String> {
+    let mut command = roc_command();
+    command
+        .arg("test")
+        .arg("--reporter=json")
+        .arg(format!("--filter={}:{line}", path.display()))
+        .arg(format!("--timeout={EXPECT_TIMEOUT_SECS}"))
+        .arg(path);
+
+    let output = run(command, None).await?;
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|_| "The module has errors, so its expects can't run.".to_string())?;
+
+    // the expects of the modules it imports are skipped by the filter
+    let tests = report["modules"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|module| module["tests"].as_array())
+        .flatten();
+
+    let mut outcomes = Vec::new();
+
+    for test in tests {
+        if test["status"] == "skipped" {
+            continue;
+        }
+
+        let region = &test["region"];
+        let line_column = |line: &str, column: &str| -> Option<LineColumn> {
+            Some(LineColumn {
+                line: region[line].as_u64()?.checked_sub(1)? as u32,
+                column: region[column].as_u64()?.checked_sub(1)? as u32,
+            })
+        };
+        let (Some(start), Some(end)) = (
+            line_column("start_line", "start_column"),
+            line_column("end_line", "end_column"),
+        ) else {
+            continue;
+        };
+
+        outcomes.push(ExpectOutcome {
+            region: line_info.convert_line_column_region(LineColumnRegion { start, end }),
+            failure_report: test["failure"].as_str().map(str::to_string),
+        });
+    }
+
+    Ok(outcomes)
+}
+
+/// The value of the top-level def `name` of the module at `path`, whose contents are
+/// `source`, rendered like the repl renders it, as in `42 : Num *`.
+pub(crate) async fn evaluate(path: &Path, source: &str, name: &str) -> Result<String, String> {
+    let mut command = roc_command();
+    command
+        .arg("repl")
+        .arg("--no-color")
+        .arg(format!("--eval-def={name}"));
+
+    // the module's imports are looked up next to it
+    if let Some(dir) = path.parent() {
+        command.current_dir(dir);
+    }
+
+    let output = run(command, Some(source)).await?;
+    let printed = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    // the problems that keep it from being evaluated are printed too
+    match (output.status.success(), printed.is_empty()) {
+        (true, _) => Ok(printed),
+        (false, true) => Err(format!("`{name}` could not be evaluated.")),
+        (false, false) => Err(printed),
+    }
+}
+
+/// Runs `command`, with `stdin` as its input, and returns what it printed and how it exited.
+/// It is killed if it runs for longer than [RUN_TIMEOUT].
+async fn run(mut command: Command, stdin: Option<&str>) -> Result<Output, String> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|error| format!("Couldn't start roc: {error}"))?;
+
+    let mut child_stdin = child.stdin.take();
+    let running = async move {
+        if let (Some(child_stdin), Some(input)) = (child_stdin.as_mut(), stdin) {
+            child_stdin.write_all(input.as_bytes()).await?;
+        }
+
+        // closing stdin tells roc that the input is complete
+        drop(child_stdin);
+
+        child.wait_with_output().await
+    };
+
+    match tokio::time::timeout(RUN_TIMEOUT, running).await {
+        Err(_) => Err(format!(
+            "roc was stopped after running for {} seconds.",
+            RUN_TIMEOUT.as_secs()
+        )),
+        Ok(Err(error)) => Err(format!("Running roc failed: {error}")),
+        Ok(Ok(output)) => Ok(output),
+    }
+}
+
+/// A command that runs `roc`. The language server is installed next to the `roc` it belongs
+/// to, so that one is preferred over the one on the `PATH`.
+fn roc_command() -> Command {
+    let exe_name = format!("roc{}", std::env::consts::EXE_SUFFIX);
+    let sibling = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(&exe_name)))
+        .filter(|roc| roc.is_file());
+
+    Command::new(sibling.unwrap_or_else(|| PathBuf::from(exe_name)))
+}
@@ -2,23 +2,36 @@ use analysis::HIGHLIGHT_TOKENS_LEGEND;
 
 use log::{debug, trace};
 use registry::{Registry, RegistryConfig};
+use std::collections::HashSet;
 use std::future::Future;
 use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+use futures::future::BoxFuture;
+use tower::Service;
+
 use tower_lsp::jsonrpc::{self, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use crate::analysis::{global_analysis, DocInfo};
+use crate::analysis::{
+    command_location, command_value, global_analysis, DocInfo, DEBUG_EXPECT_COMMAND,
+    EVALUATE_COMMAND, RUN_EXPECT_COMMAND,
+};
 
 mod analysis;
 mod convert;
 mod registry;
+mod run;
 
 struct RocServer {
     pub state: RocServerState,
     client: Client,
+    /// Whether the client pulls diagnostics, rather than having them published to it
+    pulls_diagnostics: AtomicBool,
 }
 
 struct RocServerConfig {
@@ -60,6 +73,7 @@ impl RocServer {
         Self {
             state: RocServerState::new(config, Registry::new(registry_config)),
             client,
+            pulls_diagnostics: AtomicBool::new(false),
         }
     }
 
@@ -83,6 +97,15 @@ impl RocServer {
                 work_done_progress: None,
             },
         };
+        let document_range_formatting_provider = DocumentRangeFormattingOptions {
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let document_on_type_formatting_provider = DocumentOnTypeFormattingOptions {
+            first_trigger_character: "\n".to_string(),
+            more_trigger_character: None,
+        };
         let semantic_tokens_provider =
             SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
                 work_done_progress_options: WorkDoneProgressOptions {
@@ -102,19 +125,85 @@ impl RocServer {
             work_done_progress_options: WorkDoneProgressOptions {
                 work_done_progress: None,
             },
+            completion_item: None,
+        };
+        let references_provider = ReferencesOptions {
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let rename_provider = RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let signature_help_provider = SignatureHelpOptions {
+            trigger_characters: Some(vec![" ".to_string()]),
+            retrigger_characters: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        };
+        let code_action_provider = CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![
+                CodeActionKind::QUICKFIX,
+                CodeActionKind::REFACTOR_REWRITE,
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+            ]),
+            resolve_provider: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        });
+        let diagnostic_provider = DiagnosticServerCapabilities::Options(DiagnosticOptions {
+            identifier: None,
+            inter_file_dependencies: true,
+            workspace_diagnostics: true,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        });
+        let code_lens_provider = CodeLensOptions {
+            resolve_provider: Some(false),
+        };
+        let execute_command_provider = ExecuteCommandOptions {
+            commands: [RUN_EXPECT_COMMAND, DEBUG_EXPECT_COMMAND, EVALUATE_COMMAND]
+                .map(String::from)
+                .to_vec(),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
         };
         ServerCapabilities {
             text_document_sync: Some(text_document_sync),
             hover_provider: Some(hover_provider),
             definition_provider: Some(OneOf::Right(definition_provider)),
             document_formatting_provider: Some(OneOf::Right(document_formatting_provider)),
+            document_range_formatting_provider: Some(OneOf::Right(
+                document_range_formatting_provider,
+            )),
+            document_on_type_formatting_provider: Some(document_on_type_formatting_provider),
             semantic_tokens_provider: Some(semantic_tokens_provider),
             completion_provider: Some(completion_provider),
+            references_provider: Some(OneOf::Right(references_provider)),
+            rename_provider: Some(OneOf::Right(rename_provider)),
+            code_action_provider: Some(code_action_provider),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            signature_help_provider: Some(signature_help_provider),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            diagnostic_provider: Some(diagnostic_provider),
+            call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+            code_lens_provider: Some(code_lens_provider),
+            execute_command_provider: Some(execute_command_provider),
+            // type hierarchies are advertised by `AdvertiseTypeHierarchy`
             ..ServerCapabilities::default()
         }
     }
 
-    /// Records a document content change.
+    /// Records a document content change, then checks the documents that import it again.
     async fn change(&self, fi: Url, text: String, version: i32) {
         let updating_result = self.state.change(&fi, text, version).await;
 
@@ -126,12 +215,86 @@ impl RocServer {
 
         debug!("Applied_changes getting and returning diagnostics");
 
-        let diagnostics = self.state.registry.diagnostics(&fi).await;
+        self.publish_diagnostics(fi.clone()).await;
+
+        // the modules that weren't analyzed before have just been checked against this change
+        let discovered = self.state.discover_modules().await;
+        for url in discovered.iter().cloned() {
+            self.publish_diagnostics(url).await;
+        }
+
+        let mut dependents = self.state.registry.dependents(&fi).await;
+        dependents.retain(|url| !discovered.contains(url));
+
+        for url in dependents {
+            // a newer change will check the dependents again
+            if self.state.registry.get_latest_version(&fi).await != Some(version) {
+                return;
+            }
+
+            match self.state.recheck(&url).await {
+                Ok(()) => self.publish_diagnostics(url).await,
+                Err(e) => debug!("Cancelled check of dependent {}. Reason:{:?}", url, e),
+            }
+        }
+    }
+
+    /// Sends the diagnostics of a document to the client, unless it pulls them itself.
+    async fn publish_diagnostics(&self, url: Url) {
+        if self.pulls_diagnostics.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let version = self
+            .state
+            .registry
+            .open_document(&url)
+            .await
+            .map(|doc_info| doc_info.version);
+        let diagnostics = self.state.registry.diagnostics(&url).await;
 
         self.client
-            .publish_diagnostics(fi, diagnostics, Some(version))
+            .publish_diagnostics(url, diagnostics, version)
             .await;
     }
+
+    /// Runs the code of a code lens, then has the editor ask for the lenses again to show
+    /// what it did.
+    async fn run_lens_command(
+        &self,
+        command: &str,
+        arguments: &[serde_json::Value],
+    ) -> std::result::Result<(), String> {
+        let registry = &self.state.registry;
+
+        if command == EVALUATE_COMMAND {
+            let (url, name) = command_value(arguments).ok_or("Expected a url and a name.")?;
+            let value = registry.evaluate(&url, name).await;
+            self.client.code_lens_refresh().await.ok();
+
+            return value.map(|_| ());
+        }
+
+        let (url, position) =
+            command_location(arguments).ok_or("Expected a url and a position.")?;
+        let outcome = registry.run_expect(&url, position).await?;
+        self.client.code_lens_refresh().await.ok();
+
+        match (command, outcome.failure_report) {
+            (DEBUG_EXPECT_COMMAND, Some(report)) => {
+                self.client.show_message(MessageType::ERROR, report).await
+            }
+            (DEBUG_EXPECT_COMMAND, None) => {
+                self.client
+                    .show_message(MessageType::INFO, "The expect passed.")
+                    .await
+            }
+            (_, Some(report)) => self.client.log_message(MessageType::ERROR, report).await,
+            (_, None) => {}
+        }
+
+        Ok(())
+    }
 }
 
 impl RocServerState {
@@ -139,7 +302,9 @@ impl RocServerState {
         Self { config, registry }
     }
 
-    async fn close(&self, _fi: Url) {}
+    async fn close(&self, fi: Url) {
+        self.registry.close(&fi).await;
+    }
 
     pub async fn change(
         &self,
@@ -150,6 +315,7 @@ impl RocServerState {
         debug!("V{:?}:starting change", version);
         let doc_info = DocInfo::new(fi.clone(), text, version);
 
+        self.registry.open(fi.clone()).await;
         self.registry
             .apply_doc_info_changes(fi.clone(), doc_info.clone())
             .await;
@@ -173,54 +339,143 @@ impl RocServerState {
                 return Err("Not latest version skipping analysis".to_string());
             }
 
-            let results = match tokio::time::timeout(
-                Duration::from_secs(60),
-                tokio::task::spawn_blocking(|| catch_unwind(|| global_analysis(doc_info))),
-            )
-            .await
-            {
-                Err(e) => {
-                    return Err(format!(
-                        "Document analysis thread timeout out after: {:?}",
-                        e
-                    ))
-                }
-                Ok(Err(e)) => {
-                    return Err(format!("Document analysis thread failed. reason:{:?}", e))
+            inner_ref.analyze(doc_info).await
+        }
+        .await;
+        debug!("V{:?}:finished document change process", version);
+        updating_result
+    }
+
+    /// Checks a document again, because a module it imports has changed. Documents that aren't
+    /// open are read from disk.
+    pub async fn recheck(&self, fi: &Url) -> std::result::Result<(), String> {
+        let doc_info = match self.registry.open_document(fi).await {
+            Some(doc_info) => doc_info,
+            None => {
+                let path = fi
+                    .to_file_path()
+                    .map_err(|()| format!("{} is not a file", fi))?;
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+                let version = self.registry.get_latest_version(fi).await.unwrap_or(0);
+
+                DocInfo::new(fi.clone(), source, version)
+            }
+        };
+
+        self.analyze(doc_info).await
+    }
+
+    /// Analyzes the modules in the directories of the analyzed documents that haven't been
+    /// analyzed yet, so that their problems are known before they are opened. Returns the
+    /// documents that were analyzed.
+    pub async fn discover_modules(&self) -> Vec<Url> {
+        let known = self.registry.urls().await;
+        let dirs: HashSet<PathBuf> = known
+            .iter()
+            .filter_map(|url| Some(url.to_file_path().ok()?.parent()?.to_path_buf()))
+            .collect();
+
+        let mut discovered = Vec::new();
+
+        for dir in dirs {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.extension().and_then(|ext| ext.to_str()) != Some("roc") {
+                    continue;
                 }
-                Ok(Ok(res)) => {
-                    res.map_err(|err| format!("Document analysis panicked with: {:?}", err))?
+
+                let Ok(url) = Url::from_file_path(&path) else {
+                    continue;
+                };
+
+                // analyzing one module analyzes the modules it imports too
+                if self.registry.get_latest_version(&url).await.is_some() {
+                    continue;
                 }
-            };
-            let latest_version = inner_ref.registry.get_latest_version(fi).await;
-
-            //if this version is not the latest another change must have come in and this analysis is useless
-            //if there is no older version we can just proceed with the update
-            if let Some(latest_version) = latest_version {
-                if latest_version != version {
-                    return Err(format!(
-                        "Version {0} doesn't match latest: {1} discarding analysis",
-                        version, latest_version
-                    ));
+
+                match self.recheck(&url).await {
+                    Ok(()) => discovered.push(url),
+                    Err(e) => debug!("Couldn't analyze {}. Reason:{:?}", url, e),
                 }
             }
-            debug!(
-                "V{:?}:finished document analysis applying changes ",
-                version
-            );
+        }
+
+        discovered
+    }
+
+    /// The diagnostics of every module in the workspace, including the ones that aren't open.
+    pub async fn workspace_diagnostic(
+        &self,
+        previous_result_ids: Vec<PreviousResultId>,
+    ) -> WorkspaceDiagnosticReport {
+        self.discover_modules().await;
+        self.registry
+            .workspace_diagnostic(previous_result_ids)
+            .await
+    }
 
-            inner_ref.registry.apply_changes(results, fi.clone()).await;
-            Ok(())
+    /// Analyzes a document, along with the modules it imports, and applies the results unless
+    /// the document has changed in the meantime.
+    async fn analyze(&self, doc_info: DocInfo) -> std::result::Result<(), String> {
+        let fi = doc_info.url.clone();
+        let version = doc_info.version;
+        let open_documents = self.registry.open_documents(&fi).await;
+
+        let results = match tokio::time::timeout(
+            Duration::from_secs(60),
+            tokio::task::spawn_blocking(|| {
+                catch_unwind(|| global_analysis(doc_info, open_documents))
+            }),
+        )
+        .await
+        {
+            Err(e) => {
+                return Err(format!(
+                    "Document analysis thread timeout out after: {:?}",
+                    e
+                ))
+            }
+            Ok(Err(e)) => return Err(format!("Document analysis thread failed. reason:{:?}", e)),
+            Ok(Ok(res)) => {
+                res.map_err(|err| format!("Document analysis panicked with: {:?}", err))?
+            }
+        };
+        let latest_version = self.registry.get_latest_version(&fi).await;
+
+        //if this version is not the latest another change must have come in and this analysis is useless
+        //if there is no older version we can just proceed with the update
+        if let Some(latest_version) = latest_version {
+            if latest_version != version {
+                return Err(format!(
+                    "Version {0} doesn't match latest: {1} discarding analysis",
+                    version, latest_version
+                ));
+            }
         }
-        .await;
-        debug!("V{:?}:finished document change process", version);
-        updating_result
+        debug!(
+            "V{:?}:finished document analysis applying changes ",
+            version
+        );
+
+        self.registry.apply_changes(results, fi).await;
+        Ok(())
     }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for RocServer {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let pulls_diagnostics = params
+            .capabilities
+            .text_document
+            .map_or(false, |text_document| text_document.diagnostic.is_some());
+        self.pulls_diagnostics
+            .store(pulls_diagnostics, Ordering::SeqCst);
+
         Ok(InitializeResult {
             capabilities: Self::capabilities(),
             ..InitializeResult::default()
@@ -304,344 +559,2027 @@ impl LanguageServer for RocServer {
         .await
     }
 
-    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
-        let DocumentFormattingParams {
-            text_document,
-            options: _,
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let ReferenceParams {
+            text_document_position:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            context: ReferenceContext {
+                include_declaration,
+            },
             work_done_progress_params: _,
+            partial_result_params: _,
         } = params;
 
-        unwind_async(self.state.registry.formatting(&text_document.uri)).await
+        unwind_async(self.state.registry.references(
+            &text_document.uri,
+            position,
+            include_declaration,
+        ))
+        .await
     }
 
-    async fn semantic_tokens_full(
+    async fn prepare_rename(
         &self,
-        params: SemanticTokensParams,
-    ) -> Result<Option<SemanticTokensResult>> {
-        let SemanticTokensParams {
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let TextDocumentPositionParams {
             text_document,
-            work_done_progress_params: _,
-            partial_result_params: _,
+            position,
         } = params;
 
-        unwind_async(self.state.registry.semantic_tokens(&text_document.uri)).await
+        unwind_async(
+            self.state
+                .registry
+                .prepare_rename(&text_document.uri, position),
+        )
+        .await?
+        .map_err(jsonrpc::Error::invalid_params)
     }
 
-    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let doc = params.text_document_position;
-        trace!("Got completion request.");
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let RenameParams {
+            text_document_position:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            new_name,
+            work_done_progress_params: _,
+        } = params;
 
         unwind_async(
             self.state
                 .registry
-                .completion_items(&doc.text_document.uri, doc.position),
+                .rename(&text_document.uri, position, &new_name),
         )
-        .await
+        .await?
+        .map_err(jsonrpc::Error::invalid_params)
     }
-}
-
-async fn unwind_async<Fut, T>(future: Fut) -> tower_lsp::jsonrpc::Result<T>
-where
-    Fut: Future<Output = T>,
-{
-    let result = { futures::FutureExt::catch_unwind(AssertUnwindSafe(future)).await };
 
-    match result {
-        Ok(a) => tower_lsp::jsonrpc::Result::Ok(a),
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let CodeActionParams {
+            text_document,
+            range,
+            context,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
 
-        Err(err) => tower_lsp::jsonrpc::Result::Err(jsonrpc::Error {
-            code: jsonrpc::ErrorCode::InternalError,
-            message: format!("{:?}", err),
-            data: None,
-        }),
+        unwind_async(self.state.registry.code_actions(
+            &text_document.uri,
+            range,
+            context.only.as_deref(),
+        ))
+        .await
     }
-}
-
-#[tokio::main]
-async fn main() {
-    env_logger::Builder::from_env("ROCLS_LOG").init();
 
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let InlayHintParams {
+            text_document,
+            range,
+            work_done_progress_params: _,
+        } = params;
 
-    let (service, socket) = LspService::new(RocServer::new);
-    use roc_error_macros::set_panic_not_exit;
-    set_panic_not_exit(true);
-    Server::new(stdin, stdout, socket).serve(service).await;
-}
+        unwind_async(self.state.registry.inlay_hints(&text_document.uri, range)).await
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::sync::Once;
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let DocumentSymbolParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
 
-    use expect_test::expect;
-    use indoc::indoc;
-    use log::info;
+        unwind_async(self.state.registry.document_symbols(&text_document.uri)).await
+    }
 
-    use super::*;
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let WorkspaceSymbolParams {
+            query,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
 
-    fn completion_resp_to_strings(
-        resp: CompletionResponse,
-    ) -> Vec<(String, Option<Documentation>)> {
-        match resp {
-            CompletionResponse::Array(list) => list.into_iter(),
-            CompletionResponse::List(list) => list.items.into_iter(),
-        }
-        .map(|item| (item.label, item.documentation))
-        .collect::<Vec<_>>()
+        unwind_async(self.state.registry.workspace_symbols(&query)).await
     }
 
-    /// gets completion and returns only the label and docs for each completion
-    async fn get_basic_completion_info(
-        reg: &Registry,
-        url: &Url,
-        position: Position,
-    ) -> Option<Vec<(String, Option<Documentation>)>> {
-        reg.completion_items(url, position)
-            .await
-            .map(completion_resp_to_strings)
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let FoldingRangeParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.folding_ranges(&text_document.uri)).await
     }
 
-    /// gets completion and returns only the label for each completion
-    fn comp_labels(
-        completions: Option<Vec<(String, Option<Documentation>)>>,
-    ) -> Option<Vec<String>> {
-        completions.map(|list| list.into_iter().map(|(labels, _)| labels).collect())
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let CodeLensParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.code_lenses(&text_document.uri)).await
     }
 
-    const DOC_LIT: &str = indoc! {r#"
-        interface Test
-          exposes []
-          imports []
-        "#};
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        let ExecuteCommandParams {
+            command,
+            arguments,
+            work_done_progress_params: _,
+        } = params;
 
-    static INIT: Once = Once::new();
+        if ![RUN_EXPECT_COMMAND, DEBUG_EXPECT_COMMAND, EVALUATE_COMMAND].contains(&command.as_str())
+        {
+            return Err(jsonrpc::Error::method_not_found());
+        }
 
-    async fn test_setup(doc: String) -> (RocServerState, Url) {
-        INIT.call_once(|| {
-            env_logger::builder()
-                .is_test(true)
-                .filter_level(log::LevelFilter::Debug)
-                .init();
-        });
-        info!("Doc is:\n{0}", doc);
-        let url = Url::parse("file:/Test.roc").unwrap();
+        let result = unwind_async(self.run_lens_command(&command, &arguments)).await?;
 
-        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
-        // setup the file
-        inner.change(&url, doc, 0).await.unwrap();
-        (inner, url)
+        if let Err(message) = &result {
+            self.client
+                .log_message(MessageType::ERROR, message.clone())
+                .await;
+        }
+
+        result
+            .map(|()| None)
+            .map_err(jsonrpc::Error::invalid_params)
     }
 
-    /// Runs a basic completion and returns the response
-    async fn completion_test(
-        initial: &str,
-        addition: &str,
-        position: Position,
-    ) -> Option<Vec<(String, Option<Documentation>)>> {
-        let doc = DOC_LIT.to_string() + initial;
-        let (inner, url) = test_setup(doc.clone()).await;
-        let registry = &inner.registry;
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let CallHierarchyPrepareParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .prepare_call_hierarchy(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.incoming_calls(&item)).await
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.outgoing_calls(&item)).await
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let TypeHierarchyPrepareParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .prepare_type_hierarchy(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let TypeHierarchySupertypesParams {
+            item,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.supertypes(&item)).await
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let TypeHierarchySubtypesParams {
+            item,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.subtypes(&item)).await
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let DocumentDiagnosticParams {
+            text_document,
+            identifier: _,
+            previous_result_id,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .document_diagnostic(&text_document.uri, previous_result_id),
+        )
+        .await
+        .map(DocumentDiagnosticReportResult::Report)
+    }
+
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        let WorkspaceDiagnosticParams {
+            identifier: _,
+            previous_result_ids,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.workspace_diagnostic(previous_result_ids))
+            .await
+            .map(WorkspaceDiagnosticReportResult::Report)
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let SignatureHelpParams {
+            text_document_position_params:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            context: _,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .signature_help(&text_document.uri, position),
+        )
+        .await
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let DocumentFormattingParams {
+            text_document,
+            options: _,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.formatting(&text_document.uri)).await
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let DocumentRangeFormattingParams {
+            text_document,
+            range,
+            options: _,
+            work_done_progress_params: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .range_formatting(&text_document.uri, range),
+        )
+        .await
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let DocumentOnTypeFormattingParams {
+            text_document_position:
+                TextDocumentPositionParams {
+                    text_document,
+                    position,
+                },
+            ch,
+            options: _,
+        } = params;
+
+        unwind_async(
+            self.state
+                .registry
+                .on_type_formatting(&text_document.uri, position, &ch),
+        )
+        .await
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let SemanticTokensParams {
+            text_document,
+            work_done_progress_params: _,
+            partial_result_params: _,
+        } = params;
+
+        unwind_async(self.state.registry.semantic_tokens(&text_document.uri)).await
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let doc = params.text_document_position;
+        trace!("Got completion request.");
+
+        unwind_async(
+            self.state
+                .registry
+                .completion_items(&doc.text_document.uri, doc.position),
+        )
+        .await
+    }
+}
+
+async fn unwind_async<Fut, T>(future: Fut) -> tower_lsp::jsonrpc::Result<T>
+where
+    Fut: Future<Output = T>,
+{
+    let result = { futures::FutureExt::catch_unwind(AssertUnwindSafe(future)).await };
+
+    match result {
+        Ok(a) => tower_lsp::jsonrpc::Result::Ok(a),
+
+        Err(err) => tower_lsp::jsonrpc::Result::Err(jsonrpc::Error {
+            code: jsonrpc::ErrorCode::InternalError,
+            message: format!("{:?}", err).into(),
+            data: None,
+        }),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env("ROCLS_LOG").init();
+
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+
+    let (service, socket) = LspService::new(RocServer::new);
+    use roc_error_macros::set_panic_not_exit;
+    set_panic_not_exit(true);
+    Server::new(stdin, stdout, socket)
+        .serve(AdvertiseTypeHierarchy(service))
+        .await;
+}
+
+/// Adds `typeHierarchyProvider` to the server capabilities in the response to `initialize`.
+/// lsp-types has no field for it in [`ServerCapabilities`], so it can't be set there.
+struct AdvertiseTypeHierarchy<S>(S);
+
+impl<S> Service<jsonrpc::Request> for AdvertiseTypeHierarchy<S>
+where
+    S: Service<jsonrpc::Request, Response = Option<jsonrpc::Response>>,
+    S::Future: Send + 'static,
+{
+    type Response = Option<jsonrpc::Response>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, std::result::Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: jsonrpc::Request) -> Self::Future {
+        let initializing = request.method() == "initialize";
+        let response = self.0.call(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+
+            Ok(match response {
+                Some(response) if initializing => Some(advertise_type_hierarchy(response)),
+                response => response,
+            })
+        })
+    }
+}
+
+fn advertise_type_hierarchy(response: jsonrpc::Response) -> jsonrpc::Response {
+    let (id, result) = response.into_parts();
+    let result = result.map(|mut result| {
+        if let Some(capabilities) = result
+            .get_mut("capabilities")
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            capabilities.insert(
+                "typeHierarchyProvider".to_string(),
+                serde_json::Value::Bool(true),
+            );
+        }
+
+        result
+    });
+
+    jsonrpc::Response::from_parts(id, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use expect_test::expect;
+    use indoc::indoc;
+    use log::info;
+
+    use super::*;
+
+    fn completion_resp_to_strings(
+        resp: CompletionResponse,
+    ) -> Vec<(String, Option<Documentation>)> {
+        match resp {
+            CompletionResponse::Array(list) => list.into_iter(),
+            CompletionResponse::List(list) => list.items.into_iter(),
+        }
+        .map(|item| (item.label, item.documentation))
+        .collect::<Vec<_>>()
+    }
+
+    /// gets completion and returns only the label and docs for each completion
+    async fn get_basic_completion_info(
+        reg: &Registry,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<(String, Option<Documentation>)>> {
+        reg.completion_items(url, position)
+            .await
+            .map(completion_resp_to_strings)
+    }
+
+    /// gets completion and returns only the label for each completion
+    fn comp_labels(
+        completions: Option<Vec<(String, Option<Documentation>)>>,
+    ) -> Option<Vec<String>> {
+        completions.map(|list| list.into_iter().map(|(labels, _)| labels).collect())
+    }
+
+    const DOC_LIT: &str = indoc! {r#"
+        interface Test
+          exposes []
+          imports []
+        "#};
+
+    static INIT: Once = Once::new();
+
+    fn init_logger() {
+        INIT.call_once(|| {
+            env_logger::builder()
+                .is_test(true)
+                .filter_level(log::LevelFilter::Debug)
+                .init();
+        });
+    }
+
+    async fn test_setup(doc: String) -> (RocServerState, Url) {
+        init_logger();
+        info!("Doc is:\n{0}", doc);
+        let url = Url::parse("file:/Test.roc").unwrap();
+
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        // setup the file
+        inner.change(&url, doc, 0).await.unwrap();
+        (inner, url)
+    }
+
+    /// A server around `state`, for calling the request handlers directly
+    fn test_service(state: RocServerState) -> LspService<RocServer> {
+        let (service, _) = LspService::new(|client| RocServer {
+            state,
+            client,
+            pulls_diagnostics: AtomicBool::new(true),
+        });
+
+        service
+    }
+
+    /// Writes `files` to a new directory, and opens the first one, which analyses all the
+    /// modules it imports too. The directory is removed when the returned `TempDir` is dropped.
+    async fn workspace_setup(
+        name: &str,
+        files: &[(&str, &str)],
+    ) -> (RocServerState, Url, tempfile::TempDir) {
+        init_logger();
+
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("roc_ls_{name}"))
+            .tempdir()
+            .unwrap();
+
+        for (file_name, source) in files {
+            std::fs::write(dir.path().join(file_name), source).unwrap();
+        }
+
+        let (file_name, source) = files[0];
+        let url = Url::from_file_path(dir.path().join(file_name)).unwrap();
+
+        let inner = RocServerState::new(RocServerConfig::default(), Registry::default());
+        inner.change(&url, source.to_string(), 0).await.unwrap();
+        (inner, url, dir)
+    }
+
+    /// The file name and range of each edit, with its new text, in order
+    fn edits_by_file(edit: WorkspaceEdit) -> Vec<(String, Range, String)> {
+        let mut edits: Vec<_> = edit
+            .changes
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(url, edits)| {
+                let file_name = url.path_segments().unwrap().last().unwrap().to_string();
+
+                edits
+                    .into_iter()
+                    .map(move |edit| (file_name.clone(), edit.range, edit.new_text))
+            })
+            .collect();
+
+        edits.sort_by_key(|(file_name, range, _)| {
+            (file_name.clone(), range.start.line, range.start.character)
+        });
+        edits
+    }
+
+    fn range(line: u32, start: u32, end: u32) -> Range {
+        Range::new(Position::new(line, start), Position::new(line, end))
+    }
+
+    /// Runs a basic completion and returns the response
+    async fn completion_test(
+        initial: &str,
+        addition: &str,
+        position: Position,
+    ) -> Option<Vec<(String, Option<Documentation>)>> {
+        let doc = DOC_LIT.to_string() + initial;
+        let (inner, url) = test_setup(doc.clone()).await;
+        let registry = &inner.registry;
+
+        let change = doc.clone() + addition;
+        info!("doc is:\n{0}", change);
+
+        inner.change(&url, change, 1).await.unwrap();
+
+        get_basic_completion_info(registry, &url, position).await
+    }
+
+    async fn completion_test_labels(
+        initial: &str,
+        addition: &str,
+        position: Position,
+    ) -> Option<Vec<String>> {
+        comp_labels(completion_test(initial, addition, position).await)
+    }
+
+    /// Test that completion works properly when we apply an "as" pattern to an identifier
+    #[tokio::test]
+    async fn test_completion_as_identifier() {
+        let suffix = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+              when a is
+                inn as outer -> 
+                  "#};
+
+        let (inner, url) = test_setup(suffix.clone()).await;
+        let position = Position::new(6, 7);
+        let registry = &inner.registry;
+
+        let change = suffix.clone() + "o";
+        inner.change(&url, change, 1).await.unwrap();
+        let comp1 = comp_labels(get_basic_completion_info(registry, &url, position).await);
+
+        let c = suffix.clone() + "i";
+        inner.change(&url, c, 2).await.unwrap();
+        let comp2 = comp_labels(get_basic_completion_info(registry, &url, position).await);
+
+        let actual = [comp1, comp2];
+
+        expect![[r#"
+            [
+                Some(
+                    [
+                        "outer",
+                    ],
+                ),
+                Some(
+                    [
+                        "inn",
+                        "outer",
+                    ],
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&actual)
+    }
+
+    /// Tests that completion works properly when we apply an "as" pattern to a record.
+    #[tokio::test]
+    async fn test_completion_as_record() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+              when a is
+                {one,two} as outer -> 
+                  "#};
+
+        let (inner, url) = test_setup(doc.clone()).await;
+        let position = Position::new(6, 7);
+        let reg = &inner.registry;
+
+        let change = doc.clone() + "o";
+        inner.change(&url, change, 1).await.unwrap();
+        let comp1 = comp_labels(get_basic_completion_info(reg, &url, position).await);
+
+        let c = doc.clone() + "t";
+        inner.change(&url, c, 2).await.unwrap();
+        let comp2 = comp_labels(get_basic_completion_info(reg, &url, position).await);
+        let actual = [comp1, comp2];
+
+        expect![[r#"
+            [
+                Some(
+                    [
+                        "one",
+                        "two",
+                        "outer",
+                    ],
+                ),
+                Some(
+                    [
+                        "one",
+                        "two",
+                        "outer",
+                    ],
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    /// Test that completion works properly when we apply an "as" pattern to a record
+    #[tokio::test]
+    async fn test_completion_fun_params() {
+        let actual = completion_test_labels(
+            indoc! {r"
+            main = \param1, param2 ->
+              "},
+            "par",
+            Position::new(4, 3),
+        )
+        .await;
+
+        expect![[r#"
+            Some(
+                [
+                    "param1",
+                    "param2",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_completion_closure() {
+        let actual = completion_test_labels(
+            indoc! {r"
+            main = [] |> List.map \ param1 , param2-> 
+              "},
+            "par",
+            Position::new(4, 3),
+        )
+        .await;
+        expect![[r#"
+            Some(
+                [
+                    "param1",
+                    "param2",
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_completion_with_docs() {
+        let actual = completion_test(
+            indoc! {r"
+            ## This is the main function
+            main = mai
+              "},
+            "par",
+            Position::new(4, 10),
+        )
+        .await;
+
+        expect![[r#"
+            Some(
+                [
+                    (
+                        "main",
+                        Some(
+                            MarkupContent(
+                                MarkupContent {
+                                    kind: Markdown,
+                                    value: "This is the main function",
+                                },
+                            ),
+                        ),
+                    ),
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&actual);
+    }
 
-        let change = doc.clone() + addition;
-        info!("doc is:\n{0}", change);
+    #[tokio::test]
+    async fn test_completion_on_utf8() {
+        let actual = completion_test(
+            indoc! {r"
+            main =
+              "},
+            "ç",
+            Position::new(4, 3),
+        )
+        .await;
+
+        expect![[r#"
+            Some(
+                [
+                    (
+                        "main",
+                        None,
+                    ),
+                ],
+            )
+        "#]]
+        .assert_debug_eq(&actual);
+    }
+
+    #[tokio::test]
+    async fn test_references_to_local() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r"
+            main =
+              value = 1
+              value + value
+            "};
+
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        let ranges = |locations: Option<Vec<Location>>| {
+            locations
+                .unwrap()
+                .into_iter()
+                .map(|location| location.range)
+                .collect::<Vec<_>>()
+        };
+
+        let with_declaration = registry.references(&url, Position::new(5, 3), true).await;
+        let without_declaration = registry.references(&url, Position::new(5, 3), false).await;
+
+        assert_eq!(
+            ranges(with_declaration),
+            vec![range(4, 2, 7), range(5, 2, 7), range(5, 10, 15)]
+        );
+        assert_eq!(
+            ranges(without_declaration),
+            vec![range(5, 2, 7), range(5, 10, 15)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_exposed_across_modules() {
+        let (inner, url, _dir) = workspace_setup(
+            "rename_exposed",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.bar "hi"
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar]
+
+                    bar : Str -> Str
+                    bar = \s -> s
+                    "},
+                ),
+            ],
+        )
+        .await;
+        let registry = &inner.registry;
+
+        let prepared = registry.prepare_rename(&url, Position::new(4, 14)).await;
+        assert_eq!(
+            prepared,
+            Ok(Some(PrepareRenameResponse::Range(range(4, 13, 16))))
+        );
+
+        let edit = registry
+            .rename(&url, Position::new(4, 14), "baz")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let baz = "baz".to_string();
+        assert_eq!(
+            edits_by_file(edit),
+            vec![
+                ("Other.roc".to_string(), range(0, 8, 11), baz.clone()),
+                ("Other.roc".to_string(), range(2, 0, 3), baz.clone()),
+                ("Other.roc".to_string(), range(3, 0, 3), baz.clone()),
+                ("Test.roc".to_string(), range(4, 13, 16), baz),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_record_field_shorthand() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r"
+            main =
+              name = 1
+              { name }
+            "};
+
+        let (inner, url) = test_setup(doc).await;
+
+        let edit = inner
+            .registry
+            .rename(&url, Position::new(4, 3), "fullName")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            edits_by_file(edit),
+            vec![
+                (
+                    "Test.roc".to_string(),
+                    range(4, 2, 6),
+                    "fullName".to_string()
+                ),
+                (
+                    "Test.roc".to_string(),
+                    range(5, 4, 8),
+                    "name: fullName".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_rejects_invalid_names_and_builtins() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main =
+              value = Str.concat "a" "b"
+              value
+            "#};
+
+        let (inner, url) = test_setup(doc).await;
+        let registry = &inner.registry;
+
+        assert!(registry
+            .rename(&url, Position::new(5, 3), "Value")
+            .await
+            .is_err());
+        assert!(registry
+            .prepare_rename(&url, Position::new(4, 15))
+            .await
+            .is_err());
+    }
+
+    /// The title of each code action for `range`, with its edits
+    async fn code_actions_on_range(
+        registry: &Registry,
+        url: &Url,
+        range: Range,
+        only: Option<&[CodeActionKind]>,
+    ) -> Vec<(String, Vec<(String, Range, String)>)> {
+        registry
+            .code_actions(url, range, only)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => {
+                    (action.title, edits_by_file(action.edit.unwrap()))
+                }
+                CodeActionOrCommand::Command(command) => (command.title, vec![]),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_code_action_adds_missing_branches() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r#"
+            main : Result U64 Str -> U64
+            main = \r ->
+              when r is
+                Ok n -> n
+            "#};
+
+        let (inner, url) = test_setup(doc).await;
+
+        let actions = code_actions_on_range(&inner.registry, &url, range(6, 0, 0), None).await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Add the missing branch".to_string(),
+                vec![(
+                    "Test.roc".to_string(),
+                    range(6, 13, 13),
+                    "\n    Err _ -> crash \"TODO\"".to_string()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_code_action_exposes_unused_def() {
+        let doc = indoc! {r#"
+            module []
+
+            helper = 1
+            "#};
+
+        let (inner, url) = test_setup(doc.to_string()).await;
+
+        let actions = code_actions_on_range(
+            &inner.registry,
+            &url,
+            range(2, 0, 0),
+            Some(&[CodeActionKind::QUICKFIX]),
+        )
+        .await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Expose `helper`".to_string(),
+                vec![("Test.roc".to_string(), range(0, 8, 8), "helper".to_string())]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_code_action_exposes_value_of_imported_module() {
+        let (inner, url, _dir) = workspace_setup(
+            "expose_imported",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.farewell
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r#"
+                    module [greeting]
+
+                    greeting = "hi"
+                    farewell = "bye"
+                    "#},
+                ),
+            ],
+        )
+        .await;
+
+        let actions = code_actions_on_range(
+            &inner.registry,
+            &url,
+            range(4, 7, 7),
+            Some(&[CodeActionKind::QUICKFIX]),
+        )
+        .await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Expose `farewell` from Other".to_string(),
+                vec![(
+                    "Other.roc".to_string(),
+                    range(0, 16, 16),
+                    ", farewell".to_string()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_code_action_removes_unused_import() {
+        let (inner, url, _dir) = workspace_setup(
+            "unused_import",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other exposing [bar, baz]
+
+                    main = bar "hi"
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar, baz]
+
+                    bar = \s -> s
+                    baz = \s -> s
+                    "},
+                ),
+            ],
+        )
+        .await;
+
+        let actions = code_actions_on_range(&inner.registry, &url, range(2, 29, 29), None).await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Remove the unused import".to_string(),
+                vec![("Test.roc".to_string(), range(2, 26, 31), String::new())]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_organize_imports_sorts_merges_and_removes_unused() {
+        let (inner, url, _dir) = workspace_setup(
+            "organize_imports",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other exposing [baz]
+                    import Another
+                    import Other exposing [bar]
+
+                    main = bar (Another.id "hi")
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar, baz]
+
+                    bar = \s -> s
+                    baz = \s -> s
+                    "},
+                ),
+                (
+                    "Another.roc",
+                    indoc! {r"
+                    module [id]
+
+                    id = \s -> s
+                    "},
+                ),
+            ],
+        )
+        .await;
+
+        let only = [CodeActionKind::SOURCE];
+        let actions =
+            code_actions_on_range(&inner.registry, &url, range(0, 0, 0), Some(&only)).await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Organize imports".to_string(),
+                vec![(
+                    "Test.roc".to_string(),
+                    Range::new(Position::new(2, 0), Position::new(3, 0)),
+                    String::new()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completion_imports_names_from_other_modules() {
+        let doc = indoc! {r#"
+            module [main]
+
+            main =
+                "hi"
+            "#};
+        let (inner, url, _dir) = workspace_setup(
+            "auto_import",
+            &[
+                ("Test.roc", doc),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar, Baz]
+
+                    Baz := U64
+
+                    bar = \s -> s
+                    "},
+                ),
+            ],
+        )
+        .await;
+        inner.discover_modules().await;
+
+        let change = doc.replace("\"hi\"", "ba");
+        inner.change(&url, change, 1).await.unwrap();
+
+        let Some(CompletionResponse::Array(items)) = inner
+            .registry
+            .completion_items(&url, Position::new(3, 6))
+            .await
+        else {
+            panic!("expected completion items");
+        };
+
+        let imported = items
+            .into_iter()
+            .filter_map(|item| {
+                let description = item.label_details?.description?;
+                let edits = item
+                    .additional_text_edits?
+                    .into_iter()
+                    .map(|edit| (edit.range, edit.new_text))
+                    .collect::<Vec<_>>();
+
+                Some((item.label, description, edits))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            imported,
+            vec![(
+                "bar".to_string(),
+                "Other".to_string(),
+                vec![(
+                    range(0, 13, 13),
+                    "\n\nimport Other exposing [bar]".to_string()
+                )]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inlay_hints_for_unannotated_bindings() {
+        let doc = DOC_LIT.to_string()
+            + indoc! {r"
+            main =
+              double = \n -> Num.mul n 2u8
+              double 4
+            "};
+
+        let (inner, url) = test_setup(doc).await;
+        let service = test_service(inner);
+        let server = service.inner();
+
+        let params = InlayHintParams {
+            text_document: TextDocumentIdentifier::new(url.clone()),
+            range: Range::new(Position::new(0, 0), Position::new(6, 0)),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let hints = server
+            .inlay_hint(params)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => (hint.position, label),
+                InlayHintLabel::LabelParts(_) => panic!("expected a plain label"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            hints,
+            vec![
+                (Position::new(3, 4), ": U8".to_string()),
+                (Position::new(4, 8), ": U8 -> U8".to_string()),
+                (Position::new(4, 13), ": U8".to_string()),
+            ]
+        );
+
+        let registry = &server.state.registry;
+        let actions = code_actions_on_range(registry, &url, range(4, 3, 3), None).await;
+
+        assert_eq!(
+            actions,
+            vec![(
+                "Add a type annotation for `double`".to_string(),
+                vec![(
+                    "Test.roc".to_string(),
+                    range(4, 2, 2),
+                    "double : U8 -> U8\n  ".to_string()
+                )]
+            )]
+        );
+    }
+
+    /// The name and kind of each symbol, and of its children after it, indented by depth
+    fn symbol_outline(symbols: Vec<DocumentSymbol>, depth: usize) -> Vec<(String, SymbolKind)> {
+        symbols
+            .into_iter()
+            .flat_map(|symbol| {
+                let name = format!("{}{}", "  ".repeat(depth), symbol.name);
+                let children = symbol_outline(symbol.children.unwrap_or_default(), depth + 1);
+
+                std::iter::once((name, symbol.kind)).chain(children)
+            })
+            .collect()
+    }
+
+    const SHAPES_DOC: &str = indoc! {r"
+        Shape : [Circle F64, Square F64]
+
+        main =
+          area = \shape ->
+            when shape is
+              Circle r -> r * r
+              Square s -> s * s
+          area (Circle 1)
+        "};
+
+    #[tokio::test]
+    async fn test_document_symbols_nest_defs() {
+        let (inner, url) = test_setup(DOC_LIT.to_string() + SHAPES_DOC).await;
+
+        let Some(DocumentSymbolResponse::Nested(symbols)) =
+            inner.registry.document_symbols(&url).await
+        else {
+            panic!("expected nested document symbols");
+        };
+
+        assert_eq!(
+            symbol_outline(symbols, 0),
+            vec![
+                ("Shape".to_string(), SymbolKind::TYPE_PARAMETER),
+                ("main".to_string(), SymbolKind::CONSTANT),
+                ("  area".to_string(), SymbolKind::FUNCTION),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_folding_ranges_for_multi_line_exprs() {
+        let (inner, url) = test_setup(DOC_LIT.to_string() + SHAPES_DOC).await;
+
+        let ranges = inner
+            .registry
+            .folding_ranges(&url)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(ranges, vec![(5, 10), (6, 9), (7, 9)]);
+    }
+
+    #[tokio::test]
+    async fn test_code_lenses_for_expects_and_values() {
+        let doc = indoc! {r"
+            module [answer, double]
+
+            answer = 42
+
+            double = \n -> n * 2
+
+            expect double 2 == 4
+            "};
+        let (inner, url) = test_setup(doc.to_string()).await;
+
+        let lenses = inner
+            .registry
+            .code_lenses(&url)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|lens| {
+                let command = lens.command.unwrap();
+                (command.title, command.command, lens.range.start)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lenses,
+            vec![
+                (
+                    "Evaluate".to_string(),
+                    EVALUATE_COMMAND.to_string(),
+                    Position::new(2, 0)
+                ),
+                (
+                    "▶ Run".to_string(),
+                    RUN_EXPECT_COMMAND.to_string(),
+                    Position::new(6, 7)
+                ),
+                (
+                    "Debug".to_string(),
+                    DEBUG_EXPECT_COMMAND.to_string(),
+                    Position::new(6, 7)
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workspace_symbols_fuzzy_match_across_modules() {
+        let (inner, _url, _dir) = workspace_setup(
+            "workspace_symbols",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.bar "hi"
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar]
+
+                    bar = \s -> s
+                    "},
+                ),
+            ],
+        )
+        .await;
+
+        let symbols = inner
+            .registry
+            .workspace_symbols("br")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.container_name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            symbols,
+            vec![(
+                "bar".to_string(),
+                SymbolKind::FUNCTION,
+                Some("Other".to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signature_help_highlights_next_argument() {
+        let doc = indoc! {r#"
+            module [join, main]
+
+            ## Joins the words with a separator.
+            join : Str, List Str -> Str
+            join = \sep, words -> Str.joinWith words sep
+
+            "#}
+        .to_string()
+            + r#"main = join ", " "#;
+
+        let (inner, url) = test_setup(doc).await;
+
+        let help = inner
+            .registry
+            .signature_help(&url, Position::new(6, 17))
+            .await;
+
+        let parameter = |start, end| ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, end]),
+            documentation: None,
+        };
+
+        assert_eq!(
+            help,
+            Some(SignatureHelp {
+                signatures: vec![SignatureInformation {
+                    label: "join : Str, List Str -> Str".to_string(),
+                    documentation: Some(Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: "Joins the words with a separator.".to_string(),
+                    })),
+                    parameters: Some(vec![parameter(7, 10), parameter(12, 20)]),
+                    active_parameter: Some(1),
+                }],
+                active_signature: Some(0),
+                active_parameter: Some(1),
+            })
+        );
+    }
+
+    /// The label of the signature shown at `position`, and the parameter it highlights
+    async fn signature_label(
+        inner: &RocServerState,
+        url: &Url,
+        position: Position,
+    ) -> Option<(String, Option<u32>)> {
+        let help = inner.registry.signature_help(url, position).await?;
+        let signature = help.signatures.into_iter().next()?;
+
+        Some((signature.label, signature.active_parameter))
+    }
+
+    #[tokio::test]
+    async fn test_signature_help_for_imported_function() {
+        let doc = indoc! {r#"
+            module [main]
+
+            import Other
+
+            "#}
+        .to_string()
+            + r#"main = Other.pad "a" "#;
+
+        let (inner, url, _dir) = workspace_setup(
+            "signature_help_imported",
+            &[
+                ("Test.roc", &doc),
+                (
+                    "Other.roc",
+                    indoc! {r#"
+                    module [pad]
+
+                    ## Pads the string on the left.
+                    pad : Str, U64 -> Str
+                    pad = \str, _ -> str
+                    "#},
+                ),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            signature_label(&inner, &url, Position::new(4, 21)).await,
+            Some(("Other.pad : Str, U64 -> Str".to_string(), Some(1)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signature_help_for_record_field_function() {
+        let doc = indoc! {r#"
+            module [main]
+
+            ops : { add : U64, U64 -> U64 }
+            ops = { add: \a, b -> a + b }
+
+            main = ops.add 1 2
+            "#};
+
+        // the call is complete: an incomplete one would make the record's type an error, as the
+        // record isn't generalized like a function is
+        let (inner, url) = test_setup(doc.to_string()).await;
+
+        assert_eq!(
+            signature_label(&inner, &url, Position::new(5, 17)).await,
+            Some(("ops.add : U64, U64 -> U64".to_string(), Some(1)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signature_help_for_local_lambda() {
+        let doc = indoc! {r#"
+            module [main]
+
+            main =
+                join = \a, b -> Str.concat a b
 
-        inner.change(&url, change, 1).await.unwrap();
+            "#}
+        .to_string()
+            + "    join \"a\" ";
 
-        get_basic_completion_info(registry, &url, position).await
-    }
+        let (inner, url) = test_setup(doc).await;
 
-    async fn completion_test_labels(
-        initial: &str,
-        addition: &str,
-        position: Position,
-    ) -> Option<Vec<String>> {
-        comp_labels(completion_test(initial, addition, position).await)
+        assert_eq!(
+            signature_label(&inner, &url, Position::new(5, 13)).await,
+            Some(("join : Str, Str -> Str".to_string(), Some(1)))
+        );
     }
 
-    /// Test that completion works properly when we apply an "as" pattern to an identifier
     #[tokio::test]
-    async fn test_completion_as_identifier() {
-        let suffix = DOC_LIT.to_string()
-            + indoc! {r#"
-            main =
-              when a is
-                inn as outer -> 
-                  "#};
+    async fn test_unsaved_buffers_are_used_for_imported_modules() {
+        let (inner, url, _dir) = workspace_setup(
+            "unsaved_buffers",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.farewell
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r#"
+                    module [greeting]
 
-        let (inner, url) = test_setup(suffix.clone()).await;
-        let position = Position::new(6, 7);
-        let registry = &inner.registry;
+                    greeting = "hi"
+                    "#},
+                ),
+            ],
+        )
+        .await;
 
-        let change = suffix.clone() + "o";
-        inner.change(&url, change, 1).await.unwrap();
-        let comp1 = comp_labels(get_basic_completion_info(registry, &url, position).await);
+        assert!(!inner.registry.diagnostics(&url).await.is_empty());
 
-        let c = suffix.clone() + "i";
-        inner.change(&url, c, 2).await.unwrap();
-        let comp2 = comp_labels(get_basic_completion_info(registry, &url, position).await);
+        let other_url = url.join("Other.roc").unwrap();
+        let unsaved = indoc! {r#"
+            module [greeting, farewell]
 
-        let actual = [comp1, comp2];
+            greeting = "hi"
+            farewell = "bye"
+            "#};
+        inner
+            .change(&other_url, unsaved.to_string(), 1)
+            .await
+            .unwrap();
 
-        expect![[r#"
-            [
-                Some(
-                    [
-                        "outer",
-                    ],
-                ),
-                Some(
-                    [
-                        "inn",
-                        "outer",
-                    ],
-                ),
-            ]
-        "#]]
-        .assert_debug_eq(&actual)
+        assert_eq!(
+            inner.registry.dependents(&other_url).await,
+            vec![url.clone()]
+        );
+
+        inner.recheck(&url).await.unwrap();
+
+        assert_eq!(inner.registry.diagnostics(&url).await, vec![]);
     }
 
-    /// Tests that completion works properly when we apply an "as" pattern to a record.
     #[tokio::test]
-    async fn test_completion_as_record() {
-        let doc = DOC_LIT.to_string()
-            + indoc! {r#"
-            main =
-              when a is
-                {one,two} as outer -> 
-                  "#};
+    async fn test_change_rechecks_importing_modules() {
+        let (state, url, _dir) = workspace_setup(
+            "change_rechecks_importing",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.farewell
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r#"
+                    module [greeting]
 
-        let (inner, url) = test_setup(doc.clone()).await;
-        let position = Position::new(6, 7);
-        let reg = &inner.registry;
+                    greeting = "hi"
+                    "#},
+                ),
+            ],
+        )
+        .await;
 
-        let change = doc.clone() + "o";
-        inner.change(&url, change, 1).await.unwrap();
-        let comp1 = comp_labels(get_basic_completion_info(reg, &url, position).await);
+        let service = test_service(state);
+        let server = service.inner();
 
-        let c = doc.clone() + "t";
-        inner.change(&url, c, 2).await.unwrap();
-        let comp2 = comp_labels(get_basic_completion_info(reg, &url, position).await);
-        let actual = [comp1, comp2];
+        assert!(!server.state.registry.diagnostics(&url).await.is_empty());
 
-        expect![[r#"
-            [
-                Some(
-                    [
-                        "one",
-                        "two",
-                        "outer",
-                    ],
+        let other_url = url.join("Other.roc").unwrap();
+        let unsaved = indoc! {r#"
+            module [greeting, farewell]
+
+            greeting = "hi"
+            farewell = "bye"
+            "#};
+        server.change(other_url, unsaved.to_string(), 1).await;
+
+        assert_eq!(server.state.registry.diagnostics(&url).await, vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_diagnostic_includes_unopened_modules() {
+        let (inner, url, _dir) = workspace_setup(
+            "workspace_diagnostic",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main]
+
+                    import Other
+
+                    main = Other.bar "hi"
+                    "#},
                 ),
-                Some(
-                    [
-                        "one",
-                        "two",
-                        "outer",
-                    ],
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar]
+
+                    bar : Str -> Str
+                    bar = \s -> s
+                    "},
+                ),
+                (
+                    "Uses.roc",
+                    indoc! {r"
+                    module [main]
+
+                    import Other
+
+                    main = Other.bar 1
+                    "},
                 ),
+            ],
+        )
+        .await;
+
+        let service = test_service(inner);
+        let server = service.inner();
+
+        let params = WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: Vec::new(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let report = match server.workspace_diagnostic(params).await.unwrap() {
+            WorkspaceDiagnosticReportResult::Report(report) => report,
+            WorkspaceDiagnosticReportResult::Partial(_) => panic!("expected a full report"),
+        };
+
+        let mut result_id = None;
+        let reports = report
+            .items
+            .into_iter()
+            .map(|item| match item {
+                WorkspaceDocumentDiagnosticReport::Full(full) => {
+                    let file_name = full.uri.path_segments().unwrap().last().unwrap();
+                    let diagnostics = full.full_document_diagnostic_report;
+                    result_id = diagnostics.result_id;
+
+                    (
+                        file_name.to_string(),
+                        full.version,
+                        diagnostics.items.is_empty(),
+                    )
+                }
+                WorkspaceDocumentDiagnosticReport::Unchanged(_) => panic!("nothing was pulled yet"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            reports,
+            vec![
+                ("Other.roc".to_string(), None, true),
+                ("Test.roc".to_string(), Some(0), true),
+                ("Uses.roc".to_string(), None, false),
             ]
-        "#]]
-        .assert_debug_eq(&actual);
+        );
+
+        let params = DocumentDiagnosticParams {
+            text_document: TextDocumentIdentifier::new(url),
+            identifier: None,
+            previous_result_id: result_id,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let report = server.diagnostic(params).await.unwrap();
+
+        assert!(matches!(
+            report,
+            DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Unchanged(_))
+        ));
+    }
+
+    fn file_name(url: &Url) -> String {
+        url.path_segments().unwrap().last().unwrap().to_string()
     }
 
-    /// Test that completion works properly when we apply an "as" pattern to a record
     #[tokio::test]
-    async fn test_completion_fun_params() {
-        let actual = completion_test_labels(
-            indoc! {r"
-            main = \param1, param2 ->
-              "},
-            "par",
-            Position::new(4, 3),
+    async fn test_call_hierarchy_across_modules() {
+        let (inner, url, _dir) = workspace_setup(
+            "call_hierarchy",
+            &[
+                (
+                    "Test.roc",
+                    indoc! {r#"
+                    module [main, twice]
+
+                    import Other
+
+                    twice = \s -> Other.bar (Other.bar s)
+
+                    main = twice "hi"
+                    "#},
+                ),
+                (
+                    "Other.roc",
+                    indoc! {r"
+                    module [bar]
+
+                    bar : Str -> Str
+                    bar = \s -> s
+                    "},
+                ),
+            ],
         )
         .await;
+        let registry = &inner.registry;
 
-        expect![[r#"
-            Some(
-                [
-                    "param1",
-                    "param2",
-                ],
-            )
-        "#]]
-        .assert_debug_eq(&actual);
+        let bar = registry
+            .prepare_call_hierarchy(&url, Position::new(4, 20))
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            (file_name(&bar.uri), bar.name.as_str(), bar.kind),
+            ("Other.roc".to_string(), "bar", SymbolKind::FUNCTION)
+        );
+        assert_eq!(bar.detail.as_deref(), Some("Str -> Str"));
+
+        let incoming = registry
+            .incoming_calls(&bar)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|call| (file_name(&call.from.uri), call.from.name, call.from_ranges))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            incoming,
+            vec![(
+                "Test.roc".to_string(),
+                "twice".to_string(),
+                vec![range(4, 14, 23), range(4, 25, 34)]
+            )]
+        );
+
+        let main = registry
+            .prepare_call_hierarchy(&url, Position::new(6, 1))
+            .await
+            .unwrap()
+            .remove(0);
+        let twice = registry.outgoing_calls(&main).await.unwrap().remove(0);
+        assert_eq!(
+            (twice.to.name.as_str(), twice.from_ranges),
+            ("twice", vec![range(6, 7, 12)])
+        );
+
+        let outgoing = registry
+            .outgoing_calls(&twice.to)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|call| (file_name(&call.to.uri), call.to.name, call.from_ranges))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outgoing,
+            vec![(
+                "Other.roc".to_string(),
+                "bar".to_string(),
+                vec![range(4, 14, 23), range(4, 25, 34)]
+            )]
+        );
     }
 
     #[tokio::test]
-    async fn test_completion_closure() {
-        let actual = completion_test_labels(
-            indoc! {r"
-            main = [] |> List.map \ param1 , param2-> 
-              "},
-            "par",
-            Position::new(4, 3),
-        )
-        .await;
-        expect![[r#"
-            Some(
-                [
-                    "param1",
-                    "param2",
-                ],
-            )
-        "#]]
-        .assert_debug_eq(&actual);
+    async fn test_initialize_advertises_type_hierarchies() {
+        let (state, _) = test_setup(DOC_LIT.to_string()).await;
+        let mut service = AdvertiseTypeHierarchy(test_service(state));
+
+        let request = jsonrpc::Request::build("initialize")
+            .params(serde_json::json!({ "capabilities": {} }))
+            .id(1)
+            .finish();
+        futures::future::poll_fn(|cx| service.poll_ready(cx))
+            .await
+            .unwrap();
+        let response = service.call(request).await.unwrap().unwrap();
+
+        let (_, result) = response.into_parts();
+        let capabilities = &result.unwrap()["capabilities"];
+        assert_eq!(capabilities["typeHierarchyProvider"], true);
+        assert_eq!(capabilities["callHierarchyProvider"], true);
     }
 
     #[tokio::test]
-    async fn test_completion_with_docs() {
-        let actual = completion_test(
-            indoc! {r"
-            ## This is the main function
-            main = mai
-              "},
-            "par",
-            Position::new(4, 10),
+    async fn test_type_hierarchy_of_abilities() {
+        let (inner, url, _dir) = workspace_setup(
+            "type_hierarchy",
+            &[(
+                "Test.roc",
+                indoc! {r"
+                module [Named, name, Id, Shape]
+
+                Named implements
+                    name : a -> Str where a implements Named
+
+                Id := U64 implements [Eq, Hash, Named { name: idName }]
+
+                idName = \@Id n -> Num.toStr n
+
+                Shape := Str implements [Named { name: shapeName }]
+
+                shapeName = \@Shape s -> s
+                "},
+            )],
         )
         .await;
+        let registry = &inner.registry;
 
-        expect![[r#"
-            Some(
-                [
-                    (
-                        "main",
-                        Some(
-                            MarkupContent(
-                                MarkupContent {
-                                    kind: Markdown,
-                                    value: "This is the main function",
-                                },
-                            ),
-                        ),
-                    ),
-                ],
-            )
-        "#]]
-        .assert_debug_eq(&actual);
+        let outline = |items: Vec<TypeHierarchyItem>| {
+            items
+                .into_iter()
+                .map(|item| (item.name, item.kind, item.detail))
+                .collect::<Vec<_>>()
+        };
+        let derived = Some("derived".to_string());
+        let custom = Some("custom".to_string());
+
+        let id = registry
+            .prepare_type_hierarchy(&url, Position::new(5, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!((id.name.as_str(), id.kind), ("Id", SymbolKind::STRUCT));
+
+        assert_eq!(
+            outline(registry.supertypes(&id).await.unwrap()),
+            vec![
+                ("Eq".to_string(), SymbolKind::INTERFACE, derived.clone()),
+                ("Hash".to_string(), SymbolKind::INTERFACE, derived.clone()),
+                ("Named".to_string(), SymbolKind::INTERFACE, custom.clone()),
+            ]
+        );
+
+        let named = registry
+            .prepare_type_hierarchy(&url, Position::new(2, 0))
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            outline(registry.subtypes(&named).await.unwrap()),
+            vec![
+                ("Id".to_string(), SymbolKind::STRUCT, custom.clone()),
+                ("Shape".to_string(), SymbolKind::STRUCT, custom),
+            ]
+        );
+
+        // builtin abilities are shown where they are implemented
+        let eq = registry
+            .prepare_type_hierarchy(&url, Position::new(5, 22))
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(eq.selection_range, range(5, 22, 24));
+        assert_eq!(
+            outline(registry.subtypes(&eq).await.unwrap()),
+            vec![("Id".to_string(), SymbolKind::STRUCT, derived)]
+        );
     }
 
     #[tokio::test]
-    async fn test_completion_on_utf8() {
-        let actual = completion_test(
-            indoc! {r"
+    async fn test_formatting_edits_only_what_changes() {
+        let doc = indoc! {r"
+            module [main, other]
+
+            main = 1+2
+
+            other = 3+4
+            "};
+        let (inner, url) = test_setup(doc.to_string()).await;
+        let registry = &inner.registry;
+
+        assert_eq!(
+            registry.formatting(&url).await,
+            Some(vec![
+                TextEdit::new(range(2, 8, 9), " + ".to_string()),
+                TextEdit::new(range(4, 9, 10), " + ".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            registry.range_formatting(&url, range(4, 0, 11)).await,
+            Some(vec![TextEdit::new(range(4, 9, 10), " + ".to_string())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_type_formatting_indents_new_blocks() {
+        let doc = indoc! {r"
+            module [main]
+
             main =
-              "},
-            "ç",
-            Position::new(4, 3),
-        )
-        .await;
 
-        expect![[r#"
-            Some(
-                [
-                    (
-                        "main",
-                        None,
-                    ),
-                ],
-            )
-        "#]]
-        .assert_debug_eq(&actual);
+            "};
+        let (inner, url) = test_setup(doc.to_string()).await;
+        let registry = &inner.registry;
+
+        assert_eq!(
+            registry
+                .on_type_formatting(&url, Position::new(3, 0), "\n")
+                .await,
+            Some(vec![TextEdit::new(range(3, 0, 0), "    ".to_string())])
+        );
+
+        // the new line starts with less indentation than the branches of the `when` need
+        let doc = "module [main]\n\nmain = \\x ->\n    when x is\n  \n";
+        inner.change(&url, doc.to_string(), 1).await.unwrap();
+
+        assert_eq!(
+            registry
+                .on_type_formatting(&url, Position::new(4, 2), "\n")
+                .await,
+            Some(vec![TextEdit::new(range(4, 0, 2), "        ".to_string())])
+        );
+
+        let doc = indoc! {r"
+            module [main]
+
+            main = 1
+
+            "};
+        inner.change(&url, doc.to_string(), 2).await.unwrap();
+
+        assert_eq!(
+            registry
+                .on_type_formatting(&url, Position::new(3, 0), "\n")
+                .await,
+            None
+        );
     }
 }
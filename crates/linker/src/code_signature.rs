@@ -0,0 +1,154 @@
+//! Ad-hoc code signatures for Mach-O executables.
+//!
+//! On Apple silicon the kernel refuses to run a binary that is not signed, and any change we make
+//! to a host invalidates the signature that its linker created. An ad-hoc signature is nothing
+//! more than a list of SHA-256 hashes of the pages of the file, so we can produce one ourselves
+//! instead of shelling out to `codesign`.
+
+use sha2::{Digest, Sha256};
+
+const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xfade_0cc0;
+const CSMAGIC_CODEDIRECTORY: u32 = 0xfade_0c02;
+const CSSLOT_CODEDIRECTORY: u32 = 0;
+
+/// The first version of the code directory that has the `execSeg*` fields.
+const CS_SUPPORTSEXECSEG: u32 = 0x20400;
+
+const CS_ADHOC: u32 = 0x2;
+const CS_LINKER_SIGNED: u32 = 0x20000;
+
+const CS_HASHTYPE_SHA256: u8 = 2;
+const CS_EXECSEG_MAIN_BINARY: u64 = 0x1;
+
+const PAGE_SIZE_LOG2: u8 = 12;
+const PAGE_SIZE: usize = 1 << PAGE_SIZE_LOG2;
+const HASH_SIZE: usize = 32;
+
+const SUPER_BLOB_HEADER_SIZE: usize = 12;
+const BLOB_INDEX_SIZE: usize = 8;
+const CODE_DIRECTORY_HEADER_SIZE: usize = 88;
+
+pub(crate) struct AdHocSignature<'a> {
+    pub identifier: &'a str,
+    /// Offset of the signature in the file. Everything before it is hashed.
+    pub code_limit: usize,
+    pub exec_segment_offset: u64,
+    pub exec_segment_size: u64,
+    pub is_executable: bool,
+}
+
+impl AdHocSignature<'_> {
+    fn page_count(&self) -> usize {
+        self.code_limit.div_ceil(PAGE_SIZE)
+    }
+
+    fn code_directory_size(&self) -> usize {
+        CODE_DIRECTORY_HEADER_SIZE + self.identifier.len() + 1 + self.page_count() * HASH_SIZE
+    }
+
+    /// The number of bytes that `write` will produce, padded like `ld` does.
+    pub fn size(&self) -> usize {
+        let size = SUPER_BLOB_HEADER_SIZE + BLOB_INDEX_SIZE + self.code_directory_size();
+
+        size.next_multiple_of(16)
+    }
+
+    /// Hash `file[..code_limit]` and write the signature to the start of `output`, which must be
+    /// at least `self.size()` bytes long.
+    pub fn write(&self, file: &[u8], output: &mut [u8]) {
+        let code_directory_offset = SUPER_BLOB_HEADER_SIZE + BLOB_INDEX_SIZE;
+        let code_directory_size = self.code_directory_size();
+        let ident_offset = CODE_DIRECTORY_HEADER_SIZE;
+        let hash_offset = ident_offset + self.identifier.len() + 1;
+
+        let mut blob = Vec::with_capacity(self.size());
+
+        // SuperBlob with a single entry: the CodeDirectory
+        blob.extend(CSMAGIC_EMBEDDED_SIGNATURE.to_be_bytes());
+        blob.extend((self.size() as u32).to_be_bytes());
+        blob.extend(1u32.to_be_bytes());
+        blob.extend(CSSLOT_CODEDIRECTORY.to_be_bytes());
+        blob.extend((code_directory_offset as u32).to_be_bytes());
+
+        let exec_segment_flags = if self.is_executable {
+            CS_EXECSEG_MAIN_BINARY
+        } else {
+            0
+        };
+
+        blob.extend(CSMAGIC_CODEDIRECTORY.to_be_bytes());
+        blob.extend((code_directory_size as u32).to_be_bytes());
+        blob.extend(CS_SUPPORTSEXECSEG.to_be_bytes());
+        blob.extend((CS_ADHOC | CS_LINKER_SIGNED).to_be_bytes());
+        blob.extend((hash_offset as u32).to_be_bytes());
+        blob.extend((ident_offset as u32).to_be_bytes());
+        blob.extend(0u32.to_be_bytes()); // nSpecialSlots
+        blob.extend((self.page_count() as u32).to_be_bytes()); // nCodeSlots
+        blob.extend((self.code_limit as u32).to_be_bytes());
+        blob.push(HASH_SIZE as u8);
+        blob.push(CS_HASHTYPE_SHA256);
+        blob.push(0); // platform
+        blob.push(PAGE_SIZE_LOG2);
+        blob.extend(0u32.to_be_bytes()); // spare2
+        blob.extend(0u32.to_be_bytes()); // scatterOffset
+        blob.extend(0u32.to_be_bytes()); // teamOffset
+        blob.extend(0u32.to_be_bytes()); // spare3
+        blob.extend(0u64.to_be_bytes()); // codeLimit64
+        blob.extend(self.exec_segment_offset.to_be_bytes());
+        blob.extend(self.exec_segment_size.to_be_bytes());
+        blob.extend(exec_segment_flags.to_be_bytes());
+        debug_assert_eq!(blob.len(), code_directory_offset + ident_offset);
+
+        blob.extend(self.identifier.as_bytes());
+        blob.push(0);
+
+        for page in file[..self.code_limit].chunks(PAGE_SIZE) {
+            blob.extend(Sha256::digest(page));
+        }
+
+        debug_assert_eq!(blob.len(), code_directory_offset + code_directory_size);
+
+        blob.resize(self.size(), 0);
+        output[..blob.len()].copy_from_slice(&blob);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_hashes_every_page() {
+        let file = vec![0xab; PAGE_SIZE + 100];
+        let signature = AdHocSignature {
+            identifier: "app",
+            code_limit: file.len(),
+            exec_segment_offset: 0,
+            exec_segment_size: PAGE_SIZE as u64,
+            is_executable: true,
+        };
+
+        let mut output = vec![0; signature.size()];
+        signature.write(&file, &mut output);
+
+        let read_u32 =
+            |offset: usize| u32::from_be_bytes(output[offset..][..4].try_into().unwrap());
+        assert_eq!(read_u32(0), CSMAGIC_EMBEDDED_SIGNATURE);
+        assert_eq!(read_u32(4) as usize, signature.size());
+
+        let code_directory = read_u32(16) as usize;
+        assert_eq!(read_u32(code_directory), CSMAGIC_CODEDIRECTORY);
+        assert_eq!(read_u32(code_directory + 28), 2);
+        assert_eq!(read_u32(code_directory + 32) as usize, file.len());
+
+        let hash_offset = code_directory + read_u32(code_directory + 16) as usize;
+        assert_eq!(
+            &output[hash_offset..][..HASH_SIZE],
+            Sha256::digest(&file[..PAGE_SIZE]).as_slice()
+        );
+        assert_eq!(
+            &output[hash_offset + HASH_SIZE..][..HASH_SIZE],
+            Sha256::digest(&file[PAGE_SIZE..]).as_slice()
+        );
+    }
+}
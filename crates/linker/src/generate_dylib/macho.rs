@@ -1,127 +1,357 @@
-use object::write;
-use object::{Architecture, BinaryFormat, Endianness, SymbolFlags, SymbolKind, SymbolScope};
-use roc_error_macros::internal_error;
+use object::macho;
 use roc_target::Target;
-use std::path::Path;
-use std::process::Command;
 
-// TODO: Eventually do this from scratch and in memory instead of with ld.
+use crate::pe::next_multiple_of;
+
+/// The name the host records in its `LC_LOAD_DYLIB`, and that `preprocess_macho_le` looks for.
+const INSTALL_NAME: &[u8] = b"libapp.dylib";
+
+const HEADER_SIZE: usize = 32;
+const SEGMENT_COMMAND_SIZE: usize = 72;
+const SECTION_SIZE: usize = 80;
+const NLIST_SIZE: usize = 16;
+
+/// Build a `MH_DYLIB` that exports `custom_names` from a tiny `__text` section. The host is
+/// linked against it, so the only things that matter are the install name and the exported
+/// symbols; the surgical linker replaces all of this with the real app later on.
 pub fn create_dylib_macho(
     custom_names: &[String],
     target: Target,
 ) -> object::read::Result<Vec<u8>> {
-    let dummy_obj_file = tempfile::Builder::new()
-        .prefix("roc_lib")
-        .suffix(".o")
-        .tempfile()
-        .unwrap_or_else(|e| internal_error!("{}", e));
-    let tmp = tempfile::tempdir().unwrap_or_else(|e| internal_error!("{}", e));
-    let dummy_lib_file = tmp.path().to_path_buf().with_file_name("libapp.so");
-
-    let obj_target = BinaryFormat::MachO;
-    let obj_arch = match target.architecture() {
-        roc_target::Architecture::X86_64 => Architecture::X86_64,
-        roc_target::Architecture::Aarch64 => Architecture::Aarch64,
-        _ => {
-            // We should have verified this via supported() before calling this function
-            unreachable!()
+    let (cpu_type, cpu_subtype, page_size, return_instruction, min_os): (_, _, _, &[u8], _) =
+        match target.architecture() {
+            roc_target::Architecture::X86_64 => (
+                macho::CPU_TYPE_X86_64,
+                macho::CPU_SUBTYPE_X86_64_ALL,
+                0x1000,
+                &[0xc3],
+                // 10.13
+                0x000a_0d00,
+            ),
+            roc_target::Architecture::Aarch64 => (
+                macho::CPU_TYPE_ARM64,
+                macho::CPU_SUBTYPE_ARM64_ALL,
+                0x4000,
+                &[0xc0, 0x03, 0x5f, 0xd6],
+                // 11.0
+                0x000b_0000,
+            ),
+            _ => {
+                // We should have verified this via supported() before calling this function
+                unreachable!()
+            }
+        };
+
+    // mach-o symbol names get a leading underscore
+    let symbol_names: Vec<Vec<u8>> = custom_names
+        .iter()
+        .map(|name| [b"_", name.as_bytes()].concat())
+        .collect();
+
+    let id_dylib_size = next_multiple_of(24 + INSTALL_NAME.len() + 1, 8);
+    let commands = [
+        SEGMENT_COMMAND_SIZE + SECTION_SIZE, // __TEXT
+        SEGMENT_COMMAND_SIZE,                // __LINKEDIT
+        id_dylib_size,                       // LC_ID_DYLIB
+        24,                                  // LC_SYMTAB
+        80,                                  // LC_DYSYMTAB
+        16,                                  // LC_DYLD_EXPORTS_TRIE
+        24,                                  // LC_BUILD_VERSION
+    ];
+    let sizeofcmds: usize = commands.iter().sum();
+
+    let text_offset = next_multiple_of(HEADER_SIZE + sizeofcmds, 16);
+    let text_size = return_instruction.len();
+    let text_segment_size = next_multiple_of(text_offset + text_size, page_size);
+
+    // __LINKEDIT: export trie, symbol table, string table
+    let linkedit_offset = text_segment_size;
+    let mut export_trie = export_trie(&symbol_names, text_offset as u64);
+    export_trie.resize(next_multiple_of(export_trie.len(), 8), 0);
+
+    let symtab_offset = linkedit_offset + export_trie.len();
+    let strtab_offset = symtab_offset + symbol_names.len() * NLIST_SIZE;
+
+    let mut strtab = vec![b' ', 0];
+    let mut symtab = Vec::with_capacity(symbol_names.len() * NLIST_SIZE);
+    for name in &symbol_names {
+        symtab.extend((strtab.len() as u32).to_le_bytes());
+        symtab.push(macho::N_SECT | macho::N_EXT);
+        symtab.push(1); // n_sect: __text
+        symtab.extend(0u16.to_le_bytes());
+        symtab.extend((text_offset as u64).to_le_bytes());
+
+        strtab.extend(name);
+        strtab.push(0);
+    }
+    strtab.resize(next_multiple_of(strtab.len(), 8), 0);
+
+    let linkedit_size = export_trie.len() + symtab.len() + strtab.len();
+
+    let mut out = Vec::with_capacity(linkedit_offset + linkedit_size);
+    let u32s = |out: &mut Vec<u8>, values: &[u32]| {
+        for value in values {
+            out.extend(value.to_le_bytes());
         }
     };
-    let mut out_object = write::Object::new(obj_target, obj_arch, Endianness::Little);
-
-    let text_section = out_object.section_id(write::StandardSection::Text);
-
-    for name in custom_names {
-        out_object.add_symbol(write::Symbol {
-            name: name.as_bytes().to_vec(),
-            value: 0,
-            size: 0,
-            kind: SymbolKind::Text,
-            scope: SymbolScope::Dynamic,
-            weak: false,
-            section: write::SymbolSection::Section(text_section),
-            flags: SymbolFlags::None,
-        });
+    let segment_name = |out: &mut Vec<u8>, name: &[u8]| {
+        let mut bytes = [0; 16];
+        bytes[..name.len()].copy_from_slice(name);
+        out.extend(bytes);
+    };
+
+    u32s(
+        &mut out,
+        &[
+            macho::MH_MAGIC_64,
+            cpu_type,
+            cpu_subtype,
+            macho::MH_DYLIB,
+            commands.len() as u32,
+            sizeofcmds as u32,
+            macho::MH_NOUNDEFS | macho::MH_DYLDLINK | macho::MH_TWOLEVEL,
+            0,
+        ],
+    );
+
+    // __TEXT
+    u32s(
+        &mut out,
+        &[
+            macho::LC_SEGMENT_64,
+            (SEGMENT_COMMAND_SIZE + SECTION_SIZE) as u32,
+        ],
+    );
+    segment_name(&mut out, b"__TEXT");
+    for value in [0, text_segment_size, 0, text_segment_size] {
+        out.extend((value as u64).to_le_bytes());
     }
+    let read_execute = macho::VM_PROT_READ | macho::VM_PROT_EXECUTE;
+    u32s(&mut out, &[read_execute, read_execute, 1, 0]);
 
-    std::fs::write(
-        dummy_obj_file.path(),
-        out_object.write().expect("failed to build output object"),
-    )
-    .expect("failed to write object to file");
-
-    // This path only exists on macOS Big Sur, and it causes ld errors
-    // on Catalina if it's specified with -L, so we replace it with a
-    // redundant -lSystem if the directory isn't there.
-    let big_sur_path = "/Library/Developer/CommandLineTools/SDKs/MacOSX.sdk/usr/lib";
-    let big_sur_fix = if Path::new(big_sur_path).exists() {
-        "-L/Library/Developer/CommandLineTools/SDKs/MacOSX.sdk/usr/lib"
-    } else {
-        "-lSystem" // We say -lSystem twice in the case of non-Big-Sur OSes, but it's fine.
-    };
+    segment_name(&mut out, b"__text");
+    segment_name(&mut out, b"__TEXT");
+    out.extend((text_offset as u64).to_le_bytes());
+    out.extend((text_size as u64).to_le_bytes());
+    u32s(
+        &mut out,
+        &[
+            text_offset as u32,
+            4, // align 2^4
+            0,
+            0,
+            macho::S_REGULAR | macho::S_ATTR_PURE_INSTRUCTIONS | macho::S_ATTR_SOME_INSTRUCTIONS,
+            0,
+            0,
+            0,
+        ],
+    );
 
-    let ld_flag_soname = "-install_name";
-    let ld_prefix_args = [big_sur_fix, "-lSystem", "-dylib"];
-    let macos_version = get_macos_version();
-
-    let output = Command::new("ld")
-        .args(ld_prefix_args)
-        .args([
-            ld_flag_soname,
-            dummy_lib_file.file_name().unwrap().to_str().unwrap(),
-            dummy_obj_file.path().to_str().unwrap(),
-            "-o",
-            dummy_lib_file.to_str().unwrap(),
-            // Suppress fixup chains to ease working out dynamic relocs by the
-            // surgical linker. In my experience, working with dyld opcodes is
-            // slightly easier than unpacking compressed info from the __got section
-            // and fixups load command.
-            "-no_fixup_chains",
-            "-platform_version",
-            "macos",
-            &macos_version,
-            &macos_version,
-            // Suppress all warnings, at least for now. Ideally, there are no warnings
-            // from the linker.
-            "-w",
-        ])
-        .output()
-        .unwrap();
-
-    // Extend the lifetime of the tempfile so it doesn't get dropped
-    // (and thus deleted) before the linker process is done using it!
-    let _ = dummy_obj_file;
-
-    if !output.status.success() {
-        match std::str::from_utf8(&output.stderr) {
-            Ok(stderr) => panic!(
-                "Failed to link dummy shared library - stderr of the `ld` command was:\n{stderr}"
-            ),
-            Err(utf8_err) => panic!(
-                "Failed to link dummy shared library  - stderr of the `ld` command was invalid utf8 ({utf8_err:?})"
-            ),
-        }
+    // __LINKEDIT
+    u32s(
+        &mut out,
+        &[macho::LC_SEGMENT_64, SEGMENT_COMMAND_SIZE as u32],
+    );
+    segment_name(&mut out, b"__LINKEDIT");
+    for value in [
+        linkedit_offset,
+        next_multiple_of(linkedit_size, page_size),
+        linkedit_offset,
+        linkedit_size,
+    ] {
+        out.extend((value as u64).to_le_bytes());
     }
+    u32s(&mut out, &[macho::VM_PROT_READ, macho::VM_PROT_READ, 0, 0]);
+
+    // LC_ID_DYLIB
+    let id_dylib_start = out.len();
+    u32s(
+        &mut out,
+        &[
+            macho::LC_ID_DYLIB,
+            id_dylib_size as u32,
+            24,
+            2,
+            0x1_0000,
+            0x1_0000,
+        ],
+    );
+    out.extend(INSTALL_NAME);
+    out.resize(id_dylib_start + id_dylib_size, 0);
+
+    u32s(
+        &mut out,
+        &[
+            macho::LC_SYMTAB,
+            24,
+            symtab_offset as u32,
+            symbol_names.len() as u32,
+            strtab_offset as u32,
+            strtab.len() as u32,
+        ],
+    );
+
+    // all symbols are external definitions
+    let mut dysymtab = [0u32; 20];
+    dysymtab[0] = macho::LC_DYSYMTAB;
+    dysymtab[1] = 80;
+    dysymtab[5] = symbol_names.len() as u32; // nextdefsym
+    dysymtab[6] = symbol_names.len() as u32; // iundefsym
+    u32s(&mut out, &dysymtab);
+
+    u32s(
+        &mut out,
+        &[
+            macho::LC_DYLD_EXPORTS_TRIE,
+            16,
+            linkedit_offset as u32,
+            export_trie.len() as u32,
+        ],
+    );
+
+    u32s(
+        &mut out,
+        &[
+            macho::LC_BUILD_VERSION,
+            24,
+            macho::PLATFORM_MACOS,
+            min_os,
+            min_os,
+            0,
+        ],
+    );
+
+    debug_assert_eq!(out.len(), HEADER_SIZE + sizeofcmds);
 
-    Ok(std::fs::read(dummy_lib_file).expect("Failed to load dummy library"))
+    out.resize(text_offset, 0);
+    out.extend(return_instruction);
+    out.resize(linkedit_offset, 0);
+
+    out.extend(export_trie);
+    out.extend(symtab);
+    out.extend(strtab);
+
+    Ok(out)
 }
 
-fn get_macos_version() -> String {
-    let mut cmd = Command::new("sw_vers");
-    cmd.arg("-productVersion");
+/// The export trie that `ld` and `dyld` use to look up symbols. Every name is exported at the
+/// same address. Child offsets are written as fixed-width ULEB128s, so that node sizes do not
+/// depend on the layout.
+fn export_trie(names: &[Vec<u8>], address: u64) -> Vec<u8> {
+    const OFFSET_WIDTH: usize = 5;
+
+    #[derive(Default)]
+    struct Node {
+        terminal: bool,
+        children: Vec<(u8, usize)>,
+    }
+
+    let mut nodes = vec![Node::default()];
+    for name in names {
+        let mut current = 0;
+        for &byte in name {
+            current = match nodes[current].children.iter().find(|(b, _)| *b == byte) {
+                Some(&(_, child)) => child,
+                None => {
+                    nodes.push(Node::default());
+                    let child = nodes.len() - 1;
+                    nodes[current].children.push((byte, child));
+                    child
+                }
+            };
+        }
+        nodes[current].terminal = true;
+    }
+
+    struct Edge {
+        label: Vec<u8>,
+        child: usize,
+    }
 
-    let cmd_stdout = cmd
-        .output()
-        .expect("Failed to execute command 'sw_vers -productVersion'")
-        .stdout;
+    // collapse chains of nodes with a single child into one edge
+    let mut emitted: Vec<(bool, Vec<Edge>)> = vec![(nodes[0].terminal, vec![])];
+    let mut worklist = vec![(0, 0)];
+    while let Some((node, index)) = worklist.pop() {
+        for &(byte, mut child) in &nodes[node].children {
+            let mut label = vec![byte];
+            while !nodes[child].terminal && nodes[child].children.len() == 1 {
+                let (next_byte, next_child) = nodes[child].children[0];
+                label.push(next_byte);
+                child = next_child;
+            }
 
-    let full_version_string = String::from_utf8(cmd_stdout)
-        .expect("Failed to convert output of command 'sw_vers -productVersion' into a utf8 string");
+            emitted.push((nodes[child].terminal, vec![]));
+            let child_index = emitted.len() - 1;
+            emitted[index].1.push(Edge {
+                label,
+                child: child_index,
+            });
+            worklist.push((child, child_index));
+        }
+    }
 
-    full_version_string
-        .trim_end()
-        .split('.')
-        .take(3)
-        .collect::<Vec<&str>>()
-        .join(".")
+    let mut terminal_info = Vec::new();
+    uleb128(
+        &mut terminal_info,
+        macho::EXPORT_SYMBOL_FLAGS_KIND_REGULAR as u64,
+    );
+    uleb128(&mut terminal_info, address);
+
+    let node_size = |(terminal, edges): &(bool, Vec<Edge>)| {
+        let terminal_size = if *terminal {
+            1 + terminal_info.len()
+        } else {
+            1
+        };
+        let edges_size: usize = edges
+            .iter()
+            .map(|edge| edge.label.len() + 1 + OFFSET_WIDTH)
+            .sum();
+
+        terminal_size + 1 + edges_size
+    };
+
+    let mut offsets = Vec::with_capacity(emitted.len());
+    let mut offset = 0;
+    for node in &emitted {
+        offsets.push(offset);
+        offset += node_size(node);
+    }
+
+    let mut out = Vec::with_capacity(offset);
+    for (terminal, edges) in &emitted {
+        if *terminal {
+            out.push(terminal_info.len() as u8);
+            out.extend(&terminal_info);
+        } else {
+            out.push(0);
+        }
+
+        out.push(edges.len() as u8);
+        for edge in edges {
+            out.extend(&edge.label);
+            out.push(0);
+
+            let child_offset = offsets[edge.child] as u64;
+            for i in 0..OFFSET_WIDTH {
+                let byte = ((child_offset >> (7 * i)) & 0x7f) as u8;
+                let more = if i + 1 < OFFSET_WIDTH { 0x80 } else { 0 };
+                out.push(byte | more);
+            }
+        }
+    }
+
+    out
+}
+
+fn uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
 }
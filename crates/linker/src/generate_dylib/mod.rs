@@ -57,6 +57,21 @@ mod tests {
         check_exports(&target);
     }
 
+    #[test]
+    fn check_exports_macho() {
+        let custom_names = ["foo".to_string(), "bar".to_string()];
+
+        for target in [Target::MacX64, Target::MacArm64] {
+            let bytes = generate(target, &custom_names).unwrap();
+            let object = object::File::parse(bytes.as_slice()).unwrap();
+
+            // mach-o symbol names have a leading underscore
+            let exports = object.exports().unwrap();
+            let names: Vec<_> = exports.iter().map(|e| e.name()).collect();
+            assert_eq!(names, [b"_foo".as_slice(), b"_bar"]);
+        }
+    }
+
     #[test]
     fn check_exports_coff() {
         // NOTE: this does not work
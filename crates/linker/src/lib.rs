@@ -13,6 +13,7 @@ use std::cmp::Ordering;
 use std::mem;
 use std::path::{Path, PathBuf};
 
+mod code_signature;
 mod elf;
 mod macho;
 mod pe;
@@ -40,9 +41,8 @@ pub fn support_level(link_type: LinkType, target: Target) -> SupportLevel {
         match target {
             Target::LinuxX64 => SupportLevel::Full,
             Target::WinX64 => SupportLevel::Full,
-            // macho support is incomplete
-            Target::MacX64 => SupportLevel::None,
-            Target::MacArm64 => SupportLevel::Wip,
+            Target::MacX64 => SupportLevel::Full,
+            Target::MacArm64 => SupportLevel::Full,
             _ => SupportLevel::None,
         }
     } else {
@@ -259,6 +259,7 @@ fn object_matches_target<'a>(target: Target, object: &object::File<'a, &'a [u8]>
             let target_format = match target.operating_system() {
                 OperatingSystem::Linux => object::BinaryFormat::Elf,
                 OperatingSystem::Windows => object::BinaryFormat::Pe,
+                OperatingSystem::Mac => object::BinaryFormat::MachO,
                 _ => todo!("surgical linker does not support target {:?}", target),
             };
 
@@ -290,7 +291,11 @@ fn stub_lib_is_up_to_date(target: Target, stub_lib_path: &Path, custom_names: &[
     // we need all symbols to be there and in the correct order
     let dynamic_symbols: Vec<_> = object.exports().unwrap();
 
-    let it1 = dynamic_symbols.iter().map(|e| e.name());
+    // mach-o symbol names have a leading underscore
+    let it1 = dynamic_symbols.iter().map(|e| match object.format() {
+        object::BinaryFormat::MachO => e.name().strip_prefix(b"_").unwrap_or(e.name()),
+        _ => e.name(),
+    });
     let it2 = custom_names.iter().map(|s| s.as_bytes());
 
     it1.eq(it2)
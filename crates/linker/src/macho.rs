@@ -1,42 +1,58 @@
 use bincode::{deserialize_from, serialize_into};
 use iced_x86::{Decoder, DecoderOptions, Instruction, OpCodeOperandKind, OpKind};
 use memmap2::MmapMut;
-use object::macho;
+use object::macho::{self, LinkeditDataCommand, Section64, SegmentCommand64};
 use object::{
-    LittleEndian as LE, Object, ObjectSection, ObjectSymbol, RelocationFlags, RelocationKind,
-    RelocationTarget, Section, SectionIndex, SectionKind, Symbol, SymbolIndex, SymbolSection,
+    LittleEndian as LE, Object, ObjectSection, ObjectSymbol, RelocationFlags, RelocationTarget,
+    Section, SectionIndex, SectionKind, Symbol, SymbolIndex, SymbolSection,
 };
 use roc_collections::all::MutMap;
-use roc_error_macros::internal_error;
+use roc_error_macros::{internal_error, user_error};
 use roc_target::Architecture;
 use serde::{Deserialize, Serialize};
 use std::{
-    ffi::{c_char, CStr},
     io::{BufReader, BufWriter},
     mem,
     path::Path,
     time::Instant,
 };
 
+use crate::code_signature::AdHocSignature;
 use crate::util::{is_roc_definition, is_roc_undefined, report_timing};
 use crate::{
-    align_by_constraint, align_to_offset_by_constraint, load_struct_inplace,
-    load_struct_inplace_mut, load_structs_inplace, load_structs_inplace_mut, open_mmap,
-    open_mmap_mut,
+    align_by_constraint, load_struct_inplace, load_struct_inplace_mut, load_structs_inplace,
+    load_structs_inplace_mut, open_mmap, open_mmap_mut,
 };
 
-const MIN_SECTION_ALIGNMENT: usize = 0x40;
-
-// TODO: Analyze if this offset is always correct.
-const PLT_ADDRESS_OFFSET: u64 = 0x10;
-const STUB_ADDRESS_OFFSET: u64 = 0x06;
-
-// struct MachoDynamicDeps {
-//     got_app_syms: Vec<(String, usize)>,
-//     got_sections: Vec<(usize, usize)>,
-//     dynamic_lib_count: usize,
-//     shared_lib_index: usize,
-// }
+const SEGMENT_COMMAND_SIZE: usize = mem::size_of::<SegmentCommand64<LE>>();
+const SECTION_SIZE: usize = mem::size_of::<Section64<LE>>();
+
+// The segments that preprocessing reserves for the app. They go right before __LINKEDIT, which
+// has to stay the last segment of the file.
+const APP_TEXT_SEGMENT: &[u8] = b"__ROC_TEXT";
+const APP_TEXT_SECTIONS: [(&[u8], u32); 1] = [(
+    b"__text",
+    macho::S_REGULAR | macho::S_ATTR_PURE_INSTRUCTIONS | macho::S_ATTR_SOME_INSTRUCTIONS,
+)];
+const APP_DATA_SEGMENT: &[u8] = b"__ROC_DATA";
+const APP_DATA_SECTIONS: [(&[u8], u32); 3] = [
+    (b"__const", macho::S_REGULAR),
+    (b"__got", macho::S_REGULAR),
+    (b"__bss", macho::S_ZEROFILL),
+];
+
+// Chained fixups, see `mach-o/fixup-chains.h`. These are not in the object crate.
+const DYLD_CHAINED_PTR_64: u16 = 2;
+const DYLD_CHAINED_PTR_64_OFFSET: u16 = 6;
+const DYLD_CHAINED_PTR_START_NONE: u16 = 0xffff;
+const DYLD_CHAINED_IMPORT: u32 = 1;
+const DYLD_CHAINED_IMPORT_ADDEND: u32 = 2;
+const DYLD_CHAINED_IMPORT_ADDEND64: u32 = 3;
+const CHAINED_FIXUPS_HEADER_SIZE: usize = 28;
+/// The size of `dyld_chained_starts_in_segment`, without its `page_start` array
+const CHAINED_STARTS_IN_SEGMENT_SIZE: usize = 22;
+
+const ARM64_NOP: u32 = 0xd503_201f;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 enum VirtualOffset {
@@ -51,29 +67,25 @@ struct SurgeryEntry {
     size: u8,
 }
 
-// TODO: Reanalyze each piece of data in this struct.
-// I think a number of them can be combined to reduce string duplication.
-// Also I think a few of them aren't need.
-// For example, I think preprocessing can deal with all shifting and remove the need for added_byte_count.
 // TODO: we probably should be storing numbers in an endian neutral way.
 #[derive(Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
 struct Metadata {
     app_functions: Vec<String>,
     // offset followed by address.
     plt_addresses: MutMap<String, (u64, u64)>,
+    stub_size: u64,
     surgeries: MutMap<String, Vec<SurgeryEntry>>,
-    dynamic_symbol_indices: MutMap<String, u64>,
-    _static_symbol_indices: MutMap<String, u64>,
+    /// File offsets of the chained fixups that bind a pointer in the host to an app function
+    chained_binds: MutMap<String, Vec<u64>>,
     roc_symbol_vaddresses: MutMap<String, u64>,
     exec_len: u64,
     load_align_constraint: u64,
-    added_byte_count: u64,
-    last_vaddr: u64,
-    _dynamic_section_offset: u64,
-    _dynamic_symbol_table_section_offset: u64,
-    _symbol_table_section_offset: u64,
-    _symbol_table_size: u64,
-    macho_cmd_loc: u64,
+    /// Offsets of the segment commands that preprocessing reserved for the app
+    text_segment_cmd: u64,
+    data_segment_cmd: u64,
+    /// The dylib ordinal of libSystem, which provides whatever the app imports. 0 if the host
+    /// does not link it.
+    libsystem_ordinal: u32,
 }
 
 impl Metadata {
@@ -284,6 +296,328 @@ impl<'a> Surgeries<'a> {
     }
 }
 
+/// The offsets of the load commands that preprocessing and surgery care about.
+#[derive(Default, Debug)]
+struct LoadCommands {
+    /// The end of the load commands
+    end: usize,
+    segments: Vec<usize>,
+    linkedit: Option<usize>,
+    symtab: Option<usize>,
+    dysymtab: Option<usize>,
+    dyld_info: Option<usize>,
+    chained_fixups: Option<usize>,
+    code_signature: Option<usize>,
+    /// In the order of their dylib ordinals
+    dylibs: Vec<usize>,
+}
+
+impl LoadCommands {
+    fn parse(data: &[u8]) -> Self {
+        let header = load_struct_inplace::<macho::MachHeader64<LE>>(data, 0);
+        let mut commands = Self::default();
+        let mut offset = mem::size_of_val(header);
+
+        for _ in 0..header.ncmds.get(LE) {
+            let info = load_struct_inplace::<macho::LoadCommand<LE>>(data, offset);
+
+            match info.cmd.get(LE) {
+                macho::LC_SEGMENT_64 => {
+                    let segment = load_struct_inplace::<SegmentCommand64<LE>>(data, offset);
+                    if name_matches(&segment.segname, b"__LINKEDIT") {
+                        commands.linkedit = Some(offset);
+                    }
+                    commands.segments.push(offset);
+                }
+                macho::LC_SYMTAB => commands.symtab = Some(offset),
+                macho::LC_DYSYMTAB => commands.dysymtab = Some(offset),
+                macho::LC_DYLD_INFO | macho::LC_DYLD_INFO_ONLY => commands.dyld_info = Some(offset),
+                macho::LC_DYLD_CHAINED_FIXUPS => commands.chained_fixups = Some(offset),
+                macho::LC_CODE_SIGNATURE => commands.code_signature = Some(offset),
+                macho::LC_LOAD_DYLIB
+                | macho::LC_LOAD_WEAK_DYLIB
+                | macho::LC_REEXPORT_DYLIB
+                | macho::LC_LAZY_LOAD_DYLIB
+                | macho::LC_LOAD_UPWARD_DYLIB => commands.dylibs.push(offset),
+                _ => {}
+            }
+
+            offset += info.cmdsize.get(LE) as usize;
+        }
+
+        commands.end = offset;
+        commands
+    }
+
+    fn linkedit(&self) -> usize {
+        match self.linkedit {
+            Some(offset) => offset,
+            None => internal_error!("The host has no __LINKEDIT segment"),
+        }
+    }
+
+    fn segment<'a>(&self, data: &'a [u8], index: usize) -> &'a SegmentCommand64<LE> {
+        load_struct_inplace::<SegmentCommand64<LE>>(data, self.segments[index])
+    }
+
+    fn sections<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = &'a Section64<LE>> + 'a {
+        self.segments.iter().flat_map(move |offset| {
+            let segment = load_struct_inplace::<SegmentCommand64<LE>>(data, *offset);
+            load_structs_inplace::<Section64<LE>>(
+                data,
+                offset + SEGMENT_COMMAND_SIZE,
+                segment.nsects.get(LE) as usize,
+            )
+        })
+    }
+
+    /// The segment that maps the mach header, usually __TEXT
+    fn header_segment<'a>(&self, data: &'a [u8]) -> &'a SegmentCommand64<LE> {
+        (0..self.segments.len())
+            .map(|index| self.segment(data, index))
+            .find(|segment| segment.fileoff.get(LE) == 0 && segment.filesize.get(LE) > 0)
+            .unwrap_or_else(|| internal_error!("The host has no segment that maps its header"))
+    }
+
+    /// Chained fixups store addresses relative to the mach header.
+    fn image_base(&self, data: &[u8]) -> u64 {
+        self.header_segment(data).vmaddr.get(LE)
+    }
+
+    /// Load commands can grow until they hit the contents of the first section.
+    fn free_space(&self, data: &[u8]) -> usize {
+        let first_section = self
+            .sections(data)
+            .filter(|section| {
+                section.offset.get(LE) != 0
+                    && section.flags.get(LE) & macho::SECTION_TYPE != macho::S_ZEROFILL
+            })
+            .map(|section| section.offset.get(LE) as usize)
+            .min()
+            .unwrap_or(data.len());
+
+        first_section.saturating_sub(self.end)
+    }
+
+    fn dylib_name<'a>(&self, data: &'a [u8], ordinal: usize) -> &'a [u8] {
+        let offset = self.dylibs[ordinal - 1];
+        let cmd = load_struct_inplace::<macho::DylibCommand<LE>>(data, offset);
+        let start = offset + cmd.dylib.name.offset.get(LE) as usize;
+        let end = offset + cmd.cmdsize.get(LE) as usize;
+
+        c_string(&data[start..end])
+    }
+}
+
+fn c_string(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+fn name_matches(name: &[u8; 16], expected: &[u8]) -> bool {
+    c_string(name) == expected
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// The `LC_DYLD_CHAINED_FIXUPS` blob of a host. Instead of a separate table of relocations, every
+/// pointer that dyld has to fix up holds either a rebase or a bind, and the offset of the next
+/// such pointer on the same page.
+struct ChainedFixups<'a> {
+    blob: &'a [u8],
+    starts_offset: usize,
+    imports_offset: usize,
+    symbols_offset: usize,
+    imports_count: usize,
+    imports_format: u32,
+}
+
+impl<'a> ChainedFixups<'a> {
+    fn parse(blob: &'a [u8]) -> Self {
+        let fixups_version = read_u32(blob, 0);
+        if fixups_version != 0 {
+            internal_error!("Unsupported chained fixups version: {fixups_version}");
+        }
+
+        let imports_format = read_u32(blob, 20);
+        if !matches!(
+            imports_format,
+            DYLD_CHAINED_IMPORT | DYLD_CHAINED_IMPORT_ADDEND | DYLD_CHAINED_IMPORT_ADDEND64
+        ) {
+            internal_error!("Unsupported chained fixups import format: {imports_format}");
+        }
+
+        if read_u32(blob, 24) != 0 {
+            internal_error!("Compressed symbol names in chained fixups are not supported");
+        }
+
+        Self {
+            blob,
+            starts_offset: read_u32(blob, 4) as usize,
+            imports_offset: read_u32(blob, 8) as usize,
+            symbols_offset: read_u32(blob, 12) as usize,
+            imports_count: read_u32(blob, 16) as usize,
+            imports_format,
+        }
+    }
+
+    fn import_size(&self) -> usize {
+        match self.imports_format {
+            DYLD_CHAINED_IMPORT => 4,
+            DYLD_CHAINED_IMPORT_ADDEND => 8,
+            _ => 16,
+        }
+    }
+
+    /// The dylib ordinal and the symbol name of an import
+    fn import(&self, index: usize) -> (u32, &'a [u8]) {
+        let offset = self.imports_offset + index * self.import_size();
+        let (ordinal, name_offset) = match self.imports_format {
+            DYLD_CHAINED_IMPORT_ADDEND64 => {
+                let raw = read_u64(self.blob, offset);
+                ((raw & 0xffff) as u32, (raw >> 32) as usize)
+            }
+            _ => {
+                let raw = read_u32(self.blob, offset);
+                (raw & 0xff, (raw >> 9) as usize)
+            }
+        };
+
+        (
+            ordinal,
+            c_string(&self.blob[self.symbols_offset + name_offset..]),
+        )
+    }
+
+    fn segment_count(&self) -> usize {
+        read_u32(self.blob, self.starts_offset) as usize
+    }
+
+    /// The `dyld_chained_starts_in_segment` of a segment, if it has any fixups
+    fn segment_starts(&self, segment_index: usize) -> Option<&'a [u8]> {
+        if segment_index >= self.segment_count() {
+            return None;
+        }
+
+        let info_offset = read_u32(self.blob, self.starts_offset + 4 + 4 * segment_index);
+        if info_offset == 0 {
+            return None;
+        }
+
+        let start = self.starts_offset + info_offset as usize;
+        let size = read_u32(self.blob, start) as usize;
+        Some(&self.blob[start..start + size])
+    }
+
+    /// The page size and pointer format the host uses for its fixups
+    fn pointer_layout(&self) -> Option<(u16, u16)> {
+        (0..self.segment_count())
+            .find_map(|index| self.segment_starts(index))
+            .map(|starts| (read_u16(starts, 4), read_u16(starts, 6)))
+    }
+
+    /// Calls `f` with the file offset and the raw value of every fixup in the file
+    fn for_each_fixup(&self, data: &[u8], commands: &LoadCommands, mut f: impl FnMut(usize, u64)) {
+        for segment_index in 0..commands.segments.len() {
+            let starts = match self.segment_starts(segment_index) {
+                Some(starts) => starts,
+                None => continue,
+            };
+            let segment = commands.segment(data, segment_index);
+
+            let page_size = read_u16(starts, 4) as usize;
+            let pointer_format = read_u16(starts, 6);
+            if !matches!(
+                pointer_format,
+                DYLD_CHAINED_PTR_64 | DYLD_CHAINED_PTR_64_OFFSET
+            ) {
+                internal_error!("Unsupported chained fixups pointer format: {pointer_format}");
+            }
+
+            let page_count = read_u16(starts, 20) as usize;
+            for page in 0..page_count {
+                let page_start = read_u16(starts, CHAINED_STARTS_IN_SEGMENT_SIZE + 2 * page);
+                if page_start == DYLD_CHAINED_PTR_START_NONE {
+                    continue;
+                }
+
+                let mut offset =
+                    segment.fileoff.get(LE) as usize + page * page_size + page_start as usize;
+                loop {
+                    let raw = read_u64(data, offset);
+                    f(offset, raw);
+
+                    let next = ((raw >> 51) & 0xfff) as usize;
+                    if next == 0 {
+                        break;
+                    }
+                    offset += next * 4;
+                }
+            }
+        }
+    }
+}
+
+/// The part of __LINKEDIT that a `linkedit_data_command` refers to
+fn linkedit_data(data: &[u8], command_offset: usize) -> &[u8] {
+    let cmd = load_struct_inplace::<LinkeditDataCommand<LE>>(data, command_offset);
+    let start = cmd.dataoff.get(LE) as usize;
+
+    &data[start..start + cmd.datasize.get(LE) as usize]
+}
+
+fn is_chained_bind(raw: u64) -> bool {
+    raw >> 63 == 1
+}
+
+fn chained_next(raw: u64) -> u64 {
+    (raw >> 51) & 0xfff
+}
+
+fn chained_rebase(pointer_format: u16, image_base: u64, target: u64, next: u64) -> u64 {
+    let target = match pointer_format {
+        DYLD_CHAINED_PTR_64_OFFSET => target - image_base,
+        _ => target,
+    };
+
+    if target & 0x00ff_fff0_0000_0000 != 0 {
+        internal_error!("Address {target:+x} does not fit in a chained fixup");
+    }
+
+    let high8 = target >> 56;
+    (target & 0xf_ffff_ffff) | (high8 << 36) | (next << 51)
+}
+
+fn chained_bind(import: usize, next: u64) -> u64 {
+    if import >= 1 << 24 {
+        internal_error!("Too many imports for chained fixups");
+    }
+
+    (1 << 63) | (next << 51) | import as u64
+}
+
 /// Constructs a `Metadata` from a host executable binary, and writes it to disk
 pub(crate) fn preprocess_macho_le(
     arch: Architecture,
@@ -316,169 +650,65 @@ pub(crate) fn preprocess_macho_le(
         );
     }
 
+    let commands = LoadCommands::parse(exec_data);
+
     let exec_parsing_duration = exec_parsing_start.elapsed();
 
-    // PLT stands for Procedure Linkage Table which is, put simply, used to call external
-    // procedures/functions whose address isn't known in the time of linking, and is left
-    // to be resolved by the dynamic linker at run time.
+    // __stubs is the Mach-O version of the PLT: the host calls the app's functions through small
+    // stubs that jump to whatever address dyld put in the matching __got slot.
     let symbol_and_plt_processing_start = Instant::now();
-    let plt_section_name = "__stubs";
-
-    let (plt_address, plt_offset) = match exec_obj.section_by_name(plt_section_name) {
-        Some(section) => {
-            let file_offset = if let Some((file_offset, _)) = section.file_range() {
-                file_offset
-            } else {
-                internal_error!("Could not get file range for {section:+x?}");
-            };
-            (section.address(), file_offset)
-        }
-        None => {
-            internal_error!("Failed to find PLT section. Probably an malformed executable.");
-        }
-    };
-    if verbose {
-        println!("PLT Address: {plt_address:+x}");
-        println!("PLT File Offset: {plt_offset:+x}");
-    }
 
     let app_syms: Vec<_> = exec_obj.symbols().filter(is_roc_undefined).collect();
 
-    let mut app_func_addresses: MutMap<u64, &str> = MutMap::default();
-    let mut macho_load_so_offset = None;
-
-    {
-        use macho::{DyldInfoCommand, DylibCommand, Section64, SegmentCommand64};
-
-        let exec_header = load_struct_inplace::<macho::MachHeader64<LE>>(exec_data, 0);
-        let num_load_cmds = exec_header.ncmds.get(LE);
-
-        let mut offset = mem::size_of_val(exec_header);
-
-        let mut stubs_symbol_index = None;
-        let mut stubs_symbol_count = None;
-
-        'cmds: for _ in 0..num_load_cmds {
-            let info = load_struct_inplace::<macho::LoadCommand<LE>>(exec_data, offset);
-            let cmd = info.cmd.get(LE);
-            let cmdsize = info.cmdsize.get(LE);
-
-            if cmd == macho::LC_SEGMENT_64 {
-                let info = load_struct_inplace::<SegmentCommand64<LE>>(exec_data, offset);
+    let shared_lib_filename = shared_lib.file_name();
+    let app_ordinal = (1..=commands.dylibs.len())
+        .find(|ordinal| {
+            let name = commands.dylib_name(exec_data, *ordinal);
+            std::str::from_utf8(name)
+                .map(|name| Path::new(name).file_name() == shared_lib_filename)
+                .unwrap_or(false)
+        })
+        .unwrap_or_else(|| {
+            internal_error!("Host does not link library `{}`!", shared_lib.display())
+        });
 
-                if &info.segname[0..6] == b"__TEXT" {
-                    let sections = info.nsects.get(LE);
+    md.libsystem_ordinal = (1..=commands.dylibs.len())
+        .find(|ordinal| {
+            commands
+                .dylib_name(exec_data, *ordinal)
+                .ends_with(b"/libSystem.B.dylib")
+        })
+        .unwrap_or(0) as u32;
 
-                    let sections_info = load_structs_inplace::<Section64<LE>>(
-                        exec_data,
-                        offset + mem::size_of_val(info),
-                        sections as usize,
-                    );
+    let app_func_addresses = find_app_stubs(exec_data, &exec_obj, &commands, &app_syms, &mut md);
 
-                    for section_info in sections_info {
-                        if &section_info.sectname[0..7] == b"__stubs" {
-                            stubs_symbol_index = Some(section_info.reserved1.get(LE));
-                            stubs_symbol_count =
-                                Some(section_info.size.get(LE) / STUB_ADDRESS_OFFSET);
+    if let Some(chained_fixups) = commands.chained_fixups {
+        let fixups = ChainedFixups::parse(linkedit_data(exec_data, chained_fixups));
 
-                            break 'cmds;
-                        }
-                    }
-                }
+        let mut app_imports = MutMap::default();
+        for index in 0..fixups.imports_count {
+            let (ordinal, name) = fixups.import(index);
+            if ordinal as usize == app_ordinal {
+                let name = String::from_utf8_lossy(name).to_string();
+                app_imports.insert(index as u64, name);
             }
-
-            offset += cmdsize as usize;
         }
 
-        let stubs_symbol_index = stubs_symbol_index.unwrap_or_else(|| {
-            panic!("Could not find stubs symbol index.");
-        });
-        let stubs_symbol_count = stubs_symbol_count.unwrap_or_else(|| {
-            panic!("Could not find stubs symbol count.");
-        });
-
-        // Reset offset before looping through load commands again
-        offset = mem::size_of_val(exec_header);
-
-        let shared_lib_filename = shared_lib.file_name();
-
-        for _ in 0..num_load_cmds {
-            let info = load_struct_inplace::<macho::LoadCommand<LE>>(exec_data, offset);
-            let cmd = info.cmd.get(LE);
-            let cmdsize = info.cmdsize.get(LE);
-
-            if cmd == macho::LC_DYLD_INFO_ONLY {
-                let info = load_struct_inplace::<DyldInfoCommand<LE>>(exec_data, offset);
-
-                let lazy_bind_offset = info.lazy_bind_off.get(LE) as usize;
-
-                let lazy_bind_symbols = mach_object::LazyBind::parse(
-                    &exec_data[lazy_bind_offset..],
-                    mem::size_of::<usize>(),
-                );
-
-                // Find all the lazily-bound roc symbols
-                // (e.g. "_roc__mainForHost_1_exposed")
-                // For Macho, we may need to deal with some GOT stuff here as well.
-                for (i, symbol) in lazy_bind_symbols
-                    .skip(stubs_symbol_index as usize)
-                    .take(stubs_symbol_count as usize)
-                    .enumerate()
-                {
-                    if let Some(sym) = app_syms
-                        .iter()
-                        .find(|app_sym| app_sym.name() == Ok(&symbol.name))
-                    {
-                        let func_address = (i as u64 + 1) * STUB_ADDRESS_OFFSET + plt_address;
-                        let func_offset = (i as u64 + 1) * STUB_ADDRESS_OFFSET + plt_offset;
-                        app_func_addresses.insert(func_address, sym.name().unwrap());
-                        md.plt_addresses
-                            .insert(sym.name().unwrap().to_string(), (func_offset, func_address));
-                    }
-                }
-            } else if cmd == macho::LC_LOAD_DYLIB {
-                let info = load_struct_inplace::<DylibCommand<LE>>(exec_data, offset);
-                let name_offset = info.dylib.name.offset.get(LE) as usize;
-                let str_start_index = offset + name_offset;
-                let str_end_index = offset + cmdsize as usize;
-                let str_bytes = &exec_data[str_start_index..str_end_index];
-                let path = {
-                    if str_bytes[str_bytes.len() - 1] == 0 {
-                        // If it's nul-terminated, it's a C String.
-                        // Use the unchecked version because these are
-                        // padded with 0s at the end, so since we don't
-                        // know the exact length, using the checked version
-                        // of this can fail due to the interior nul bytes.
-                        //
-                        // Also, we have to use from_ptr instead of
-                        // from_bytes_with_nul_unchecked because currently
-                        // std::ffi::CStr is actually not a char* under
-                        // the hood (!) but rather an array, so to strip
-                        // the trailing null bytes we have to use from_ptr.
-                        let c_str = unsafe { CStr::from_ptr(str_bytes.as_ptr() as *const c_char) };
-
-                        Path::new(c_str.to_str().unwrap())
-                    } else {
-                        // It wasn't nul-terminated, so treat all the bytes
-                        // as the string
-
-                        Path::new(std::str::from_utf8(str_bytes).unwrap())
-                    }
-                };
-
-                if path.file_name() == shared_lib_filename {
-                    macho_load_so_offset = Some(offset);
+        fixups.for_each_fixup(exec_data, &commands, |offset, raw| {
+            if is_chained_bind(raw) {
+                if let Some(name) = app_imports.get(&(raw & 0xff_ffff)) {
+                    md.chained_binds
+                        .entry(name.clone())
+                        .or_default()
+                        .push(offset as u64);
                 }
             }
-
-            offset += cmdsize as usize;
-        }
+        });
     }
 
     for sym in app_syms.iter() {
         let name = sym.name().unwrap().to_string();
-        md.app_functions.push(name.clone());
-        md.dynamic_symbol_indices.insert(name, sym.index().0 as u64);
+        md.app_functions.push(name);
     }
     if verbose {
         println!();
@@ -489,48 +719,33 @@ pub(crate) fn preprocess_macho_le(
 
         println!();
         println!("App Function Address Map: {app_func_addresses:+x?}");
+        println!("Chained Binds to App Functions: {:+x?}", md.chained_binds);
     }
     let symbol_and_plt_processing_duration = symbol_and_plt_processing_start.elapsed();
 
     // look at the text (i.e. code) sections and see collect work needs to be done
     let text_disassembly_start = Instant::now();
 
-    let mut surgeries = Surgeries::new(&app_syms, app_func_addresses);
-    surgeries.append_text_sections(exec_data, &exec_obj, verbose);
-    md.surgeries = surgeries.surgeries;
+    // On aarch64 the stubs load their target from __got, which is all we need to patch.
+    if arch == Architecture::X86_64 {
+        let mut surgeries = Surgeries::new(&app_syms, app_func_addresses);
+        surgeries.append_text_sections(exec_data, &exec_obj, verbose);
+        md.surgeries = surgeries.surgeries;
+    }
 
     let text_disassembly_duration = text_disassembly_start.elapsed();
 
-    let scanning_dynamic_deps_start = Instant::now();
-
-    // let ElfDynamicDeps {
-    //     got_app_syms,
-    //     got_sections,
-    //     dynamic_lib_count,
-    //     shared_lib_index,
-    // } = scan_elf_dynamic_deps(
-    //     &exec_obj, &mut md, &app_syms, shared_lib, exec_data, verbose,
-    // );
-
-    let scanning_dynamic_deps_duration = scanning_dynamic_deps_start.elapsed();
-
     let platform_gen_start = Instant::now();
 
-    // TODO little endian
-    let macho_load_so_offset = match macho_load_so_offset {
-        Some(offset) => offset,
-        None => {
-            internal_error!("Host does not link library `{}`!", shared_lib.display());
-        }
-    };
-
     md.load_align_constraint = page_size(arch);
 
+    let app_dylib_cmd = commands.dylibs[app_ordinal - 1];
     let out_mmap = gen_macho_le(
         exec_data,
         &mut md,
+        &commands,
         preprocessed_path,
-        macho_load_so_offset,
+        app_dylib_cmd,
         verbose,
     );
 
@@ -564,7 +779,6 @@ pub(crate) fn preprocess_macho_le(
             symbol_and_plt_processing_duration,
         );
         report_timing("Text Disassembly", text_disassembly_duration);
-        report_timing("Scanning Dynamic Deps", scanning_dynamic_deps_duration);
         report_timing("Generate Modified Platform", platform_gen_duration);
         report_timing("Saving Metadata", saving_metadata_duration);
         report_timing("Flushing Data to Disk", flushing_data_duration);
@@ -574,7 +788,6 @@ pub(crate) fn preprocess_macho_le(
                 - exec_parsing_duration
                 - symbol_and_plt_processing_duration
                 - text_disassembly_duration
-                - scanning_dynamic_deps_duration
                 - platform_gen_duration
                 - saving_metadata_duration
                 - flushing_data_duration,
@@ -583,12 +796,68 @@ pub(crate) fn preprocess_macho_le(
     }
 }
 
+/// Finds the stubs through which the host calls app functions, using the indirect symbol table
+/// that maps every stub to the symbol it jumps to.
+fn find_app_stubs<'a>(
+    exec_data: &[u8],
+    exec_obj: &object::File<'a, &'a [u8]>,
+    commands: &LoadCommands,
+    app_syms: &[Symbol<'a, '_>],
+    md: &mut Metadata,
+) -> MutMap<u64, &'a str> {
+    let mut app_func_addresses = MutMap::default();
+
+    let dysymtab = match commands.dysymtab {
+        Some(offset) => load_struct_inplace::<macho::DysymtabCommand<LE>>(exec_data, offset),
+        None => internal_error!("The host has no dynamic symbol table"),
+    };
+    let indirect_symbols = load_structs_inplace::<object::U32<LE>>(
+        exec_data,
+        dysymtab.indirectsymoff.get(LE) as usize,
+        dysymtab.nindirectsyms.get(LE) as usize,
+    );
+
+    for section in commands.sections(exec_data) {
+        if section.flags.get(LE) & macho::SECTION_TYPE != macho::S_SYMBOL_STUBS {
+            continue;
+        }
+
+        let first_symbol = section.reserved1.get(LE) as usize;
+        let stub_size = section.reserved2.get(LE) as u64;
+        let stub_count = section.size.get(LE) / stub_size;
+        md.stub_size = stub_size;
+
+        for i in 0..stub_count {
+            let symbol_index = indirect_symbols[first_symbol + i as usize].get(LE);
+            if symbol_index & (macho::INDIRECT_SYMBOL_LOCAL | macho::INDIRECT_SYMBOL_ABS) != 0 {
+                continue;
+            }
+
+            let name = match exec_obj.symbol_by_index(SymbolIndex(symbol_index as usize)) {
+                Ok(symbol) => symbol.name().unwrap_or_default(),
+                Err(_) => continue,
+            };
+
+            if let Some(sym) = app_syms.iter().find(|sym| sym.name() == Ok(name)) {
+                let func_address = section.addr.get(LE) + i * stub_size;
+                let func_offset = section.offset.get(LE) as u64 + i * stub_size;
+                app_func_addresses.insert(func_address, sym.name().unwrap());
+                md.plt_addresses
+                    .insert(name.to_string(), (func_offset, func_address));
+            }
+        }
+    }
+
+    app_func_addresses
+}
+
 fn gen_macho_le(
     exec_data: &[u8],
     md: &mut Metadata,
+    commands: &LoadCommands,
     out_filename: &Path,
-    macho_load_so_offset: usize,
-    _verbose: bool,
+    app_dylib_cmd: usize,
+    verbose: bool,
 ) -> MmapMut {
     // Just adding some extra context/useful info here.
     // I was talking to Jakub from the Zig team about macho linking and here are some useful comments:
@@ -600,503 +869,171 @@ fn gen_macho_le(
     // 3) Jakub wants to make apple tooling absolute is working on zignature for code signing and zig-deploy for ios apps
     // https://github.com/kubkon/zignature
     // https://github.com/kubkon/zig-deploy
+    //
+    // Shifting the contents of the host breaks every pc-relative reference between its segments,
+    // so the host stays where it is. Instead, the load commands for the app's segments go into the
+    // padding between the load commands and the first section, and the app itself goes where
+    // __LINKEDIT is now. Surgery moves __LINKEDIT behind it.
+
+    let new_segments_size = 2 * SEGMENT_COMMAND_SIZE
+        + (APP_TEXT_SECTIONS.len() + APP_DATA_SECTIONS.len()) * SECTION_SIZE;
+    let code_signature_size = match commands.code_signature {
+        Some(_) => 0,
+        None => mem::size_of::<LinkeditDataCommand<LE>>(),
+    };
+    let added_bytes = new_segments_size + code_signature_size;
 
-    use macho::{Section64, SegmentCommand64};
-
-    let exec_header = load_struct_inplace::<macho::MachHeader64<LE>>(exec_data, 0);
-    let num_load_cmds = exec_header.ncmds.get(LE);
-    let size_of_cmds = exec_header.sizeofcmds.get(LE) as usize;
-
-    // Add a new text segment and data segment
-    let segment_cmd_size = mem::size_of::<SegmentCommand64<LE>>();
-    let section_size = mem::size_of::<Section64<LE>>();
-
-    // We need the full command size, including the dynamic-length string at the end.
-    // To get that, we need to load the command.
-    let info = load_struct_inplace::<macho::LoadCommand<LE>>(exec_data, macho_load_so_offset);
-    let total_cmd_size = info.cmdsize.get(LE) as usize;
-
-    // ======================== Important TODO ==========================
-    // TODO: we accidentally instroduced a big change here.
-    // We use a mix of added_bytes and md.added_byte_count.
-    // Theses should proabably be the same variable.
-    // Also, I just realized that I have been shifting a lot of virtual offsets.
-    // This should not be necessary. If we add a fully 4k of buffer to the file, all of the virtual offsets will still stay aligned.
-    // So a lot of the following work can probably be commented out if we fix that.
-    // Of coruse, it will cost about 4k bytes to a final executable.
-    // This is what the elf version currently does.
-    // Theoretically it is not needed (if we update all virtual addresses in the binary),
-    // but I definitely ran into problems with elf when not adding this extra buffering.
-
-    // Copy header and shift everything to enable more program sections.
-    let added_byte_count = ((2 * segment_cmd_size) + (2 * section_size) - total_cmd_size) as u64;
-    md.added_byte_count = added_byte_count
-        // add some alignment padding
-        + (MIN_SECTION_ALIGNMENT as u64 - added_byte_count % MIN_SECTION_ALIGNMENT as u64);
-
-    md.exec_len = exec_data.len() as u64 + md.added_byte_count;
-
-    let mut out_mmap = open_mmap_mut(out_filename, md.exec_len as usize);
-    let end_of_cmds = size_of_cmds + mem::size_of_val(exec_header);
-
-    // "Delete" the dylib load command - by copying all the bytes before it
-    // and all the bytes after it, while skipping over its bytes.
-    // It has a dynamic-length string at the end that we also need to delete,
-    // in addition to the header.
-    out_mmap[..macho_load_so_offset].copy_from_slice(&exec_data[..macho_load_so_offset]);
-
-    out_mmap[macho_load_so_offset..end_of_cmds - total_cmd_size]
-        .copy_from_slice(&exec_data[macho_load_so_offset + total_cmd_size..end_of_cmds]);
-
-    // Copy the rest of the file, leaving a gap for the surgical linking to add our 2 commands
-    // (which happens after preprocessing), and some zero padding at the end for alignemnt.
-    // (It seems to cause bugs if that padding isn't there!)
-    let rest_of_data = &exec_data[end_of_cmds..];
-    let start_index = end_of_cmds + md.added_byte_count as usize;
-
-    out_mmap[start_index..start_index + rest_of_data.len()].copy_from_slice(rest_of_data);
+    let free_space = commands.free_space(exec_data);
+    if free_space < added_bytes {
+        user_error!(
+            "The host only has room for {free_space} more bytes of load commands, but the app needs {added_bytes}.\n\nTo make room, link the host with the `-headerpad_max_install_names` linker flag (e.g. `-Wl,-headerpad_max_install_names`)."
+        );
+    }
 
-    let out_header = load_struct_inplace_mut::<macho::MachHeader64<LE>>(&mut out_mmap, 0);
+    md.exec_len = exec_data.len() as u64;
 
-    // TODO: this needs to change to adding the 2 new commands when we are ready.
-    // -1 because we're deleting 1 load command and then NOT adding 2 new ones.
-    {
-        let added_bytes = -(total_cmd_size as isize); // TODO: Change when add the new sections.
-        out_header.ncmds.set(LE, num_load_cmds - 1);
-        out_header
-            .sizeofcmds
-            .set(LE, (size_of_cmds as isize + added_bytes) as u32);
-    }
-
-    // Go through every command and shift it by added_bytes if it's absolute, unless it's inside the command header
-    let mut offset = mem::size_of_val(exec_header);
-
-    // TODO: Add this back in the future when needed.
-    // let cpu_type = match target.architecture {
-    //     target_lexicon::Architecture::X86_64 => macho::CPU_TYPE_X86_64,
-    //     target_lexicon::Architecture::Aarch64(_) => macho::CPU_TYPE_ARM64,
-    //     _ => {
-    //         // We should have verified this via supported() before calling this function
-    //         unreachable!()
-    //     }
-    // };
-
-    // minus one because we "deleted" a load command
-    for _ in 0..(num_load_cmds - 1) {
-        let info = load_struct_inplace::<macho::LoadCommand<LE>>(&out_mmap, offset);
-        let cmd_size = info.cmdsize.get(LE) as usize;
+    let mut out_mmap = open_mmap_mut(out_filename, exec_data.len());
+    out_mmap.copy_from_slice(exec_data);
 
-        match info.cmd.get(LE) {
-            macho::LC_SEGMENT_64 => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::SegmentCommand64<LE>>(&mut out_mmap, offset);
+    let linkedit = commands.linkedit();
+    let shifted = |offset: usize| {
+        if offset >= linkedit {
+            offset + new_segments_size
+        } else {
+            offset
+        }
+    };
 
-                // Ignore page zero, it never moves.
-                if cmd.segname == "__PAGEZERO\0\0\0\0\0\0".as_bytes() || cmd.vmaddr.get(LE) == 0 {
-                    offset += cmd_size;
-                    continue;
-                }
+    out_mmap.copy_within(linkedit..commands.end, linkedit + new_segments_size);
 
-                let old_file_offest = cmd.fileoff.get(LE);
-                // The segment with file offset zero also includes the header.
-                // As such, its file offset does not change.
-                // Instead, its file size should be increased.
-                if old_file_offest > 0 {
-                    cmd.fileoff.set(LE, old_file_offest + md.added_byte_count);
-                    cmd.vmaddr.set(LE, cmd.vmaddr.get(LE) + md.added_byte_count);
-                } else {
-                    cmd.filesize
-                        .set(LE, cmd.filesize.get(LE) + md.added_byte_count);
-                    cmd.vmsize.set(LE, cmd.vmsize.get(LE) + md.added_byte_count);
-                }
+    md.text_segment_cmd = linkedit as u64;
+    write_segment_command(
+        &mut out_mmap,
+        linkedit,
+        APP_TEXT_SEGMENT,
+        &APP_TEXT_SECTIONS,
+        macho::VM_PROT_READ | macho::VM_PROT_EXECUTE,
+    );
 
-                // let num_sections = cmd.nsects.get(LE);
-                // let sections = load_structs_inplace_mut::<macho::Section64<LE >>(
-                //     &mut out_mmap,
-                //     offset + mem::size_of::<macho::SegmentCommand64<LE >>(),
-                //     num_sections as usize,
-                // );
-                // struct Relocation {
-                //     offset: u32,
-                //     num_relocations: u32,
-                // }
-
-                // let mut relocation_offsets = Vec::with_capacity(sections.len());
-
-                // for section in sections {
-                //     section.addr.set(
-                //         LE ,
-                //         section.addr.get(LE) + md.added_byte_count as u64,
-                //     );
-
-                //     // If offset is zero, don't update it.
-                //     // Zero is used for things like BSS that don't exist in the file.
-                //     let old_offset = section.offset.get(LE);
-                //     if old_offset > 0 {
-                //         section
-                //             .offset
-                //             .set(LE , old_offset + md.added_byte_count as u32);
-                //     }
-
-                //     if section.nreloc.get(LE) > 0 {
-                //         section.reloff.set(
-                //             LE ,
-                //             section.reloff.get(LE) + md.added_byte_count as u32,
-                //         );
-                //     }
-
-                //     relocation_offsets.push(Relocation {
-                //         offset: section.reloff.get(LE),
-                //         num_relocations: section.nreloc.get(LE),
-                //     });
-                // }
-
-                // TODO FIXME this is necessary for ARM, but seems to be broken. Skipping for now since we're just targeting x86
-                // for Relocation {
-                //     offset,
-                //     num_relocations,
-                // } in relocation_offsets
-                // {
-                //     let relos = load_structs_inplace_mut::<macho::Relocation<LE >>(
-                //         &mut out_mmap,
-                //         offset as usize,
-                //         num_relocations as usize,
-                //     );
-
-                //     // TODO this has never been tested, because scattered relocations only come up on ARM!
-                //     for relo in relos.iter_mut() {
-                //         if relo.r_scattered(LE , cpu_type) {
-                //             let mut scattered_info = relo.scattered_info(LE);
-
-                //             if !scattered_info.r_pcrel {
-                //                 scattered_info.r_value += md.added_byte_count as u32;
-
-                //                 let new_info = scattered_info.relocation(LE );
-
-                //                 relo.r_word0 = new_info.r_word0;
-                //                 relo.r_word1 = new_info.r_word1;
-                //             }
-                //         }
-                //     }
-                // }
-
-                // TODO this seems to be wrong and unnecessary, and should probably be deleted.
-                // offset += num_sections as usize * mem::size_of::<macho::Section64<LE >>();
-            }
-            macho::LC_SYMTAB => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::SymtabCommand<LE>>(&mut out_mmap, offset);
+    let data_segment_cmd = linkedit + SEGMENT_COMMAND_SIZE + APP_TEXT_SECTIONS.len() * SECTION_SIZE;
+    md.data_segment_cmd = data_segment_cmd as u64;
+    write_segment_command(
+        &mut out_mmap,
+        data_segment_cmd,
+        APP_DATA_SEGMENT,
+        &APP_DATA_SECTIONS,
+        macho::VM_PROT_READ | macho::VM_PROT_WRITE,
+    );
 
-                let sym_offset = cmd.symoff.get(LE);
-                let num_syms = cmd.nsyms.get(LE);
+    // Changing any byte of the file invalidates the signature, so surgery writes a new one. If
+    // the host had none, make room for it now.
+    if commands.code_signature.is_none() {
+        let cmd = load_struct_inplace_mut::<LinkeditDataCommand<LE>>(
+            &mut out_mmap,
+            commands.end + new_segments_size,
+        );
+        cmd.cmd.set(LE, macho::LC_CODE_SIGNATURE);
+        cmd.cmdsize.set(LE, code_signature_size as u32);
+        cmd.dataoff.set(LE, 0);
+        cmd.datasize.set(LE, 0);
+    }
 
-                if num_syms > 0 {
-                    cmd.symoff.set(LE, sym_offset + md.added_byte_count as u32);
-                }
+    // The app will be part of the executable, so there is no dylib to load at runtime. A weak
+    // dylib is allowed to be missing, and keeps the ordinals of all other dylibs intact.
+    let cmd =
+        load_struct_inplace_mut::<macho::DylibCommand<LE>>(&mut out_mmap, shifted(app_dylib_cmd));
+    cmd.cmd.set(LE, macho::LC_LOAD_WEAK_DYLIB);
 
-                if cmd.strsize.get(LE) > 0 {
-                    cmd.stroff
-                        .set(LE, cmd.stroff.get(LE) + md.added_byte_count as u32);
-                }
+    let out_header = load_struct_inplace_mut::<macho::MachHeader64<LE>>(&mut out_mmap, 0);
+    let added_commands = if commands.code_signature.is_none() {
+        3
+    } else {
+        2
+    };
+    out_header
+        .ncmds
+        .set(LE, out_header.ncmds.get(LE) + added_commands);
+    out_header
+        .sizeofcmds
+        .set(LE, out_header.sizeofcmds.get(LE) + added_bytes as u32);
 
-                let table = load_structs_inplace_mut::<macho::Nlist64<LE>>(
-                    &mut out_mmap,
-                    sym_offset as usize + md.added_byte_count as usize,
-                    num_syms as usize,
-                );
+    if verbose {
+        println!();
+        println!(
+            "Reserved {added_bytes} bytes of load commands for the app at {:+x}",
+            md.text_segment_cmd
+        );
+    }
 
-                for entry in table {
-                    let entry_type = entry.n_type & macho::N_TYPE;
-                    if entry_type == macho::N_ABS || entry_type == macho::N_SECT {
-                        entry
-                            .n_value
-                            .set(LE, entry.n_value.get(LE) + md.added_byte_count);
-                    }
-                }
-            }
-            macho::LC_DYSYMTAB => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::DysymtabCommand<LE>>(&mut out_mmap, offset);
+    out_mmap
+}
 
-                if cmd.ntoc.get(LE) > 0 {
-                    cmd.tocoff
-                        .set(LE, cmd.tocoff.get(LE) + md.added_byte_count as u32);
-                }
+fn write_segment_command(
+    data: &mut [u8],
+    offset: usize,
+    segname: &[u8],
+    sections: &[(&[u8], u32)],
+    protection: u32,
+) {
+    let cmdsize = SEGMENT_COMMAND_SIZE + sections.len() * SECTION_SIZE;
+    data[offset..offset + cmdsize].fill(0);
+
+    let mut name = [0; 16];
+    name[..segname.len()].copy_from_slice(segname);
+
+    let cmd = load_struct_inplace_mut::<SegmentCommand64<LE>>(data, offset);
+    cmd.cmd.set(LE, macho::LC_SEGMENT_64);
+    cmd.cmdsize.set(LE, cmdsize as u32);
+    cmd.segname = name;
+    cmd.maxprot.set(LE, protection);
+    cmd.initprot.set(LE, protection);
+    cmd.nsects.set(LE, sections.len() as u32);
+
+    let section_headers = load_structs_inplace_mut::<Section64<LE>>(
+        data,
+        offset + SEGMENT_COMMAND_SIZE,
+        sections.len(),
+    );
+    for (header, (sectname, flags)) in section_headers.iter_mut().zip(sections) {
+        header.sectname[..sectname.len()].copy_from_slice(sectname);
+        header.segname = name;
+        header.flags.set(LE, *flags);
+    }
+}
 
-                if cmd.nmodtab.get(LE) > 0 {
-                    cmd.modtaboff
-                        .set(LE, cmd.modtaboff.get(LE) + md.added_byte_count as u32);
-                }
+pub(crate) fn surgery_macho(
+    roc_app_bytes: &[u8],
+    metadata_path: &Path,
+    executable_path: &Path,
+    verbose: bool,
+    time: bool,
+) {
+    let app_obj = match object::File::parse(roc_app_bytes) {
+        Ok(obj) => obj,
+        Err(err) => {
+            internal_error!("Failed to parse application file: {}", err);
+        }
+    };
 
-                if cmd.nextrefsyms.get(LE) > 0 {
-                    cmd.extrefsymoff
-                        .set(LE, cmd.extrefsymoff.get(LE) + md.added_byte_count as u32);
-                }
+    let total_start = Instant::now();
 
-                if cmd.nindirectsyms.get(LE) > 0 {
-                    cmd.indirectsymoff
-                        .set(LE, cmd.indirectsymoff.get(LE) + md.added_byte_count as u32);
-                }
+    let loading_metadata_start = total_start;
+    let md = Metadata::read_from_file(metadata_path);
+    let loading_metadata_duration = loading_metadata_start.elapsed();
 
-                if cmd.nextrel.get(LE) > 0 {
-                    cmd.extreloff
-                        .set(LE, cmd.extreloff.get(LE) + md.added_byte_count as u32);
-                }
-
-                if cmd.nlocrel.get(LE) > 0 {
-                    cmd.locreloff
-                        .set(LE, cmd.locreloff.get(LE) + md.added_byte_count as u32);
-                }
-
-                // TODO maybe we need to update something else too - relocations maybe?
-                // I think this also has symbols that need to get moved around.
-                // Look at otool -I at least for the indirect symbols.
-            }
-            macho::LC_TWOLEVEL_HINTS => {
-                let cmd = load_struct_inplace_mut::<macho::TwolevelHintsCommand<LE>>(
-                    &mut out_mmap,
-                    offset,
-                );
-
-                if cmd.nhints.get(LE) > 0 {
-                    cmd.offset
-                        .set(LE, cmd.offset.get(LE) + md.added_byte_count as u32);
-                }
-            }
-            macho::LC_FUNCTION_STARTS => {
-                let cmd = load_struct_inplace_mut::<macho::LinkeditDataCommand<LE>>(
-                    &mut out_mmap,
-                    offset,
-                );
-
-                if cmd.datasize.get(LE) > 0 {
-                    cmd.dataoff
-                        .set(LE, cmd.dataoff.get(LE) + md.added_byte_count as u32);
-                    // TODO: This lists the start of every function. Which, of course, have moved.
-                    // That being said, to my understanding this section is optional and may just be debug information.
-                    // As such, updating it should not be required.
-                    // It will be more work to update due to being in "DWARF-style ULEB128" values.
-                }
-            }
-            macho::LC_DATA_IN_CODE => {
-                let (offset, size) = {
-                    let cmd = load_struct_inplace_mut::<macho::LinkeditDataCommand<LE>>(
-                        &mut out_mmap,
-                        offset,
-                    );
-
-                    if cmd.datasize.get(LE) > 0 {
-                        cmd.dataoff
-                            .set(LE, cmd.dataoff.get(LE) + md.added_byte_count as u32);
-                    }
-                    (cmd.dataoff.get(LE), cmd.datasize.get(LE))
-                };
-
-                // Update every data in code entry.
-                if size > 0 {
-                    let entry_size = mem::size_of::<macho::DataInCodeEntry<LE>>();
-                    let entries = load_structs_inplace_mut::<macho::DataInCodeEntry<LE>>(
-                        &mut out_mmap,
-                        offset as usize,
-                        size as usize / entry_size,
-                    );
-                    for entry in entries.iter_mut() {
-                        entry
-                            .offset
-                            .set(LE, entry.offset.get(LE) + md.added_byte_count as u32)
-                    }
-                }
-            }
-            macho::LC_CODE_SIGNATURE
-            | macho::LC_SEGMENT_SPLIT_INFO
-            | macho::LC_DYLIB_CODE_SIGN_DRS
-            | macho::LC_LINKER_OPTIMIZATION_HINT
-            | macho::LC_DYLD_EXPORTS_TRIE
-            | macho::LC_DYLD_CHAINED_FIXUPS => {
-                let cmd = load_struct_inplace_mut::<macho::LinkeditDataCommand<LE>>(
-                    &mut out_mmap,
-                    offset,
-                );
-
-                if cmd.datasize.get(LE) > 0 {
-                    cmd.dataoff
-                        .set(LE, cmd.dataoff.get(LE) + md.added_byte_count as u32);
-                }
-            }
-            macho::LC_ENCRYPTION_INFO_64 => {
-                let cmd = load_struct_inplace_mut::<macho::EncryptionInfoCommand64<LE>>(
-                    &mut out_mmap,
-                    offset,
-                );
-
-                if cmd.cryptsize.get(LE) > 0 {
-                    cmd.cryptoff
-                        .set(LE, cmd.cryptoff.get(LE) + md.added_byte_count as u32);
-                }
-            }
-            macho::LC_DYLD_INFO | macho::LC_DYLD_INFO_ONLY => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::DyldInfoCommand<LE>>(&mut out_mmap, offset);
-
-                if cmd.rebase_size.get(LE) > 0 {
-                    cmd.rebase_off
-                        .set(LE, cmd.rebase_off.get(LE) + md.added_byte_count as u32);
-                }
-
-                if cmd.bind_size.get(LE) > 0 {
-                    cmd.bind_off
-                        .set(LE, cmd.bind_off.get(LE) + md.added_byte_count as u32);
-                }
-
-                if cmd.weak_bind_size.get(LE) > 0 {
-                    cmd.weak_bind_off
-                        .set(LE, cmd.weak_bind_off.get(LE) + md.added_byte_count as u32);
-                }
-
-                if cmd.lazy_bind_size.get(LE) > 0 {
-                    cmd.lazy_bind_off
-                        .set(LE, cmd.lazy_bind_off.get(LE) + md.added_byte_count as u32);
-                }
-
-                // TODO: Parse and update the related tables here.
-                // It is possible we may just need to delete things that point to stuff that will be in the roc app.
-                // We also may just be able to ignore it (lazy bindings should never run).
-                // This definitely has a list of virtual address that need to be updated.
-                // Some of them definitely will point to the roc app and should probably be removed.
-                // Also `xcrun dyldinfo` is useful for debugging this.
-            }
-            macho::LC_SYMSEG => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::SymsegCommand<LE>>(&mut out_mmap, offset);
-
-                if cmd.size.get(LE) > 0 {
-                    cmd.offset
-                        .set(LE, cmd.offset.get(LE) + md.added_byte_count as u32);
-                }
-            }
-            macho::LC_MAIN => {
-                let cmd =
-                    load_struct_inplace_mut::<macho::EntryPointCommand<LE>>(&mut out_mmap, offset);
-
-                cmd.entryoff
-                    .set(LE, cmd.entryoff.get(LE) + md.added_byte_count);
-            }
-            macho::LC_NOTE => {
-                let cmd = load_struct_inplace_mut::<macho::NoteCommand<LE>>(&mut out_mmap, offset);
-
-                if cmd.size.get(LE) > 0 {
-                    cmd.offset.set(LE, cmd.offset.get(LE) + md.added_byte_count);
-                }
-            }
-            macho::LC_ID_DYLIB
-            | macho::LC_LOAD_WEAK_DYLIB
-            | macho::LC_LOAD_DYLIB
-            | macho::LC_REEXPORT_DYLIB
-            | macho::LC_SOURCE_VERSION
-            | macho::LC_IDENT
-            | macho::LC_LINKER_OPTION
-            | macho::LC_BUILD_VERSION
-            | macho::LC_VERSION_MIN_MACOSX
-            | macho::LC_VERSION_MIN_IPHONEOS
-            | macho::LC_VERSION_MIN_WATCHOS
-            | macho::LC_VERSION_MIN_TVOS
-            | macho::LC_UUID
-            | macho::LC_RPATH
-            | macho::LC_ID_DYLINKER
-            | macho::LC_LOAD_DYLINKER
-            | macho::LC_DYLD_ENVIRONMENT
-            | macho::LC_ROUTINES_64
-            | macho::LC_THREAD
-            | macho::LC_UNIXTHREAD
-            | macho::LC_PREBOUND_DYLIB
-            | macho::LC_SUB_FRAMEWORK
-            | macho::LC_SUB_CLIENT
-            | macho::LC_SUB_UMBRELLA
-            | macho::LC_SUB_LIBRARY => {
-                // These don't involve file offsets, so no change is needed for these.
-                // Just continue the loop!
-            }
-            macho::LC_PREBIND_CKSUM => {
-                // We don't *think* we need to change this, but
-                // maybe what we're doing will break checksums?
-            }
-            macho::LC_SEGMENT | macho::LC_ROUTINES | macho::LC_ENCRYPTION_INFO => {
-                // These are 32-bit and unsuppoted
-                unreachable!();
-            }
-            macho::LC_FVMFILE | macho::LC_IDFVMLIB | macho::LC_LOADFVMLIB => {
-                // These are obsolete and unsupported
-                unreachable!()
-            }
-            cmd => {
-                eprintln!(
-                    "- - - Unrecognized Mach-O command during linker preprocessing: 0x{cmd:x?}"
-                );
-                // panic!(
-                //     "Unrecognized Mach-O command during linker preprocessing: 0x{:x?}",
-                //     cmd
-                // );
-            }
-        }
-
-        offset += cmd_size;
-    }
-
-    // cmd_loc should be where the last offset ended
-    md.macho_cmd_loc = offset as u64;
-
-    out_mmap
-}
-
-// fn scan_macho_dynamic_deps(
-//     _exec_obj: &object::File,
-//     _md: &mut Metadata,
-//     _app_syms: &[Symbol],
-//     _shared_lib: &str,
-//     _exec_data: &[u8],
-//     _verbose: bool,
-// ) {
-//     // TODO
-// }
-
-pub(crate) fn surgery_macho(
-    roc_app_bytes: &[u8],
-    metadata_path: &Path,
-    executable_path: &Path,
-    verbose: bool,
-    time: bool,
-) {
-    let app_obj = match object::File::parse(roc_app_bytes) {
-        Ok(obj) => obj,
-        Err(err) => {
-            internal_error!("Failed to parse application file: {}", err);
-        }
-    };
-
-    let total_start = Instant::now();
-
-    let loading_metadata_start = total_start;
-    let md = Metadata::read_from_file(metadata_path);
-    let loading_metadata_duration = loading_metadata_start.elapsed();
-
-    let load_and_mmap_start = Instant::now();
-    let max_out_len = md.exec_len + roc_app_bytes.len() as u64 + md.load_align_constraint;
-    let mut exec_mmap = open_mmap_mut(executable_path, max_out_len as usize);
-    let load_and_mmap_duration = load_and_mmap_start.elapsed();
+    let load_and_mmap_start = Instant::now();
+    // The app's fixups and imports can not take up more room than its relocations and symbols,
+    // and the code signature needs 32 bytes for every page of the file.
+    let max_out_len = {
+        let len = md.exec_len + 2 * roc_app_bytes.len() as u64 + 4 * md.load_align_constraint;
+        len + len / 64 + md.load_align_constraint
+    };
+    let mut exec_mmap = open_mmap_mut(executable_path, max_out_len as usize);
+    let load_and_mmap_duration = load_and_mmap_start.elapsed();
 
     let out_gen_start = Instant::now();
-    let mut offset = 0;
 
-    surgery_macho_help(
-        metadata_path,
-        executable_path,
-        verbose,
-        time,
-        &md,
-        &mut exec_mmap,
-        &mut offset,
-        app_obj,
-    );
+    let out_len = surgery_macho_help(executable_path, verbose, &md, &mut exec_mmap, app_obj);
 
     let out_gen_duration = out_gen_start.elapsed();
     let flushing_data_start = Instant::now();
@@ -1108,6 +1045,13 @@ pub(crate) fn surgery_macho(
     // Also drop files to to ensure data is fully written here.
     drop(exec_mmap);
 
+    // The code signature has to be at the very end of the file.
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(executable_path)
+        .and_then(|file| file.set_len(out_len as u64))
+        .unwrap_or_else(|e| internal_error!("{}", e));
+
     let flushing_data_duration = flushing_data_start.elapsed();
 
     // Make sure the final executable has permision to execute.
@@ -1142,49 +1086,210 @@ pub(crate) fn surgery_macho(
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Where an app section ends up in the executable
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    /// `None` for zero-fill sections
+    file_offset: Option<usize>,
+    vmaddr: u64,
+    /// The address of the section in the app object
+    object_address: u64,
+}
+
+/// A pointer in the app's data segment that dyld has to fix up at load time. Offsets are
+/// relative to the start of the segment.
+#[derive(Debug)]
+enum Fixup {
+    Rebase { offset: u64, target: u64 },
+    Bind { offset: u64, import: usize },
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SegmentLayout {
+    file_offset: usize,
+    vmaddr: u64,
+    file_size: usize,
+    vm_size: u64,
+}
+
+impl SegmentLayout {
+    fn contains(&self, file_offset: usize) -> bool {
+        (self.file_offset..self.file_offset + self.file_size).contains(&file_offset)
+    }
+}
+
+/// Everything needed to resolve the relocations of the app
+struct AppLayout<'a, 'b> {
+    app_obj: &'b object::File<'a, &'a [u8]>,
+    md: &'b Metadata,
+    placements: MutMap<SectionIndex, Placement>,
+    /// Undefined symbols that neither the app nor the host provide. They are imported from
+    /// libSystem through a new __got slot, and called through a new stub.
+    imports: Vec<SymbolIndex>,
+    got: Vec<SymbolIndex>,
+    stubs_vmaddr: u64,
+    stub_size: u64,
+    got_vmaddr: u64,
+}
+
+impl AppLayout<'_, '_> {
+    fn symbol_name(&self, index: SymbolIndex) -> &str {
+        self.app_obj
+            .symbol_by_index(index)
+            .and_then(|sym| sym.name())
+            .unwrap_or_default()
+    }
+
+    fn is_import(&self, index: SymbolIndex) -> bool {
+        self.imports.contains(&index)
+    }
+
+    fn section_address(&self, index: SectionIndex, object_address: u64) -> u64 {
+        match self.placements.get(&index) {
+            Some(placement) => placement.vmaddr + object_address - placement.object_address,
+            None => internal_error!(
+                "Relocation refers to a section that is not part of the executable: {:?}",
+                self.app_obj
+                    .section_by_index(index)
+                    .and_then(|sec| sec.name())
+            ),
+        }
+    }
+
+    fn symbol_address(&self, index: SymbolIndex, verbose: bool) -> u64 {
+        let sym = match self.app_obj.symbol_by_index(index) {
+            Ok(sym) => sym,
+            Err(err) => internal_error!("Failed to load symbol {index:?}: {err}"),
+        };
+
+        if let SymbolSection::Section(section_index) = sym.section() {
+            let address = self.section_address(section_index, sym.address());
+            if verbose {
+                println!("\t\tRelocation targets symbol in app at: {address:+x}");
+            }
+            return address;
+        }
+
+        let name = sym.name().unwrap_or_default().trim_start_matches('_');
+        if let Some(address) = self.md.roc_symbol_vaddresses.get(name) {
+            if verbose {
+                println!("\t\tRelocation targets symbol in host: {name} @ {address:+x}");
+            }
+            return *address;
+        }
+
+        if let Some(import) = self.imports.iter().position(|i| *i == index) {
+            return self.stubs_vmaddr + import as u64 * self.stub_size;
+        }
+
+        internal_error!("Undefined Symbol in relocation: {:+x?}", sym)
+    }
+
+    fn got_slot(&self, index: SymbolIndex) -> u64 {
+        match self.got.iter().position(|i| *i == index) {
+            Some(slot) => self.got_vmaddr + 8 * slot as u64,
+            None => internal_error!("No __got slot for {}", self.symbol_name(index)),
+        }
+    }
+}
+
+fn write_u32_at(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64_at(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn is_got_relocation(arch: Architecture, r_type: u8) -> bool {
+    match arch {
+        Architecture::X86_64 => {
+            matches!(
+                r_type,
+                macho::X86_64_RELOC_GOT_LOAD | macho::X86_64_RELOC_GOT
+            )
+        }
+        _ => matches!(
+            r_type,
+            macho::ARM64_RELOC_GOT_LOAD_PAGE21
+                | macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12
+                | macho::ARM64_RELOC_POINTER_TO_GOT
+        ),
+    }
+}
+
+/// Lays out the app in the segments that preprocessing reserved, moves __LINKEDIT behind them,
+/// and signs the result. Returns the length of the final executable.
 fn surgery_macho_help(
-    _metadata_filename: &Path,
-    _out_filename: &Path,
+    executable_path: &Path,
     verbose: bool,
-    _time: bool,
     md: &Metadata,
     exec_mmap: &mut MmapMut,
-    offset_ref: &mut usize, // TODO return this instead of taking a mutable reference to it
     app_obj: object::File,
-) {
-    let mut offset = align_by_constraint(md.exec_len as usize, MIN_SECTION_ALIGNMENT);
-    // let new_rodata_section_offset = offset;
-
-    // Align physical and virtual address of new segment.
-    let mut virt_offset = align_to_offset_by_constraint(
-        md.last_vaddr as usize,
-        offset,
-        md.load_align_constraint as usize,
-    );
-    let new_rodata_section_vaddr = virt_offset;
-    if verbose {
-        println!();
-        println!("New Virtual Rodata Section Address: {new_rodata_section_vaddr:+x?}");
-    }
+) -> usize {
+    let commands = LoadCommands::parse(exec_mmap);
+    let header = load_struct_inplace::<macho::MachHeader64<LE>>(exec_mmap, 0);
+    let arch = match header.cputype.get(LE) {
+        macho::CPU_TYPE_X86_64 => Architecture::X86_64,
+        macho::CPU_TYPE_ARM64 => Architecture::Aarch64,
+        cputype => internal_error!("Unsupported Mach-O cpu type: {cputype:x}"),
+    };
+    let is_executable = header.filetype.get(LE) == macho::MH_EXECUTE;
+    let page_size = md.load_align_constraint as usize;
 
-    // First decide on sections locations and then recode every exact symbol locations.
+    let linkedit = commands.linkedit();
+    let linkedit_index = commands
+        .segments
+        .iter()
+        .position(|offset| *offset == linkedit)
+        .unwrap();
+    let (old_linkedit_offset, old_linkedit_size, old_linkedit_vmaddr) = {
+        let segment = load_struct_inplace::<SegmentCommand64<LE>>(exec_mmap, linkedit);
+        (
+            segment.fileoff.get(LE) as usize,
+            segment.filesize.get(LE) as usize,
+            segment.vmaddr.get(LE),
+        )
+    };
+
+    // The old code signature is replaced; everything else in __LINKEDIT is kept.
+    let old_signature = commands.code_signature.and_then(|offset| {
+        let cmd = load_struct_inplace::<LinkeditDataCommand<LE>>(exec_mmap, offset);
+        (cmd.datasize.get(LE) > 0).then_some(cmd.dataoff.get(LE) as usize)
+    });
+    let kept_linkedit_size = match old_signature {
+        Some(offset) if offset >= old_linkedit_offset => offset - old_linkedit_offset,
+        _ => old_linkedit_size,
+    };
+    let old_linkedit =
+        exec_mmap[old_linkedit_offset..old_linkedit_offset + kept_linkedit_size].to_vec();
+
+    // Copies of the host's dyld info, which we have to extend with the app's
+    let host_fixups_blob = commands
+        .chained_fixups
+        .map(|offset| linkedit_data(exec_mmap, offset).to_vec());
+    let host_dyld_info = commands.dyld_info.map(|offset| {
+        let cmd = load_struct_inplace::<macho::DyldInfoCommand<LE>>(exec_mmap, offset);
+        let opcodes = |offset: &object::U32<LE>, size: &object::U32<LE>| {
+            let offset = offset.get(LE) as usize;
+            exec_mmap[offset..offset + size.get(LE) as usize].to_vec()
+        };
 
-    // Copy sections and resolve their symbols/relocations.
-    let symbols = app_obj.symbols().collect::<Vec<Symbol>>();
-    let mut section_offset_map: MutMap<SectionIndex, (usize, usize)> = MutMap::default();
-    let mut symbol_vaddr_map: MutMap<SymbolIndex, usize> = MutMap::default();
-    let mut app_func_vaddr_map: MutMap<String, usize> = MutMap::default();
-    let mut app_func_size_map: MutMap<String, u64> = MutMap::default();
+        (
+            opcodes(&cmd.rebase_off, &cmd.rebase_size),
+            opcodes(&cmd.bind_off, &cmd.bind_size),
+        )
+    });
 
-    // TODO: In the future Roc may use a data section to store memoized toplevel thunks
-    // in development builds for caching the results of top-level constants
+    exec_mmap[old_linkedit_offset..].fill(0);
 
     let rodata_sections: Vec<Section> = app_obj
         .sections()
         .filter(|sec| match sec.kind() {
-            SectionKind::ReadOnlyData => sec.name().unwrap_or("") != "__eh_frame",
-            SectionKind::ReadOnlyString => true,
+            SectionKind::ReadOnlyData
+            | SectionKind::ReadOnlyDataWithRel
+            | SectionKind::ReadOnlyString
+            | SectionKind::Data => !matches!(sec.name(), Ok("__eh_frame" | "__compact_unwind")),
             _ => false,
         })
         .collect();
@@ -1207,362 +1312,267 @@ fn surgery_macho_help(
         println!();
         println!("Roc symbol addresses: {:+x?}", md.roc_symbol_vaddresses);
         println!("App functions: {:?}", md.app_functions);
-        println!("Dynamic symbol indices: {:+x?}", md.dynamic_symbol_indices);
         println!("PLT addresses: {:+x?}", md.plt_addresses);
         println!();
     }
 
-    // Calculate addresses and load symbols.
-    // Note, it is important the bss sections come after the rodata sections.
-    for sec in rodata_sections
-        .iter()
-        .chain(bss_sections.iter())
-        .chain(text_sections.iter())
-    {
-        offset = align_by_constraint(offset, MIN_SECTION_ALIGNMENT);
-        virt_offset =
-            align_to_offset_by_constraint(virt_offset, offset, md.load_align_constraint as usize);
-        if verbose {
-            println!(
-                "Section, {}, is being put at offset: {:+x}(virt: {:+x})",
-                sec.name().unwrap(),
-                offset,
-                virt_offset
-            );
-        }
-        section_offset_map.insert(sec.index(), (offset, virt_offset));
-        for sym in symbols.iter() {
-            if sym.section() == SymbolSection::Section(sec.index()) {
-                let name = sym.name().unwrap_or_default().to_string();
-                if !md.roc_symbol_vaddresses.contains_key(&name) {
-                    symbol_vaddr_map.insert(sym.index(), virt_offset + sym.address() as usize);
-                }
-                if md.app_functions.contains(&name) {
-                    app_func_vaddr_map.insert(name.clone(), virt_offset + sym.address() as usize);
-                    app_func_size_map.insert(name, sym.size());
-                }
+    // Find what the app needs from outside of the app and the host.
+    let mut imports = Vec::new();
+    let mut got = Vec::new();
+    for sec in text_sections.iter().chain(rodata_sections.iter()) {
+        for (_, rel) in sec.relocations() {
+            let (RelocationTarget::Symbol(index), RelocationFlags::MachO { r_type, .. }) =
+                (rel.target(), rel.flags())
+            else {
+                continue;
+            };
+
+            let sym = match app_obj.symbol_by_index(index) {
+                Ok(sym) => sym,
+                Err(err) => internal_error!("Failed to load symbol {index:?}: {err}"),
+            };
+            let name = sym.name().unwrap_or_default();
+            let is_import = sym.is_undefined()
+                && !md
+                    .roc_symbol_vaddresses
+                    .contains_key(name.trim_start_matches('_'));
+
+            if is_import && !imports.contains(&index) {
+                imports.push(index);
+            }
+            if (is_import || is_got_relocation(arch, r_type)) && !got.contains(&index) {
+                got.push(index);
             }
         }
-        let section_size = match sec.file_range() {
-            Some((_, size)) => size,
-            None => 0,
-        };
-        if sec.name().unwrap_or_default().starts_with("__bss") {
-            // bss sections only modify the virtual size.
-            virt_offset += sec.size() as usize;
-        } else if section_size != sec.size() {
-            internal_error!( "We do not deal with non bss sections that have different on disk and in memory sizes");
-        } else {
-            offset += section_size as usize;
-            virt_offset += sec.size() as usize;
+    }
+
+    if !imports.is_empty() && md.libsystem_ordinal == 0 {
+        internal_error!("The app imports functions from libSystem, but the host does not link it");
+    }
+
+    // Lay out the text segment: code, followed by the stubs for the imports.
+    let stub_size: u64 = match arch {
+        Architecture::X86_64 => 6,
+        _ => 12,
+    };
+    let mut placements = MutMap::default();
+    let mut text = SegmentLayout {
+        file_offset: align_by_constraint(old_linkedit_offset, page_size),
+        vmaddr: align_by_constraint(old_linkedit_vmaddr as usize, page_size) as u64,
+        ..Default::default()
+    };
+    let mut offset = text.file_offset;
+    for sec in text_sections.iter() {
+        offset = align_by_constraint(offset, sec.align().max(4) as usize);
+        placements.insert(
+            sec.index(),
+            Placement {
+                file_offset: Some(offset),
+                vmaddr: text.vmaddr + (offset - text.file_offset) as u64,
+                object_address: sec.address(),
+            },
+        );
+        offset += sec.size() as usize;
+    }
+    let stubs_offset = align_by_constraint(offset, 4);
+    let stubs_vmaddr = text.vmaddr + (stubs_offset - text.file_offset) as u64;
+    offset = stubs_offset + imports.len() * stub_size as usize;
+    let text_end = offset;
+
+    text.file_size = align_by_constraint(text_end, page_size) - text.file_offset;
+    text.vm_size = text.file_size as u64;
+
+    // Then the data segment: constants, the __got, and zero-filled data at the end.
+    let mut data = SegmentLayout {
+        file_offset: text.file_offset + text.file_size,
+        vmaddr: text.vmaddr + text.vm_size,
+        ..Default::default()
+    };
+    offset = data.file_offset;
+    let mut const_align = 1;
+    for sec in rodata_sections.iter() {
+        const_align = const_align.max(sec.align() as usize);
+        offset = align_by_constraint(offset, sec.align() as usize);
+        placements.insert(
+            sec.index(),
+            Placement {
+                file_offset: Some(offset),
+                vmaddr: data.vmaddr + (offset - data.file_offset) as u64,
+                object_address: sec.address(),
+            },
+        );
+
+        match sec.file_range() {
+            Some((_, size)) if size == sec.size() => offset += size as usize,
+            _ => internal_error!(
+                "We do not deal with non bss sections that have different on disk and in memory sizes"
+            ),
         }
     }
+    let const_end = offset;
+    let got_offset = align_by_constraint(offset, 8);
+    let got_vmaddr = data.vmaddr + (got_offset - data.file_offset) as u64;
+    offset = got_offset + 8 * got.len();
+    data.file_size = offset - data.file_offset;
+
+    let bss_vmaddr = data.vmaddr + data.file_size as u64;
+    let mut vmaddr = bss_vmaddr;
+    for sec in bss_sections.iter() {
+        vmaddr = align_by_constraint(vmaddr as usize, sec.align() as usize) as u64;
+        placements.insert(
+            sec.index(),
+            Placement {
+                file_offset: None,
+                vmaddr,
+                object_address: sec.address(),
+            },
+        );
+        vmaddr += sec.size();
+    }
+    // a segment can not be empty
+    data.vm_size = align_by_constraint((vmaddr - data.vmaddr).max(1) as usize, page_size) as u64;
+
+    let new_linkedit_offset = align_by_constraint(data.file_offset + data.file_size, page_size);
+    let new_linkedit_vmaddr = data.vmaddr + data.vm_size;
+
     if verbose {
-        println!("Data Relocation Offsets: {symbol_vaddr_map:+x?}");
-        println!("Found App Function Symbols: {app_func_vaddr_map:+x?}");
+        println!("App text segment: {text:+x?}");
+        println!("App data segment: {data:+x?}");
+        println!("Section placements: {placements:+x?}");
+        println!("Imports: {imports:?}");
     }
 
-    // let (new_text_section_offset, new_text_section_vaddr) = text_sections
-    //     .iter()
-    //     .map(|sec| section_offset_map.get(&sec.index()).unwrap())
-    //     .min()
-    //     .unwrap();
-    // let (new_text_section_offset, new_text_section_vaddr) =
-    //     (*new_text_section_offset, *new_text_section_vaddr);
+    let layout = AppLayout {
+        app_obj: &app_obj,
+        md,
+        placements,
+        imports,
+        got,
+        stubs_vmaddr,
+        stub_size,
+        got_vmaddr,
+    };
 
-    // Move data and deal with relocations.
-    for sec in rodata_sections
-        .iter()
-        .chain(bss_sections.iter())
-        // TODO why do we even include uninitialized data if it cannot
-        // ever have any relocations in the first place?
-        .chain(text_sections.iter())
-    {
-        let data = sec.data().unwrap_or_else(|err| {
+    // Copy the sections and apply their relocations.
+    let mut fixups = Vec::new();
+    for sec in text_sections.iter().chain(rodata_sections.iter()) {
+        let section_data = sec.data().unwrap_or_else(|err| {
             internal_error!(
                 "Failed to load data for section, {:+x?}: {err}",
                 sec.name().unwrap(),
             );
         });
-        let (section_offset, section_virtual_offset) =
-            section_offset_map.get(&sec.index()).unwrap();
-        let (section_offset, section_virtual_offset) = (*section_offset, *section_virtual_offset);
-        exec_mmap[section_offset..section_offset + data.len()].copy_from_slice(data);
-        // Deal with definitions and relocations for this section.
+        let placement = layout.placements[&sec.index()];
+        let section_offset = placement.file_offset.unwrap();
+        exec_mmap[section_offset..section_offset + section_data.len()]
+            .copy_from_slice(section_data);
+
         if verbose {
-            let segname = sec
-                .segment_name()
-                .expect(
-                    "valid segment
-                    name",
-                )
-                .unwrap();
-            let sectname = sec.name().unwrap();
+            let segname = sec.segment_name().ok().flatten().unwrap_or_default();
+            let sectname = sec.name().unwrap_or_default();
             println!();
             println!(
-                "Processing Relocations for Section '{segname},{sectname}': 0x{sec:+x?} @ {section_offset:+x} (virt: {section_virtual_offset:+x})"
+                "Processing Relocations for Section '{segname},{sectname}': 0x{sec:+x?} @ {section_offset:+x} (virt: {:+x})",
+                placement.vmaddr
             );
         }
 
-        let mut subtractor: Option<SymbolIndex> = None;
-        for rel in sec.relocations() {
+        let mut subtractor = None;
+        for (rel_offset, rel) in sec.relocations() {
             if verbose {
                 println!("\tFound Relocation: {rel:+x?}");
             }
-            match rel.1.target() {
-                RelocationTarget::Symbol(index) => {
-                    let target_offset = if let Some(target_offset) =
-                        get_target_offset(index, &app_obj, md, &symbol_vaddr_map, verbose)
-                    {
-                        target_offset
-                    } else if matches!(app_obj.symbol_by_index(index), Ok(sym) if ["__divti3", "__udivti3", "___divti3", "___udivti3"].contains(&sym.name().unwrap_or_default()))
-                    {
-                        // Explicitly ignore some symbols that are currently always linked.
-                        continue;
-                    } else if matches!(app_obj.symbol_by_index(index), Ok(sym) if ["_longjmp", "_setjmp"].contains(&sym.name().unwrap_or_default()))
-                    {
-                        // These symbols have to stay undefined as we dynamically link them from libSystem.dylib at runtime.
-                        // TODO have a table of all known symbols; perhaps parse and use an Apple provided libSystem.tbd stub file?
-                        let name = app_obj
-                            .symbol_by_index(index)
-                            .and_then(|sym| sym.name())
-                            .ok()
-                            .unwrap();
-                        match rel.1.kind() {
-                            RelocationKind::PltRelative => {
-                                if verbose {
-                                    println!("\t\tTODO synthesise __stub entry for {name}")
-                                }
-                            }
-                            RelocationKind::Got => {
-                                if verbose {
-                                    println!("\t\tTODO synthesise __got entry for {name}")
-                                }
-                            }
-                            RelocationKind::Unknown => {
-                                if let RelocationFlags::MachO { r_type, .. } = rel.1.flags() {
-                                    match r_type {
-                                        macho::ARM64_RELOC_GOT_LOAD_PAGE21
-                                        | macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12 => {
-                                            if verbose {
-                                                println!(
-                                                    "\t\tTODO synthesise __got entry for {name}"
-                                                )
-                                            }
-                                        }
-                                        macho::ARM64_RELOC_BRANCH26 => {
-                                            if verbose {
-                                                println!(
-                                                    "\t\tTODO synthesise __stub entry for {name}"
-                                                )
-                                            }
-                                        }
-                                        _ => internal_error!(
-                                            "Invalid relocation for libc symbol, {:+x?}: {name}",
-                                            rel
-                                        ),
-                                    }
-                                } else {
-                                    internal_error!(
-                                        "Invalid relocation found for Mach-O: {:?}",
-                                        rel
-                                    );
-                                }
-                            }
-                            _ => internal_error!(
-                                "Invalid relocation for libc symbol, {:+x?}: {name}",
-                                rel
-                            ),
-                        }
-                        continue;
-                    } else {
-                        internal_error!(
-                            "Undefined Symbol in relocation, {:+x?}: {:+x?}",
-                            rel,
-                            app_obj.symbol_by_index(index)
-                        )
-                    };
-
-                    let virt_base = section_virtual_offset + rel.0 as usize;
-                    let base = section_offset + rel.0 as usize;
-                    let target: i64 = match rel.1.kind() {
-                        RelocationKind::Relative | RelocationKind::PltRelative => {
-                            target_offset - virt_base as i64 + rel.1.addend()
-                        }
-                        RelocationKind::Absolute => {
-                            target_offset + rel.1.addend()
-                                - subtractor
-                                    .take()
-                                    .map(|index| {
-                                        get_target_offset(
-                                            index,
-                                            &app_obj,
-                                            md,
-                                            &symbol_vaddr_map,
-                                            verbose,
-                                        )
-                                        .unwrap_or(0)
-                                    })
-                                    .unwrap()
-                        }
-                        RelocationKind::Unknown => {
-                            if let RelocationFlags::MachO { r_type, .. } = rel.1.flags() {
-                                match r_type {
-                                    macho::ARM64_RELOC_SUBTRACTOR => {
-                                        if subtractor.is_some() {
-                                            internal_error!("Malformed object: SUBTRACTOR must not be followed by SUBTRACTOR");
-                                        } else {
-                                            subtractor = Some(index);
-                                        }
-                                        continue;
-                                    }
-                                    _ => {
-                                        if verbose {
-                                            println!(
-                                                "\t\tHandle other MachO relocs: {}",
-                                                format_reloc_type(r_type)
-                                            );
-                                        }
-                                        0
-                                    }
-                                }
-                            } else {
-                                internal_error!("Invalid relocation found for Mach-O: {:?}", rel);
-                            }
-                        }
-                        x => {
-                            internal_error!("Relocation Kind not yet support: {:?}", x);
-                        }
-                    };
-                    if verbose {
-                        println!("\t\tRelocation base location: {base:+x} (virt: {virt_base:+x})");
-                        println!("\t\tFinal relocation target offset: {target:+x}");
-                    }
-                    match rel.1.size() {
-                        32 => {
-                            let data = (target as i32).to_le_bytes();
-                            exec_mmap[base..base + 4].copy_from_slice(&data);
-                        }
-                        64 => {
-                            let data = target.to_le_bytes();
-                            exec_mmap[base..base + 8].copy_from_slice(&data);
-                        }
-                        x => {
-                            internal_error!("Relocation size not yet supported: {}", x);
-                        }
-                    }
+
+            let relocation = AppRelocation {
+                file_offset: section_offset + rel_offset as usize,
+                vmaddr: placement.vmaddr + rel_offset,
+                object_address: placement.object_address + rel_offset,
+                relocation: rel,
+            };
+            let fixup = match arch {
+                Architecture::X86_64 => {
+                    relocate_x86_64(exec_mmap, &layout, &relocation, &mut subtractor, verbose)
                 }
+                _ => relocate_aarch64(exec_mmap, &layout, &relocation, &mut subtractor, verbose),
+            };
 
-                _ => {
-                    internal_error!("Relocation target not yet support: {:+x?}", rel);
+            if let Some(fixup) = fixup {
+                if !data.contains(relocation.file_offset) {
+                    internal_error!(
+                        "The app needs an absolute address outside of its data: {:+x?}",
+                        relocation.relocation
+                    );
                 }
+                let offset = relocation.vmaddr - data.vmaddr;
+                fixups.push(match fixup {
+                    AppFixup::Rebase(target) => Fixup::Rebase { offset, target },
+                    AppFixup::Bind(index) => Fixup::Bind {
+                        offset,
+                        import: layout.imports.iter().position(|i| *i == index).unwrap(),
+                    },
+                });
             }
         }
     }
 
-    // Flush app only data to speed up write to disk.
-    exec_mmap
-        .flush_async_range(md.exec_len as usize, offset - md.exec_len as usize)
-        .unwrap_or_else(|e| internal_error!("{}", e));
+    // The stubs jump to the address in their __got slot.
+    for (i, index) in layout.imports.iter().enumerate() {
+        let stub_offset = stubs_offset + i * stub_size as usize;
+        let stub_vmaddr = stubs_vmaddr + i as u64 * stub_size;
+        let slot = layout.got_slot(*index);
+
+        match arch {
+            Architecture::X86_64 => {
+                // jmp *slot(%rip)
+                let displacement = (slot as i64 - (stub_vmaddr as i64 + 6)) as i32;
+                exec_mmap[stub_offset..stub_offset + 2].copy_from_slice(&[0xff, 0x25]);
+                exec_mmap[stub_offset + 2..stub_offset + 6]
+                    .copy_from_slice(&displacement.to_le_bytes());
+            }
+            _ => {
+                // adrp x16, slot@PAGE; ldr x16, [x16, slot@PAGEOFF]; br x16
+                let adrp = encode_adrp(0x9000_0010, stub_vmaddr, slot);
+                let ldr = encode_page_offset(0xf940_0210, slot);
+                write_u32_at(exec_mmap, stub_offset, adrp);
+                write_u32_at(exec_mmap, stub_offset + 4, ldr);
+                write_u32_at(exec_mmap, stub_offset + 8, 0xd61f_0200);
+            }
+        }
+    }
+
+    for (slot, index) in layout.got.iter().enumerate() {
+        let offset = got_vmaddr + 8 * slot as u64 - data.vmaddr;
+        fixups.push(match layout.imports.iter().position(|i| i == index) {
+            Some(import) => Fixup::Bind { offset, import },
+            None => Fixup::Rebase {
+                offset,
+                target: layout.symbol_address(*index, verbose),
+            },
+        });
+    }
+
+    // Point the host at the app's functions.
+    let mut app_func_vaddr_map = MutMap::default();
+    for sym in app_obj.symbols() {
+        let name = sym.name().unwrap_or_default();
+        if md.app_functions.iter().any(|func| func == name) && sym.is_definition() {
+            app_func_vaddr_map.insert(name.to_string(), layout.symbol_address(sym.index(), false));
+        }
+    }
+    if verbose {
+        println!("Found App Function Symbols: {app_func_vaddr_map:+x?}");
+    }
 
-    // TODO: look into merging symbol tables, debug info, and eh frames to enable better debugger experience.
-
-    // let mut cmd_offset = md.macho_cmd_loc as usize;
-
-    // // Load this section (we made room for it earlier) and then mutate all its data to make it the desired command
-    // {
-    //     let cmd =
-    //         load_struct_inplace_mut::<macho::SegmentCommand64<LE >>(exec_mmap, cmd_offset);
-    //     let size_of_section = mem::size_of::<macho::Section64<LE >>() as u32;
-    //     let size_of_cmd = mem::size_of_val(cmd);
-
-    //     cmd_offset += size_of_cmd;
-
-    //     cmd.cmd.set(LE , macho::LC_SEGMENT_64);
-    //     cmd.cmdsize
-    //         .set(LE , size_of_section + size_of_cmd as u32);
-    //     cmd.segname = *b"__DATA_CONST\0\0\0\0";
-    //     cmd.vmaddr
-    //         .set(LE , new_rodata_section_vaddr as u64);
-    //     cmd.vmsize.set(
-    //         LE ,
-    //         (new_text_section_vaddr - new_rodata_section_vaddr) as u64,
-    //     );
-    //     cmd.fileoff
-    //         .set(LE , new_rodata_section_offset as u64);
-    //     cmd.filesize.set(
-    //         LE ,
-    //         (new_text_section_offset - new_rodata_section_offset) as u64,
-    //     );
-    //     cmd.nsects.set(LE , 1);
-    //     cmd.maxprot.set(LE , 0x00000003);
-    //     cmd.initprot.set(LE , 0x00000003);
-
-    //     // TODO set protection
-    // }
-
-    // {
-    //     let cmd = load_struct_inplace_mut::<macho::Section64<LE >>(exec_mmap, cmd_offset);
-    //     let size_of_cmd = mem::size_of_val(cmd);
-
-    //     cmd_offset += size_of_cmd;
-
-    //     cmd.sectname = *b"__const\0\0\0\0\0\0\0\0\0";
-    //     cmd.segname = *b"__DATA_CONST\0\0\0\0";
-    //     cmd.addr.set(LE , new_rodata_section_vaddr as u64);
-    //     cmd.size.set(
-    //         LE ,
-    //         (new_text_section_offset - new_rodata_section_offset) as u64,
-    //     );
-    //     cmd.offset.set(LE , 0); // TODO is this offset since the start of the file, or segment offset?
-    //     cmd.align.set(LE , 12); // TODO should this be 4096?
-    //     cmd.reloff.set(LE , 264); // TODO this should NOT be hardcoded! Should get it from somewhere.
-    // }
-
-    // {
-    //     let cmd =
-    //         load_struct_inplace_mut::<macho::SegmentCommand64<LE >>(exec_mmap, cmd_offset);
-    //     let size_of_section = mem::size_of::<macho::Section64<LE >>() as u32;
-    //     let size_of_cmd = mem::size_of_val(cmd);
-
-    //     cmd_offset += size_of_cmd;
-
-    //     cmd.cmd.set(LE , macho::LC_SEGMENT_64);
-    //     cmd.cmdsize
-    //         .set(LE , size_of_section + size_of_cmd as u32);
-    //     cmd.segname = *b"__TEXT\0\0\0\0\0\0\0\0\0\0";
-    //     cmd.vmaddr.set(LE , new_text_section_vaddr as u64);
-    //     cmd.vmsize
-    //         .set(LE , (offset - new_text_section_offset) as u64);
-    //     cmd.fileoff
-    //         .set(LE , new_text_section_offset as u64);
-    //     cmd.filesize
-    //         .set(LE , (offset - new_text_section_offset) as u64);
-    //     cmd.nsects.set(LE , 1);
-    //     cmd.maxprot.set(LE , 0x00000005); // this is what a zig-generated host had
-    //     cmd.initprot.set(LE , 0x00000005); // this is what a zig-generated host had
-    // }
-
-    // {
-    //     let cmd = load_struct_inplace_mut::<macho::Section64<LE >>(exec_mmap, cmd_offset);
-
-    //     cmd.segname = *b"__TEXT\0\0\0\0\0\0\0\0\0\0";
-    //     cmd.sectname = *b"__text\0\0\0\0\0\0\0\0\0\0";
-    //     cmd.addr.set(LE , new_text_section_vaddr as u64);
-    //     cmd.size
-    //         .set(LE , (offset - new_text_section_offset) as u64);
-    //     cmd.offset.set(LE , 0); // TODO is this offset since the start of the file, or segment offset?
-    //     cmd.align.set(LE , 12); // TODO this is 4096 (2^12) - which load_align_constraint does, above - but should it?
-    //     cmd.flags.set(LE , 0x80000400); // TODO this is what a zig-generated host had
-    //     cmd.reloff.set(LE , 264); // TODO this should NOT be hardcoded! Should get it from somewhere.
-    // }
-
-    // Update calls from platform and dynamic symbols.
-    // let dynsym_offset = md.dynamic_symbol_table_section_offset + md.added_byte_count;
+    let host_fixups = host_fixups_blob.as_deref().map(ChainedFixups::parse);
+    let image_base = commands.image_base(exec_mmap);
+    let (chain_page_size, pointer_format) = host_fixups
+        .as_ref()
+        .and_then(ChainedFixups::pointer_layout)
+        .unwrap_or((page_size as u16, DYLD_CHAINED_PTR_64_OFFSET));
 
     for func_name in md.app_functions.iter() {
         let func_virt_offset = match app_func_vaddr_map.get(func_name) {
-            Some(offset) => *offset as u64,
+            Some(offset) => *offset,
             None => {
                 internal_error!("Function, {}, was not defined by the app", &func_name);
             }
@@ -1579,29 +1589,24 @@ fn surgery_macho_help(
                 println!("\tPerforming surgery: {s:+x?}");
             }
             let surgery_virt_offset = match s.virtual_offset {
-                VirtualOffset::Relative(vs) => (vs + md.added_byte_count) as i64,
+                VirtualOffset::Relative(vs) => vs as i64,
                 VirtualOffset::Absolute => 0,
             };
+            let file_offset = s.file_offset as usize;
             match s.size {
                 4 => {
                     let target = (func_virt_offset as i64 - surgery_virt_offset) as i32;
                     if verbose {
                         println!("\tTarget Jump: {target:+x}");
                     }
-                    let data = target.to_le_bytes();
-                    exec_mmap[(s.file_offset + md.added_byte_count) as usize
-                        ..(s.file_offset + md.added_byte_count) as usize + 4]
-                        .copy_from_slice(&data);
+                    exec_mmap[file_offset..file_offset + 4].copy_from_slice(&target.to_le_bytes());
                 }
                 8 => {
                     let target = func_virt_offset as i64 - surgery_virt_offset;
                     if verbose {
                         println!("\tTarget Jump: {target:+x}");
                     }
-                    let data = target.to_le_bytes();
-                    exec_mmap[(s.file_offset + md.added_byte_count) as usize
-                        ..(s.file_offset + md.added_byte_count) as usize + 8]
-                        .copy_from_slice(&data);
+                    exec_mmap[file_offset..file_offset + 8].copy_from_slice(&target.to_le_bytes());
                 }
                 x => {
                     internal_error!("Surgery size not yet supported: {}", x);
@@ -1609,102 +1614,875 @@ fn surgery_macho_help(
             }
         }
 
+        // Pointers that dyld would have bound to the stub library now point into the app.
+        for file_offset in md.chained_binds.get(func_name).unwrap_or(&vec![]) {
+            let file_offset = *file_offset as usize;
+            let next = chained_next(read_u64(exec_mmap, file_offset));
+            let rebase = chained_rebase(pointer_format, image_base, func_virt_offset, next);
+            write_u64_at(exec_mmap, file_offset, rebase);
+        }
+
         // Replace plt call code with just a jump.
         // This is a backup incase we missed a call to the plt.
         if let Some((plt_off, plt_vaddr)) = md.plt_addresses.get(func_name) {
-            let plt_off = (*plt_off + md.added_byte_count) as usize;
-            let plt_vaddr = *plt_vaddr + md.added_byte_count;
-            let jmp_inst_len = 5;
-            let target =
-                (func_virt_offset as i64 - (plt_vaddr as i64 + jmp_inst_len as i64)) as i32;
+            let plt_off = *plt_off as usize;
+            let plt_vaddr = *plt_vaddr;
             if verbose {
                 println!("\tPLT: {plt_off:+x}, {plt_vaddr:+x}");
-                println!("\tTarget Jump: {target:+x}");
-            }
-            let data = target.to_le_bytes();
-            exec_mmap[plt_off] = 0xE9;
-            exec_mmap[plt_off + 1..plt_off + jmp_inst_len].copy_from_slice(&data);
-            for i in jmp_inst_len..PLT_ADDRESS_OFFSET as usize {
-                exec_mmap[plt_off + i] = 0x90;
             }
-        }
 
-        // Commented out because it doesn't apply to mach-o
-        // if let Some(i) = md.dynamic_symbol_indices.get(func_name) {
-        //     let sym = load_struct_inplace_mut::<elf::Sym64<LE >>(
-        //         exec_mmap,
-        //         dynsym_offset as usize + *i as usize * mem::size_of::<elf::Sym64<LE >>(),
-        //     );
-        //     sym.st_value.set(LE , func_virt_offset as u64);
-        //     sym.st_size.set(
-        //         LE ,
-        //         match app_func_size_map.get(func_name) {
-        //             Some(size) => *size,
-        //             None => {
-        //                 internal_error!("Size missing for: {}", func_name);
-        //             }
-        //         },
-        //     );
-        // }
-    }
-
-    *offset_ref = offset;
-}
+            match arch {
+                Architecture::X86_64 => {
+                    let jmp_inst_len = 5;
+                    let target =
+                        (func_virt_offset as i64 - (plt_vaddr as i64 + jmp_inst_len as i64)) as i32;
+                    if verbose {
+                        println!("\tTarget Jump: {target:+x}");
+                    }
+                    exec_mmap[plt_off] = 0xE9;
+                    exec_mmap[plt_off + 1..plt_off + jmp_inst_len]
+                        .copy_from_slice(&target.to_le_bytes());
+                    for i in jmp_inst_len..md.stub_size as usize {
+                        exec_mmap[plt_off + i] = 0x90;
+                    }
+                }
+                _ => {
+                    let displacement = func_virt_offset as i64 - plt_vaddr as i64;
+                    if displacement.unsigned_abs() < 1 << 27 {
+                        let branch = 0x1400_0000 | ((displacement >> 2) as u32 & 0x03ff_ffff);
+                        write_u32_at(exec_mmap, plt_off, branch);
+                        for i in (4..md.stub_size as usize).step_by(4) {
+                            write_u32_at(exec_mmap, plt_off + i, ARM64_NOP);
+                        }
+                    } else if host_fixups.is_none() {
+                        internal_error!("{func_name} is too far away from the host's stub");
+                    }
+                    // otherwise the stub loads the address from the rebased __got slot.
+                }
+            }
+        }
+    }
 
-fn get_target_offset(
-    index: SymbolIndex,
-    app_obj: &object::File,
-    md: &Metadata,
-    symbol_vaddr_map: &MutMap<SymbolIndex, usize>,
+    // Build the new __LINKEDIT: the old contents, new dyld info, and a new code signature.
+    let mut linkedit_data = old_linkedit;
+    fixups.sort_by_key(|fixup| match fixup {
+        Fixup::Rebase { offset, .. } | Fixup::Bind { offset, .. } => *offset,
+    });
+
+    let import_names: Vec<&str> = layout
+        .imports
+        .iter()
+        .map(|index| layout.symbol_name(*index))
+        .collect();
+
+    shift_linkedit_offsets(
+        exec_mmap,
+        &commands,
+        (new_linkedit_offset - old_linkedit_offset) as u32,
+    );
+
+    let data_segment_index = linkedit_index - 1;
+    if let Some(host_fixups) = &host_fixups {
+        write_chained_pointers(
+            exec_mmap,
+            &fixups,
+            data.file_offset,
+            chain_page_size as u64,
+            pointer_format,
+            image_base,
+            host_fixups.imports_count,
+        );
+
+        linkedit_data.resize(align_by_constraint(linkedit_data.len(), 8), 0);
+        let blob_offset = linkedit_data.len();
+        linkedit_data.extend(chained_fixups_blob(
+            host_fixups,
+            &fixups,
+            commands.segments.len(),
+            data_segment_index,
+            data.vmaddr - image_base,
+            (chain_page_size, pointer_format),
+            &import_names,
+            md.libsystem_ordinal,
+        ));
+
+        let cmd = load_struct_inplace_mut::<LinkeditDataCommand<LE>>(
+            exec_mmap,
+            commands.chained_fixups.unwrap(),
+        );
+        cmd.dataoff
+            .set(LE, (new_linkedit_offset + blob_offset) as u32);
+        cmd.datasize
+            .set(LE, (linkedit_data.len() - blob_offset) as u32);
+    } else if let (Some(dyld_info), Some((host_rebases, host_binds))) =
+        (commands.dyld_info, &host_dyld_info)
+    {
+        for fixup in fixups.iter() {
+            if let Fixup::Rebase { offset, target } = fixup {
+                write_u64_at(exec_mmap, data.file_offset + *offset as usize, *target);
+            }
+        }
+
+        let (rebases, binds) = dyld_info_opcodes(
+            host_rebases,
+            host_binds,
+            &fixups,
+            data_segment_index,
+            &import_names,
+            md.libsystem_ordinal,
+        );
+
+        linkedit_data.resize(align_by_constraint(linkedit_data.len(), 8), 0);
+        let rebase_offset = linkedit_data.len();
+        linkedit_data.extend(rebases);
+        let bind_offset = linkedit_data.len();
+        linkedit_data.extend(binds);
+
+        let cmd = load_struct_inplace_mut::<macho::DyldInfoCommand<LE>>(exec_mmap, dyld_info);
+        cmd.rebase_off
+            .set(LE, (new_linkedit_offset + rebase_offset) as u32);
+        cmd.rebase_size
+            .set(LE, (bind_offset - rebase_offset) as u32);
+        cmd.bind_off
+            .set(LE, (new_linkedit_offset + bind_offset) as u32);
+        cmd.bind_size
+            .set(LE, (linkedit_data.len() - bind_offset) as u32);
+    } else if !fixups.is_empty() {
+        internal_error!("The host has neither chained fixups nor dyld info");
+    }
+
+    // The signature must be the last thing in the file.
+    linkedit_data.resize(align_by_constraint(linkedit_data.len(), 16), 0);
+    let signature_offset = new_linkedit_offset + linkedit_data.len();
+    let identifier = executable_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("roc-app");
+    let text_segment = commands.header_segment(exec_mmap);
+    let signature = AdHocSignature {
+        identifier,
+        code_limit: signature_offset,
+        exec_segment_offset: text_segment.fileoff.get(LE),
+        exec_segment_size: text_segment.filesize.get(LE),
+        is_executable,
+    };
+    let signature_size = signature.size();
+    let out_len = signature_offset + signature_size;
+
+    exec_mmap[new_linkedit_offset..signature_offset].copy_from_slice(&linkedit_data);
+
+    // Update the load commands for the new layout.
+    {
+        let cmd = load_struct_inplace_mut::<SegmentCommand64<LE>>(exec_mmap, linkedit);
+        cmd.fileoff.set(LE, new_linkedit_offset as u64);
+        cmd.vmaddr.set(LE, new_linkedit_vmaddr);
+        cmd.filesize.set(LE, (out_len - new_linkedit_offset) as u64);
+        cmd.vmsize.set(
+            LE,
+            align_by_constraint(out_len - new_linkedit_offset, page_size) as u64,
+        );
+    }
+
+    let text_section_size = (text_end - text.file_offset) as u64;
+    update_segment_command(
+        exec_mmap,
+        md.text_segment_cmd as usize,
+        &text,
+        &[(text.vmaddr, text_section_size, Some(text.file_offset), 4)],
+    );
+
+    let const_size = (const_end - data.file_offset) as u64;
+    update_segment_command(
+        exec_mmap,
+        md.data_segment_cmd as usize,
+        &data,
+        &[
+            (
+                data.vmaddr,
+                const_size,
+                Some(data.file_offset),
+                const_align.trailing_zeros(),
+            ),
+            (got_vmaddr, 8 * layout.got.len() as u64, Some(got_offset), 3),
+            (bss_vmaddr, vmaddr - bss_vmaddr, None, 4),
+        ],
+    );
+
+    match commands.code_signature {
+        Some(offset) => {
+            let cmd = load_struct_inplace_mut::<LinkeditDataCommand<LE>>(exec_mmap, offset);
+            cmd.dataoff.set(LE, signature_offset as u32);
+            cmd.datasize.set(LE, signature_size as u32);
+        }
+        None => internal_error!("The host has no code signature command. Was it preprocessed?"),
+    }
+
+    // Finally, hash every page of the file.
+    let (contents, signature_data) = exec_mmap.split_at_mut(signature_offset);
+    signature.write(contents, signature_data);
+
+    out_len
+}
+
+/// A relocation of the app, at its final place in the executable
+struct AppRelocation {
+    file_offset: usize,
+    vmaddr: u64,
+    object_address: u64,
+    relocation: object::Relocation,
+}
+
+enum AppFixup {
+    Rebase(u64),
+    Bind(SymbolIndex),
+}
+
+fn relocation_flags(rel: &object::Relocation) -> (u8, bool, u8) {
+    match rel.flags() {
+        RelocationFlags::MachO {
+            r_type,
+            r_pcrel,
+            r_length,
+        } => (r_type, r_pcrel, r_length),
+        _ => internal_error!("Invalid relocation found for Mach-O: {:?}", rel),
+    }
+}
+
+/// `X86_64_RELOC_UNSIGNED` and `ARM64_RELOC_UNSIGNED`: an absolute address, or the difference
+/// between two addresses after a SUBTRACTOR.
+fn relocate_unsigned(
+    exec_mmap: &mut [u8],
+    layout: &AppLayout,
+    app_relocation: &AppRelocation,
+    subtractor: &mut Option<u64>,
     verbose: bool,
-) -> Option<i64> {
-    if let Some(target_offset) = symbol_vaddr_map.get(&index) {
-        if verbose {
-            println!("\t\tRelocation targets symbol in app at: {target_offset:+x}");
+) -> Option<AppFixup> {
+    let rel = &app_relocation.relocation;
+    let base = app_relocation.file_offset;
+    let (_, _, r_length) = relocation_flags(rel);
+
+    let implicit_addend = match r_length {
+        2 => i32::from_le_bytes(exec_mmap[base..base + 4].try_into().unwrap()) as i64,
+        3 => i64::from_le_bytes(exec_mmap[base..base + 8].try_into().unwrap()),
+        _ => internal_error!("Relocation size not yet supported: {:?}", rel),
+    };
+    let addend = if rel.has_implicit_addend() {
+        implicit_addend
+    } else {
+        rel.addend()
+    };
+
+    let (target, fixup) = match rel.target() {
+        RelocationTarget::Symbol(index) if layout.is_import(index) => {
+            if subtractor.is_some() || r_length != 3 || addend != 0 {
+                internal_error!("Unsupported reference to an import: {:?}", rel);
+            }
+            (0, Some(AppFixup::Bind(index)))
+        }
+        RelocationTarget::Symbol(index) => {
+            let target = layout.symbol_address(index, verbose) as i64 + addend;
+            (target, Some(AppFixup::Rebase(target as u64)))
+        }
+        // the addend is the address of the target in the object
+        RelocationTarget::Section(index) => {
+            let target = layout.section_address(index, addend as u64) as i64;
+            (target, Some(AppFixup::Rebase(target as u64)))
+        }
+        _ => internal_error!("Relocation target not yet support: {:+x?}", rel),
+    };
+
+    let (value, fixup) = match subtractor.take() {
+        Some(minus) => (target - minus as i64, None),
+        None if r_length == 3 => (target, fixup),
+        None => internal_error!("32-bit absolute addresses are not supported: {:?}", rel),
+    };
+
+    if verbose {
+        println!("\t\tFinal relocation value: {value:+x}");
+    }
+
+    match r_length {
+        2 => exec_mmap[base..base + 4].copy_from_slice(&(value as i32).to_le_bytes()),
+        _ => exec_mmap[base..base + 8].copy_from_slice(&value.to_le_bytes()),
+    }
+
+    fixup
+}
+
+fn relocate_x86_64(
+    exec_mmap: &mut [u8],
+    layout: &AppLayout,
+    app_relocation: &AppRelocation,
+    subtractor: &mut Option<u64>,
+    verbose: bool,
+) -> Option<AppFixup> {
+    let rel = &app_relocation.relocation;
+    let base = app_relocation.file_offset;
+    let (r_type, _, _) = relocation_flags(rel);
+
+    match r_type {
+        macho::X86_64_RELOC_SUBTRACTOR => {
+            if subtractor.is_some() {
+                internal_error!("Malformed object: SUBTRACTOR must not be followed by SUBTRACTOR");
+            }
+            match rel.target() {
+                RelocationTarget::Symbol(index) => {
+                    *subtractor = Some(layout.symbol_address(index, verbose))
+                }
+                _ => internal_error!("Relocation target not yet support: {:+x?}", rel),
+            }
+            None
+        }
+        macho::X86_64_RELOC_UNSIGNED => {
+            relocate_unsigned(exec_mmap, layout, app_relocation, subtractor, verbose)
+        }
+        macho::X86_64_RELOC_SIGNED
+        | macho::X86_64_RELOC_SIGNED_1
+        | macho::X86_64_RELOC_SIGNED_2
+        | macho::X86_64_RELOC_SIGNED_4
+        | macho::X86_64_RELOC_BRANCH
+        | macho::X86_64_RELOC_GOT_LOAD
+        | macho::X86_64_RELOC_GOT => {
+            // The displacement is relative to the end of the instruction, which `addend`
+            // accounts for. Any other addend is stored in the instruction.
+            let implicit_addend =
+                i32::from_le_bytes(exec_mmap[base..base + 4].try_into().unwrap()) as i64;
+
+            let target = match rel.target() {
+                RelocationTarget::Symbol(index)
+                    if is_got_relocation(Architecture::X86_64, r_type) =>
+                {
+                    layout.got_slot(index) as i64 + implicit_addend
+                }
+                RelocationTarget::Symbol(index) => {
+                    layout.symbol_address(index, verbose) as i64 + implicit_addend
+                }
+                RelocationTarget::Section(index) => {
+                    let object_target =
+                        app_relocation.object_address as i64 + implicit_addend - rel.addend();
+                    layout.section_address(index, object_target as u64) as i64
+                }
+                _ => internal_error!("Relocation target not yet support: {:+x?}", rel),
+            };
+
+            let value = target + rel.addend() - app_relocation.vmaddr as i64;
+            if verbose {
+                println!("\t\tFinal relocation value: {value:+x}");
+            }
+            exec_mmap[base..base + 4].copy_from_slice(&(value as i32).to_le_bytes());
+
+            None
+        }
+        _ => internal_error!(
+            "Relocation Kind not yet support: {}",
+            format_reloc_type(Architecture::X86_64, r_type)
+        ),
+    }
+}
+
+fn relocate_aarch64(
+    exec_mmap: &mut [u8],
+    layout: &AppLayout,
+    app_relocation: &AppRelocation,
+    subtractor: &mut Option<u64>,
+    verbose: bool,
+) -> Option<AppFixup> {
+    let rel = &app_relocation.relocation;
+    let base = app_relocation.file_offset;
+    let place = app_relocation.vmaddr;
+    let (r_type, _, _) = relocation_flags(rel);
+
+    let index = match rel.target() {
+        RelocationTarget::Symbol(index) => index,
+        RelocationTarget::Section(_) if r_type == macho::ARM64_RELOC_UNSIGNED => {
+            return relocate_unsigned(exec_mmap, layout, app_relocation, subtractor, verbose);
         }
-        Some(*target_offset as i64)
+        _ => internal_error!("Relocation target not yet support: {:+x?}", rel),
+    };
+
+    // Instructions have no room for an implicit addend, it comes from an ARM64_RELOC_ADDEND.
+    let addend = if rel.has_implicit_addend() {
+        0
     } else {
-        app_obj
-            .symbol_by_index(index)
-            .and_then(|sym| sym.name().map(|name| name.trim_start_matches('_')))
-            .ok()
-            .and_then(|name| {
-                md.roc_symbol_vaddresses.get(name).map(|address| {
-                    let vaddr = (*address + md.added_byte_count) as i64;
-                    if verbose {
-                        println!("\t\tRelocation targets symbol in host: {name} @ {vaddr:+x}");
-                    }
-                    vaddr
-                })
-            })
+        rel.addend()
+    };
+    let instruction = read_u32(exec_mmap, base);
+
+    let instruction = match r_type {
+        macho::ARM64_RELOC_SUBTRACTOR => {
+            if subtractor.is_some() {
+                internal_error!("Malformed object: SUBTRACTOR must not be followed by SUBTRACTOR");
+            }
+            *subtractor = Some(layout.symbol_address(index, verbose));
+            return None;
+        }
+        macho::ARM64_RELOC_UNSIGNED => {
+            return relocate_unsigned(exec_mmap, layout, app_relocation, subtractor, verbose);
+        }
+        macho::ARM64_RELOC_POINTER_TO_GOT => {
+            let value = layout.got_slot(index) as i64 - place as i64;
+            exec_mmap[base..base + 4].copy_from_slice(&(value as i32).to_le_bytes());
+            return None;
+        }
+        macho::ARM64_RELOC_BRANCH26 => {
+            let target = layout.symbol_address(index, verbose) as i64 + addend;
+            let displacement = target - place as i64;
+            if displacement.unsigned_abs() >= 1 << 27 {
+                internal_error!("Branch target is out of range: {:?}", rel);
+            }
+            (instruction & 0xfc00_0000) | ((displacement >> 2) as u32 & 0x03ff_ffff)
+        }
+        macho::ARM64_RELOC_PAGE21 => {
+            let target = layout.symbol_address(index, verbose) as i64 + addend;
+            encode_adrp(instruction, place, target as u64)
+        }
+        macho::ARM64_RELOC_GOT_LOAD_PAGE21 => {
+            encode_adrp(instruction, place, layout.got_slot(index))
+        }
+        macho::ARM64_RELOC_PAGEOFF12 => {
+            let target = layout.symbol_address(index, verbose) as i64 + addend;
+            encode_page_offset(instruction, target as u64)
+        }
+        macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12 => {
+            encode_page_offset(instruction, layout.got_slot(index))
+        }
+        _ => internal_error!(
+            "Relocation Kind not yet support: {}",
+            format_reloc_type(Architecture::Aarch64, r_type)
+        ),
+    };
+
+    if verbose {
+        println!("\t\tRelocated instruction: {instruction:08x}");
+    }
+    write_u32_at(exec_mmap, base, instruction);
+
+    None
+}
+
+/// Set the immediate of an `adrp` at `place` so that it loads the page of `target`.
+fn encode_adrp(instruction: u32, place: u64, target: u64) -> u32 {
+    let pages = (target as i64 >> 12) - (place as i64 >> 12);
+    if !(-(1 << 20)..(1 << 20)).contains(&pages) {
+        internal_error!("adrp target {target:+x} is out of range of {place:+x}");
+    }
+
+    let immlo = (pages as u32) & 0x3;
+    let immhi = ((pages as u32) >> 2) & 0x7_ffff;
+    (instruction & 0x9f00_001f) | (immlo << 29) | (immhi << 5)
+}
+
+/// Set the 12-bit immediate of an `add` or a load/store to the offset of `target` in its page.
+/// Loads and stores scale the immediate by the size of their access.
+fn encode_page_offset(instruction: u32, target: u64) -> u32 {
+    let offset = (target & 0xfff) as u32;
+
+    let scale = if instruction & 0x3b00_0000 == 0x3900_0000 {
+        let size = instruction >> 30;
+        // 128-bit vector loads and stores
+        if size == 0 && instruction & 0x0480_0000 == 0x0480_0000 {
+            4
+        } else {
+            size
+        }
+    } else {
+        0
+    };
+
+    if offset & ((1 << scale) - 1) != 0 {
+        internal_error!("Page offset {offset:+x} is not aligned for instruction {instruction:08x}");
+    }
+
+    (instruction & !(0xfff << 10)) | ((offset >> scale) << 10)
+}
+
+fn update_segment_command(
+    data: &mut [u8],
+    offset: usize,
+    layout: &SegmentLayout,
+    sections: &[(u64, u64, Option<usize>, u32)],
+) {
+    let cmd = load_struct_inplace_mut::<SegmentCommand64<LE>>(data, offset);
+    cmd.vmaddr.set(LE, layout.vmaddr);
+    cmd.vmsize.set(LE, layout.vm_size);
+    cmd.fileoff.set(LE, layout.file_offset as u64);
+    cmd.filesize.set(LE, layout.file_size as u64);
+
+    let headers = load_structs_inplace_mut::<Section64<LE>>(
+        data,
+        offset + SEGMENT_COMMAND_SIZE,
+        sections.len(),
+    );
+    for (header, (addr, size, file_offset, align)) in headers.iter_mut().zip(sections) {
+        header.addr.set(LE, *addr);
+        header.size.set(LE, *size);
+        header.offset.set(LE, file_offset.unwrap_or(0) as u32);
+        header.align.set(LE, *align);
+    }
+}
+
+/// Everything in __LINKEDIT moves by `delta` bytes.
+fn shift_linkedit_offsets(data: &mut [u8], commands: &LoadCommands, delta: u32) {
+    let mut offset = mem::size_of::<macho::MachHeader64<LE>>();
+
+    while offset < commands.end {
+        let info = load_struct_inplace::<macho::LoadCommand<LE>>(data, offset);
+        let cmd_size = info.cmdsize.get(LE) as usize;
+
+        match info.cmd.get(LE) {
+            macho::LC_SYMTAB => {
+                let cmd = load_struct_inplace_mut::<macho::SymtabCommand<LE>>(data, offset);
+
+                if cmd.nsyms.get(LE) > 0 {
+                    cmd.symoff.set(LE, cmd.symoff.get(LE) + delta);
+                }
+
+                if cmd.strsize.get(LE) > 0 {
+                    cmd.stroff.set(LE, cmd.stroff.get(LE) + delta);
+                }
+            }
+            macho::LC_DYSYMTAB => {
+                let cmd = load_struct_inplace_mut::<macho::DysymtabCommand<LE>>(data, offset);
+
+                if cmd.ntoc.get(LE) > 0 {
+                    cmd.tocoff.set(LE, cmd.tocoff.get(LE) + delta);
+                }
+
+                if cmd.nmodtab.get(LE) > 0 {
+                    cmd.modtaboff.set(LE, cmd.modtaboff.get(LE) + delta);
+                }
+
+                if cmd.nextrefsyms.get(LE) > 0 {
+                    cmd.extrefsymoff.set(LE, cmd.extrefsymoff.get(LE) + delta);
+                }
+
+                if cmd.nindirectsyms.get(LE) > 0 {
+                    cmd.indirectsymoff
+                        .set(LE, cmd.indirectsymoff.get(LE) + delta);
+                }
+
+                if cmd.nextrel.get(LE) > 0 {
+                    cmd.extreloff.set(LE, cmd.extreloff.get(LE) + delta);
+                }
+
+                if cmd.nlocrel.get(LE) > 0 {
+                    cmd.locreloff.set(LE, cmd.locreloff.get(LE) + delta);
+                }
+            }
+            macho::LC_TWOLEVEL_HINTS => {
+                let cmd = load_struct_inplace_mut::<macho::TwolevelHintsCommand<LE>>(data, offset);
+
+                if cmd.nhints.get(LE) > 0 {
+                    cmd.offset.set(LE, cmd.offset.get(LE) + delta);
+                }
+            }
+            macho::LC_FUNCTION_STARTS
+            | macho::LC_DATA_IN_CODE
+            | macho::LC_CODE_SIGNATURE
+            | macho::LC_SEGMENT_SPLIT_INFO
+            | macho::LC_DYLIB_CODE_SIGN_DRS
+            | macho::LC_LINKER_OPTIMIZATION_HINT
+            | macho::LC_DYLD_EXPORTS_TRIE
+            | macho::LC_DYLD_CHAINED_FIXUPS => {
+                let cmd = load_struct_inplace_mut::<LinkeditDataCommand<LE>>(data, offset);
+
+                if cmd.datasize.get(LE) > 0 {
+                    cmd.dataoff.set(LE, cmd.dataoff.get(LE) + delta);
+                }
+            }
+            macho::LC_DYLD_INFO | macho::LC_DYLD_INFO_ONLY => {
+                let cmd = load_struct_inplace_mut::<macho::DyldInfoCommand<LE>>(data, offset);
+
+                if cmd.rebase_size.get(LE) > 0 {
+                    cmd.rebase_off.set(LE, cmd.rebase_off.get(LE) + delta);
+                }
+
+                if cmd.bind_size.get(LE) > 0 {
+                    cmd.bind_off.set(LE, cmd.bind_off.get(LE) + delta);
+                }
+
+                if cmd.weak_bind_size.get(LE) > 0 {
+                    cmd.weak_bind_off.set(LE, cmd.weak_bind_off.get(LE) + delta);
+                }
+
+                if cmd.lazy_bind_size.get(LE) > 0 {
+                    cmd.lazy_bind_off.set(LE, cmd.lazy_bind_off.get(LE) + delta);
+                }
+
+                if cmd.export_size.get(LE) > 0 {
+                    cmd.export_off.set(LE, cmd.export_off.get(LE) + delta);
+                }
+            }
+            macho::LC_SYMSEG => {
+                let cmd = load_struct_inplace_mut::<macho::SymsegCommand<LE>>(data, offset);
+
+                if cmd.size.get(LE) > 0 {
+                    cmd.offset.set(LE, cmd.offset.get(LE) + delta);
+                }
+            }
+            _ => {
+                // Everything else either has no file offsets, or points before __LINKEDIT,
+                // which does not move.
+            }
+        }
+
+        offset += cmd_size;
+    }
+}
+
+/// Link the app's fixups into chains, one per page, and write them into the data segment.
+fn write_chained_pointers(
+    exec_mmap: &mut [u8],
+    fixups: &[Fixup],
+    segment_file_offset: usize,
+    page_size: u64,
+    pointer_format: u16,
+    image_base: u64,
+    first_import: usize,
+) {
+    for (i, fixup) in fixups.iter().enumerate() {
+        let (offset, next_offset) = match (fixup, fixups.get(i + 1)) {
+            (
+                Fixup::Rebase { offset, .. } | Fixup::Bind { offset, .. },
+                Some(Fixup::Rebase { offset: next, .. } | Fixup::Bind { offset: next, .. }),
+            ) => (*offset, Some(*next)),
+            (Fixup::Rebase { offset, .. } | Fixup::Bind { offset, .. }, None) => (*offset, None),
+        };
+
+        if offset % 4 != 0 {
+            internal_error!("Pointer at offset {offset:+x} is not aligned");
+        }
+
+        let next = match next_offset {
+            Some(next_offset) if next_offset / page_size == offset / page_size => {
+                (next_offset - offset) / 4
+            }
+            _ => 0,
+        };
+
+        let raw = match fixup {
+            Fixup::Rebase { target, .. } => {
+                chained_rebase(pointer_format, image_base, *target, next)
+            }
+            Fixup::Bind { import, .. } => chained_bind(first_import + import, next),
+        };
+
+        write_u64_at(exec_mmap, segment_file_offset + offset as usize, raw);
+    }
+}
+
+/// A copy of the host's chained fixups, with starts for the app's data segment and the app's
+/// imports added.
+#[allow(clippy::too_many_arguments)]
+fn chained_fixups_blob(
+    host_fixups: &ChainedFixups,
+    fixups: &[Fixup],
+    segment_count: usize,
+    data_segment_index: usize,
+    data_segment_offset: u64,
+    (page_size, pointer_format): (u16, u16),
+    import_names: &[&str],
+    libsystem_ordinal: u32,
+) -> Vec<u8> {
+    let mut blob = vec![0; CHAINED_FIXUPS_HEADER_SIZE];
+    blob.resize(align_by_constraint(blob.len(), 8), 0);
+
+    // dyld_chained_starts_in_image
+    let starts_offset = blob.len();
+    blob.extend((segment_count as u32).to_le_bytes());
+    blob.resize(starts_offset + 4 + 4 * segment_count, 0);
+
+    let set_segment_starts = |blob: &mut Vec<u8>, index: usize, starts: &[u8]| {
+        blob.resize(align_by_constraint(blob.len(), 8), 0);
+        let info_offset = (blob.len() - starts_offset) as u32;
+        write_u32_at(blob, starts_offset + 4 + 4 * index, info_offset);
+        blob.extend(starts);
+    };
+
+    // Everything before the app's segments keeps its fixups.
+    for index in 0..data_segment_index - 1 {
+        if let Some(starts) = host_fixups.segment_starts(index) {
+            set_segment_starts(&mut blob, index, starts);
+        }
+    }
+
+    if let Some(last_offset) = fixups.last().map(|fixup| match fixup {
+        Fixup::Rebase { offset, .. } | Fixup::Bind { offset, .. } => *offset,
+    }) {
+        let page_count = (last_offset / page_size as u64 + 1) as usize;
+        let mut page_starts = vec![DYLD_CHAINED_PTR_START_NONE; page_count];
+        for fixup in fixups.iter().rev() {
+            let offset = match fixup {
+                Fixup::Rebase { offset, .. } | Fixup::Bind { offset, .. } => *offset,
+            };
+            page_starts[(offset / page_size as u64) as usize] = (offset % page_size as u64) as u16;
+        }
+
+        let mut starts = Vec::new();
+        starts.extend(((CHAINED_STARTS_IN_SEGMENT_SIZE + 2 * page_count) as u32).to_le_bytes());
+        starts.extend(page_size.to_le_bytes());
+        starts.extend(pointer_format.to_le_bytes());
+        starts.extend(data_segment_offset.to_le_bytes());
+        starts.extend(0u32.to_le_bytes()); // max_valid_pointer
+        starts.extend((page_count as u16).to_le_bytes());
+        for page_start in page_starts {
+            starts.extend(page_start.to_le_bytes());
+        }
+
+        set_segment_starts(&mut blob, data_segment_index, &starts);
     }
+
+    // Imports: the host's, then the app's
+    blob.resize(align_by_constraint(blob.len(), 4), 0);
+    let imports_offset = blob.len();
+    let import_size = host_fixups.import_size();
+    let host_imports =
+        &host_fixups.blob[host_fixups.imports_offset..][..host_fixups.imports_count * import_size];
+    blob.extend(host_imports);
+
+    let host_symbols = &host_fixups.blob[host_fixups.symbols_offset..];
+    let mut symbols = host_symbols.to_vec();
+    for name in import_names {
+        let name_offset = symbols.len() as u64;
+        symbols.extend(name.as_bytes());
+        symbols.push(0);
+
+        match host_fixups.imports_format {
+            DYLD_CHAINED_IMPORT_ADDEND64 => {
+                let raw = libsystem_ordinal as u64 | (name_offset << 32);
+                blob.extend(raw.to_le_bytes());
+                blob.extend(0u64.to_le_bytes());
+            }
+            format => {
+                if name_offset >= 1 << 23 || libsystem_ordinal > 0xff {
+                    internal_error!("The app's imports do not fit in the host's chained fixups");
+                }
+                let raw = libsystem_ordinal | ((name_offset as u32) << 9);
+                blob.extend(raw.to_le_bytes());
+                if format == DYLD_CHAINED_IMPORT_ADDEND {
+                    blob.extend(0u32.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    let symbols_offset = blob.len();
+    blob.extend(symbols);
+
+    // dyld_chained_fixups_header
+    for (i, value) in [
+        0,
+        starts_offset as u32,
+        imports_offset as u32,
+        symbols_offset as u32,
+        (host_fixups.imports_count + import_names.len()) as u32,
+        host_fixups.imports_format,
+        0,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        write_u32_at(&mut blob, 4 * i, value);
+    }
+
+    blob
+}
+
+/// For hosts without chained fixups: the rebase and bind opcodes for the app's data, followed by
+/// those of the host. The host's opcodes set all the state they depend on, so they still work
+/// when they run after ours.
+fn dyld_info_opcodes(
+    host_rebases: &[u8],
+    host_binds: &[u8],
+    fixups: &[Fixup],
+    data_segment_index: usize,
+    import_names: &[&str],
+    libsystem_ordinal: u32,
+) -> (Vec<u8>, Vec<u8>) {
+    if data_segment_index > 0xf {
+        internal_error!("The host has too many segments for dyld info opcodes");
+    }
+    let segment = data_segment_index as u8;
+
+    let mut rebases = vec![macho::REBASE_OPCODE_SET_TYPE_IMM | macho::REBASE_TYPE_POINTER];
+    let mut binds = Vec::new();
+    for fixup in fixups {
+        match fixup {
+            Fixup::Rebase { offset, .. } => {
+                rebases.push(macho::REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | segment);
+                uleb128(&mut rebases, *offset);
+                rebases.push(macho::REBASE_OPCODE_DO_REBASE_IMM_TIMES | 1);
+            }
+            Fixup::Bind { offset, import } => {
+                if libsystem_ordinal <= 0xf {
+                    binds.push(macho::BIND_OPCODE_SET_DYLIB_ORDINAL_IMM | libsystem_ordinal as u8);
+                } else {
+                    binds.push(macho::BIND_OPCODE_SET_DYLIB_ORDINAL_ULEB);
+                    uleb128(&mut binds, libsystem_ordinal as u64);
+                }
+                binds.push(macho::BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM);
+                binds.extend(import_names[*import].as_bytes());
+                binds.push(0);
+                binds.push(macho::BIND_OPCODE_SET_TYPE_IMM | macho::BIND_TYPE_POINTER);
+                binds.push(macho::BIND_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | segment);
+                uleb128(&mut binds, *offset);
+                binds.push(macho::BIND_OPCODE_DO_BIND);
+            }
+        }
+    }
+
+    rebases.extend(host_rebases);
+    if host_rebases.is_empty() {
+        rebases.push(macho::REBASE_OPCODE_DONE);
+    }
+    binds.extend(host_binds);
+    if host_binds.is_empty() {
+        binds.push(macho::BIND_OPCODE_DONE);
+    }
+
+    (rebases, binds)
 }
 
-fn format_reloc_type(value: u8) -> impl std::fmt::Display {
-    struct Inner(u8);
+fn format_reloc_type(arch: Architecture, value: u8) -> impl std::fmt::Display {
+    struct Inner(Architecture, u8);
 
     impl std::fmt::Display for Inner {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             let name: &str = match self.0 {
-                macho::ARM64_RELOC_ADDEND => "ARM64_RELOC_ADDEND",
-                macho::ARM64_RELOC_PAGE21 => "ARM64_RELOC_PAGE21",
-                macho::ARM64_RELOC_PAGEOFF12 => "ARM64_RELOC_PAGEOFF12",
-                macho::ARM64_RELOC_BRANCH26 => "ARM64_RELOC_BRANCH26",
-                macho::ARM64_RELOC_UNSIGNED => "ARM64_RELOC_UNSIGNED",
-                macho::ARM64_RELOC_SUBTRACTOR => "ARM64_RELOC_SUBTRACTOR",
-                macho::ARM64_RELOC_GOT_LOAD_PAGE21 => "ARM64_RELOC_GOT_LOAD_PAGE21",
-                macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12 => "ARM64_RELOC_GOT_LOAD_PAGEOFF12",
-                macho::ARM64_RELOC_TLVP_LOAD_PAGE21 => "ARM64_RELOC_TLVP_LOAD_PAGE21",
-                macho::ARM64_RELOC_TLVP_LOAD_PAGEOFF12 => "ARM64_RELOC_TLVP_LOAD_PAGEOFF12",
-                macho::ARM64_RELOC_POINTER_TO_GOT => "ARM64_RELOC_POINTER_TO_GOT",
-                macho::ARM64_RELOC_AUTHENTICATED_POINTER => "ARM64_RELOC_AUTHENTICATED_POINTER",
-                _ => "ARM64_RELOC_UNKNOWN",
+                Architecture::X86_64 => match self.1 {
+                    macho::X86_64_RELOC_UNSIGNED => "X86_64_RELOC_UNSIGNED",
+                    macho::X86_64_RELOC_SIGNED => "X86_64_RELOC_SIGNED",
+                    macho::X86_64_RELOC_BRANCH => "X86_64_RELOC_BRANCH",
+                    macho::X86_64_RELOC_GOT_LOAD => "X86_64_RELOC_GOT_LOAD",
+                    macho::X86_64_RELOC_GOT => "X86_64_RELOC_GOT",
+                    macho::X86_64_RELOC_SUBTRACTOR => "X86_64_RELOC_SUBTRACTOR",
+                    macho::X86_64_RELOC_SIGNED_1 => "X86_64_RELOC_SIGNED_1",
+                    macho::X86_64_RELOC_SIGNED_2 => "X86_64_RELOC_SIGNED_2",
+                    macho::X86_64_RELOC_SIGNED_4 => "X86_64_RELOC_SIGNED_4",
+                    macho::X86_64_RELOC_TLV => "X86_64_RELOC_TLV",
+                    _ => "X86_64_RELOC_UNKNOWN",
+                },
+                _ => match self.1 {
+                    macho::ARM64_RELOC_ADDEND => "ARM64_RELOC_ADDEND",
+                    macho::ARM64_RELOC_PAGE21 => "ARM64_RELOC_PAGE21",
+                    macho::ARM64_RELOC_PAGEOFF12 => "ARM64_RELOC_PAGEOFF12",
+                    macho::ARM64_RELOC_BRANCH26 => "ARM64_RELOC_BRANCH26",
+                    macho::ARM64_RELOC_UNSIGNED => "ARM64_RELOC_UNSIGNED",
+                    macho::ARM64_RELOC_SUBTRACTOR => "ARM64_RELOC_SUBTRACTOR",
+                    macho::ARM64_RELOC_GOT_LOAD_PAGE21 => "ARM64_RELOC_GOT_LOAD_PAGE21",
+                    macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12 => "ARM64_RELOC_GOT_LOAD_PAGEOFF12",
+                    macho::ARM64_RELOC_TLVP_LOAD_PAGE21 => "ARM64_RELOC_TLVP_LOAD_PAGE21",
+                    macho::ARM64_RELOC_TLVP_LOAD_PAGEOFF12 => "ARM64_RELOC_TLVP_LOAD_PAGEOFF12",
+                    macho::ARM64_RELOC_POINTER_TO_GOT => "ARM64_RELOC_POINTER_TO_GOT",
+                    macho::ARM64_RELOC_AUTHENTICATED_POINTER => "ARM64_RELOC_AUTHENTICATED_POINTER",
+                    _ => "ARM64_RELOC_UNKNOWN",
+                },
             };
-            write!(f, "{name}({})", self.0)
+            write!(f, "{name}({})", self.1)
         }
     }
 
-    Inner(value)
+    Inner(arch, value)
 }
 
 fn page_size(arch: Architecture) -> u64 {
@@ -1714,3 +2492,798 @@ fn page_size(arch: Architecture) -> u64 {
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use object::write;
+    use object::{BinaryFormat, Endianness, ObjectSegment, SymbolFlags, SymbolKind, SymbolScope};
+
+    const IMAGE_BASE: u64 = 0x1_0000_0000;
+    const APP_FUNCTION: &str = "_roc__mainForHost_1_exposed";
+
+    fn push_u32s(out: &mut Vec<u8>, values: &[u32]) {
+        for value in values {
+            out.extend(value.to_le_bytes());
+        }
+    }
+
+    fn name16(name: &[u8]) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..name.len()].copy_from_slice(name);
+        bytes
+    }
+
+    struct SectionFixture {
+        sectname: &'static [u8],
+        addr: u64,
+        size: u64,
+        offset: u32,
+        flags: u32,
+        reserved1: u32,
+        reserved2: u32,
+    }
+
+    fn segment_command(
+        segname: &[u8],
+        (vmaddr, vmsize, fileoff, filesize): (u64, u64, u64, u64),
+        protection: u32,
+        sections: &[SectionFixture],
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        let cmdsize = SEGMENT_COMMAND_SIZE + sections.len() * SECTION_SIZE;
+        push_u32s(&mut out, &[macho::LC_SEGMENT_64, cmdsize as u32]);
+        out.extend(name16(segname));
+        for value in [vmaddr, vmsize, fileoff, filesize] {
+            out.extend(value.to_le_bytes());
+        }
+        push_u32s(
+            &mut out,
+            &[protection, protection, sections.len() as u32, 0],
+        );
+
+        for section in sections {
+            out.extend(name16(section.sectname));
+            out.extend(name16(segname));
+            out.extend(section.addr.to_le_bytes());
+            out.extend(section.size.to_le_bytes());
+            push_u32s(
+                &mut out,
+                &[
+                    section.offset,
+                    2,
+                    0,
+                    0,
+                    section.flags,
+                    section.reserved1,
+                    section.reserved2,
+                    0,
+                ],
+            );
+        }
+
+        out
+    }
+
+    fn dylib_command(name: &[u8]) -> Vec<u8> {
+        let size = (24 + name.len() + 1).next_multiple_of(8);
+        let mut out = Vec::new();
+        push_u32s(
+            &mut out,
+            &[macho::LC_LOAD_DYLIB, size as u32, 24, 2, 0x1_0000, 0x1_0000],
+        );
+        out.extend(name);
+        out.resize(size, 0);
+        out
+    }
+
+    /// A host like `ld` would produce it: it calls `roc__mainForHost_1_exposed` through a stub,
+    /// provides `roc_alloc`, and links `libapp.dylib` and libSystem.
+    fn host_fixture(arch: Architecture, chained_fixups: bool) -> Vec<u8> {
+        let page = page_size(arch);
+        let text_addr = IMAGE_BASE + page - 0x100;
+        let stubs_addr = IMAGE_BASE + page - 0x40;
+        let got_addr = IMAGE_BASE + page;
+        let linkedit_offset = 2 * page;
+
+        let (cputype, cpusubtype, stub, text) = match arch {
+            Architecture::X86_64 => {
+                // jmp *got(%rip)
+                let mut stub = vec![0xff, 0x25];
+                stub.extend(((got_addr - (stubs_addr + 6)) as u32).to_le_bytes());
+
+                // call stub; ret
+                let mut text = vec![0xe8];
+                text.extend(((stubs_addr - (text_addr + 5)) as u32).to_le_bytes());
+                text.push(0xc3);
+
+                (
+                    macho::CPU_TYPE_X86_64,
+                    macho::CPU_SUBTYPE_X86_64_ALL,
+                    stub,
+                    text,
+                )
+            }
+            _ => {
+                let mut stub = Vec::new();
+                push_u32s(
+                    &mut stub,
+                    &[
+                        encode_adrp(0x9000_0010, stubs_addr, got_addr),
+                        encode_page_offset(0xf940_0210, got_addr),
+                        0xd61f_0200,
+                    ],
+                );
+
+                // bl stub; ret
+                let mut text = Vec::new();
+                push_u32s(
+                    &mut text,
+                    &[
+                        0x9400_0000 | ((stubs_addr - text_addr) >> 2) as u32,
+                        0xd65f_03c0,
+                    ],
+                );
+
+                (
+                    macho::CPU_TYPE_ARM64,
+                    macho::CPU_SUBTYPE_ARM64_ALL,
+                    stub,
+                    text,
+                )
+            }
+        };
+
+        // __LINKEDIT: the dyld info, the symbol table, the indirect symbol table and the strings
+        let mut linkedit = Vec::new();
+        let mut got_value = 0u64;
+        let dyld_info_command = if chained_fixups {
+            got_value = chained_bind(0, 0);
+
+            let mut blob = Vec::new();
+            push_u32s(&mut blob, &[0, 32, 80, 84, 1, DYLD_CHAINED_IMPORT, 0, 0]);
+            // starts in image: __PAGEZERO, __TEXT, __DATA_CONST, __LINKEDIT
+            push_u32s(&mut blob, &[4, 0, 0, 24, 0, 0]);
+            // starts in __DATA_CONST
+            blob.extend(24u32.to_le_bytes());
+            blob.extend((page as u16).to_le_bytes());
+            blob.extend(DYLD_CHAINED_PTR_64_OFFSET.to_le_bytes());
+            blob.extend(page.to_le_bytes());
+            blob.extend(0u32.to_le_bytes());
+            blob.extend(1u16.to_le_bytes());
+            blob.extend(0u16.to_le_bytes());
+            assert_eq!(blob.len(), 80);
+            // the import, from dylib 2
+            blob.extend((2u32 | (1 << 9)).to_le_bytes());
+            blob.push(0);
+            blob.extend(APP_FUNCTION.as_bytes());
+            blob.push(0);
+            blob.resize(blob.len().next_multiple_of(8), 0);
+
+            let mut command = Vec::new();
+            push_u32s(
+                &mut command,
+                &[
+                    macho::LC_DYLD_CHAINED_FIXUPS,
+                    16,
+                    linkedit_offset as u32,
+                    blob.len() as u32,
+                ],
+            );
+            linkedit.extend(blob);
+            command
+        } else {
+            let mut binds = vec![
+                macho::BIND_OPCODE_SET_DYLIB_ORDINAL_IMM | 2,
+                macho::BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM,
+            ];
+            binds.extend(APP_FUNCTION.as_bytes());
+            binds.extend([
+                0,
+                macho::BIND_OPCODE_SET_TYPE_IMM | macho::BIND_TYPE_POINTER,
+                macho::BIND_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 2,
+                0,
+                macho::BIND_OPCODE_DO_BIND,
+                macho::BIND_OPCODE_DONE,
+            ]);
+            binds.resize(binds.len().next_multiple_of(8), 0);
+
+            let mut command = Vec::new();
+            push_u32s(
+                &mut command,
+                &[
+                    macho::LC_DYLD_INFO_ONLY,
+                    48,
+                    0,
+                    0,
+                    linkedit_offset as u32,
+                    binds.len() as u32,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ],
+            );
+            linkedit.extend(binds);
+            command
+        };
+
+        let strings = [
+            b" \0".as_slice(),
+            b"_roc_alloc\0",
+            APP_FUNCTION.as_bytes(),
+            b"\0",
+        ]
+        .concat();
+        let symtab_offset = linkedit_offset as usize + linkedit.len();
+        linkedit.extend(2u32.to_le_bytes());
+        linkedit.extend([macho::N_SECT | macho::N_EXT, 1, 0, 0]);
+        linkedit.extend(text_addr.to_le_bytes());
+        linkedit.extend(13u32.to_le_bytes());
+        linkedit.extend([macho::N_UNDF | macho::N_EXT, 0, 0, 2]);
+        linkedit.extend(0u64.to_le_bytes());
+        let indirect_offset = linkedit_offset as usize + linkedit.len();
+        push_u32s(&mut linkedit, &[1, 1]);
+        let strings_offset = linkedit_offset as usize + linkedit.len();
+        linkedit.extend(&strings);
+        linkedit.resize(linkedit.len().next_multiple_of(8), 0);
+
+        let mut symtab = Vec::new();
+        push_u32s(
+            &mut symtab,
+            &[
+                macho::LC_SYMTAB,
+                24,
+                symtab_offset as u32,
+                2,
+                strings_offset as u32,
+                strings.len() as u32,
+            ],
+        );
+
+        let mut dysymtab = vec![0u32; 20];
+        dysymtab[0] = macho::LC_DYSYMTAB;
+        dysymtab[1] = 80;
+        dysymtab[5] = 1; // nextdefsym
+        dysymtab[6] = 1; // iundefsym
+        dysymtab[7] = 1; // nundefsym
+        dysymtab[14] = indirect_offset as u32;
+        dysymtab[15] = 2; // nindirectsyms
+        let mut dysymtab_command = Vec::new();
+        push_u32s(&mut dysymtab_command, &dysymtab);
+
+        let mut main = Vec::new();
+        push_u32s(&mut main, &[macho::LC_MAIN, 24]);
+        main.extend((text_addr - IMAGE_BASE).to_le_bytes());
+        main.extend(0u64.to_le_bytes());
+
+        let read_execute = macho::VM_PROT_READ | macho::VM_PROT_EXECUTE;
+        let read_write = macho::VM_PROT_READ | macho::VM_PROT_WRITE;
+        let commands = [
+            segment_command(b"__PAGEZERO", (0, IMAGE_BASE, 0, 0), 0, &[]),
+            segment_command(
+                b"__TEXT",
+                (IMAGE_BASE, page, 0, page),
+                read_execute,
+                &[
+                    SectionFixture {
+                        sectname: b"__text",
+                        addr: text_addr,
+                        size: text.len() as u64,
+                        offset: (text_addr - IMAGE_BASE) as u32,
+                        flags: macho::S_ATTR_PURE_INSTRUCTIONS | macho::S_ATTR_SOME_INSTRUCTIONS,
+                        reserved1: 0,
+                        reserved2: 0,
+                    },
+                    SectionFixture {
+                        sectname: b"__stubs",
+                        addr: stubs_addr,
+                        size: stub.len() as u64,
+                        offset: (stubs_addr - IMAGE_BASE) as u32,
+                        flags: macho::S_SYMBOL_STUBS
+                            | macho::S_ATTR_PURE_INSTRUCTIONS
+                            | macho::S_ATTR_SOME_INSTRUCTIONS,
+                        reserved1: 0,
+                        reserved2: stub.len() as u32,
+                    },
+                ],
+            ),
+            segment_command(
+                b"__DATA_CONST",
+                (IMAGE_BASE + page, page, page, page),
+                read_write,
+                &[SectionFixture {
+                    sectname: b"__got",
+                    addr: got_addr,
+                    size: 8,
+                    offset: page as u32,
+                    flags: macho::S_NON_LAZY_SYMBOL_POINTERS,
+                    reserved1: 1,
+                    reserved2: 0,
+                }],
+            ),
+            segment_command(
+                b"__LINKEDIT",
+                (
+                    IMAGE_BASE + linkedit_offset,
+                    page,
+                    linkedit_offset,
+                    linkedit.len() as u64,
+                ),
+                macho::VM_PROT_READ,
+                &[],
+            ),
+            dyld_info_command,
+            symtab,
+            dysymtab_command,
+            dylib_command(b"/usr/lib/libSystem.B.dylib"),
+            dylib_command(b"libapp.dylib"),
+            main,
+        ];
+
+        let sizeofcmds: usize = commands.iter().map(Vec::len).sum();
+        let mut out = Vec::new();
+        push_u32s(
+            &mut out,
+            &[
+                macho::MH_MAGIC_64,
+                cputype,
+                cpusubtype,
+                macho::MH_EXECUTE,
+                commands.len() as u32,
+                sizeofcmds as u32,
+                macho::MH_DYLDLINK | macho::MH_TWOLEVEL | macho::MH_PIE,
+                0,
+            ],
+        );
+        for command in commands {
+            out.extend(command);
+        }
+
+        out.resize((text_addr - IMAGE_BASE) as usize, 0);
+        out.extend(text);
+        out.resize((stubs_addr - IMAGE_BASE) as usize, 0);
+        out.extend(stub);
+        out.resize(page as usize, 0);
+        out.extend(got_value.to_le_bytes());
+        out.resize(linkedit_offset as usize, 0);
+        out.extend(linkedit);
+
+        out
+    }
+
+    /// An app that calls into the host and into libSystem, and has a pointer to itself.
+    fn app_fixture(arch: Architecture) -> Vec<u8> {
+        let (architecture, code) = match arch {
+            Architecture::X86_64 => (
+                object::Architecture::X86_64,
+                [
+                    // lea _str(%rip), %rdi
+                    vec![0x48, 0x8d, 0x3d, 0, 0, 0, 0],
+                    // call _roc_alloc
+                    vec![0xe8, 0, 0, 0, 0],
+                    // call _memcpy
+                    vec![0xe8, 0, 0, 0, 0],
+                    // mov _memcpy@GOTPCREL(%rip), %rax
+                    vec![0x48, 0x8b, 0x05, 0, 0, 0, 0],
+                    // ret
+                    vec![0xc3],
+                ]
+                .concat(),
+            ),
+            _ => {
+                let mut code = Vec::new();
+                push_u32s(
+                    &mut code,
+                    &[
+                        0x9000_0000, // adrp x0, _str@PAGE
+                        0x9100_0000, // add x0, x0, _str@PAGEOFF
+                        0x9400_0000, // bl _roc_alloc
+                        0x9400_0000, // bl _memcpy
+                        0x9000_0001, // adrp x1, _memcpy@GOTPAGE
+                        0xf940_0021, // ldr x1, [x1, _memcpy@GOTPAGEOFF]
+                        0xd65f_03c0, // ret
+                    ],
+                );
+                (object::Architecture::Aarch64, code)
+            }
+        };
+
+        let mut obj = write::Object::new(BinaryFormat::MachO, architecture, Endianness::Little);
+        obj.set_mangling(write::Mangling::None);
+
+        let text = obj.section_id(write::StandardSection::Text);
+        let data = obj.section_id(write::StandardSection::ReadOnlyDataWithRel);
+        obj.append_section_data(text, &code, 16);
+        obj.append_section_data(data, b"hi\0\0\0\0\0\0\0\0\0\0\0\0\0\0", 8);
+
+        let mut symbol = |name: &str, section, kind| {
+            obj.add_symbol(write::Symbol {
+                name: name.as_bytes().to_vec(),
+                value: 0,
+                size: 0,
+                kind,
+                scope: SymbolScope::Dynamic,
+                weak: false,
+                section,
+                flags: SymbolFlags::None,
+            })
+        };
+        let main = symbol(
+            APP_FUNCTION,
+            write::SymbolSection::Section(text),
+            SymbolKind::Text,
+        );
+        let string = symbol(
+            "_str",
+            write::SymbolSection::Section(data),
+            SymbolKind::Data,
+        );
+        let roc_alloc = symbol(
+            "_roc_alloc",
+            write::SymbolSection::Undefined,
+            SymbolKind::Text,
+        );
+        let memcpy = symbol("_memcpy", write::SymbolSection::Undefined, SymbolKind::Text);
+
+        let relocations = match arch {
+            Architecture::X86_64 => vec![
+                (3, string, macho::X86_64_RELOC_SIGNED, true),
+                (8, roc_alloc, macho::X86_64_RELOC_BRANCH, true),
+                (13, memcpy, macho::X86_64_RELOC_BRANCH, true),
+                (20, memcpy, macho::X86_64_RELOC_GOT_LOAD, true),
+            ],
+            _ => vec![
+                (0, string, macho::ARM64_RELOC_PAGE21, true),
+                (4, string, macho::ARM64_RELOC_PAGEOFF12, false),
+                (8, roc_alloc, macho::ARM64_RELOC_BRANCH26, true),
+                (12, memcpy, macho::ARM64_RELOC_BRANCH26, true),
+                (16, memcpy, macho::ARM64_RELOC_GOT_LOAD_PAGE21, true),
+                (20, memcpy, macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12, false),
+            ],
+        };
+        for (offset, symbol, r_type, r_pcrel) in relocations {
+            let addend = match (arch, r_pcrel) {
+                (Architecture::X86_64, true) => -4,
+                _ => 0,
+            };
+            obj.add_relocation(
+                text,
+                write::Relocation {
+                    offset,
+                    symbol,
+                    addend,
+                    flags: RelocationFlags::MachO {
+                        r_type,
+                        r_pcrel,
+                        r_length: 2,
+                    },
+                },
+            )
+            .unwrap();
+        }
+
+        let r_type = match arch {
+            Architecture::X86_64 => macho::X86_64_RELOC_UNSIGNED,
+            _ => macho::ARM64_RELOC_UNSIGNED,
+        };
+        obj.add_relocation(
+            data,
+            write::Relocation {
+                offset: 8,
+                symbol: main,
+                addend: 0,
+                flags: RelocationFlags::MachO {
+                    r_type,
+                    r_pcrel: false,
+                    r_length: 3,
+                },
+            },
+        )
+        .unwrap();
+
+        obj.write().unwrap()
+    }
+
+    fn link(arch: Architecture, chained_fixups: bool) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        let host_path = dir.join("host");
+        let metadata_path = dir.join("metadata");
+        let executable_path = dir.join("app");
+
+        std::fs::write(&host_path, host_fixture(arch, chained_fixups)).unwrap();
+
+        preprocess_macho_le(
+            arch,
+            &host_path,
+            &metadata_path,
+            &executable_path,
+            Path::new("libapp.dylib"),
+            false,
+            false,
+        );
+
+        surgery_macho(
+            &app_fixture(arch),
+            &metadata_path,
+            &executable_path,
+            false,
+            false,
+        );
+
+        std::fs::read(executable_path).unwrap()
+    }
+
+    /// The address that a pc-relative branch at `address` jumps to
+    fn branch_target(arch: Architecture, data: &[u8], offset: usize, address: u64) -> u64 {
+        match arch {
+            Architecture::X86_64 => {
+                let displacement = i32::from_le_bytes(data[offset + 1..][..4].try_into().unwrap());
+                (address as i64 + 5 + displacement as i64) as u64
+            }
+            _ => {
+                let imm26 = read_u32(data, offset) & 0x03ff_ffff;
+                let displacement = ((imm26 << 6) as i32 >> 6) << 2;
+                (address as i64 + displacement as i64) as u64
+            }
+        }
+    }
+
+    struct Linked<'a> {
+        data: &'a [u8],
+        object: object::File<'a, &'a [u8]>,
+        commands: LoadCommands,
+    }
+
+    impl<'a> Linked<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            Self {
+                data,
+                object: object::File::parse(data).unwrap(),
+                commands: LoadCommands::parse(data),
+            }
+        }
+
+        fn segment_names(&self) -> Vec<String> {
+            self.object
+                .segments()
+                .map(|segment| segment.name().unwrap().unwrap().to_string())
+                .collect()
+        }
+
+        fn section(&self, segname: &[u8], sectname: &[u8]) -> (usize, u64) {
+            let section = self
+                .commands
+                .sections(self.data)
+                .find(|section| {
+                    name_matches(&section.segname, segname)
+                        && name_matches(&section.sectname, sectname)
+                })
+                .unwrap();
+
+            (section.offset.get(LE) as usize, section.addr.get(LE))
+        }
+
+        fn check_signature(&self) {
+            let cmd = load_struct_inplace::<LinkeditDataCommand<LE>>(
+                self.data,
+                self.commands.code_signature.unwrap(),
+            );
+            let offset = cmd.dataoff.get(LE) as usize;
+            assert_eq!(offset + cmd.datasize.get(LE) as usize, self.data.len());
+            assert_eq!(&self.data[offset..offset + 4], &[0xfa, 0xde, 0x0c, 0xc0]);
+
+            // the signature covers everything before it
+            let code_directory = offset + 20;
+            let code_limit =
+                u32::from_be_bytes(self.data[code_directory + 32..][..4].try_into().unwrap());
+            assert_eq!(code_limit as usize, offset);
+
+            let linkedit = self.object.segments().last().unwrap();
+            assert_eq!(linkedit.name().unwrap(), Some("__LINKEDIT"));
+            let (fileoff, filesize) = linkedit.file_range();
+            assert_eq!((fileoff + filesize) as usize, self.data.len());
+        }
+    }
+
+    #[test]
+    fn link_aarch64_with_chained_fixups() {
+        let arch = Architecture::Aarch64;
+        let data = link(arch, true);
+        let linked = Linked::new(&data);
+
+        assert_eq!(
+            linked.segment_names(),
+            [
+                "__PAGEZERO",
+                "__TEXT",
+                "__DATA_CONST",
+                "__ROC_TEXT",
+                "__ROC_DATA",
+                "__LINKEDIT"
+            ]
+        );
+        linked.check_signature();
+
+        let (text_offset, main) = linked.section(b"__ROC_TEXT", b"__text");
+        let (const_offset, string) = linked.section(b"__ROC_DATA", b"__const");
+        let (_, got) = linked.section(b"__ROC_DATA", b"__got");
+        let host_text = IMAGE_BASE + 0x4000 - 0x100;
+        let host_stub = IMAGE_BASE + 0x4000 - 0x40;
+
+        // the host's stub jumps straight into the app
+        let stub_offset = (host_stub - IMAGE_BASE) as usize;
+        assert_eq!(branch_target(arch, &data, stub_offset, host_stub), main);
+        assert_eq!(read_u32(&data, stub_offset + 4), ARM64_NOP);
+
+        // adrp x0, _str@PAGE; add x0, x0, _str@PAGEOFF
+        assert_eq!(
+            read_u32(&data, text_offset),
+            encode_adrp(0x9000_0000, main, string)
+        );
+        assert_eq!(
+            read_u32(&data, text_offset + 4),
+            encode_page_offset(0x9100_0000, string)
+        );
+
+        // calls into the host, and into libSystem through a new stub
+        assert_eq!(
+            branch_target(arch, &data, text_offset + 8, main + 8),
+            host_text
+        );
+        let memcpy_stub = branch_target(arch, &data, text_offset + 12, main + 12);
+        let memcpy_stub_offset = text_offset + (memcpy_stub - main) as usize;
+        assert_eq!(
+            read_u32(&data, memcpy_stub_offset),
+            encode_adrp(0x9000_0010, memcpy_stub, got)
+        );
+        assert_eq!(
+            read_u32(&data, memcpy_stub_offset + 4),
+            encode_page_offset(0xf940_0210, got)
+        );
+        assert_eq!(
+            read_u32(&data, text_offset + 16),
+            encode_adrp(0x9000_0001, main + 16, got)
+        );
+
+        // all pointers are chained fixups
+        let fixups = ChainedFixups::parse(linkedit_data(
+            &data,
+            linked.commands.chained_fixups.unwrap(),
+        ));
+        assert_eq!(fixups.segment_count(), 6);
+        assert_eq!(fixups.imports_count, 2);
+        assert_eq!(fixups.import(0), (2, APP_FUNCTION.as_bytes()));
+        assert_eq!(fixups.import(1), (1, b"_memcpy".as_slice()));
+
+        let mut found = MutMap::default();
+        fixups.for_each_fixup(&data, &linked.commands, |offset, raw| {
+            found.insert(offset, raw);
+        });
+        let rebase =
+            |target: u64| chained_rebase(DYLD_CHAINED_PTR_64_OFFSET, IMAGE_BASE, target, 0);
+
+        let host_got_offset = 0x4000;
+        let app_got_offset = const_offset + (got - string) as usize;
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[&host_got_offset], rebase(main));
+        assert_eq!(found[&app_got_offset], chained_bind(1, 0));
+        assert_eq!(found[&(const_offset + 8)] & !(0xfff << 51), rebase(main));
+        assert_eq!(read_u64(&data, host_got_offset), rebase(main));
+    }
+
+    #[test]
+    fn link_x86_64_with_dyld_info() {
+        let arch = Architecture::X86_64;
+        let data = link(arch, false);
+        let linked = Linked::new(&data);
+
+        assert_eq!(
+            linked.segment_names(),
+            [
+                "__PAGEZERO",
+                "__TEXT",
+                "__DATA_CONST",
+                "__ROC_TEXT",
+                "__ROC_DATA",
+                "__LINKEDIT"
+            ]
+        );
+        linked.check_signature();
+
+        let (text_offset, main) = linked.section(b"__ROC_TEXT", b"__text");
+        let (const_offset, string) = linked.section(b"__ROC_DATA", b"__const");
+        let (got_offset, got) = linked.section(b"__ROC_DATA", b"__got");
+        let host_text = IMAGE_BASE + 0x1000 - 0x100;
+        let host_stub = IMAGE_BASE + 0x1000 - 0x40;
+
+        // the call in the host, and the stub, both go straight to the app
+        let host_text_offset = (host_text - IMAGE_BASE) as usize;
+        assert_eq!(
+            branch_target(arch, &data, host_text_offset, host_text),
+            main
+        );
+        let stub_offset = (host_stub - IMAGE_BASE) as usize;
+        assert_eq!(data[stub_offset], 0xe9);
+        assert_eq!(branch_target(arch, &data, stub_offset, host_stub), main);
+        assert_eq!(data[stub_offset + 5], 0x90);
+
+        let rip_relative = |offset: usize| {
+            let displacement =
+                i32::from_le_bytes(data[text_offset + offset..][..4].try_into().unwrap());
+            (main as i64 + offset as i64 + 4 + displacement as i64) as u64
+        };
+
+        assert_eq!(rip_relative(3), string);
+        assert_eq!(
+            branch_target(arch, &data, text_offset + 7, main + 7),
+            host_text
+        );
+        let memcpy_stub = branch_target(arch, &data, text_offset + 12, main + 12);
+        let memcpy_stub_offset = text_offset + (memcpy_stub - main) as usize;
+        assert_eq!(&data[memcpy_stub_offset..][..2], &[0xff, 0x25]);
+        let displacement =
+            i32::from_le_bytes(data[memcpy_stub_offset + 2..][..4].try_into().unwrap());
+        assert_eq!((memcpy_stub as i64 + 6 + displacement as i64) as u64, got);
+        assert_eq!(rip_relative(20), got);
+
+        // the pointer to the app is rebased, and _memcpy is bound by dyld
+        assert_eq!(read_u64(&data, const_offset + 8), main);
+        assert_eq!(read_u64(&data, got_offset), 0);
+
+        let cmd = load_struct_inplace::<macho::DyldInfoCommand<LE>>(
+            &data,
+            linked.commands.dyld_info.unwrap(),
+        );
+        let opcodes = |offset: &object::U32<LE>, size: &object::U32<LE>| {
+            let offset = offset.get(LE) as usize;
+            data[offset..offset + size.get(LE) as usize].to_vec()
+        };
+
+        let segment = 4;
+        let expected_rebases = [
+            macho::REBASE_OPCODE_SET_TYPE_IMM | macho::REBASE_TYPE_POINTER,
+            macho::REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | segment,
+            8,
+            macho::REBASE_OPCODE_DO_REBASE_IMM_TIMES | 1,
+            macho::REBASE_OPCODE_DONE,
+        ];
+        assert_eq!(opcodes(&cmd.rebase_off, &cmd.rebase_size), expected_rebases);
+
+        let binds = opcodes(&cmd.bind_off, &cmd.bind_size);
+        let memcpy = binds.windows(8).position(|w| w == b"_memcpy\0").unwrap();
+        assert_eq!(binds[0], macho::BIND_OPCODE_SET_DYLIB_ORDINAL_IMM | 1);
+        assert_eq!(
+            binds[memcpy + 10],
+            (got - string) as u8,
+            "the __got slot of _memcpy"
+        );
+        let host_binds = binds
+            .windows(APP_FUNCTION.len())
+            .position(|w| w == APP_FUNCTION.as_bytes());
+        assert!(host_binds.unwrap() > memcpy);
+    }
+
+    #[test]
+    fn preprocess_needs_room_for_load_commands() {
+        let mut host = host_fixture(Architecture::Aarch64, true);
+        let commands = LoadCommands::parse(&host);
+        assert!(commands.free_space(&host) > 0x3000);
+
+        // move __text right behind the load commands
+        let text = load_structs_inplace_mut::<Section64<LE>>(
+            &mut host,
+            commands.segments[1] + SEGMENT_COMMAND_SIZE,
+            1,
+        );
+        text[0].offset.set(LE, (commands.end + 0x100) as u32);
+
+        let commands = LoadCommands::parse(&host);
+        assert_eq!(commands.free_space(&host), 0x100);
+    }
+}
@@ -15,7 +15,7 @@ use object::{
 };
 use serde::{Deserialize, Serialize};
 
-use roc_collections::{MutMap, VecMap};
+use roc_collections::MutMap;
 use roc_error_macros::internal_error;
 
 use crate::{
@@ -255,10 +255,37 @@ fn remove_dummy_dll_import_table_entry(executable: &mut [u8], md: &PeMetadata) {
 fn relocate_to(
     executable: &mut [u8],
     file_offset: usize,
-    destination_in_file: i64,
+    value: i64,
     relocation: &object::Relocation,
 ) {
     match relocation.size() {
+        7 => {
+            // SECREL7: only the low 7 bits of the byte are the offset
+            let byte = &mut executable[file_offset];
+            let implicit = if relocation.has_implicit_addend() {
+                (*byte & 0x7f) as i64
+            } else {
+                0
+            };
+
+            let delta = value + relocation.addend() + implicit;
+
+            *byte = (*byte & 0x80) | (delta as u8 & 0x7f);
+        }
+
+        16 => {
+            let slice = &mut executable[file_offset..][..2];
+            let implicit = if relocation.has_implicit_addend() {
+                i16::from_le_bytes(slice.try_into().unwrap())
+            } else {
+                0
+            };
+
+            let delta = value + relocation.addend() + implicit as i64;
+
+            slice.copy_from_slice(&(delta as i16).to_le_bytes());
+        }
+
         32 => {
             let slice = &mut executable[file_offset..][..4];
             let implicit = if relocation.has_implicit_addend() {
@@ -267,7 +294,7 @@ fn relocate_to(
                 0
             };
 
-            let delta = destination_in_file + relocation.addend() + implicit as i64;
+            let delta = value + relocation.addend() + implicit as i64;
 
             slice.copy_from_slice(&(delta as i32).to_le_bytes());
         }
@@ -280,39 +307,387 @@ fn relocate_to(
                 0
             };
 
-            let delta = destination_in_file + relocation.addend() + implicit;
+            let delta = value + relocation.addend() + implicit;
 
             slice.copy_from_slice(&delta.to_le_bytes());
         }
 
-        other => unimplemented!("relocations of {other} bits are not supported"),
+        other => internal_error!("relocations of {other} bits are not supported"),
+    }
+}
+
+/// A relocation target, resolved to its place in the final executable
+#[derive(Debug, Clone, Copy)]
+struct Destination {
+    /// Relative virtual address of the target
+    rva: i64,
+    /// The app section the target ends up in, if the app defines it
+    section_kind: Option<SectionKind>,
+}
+
+/// Tables of the host that the app's entries are merged into
+#[derive(Debug)]
+struct HostTables {
+    /// The `RUNTIME_FUNCTION` entries of the exception directory, usually the host's `.pdata`
+    unwind_entries: Vec<u8>,
+    /// The blocks of the base relocation directory, usually the host's `.reloc`
+    base_relocations: Vec<u8>,
+}
+
+impl HostTables {
+    fn from_executable(data: &[u8]) -> Self {
+        let file = object::read::pe::PeFile64::parse(data)
+            .unwrap_or_else(|err| internal_error!("Failed to parse executable file: {}", err));
+        let sections = file.section_table();
+
+        let directory = |index| {
+            file.data_directory(index)
+                .and_then(|dir| dir.data(data, &sections).ok())
+                .unwrap_or_default()
+        };
+
+        // only keep whole blocks; the directory may be followed by zeroed-out padding
+        let mut base_relocations = directory(pe::IMAGE_DIRECTORY_ENTRY_BASERELOC);
+        let mut length = 0;
+        while let Some(header) = base_relocations.get(length..length + 8) {
+            let size_of_block = u32::from_le_bytes(header[4..].try_into().unwrap()) as usize;
+
+            if size_of_block < 8 || length + size_of_block > base_relocations.len() {
+                break;
+            }
+
+            length += size_of_block;
+        }
+        base_relocations = &base_relocations[..length];
+
+        Self {
+            unwind_entries: directory(pe::IMAGE_DIRECTORY_ENTRY_EXCEPTION).to_vec(),
+            base_relocations: base_relocations.to_vec(),
+        }
+    }
+}
+
+/// Where the app sections, and the tables the linker generates for them, end up in the executable.
+/// Offsets of the generated tables are relative to the start of `.rdata1`.
+#[derive(Debug)]
+struct AppLayout {
+    text_rva: u32,
+    text_length: usize,
+    rdata_rva: u32,
+    /// Length of `.rdata1`, including the generated tables
+    rdata_length: usize,
+
+    /// The `__imp_` symbols the app refers to. Each one gets an 8-byte slot that holds the
+    /// address of the function, like the import address table of a regular .dll would.
+    import_slots: Vec<String>,
+    import_slots_offset: usize,
+
+    /// The exception directory must be one sorted table, so when the app has `.pdata`, the host
+    /// entries are copied and followed by the app entries (which come later in memory)
+    unwind_table_offset: usize,
+    unwind_table_length: usize,
+
+    /// Absolute relocations need the loader to rebase them, so the host base relocations are
+    /// copied and followed by blocks for the app
+    base_relocations_offset: usize,
+    base_relocations_capacity: usize,
+}
+
+impl AppLayout {
+    fn new(app: &AppSections, host: &HostTables, text_rva: u32, section_alignment: usize) -> Self {
+        let length_of = |kind| -> usize {
+            app.sections
+                .iter()
+                .filter(|s| s.kind == kind)
+                .map(|s| s.offset_in_kind as usize + s.bytes.len())
+                .max()
+                .unwrap_or_default()
+        };
+
+        let text_length = length_of(SectionKind::Text);
+        let rdata_rva = text_rva + next_multiple_of(text_length, section_alignment) as u32;
+
+        let relocations = || {
+            app.sections
+                .iter()
+                .flat_map(|s| s.relocations.iter())
+                .flat_map(|(name, rs)| rs.iter().map(move |r| (name, r)))
+        };
+
+        let mut import_slots: Vec<String> = relocations()
+            .filter(|(name, _)| name.starts_with("__imp_"))
+            .map(|(name, _)| name.clone())
+            .collect();
+        import_slots.sort_unstable();
+        import_slots.dedup();
+
+        let absolute_relocations = import_slots.len()
+            + relocations()
+                .filter(|(_, r)| r.relocation.kind() == object::RelocationKind::Absolute)
+                .count();
+
+        let app_unwind_entries: usize = app
+            .sections
+            .iter()
+            .filter(|s| s.is_unwind_table)
+            .map(|s| s.bytes.len())
+            .sum();
+
+        let import_slots_offset = next_multiple_of(length_of(SectionKind::ReadOnlyData), 8);
+        let unwind_table_offset = import_slots_offset + 8 * import_slots.len();
+        let unwind_table_length = match app_unwind_entries {
+            0 => 0,
+            n => host.unwind_entries.len() + n,
+        };
+
+        // worst case, every relocation is on its own page, with a header and padding entry
+        let base_relocations_offset =
+            next_multiple_of(unwind_table_offset + unwind_table_length, 4);
+        let base_relocations_capacity = match absolute_relocations {
+            0 => 0,
+            n => host.base_relocations.len() + n * 12,
+        };
+
+        Self {
+            text_rva,
+            text_length,
+            rdata_rva,
+            rdata_length: base_relocations_offset + base_relocations_capacity,
+            import_slots,
+            import_slots_offset,
+            unwind_table_offset,
+            unwind_table_length,
+            base_relocations_offset,
+            base_relocations_capacity,
+        }
+    }
+
+    fn section_rva(&self, kind: SectionKind) -> u32 {
+        match kind {
+            SectionKind::Text => self.text_rva,
+            SectionKind::ReadOnlyData => self.rdata_rva,
+        }
+    }
+
+    /// The one-based index of the section header of the app section of this kind
+    fn section_number(kind: SectionKind, host_section_count: usize) -> usize {
+        match kind {
+            SectionKind::Text => host_section_count + 1,
+            SectionKind::ReadOnlyData => host_section_count + 2,
+        }
+    }
+
+    fn import_slot_rva(&self, name: &str) -> Option<u32> {
+        let index = self.import_slots.iter().position(|slot| slot == name)?;
+
+        Some(self.rdata_rva + (self.import_slots_offset + 8 * index) as u32)
+    }
+
+    fn resolve(
+        &self,
+        name: &str,
+        app_relocation: &AppRelocation,
+        md: &PeMetadata,
+        section_starts: &MutMap<SectionIndex, (SectionKind, u64)>,
+    ) -> Destination {
+        let app_target = app_relocation
+            .target
+            .and_then(|(index, address)| Some((section_starts.get(&index)?, address)));
+
+        if let Some(destination) = md.exports.get(name) {
+            Destination {
+                rva: *destination,
+                section_kind: None,
+            }
+        } else if let Some(((kind, start), address)) = app_target {
+            // relocations between two places within the app, as opposed to relocations for
+            // symbols the app imports from the host
+            Destination {
+                rva: self.section_rva(*kind) as i64 + (start + address) as i64,
+                section_kind: Some(*kind),
+            }
+        } else if let Some(rva) = self.import_slot_rva(name) {
+            Destination {
+                rva: rva as i64,
+                section_kind: Some(SectionKind::ReadOnlyData),
+            }
+        } else if name == "___chkstk_ms" {
+            // this is a stack probe that is inserted when a function uses more than 2
+            // pages of stack space. The source of this function is not linked in, so we
+            // have to do it ourselves. We patch in the bytes as a separate section, and
+            // here just need to jump to those bytes
+
+            // This relies on the ___CHKSTK_MS section being the last text section in the list of sections
+            let offset = self.text_length - ___CHKSTK_MS.len();
+
+            Destination {
+                rva: self.text_rva as i64 + offset as i64,
+                section_kind: Some(SectionKind::Text),
+            }
+        } else {
+            let address = app_relocation.address;
+
+            if address == 0 && !name.starts_with("roc") && !is_ingested_compiler_rt(name) {
+                eprintln!(
+                    "I don't know the address of the {name} function! this may cause segfaults"
+                );
+            }
+
+            Destination {
+                rva: address as i64,
+                section_kind: None,
+            }
+        }
     }
 }
 
+fn is_ingested_compiler_rt(name: &str) -> bool {
+    [
+        "__addtf3",
+        "__ceilx",
+        "__cmpdf2",
+        "__cmphf2",
+        "__cmpsf2",
+        "__cmptf2",
+        "__cmpxf2",
+        "__cosx",
+        "__divsf3",
+        "__divtf3",
+        "__divti3",
+        "__exp2x",
+        "__expx",
+        "__extendhfsf2",
+        "__fabsx",
+        "__fixdfti",
+        "__fixsfti",
+        "__fixunsdfti",
+        "__fixunssfti",
+        "__floorx",
+        "__fmax",
+        "__fmaxx",
+        "__fminx",
+        "__fmodx",
+        "__gedf2",
+        "__gehf2",
+        "__gesf2",
+        "__getf2",
+        "__gexf2",
+        "__log10x",
+        "__log2x",
+        "__logx",
+        "__lshrti3",
+        "__modti3",
+        "__muloti4",
+        "__multf3",
+        "__roundx",
+        "__sincosx",
+        "__sinx",
+        "__sqrtx",
+        "__tanx",
+        "__truncsfhf2",
+        "__truncx",
+        "__udivmoddi4",
+        "__udivti3",
+        "__umodti3",
+        "ceilq",
+        "cos",
+        "cosf",
+        "cosq",
+        "exp",
+        "exp2",
+        "exp2q",
+        "expf",
+        "expq",
+        "floor",
+        "floorf",
+        "floorq",
+        "fmaq",
+        "fmaxf",
+        "fmaxl",
+        "fmodf",
+        "log10",
+        "log10q",
+        "log2",
+        "log2q",
+        "logq",
+        "memcpy",
+        "roundq",
+        "sin",
+        "sincos",
+        "sincosf",
+        "sincosq",
+        "sinf",
+        "sinq",
+        "sqrt",
+        "sqrtf",
+        "sqrtq",
+        "tan",
+        "tanf",
+        "tanq",
+    ]
+    .contains(&name)
+}
+
+/// Encodes base relocations, given as (relative virtual address, type) pairs, into blocks that
+/// each cover one 4K page
+fn base_relocation_blocks(mut relocations: Vec<(u32, u16)>) -> Vec<u8> {
+    relocations.sort_unstable();
+
+    let mut blocks = Vec::new();
+    let mut it = relocations.iter().peekable();
+
+    while let Some((first, _)) = it.peek() {
+        let page = first & !0xfff;
+
+        let mut entries = Vec::new();
+        while let Some((rva, typ)) = it.next_if(|(rva, _)| rva & !0xfff == page) {
+            entries.push((typ << 12) | (rva & 0xfff) as u16);
+        }
+
+        // blocks must start at a 32-bit boundary; pad with an entry that the loader skips
+        if entries.len() % 2 == 1 {
+            entries.push(pe::IMAGE_REL_BASED_ABSOLUTE << 12);
+        }
+
+        let size_of_block = std::mem::size_of::<ImageBaseRelocation>() + 2 * entries.len();
+
+        blocks.extend(page.to_le_bytes());
+        blocks.extend((size_of_block as u32).to_le_bytes());
+        blocks.extend(entries.iter().flat_map(|entry| entry.to_le_bytes()));
+    }
+
+    blocks
+}
+
 pub(crate) fn surgery_pe(executable_path: &Path, metadata_path: &Path, roc_app_bytes: &[u8]) {
     let md = PeMetadata::read_from_file(metadata_path);
 
     let app_obj_sections = AppSections::from_data(roc_app_bytes);
 
-    let mut symbols = app_obj_sections.roc_symbols;
-
     let image_base: u64 = md.image_base;
     let file_alignment = md.file_alignment as usize;
     let section_alignment = md.section_alignment as usize;
 
-    let app_sections_size: usize = app_obj_sections
-        .sections
-        .iter()
-        .map(|s| next_multiple_of(s.bytes.len(), file_alignment))
-        .sum();
-
-    let executable = &mut open_mmap_mut(executable_path, md.dynhost_file_size + app_sections_size);
-
     let app_code_section_va = md.last_host_section_address
         + next_multiple_of(md.last_host_section_size as usize, section_alignment) as u64;
 
+    let host_tables = HostTables::from_executable(&open_mmap(executable_path));
+    let layout = AppLayout::new(
+        &app_obj_sections,
+        &host_tables,
+        (app_code_section_va - image_base) as u32,
+        section_alignment,
+    );
+
+    let mut symbols = app_obj_sections.roc_symbols;
+
+    let app_sections_size = next_multiple_of(layout.text_length, file_alignment)
+        + next_multiple_of(layout.rdata_length, file_alignment);
+
+    let executable = &mut open_mmap_mut(executable_path, md.dynhost_file_size + app_sections_size);
+
     let mut section_file_offset = md.dynhost_file_size;
-    let mut section_virtual_address = (app_code_section_va - image_base) as u32;
+    let mut section_virtual_address = layout.text_rva;
 
     // find the location to write the section headers for our new sections
     let mut section_header_start = md.dynamic_relocations.section_headers_offset_in_file as usize
@@ -324,22 +699,17 @@ pub(crate) fn surgery_pe(executable_path: &Path, metadata_path: &Path, roc_app_b
     let mut data_bytes_added = 0;
     let mut file_bytes_added = 0;
 
-    // relocations between the sections of the roc application
-    // (as opposed to relocations for symbols the app imports from the host)
-    let inter_app_relocations = process_internal_relocations(
-        &app_obj_sections.sections,
-        &app_obj_sections.other_symbols,
-        (app_code_section_va - image_base) as u32,
-        section_alignment,
-    );
+    // (relative virtual address, type) of every place the loader must rebase
+    let mut base_relocations: Vec<(u32, u16)> = Vec::new();
+
+    // file ranges of the (relocated) `.pdata` sections of the app
+    let mut app_unwind_entries = Vec::new();
 
     for kind in [SectionKind::Text, SectionKind::ReadOnlyData] {
-        let length: usize = app_obj_sections
-            .sections
-            .iter()
-            .filter(|s| s.kind == kind)
-            .map(|s| s.bytes.len())
-            .sum();
+        let length = match kind {
+            SectionKind::Text => layout.text_length,
+            SectionKind::ReadOnlyData => layout.rdata_length,
+        };
 
         // offset_in_section now becomes a proper virtual address
         for symbol in symbols.iter_mut() {
@@ -383,166 +753,90 @@ pub(crate) fn surgery_pe(executable_path: &Path, metadata_path: &Path, roc_app_b
             }
         }
 
-        let mut offset = section_file_offset;
         let it = app_obj_sections.sections.iter().filter(|s| s.kind == kind);
         for section in it {
+            let offset = section_file_offset + section.offset_in_kind as usize;
             let slice = section.bytes;
             executable[offset..][..slice.len()].copy_from_slice(slice);
 
+            let section_rva = section_virtual_address as i64 + section.offset_in_kind as i64;
+
             let it = section
                 .relocations
                 .iter()
                 .flat_map(|(name, rs)| rs.iter().map(move |r| (name, r)));
 
             for (name, app_relocation) in it {
-                let AppRelocation {
-                    offset_in_section,
-                    relocation,
-                    address,
-                } = app_relocation;
-
-                if let Some(destination) = md.exports.get(name) {
-                    match relocation.kind() {
-                        object::RelocationKind::Relative => {
-                            relocate_to(
-                                executable,
-                                offset + *offset_in_section as usize,
-                                destination
-                                    - section_virtual_address as i64
-                                    - *offset_in_section as i64,
-                                relocation,
-                            );
-                        }
-                        _ => todo!(),
+                let relocation = &app_relocation.relocation;
+                let place = section_rva + app_relocation.offset_in_section as i64;
+
+                // padding that MSVC emits, which relocates nothing
+                if let object::RelocationFlags::Coff {
+                    typ: pe::IMAGE_REL_AMD64_ABSOLUTE,
+                } = relocation.flags()
+                {
+                    continue;
+                }
+
+                let destination =
+                    layout.resolve(name, app_relocation, &md, &app_obj_sections.section_starts);
+
+                let value = match relocation.kind() {
+                    object::RelocationKind::Relative => destination.rva - place,
+                    object::RelocationKind::Absolute => {
+                        let typ = match relocation.size() {
+                            64 => pe::IMAGE_REL_BASED_DIR64,
+                            32 => pe::IMAGE_REL_BASED_HIGHLOW,
+                            other => internal_error!(
+                                "absolute relocations of {other} bits are not supported"
+                            ),
+                        };
+
+                        base_relocations.push((place as u32, typ));
+
+                        image_base as i64 + destination.rva
                     }
-                } else if let Some(destination) = inter_app_relocations.get(name) {
-                    relocate_to(
-                        executable,
-                        offset + *offset_in_section as usize,
-                        destination - section_virtual_address as i64 - *offset_in_section as i64,
-                        relocation,
-                    );
-                } else if name == "___chkstk_ms" {
-                    // this is a stack probe that is inserted when a function uses more than 2
-                    // pages of stack space. The source of this function is not linked in, so we
-                    // have to do it ourselves. We patch in the bytes as a separate section, and
-                    // here just need to jump to those bytes
-
-                    // This relies on the ___CHKSTK_MS section being the last text section in the list of sections
-                    let destination = length - ___CHKSTK_MS.len();
-
-                    relocate_to(
-                        executable,
-                        offset + *offset_in_section as usize,
-                        destination as i64 - *offset_in_section as i64,
-                        relocation,
-                    );
-                } else {
-                    let is_ingested_compiler_rt = [
-                        "__addtf3",
-                        "__ceilx",
-                        "__cmpdf2",
-                        "__cmphf2",
-                        "__cmpsf2",
-                        "__cmptf2",
-                        "__cmpxf2",
-                        "__cosx",
-                        "__divsf3",
-                        "__divtf3",
-                        "__divti3",
-                        "__exp2x",
-                        "__expx",
-                        "__extendhfsf2",
-                        "__fabsx",
-                        "__fixdfti",
-                        "__fixsfti",
-                        "__fixunsdfti",
-                        "__fixunssfti",
-                        "__floorx",
-                        "__fmax",
-                        "__fmaxx",
-                        "__fminx",
-                        "__fmodx",
-                        "__gedf2",
-                        "__gehf2",
-                        "__gesf2",
-                        "__getf2",
-                        "__gexf2",
-                        "__log10x",
-                        "__log2x",
-                        "__logx",
-                        "__lshrti3",
-                        "__modti3",
-                        "__muloti4",
-                        "__multf3",
-                        "__roundx",
-                        "__sincosx",
-                        "__sinx",
-                        "__sqrtx",
-                        "__tanx",
-                        "__truncsfhf2",
-                        "__truncx",
-                        "__udivmoddi4",
-                        "__udivti3",
-                        "__umodti3",
-                        "ceilq",
-                        "cos",
-                        "cosf",
-                        "cosq",
-                        "exp",
-                        "exp2",
-                        "exp2q",
-                        "expf",
-                        "expq",
-                        "floor",
-                        "floorf",
-                        "floorq",
-                        "fmaq",
-                        "fmaxf",
-                        "fmaxl",
-                        "fmodf",
-                        "log10",
-                        "log10q",
-                        "log2",
-                        "log2q",
-                        "logq",
-                        "memcpy",
-                        "roundq",
-                        "sin",
-                        "sincos",
-                        "sincosf",
-                        "sincosq",
-                        "sinf",
-                        "sinq",
-                        "sqrt",
-                        "sqrtf",
-                        "sqrtq",
-                        "tan",
-                        "tanf",
-                        "tanq",
-                    ]
-                    .contains(&name.as_str());
-                    if *address == 0 && !name.starts_with("roc") && !is_ingested_compiler_rt {
-                        eprintln!(
-                            "I don't know the address of the {name} function! this may cause segfaults"
-                        );
+                    object::RelocationKind::ImageOffset => destination.rva,
+                    object::RelocationKind::SectionOffset => match destination.section_kind {
+                        Some(kind) => destination.rva - layout.section_rva(kind) as i64,
+                        None => internal_error!(
+                            "section-relative relocation to {name}, which is not defined by the app"
+                        ),
+                    },
+                    object::RelocationKind::SectionIndex => match destination.section_kind {
+                        Some(kind) => AppLayout::section_number(kind, md.host_section_count) as i64,
+                        None => internal_error!(
+                            "section index relocation to {name}, which is not defined by the app"
+                        ),
+                    },
+                    _ => {
+                        internal_error!("unsupported relocation {:?} to {name}", relocation.flags())
                     }
+                };
 
-                    match relocation.kind() {
-                        object::RelocationKind::Relative => {
-                            relocate_to(
-                                executable,
-                                offset + *offset_in_section as usize,
-                                *address as i64 - *offset_in_section as i64,
-                                relocation,
-                            );
-                        }
-                        _ => todo!(),
-                    }
-                }
+                relocate_to(
+                    executable,
+                    offset + app_relocation.offset_in_section as usize,
+                    value,
+                    relocation,
+                );
             }
 
-            offset += slice.len();
+            if section.is_unwind_table {
+                app_unwind_entries.push(offset..offset + slice.len());
+            }
+        }
+
+        if kind == SectionKind::ReadOnlyData {
+            write_generated_tables(
+                executable,
+                &md,
+                &layout,
+                &host_tables,
+                section_file_offset,
+                &app_unwind_entries,
+                std::mem::take(&mut base_relocations),
+            );
         }
 
         section_header_start += std::mem::size_of::<ImageSectionHeader>();
@@ -572,6 +866,90 @@ pub(crate) fn surgery_pe(executable_path: &Path, metadata_path: &Path, roc_app_b
     );
 }
 
+/// Writes the import slots, exception directory and base relocations of the app after its
+/// read-only data, and points the data directories of the executable at them
+fn write_generated_tables(
+    executable: &mut [u8],
+    md: &PeMetadata,
+    layout: &AppLayout,
+    host_tables: &HostTables,
+    rdata_file_offset: usize,
+    app_unwind_entries: &[std::ops::Range<usize>],
+    mut base_relocations: Vec<(u32, u16)>,
+) {
+    for (index, slot) in layout.import_slots.iter().enumerate() {
+        let name = slot.trim_start_matches("__imp_");
+        let offset = layout.import_slots_offset + 8 * index;
+
+        let address = match md.exports.get(name) {
+            Some(rva) => md.image_base as i64 + rva,
+            None => {
+                eprintln!(
+                    "I don't know the address of the {name} function! this may cause segfaults"
+                );
+                0
+            }
+        };
+
+        executable[rdata_file_offset + offset..][..8].copy_from_slice(&address.to_le_bytes());
+
+        base_relocations.push((layout.rdata_rva + offset as u32, pe::IMAGE_REL_BASED_DIR64));
+    }
+
+    if layout.unwind_table_length > 0 {
+        let mut table = host_tables.unwind_entries.clone();
+        for range in app_unwind_entries {
+            table.extend_from_slice(&executable[range.clone()]);
+        }
+
+        let offset = rdata_file_offset + layout.unwind_table_offset;
+        executable[offset..][..table.len()].copy_from_slice(&table);
+
+        update_data_directory(
+            executable,
+            md,
+            pe::IMAGE_DIRECTORY_ENTRY_EXCEPTION,
+            layout.rdata_rva + layout.unwind_table_offset as u32,
+            table.len() as u32,
+        );
+    }
+
+    if !base_relocations.is_empty() {
+        let mut blocks = host_tables.base_relocations.clone();
+        blocks.extend(base_relocation_blocks(base_relocations));
+
+        debug_assert!(blocks.len() <= layout.base_relocations_capacity);
+
+        let offset = rdata_file_offset + layout.base_relocations_offset;
+        executable[offset..][..blocks.len()].copy_from_slice(&blocks);
+
+        update_data_directory(
+            executable,
+            md,
+            pe::IMAGE_DIRECTORY_ENTRY_BASERELOC,
+            layout.rdata_rva + layout.base_relocations_offset as u32,
+            blocks.len() as u32,
+        );
+    }
+}
+
+fn update_data_directory(
+    executable: &mut [u8],
+    md: &PeMetadata,
+    index: usize,
+    virtual_address: u32,
+    size: u32,
+) {
+    let dir = load_struct_inplace_mut::<pe::ImageDataDirectory>(
+        executable,
+        md.dynamic_relocations.data_directories_offset_in_file as usize
+            + index * std::mem::size_of::<pe::ImageDataDirectory>(),
+    );
+
+    dir.virtual_address.set(LE, virtual_address);
+    dir.size.set(LE, size);
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct DynamicRelocationsPe {
     name_by_virtual_address: MutMap<u32, String>,
@@ -1105,14 +1483,19 @@ struct AppRelocation {
     offset_in_section: u64,
     address: u64,
     relocation: object::Relocation,
+    /// The section and address of the target symbol, when the app defines it
+    target: Option<(SectionIndex, u64)>,
 }
 
 #[derive(Debug)]
 struct Section<'a> {
     bytes: &'a [u8],
     kind: SectionKind,
+    /// Where this section starts within the app section of its kind, respecting its alignment
+    offset_in_kind: u64,
     relocations: MutMap<String, Vec<AppRelocation>>,
-    app_section_index: SectionIndex,
+    /// `.pdata` sections hold the `RUNTIME_FUNCTION` entries of the exception directory
+    is_unwind_table: bool,
 }
 
 #[derive(Debug)]
@@ -1126,43 +1509,8 @@ struct AppSymbol {
 struct AppSections<'a> {
     sections: Vec<Section<'a>>,
     roc_symbols: Vec<AppSymbol>,
-    other_symbols: Vec<(SectionIndex, AppSymbol)>,
-}
-
-/// Process relocations between two places within the app. This a bit different from doing a
-/// relocation of a symbol that will be "imported" from the host
-fn process_internal_relocations(
-    sections: &[Section],
-    other_symbols: &[(SectionIndex, AppSymbol)],
-    first_host_section_virtual_address: u32,
-    section_alignment: usize,
-) -> VecMap<String, i64> {
-    let mut result = VecMap::default();
-    let mut host_section_virtual_address = first_host_section_virtual_address;
-
-    for kind in [SectionKind::Text, SectionKind::ReadOnlyData] {
-        let it = sections.iter().filter(|s| s.kind == kind);
-        for section in it {
-            for (s_index, app_symbol) in other_symbols.iter() {
-                if *s_index == section.app_section_index {
-                    result.insert(
-                        app_symbol.name.clone(),
-                        app_symbol.offset_in_section as i64 + host_section_virtual_address as i64,
-                    );
-                }
-            }
-        }
-
-        let length: usize = sections
-            .iter()
-            .filter(|s| s.kind == kind)
-            .map(|s| s.bytes.len())
-            .sum();
-
-        host_section_virtual_address += next_multiple_of(length, section_alignment) as u32;
-    }
-
-    result
+    /// The kind of every copied section, and where it starts within the app section of that kind
+    section_starts: MutMap<SectionIndex, (SectionKind, u64)>,
 }
 
 impl<'a> AppSections<'a> {
@@ -1183,6 +1531,9 @@ impl<'a> AppSections<'a> {
                         _ if name.starts_with(".text") => SectionKind::Text,
                         // _ if name.starts_with(".data") => SectionKind::Data,
                         _ if name.starts_with(".rdata") => SectionKind::ReadOnlyData,
+                        // unwind info, and the table of functions that use it
+                        _ if name.starts_with(".xdata") => SectionKind::ReadOnlyData,
+                        _ if name.starts_with(".pdata") => SectionKind::ReadOnlyData,
                         _ => continue,
                     }
                 }
@@ -1197,6 +1548,10 @@ impl<'a> AppSections<'a> {
                         let symbol = file.symbol_by_index(symbol_index);
 
                         let address = symbol.as_ref().map(|s| s.address()).unwrap_or_default();
+                        let target = match symbol.as_ref().map(|s| s.section()) {
+                            Ok(object::SymbolSection::Section(index)) => Some((index, address)),
+                            _ => None,
+                        };
                         let name = symbol.and_then(|s| s.name()).unwrap_or_default();
                         let name = redirect_libc_functions(name).unwrap_or(name).to_string();
 
@@ -1204,9 +1559,13 @@ impl<'a> AppSections<'a> {
                             offset_in_section,
                             address,
                             relocation,
+                            target,
                         });
                     }
-                    _ => todo!(),
+                    other => internal_error!(
+                        "relocation to {other:?} in section {:?} is not supported",
+                        section.name()
+                    ),
                 }
             }
 
@@ -1216,22 +1575,27 @@ impl<'a> AppSections<'a> {
             // sections are one-indexed...
             let index = SectionIndex(i + 1);
 
-            match kind {
-                SectionKind::Text => {
-                    section_starts.insert(index, (kind, text_bytes));
-                    text_bytes += length;
-                }
-                SectionKind::ReadOnlyData => {
-                    section_starts.insert(index, (kind, rdata_bytes));
-                    rdata_bytes += length;
-                }
-            }
+            let kind_bytes = match kind {
+                SectionKind::Text => &mut text_bytes,
+                SectionKind::ReadOnlyData => &mut rdata_bytes,
+            };
+
+            // e.g. unwind info and the function table must be 4-byte aligned
+            let offset_in_kind =
+                next_multiple_of(*kind_bytes as usize, section.align().max(1) as usize) as u64;
+            *kind_bytes = offset_in_kind + length;
+
+            section_starts.insert(index, (kind, offset_in_kind));
 
             let section = Section {
-                app_section_index: index,
                 bytes: file_range,
                 kind,
+                offset_in_kind,
                 relocations,
+                is_unwind_table: section
+                    .name_bytes()
+                    .unwrap_or_default()
+                    .starts_with(b".pdata"),
             };
 
             sections.push(section);
@@ -1241,14 +1605,14 @@ impl<'a> AppSections<'a> {
         let stack_check_section = Section {
             bytes: &___CHKSTK_MS,
             kind: SectionKind::Text,
+            offset_in_kind: next_multiple_of(text_bytes as usize, 16) as u64,
             relocations: Default::default(),
-            app_section_index: object::SectionIndex(0),
+            is_unwind_table: false,
         };
 
         sections.push(stack_check_section);
 
         let mut roc_symbols = Vec::new();
-        let mut other_symbols = Vec::new();
 
         for symbol in file.symbols() {
             if symbol.name_bytes().unwrap_or_default().starts_with(b"roc") {
@@ -1263,23 +1627,13 @@ impl<'a> AppSections<'a> {
 
                     roc_symbols.push(symbol);
                 }
-            } else if let object::SymbolSection::Section(index) = symbol.section() {
-                if let Some((kind, offset_in_host_section)) = section_starts.get(&index) {
-                    let symbol = AppSymbol {
-                        name: symbol.name().unwrap_or_default().to_string(),
-                        section_kind: *kind,
-                        offset_in_section: (offset_in_host_section + symbol.address()) as usize,
-                    };
-
-                    other_symbols.push((index, symbol));
-                }
             }
         }
 
         AppSections {
             sections,
             roc_symbols,
-            other_symbols,
+            section_starts,
         }
     }
 }
@@ -2034,4 +2388,231 @@ mod test {
     fn preprocessing_wine() {
         assert_eq!("Hello there\n", wine_test(preprocessing_help))
     }
+
+    /// A COFF app object with the relocations that MSVC and mingw emit for a function that calls
+    /// into the host, both directly and through `__declspec(dllimport)`, and has unwind info
+    fn coff_app_object() -> Vec<u8> {
+        use object::write::{self, Symbol, SymbolSection};
+        use object::{
+            Architecture, BinaryFormat, Endianness, RelocationFlags, SymbolFlags, SymbolKind,
+            SymbolScope,
+        };
+
+        let mut obj =
+            write::Object::new(BinaryFormat::Coff, Architecture::X86_64, Endianness::Little);
+
+        let text = obj.add_section(vec![], b".text".to_vec(), object::SectionKind::Text);
+        let rdata = obj.add_section(
+            vec![],
+            b".rdata".to_vec(),
+            object::SectionKind::ReadOnlyData,
+        );
+        let xdata = obj.add_section(
+            vec![],
+            b".xdata".to_vec(),
+            object::SectionKind::ReadOnlyData,
+        );
+        let pdata = obj.add_section(
+            vec![],
+            b".pdata".to_vec(),
+            object::SectionKind::ReadOnlyData,
+        );
+
+        let code = [
+            0xe8, 0, 0, 0, 0, // call roc_alloc
+            0xff, 0x15, 0, 0, 0, 0, // call [__imp_roc_alloc]
+            0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0,    // movabs rax, message
+            0xc3, // ret
+        ];
+
+        obj.append_section_data(text, &code, 16);
+        obj.append_section_data(rdata, b"hello\0", 1);
+        obj.append_section_data(xdata, &[1, 0, 0, 0], 4);
+        obj.append_section_data(pdata, &[0; 12], 4);
+
+        let mut symbol = |name: &[u8], kind, scope, section| {
+            obj.add_symbol(Symbol {
+                name: name.to_vec(),
+                value: 0,
+                size: 0,
+                kind,
+                scope,
+                weak: false,
+                section,
+                flags: SymbolFlags::None,
+            })
+        };
+
+        let main = symbol(
+            b"roc__mainForHost_1_exposed_generic",
+            SymbolKind::Text,
+            SymbolScope::Dynamic,
+            SymbolSection::Section(text),
+        );
+        let message = symbol(
+            b"message",
+            SymbolKind::Data,
+            SymbolScope::Compilation,
+            SymbolSection::Section(rdata),
+        );
+        let roc_alloc = symbol(
+            b"roc_alloc",
+            SymbolKind::Text,
+            SymbolScope::Dynamic,
+            SymbolSection::Undefined,
+        );
+        let imp_roc_alloc = symbol(
+            b"__imp_roc_alloc",
+            SymbolKind::Data,
+            SymbolScope::Dynamic,
+            SymbolSection::Undefined,
+        );
+        let xdata_symbol = obj.section_symbol(xdata);
+
+        let relocations = [
+            (text, 1, roc_alloc, -4, pe::IMAGE_REL_AMD64_REL32),
+            (text, 7, imp_roc_alloc, -4, pe::IMAGE_REL_AMD64_REL32),
+            (text, 13, message, 0, pe::IMAGE_REL_AMD64_ADDR64),
+            (pdata, 0, main, 0, pe::IMAGE_REL_AMD64_ADDR32NB),
+            (
+                pdata,
+                4,
+                main,
+                code.len() as i64,
+                pe::IMAGE_REL_AMD64_ADDR32NB,
+            ),
+            (pdata, 8, xdata_symbol, 0, pe::IMAGE_REL_AMD64_ADDR32NB),
+        ];
+
+        for (section, offset, symbol, addend, typ) in relocations {
+            let relocation = write::Relocation {
+                offset,
+                symbol,
+                addend,
+                flags: RelocationFlags::Coff { typ },
+            };
+
+            obj.add_relocation(section, relocation).unwrap();
+        }
+
+        obj.write().unwrap()
+    }
+
+    #[test]
+    fn surgery_relocation_kinds() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        let names = [
+            "roc__mainForHost_1__Fx_caller",
+            "roc__mainForHost_1__Fx_result_size",
+            "roc__mainForHost_1_exposed_generic",
+            "roc__mainForHost_size",
+        ]
+        .map(String::from);
+
+        std::fs::write(dir.join("host.exe"), PE_DYNHOST).unwrap();
+        std::fs::write(
+            dir.join("libapp.dll"),
+            crate::generate_dylib::synthetic_dll(&names),
+        )
+        .unwrap();
+
+        preprocess_windows(
+            &dir.join("host.exe"),
+            &dir.join("metadata"),
+            &dir.join("app.exe"),
+            &dir.join("libapp.dll"),
+            false,
+            false,
+        )
+        .unwrap();
+
+        surgery_pe(
+            &dir.join("app.exe"),
+            &dir.join("metadata"),
+            &coff_app_object(),
+        );
+
+        let data = std::fs::read(dir.join("app.exe")).unwrap();
+        let file = PeFile64::parse(data.as_slice()).unwrap();
+        let image_base = file.relative_address_base();
+        let sections = file.section_table();
+
+        let host = PeFile64::parse(PE_DYNHOST).unwrap();
+        let roc_alloc = host
+            .exports()
+            .unwrap()
+            .into_iter()
+            .find(|e| e.name() == b"roc_alloc")
+            .unwrap()
+            .address();
+
+        let text1 = file.section_by_name(".text1").unwrap();
+        let rdata1 = file.section_by_name(".rdata1").unwrap();
+        let code = text1.data().unwrap();
+        let read_only = rdata1.data().unwrap();
+
+        let i32_at = |bytes: &[u8], offset: usize| {
+            i32::from_le_bytes(bytes[offset..][..4].try_into().unwrap()) as i64
+        };
+        let u64_at = |bytes: &[u8], offset: usize| {
+            u64::from_le_bytes(bytes[offset..][..8].try_into().unwrap())
+        };
+
+        // direct call into the host
+        assert_eq!(
+            text1.address() as i64 + 5 + i32_at(code, 1),
+            roc_alloc as i64
+        );
+
+        // call through an import slot, which holds the address of the host function
+        let slot = (text1.address() as i64 + 11 + i32_at(code, 7)) as u64;
+        assert_eq!(
+            u64_at(read_only, (slot - rdata1.address()) as usize),
+            roc_alloc
+        );
+
+        // absolute address of the message, which the loader must rebase
+        assert_eq!(u64_at(code, 13), rdata1.address());
+
+        let rebased: Vec<u64> = file
+            .data_directories()
+            .relocation_blocks(data.as_slice(), &sections)
+            .unwrap()
+            .unwrap()
+            .flat_map(|block| {
+                block
+                    .unwrap()
+                    .map(|r| image_base + r.virtual_address as u64)
+            })
+            .collect();
+
+        assert!(rebased.contains(&(text1.address() + 13)));
+        assert!(rebased.contains(&slot));
+
+        // the exception directory still has the host functions, followed by the app function
+        let host_unwind_entries = host
+            .data_directory(pe::IMAGE_DIRECTORY_ENTRY_EXCEPTION)
+            .unwrap()
+            .size
+            .get(LE) as usize
+            / 12;
+
+        let exception_directory = file
+            .data_directory(pe::IMAGE_DIRECTORY_ENTRY_EXCEPTION)
+            .unwrap()
+            .data(data.as_slice(), &sections)
+            .unwrap();
+
+        assert_eq!(exception_directory.len() / 12, host_unwind_entries + 1);
+
+        let last_entry = &exception_directory[exception_directory.len() - 12..];
+        let text1_rva = (text1.address() - image_base) as i64;
+        let rdata1_rva = (rdata1.address() - image_base) as i64;
+
+        assert_eq!(i32_at(last_entry, 0), text1_rva);
+        assert_eq!(i32_at(last_entry, 4), text1_rva + 22);
+        assert_eq!(i32_at(last_entry, 8), rdata1_rva + 8);
+    }
 }
@@ -213,6 +213,7 @@ fn mono_module_to_dylib_llvm<'a>(
         module,
         target,
         mode: LlvmBackendMode::GenTest, // so roc_panic is generated
+        debug_sources: None,
         // important! we don't want any procedures to get the C calling convention
         exposed_to_host: MutSet::default(),
     };
@@ -300,6 +301,7 @@ fn mono_module_to_dylib_asm<'a>(
         exposed_to_host: exposed_to_host.top_level_values.keys().copied().collect(),
         lazy_literals,
         mode: roc_gen_dev::AssemblyBackendMode::Repl,
+        debug_sources: None,
     };
 
     let module_object =
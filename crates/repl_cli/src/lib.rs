@@ -5,7 +5,7 @@ use bumpalo::Bump;
 use const_format::concatcp;
 use roc_load::MonomorphizedModule;
 use roc_mono::ir::OptLevel;
-use roc_repl_eval::gen::Problems;
+use roc_repl_eval::gen::{compile_to_mono_in, Problems};
 use roc_repl_ui::colors::{CYAN, END_COL};
 use roc_repl_ui::repl_state::{ReplAction, ReplState};
 use roc_repl_ui::{format_output, is_incomplete, CONT_PROMPT, PROMPT, SHORT_INSTRUCTIONS, TIPS};
@@ -18,6 +18,7 @@ use rustyline::validate::{self, ValidationContext, ValidationResult, Validator};
 use rustyline::Config;
 use rustyline_derive::{Completer, Helper, Hinter};
 use std::borrow::Cow;
+use std::path::PathBuf;
 use target_lexicon::Triple;
 
 use crate::cli_gen::eval_llvm;
@@ -138,6 +139,43 @@ pub fn evaluate(
     format_output(ANSI_STYLE_CODES, opt_output, problems)
 }
 
+/// The value of the top-level def `name` of the module whose contents are `source`, rendered
+/// like the repl renders it, as in `42 : Num *`. The modules it imports are looked up in
+/// `src_dir`.
+pub fn evaluate_module_def(
+    src_dir: PathBuf,
+    source: &str,
+    name: &str,
+    target: Target,
+) -> Result<String, String> {
+    use roc_parse::{header::parse_header, state::State};
+
+    let arena = &Bump::new();
+
+    let (_, state) = parse_header(arena, State::new(source.as_bytes()))
+        .map_err(|_| "The module header could not be parsed.".to_string())?;
+
+    // the defs are evaluated in a repl module of their own, which imports what they import
+    let defs = &source[state.pos().offset as usize..];
+    let (opt_mono, problems) = compile_to_mono_in(
+        arena,
+        src_dir,
+        std::iter::once(defs),
+        name,
+        target,
+        DEFAULT_PALETTE,
+    );
+
+    if !problems.errors.is_empty() {
+        return Err(strip_colors(&problems.errors.join("\n\n")));
+    }
+
+    // the warnings are about the module's defs, which are reported where the module is checked
+    let output = evaluate(opt_mono, Problems::default(), target);
+
+    Ok(strip_colors(&output).trim().to_string())
+}
+
 #[derive(Default)]
 struct InputValidator {}
 
@@ -57,9 +57,20 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
     expr: &str,
     target: Target,
     palette: Palette,
+) -> (Option<MonomorphizedModule<'a>>, Problems) {
+    compile_to_mono_in(arena, PathBuf::from("."), defs, expr, target, palette)
+}
+
+/// Like [compile_to_mono], but the modules that the defs import are looked up in `src_dir`.
+pub fn compile_to_mono_in<'a, 'i, I: Iterator<Item = &'i str>>(
+    arena: &'a Bump,
+    src_dir: PathBuf,
+    defs: I,
+    expr: &str,
+    target: Target,
+    palette: Palette,
 ) -> (Option<MonomorphizedModule<'a>>, Problems) {
     let filename = PathBuf::from("replfile.roc");
-    let src_dir = PathBuf::from(".");
     let (bytes_before_expr, module_src) = promote_expr_to_module(arena, defs, expr);
     let loaded = roc_load::load_and_monomorphize_from_str(
         arena,
@@ -75,6 +86,7 @@ pub fn compile_to_mono<'a, 'i, I: Iterator<Item = &'i str>>(
             palette,
             threading: Threading::Single,
             exec_mode: ExecutionMode::Executable,
+            incremental: None,
         },
     );
 
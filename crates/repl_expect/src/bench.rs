@@ -0,0 +1,117 @@
+//! Running the benchmarks of `roc bench`: top-level functions like `benchSort = \{} -> ...`,
+//! which are called over and over while their runs are timed.
+//!
+//! Each benchmark is warmed up first, which also tells how long one run takes. Runs are then
+//! timed in batches (samples) that are long enough for the clock to measure them accurately.
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use roc_gen_llvm::run_roc::RocCallResult;
+use roc_mono::ir::CrashTag;
+
+use crate::run::{ExpectMemory, ToplevelBenchmark};
+
+/// How `roc bench` samples the runs of each benchmark
+#[derive(Debug, Clone, Copy)]
+pub struct BenchOptions {
+    /// How long a benchmark runs before it is timed
+    pub warmup: Duration,
+    /// How long the timed runs of a benchmark take in total, roughly
+    pub measurement: Duration,
+    /// How many samples the timed runs are split into
+    pub samples: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            warmup: Duration::from_millis(500),
+            measurement: Duration::from_secs(2),
+            samples: 50,
+        }
+    }
+}
+
+/// The timed runs of one benchmark
+#[derive(Debug, Clone)]
+pub struct BenchSamples {
+    /// How long one run took, on average, in each sample
+    pub times: Vec<Duration>,
+    /// How many runs each sample timed
+    pub runs_per_sample: u64,
+}
+
+type Thunk = unsafe extern "C" fn(*mut RocCallResult<()>);
+
+/// Runs every benchmark, and calls `on_result` with its samples, or with the message of the
+/// panic that stopped it.
+pub fn run_benchmarks<'a>(
+    lib: &libloading::Library,
+    benchmarks: impl IntoIterator<Item = ToplevelBenchmark<'a>>,
+    options: BenchOptions,
+    mut on_result: impl FnMut(ToplevelBenchmark<'a>, Result<BenchSamples, String>),
+) {
+    // Benchmarks can contain `expect`s and `dbg`s too, which write to this memory.
+    let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
+    let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
+    memory.set_shared_buffer(lib);
+
+    for benchmark in benchmarks {
+        let thunk = unsafe { lib.get::<Thunk>(benchmark.name.as_bytes()) }
+            .unwrap_or_else(|_| panic!("benchmark {} is missing from the dylib", benchmark.name));
+
+        on_result(benchmark, sample(*thunk, options));
+
+        memory.reset();
+    }
+}
+
+fn sample(thunk: Thunk, options: BenchOptions) -> Result<BenchSamples, String> {
+    // Warm up by doubling the runs until the warmup time is used up, which also gives
+    // an estimate of how long a single run takes.
+    let mut warmup_runs = 0;
+    let mut warmup_time = Duration::ZERO;
+    let mut batch = 1;
+
+    while warmup_time < options.warmup {
+        warmup_time += run_timed(thunk, batch)?;
+        warmup_runs += batch;
+        batch *= 2;
+    }
+
+    let time_per_run = warmup_time.as_secs_f64() / warmup_runs as f64;
+    let time_per_sample = options.measurement.as_secs_f64() / options.samples as f64;
+    let runs_per_sample = ((time_per_sample / time_per_run) as u64).max(1);
+
+    let mut times = Vec::with_capacity(options.samples);
+
+    for _ in 0..options.samples {
+        let elapsed = run_timed(thunk, runs_per_sample)?;
+
+        times.push(elapsed.div_f64(runs_per_sample as f64));
+    }
+
+    Ok(BenchSamples {
+        times,
+        runs_per_sample,
+    })
+}
+
+/// Runs the benchmark `runs` times, and returns how long that took.
+fn run_timed(thunk: Thunk, runs: u64) -> Result<Duration, String> {
+    let start = Instant::now();
+
+    for _ in 0..runs {
+        let mut result = MaybeUninit::uninit();
+
+        let result: Result<(), (String, CrashTag)> = unsafe {
+            thunk(result.as_mut_ptr());
+
+            result.assume_init().into()
+        };
+
+        result.map_err(|(message, _)| message)?;
+    }
+
+    Ok(start.elapsed())
+}
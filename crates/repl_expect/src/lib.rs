@@ -17,6 +17,16 @@ mod app;
 #[cfg(not(windows))]
 pub mod run;
 
+#[cfg(not(windows))]
+pub mod bench;
+
+#[cfg(not(windows))]
+mod property;
+#[cfg(not(windows))]
+mod snapshot;
+#[cfg(not(windows))]
+mod supervise;
+
 #[cfg(not(windows))]
 use app::{ExpectMemory, ExpectReplApp};
 
@@ -103,6 +113,38 @@ mod test {
     use crate::run::expect_mono_module_to_dylib;
 
     fn run_expect_test(source: &str, expected: &str) {
+        let actual = run_expect_test_with_snapshots(source, &[]);
+        let expected = expected.trim_end();
+
+        if actual != expected {
+            println!("{actual}");
+        }
+
+        assert_eq!(expected, actual);
+    }
+
+    /// Runs the expects with these `(name, contents)` snapshots stored next to the module, and
+    /// returns the output without its first line.
+    fn run_expect_test_with_snapshots(source: &str, snapshots: &[(&str, &str)]) -> String {
+        run_expect_test_with_options(source, snapshots, crate::run::ExpectOptions::default())
+    }
+
+    fn run_expect_test_with_options(
+        source: &str,
+        snapshots: &[(&str, &str)],
+        options: crate::run::ExpectOptions,
+    ) -> String {
+        run_expect_test_with_coverage(source, snapshots, options, false).0
+    }
+
+    /// Like [`run_expect_test_with_options`], but also returns how often each coverage point ran
+    /// when `coverage` is on.
+    fn run_expect_test_with_coverage(
+        source: &str,
+        snapshots: &[(&str, &str)],
+        options: crate::run::ExpectOptions,
+        coverage: bool,
+    ) -> (String, Vec<u64>) {
         let arena = bumpalo::Bump::new();
         let arena = &arena;
 
@@ -117,13 +159,21 @@ mod test {
 
         std::fs::write(&filename, source).unwrap();
 
+        for (name, contents) in snapshots {
+            let path = crate::snapshot::snapshot_path(&filename, name).unwrap();
+
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
         let load_config = LoadConfig {
             target,
             function_kind,
             render: RenderTarget::ColorTerminal,
             palette: DEFAULT_PALETTE,
             threading: Threading::Single,
-            exec_mode: ExecutionMode::Test,
+            exec_mode: ExecutionMode::Test { coverage },
+            incremental: None,
         };
         let loaded = match roc_load::load_and_monomorphize_from_str(
             arena,
@@ -143,6 +193,7 @@ mod test {
 
         let mut loaded = loaded;
         let mut expectations = std::mem::take(&mut loaded.expectations);
+        let coverage_points = std::mem::take(&mut loaded.coverage_points);
         let loaded = loaded;
 
         let interns = loaded.interns.clone();
@@ -165,10 +216,11 @@ mod test {
         unsafe { set_shared_buffer((shared_buffer.as_mut_ptr(), BUFFER_SIZE), &mut result) };
 
         let mut writer = Vec::with_capacity(1024);
+        let coverage_counters = crate::run::CoverageCounters::from_lib(&dy_lib, &coverage_points);
 
         let global_layout_interner = layout_interner.into_global();
         for (_, expect_funcs) in expects_by_module {
-            let (_failed, _passed) = crate::run::run_expects_with_memory(
+            let _results = crate::run::run_expects_with_memory(
                 &mut writer,
                 RenderTarget::ColorTerminal,
                 arena,
@@ -178,10 +230,19 @@ mod test {
                 &mut expectations,
                 expect_funcs,
                 &mut memory,
+                &coverage_counters,
+                options,
             )
             .unwrap();
         }
 
+        let hits = coverage_points
+            .iter()
+            .flat_map(|(module_id, points)| {
+                crate::run::coverage_counts(&dy_lib, *module_id, points)
+            })
+            .collect();
+
         // Remove ANSI escape codes from the answer - for example:
         //
         //     Before: "42 \u{1b}[35m:\u{1b}[0m Num *"
@@ -194,16 +255,10 @@ mod test {
             // changes between test runs
             let p = actual.bytes().position(|c| c == b'\n').unwrap();
             let (_, x) = actual.split_at(p);
-            let x = x.trim();
-            let expected = expected.trim_end();
 
-            if x != expected {
-                println!("{x}");
-            }
-
-            assert_eq!(expected, x);
+            (x.trim().to_string(), hits)
         } else {
-            assert_eq!(expected, actual);
+            (actual, hits)
         }
     }
 
@@ -1249,4 +1304,319 @@ mod test {
             ),
         );
     }
+
+    #[test]
+    fn property_pass() {
+        run_expect_test(
+            indoc!(
+                r#"
+                app "test" provides [main] to "./platform"
+
+                main = 0
+
+                expect \s, t -> Str.countUtf8Bytes (Str.concat s t) == Str.countUtf8Bytes s + Str.countUtf8Bytes t
+                "#
+            ),
+            "",
+        );
+    }
+
+    #[test]
+    fn property_fail_shrinks() {
+        run_expect_test(
+            indoc!(
+                r#"
+                app "test" provides [main] to "./platform"
+
+                main = 0
+
+                expect \n -> n < 10
+                "#
+            ),
+            indoc!(
+                r"
+                This expectation failed:
+
+                5│  expect \n -> n < 10
+                    ^^^^^^^^^^^^^^^^^^^
+
+                When it failed, these variables had these values:
+
+                n : Num *
+                n = 10
+
+                These inputs were shrunk from a random failing input. To run the same inputs again, use `roc test --seed 0`.
+                "
+            ),
+        );
+    }
+
+    const SNAPSHOT_SOURCE: &str = indoc!(
+        r#"
+        app "test" provides [main] to "./platform"
+
+        main = 0
+
+        expect Inspect.snapshot "numbers" [1, 2, 3]
+        "#
+    );
+
+    #[test]
+    fn snapshot_matches() {
+        let actual = run_expect_test_with_snapshots(SNAPSHOT_SOURCE, &[("numbers", "[1, 2, 3]")]);
+
+        assert_eq!(actual, "");
+    }
+
+    #[test]
+    fn snapshot_missing() {
+        let actual = run_expect_test_with_snapshots(SNAPSHOT_SOURCE, &[]);
+
+        assert!(actual.contains("no stored snapshot"), "{actual}");
+        assert!(actual.contains("Test.snapshots/numbers.txt"), "{actual}");
+        assert!(actual.contains("roc test --update-snapshots"), "{actual}");
+    }
+
+    #[test]
+    fn snapshot_changed() {
+        let actual = run_expect_test_with_snapshots(SNAPSHOT_SOURCE, &[("numbers", "[1, 2]")]);
+
+        assert!(actual.contains("- [1, 2]"), "{actual}");
+        assert!(actual.contains("+ [1, 2, 3]"), "{actual}");
+    }
+
+    /// Runs the benchmarks briefly, and returns their names with whether they completed.
+    fn run_bench_test(source: &str) -> Vec<(String, Result<(), String>)> {
+        let arena = bumpalo::Bump::new();
+        let arena = &arena;
+
+        let src_dir = tempfile::tempdir().unwrap();
+        let filename = src_dir.path().join("Test.roc");
+
+        std::fs::write(&filename, source).unwrap();
+
+        let load_config = LoadConfig {
+            target: Triple::host().into(),
+            function_kind: FunctionKind::LambdaSet,
+            render: RenderTarget::ColorTerminal,
+            palette: DEFAULT_PALETTE,
+            threading: Threading::Single,
+            exec_mode: ExecutionMode::Bench,
+            incremental: None,
+        };
+        let loaded = match roc_load::load_and_monomorphize_from_str(
+            arena,
+            filename,
+            source,
+            src_dir.path().to_path_buf(),
+            None,
+            RocCacheDir::Disallowed,
+            load_config,
+        ) {
+            Ok(m) => m,
+            Err(LoadMonomorphizedError::ErrorModule(m)) => {
+                internal_error!("{:?}", (m.can_problems, m.type_problems))
+            }
+            Err(e) => internal_error!("{e:?}"),
+        };
+
+        let interns = loaded.interns.clone();
+
+        let (dy_lib, expects_by_module, _) = expect_mono_module_to_dylib(
+            arena,
+            Triple::host().into(),
+            loaded,
+            roc_mono::ir::OptLevel::Optimize,
+            LlvmBackendMode::CliTest,
+        )
+        .unwrap();
+
+        let options = crate::bench::BenchOptions {
+            warmup: std::time::Duration::from_millis(1),
+            measurement: std::time::Duration::from_millis(5),
+            samples: 5,
+        };
+
+        let mut results = Vec::new();
+
+        for (_, expects) in expects_by_module {
+            assert!(expects.pure.is_empty());
+
+            crate::bench::run_benchmarks(&dy_lib, expects.benchmarks, options, |bench, samples| {
+                let samples = samples.map(|samples| assert_eq!(samples.times.len(), 5));
+
+                results.push((bench.symbol.as_str(&interns).to_string(), samples));
+            });
+        }
+
+        results
+    }
+
+    #[test]
+    fn benchmarks_run() {
+        let results = run_bench_test(indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = 0
+
+            numbers = List.range { start: At 0, end: Before 100 }
+
+            benchSum = \{} -> List.sum numbers
+
+            benchReverse = \{} -> List.reverse numbers
+
+            # not benchmarks: the name doesn't continue with a capital letter, or it takes an argument
+            benchmarked = \{} -> 1
+
+            benchDouble = \n -> n * 2
+
+            expect benchDouble 1 == 2
+            "#
+        ));
+
+        let mut names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+        names.sort_unstable();
+
+        assert_eq!(names, ["benchReverse", "benchSum"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+    }
+
+    #[test]
+    fn benchmark_crash() {
+        let results = run_bench_test(indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = 0
+
+            benchCrash : {} -> U64
+            benchCrash = \{} -> crash "too slow"
+            "#
+        ));
+
+        assert_eq!(
+            results,
+            [("benchCrash".to_string(), Err("too slow".to_string()))]
+        );
+    }
+
+    fn isolated_expect_output(source: &str) -> String {
+        let options = crate::run::ExpectOptions {
+            isolate: true,
+            timeout: Some(std::time::Duration::from_millis(500)),
+            ..Default::default()
+        };
+
+        run_expect_test_with_options(source, &[], options)
+    }
+
+    #[test]
+    fn isolated_fail() {
+        let actual = isolated_expect_output(indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = 0
+
+            expect 1 == 2
+
+            expect 1 == 1
+            "#
+        ));
+
+        assert!(actual.contains("This expectation failed:"), "{actual}");
+        assert!(actual.contains("5│  expect 1 == 2"), "{actual}");
+    }
+
+    #[test]
+    fn isolated_timeout() {
+        let actual = isolated_expect_output(indoc!(
+            r#"
+            app "test" provides [main] to "./platform"
+
+            main = 0
+
+            loop : U64 -> U64
+            loop = \n -> if n == 0 then 0 else loop (n + 1)
+
+            expect loop 1 == 0
+
+            expect 1 == 2
+            "#
+        ));
+
+        assert!(actual.contains("EXPECT TIMED OUT"), "{actual}");
+        assert!(actual.contains("after 500ms"), "{actual}");
+
+        // the expects after the one that timed out still run
+        assert!(actual.contains("expect 1 == 2"), "{actual}");
+    }
+
+    #[test]
+    fn isolated_in_parallel() {
+        let options = crate::run::ExpectOptions {
+            isolate: true,
+            max_children: 4,
+            ..Default::default()
+        };
+
+        let actual = run_expect_test_with_options(
+            indoc!(
+                r#"
+                app "test" provides [main] to "./platform"
+
+                main = 0
+
+                loop : U64 -> U64
+                loop = \n -> if n == 0 then 0 else loop (n - 1)
+
+                expect loop 100_000_000 == 1
+
+                expect 1 == 1
+
+                expect 2 == 3
+                "#
+            ),
+            &[],
+            options,
+        );
+
+        // the reports are in source order, even though the slow expect finishes last
+        let slow = actual.find("expect loop 100_000_000 == 1").unwrap();
+        let fast = actual.find("expect 2 == 3").unwrap();
+
+        assert!(slow < fast, "{actual}");
+    }
+
+    #[test]
+    fn isolated_coverage() {
+        let options = crate::run::ExpectOptions {
+            isolate: true,
+            ..Default::default()
+        };
+
+        let (_, hits) = run_expect_test_with_coverage(
+            indoc!(
+                r#"
+                app "test" provides [main] to "./platform"
+
+                main = 0
+
+                double : U64 -> U64
+                double = \n -> n * 2
+
+                expect double 1 == 2
+
+                expect double 2 == 4
+                "#
+            ),
+            &[],
+            options,
+            true,
+        );
+
+        // the counts of each child process are added up
+        assert!(hits.contains(&2), "{hits:?}");
+    }
 }
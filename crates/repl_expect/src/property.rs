@@ -0,0 +1,204 @@
+//! Running property expects like `expect \x, y -> x + y == y + x` with many generated inputs,
+//! and shrinking the inputs of a failure to a simpler one that fails the same way.
+//!
+//! Generated code builds its inputs out of choices (see [`roc_mono::arbitrary`]), which it reads
+//! from a buffer that is set here before each run. Smaller choices give simpler inputs, so
+//! shrinking works on the choices rather than on the values they turned into.
+use roc_mono::arbitrary::{CHOICES, CHOICES_LEN, CHOICES_USED};
+
+/// How many random inputs a property expect is run with, if none of them fail first
+pub const RUNS: usize = 100;
+
+/// How many choices each random run gets. Inputs that need more use 0 for the rest.
+const CHOICES_PER_RUN: usize = 256;
+
+/// How many times shrinking runs an expect at most, so that a slow expect can't take forever
+const MAX_SHRINK_RUNS: usize = 2000;
+
+/// The globals that generated code reads its choices from.
+pub struct Choices {
+    buffer: *mut *const u64,
+    len: *mut u64,
+    used: *mut u64,
+    current: Vec<u64>,
+}
+
+impl Choices {
+    /// `None` if no expect in the dylib generates inputs, because the globals only exist then.
+    pub fn from_lib(lib: &libloading::Library) -> Option<Self> {
+        unsafe {
+            let buffer = *lib.get::<*mut *const u64>(CHOICES.as_bytes()).ok()?;
+            let len = *lib.get::<*mut u64>(CHOICES_LEN.as_bytes()).ok()?;
+            let used = *lib.get::<*mut u64>(CHOICES_USED.as_bytes()).ok()?;
+
+            Some(Self {
+                buffer,
+                len,
+                used,
+                current: Vec::new(),
+            })
+        }
+    }
+
+    /// Makes the next run of an expect draw `choices`, followed by as many 0s as it needs.
+    pub fn set(&mut self, choices: &[u64]) {
+        self.current.clear();
+        self.current.extend_from_slice(choices);
+
+        unsafe {
+            self.buffer.write_volatile(self.current.as_ptr());
+            self.len.write_volatile(self.current.len() as u64);
+            self.used.write_volatile(0);
+        }
+    }
+
+    /// The choices that the last run actually drew
+    pub fn used(&self) -> Vec<u64> {
+        let used = unsafe { self.used.read_volatile() } as usize;
+
+        (0..used)
+            .map(|index| self.current.get(index).copied().unwrap_or(0))
+            .collect()
+    }
+}
+
+/// The SplitMix64 generator: tiny, and good enough to pick inputs. A given seed always gives
+/// the same sequence, which is what makes failures reproducible with `--seed`.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// The choices for one random run
+    pub fn choices(&mut self) -> Vec<u64> {
+        (0..CHOICES_PER_RUN).map(|_| self.next_u64()).collect()
+    }
+}
+
+/// The seed for one expect, so that its inputs don't depend on which other expects ran before it.
+pub fn expect_seed(seed: u64, expect_offset: u32) -> u64 {
+    Random::new(seed ^ (expect_offset as u64).rotate_left(32)).next_u64()
+}
+
+/// Shrinks the `choices` of a failing run to ones that are as small as this can find, and still
+/// fail. Deleting choices comes first, because that removes whole list elements and tags,
+/// and then each remaining choice is lowered as far as it can go.
+pub fn shrink(mut choices: Vec<u64>, mut fails: impl FnMut(&[u64]) -> bool) -> Vec<u64> {
+    let mut runs = 0;
+    let mut fails = |candidate: &[u64]| {
+        runs += 1;
+        fails(candidate)
+    };
+
+    let mut improved = true;
+
+    while improved && runs < MAX_SHRINK_RUNS {
+        improved = false;
+
+        for chunk_len in [8, 4, 2, 1] {
+            let mut start = 0;
+
+            while start + chunk_len <= choices.len() && runs < MAX_SHRINK_RUNS {
+                let mut candidate = choices.clone();
+                candidate.drain(start..start + chunk_len);
+
+                if fails(&candidate) {
+                    choices = candidate;
+                    improved = true;
+                } else {
+                    start += 1;
+                }
+            }
+        }
+
+        for index in 0..choices.len() {
+            if choices[index] == 0 || runs >= MAX_SHRINK_RUNS {
+                continue;
+            }
+
+            // Find the smallest failing value by bisection, assuming that smaller values
+            // are more likely to pass.
+            let mut passing = None;
+            let mut failing = choices[index];
+
+            while passing.map_or(0, |passing: u64| passing + 1) < failing && runs < MAX_SHRINK_RUNS
+            {
+                let candidate_value = match passing {
+                    None => 0,
+                    Some(passing) => passing + (failing - passing) / 2,
+                };
+
+                let mut candidate = choices.clone();
+                candidate[index] = candidate_value;
+
+                if fails(&candidate) {
+                    failing = candidate_value;
+                } else {
+                    passing = Some(candidate_value);
+                }
+            }
+
+            if failing < choices[index] {
+                choices[index] = failing;
+                improved = true;
+            }
+        }
+    }
+
+    // Trailing zeros are what the generated code draws anyway.
+    while choices.last() == Some(&0) {
+        choices.pop();
+    }
+
+    choices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrinks_to_smallest_failing_value() {
+        let shrunk = shrink(vec![3, 123_456, 99], |choices| {
+            choices.get(1).copied().unwrap_or(0) >= 10
+        });
+
+        assert_eq!(shrunk, vec![0, 10]);
+    }
+
+    #[test]
+    fn shrinks_by_deleting_choices() {
+        // a "list" whose length is the first choice, failing when it has an element over 5
+        let fails = |choices: &[u64]| {
+            let len = choices.first().copied().unwrap_or(0) as usize;
+
+            (1..=len).any(|index| choices.get(index).copied().unwrap_or(0) > 5)
+        };
+
+        let shrunk = shrink(vec![4, 1, 2, 70, 3], fails);
+
+        assert_eq!(shrunk, vec![1, 6]);
+    }
+
+    #[test]
+    fn same_seed_same_choices() {
+        let seed = expect_seed(42, 17);
+
+        assert_eq!(Random::new(seed).choices(), Random::new(seed).choices());
+        assert_ne!(expect_seed(42, 17), expect_seed(42, 18));
+    }
+}
@@ -1,12 +1,16 @@
-use std::sync::{
-    atomic::{AtomicBool, AtomicU32},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use bumpalo::collections::Vec as BumpVec;
 use bumpalo::Bump;
 use inkwell::context::Context;
 use roc_build::link::llvm_module_to_dylib;
+use roc_can::coverage::CoveragePoint;
 use roc_can::expr::ExpectLookup;
 use roc_collections::{MutMap, MutSet, VecMap};
 use roc_error_macros::internal_error;
@@ -18,14 +22,37 @@ use roc_gen_llvm::{
 use roc_load::{Expectations, MonomorphizedModule};
 use roc_module::symbol::{Interns, ModuleId, Symbol};
 use roc_mono::{
+    coverage,
     ir::OptLevel,
     layout::{GlobalLayoutInterner, STLayoutInterner},
 };
 use roc_region::all::Region;
-use roc_reporting::{error::expect::Renderer, report::RenderTarget};
+use roc_reporting::{
+    error::expect::{ExpectAbort, Renderer, SnapshotFailure},
+    report::RenderTarget,
+};
 use roc_target::Target;
 use roc_types::subs::Subs;
 
+use crate::property::{self, Choices};
+use crate::snapshot::{SnapshotOutcome, Snapshots};
+use crate::supervise::{self, ChildOutcome};
+
+/// How `roc test` runs the expects it was asked to run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExpectOptions {
+    /// What the inputs of property expects are generated from
+    pub seed: u64,
+    /// Whether snapshot expects store their values, rather than comparing them to what is stored
+    pub update_snapshots: bool,
+    /// Whether each expect runs in a child process, so that a crash only fails that expect
+    pub isolate: bool,
+    /// How long an isolated expect may run before it is stopped and fails
+    pub timeout: Option<Duration>,
+    /// How many isolated expects may run at once (at least one)
+    pub max_children: usize,
+}
+
 pub struct ExpectMemory<'a> {
     ptr: *mut u8,
     length: usize,
@@ -35,7 +62,6 @@ pub struct ExpectMemory<'a> {
 impl<'a> ExpectMemory<'a> {
     const SHM_SIZE: usize = 1024;
 
-    #[cfg(test)]
     pub(crate) fn from_slice(slice: &mut [u8]) -> Self {
         Self {
             ptr: slice.as_mut_ptr(),
@@ -96,7 +122,7 @@ impl<'a> ExpectMemory<'a> {
         }
     }
 
-    fn set_shared_buffer(&mut self, lib: &libloading::Library) {
+    pub(crate) fn set_shared_buffer(&mut self, lib: &libloading::Library) {
         let set_shared_buffer = run_roc_dylib!(lib, "set_shared_buffer", (*mut u8, usize), ());
         let mut result = RocCallResult::default();
         unsafe { set_shared_buffer((self.ptr, self.length), &mut result) };
@@ -123,7 +149,9 @@ pub fn run_toplevel_expects<'a, W: std::io::Write>(
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
     expects: ExpectFunctions<'_>,
-) -> std::io::Result<(usize, usize)> {
+    coverage: &CoverageCounters,
+    options: ExpectOptions,
+) -> std::io::Result<Vec<ExpectResult>> {
     let shm_name = format!("/roc_expect_buffer_{}", std::process::id());
     let mut memory = ExpectMemory::create_or_reuse_mmap(&shm_name);
 
@@ -137,6 +165,8 @@ pub fn run_toplevel_expects<'a, W: std::io::Write>(
         expectations,
         expects,
         &mut memory,
+        coverage,
+        options,
     )
 }
 
@@ -151,14 +181,36 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
     expectations: &mut VecMap<ModuleId, Expectations>,
     expects: ExpectFunctions<'_>,
     memory: &mut ExpectMemory,
-) -> std::io::Result<(usize, usize)> {
-    let mut failed = 0;
-    let mut passed = 0;
+    coverage: &CoverageCounters,
+    options: ExpectOptions,
+) -> std::io::Result<Vec<ExpectResult>> {
+    let mut results = Vec::with_capacity(expects.pure.len());
+    let mut choices = Choices::from_lib(lib);
+    let snapshots = Snapshots::from_lib(lib);
 
     memory.set_shared_buffer(lib);
 
+    if options.isolate {
+        return run_expects_isolated(
+            writer,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            lib,
+            expectations,
+            &expects.pure,
+            choices.as_mut(),
+            snapshots.as_ref(),
+            coverage,
+            options,
+        );
+    }
+
     for expect in expects.pure {
-        let result = run_expect_pure(
+        let start_time = Instant::now();
+
+        let failure_report = run_expect_pure(
             writer,
             render_target,
             arena,
@@ -167,18 +219,322 @@ pub(crate) fn run_expects_with_memory<'a, W: std::io::Write>(
             lib,
             expectations,
             memory,
+            choices.as_mut(),
+            snapshots.as_ref(),
+            options,
+            expect,
+        )?;
+
+        results.push(ExpectResult {
+            symbol: expect.symbol,
+            region: expect.region,
+            duration: start_time.elapsed(),
+            failure_report,
+        });
+    }
+
+    Ok(results)
+}
+
+/// How often each of `points` in `module_id` ran so far, according to the counters of a dylib
+/// built with coverage enabled. Regions whose code was optimized away (or never generated,
+/// because nothing used it) have no counter, and count as never having run.
+pub fn coverage_counts(
+    lib: &libloading::Library,
+    module_id: ModuleId,
+    points: &[CoveragePoint],
+) -> Vec<u64> {
+    points
+        .iter()
+        .map(|point| {
+            let (module_id, region) = coverage::counter_args(module_id, point.region);
+            let name = coverage::counter_name(module_id, region);
+
+            match unsafe { lib.get::<*const u64>(name.as_bytes()) } {
+                Ok(counter) => unsafe { (*counter).read_volatile() },
+                Err(_) => 0,
+            }
+        })
+        .collect()
+}
+
+/// The coverage counters of a dylib built with coverage enabled. An isolated expect increments
+/// the counters of its child process, which sends them back when it is done.
+#[derive(Default)]
+pub struct CoverageCounters {
+    counters: Vec<*mut u64>,
+}
+
+impl CoverageCounters {
+    pub fn from_lib(
+        lib: &libloading::Library,
+        coverage_points: &MutMap<ModuleId, Vec<CoveragePoint>>,
+    ) -> Self {
+        let mut counters = Vec::new();
+
+        for (module_id, points) in coverage_points.iter() {
+            for point in points {
+                let (module_id, region) = coverage::counter_args(*module_id, point.region);
+                let name = coverage::counter_name(module_id, region);
+
+                if let Ok(counter) = unsafe { lib.get::<*mut u64>(name.as_bytes()) } {
+                    counters.push(*counter);
+                }
+            }
+        }
+
+        Self { counters }
+    }
+
+    fn byte_len(&self) -> usize {
+        self.counters.len() * std::mem::size_of::<u64>()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.counters
+            .iter()
+            .flat_map(|counter| unsafe { counter.read_volatile() }.to_ne_bytes())
+            .collect()
+    }
+
+    /// Sets the counters to the values a child process sent back. The child started with the
+    /// counts of this process, so its counts already include them.
+    fn restore(&self, bytes: &[u8]) {
+        let counts = bytes.chunks_exact(std::mem::size_of::<u64>());
+
+        for (counter, count) in self.counters.iter().zip(counts) {
+            let count = u64::from_ne_bytes(count.try_into().unwrap());
+
+            unsafe { counter.write_volatile(count) };
+        }
+    }
+}
+
+const PASSED: i32 = 0;
+const FAILED: i32 = 1;
+const ERRORED: i32 = 2;
+
+/// Like [`run_expect_pure`] for each of `expects`, but every one of them runs in a child
+/// process that is stopped once it runs for longer than the timeout. Up to
+/// `options.max_children` of them run at once, and their output is written in the order of
+/// `expects`, whichever finishes first.
+#[allow(clippy::too_many_arguments)]
+fn run_expects_isolated<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    expects: &[ToplevelExpect<'_>],
+    mut choices: Option<&mut Choices>,
+    snapshots: Option<&Snapshots>,
+    coverage: &CoverageCounters,
+    options: ExpectOptions,
+) -> std::io::Result<Vec<ExpectResult>> {
+    let mut finished: Vec<Option<(Vec<u8>, ExpectResult)>> = std::iter::repeat_with(|| None)
+        .take(expects.len())
+        .collect();
+    let mut written = 0;
+
+    let mut pending = expects.iter().copied().enumerate();
+    let mut running = Vec::new();
+
+    loop {
+        while running.len() < options.max_children.max(1) {
+            let Some((index, expect)) = pending.next() else {
+                break;
+            };
+
+            let child = spawn_expect(
+                render_target,
+                arena,
+                interns,
+                layout_interner,
+                lib,
+                expectations,
+                choices.as_deref_mut(),
+                snapshots,
+                coverage,
+                options,
+                expect,
+            )?;
+
+            running.push(((index, Instant::now()), child));
+        }
+
+        if running.is_empty() {
+            break;
+        }
+
+        let ((index, start_time), outcome, printed) = supervise::wait_for_any(&mut running)?;
+        let duration = start_time.elapsed();
+        let expect = expects[index];
+
+        // what the expect printed comes before its report, like when it runs in this process
+        let mut output = printed;
+        let failure_report = isolated_outcome(
+            &mut output,
+            render_target,
+            arena,
+            interns,
+            expectations,
+            coverage,
+            options,
             expect,
+            outcome,
         )?;
 
-        match result {
-            true => passed += 1,
-            false => failed += 1,
+        finished[index] = Some((
+            output,
+            ExpectResult {
+                symbol: expect.symbol,
+                region: expect.region,
+                duration,
+                failure_report,
+            },
+        ));
+
+        while let Some(Some((output, _))) = finished.get(written) {
+            writer.write_all(output)?;
+            written += 1;
         }
     }
 
-    Ok((failed, passed))
+    Ok(finished
+        .into_iter()
+        .map(|finished| finished.unwrap().1)
+        .collect())
+}
+
+/// Starts running `expect` in a child process, which sends back its output and the coverage
+/// counts it ends up with.
+#[allow(clippy::too_many_arguments)]
+fn spawn_expect<'a>(
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    layout_interner: &GlobalLayoutInterner<'a>,
+    lib: &libloading::Library,
+    expectations: &mut VecMap<ModuleId, Expectations>,
+    choices: Option<&mut Choices>,
+    snapshots: Option<&Snapshots>,
+    coverage: &CoverageCounters,
+    options: ExpectOptions,
+    expect: ToplevelExpect<'_>,
+) -> std::io::Result<supervise::Child> {
+    supervise::spawn_child(options.timeout, || {
+        // Other children run at the same time, so this one gets a buffer of its own.
+        let mut buffer = vec![0; ExpectMemory::SHM_SIZE];
+        let mut memory = ExpectMemory::from_slice(&mut buffer);
+        memory.set_shared_buffer(lib);
+
+        let mut output = Vec::new();
+
+        let result = run_expect_pure(
+            &mut output,
+            render_target,
+            arena,
+            interns,
+            layout_interner,
+            lib,
+            expectations,
+            &mut memory,
+            choices,
+            snapshots,
+            options,
+            expect,
+        );
+
+        let (mut output, code) = match result {
+            Ok(None) => (output, PASSED),
+            Ok(Some(_)) => (output, FAILED),
+            Err(error) => (error.to_string().into_bytes(), ERRORED),
+        };
+
+        output.extend(coverage.to_bytes());
+
+        (output, code)
+    })
 }
 
+/// Writes the output of an isolated expect, and returns its failure report if it failed. If
+/// the child didn't finish, the expect fails with a report about why it didn't, and the
+/// coverage of that run is lost.
+#[allow(clippy::too_many_arguments)]
+fn isolated_outcome<'a, W: std::io::Write>(
+    writer: &mut W,
+    render_target: RenderTarget,
+    arena: &'a Bump,
+    interns: &'a Interns,
+    expectations: &VecMap<ModuleId, Expectations>,
+    coverage: &CoverageCounters,
+    options: ExpectOptions,
+    expect: ToplevelExpect<'_>,
+    outcome: ChildOutcome,
+) -> std::io::Result<Option<String>> {
+    let abort = match outcome {
+        ChildOutcome::Exited { code, mut output } => {
+            // a child that panicked didn't get to send its counts
+            if code != supervise::PANICKED {
+                if let Some(counts_start) = output.len().checked_sub(coverage.byte_len()) {
+                    coverage.restore(&output[counts_start..]);
+                    output.truncate(counts_start);
+                }
+            }
+
+            let description = match code {
+                PASSED => {
+                    writer.write_all(&output)?;
+
+                    return Ok(None);
+                }
+                FAILED => {
+                    writer.write_all(&output)?;
+
+                    // the report is followed by an empty line, like when it's written directly
+                    let report = output.strip_suffix(b"\n").unwrap_or(&output);
+
+                    return Ok(Some(String::from_utf8_lossy(report).into_owned()));
+                }
+                ERRORED => format!("ran into an error: {}", String::from_utf8_lossy(&output)),
+                supervise::PANICKED => "panicked".to_string(),
+                code => format!("exited with code {code}"),
+            };
+
+            ExpectAbort::Exited {
+                description: arena.alloc_str(&description),
+            }
+        }
+        ChildOutcome::TimedOut => ExpectAbort::Timeout(options.timeout.unwrap_or_default()),
+        ChildOutcome::Signaled { signal } => ExpectAbort::Signal {
+            description: arena.alloc_str(&supervise::describe_signal(signal)),
+        },
+    };
+
+    let module_id = expect.symbol.module_id();
+    let data = expectations.get(&module_id).unwrap();
+    let source = std::fs::read_to_string(&data.path)?;
+    let filename = data.path.to_owned();
+
+    let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
+
+    let mut report = Vec::new();
+    renderer.render_abort(&mut report, abort, expect.region)?;
+
+    writer.write_all(&report)?;
+    writeln!(writer)?;
+
+    Ok(Some(String::from_utf8_lossy(&report).into_owned()))
+}
+
+/// Runs a single top-level `expect`. Returns the rendered failure report if the expectation
+/// failed, after also writing that report to `writer`.
+///
+/// A property expect is run with many generated inputs, and when one of them fails, the
+/// report is about the simplest failing input that shrinking found. An expect that checks a
+/// snapshot fails when its value differs from the stored snapshot.
 #[allow(clippy::too_many_arguments)]
 fn run_expect_pure<'a, W: std::io::Write>(
     writer: &mut W,
@@ -189,51 +545,177 @@ fn run_expect_pure<'a, W: std::io::Write>(
     lib: &libloading::Library,
     expectations: &mut VecMap<ModuleId, Expectations>,
     shared_memory: &mut ExpectMemory,
+    choices: Option<&mut Choices>,
+    snapshots: Option<&Snapshots>,
+    options: ExpectOptions,
     expect: ToplevelExpect<'_>,
-) -> std::io::Result<bool> {
-    use roc_gen_llvm::try_run_jit_function;
+) -> std::io::Result<Option<String>> {
+    let seed = options.seed;
+    let mut property_seed = None;
+
+    let result = match choices {
+        None => run_expect_once(lib, shared_memory, snapshots, expect),
+        Some(choices) => {
+            // The first run gets the simplest input, like 0 or an empty list.
+            choices.set(&[]);
+            let mut result = run_expect_once(lib, shared_memory, snapshots, expect);
+
+            // An expect that didn't draw anything isn't a property, so running it again
+            // would give the same result.
+            if choices.used().is_empty() {
+                result
+            } else {
+                let expect_seed = property::expect_seed(seed, expect.region.start().offset);
+                let mut random = property::Random::new(expect_seed);
+                let mut failing = None;
+
+                if result.is_some() {
+                    failing = Some(choices.used());
+                } else {
+                    for _ in 1..property::RUNS {
+                        choices.set(&random.choices());
+
+                        if run_expect_once(lib, shared_memory, snapshots, expect).is_some() {
+                            failing = Some(choices.used());
+                            break;
+                        }
+                    }
+                }
 
-    let sequence = ExpectSequence::new(shared_memory.ptr.cast());
+                if let Some(failing) = failing {
+                    let shrunk = property::shrink(failing, |candidate| {
+                        choices.set(candidate);
+                        run_expect_once(lib, shared_memory, snapshots, expect).is_some()
+                    });
+
+                    // Run the shrunk input once more, so that the shared memory
+                    // holds the values to report.
+                    choices.set(&shrunk);
+                    result = run_expect_once(lib, shared_memory, snapshots, expect);
+                    property_seed = Some(seed);
+                }
 
-    let result: Result<(), (String, _)> = try_run_jit_function!(lib, expect.name, (), |v: ()| v);
+                result
+            }
+        }
+    };
 
-    let shared_memory_ptr: *const u8 = shared_memory.ptr.cast();
+    let module_id = expect.symbol.module_id();
+
+    let Some(result) = result else {
+        // The expect passed, but its snapshot may not have.
+        let recorded = match snapshots.map(Snapshots::recorded) {
+            None | Some(Ok(None)) => return Ok(None),
+            Some(Ok(Some(recorded))) => Ok(recorded),
+            Some(Err(failure)) => Err(failure),
+        };
 
-    if result.is_err() || sequence.count_failures() > 0 {
-        let module_id = expect.symbol.module_id();
-        let data = expectations.get_mut(&module_id).unwrap();
-
-        let path = &data.path;
-        let filename = data.path.to_owned();
-        let source = std::fs::read_to_string(path).unwrap();
-
-        let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
-
-        if let Err((roc_panic_message, _roc_panic_tag)) = result {
-            renderer.render_panic(writer, &roc_panic_message, expect.region)?;
-        } else {
-            let mut offset = ExpectSequence::START_OFFSET;
-
-            for _ in 0..sequence.count_failures() {
-                offset = render_expect_failure(
-                    writer,
-                    &renderer,
-                    arena,
-                    Some(expect),
-                    expectations,
-                    interns,
-                    layout_interner,
-                    shared_memory_ptr,
-                    offset,
-                )?;
+        let module_path = &expectations.get(&module_id).unwrap().path;
+        let render_failure = |failure: SnapshotFailure<'_>| {
+            let mut report = Vec::new();
+            let source = std::fs::read_to_string(module_path)?;
+            let filename = module_path.to_owned();
+            let renderer =
+                Renderer::new(arena, interns, render_target, module_id, filename, &source);
+
+            renderer.render_snapshot_failure(&mut report, failure, expect.region)?;
+
+            std::io::Result::Ok(report)
+        };
+
+        let report = match recorded {
+            Ok(recorded) => {
+                match recorded.check(module_path, options.update_snapshots, render_failure)? {
+                    Ok(SnapshotOutcome::Matched) => return Ok(None),
+                    Ok(SnapshotOutcome::Stored(path)) => {
+                        writeln!(writer, "Stored the snapshot in {}", path.display())?;
+
+                        return Ok(None);
+                    }
+                    Err(report) => report?,
+                }
             }
-        }
+            Err(failure) => render_failure(failure)?,
+        };
 
+        writer.write_all(&report)?;
         writeln!(writer)?;
 
-        Ok(false)
+        return Ok(Some(String::from_utf8_lossy(&report).into_owned()));
+    };
+
+    let shared_memory_ptr: *const u8 = shared_memory.ptr.cast();
+    let sequence = ExpectSequence {
+        ptr: shared_memory_ptr,
+    };
+
+    let data = expectations.get_mut(&module_id).unwrap();
+
+    let path = &data.path;
+    let filename = data.path.to_owned();
+    let source = std::fs::read_to_string(path).unwrap();
+
+    let renderer = Renderer::new(arena, interns, render_target, module_id, filename, &source);
+
+    let mut report = Vec::new();
+
+    if let Err(roc_panic_message) = result {
+        renderer.render_panic(&mut report, &roc_panic_message, expect.region)?;
     } else {
-        Ok(true)
+        let mut offset = ExpectSequence::START_OFFSET;
+
+        for _ in 0..sequence.count_failures() {
+            offset = render_expect_failure(
+                &mut report,
+                &renderer,
+                arena,
+                Some(expect),
+                expectations,
+                interns,
+                layout_interner,
+                shared_memory_ptr,
+                offset,
+            )?;
+        }
+    }
+
+    if let Some(seed) = property_seed {
+        use std::io::Write;
+
+        writeln!(
+            report,
+            "\nThese inputs were shrunk from a random failing input. To run the same inputs again, use `roc test --seed {seed}`."
+        )?;
+    }
+
+    writer.write_all(&report)?;
+    writeln!(writer)?;
+
+    Ok(Some(String::from_utf8_lossy(&report).into_owned()))
+}
+
+/// Runs a single top-level `expect` once. Returns `None` if it passed, and otherwise
+/// the message of the panic that stopped it, if any.
+fn run_expect_once(
+    lib: &libloading::Library,
+    shared_memory: &mut ExpectMemory,
+    snapshots: Option<&Snapshots>,
+    expect: ToplevelExpect<'_>,
+) -> Option<Result<(), String>> {
+    use roc_gen_llvm::try_run_jit_function;
+
+    let sequence = ExpectSequence::new(shared_memory.ptr.cast());
+
+    if let Some(snapshots) = snapshots {
+        snapshots.reset();
+    }
+
+    let result: Result<(), (String, _)> = try_run_jit_function!(lib, expect.name, (), |v: ()| v);
+
+    match result {
+        Err((roc_panic_message, _roc_panic_tag)) => Some(Err(roc_panic_message)),
+        Ok(()) if sequence.count_failures() > 0 => Some(Ok(())),
+        Ok(()) => None,
     }
 }
 
@@ -444,11 +926,40 @@ pub struct ToplevelExpect<'a> {
     pub name: &'a str,
     pub symbol: Symbol,
     pub region: Region,
+    /// The top-level def this expect is written under, if any
+    pub preceding_def: Option<Symbol>,
+}
+
+/// The outcome of running a single top-level `expect`.
+#[derive(Debug, Clone)]
+pub struct ExpectResult {
+    pub symbol: Symbol,
+    pub region: Region,
+    pub duration: Duration,
+    /// The rendered report of why the expectation failed, or `None` if it passed.
+    pub failure_report: Option<String>,
+}
+
+impl ExpectResult {
+    pub fn passed(&self) -> bool {
+        self.failure_report.is_none()
+    }
+}
+
+/// A top-level function like `benchSort = \{} -> ...`, which `roc bench` measures
+#[derive(Debug, Clone, Copy)]
+pub struct ToplevelBenchmark<'a> {
+    /// The name of the exposed function that runs the benchmark once
+    pub name: &'a str,
+    /// The benchmark function itself
+    pub symbol: Symbol,
+    pub region: Region,
 }
 
 #[derive(Debug)]
 pub struct ExpectFunctions<'a> {
     pub pure: BumpVec<'a, ToplevelExpect<'a>>,
+    pub benchmarks: BumpVec<'a, ToplevelBenchmark<'a>>,
 }
 
 pub fn expect_mono_module_to_dylib<'a>(
@@ -494,6 +1005,7 @@ pub fn expect_mono_module_to_dylib<'a>(
         module,
         target,
         mode,
+        debug_sources: None,
         // important! we don't want any procedures to get the C calling convention
         exposed_to_host: MutSet::default(),
     };
@@ -502,12 +1014,20 @@ pub fn expect_mono_module_to_dylib<'a>(
     // platform to provide them.
     add_default_roc_externs(&env);
 
+    // Benchmarks are run through thunks that look just like expects,
+    // so they are exposed the same way, after the expects of their module.
     let expects_symbols = toplevel_expects
         .iter()
         .map(|(module_id, expects)| {
+            let symbols = expects
+                .pure
+                .keys()
+                .chain(expects.benchmarks.keys())
+                .copied();
+
             (
                 *module_id,
-                bumpalo::collections::Vec::from_iter_in(expects.pure.keys().copied(), env.arena),
+                bumpalo::collections::Vec::from_iter_in(symbols, env.arena),
             )
         })
         .collect();
@@ -524,6 +1044,20 @@ pub fn expect_mono_module_to_dylib<'a>(
 
     for (module_id, expects) in toplevel_expects.into_iter() {
         let expect_names = expect_names.get(&module_id).unwrap();
+        let (expect_names, benchmark_names) = expect_names.split_at(expects.pure.len());
+
+        let benchmarks = bumpalo::collections::Vec::from_iter_in(
+            expects
+                .benchmarks
+                .values()
+                .zip(benchmark_names.iter())
+                .map(|(benchmark, name)| ToplevelBenchmark {
+                    name,
+                    symbol: benchmark.symbol,
+                    region: benchmark.region,
+                }),
+            env.arena,
+        );
 
         let expects_pure =
             bumpalo::collections::Vec::from_iter_in(
@@ -532,12 +1066,16 @@ pub fn expect_mono_module_to_dylib<'a>(
                         symbol,
                         region,
                         name,
+                        preceding_def: expects.preceding_defs.get(&symbol).copied(),
                     },
                 ),
                 env.arena,
             );
 
-        let expect_funs = ExpectFunctions { pure: expects_pure };
+        let expect_funs = ExpectFunctions {
+            pure: expects_pure,
+            benchmarks,
+        };
 
         modules_expects.insert(module_id, expect_funs);
     }
@@ -0,0 +1,163 @@
+//! Checking expects like `expect Inspect.snapshot "name" value` against the snapshots stored
+//! next to their module, and storing new snapshots for `roc test --update-snapshots`.
+//!
+//! `Inspect.snapshot` puts the name and the rendered value in globals (see
+//! [`roc_gen_llvm::llvm::expect::SNAPSHOT_NAME`]), and the comparison happens here, after the
+//! expect has run.
+use std::mem::ManuallyDrop;
+use std::path::{Path, PathBuf};
+
+use roc_gen_llvm::llvm::expect::{SNAPSHOT_COUNT, SNAPSHOT_NAME, SNAPSHOT_VALUE};
+use roc_reporting::error::expect::SnapshotFailure;
+use roc_std::RocStr;
+
+/// The globals that `Inspect.snapshot` writes to.
+pub struct Snapshots {
+    name: *const RocStr,
+    value: *const RocStr,
+    count: *mut u64,
+}
+
+/// The snapshot that an expect checked
+pub struct RecordedSnapshot {
+    name: String,
+    value: String,
+}
+
+/// What happened to a snapshot that matched, or was stored because of `--update-snapshots`
+pub enum SnapshotOutcome {
+    Matched,
+    Stored(PathBuf),
+}
+
+impl Snapshots {
+    /// `None` if no expect in the dylib checks a snapshot, because the globals only exist then.
+    pub fn from_lib(lib: &libloading::Library) -> Option<Self> {
+        unsafe {
+            let name = *lib.get::<*const RocStr>(SNAPSHOT_NAME.as_bytes()).ok()?;
+            let value = *lib.get::<*const RocStr>(SNAPSHOT_VALUE.as_bytes()).ok()?;
+            let count = *lib.get::<*mut u64>(SNAPSHOT_COUNT.as_bytes()).ok()?;
+
+            Some(Self { name, value, count })
+        }
+    }
+
+    /// Forgets the snapshot of the previous run.
+    pub fn reset(&self) {
+        unsafe { self.count.write_volatile(0) }
+    }
+
+    /// The snapshot checked by the last run, if any. More than one is an error, because only the
+    /// last one is kept.
+    pub fn recorded(&self) -> Result<Option<RecordedSnapshot>, SnapshotFailure<'static>> {
+        match unsafe { self.count.read_volatile() } {
+            0 => Ok(None),
+            1 => {
+                // The strings were leaked on purpose to keep them alive until now,
+                // so they must not be decremented here either.
+                let read = |ptr: *const RocStr| {
+                    let string = ManuallyDrop::new(unsafe { ptr.read_volatile() });
+
+                    string.as_str().to_owned()
+                };
+
+                Ok(Some(RecordedSnapshot {
+                    name: read(self.name),
+                    value: read(self.value),
+                }))
+            }
+            _ => Err(SnapshotFailure::Several),
+        }
+    }
+}
+
+impl RecordedSnapshot {
+    /// Compares the snapshot with the one stored for the module at `module_path`, or stores it
+    /// when `update` is set and the stored one is missing or different. Calls `on_failure` with
+    /// the reason the snapshot failed, if it did.
+    pub fn check<T>(
+        &self,
+        module_path: &Path,
+        update: bool,
+        on_failure: impl FnOnce(SnapshotFailure<'_>) -> T,
+    ) -> std::io::Result<Result<SnapshotOutcome, T>> {
+        let Some(path) = snapshot_path(module_path, &self.name) else {
+            return Ok(Err(on_failure(SnapshotFailure::InvalidName {
+                name: &self.name,
+            })));
+        };
+
+        // Paths are shown relative to the module, where they are easiest to find.
+        let shown_path = module_path
+            .parent()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(&path);
+
+        let stored = match std::fs::read_to_string(&path) {
+            Ok(stored) => Some(stored),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+
+        match stored {
+            Some(stored) if stored == self.value => Ok(Ok(SnapshotOutcome::Matched)),
+            _ if update => {
+                std::fs::create_dir_all(path.parent().unwrap())?;
+                std::fs::write(&path, &self.value)?;
+
+                Ok(Ok(SnapshotOutcome::Stored(path)))
+            }
+            None => Ok(Err(on_failure(SnapshotFailure::Missing {
+                path: shown_path,
+                actual: &self.value,
+            }))),
+            Some(stored) => Ok(Err(on_failure(SnapshotFailure::Changed {
+                path: shown_path,
+                stored: &stored,
+                actual: &self.value,
+            }))),
+        }
+    }
+}
+
+/// Where the snapshot with this name is stored for the module at `module_path`: snapshots of
+/// `Foo.roc` are in `Foo.snapshots/`, one `.txt` file per name. `None` if the name can't be a
+/// file name.
+pub fn snapshot_path(module_path: &Path, name: &str) -> Option<PathBuf> {
+    let is_valid = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !name.contains(|c: char| c.is_control());
+
+    if !is_valid {
+        return None;
+    }
+
+    let stem = module_path.file_stem()?.to_str()?;
+    let dir = module_path.with_file_name(format!("{stem}.snapshots"));
+
+    Some(dir.join(format!("{name}.txt")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_are_next_to_the_module() {
+        assert_eq!(
+            snapshot_path(Path::new("src/Config.roc"), "default config"),
+            Some(PathBuf::from("src/Config.snapshots/default config.txt"))
+        );
+    }
+
+    #[test]
+    fn names_must_be_file_names() {
+        let module = Path::new("Config.roc");
+
+        assert_eq!(snapshot_path(module, ""), None);
+        assert_eq!(snapshot_path(module, ".."), None);
+        assert_eq!(snapshot_path(module, "a/b"), None);
+        assert_eq!(snapshot_path(module, "a\\b"), None);
+    }
+}
@@ -0,0 +1,357 @@
+//! Running an expect in a child process, so that a timeout, a segfault or a stack overflow
+//! only stops that expect, and `roc test` can go on with the others.
+//!
+//! The child is a fork of the test process, so it already has the dylib loaded. It sends its
+//! rendered output back through a pipe, because its memory is gone once it exits. Several
+//! children can run at once, which is how the expects of a module run in parallel. What they
+//! print, like the output of `dbg`, goes to a file of their own instead of the terminal, so
+//! that it can be shown along with the rest of their output rather than mixed together.
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+/// The exit code of a child whose work panicked
+pub(crate) const PANICKED: i32 = 101;
+
+/// How a child process ended
+pub(crate) enum ChildOutcome {
+    /// It exited by itself, after sending `output`.
+    Exited { code: i32, output: Vec<u8> },
+    /// It was stopped by the given signal, e.g. `SIGSEGV` after a stack overflow.
+    Signaled { signal: i32 },
+    /// It was still running when the timeout ran out, so it was killed.
+    TimedOut,
+}
+
+/// A child process started by [`spawn_child`], and what it has sent so far
+pub(crate) struct Child {
+    pid: libc::pid_t,
+    read_end: OwnedFd,
+    deadline: Option<Instant>,
+    output: Vec<u8>,
+    /// The file that the child's stdout and stderr go to
+    printed: File,
+}
+
+/// Starts running `work` in a child process, which sends back the bytes that `work` returns
+/// and then exits with the code that it returns. The child is killed if it runs for longer
+/// than `timeout`.
+pub(crate) fn spawn_child(
+    timeout: Option<Duration>,
+    work: impl FnOnce() -> (Vec<u8>, i32),
+) -> io::Result<Child> {
+    let (read_end, write_end) = pipe()?;
+    let printed = tmpfile()?;
+
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => {
+            // we are the child
+            drop(read_end);
+
+            for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+                unsafe { libc::dup2(printed.as_raw_fd(), fd) };
+            }
+
+            let (output, code) = match std::panic::catch_unwind(AssertUnwindSafe(work)) {
+                Ok(result) => result,
+                Err(_) => (Vec::new(), PANICKED),
+            };
+
+            // `_exit` doesn't flush what is still buffered
+            let _ = io::stdout().flush();
+            unsafe { libc::fflush(std::ptr::null_mut()) };
+
+            let mut file = File::from(write_end);
+            let code = match file.write_all(&output) {
+                Ok(()) => code,
+                Err(_) => PANICKED,
+            };
+
+            // Skip the exit handlers and destructors, which belong to the parent's state.
+            unsafe { libc::_exit(code) }
+        }
+        pid => {
+            drop(write_end);
+
+            Ok(Child {
+                pid,
+                read_end,
+                deadline: timeout.map(|timeout| Instant::now() + timeout),
+                output: Vec::new(),
+                printed,
+            })
+        }
+    }
+}
+
+/// Waits until one of `children` ends, and returns how it ended and what it printed, along
+/// with the tag it was stored with. Meanwhile, the others can keep sending output, so none of
+/// them is held up by a full pipe.
+pub(crate) fn wait_for_any<T>(
+    children: &mut Vec<(T, Child)>,
+) -> io::Result<(T, ChildOutcome, Vec<u8>)> {
+    let mut buffer = [0u8; 4096];
+
+    loop {
+        let now = Instant::now();
+
+        let timed_out = children
+            .iter()
+            .position(|(_, child)| matches!(child.deadline, Some(deadline) if deadline <= now));
+
+        if let Some(index) = timed_out {
+            let (tag, child) = children.remove(index);
+
+            unsafe { libc::kill(child.pid, libc::SIGKILL) };
+            wait_for(child.pid)?;
+
+            return Ok((tag, ChildOutcome::TimedOut, read_printed(child.printed)?));
+        }
+
+        let poll_timeout = match children
+            .iter()
+            .filter_map(|(_, child)| child.deadline)
+            .min()
+        {
+            None => -1,
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(now);
+
+                // round up, so that the deadline has passed when poll times out
+                remaining.as_millis().min(i32::MAX as u128 - 1) as i32 + 1
+            }
+        };
+
+        let mut poll_fds: Vec<_> = children
+            .iter()
+            .map(|(_, child)| libc::pollfd {
+                fd: child.read_end.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+
+        let ready = unsafe {
+            libc::poll(
+                poll_fds.as_mut_ptr(),
+                poll_fds.len() as libc::nfds_t,
+                poll_timeout,
+            )
+        };
+
+        if ready == -1 {
+            let error = io::Error::last_os_error();
+
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+
+            continue;
+        }
+
+        for (index, poll_fd) in poll_fds.iter().enumerate() {
+            if poll_fd.revents == 0 {
+                continue;
+            }
+
+            let child = &mut children[index].1;
+            let read = unsafe {
+                libc::read(
+                    child.read_end.as_raw_fd(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len(),
+                )
+            };
+
+            match read {
+                0 => {
+                    // the child closed the pipe, usually by exiting
+                    let (tag, child) = children.remove(index);
+                    let status = wait_for(child.pid)?;
+
+                    let outcome = if libc::WIFSIGNALED(status) {
+                        ChildOutcome::Signaled {
+                            signal: libc::WTERMSIG(status),
+                        }
+                    } else {
+                        ChildOutcome::Exited {
+                            code: libc::WEXITSTATUS(status),
+                            output: child.output,
+                        }
+                    };
+
+                    return Ok((tag, outcome, read_printed(child.printed)?));
+                }
+                -1 => {
+                    let error = io::Error::last_os_error();
+
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+                n => child.output.extend_from_slice(&buffer[..n as usize]),
+            }
+        }
+    }
+}
+
+/// A description of a signal that stops a process, for failure reports
+pub(crate) fn describe_signal(signal: i32) -> String {
+    let description = match signal {
+        libc::SIGSEGV => "a segmentation fault (SIGSEGV), which is often a stack overflow",
+        libc::SIGBUS => "a bus error (SIGBUS), which can be a stack overflow",
+        libc::SIGABRT => "an abort (SIGABRT), which happens after a stack overflow, for example",
+        libc::SIGILL => "an illegal instruction (SIGILL)",
+        libc::SIGFPE => "an arithmetic error (SIGFPE)",
+        libc::SIGKILL => "a kill signal (SIGKILL), which can be sent when memory runs out",
+        other => return format!("signal {other}"),
+    };
+
+    description.to_string()
+}
+
+/// An anonymous file, which is removed once it's closed
+fn tmpfile() -> io::Result<File> {
+    let stream = unsafe { libc::tmpfile() };
+
+    if stream.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    // keep only a descriptor of the file, rather than the C stream around it
+    let fd = unsafe { libc::dup(libc::fileno(stream)) };
+    unsafe { libc::fclose(stream) };
+
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Everything that a child that has ended printed to `printed`. The child wrote through the
+/// same open file, so it has to be read from the start.
+fn read_printed(mut printed: File) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    printed.rewind()?;
+    printed.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds: [RawFd; 2] = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+fn wait_for(pid: libc::pid_t) -> io::Result<i32> {
+    let mut status = 0;
+
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+            return Ok(status);
+        }
+
+        let error = io::Error::last_os_error();
+
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_in_child(
+        timeout: Option<Duration>,
+        work: impl FnOnce() -> (Vec<u8>, i32),
+    ) -> (ChildOutcome, Vec<u8>) {
+        let mut children = vec![((), spawn_child(timeout, work).unwrap())];
+        let ((), outcome, printed) = wait_for_any(&mut children).unwrap();
+
+        (outcome, printed)
+    }
+
+    #[test]
+    fn output_and_exit_code() {
+        let (outcome, _) = run_in_child(None, || (b"hello".to_vec(), 3));
+
+        match outcome {
+            ChildOutcome::Exited { code, output } => {
+                assert_eq!(code, 3);
+                assert_eq!(output, b"hello");
+            }
+            _ => panic!("the child should have exited"),
+        }
+    }
+
+    #[test]
+    fn children_run_at_once() {
+        let slow = spawn_child(None, || {
+            std::thread::sleep(Duration::from_millis(200));
+
+            (b"slow".to_vec(), 0)
+        })
+        .unwrap();
+        let fast = spawn_child(None, || (b"fast".to_vec(), 0)).unwrap();
+
+        let mut children = vec![("slow", slow), ("fast", fast)];
+
+        let (first, _, _) = wait_for_any(&mut children).unwrap();
+        let (second, outcome, _) = wait_for_any(&mut children).unwrap();
+
+        assert_eq!((first, second), ("fast", "slow"));
+        assert!(children.is_empty());
+
+        match outcome {
+            ChildOutcome::Exited { output, .. } => assert_eq!(output, b"slow"),
+            _ => panic!("the child should have exited"),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let (outcome, _) = run_in_child(Some(Duration::from_millis(50)), || loop {
+            std::thread::sleep(Duration::from_millis(10));
+        });
+
+        assert!(matches!(outcome, ChildOutcome::TimedOut));
+    }
+
+    #[test]
+    fn signal() {
+        let (outcome, _) = run_in_child(None, || unsafe { libc::abort() });
+
+        assert!(matches!(
+            outcome,
+            ChildOutcome::Signaled {
+                signal: libc::SIGABRT
+            }
+        ));
+    }
+
+    #[test]
+    fn printed_output_is_captured() {
+        let (outcome, printed) = run_in_child(None, || {
+            // `println!` would go to the test harness instead
+            io::stdout().write_all(b"to stdout\n").unwrap();
+            io::stderr().write_all(b"to stderr\n").unwrap();
+
+            (Vec::new(), 0)
+        });
+
+        assert!(matches!(outcome, ChildOutcome::Exited { code: 0, .. }));
+        assert_eq!(printed, b"to stdout\nto stderr\n");
+    }
+}
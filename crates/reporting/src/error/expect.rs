@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use bumpalo::Bump;
 use roc_fmt::MigrationFlags;
@@ -11,7 +12,36 @@ use roc_types::{
     types::{ErrorType, Polarity},
 };
 
-use crate::report::{RenderTarget, RocDocAllocator, RocDocBuilder};
+use crate::report::{Annotation, RenderTarget, RocDocAllocator, RocDocBuilder};
+
+/// How many unchanged lines are shown around each change in a snapshot diff
+const DIFF_CONTEXT_LINES: usize = 2;
+
+/// Why the process running an expect stopped before the expect finished.
+pub enum ExpectAbort<'b> {
+    /// The expect ran for longer than `roc test --timeout` allows.
+    Timeout(Duration),
+    /// The process was stopped by a signal, e.g. after a segfault or a stack overflow.
+    Signal { description: &'b str },
+    /// The process exited before it could report how the expect went, e.g. after a panic.
+    Exited { description: &'b str },
+}
+
+/// Why an expect like `expect Inspect.snapshot "name" value` failed, even though it ran fine.
+pub enum SnapshotFailure<'b> {
+    /// There is no stored snapshot with this name yet.
+    Missing { path: &'b Path, actual: &'b str },
+    /// The value doesn't match the stored snapshot.
+    Changed {
+        path: &'b Path,
+        stored: &'b str,
+        actual: &'b str,
+    },
+    /// The name can't be used as a file name.
+    InvalidName { name: &'b str },
+    /// The expect checked more than one snapshot, but only the last one is kept.
+    Several,
+}
 
 pub struct Renderer<'a> {
     arena: &'a Bump,
@@ -223,6 +253,140 @@ impl<'a> Renderer<'a> {
         writeln!(writer, "{}", buf.as_str())
     }
 
+    pub fn render_snapshot_failure<W>(
+        &self,
+        writer: &mut W,
+        failure: SnapshotFailure<'_>,
+        expect_region: Region,
+    ) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        use crate::report::Report;
+        use ven_pretty::DocAllocator;
+
+        let line_col_region = self.line_info.convert_region(expect_region);
+        let severity = Severity::RuntimeError;
+
+        let update_tip = || {
+            self.alloc.concat([
+                self.alloc.tip(),
+                self.alloc.reflow("Run "),
+                self.alloc.keyword("roc test --update-snapshots"),
+                self.alloc.reflow(" to store the new value."),
+            ])
+        };
+
+        let doc = match failure {
+            SnapshotFailure::Missing { path, actual } => self.alloc.stack([
+                self.alloc
+                    .reflow("This snapshot expectation has no stored snapshot to compare with:"),
+                self.alloc.region(line_col_region, severity),
+                self.alloc.concat([
+                    self.alloc.reflow("It would be stored in "),
+                    self.alloc.string(path.display().to_string()),
+                    self.alloc.reflow(", and its value is:"),
+                ]),
+                self.alloc
+                    .vcat(actual.lines().map(|line| self.alloc.string(line.to_string())))
+                    .indent(4),
+                update_tip(),
+            ]),
+            SnapshotFailure::Changed {
+                path,
+                stored,
+                actual,
+            } => self.alloc.stack([
+                self.alloc.reflow("This snapshot expectation failed:"),
+                self.alloc.region(line_col_region, severity),
+                self.alloc.concat([
+                    self.alloc.reflow("The value no longer matches the snapshot stored in "),
+                    self.alloc.string(path.display().to_string()),
+                    self.alloc.reflow(". Lines starting with - are only in the snapshot, and lines starting with + are only in the new value:"),
+                ]),
+                self.render_diff(stored, actual).indent(4),
+                update_tip(),
+            ]),
+            SnapshotFailure::InvalidName { name } => self.alloc.stack([
+                self.alloc.reflow("This snapshot expectation has a name that can't be a file name:"),
+                self.alloc.region(line_col_region, severity),
+                self.alloc.concat([
+                    self.alloc.reflow("The name is "),
+                    self.alloc.string(format!("\"{name}\"")),
+                    self.alloc.reflow(". Snapshot names must not be empty, and must not contain slashes or start with a dot."),
+                ]),
+            ]),
+            SnapshotFailure::Several => self.alloc.stack([
+                self.alloc.reflow("This expectation checked more than one snapshot:"),
+                self.alloc.region(line_col_region, severity),
+                self.alloc.reflow("Each expect can only check one snapshot, so split it into one expect per snapshot."),
+            ]),
+        };
+
+        let report = Report {
+            title: "SNAPSHOT FAILED".into(),
+            doc,
+            filename: self.filename.clone(),
+            severity,
+        };
+
+        let mut buf = String::new();
+
+        report.render(
+            self.render_target,
+            &mut buf,
+            &self.alloc,
+            &crate::report::DEFAULT_PALETTE,
+        );
+
+        write!(writer, "{buf}")
+    }
+
+    /// The changed lines between a stored snapshot and a new value, with a few unchanged
+    /// lines around each change. Long runs of unchanged lines are left out.
+    fn render_diff(&'a self, stored: &str, actual: &str) -> RocDocBuilder<'a> {
+        use ven_pretty::DocAllocator;
+
+        let lines = diff_lines(stored, actual);
+
+        let is_change = |index: usize| !matches!(lines.get(index), Some(DiffLine::Same(_)) | None);
+        let is_near_change = |index: usize| {
+            let start = index.saturating_sub(DIFF_CONTEXT_LINES);
+
+            (start..=index + DIFF_CONTEXT_LINES).any(is_change)
+        };
+
+        let mut docs = Vec::with_capacity(lines.len());
+        let mut skipped = false;
+
+        for (index, line) in lines.iter().enumerate() {
+            if !is_near_change(index) {
+                if !skipped {
+                    docs.push(self.alloc.text("  ...").annotate(Annotation::Ellipsis));
+                    skipped = true;
+                }
+
+                continue;
+            }
+
+            skipped = false;
+
+            docs.push(match line {
+                DiffLine::Same(text) => self.alloc.string(format!("  {text}")),
+                DiffLine::Removed(text) => self
+                    .alloc
+                    .string(format!("- {text}"))
+                    .annotate(Annotation::DiffRemoved),
+                DiffLine::Added(text) => self
+                    .alloc
+                    .string(format!("+ {text}"))
+                    .annotate(Annotation::DiffAdded),
+            });
+        }
+
+        self.alloc.vcat(docs)
+    }
+
     pub fn render_panic<W>(
         &self,
         writer: &mut W,
@@ -263,4 +427,223 @@ impl<'a> Renderer<'a> {
 
         write!(writer, "{buf}")
     }
+
+    pub fn render_abort<W>(
+        &self,
+        writer: &mut W,
+        abort: ExpectAbort<'_>,
+        expect_region: Region,
+    ) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        use crate::report::Report;
+        use ven_pretty::DocAllocator;
+
+        let line_col_region = self.line_info.convert_region(expect_region);
+        let severity = Severity::RuntimeError;
+
+        let (title, doc) = match abort {
+            ExpectAbort::Timeout(timeout) => (
+                "EXPECT TIMED OUT",
+                self.alloc.stack([
+                    self.alloc.reflow(self.arena.alloc_str(&format!(
+                        "This expectation was still running after {}, so it was stopped:",
+                        format_timeout(timeout)
+                    ))),
+                    self.alloc.region(line_col_region, severity),
+                    self.alloc.concat([
+                        self.alloc.tip(),
+                        self.alloc
+                            .reflow("If it just needs more time, pass a longer "),
+                        self.alloc.keyword("--timeout"),
+                        self.alloc.reflow(" to "),
+                        self.alloc.keyword("roc test"),
+                        self.alloc.reflow("."),
+                    ]),
+                ]),
+            ),
+            ExpectAbort::Signal { description } => (
+                "EXPECT CRASHED",
+                self.alloc.stack([
+                    self.alloc
+                        .reflow("This expectation crashed the process running it:"),
+                    self.alloc.region(line_col_region, severity),
+                    self.alloc.concat([
+                        self.alloc.reflow("The process was stopped by "),
+                        self.alloc.string(description.to_string()),
+                        self.alloc.reflow("."),
+                    ]),
+                ]),
+            ),
+            ExpectAbort::Exited { description } => (
+                "EXPECT ERRORED",
+                self.alloc.stack([
+                    self.alloc
+                        .reflow("This expectation stopped the process running it:"),
+                    self.alloc.region(line_col_region, severity),
+                    self.alloc.concat([
+                        self.alloc.reflow("The process "),
+                        self.alloc.string(description.to_string()),
+                        self.alloc.reflow("."),
+                    ]),
+                ]),
+            ),
+        };
+
+        let report = Report {
+            title: title.into(),
+            doc,
+            filename: self.filename.clone(),
+            severity,
+        };
+
+        let mut buf = String::new();
+
+        report.render(
+            self.render_target,
+            &mut buf,
+            &self.alloc,
+            &crate::report::DEFAULT_PALETTE,
+        );
+
+        write!(writer, "{buf}")
+    }
+}
+
+/// Like `10s` or `1.5s`, or `250ms` for timeouts under a second
+fn format_timeout(timeout: Duration) -> String {
+    if timeout < Duration::from_secs(1) {
+        format!("{}ms", timeout.as_millis())
+    } else {
+        format!("{}s", timeout.as_secs_f64())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum DiffLine<'s> {
+    Same(&'s str),
+    Removed(&'s str),
+    Added(&'s str),
+}
+
+/// The lines of `new` compared to those of `old`, using their longest common subsequence.
+fn diff_lines<'s>(old: &'s str, new: &'s str) -> Vec<DiffLine<'s>> {
+    /// Beyond this many line pairs, finding the common subsequence takes too long (and too much
+    /// memory) to be worth it, and everything in between the common start and end is shown as
+    /// changed instead.
+    const MAX_TABLE_SIZE: usize = 4_000_000;
+
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().copied().map(DiffLine::Same).collect();
+
+    if old_middle.len() * new_middle.len() > MAX_TABLE_SIZE {
+        lines.extend(old_middle.iter().copied().map(DiffLine::Removed));
+        lines.extend(new_middle.iter().copied().map(DiffLine::Added));
+    } else {
+        // common[i][j] is the length of the longest common subsequence
+        // of old_middle[i..] and new_middle[j..]
+        let width = new_middle.len() + 1;
+        let mut common = vec![0usize; (old_middle.len() + 1) * width];
+
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                common[i * width + j] = if old_middle[i] == new_middle[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    Ord::max(common[(i + 1) * width + j], common[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+
+        while i < old_middle.len() && j < new_middle.len() {
+            if old_middle[i] == new_middle[j] {
+                lines.push(DiffLine::Same(old_middle[i]));
+                i += 1;
+                j += 1;
+            } else if common[(i + 1) * width + j] >= common[i * width + j + 1] {
+                lines.push(DiffLine::Removed(old_middle[i]));
+                i += 1;
+            } else {
+                lines.push(DiffLine::Added(new_middle[j]));
+                j += 1;
+            }
+        }
+
+        lines.extend(old_middle[i..].iter().copied().map(DiffLine::Removed));
+        lines.extend(new_middle[j..].iter().copied().map(DiffLine::Added));
+    }
+
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .copied()
+            .map(DiffLine::Same),
+    );
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_lines, DiffLine::*};
+
+    #[test]
+    fn diff_identical() {
+        assert_eq!(diff_lines("a\nb", "a\nb"), [Same("a"), Same("b")]);
+        assert_eq!(diff_lines("", ""), []);
+    }
+
+    #[test]
+    fn diff_changed_line() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nB\nc"),
+            [Same("a"), Removed("b"), Added("B"), Same("c")]
+        );
+    }
+
+    #[test]
+    fn diff_added_and_removed_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc\nd", "a\nc\nd\ne"),
+            [Same("a"), Removed("b"), Same("c"), Same("d"), Added("e")]
+        );
+        assert_eq!(diff_lines("", "a\nb"), [Added("a"), Added("b")]);
+        assert_eq!(diff_lines("a\nb", ""), [Removed("a"), Removed("b")]);
+    }
+
+    #[test]
+    fn diff_keeps_longest_common_subsequence() {
+        assert_eq!(
+            diff_lines("x\na\nb\ny", "a\nz\nb"),
+            [Removed("x"), Same("a"), Added("z"), Same("b"), Removed("y")]
+        );
+    }
+
+    #[test]
+    fn diff_repeated_lines() {
+        assert_eq!(
+            diff_lines("a\na\na", "a\na"),
+            [Same("a"), Same("a"), Removed("a")]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use crate::error::canonicalize::{to_circular_def_doc, CIRCULAR_DEF};
-use crate::report::{Annotation, Report, RocDocAllocator, RocDocBuilder};
+use crate::report::{Annotation, CiWrite, Report, RocDocAllocator, RocDocBuilder};
 use itertools::EitherOrBoth;
 use itertools::Itertools;
 use roc_can::constraint::{ExpectEffectfulReason, FxCallKind, FxSuffixKind};
@@ -2697,6 +2697,22 @@ pub mod suggest {
 
         options
     }
+
+    /// The options that are close enough to `typo` that it was probably meant to be one of
+    /// them, closest first.
+    pub fn close_matches<T>(typo: &str, options: Vec<T>) -> Vec<T>
+    where
+        T: ToStr,
+    {
+        let max_distance = (typo.len() / 3).max(1);
+
+        let close = options
+            .into_iter()
+            .filter(|option| distance::damerau_levenshtein(typo, option.to_str()) <= max_distance)
+            .collect();
+
+        sort(typo, close)
+    }
 }
 
 pub struct Comparison<'b> {
@@ -5460,6 +5476,32 @@ pub fn unhandled_patterns_to_doc_block<'b>(
         .annotate(Annotation::TypeBlock)
 }
 
+/// The source code of a pattern that is missing from a `when`, like `Err (NotFound _)`, so that
+/// editors can add a branch for it. Patterns that are only unhandled because of an `if` guard
+/// have no source code.
+pub fn unhandled_pattern_to_source<'b>(
+    alloc: &'b RocDocAllocator<'b>,
+    pattern: roc_exhaustive::Pattern,
+) -> Option<String> {
+    if let roc_exhaustive::Pattern::Ctor(union, _, _) = &pattern {
+        if matches!(union.render_as, roc_exhaustive::RenderAs::Guard) {
+            return None;
+        }
+    }
+
+    // wide enough to keep the pattern on one line
+    let width = 1000;
+
+    let mut buf = String::new();
+    exhaustive_pattern_to_doc(alloc, pattern)
+        .annotate(Annotation::TypeBlock)
+        .1
+        .render_raw(width, &mut CiWrite::new(&mut buf))
+        .ok()?;
+
+    Some(buf)
+}
+
 fn exhaustive_pattern_to_doc<'b>(
     alloc: &'b RocDocAllocator<'b>,
     pattern: roc_exhaustive::Pattern,
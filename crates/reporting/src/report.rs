@@ -960,6 +960,10 @@ pub enum Annotation {
     Header,
     ParserSuggestion,
     Warning,
+    /// A line that a diff removed
+    DiffRemoved,
+    /// A line that a diff added
+    DiffAdded,
 }
 
 /// Render with minimal formatting
@@ -1176,6 +1180,12 @@ where
             Warning => {
                 self.write_str(self.palette.warning)?;
             }
+            DiffRemoved => {
+                self.write_str(self.palette.error)?;
+            }
+            DiffAdded => {
+                self.write_str(self.palette.structure)?;
+            }
             TypeBlock | InlineTypeBlock | Tag | RecordField | RecordUpdater | TupleElem => { /* nothing yet */
             }
         }
@@ -1192,7 +1202,7 @@ where
                 Emphasized | Url | TypeVariable | Alias | Symbol | BinOp | UnaryOp | Error
                 | GutterBar | Ellipsis | Typo | TypoSuggestion | ParserSuggestion | Structure
                 | CodeBlock | PlainText | LineNumber | Tip | Module | Shorthand | Header
-                | Keyword | Warning => {
+                | Keyword | Warning | DiffRemoved | DiffAdded => {
                     self.write_str(self.palette.reset)?;
                 }
 